@@ -0,0 +1,118 @@
+//! Benchmarks for graph topology lookups and repeated runs
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use fast_langgraph::executor::{reducer, Executor, GraphPool, Writes};
+use fast_langgraph::graph::{ChannelSpec, Edge, Graph, Node, NodeFunction, END, START};
+use std::any::Any;
+use std::sync::Arc;
+
+/// Chain of `size` nodes, each also linked to the following ten nodes
+fn dense_chain(size: usize) -> Graph {
+    let mut graph = Graph::new();
+    for i in 0..size {
+        graph.add_node(Node {
+            name: format!("node{}", i),
+            function: NodeFunction::Rust(Arc::new(|_| Ok(Box::new(())))),
+            retry_policy: None,
+        });
+    }
+    let direct = |source: String, target: String| Edge::Direct {
+        source,
+        target,
+        cyclic: false,
+    };
+    graph.add_edge(direct(START.to_string(), "node0".to_string()));
+    for i in 0..size {
+        for j in (i + 1)..(i + 11).min(size) {
+            graph.add_edge(direct(format!("node{}", i), format!("node{}", j)));
+        }
+    }
+    graph.add_edge(direct(format!("node{}", size - 1), END.to_string()));
+    graph
+}
+
+/// Successors of every node, as a run visiting each node once would look
+/// them up
+fn benchmark_successor_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("graph_successors");
+    for size in [100, 1000] {
+        let names: Vec<String> = (0..size).map(|i| format!("node{}", i)).collect();
+
+        let scanned = dense_chain(size);
+        group.bench_with_input(BenchmarkId::new("edge_scan", size), &names, |b, names| {
+            b.iter(|| {
+                for name in names {
+                    std::hint::black_box(scanned.successors(name));
+                }
+            })
+        });
+
+        let mut indexed = dense_chain(size);
+        indexed.index_edges();
+        group.bench_with_input(BenchmarkId::new("adjacency", size), &names, |b, names| {
+            b.iter(|| {
+                for name in names {
+                    std::hint::black_box(indexed.successors(name));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Ten-node chain of pure-Rust nodes, each adding one to a summed channel
+fn counter_chain() -> Graph {
+    let names: Vec<String> = (0..10).map(|i| format!("node{}", i)).collect();
+    let steps: Vec<(&str, NodeFunction)> = names
+        .iter()
+        .map(|name| {
+            let function = NodeFunction::from_state_fn(|_| {
+                Ok(Writes::from([(
+                    "count".to_string(),
+                    Box::new(1i64) as Box<dyn Any + Send + Sync>,
+                )]))
+            });
+            (name.as_str(), function)
+        })
+        .collect();
+    let mut graph = Graph::new();
+    graph.add_sequence(&steps).unwrap();
+    graph.add_channel(
+        "count",
+        ChannelSpec::new("BinaryOperatorAggregate").with_reducer("sum"),
+    );
+    graph
+}
+
+/// 10k short runs of one graph: preparing it for every run versus sharing
+/// it from a pool
+fn benchmark_graph_pool(c: &mut Criterion) {
+    const RUNS: usize = 10_000;
+    let mut group = c.benchmark_group("graph_pool_10k_runs");
+    group.sample_size(10);
+
+    group.bench_function("prepare_per_run", |b| {
+        b.iter(|| {
+            for _ in 0..RUNS {
+                let mut executor = Executor::new(counter_chain())
+                    .with_reducer("sum", reducer(|a: i64, b: i64| a + b));
+                std::hint::black_box(executor.invoke(Box::new(())).unwrap());
+            }
+        })
+    });
+
+    let pool = GraphPool::new(counter_chain())
+        .unwrap()
+        .with_reducer("sum", reducer(|a: i64, b: i64| a + b));
+    group.bench_function("pooled", |b| {
+        b.iter(|| {
+            for _ in 0..RUNS {
+                std::hint::black_box(pool.invoke(Box::new(())).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_successor_lookup, benchmark_graph_pool);
+criterion_main!(benches);
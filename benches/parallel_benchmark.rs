@@ -0,0 +1,74 @@
+//! Benchmarks for running the nodes of a superstep on rayon against running
+//! them one after another
+//!
+//! Nodes are Python callables, which hold the GIL while they run Python
+//! code. The `gil_released` nodes hash a large buffer with `hashlib`, which
+//! drops the GIL, so rayon can run them side by side; the `gil_held` nodes
+//! spin in a Python loop and only ever run one at a time, whatever the
+//! backend.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use fast_langgraph::core::{Edge, Node, OutputChannels, ParallelBackend, PregelCore};
+use pyo3::prelude::*;
+
+/// Nodes run in the fan-out superstep
+const WIDTH: usize = 4;
+
+/// Graph whose second superstep runs `WIDTH` copies of `work` together
+fn fan_out(py: Python<'_>, backend: ParallelBackend, work: &str) -> PregelCore {
+    let mut executor = PregelCore::new().with_parallel_backend(backend).unwrap();
+    executor.add_node(Node::with_channels(
+        "start".to_string(),
+        py.eval("lambda x: x", None, None).unwrap().to_object(py),
+        Some(vec!["input".to_string()]),
+        Some(vec!["start".to_string()]),
+    ));
+    executor.set_entry_point("start".to_string());
+    let work = py.eval(work, None, None).unwrap();
+    let names: Vec<String> = (0..WIDTH).map(|i| format!("worker{}", i)).collect();
+    for name in &names {
+        executor.add_node(Node::with_channels(
+            name.clone(),
+            work.to_object(py),
+            Some(vec!["start".to_string()]),
+            Some(vec![name.clone()]),
+        ));
+        executor.add_edge(Edge::direct("start".to_string(), name.clone()));
+    }
+    executor.set_input_channels(vec!["input".to_string()]);
+    executor.set_output_channels(OutputChannels::Multiple(names));
+    executor
+}
+
+fn benchmark_fan_out(c: &mut Criterion) {
+    pyo3::prepare_freethreaded_python();
+
+    let workloads = [
+        (
+            "gil_released",
+            "lambda x, _data=b'x' * (8 << 20): __import__('hashlib').sha256(_data).hexdigest()",
+        ),
+        ("gil_held", "lambda x: sum(i * i for i in range(200_000))"),
+    ];
+    let backends = [
+        ("sequential", ParallelBackend::Sequential),
+        ("rayon", ParallelBackend::Rayon { threads: WIDTH }),
+    ];
+
+    let mut group = c.benchmark_group("superstep_fan_out");
+    group.sample_size(20);
+    for (workload, work) in workloads {
+        for (name, backend) in backends {
+            Python::with_gil(|py| {
+                let mut executor = fan_out(py, backend, work);
+                group.bench_function(BenchmarkId::new(workload, name), |b| {
+                    b.iter(|| executor.invoke_sync(py, 1.to_object(py), None).unwrap())
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_fan_out);
+criterion_main!(benches);
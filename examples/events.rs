@@ -0,0 +1,41 @@
+//! Printing execution lifecycle events to stdout
+
+use fast_langgraph::channels::LastValueChannel;
+use fast_langgraph::pregel::{PregelExecutor, PregelNode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[tokio::main]
+async fn main() {
+    let mut executor: PregelExecutor<i32, i32> = PregelExecutor::new();
+
+    executor
+        .add_node(PregelNode {
+            id: "double".to_string(),
+            triggers: vec!["input".to_string()],
+            channels: vec!["input".to_string()],
+            processor: Arc::new(|x: i32| Ok(x * 2)),
+        })
+        .expect("Failed to add node");
+
+    for name in ["input", "output"] {
+        executor
+            .add_channel_factory(
+                name.to_string(),
+                Arc::new(|| Arc::new(RwLock::new(LastValueChannel::<i32>::new()))),
+            )
+            .expect("Failed to add channel");
+    }
+
+    // Keep the callback quick: it runs inline and blocks execution
+    executor.on_event(|event| println!("[event] {:?}", event));
+
+    let input = HashMap::from([("input".to_string(), 21)]);
+    let output = executor
+        .invoke_isolated(input, "example".to_string())
+        .await
+        .expect("Graph execution failed");
+
+    println!("Output: {:?}", output.values.get("output"));
+}
@@ -28,14 +28,20 @@ if _rust_available:
         ChannelManager,
         Checkpoint,
         FastChannelUpdater,
+        GraphCancelled,
         GraphExecutor,
+        GraphInterrupted,
+        GuardFailed,
         LastValue,
+        NodeInterrupt,
         Pregel,
         PregelAccelerator,
         # Fast checkpoint
         RustCheckpointer,
         # Function caching (low-level)
         RustFunctionCache,
+        # Long-term store
+        RustInMemoryStore,
         # Fast channel types
         RustLastValue,
         # LLM cache
@@ -47,6 +53,7 @@ if _rust_available:
         apply_writes_batch,
         deep_merge_dicts,
         get_state_diff,
+        interrupt,
         langgraph_state_update,
         # State merge operations
         merge_dicts,
@@ -81,6 +88,21 @@ else:
         def __init__(self, *args: Any, **kwargs: Any) -> None:
             raise ImportError("Rust extension not available")
 
+    class GraphCancelled(Exception):  # type: ignore[no-redef]
+        pass
+
+    class GraphInterrupted(Exception):  # type: ignore[no-redef]
+        pass
+
+    class GuardFailed(Exception):  # type: ignore[no-redef]
+        pass
+
+    class NodeInterrupt(Exception):  # type: ignore[no-redef]
+        pass
+
+    def interrupt(value: Any) -> Any:  # type: ignore[no-redef]
+        raise ImportError("Rust extension not available")
+
     PregelExecutor = GraphExecutor
     LastValueChannel = LastValue
 
@@ -112,6 +134,11 @@ __all__ = [
     "Checkpoint",
     "Pregel",
     "GraphExecutor",
+    "GraphCancelled",
+    "GraphInterrupted",
+    "GuardFailed",
+    "NodeInterrupt",
+    "interrupt",
     "PregelExecutor",
     # Hybrid acceleration
     "ChannelManager",
@@ -123,6 +150,8 @@ __all__ = [
     # Fast checkpoint
     "RustCheckpointer",
     "RustSQLiteCheckpointer",
+    # Long-term store
+    "RustInMemoryStore",
     # LLM cache
     "RustLLMCache",
     "RustSQLiteLLMCache",
@@ -2,20 +2,233 @@
 //!
 //! This module provides utilities for managing channel state during execution.
 
+use crate::errors::GraphError;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Channels mirroring the writes of other channels, so a channel can be
+/// renamed without breaking the nodes still using the old name
+///
+/// After [`add`](Self::add)`("a", "b")`, every write to `a` is also written
+/// to `b` at the same barrier: both channels advance their versions
+/// together and readers of either name see the value. Aliases chain, so if
+/// `b` is in turn aliased to `c`, writes to `a` reach `c` too. Aliases that
+/// would mirror a channel's writes back into itself are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelAliases {
+    /// Channel name -> channels directly mirroring its writes
+    aliases: HashMap<String, Vec<String>>,
+}
+
+impl ChannelAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mirror the writes to `channel` into `alias`
+    ///
+    /// Fails with [`GraphError::AliasCycle`] if writes to `alias` already
+    /// reach `channel`, or if both are the same channel.
+    pub fn add(&mut self, channel: &str, alias: &str) -> Result<(), GraphError> {
+        if let Some(path) = self.path(alias, channel) {
+            let mut cycle = vec![channel.to_string()];
+            cycle.extend(path);
+            return Err(GraphError::AliasCycle(cycle));
+        }
+        let aliases = self.aliases.entry(channel.to_string()).or_default();
+        if !aliases.iter().any(|existing| existing == alias) {
+            aliases.push(alias.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Every channel named by an alias, as a source or a mirror
+    pub fn channels(&self) -> impl Iterator<Item = &String> {
+        self.aliases
+            .iter()
+            .flat_map(|(channel, aliases)| std::iter::once(channel).chain(aliases))
+    }
+
+    /// Channels the writes to `channel` are mirrored into, nearest first
+    pub fn targets(&self, channel: &str) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::new();
+        let mut queue = VecDeque::from([channel]);
+        while let Some(current) = queue.pop_front() {
+            for alias in self.aliases.get(current).into_iter().flatten() {
+                if !targets.contains(alias) {
+                    targets.push(alias.clone());
+                    queue.push_back(alias);
+                }
+            }
+        }
+        targets
+    }
+
+    /// `writes` followed by their copies for the aliased channels
+    pub fn mirror(&self, py: Python, writes: &[(String, PyObject)]) -> Vec<(String, PyObject)> {
+        let mut mirrored: Vec<(String, PyObject)> = writes
+            .iter()
+            .map(|(channel, value)| (channel.clone(), value.clone_ref(py)))
+            .collect();
+        for (channel, value) in writes {
+            for alias in self.targets(channel) {
+                mirrored.push((alias, value.clone_ref(py)));
+            }
+        }
+        mirrored
+    }
+
+    /// Channels leading from `from` to `to` through aliases, both included
+    fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        if from == to {
+            return Some(vec![to.to_string()]);
+        }
+        self.aliases.get(from)?.iter().find_map(|alias| {
+            let mut path = self.path(alias, to)?;
+            path.insert(0, from.to_string());
+            Some(path)
+        })
+    }
+}
+
+/// Builds a channel's starting value, see [`ChannelDefaults`]
+pub type DefaultFactory = Arc<dyn Fn(Python<'_>) -> PyObject + Send + Sync>;
+
+/// Starting values built afresh for every run
+///
+/// At the start of a run, [`seed`](Self::seed) calls each channel's factory
+/// and writes the result with the channel's `update`, so any channel type
+/// can have one. Every run gets an object of its own: a mutable starting
+/// value such as an empty list is never shared between runs, and it need
+/// not be clonable. Channels restored from a checkpoint keep their value
+/// and their factory is not called.
+#[derive(Clone, Default)]
+pub struct ChannelDefaults {
+    /// Channel name -> factory of its starting value
+    factories: HashMap<String, DefaultFactory>,
+}
+
+impl ChannelDefaults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start `channel` from the value `factory` builds, replacing any
+    /// factory it had
+    pub fn add(
+        &mut self,
+        channel: &str,
+        factory: impl Fn(Python<'_>) -> PyObject + Send + Sync + 'static,
+    ) {
+        self.factories
+            .insert(channel.to_string(), Arc::new(factory));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+
+    /// Every channel with a factory
+    pub fn channels(&self) -> impl Iterator<Item = &String> {
+        self.factories.keys()
+    }
+
+    /// Write a fresh starting value to every channel in `channels` that has
+    /// a factory, except those `restored` reports, and return the names of
+    /// the seeded channels in name order
+    pub fn seed(
+        &self,
+        py: Python,
+        channels: &HashMap<String, PyObject>,
+        restored: impl Fn(&str) -> bool,
+    ) -> PyResult<Vec<String>> {
+        let mut names: Vec<&String> = self.factories.keys().collect();
+        names.sort();
+        let mut seeded = Vec::new();
+        for name in names {
+            let Some(channel) = channels.get(name) else {
+                continue;
+            };
+            if restored(name) {
+                continue;
+            }
+            let value = self.factories[name](py);
+            channel.call_method1(py, "update", (PyList::new(py, [value]),))?;
+            seeded.push(name.clone());
+        }
+        Ok(seeded)
+    }
+}
+
+impl std::fmt::Debug for ChannelDefaults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut channels: Vec<_> = self.factories.keys().collect();
+        channels.sort();
+        f.debug_struct("ChannelDefaults")
+            .field("channels", &channels)
+            .finish()
+    }
+}
 
 /// Manages channel operations during graph execution
 pub struct ChannelManager {
     /// All channels in the graph
     channels: HashMap<String, PyObject>,
+    /// Channels mirroring the writes of others
+    aliases: ChannelAliases,
+    /// Starting values built for every run
+    defaults: ChannelDefaults,
 }
 
 impl ChannelManager {
     /// Create a new channel manager
     pub fn new(channels: HashMap<String, PyObject>) -> Self {
-        Self { channels }
+        Self {
+            channels,
+            aliases: ChannelAliases::new(),
+            defaults: ChannelDefaults::new(),
+        }
+    }
+
+    /// Mirror writes into aliased channels in [`write_channels`](Self::write_channels)
+    ///
+    /// Fails with [`GraphError::UnknownChannel`] if an alias names a
+    /// channel the manager does not have.
+    pub fn with_aliases(mut self, aliases: ChannelAliases) -> Result<Self, GraphError> {
+        if let Some(unknown) = aliases.channels().find(|name| !self.has_channel(name)) {
+            return Err(GraphError::UnknownChannel(unknown.clone()));
+        }
+        self.aliases = aliases;
+        Ok(self)
+    }
+
+    /// Seed channels from `defaults` in [`apply_defaults`](Self::apply_defaults)
+    ///
+    /// Fails with [`GraphError::UnknownChannel`] if a factory names a
+    /// channel the manager does not have.
+    pub fn with_defaults(mut self, defaults: ChannelDefaults) -> Result<Self, GraphError> {
+        if let Some(unknown) = defaults.channels().find(|name| !self.has_channel(name)) {
+            return Err(GraphError::UnknownChannel(unknown.clone()));
+        }
+        self.defaults = defaults;
+        Ok(self)
+    }
+
+    /// Write a fresh starting value to every empty channel with a factory,
+    /// returning the seeded channels
+    ///
+    /// Call it when a run starts. Channels already holding a value, such as
+    /// ones restored from a checkpoint, keep it.
+    pub fn apply_defaults(&mut self, py: Python) -> PyResult<Vec<String>> {
+        self.defaults.seed(py, &self.channels, |name| {
+            matches!(self.read_channel(py, name), Ok(Some(_)))
+        })
     }
 
     /// Read from a single channel
@@ -79,7 +292,7 @@ impl ChannelManager {
         }
     }
 
-    /// Write to multiple channels
+    /// Write to multiple channels, mirroring each write into its aliases
     pub fn write_channels(
         &mut self,
         py: Python,
@@ -87,7 +300,7 @@ impl ChannelManager {
     ) -> PyResult<Vec<String>> {
         let mut updated_channels = Vec::new();
 
-        for (channel_name, value) in writes {
+        for (channel_name, value) in &self.aliases.mirror(py, writes) {
             if self.write_channel(py, channel_name, value.clone_ref(py))? {
                 updated_channels.push(channel_name.clone());
             }
@@ -191,4 +404,136 @@ mod tests {
             assert!(!manager.has_channel("nonexistent"));
         });
     }
+
+    #[test]
+    fn test_channel_aliases() {
+        let mut aliases = ChannelAliases::new();
+        aliases.add("messages", "history").unwrap();
+        aliases.add("history", "log").unwrap();
+        assert_eq!(aliases.targets("messages"), ["history", "log"]);
+        assert!(aliases.targets("log").is_empty());
+
+        let err = aliases.add("log", "messages").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Alias cycle: log -> messages -> history -> log"
+        );
+        assert!(matches!(
+            aliases.add("log", "log"),
+            Err(GraphError::AliasCycle(_))
+        ));
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+class Chan:
+    def __init__(self):
+        self.value = None
+    def update(self, values):
+        for v in values:
+            self.value = v
+        return bool(values)
+    def get(self):
+        if self.value is None:
+            raise Exception("empty")
+        return self.value
+channels = {name: Chan() for name in ["messages", "history", "log"]}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let channels: HashMap<String, PyObject> = locals
+                .get_item("channels")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            let mut manager = ChannelManager::new(channels).with_aliases(aliases).unwrap();
+
+            let updated = manager
+                .write_channels(py, &[("messages".to_string(), "hi".to_object(py))])
+                .unwrap();
+            assert_eq!(updated, ["messages", "history", "log"]);
+            for name in ["messages", "history", "log"] {
+                let value = manager.read_channel(py, name).unwrap().unwrap();
+                assert_eq!(value.extract::<String>(py).unwrap(), "hi");
+            }
+
+            let mut unknown = ChannelAliases::new();
+            unknown.add("messages", "missing").unwrap();
+            let Err(err) = ChannelManager::new(HashMap::new()).with_aliases(unknown) else {
+                panic!("aliases to unknown channels should be rejected");
+            };
+            assert!(matches!(err, GraphError::UnknownChannel(_)));
+        });
+    }
+
+    #[test]
+    fn test_channel_defaults() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+class Chan:
+    def __init__(self):
+        self.value = None
+    def update(self, values):
+        for v in values:
+            self.value = v
+        return bool(values)
+    def get(self):
+        if self.value is None:
+            raise Exception("empty")
+        return self.value
+class Topic:
+    def __init__(self):
+        self.values = []
+    def update(self, values):
+        self.values.extend(values)
+        return bool(values)
+    def get(self):
+        if not self.values:
+            raise Exception("empty")
+        return self.values
+channels = {"scratch": Chan(), "log": Topic(), "saved": Chan()}
+channels["saved"].update(["restored"])
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let channels: HashMap<String, PyObject> = locals
+                .get_item("channels")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+
+            let mut defaults = ChannelDefaults::new();
+            defaults.add("scratch", |py| PyDict::new(py).into());
+            defaults.add("log", |py| "started".to_object(py));
+            defaults.add("saved", |py| "default".to_object(py));
+            let mut manager = ChannelManager::new(channels)
+                .with_defaults(defaults)
+                .unwrap();
+
+            // Both channel types take the value; the restored one keeps its own
+            assert_eq!(manager.apply_defaults(py).unwrap(), ["log", "scratch"]);
+            let saved = manager.read_channel(py, "saved").unwrap().unwrap();
+            assert_eq!(saved.extract::<String>(py).unwrap(), "restored");
+            let log = manager.read_channel(py, "log").unwrap().unwrap();
+            assert_eq!(log.extract::<Vec<String>>(py).unwrap(), ["started"]);
+
+            let mut unknown = ChannelDefaults::new();
+            unknown.add("missing", |py| py.None());
+            let Err(err) = ChannelManager::new(HashMap::new()).with_defaults(unknown) else {
+                panic!("defaults for unknown channels should be rejected");
+            };
+            assert!(matches!(err, GraphError::UnknownChannel(_)));
+        });
+    }
 }
@@ -2,6 +2,7 @@
 
 use crate::errors::LangGraphError;
 use serde::{Deserialize, Serialize};
+use std::any::Any;
 use std::collections::VecDeque;
 
 /// Base trait for all channels
@@ -227,7 +228,6 @@ impl<T: Clone + Send + Sync + Serialize + for<'de> Deserialize<'de>> Channel<Vec
 
 /// A channel that applies a binary operator to accumulate values
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct BinaryOperatorAggregateChannel<T, F> {
     value: Option<T>,
     operator: F,
@@ -259,8 +259,132 @@ impl<T: Clone, F> BinaryOperatorAggregateChannel<T, F> {
     }
 }
 
-// Note: This implementation is simplified and would need more work for a complete implementation
-// We're not implementing the Channel trait for this because it would require complex generic bounds
+impl<T, F> Channel<T, T> for BinaryOperatorAggregateChannel<T, F>
+where
+    T: Clone + Send + Sync + Serialize + for<'de> Deserialize<'de>,
+    F: Fn(T, T) -> T + Send + Sync,
+{
+    fn get(&self) -> Result<&T, LangGraphError> {
+        self.value
+            .as_ref()
+            .ok_or(LangGraphError::ChannelError("Channel is empty".to_string()))
+    }
+
+    fn update(&mut self, values: Vec<T>) -> Result<bool, LangGraphError> {
+        if values.is_empty() {
+            return Ok(false);
+        }
+        for value in values {
+            self.value = Some(match self.value.take() {
+                Some(current) => (self.operator)(current, value),
+                None => value,
+            });
+        }
+        Ok(true)
+    }
+
+    fn is_available(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn consume(&mut self) -> bool {
+        false
+    }
+
+    fn finish(&mut self) -> bool {
+        false
+    }
+
+    fn checkpoint(&self) -> Result<serde_json::Value, LangGraphError> {
+        match &self.value {
+            Some(value) => Ok(serde_json::to_value(value)?),
+            None => Ok(serde_json::Value::Null),
+        }
+    }
+
+    fn from_checkpoint(&mut self, checkpoint: serde_json::Value) -> Result<(), LangGraphError> {
+        self.value = if checkpoint.is_null() {
+            None
+        } else {
+            Some(serde_json::from_value(checkpoint)?)
+        };
+        Ok(())
+    }
+
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// A [`Channel`] with its value and update types erased, so channels of
+/// different types can share one `GraphState`
+#[allow(clippy::wrong_self_convention)]
+pub trait AnyChannel: Send + Sync {
+    /// The channel's current value, to be downcast to its value type
+    fn value(&self) -> Result<&dyn Any, LangGraphError>;
+
+    /// Apply `values`, which must be a `Vec` of the channel's update type
+    ///
+    /// Returns `None`, without touching the channel, if they are of another
+    /// type.
+    fn update_any(&mut self, values: Box<dyn Any>) -> Option<Result<bool, LangGraphError>>;
+
+    /// Name of the value type, for error messages
+    fn value_type(&self) -> &'static str;
+
+    /// Name of the update type, for error messages
+    fn update_type(&self) -> &'static str;
+
+    fn is_available(&self) -> bool;
+
+    fn checkpoint(&self) -> Result<serde_json::Value, LangGraphError>;
+
+    fn from_checkpoint(&mut self, checkpoint: serde_json::Value) -> Result<(), LangGraphError>;
+}
+
+/// A channel of value type `T` and update type `U` as an [`AnyChannel`]
+pub struct TypedChannel<T, U> {
+    channel: Box<dyn Channel<T, U>>,
+}
+
+impl<T, U> TypedChannel<T, U> {
+    pub fn new(channel: impl Channel<T, U> + 'static) -> Self {
+        Self {
+            channel: Box::new(channel),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, U: Send + Sync + 'static> AnyChannel for TypedChannel<T, U> {
+    fn value(&self) -> Result<&dyn Any, LangGraphError> {
+        Ok(self.channel.get()? as &dyn Any)
+    }
+
+    fn update_any(&mut self, values: Box<dyn Any>) -> Option<Result<bool, LangGraphError>> {
+        let values = values.downcast::<Vec<U>>().ok()?;
+        Some(self.channel.update(*values))
+    }
+
+    fn value_type(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn update_type(&self) -> &'static str {
+        std::any::type_name::<U>()
+    }
+
+    fn is_available(&self) -> bool {
+        self.channel.is_available()
+    }
+
+    fn checkpoint(&self) -> Result<serde_json::Value, LangGraphError> {
+        self.channel.checkpoint()
+    }
+
+    fn from_checkpoint(&mut self, checkpoint: serde_json::Value) -> Result<(), LangGraphError> {
+        self.channel.from_checkpoint(checkpoint)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -5,7 +5,13 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Channel a task's `interrupt()` is recorded under as a pending write
+pub const INTERRUPT: &str = "__interrupt__";
 
 /// Channel versions mapping - maps channel name to version number
 pub type ChannelVersions = HashMap<String, serde_json::Value>;
@@ -19,6 +25,50 @@ pub struct CheckpointMetadata {
     pub parents: HashMap<String, String>,
 }
 
+/// Checkpoint ID derived from the checkpoint's content
+///
+/// The ID is a UUIDv5 in [`CheckpointId::NAMESPACE`] over the canonical JSON
+/// serialization of `{"parent": <parent id or null>, "step": <step>,
+/// "values": <channel values>}`, with object keys in sorted order. Identical
+/// runs therefore produce identical IDs, while a different parent, step or
+/// state gives a different one. The wall-clock `ts` is not hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CheckpointId(Uuid);
+
+impl CheckpointId {
+    /// Namespace of content-derived checkpoint IDs
+    pub const NAMESPACE: Uuid = Uuid::from_u128(0x6c61_6e67_6772_6170_682d_6368_6b70_7400);
+
+    /// Derive the ID of the checkpoint holding `channel_values` after `step`
+    pub fn derive(
+        parent_id: Option<&str>,
+        step: i32,
+        channel_values: &HashMap<String, Value>,
+    ) -> Self {
+        let values: BTreeMap<&String, &Value> = channel_values.iter().collect();
+        let content = serde_json::json!({
+            "parent": parent_id,
+            "step": step,
+            "values": values,
+        });
+        Self(Uuid::new_v5(
+            &Self::NAMESPACE,
+            content.to_string().as_bytes(),
+        ))
+    }
+
+    /// The underlying UUID
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl fmt::Display for CheckpointId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// State snapshot at a given point in time
 ///
 /// A Checkpoint represents the complete state of the graph at a specific
@@ -33,6 +83,9 @@ pub struct Checkpoint {
     pub versions_seen: HashMap<String, ChannelVersions>,
     pub pending_sends: Vec<Value>,
     pub updated_channels: Option<Vec<String>>,
+    /// Interrupts the run was paused on when the checkpoint was taken
+    #[serde(default)]
+    pub interrupts: Vec<Value>,
 }
 
 impl Checkpoint {
@@ -46,9 +99,17 @@ impl Checkpoint {
             versions_seen: HashMap::new(),
             pending_sends: Vec::new(),
             updated_channels: None,
+            interrupts: Vec::new(),
         }
     }
 
+    /// Replace the ID with one derived from the checkpoint's content
+    ///
+    /// See [`CheckpointId`] for the hashing scheme.
+    pub fn derive_id(&mut self, parent_id: Option<&str>, step: i32) {
+        self.id = CheckpointId::derive(parent_id, step, &self.channel_values).to_string();
+    }
+
     pub fn copy(&self) -> Self {
         Self {
             v: self.v,
@@ -59,9 +120,22 @@ impl Checkpoint {
             versions_seen: self.versions_seen.clone(),
             pending_sends: self.pending_sends.clone(),
             updated_channels: self.updated_channels.clone(),
+            interrupts: self.interrupts.clone(),
         }
     }
 
+    /// Whether the checkpoint was taken at an interrupt
+    ///
+    /// Either the checkpoint records the interrupts the run was paused on, or
+    /// one of the `(task_id, channel, value)` writes stored against it is an
+    /// [`INTERRUPT`] write.
+    pub fn is_interrupt(&self, pending_writes: &[(String, String, Value)]) -> bool {
+        !self.interrupts.is_empty()
+            || pending_writes
+                .iter()
+                .any(|(_, channel, _)| channel == INTERRUPT)
+    }
+
     /// Serialize the checkpoint to a JSON string
     pub fn to_json(&self) -> Result<String, LangGraphError> {
         Ok(serde_json::to_string(self)?)
@@ -151,6 +225,58 @@ impl Checkpoint {
     pub fn serialized_size(&self) -> Result<usize, LangGraphError> {
         Ok(self.to_json()?.len())
     }
+
+    /// Diff of this checkpoint against `parent`, keeping only the channels
+    /// whose value changed
+    pub fn diff(&self, parent: &Checkpoint) -> CheckpointDiff {
+        let changed = self
+            .channel_values
+            .iter()
+            .filter(|(name, value)| parent.channel_values.get(*name) != Some(*value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let mut removed: Vec<String> = parent
+            .channel_values
+            .keys()
+            .filter(|name| !self.channel_values.contains_key(*name))
+            .cloned()
+            .collect();
+        removed.sort();
+        CheckpointDiff {
+            parent_id: parent.id.clone(),
+            checkpoint: Checkpoint {
+                channel_values: changed,
+                ..self.copy()
+            },
+            removed,
+        }
+    }
+
+    /// Rebuild the full checkpoint that `diff` was taken from
+    ///
+    /// Fails if `parent` is not the checkpoint the diff was taken against.
+    pub fn apply_diff(parent: &Checkpoint, diff: &CheckpointDiff) -> Result<Self, LangGraphError> {
+        if diff.parent_id != parent.id {
+            return Err(LangGraphError::CheckpointError(format!(
+                "diff of checkpoint '{}' applies to parent '{}', not '{}'",
+                diff.checkpoint.id, diff.parent_id, parent.id
+            )));
+        }
+        let mut channel_values = parent.channel_values.clone();
+        for name in &diff.removed {
+            channel_values.remove(name);
+        }
+        channel_values.extend(
+            diff.checkpoint
+                .channel_values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        Ok(Checkpoint {
+            channel_values,
+            ..diff.checkpoint.copy()
+        })
+    }
 }
 
 impl Default for Checkpoint {
@@ -159,6 +285,279 @@ impl Default for Checkpoint {
     }
 }
 
+/// A checkpoint stored as the change from its parent
+///
+/// `checkpoint` carries every field of the full checkpoint except that
+/// `channel_values` holds only the channels added or changed since the
+/// parent. Rebuild the full checkpoint with [`Checkpoint::apply_diff`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointDiff {
+    /// ID of the checkpoint the diff was taken against
+    pub parent_id: String,
+    /// The checkpoint, with only the changed channel values
+    pub checkpoint: Checkpoint,
+    /// Channels of the parent missing from the checkpoint, sorted
+    pub removed: Vec<String>,
+}
+
+/// How a [`CheckpointLog`] stores checkpoints that have a parent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CheckpointMode {
+    /// Store every checkpoint in full
+    #[default]
+    Full,
+    /// Store a diff against the parent; loading walks the parent chain
+    Incremental,
+}
+
+/// Checkpoint as persisted by a [`CheckpointLog`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum StoredCheckpoint {
+    Full(Checkpoint),
+    Diff(CheckpointDiff),
+}
+
+/// Which checkpoints a checkpointer keeps as a thread grows
+///
+/// After each `put` the newest `keep_last` checkpoints are kept, along with
+/// every checkpoint taken at an interrupt when `keep_interrupts` is set;
+/// older ones are deleted. Where checkpoints are stored as diffs, the
+/// parents needed to rebuild a kept checkpoint are kept too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub keep_last: usize,
+    pub keep_interrupts: bool,
+}
+
+impl RetentionPolicy {
+    pub fn new(keep_last: usize) -> Self {
+        Self {
+            keep_last,
+            keep_interrupts: true,
+        }
+    }
+
+    /// IDs among `ids`, oldest first, that the policy deletes
+    ///
+    /// `is_interrupt` tells whether a checkpoint was taken at an interrupt,
+    /// and `parent` gives the checkpoint a stored one is rebuilt from, if any.
+    pub fn prunable(
+        &self,
+        ids: &[String],
+        is_interrupt: impl Fn(&str) -> bool,
+        parent: impl Fn(&str) -> Option<String>,
+    ) -> Vec<String> {
+        let first_kept = ids.len().saturating_sub(self.keep_last);
+        let mut retained: HashSet<String> = ids
+            .iter()
+            .enumerate()
+            .filter(|(index, id)| {
+                *index >= first_kept || (self.keep_interrupts && is_interrupt(id))
+            })
+            .map(|(_, id)| id.clone())
+            .collect();
+        let mut pending: Vec<String> = retained.iter().cloned().collect();
+        while let Some(id) = pending.pop() {
+            if let Some(parent) = parent(&id) {
+                if retained.insert(parent.clone()) {
+                    pending.push(parent);
+                }
+            }
+        }
+        ids.iter()
+            .filter(|id| !retained.contains(*id))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Checkpoints of a run by ID, stored in full or as diffs
+///
+/// In [`CheckpointMode::Incremental`] a checkpoint with small changes over a
+/// large state costs only its changes, but loading it replays every diff back
+/// to the nearest full checkpoint. [`compact`](Self::compact) collapses such
+/// a chain to bound that cost. A log set up
+/// [`with_retention`](Self::with_retention) prunes old checkpoints after
+/// every `put`.
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointLog {
+    mode: CheckpointMode,
+    entries: HashMap<String, StoredCheckpoint>,
+    /// IDs in the order they were first stored
+    order: Vec<String>,
+    /// Checkpoints stored with [`put_interrupt`](Self::put_interrupt)
+    interrupts: HashSet<String>,
+    retention: Option<RetentionPolicy>,
+}
+
+impl CheckpointLog {
+    pub fn new(mode: CheckpointMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Prune the log with `policy` after every `put`
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Store `checkpoint`, taken after the checkpoint `parent_id`
+    ///
+    /// Incremental logs store a diff against the parent, which must already
+    /// be stored; checkpoints without a parent are always stored in full.
+    pub fn put(
+        &mut self,
+        checkpoint: &Checkpoint,
+        parent_id: Option<&str>,
+    ) -> Result<(), LangGraphError> {
+        self.insert(checkpoint, parent_id, false)
+    }
+
+    /// Store `checkpoint`, taken at an interrupt after the checkpoint
+    /// `parent_id`; retention policies that keep interrupts never prune it
+    pub fn put_interrupt(
+        &mut self,
+        checkpoint: &Checkpoint,
+        parent_id: Option<&str>,
+    ) -> Result<(), LangGraphError> {
+        self.insert(checkpoint, parent_id, true)
+    }
+
+    fn insert(
+        &mut self,
+        checkpoint: &Checkpoint,
+        parent_id: Option<&str>,
+        interrupt: bool,
+    ) -> Result<(), LangGraphError> {
+        let stored = match (self.mode, parent_id) {
+            (CheckpointMode::Incremental, Some(parent_id)) => {
+                let parent = self.get(parent_id)?;
+                StoredCheckpoint::Diff(checkpoint.diff(&parent))
+            }
+            _ => StoredCheckpoint::Full(checkpoint.copy()),
+        };
+        if self.entries.insert(checkpoint.id.clone(), stored).is_none() {
+            self.order.push(checkpoint.id.clone());
+        }
+        if interrupt {
+            self.interrupts.insert(checkpoint.id.clone());
+        }
+        if let Some(policy) = self.retention {
+            self.prune(policy);
+        }
+        Ok(())
+    }
+
+    /// Record that the stored checkpoint `id` was taken at an interrupt, as
+    /// when an interrupt write arrives after the checkpoint was put
+    ///
+    /// Returns false if `id` is not stored.
+    pub fn mark_interrupt(&mut self, id: &str) -> bool {
+        if !self.entries.contains_key(id) {
+            return false;
+        }
+        self.interrupts.insert(id.to_string());
+        true
+    }
+
+    /// Whether `id` is stored
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    /// Delete the checkpoints `policy` does not retain, returning their IDs
+    /// oldest first
+    ///
+    /// Parents of retained diffs are kept so every retained checkpoint can
+    /// still be loaded.
+    pub fn prune(&mut self, policy: RetentionPolicy) -> Vec<String> {
+        let pruned = policy.prunable(
+            &self.order,
+            |id| self.interrupts.contains(id),
+            |id| match self.entries.get(id) {
+                Some(StoredCheckpoint::Diff(diff)) => Some(diff.parent_id.clone()),
+                _ => None,
+            },
+        );
+        for id in &pruned {
+            self.entries.remove(id);
+            self.interrupts.remove(id);
+        }
+        self.order.retain(|id| self.entries.contains_key(id));
+        pruned
+    }
+
+    /// Load the full checkpoint `id`, applying its chain of diffs
+    pub fn get(&self, id: &str) -> Result<Checkpoint, LangGraphError> {
+        let mut diffs = Vec::new();
+        let mut current = id;
+        let base = loop {
+            match self.entries.get(current) {
+                Some(StoredCheckpoint::Full(checkpoint)) => break checkpoint,
+                Some(StoredCheckpoint::Diff(diff)) => {
+                    diffs.push(diff);
+                    current = &diff.parent_id;
+                }
+                None => {
+                    return Err(LangGraphError::CheckpointNotFound {
+                        checkpoint_id: current.to_string(),
+                    })
+                }
+            }
+        };
+        let mut checkpoint = base.copy();
+        for diff in diffs.into_iter().rev() {
+            checkpoint = Checkpoint::apply_diff(&checkpoint, diff)?;
+        }
+        Ok(checkpoint)
+    }
+
+    /// Number of diffs applied to load `id`; 0 for a full checkpoint
+    pub fn chain_len(&self, id: &str) -> usize {
+        let mut len = 0;
+        let mut current = id;
+        while let Some(StoredCheckpoint::Diff(diff)) = self.entries.get(current) {
+            len += 1;
+            current = &diff.parent_id;
+        }
+        len
+    }
+
+    /// Store `id` in full, so loading it no longer walks its parents
+    ///
+    /// Later diffs taken against `id` are unaffected.
+    pub fn compact(&mut self, id: &str) -> Result<(), LangGraphError> {
+        let checkpoint = self.get(id)?;
+        self.entries
+            .insert(id.to_string(), StoredCheckpoint::Full(checkpoint));
+        Ok(())
+    }
+
+    /// The stored form of `id`
+    pub fn stored(&self, id: &str) -> Option<&StoredCheckpoint> {
+        self.entries.get(id)
+    }
+
+    /// Total size of the stored checkpoints serialized as JSON
+    pub fn stored_size(&self) -> Result<usize, LangGraphError> {
+        self.entries.values().try_fold(0, |size, stored| {
+            Ok(size + serde_json::to_string(stored)?.len())
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 /// A tuple containing a checkpoint and its associated data
 #[derive(Debug, Clone)]
 pub struct CheckpointTuple {
@@ -231,32 +630,130 @@ pub trait BaseCheckpointSaver {
     fn get_next_version(&self, current: Option<serde_json::Value>) -> serde_json::Value;
 }
 
+/// Pending writes keyed by checkpoint ID: (task_id, channel, value)
+type PendingWrites = HashMap<String, Vec<(String, String, Value)>>;
+
+/// Checkpoints of one thread with the metadata of each
+#[derive(Debug, Clone)]
+struct ThreadCheckpoints {
+    log: CheckpointLog,
+    metadata: HashMap<String, CheckpointMetadata>,
+}
+
 /// In-memory checkpoint saver for testing and simple use cases
+///
+/// Checkpoints are kept per `thread_id` of the config they are put with, in
+/// a [`CheckpointLog`] of the saver's [`CheckpointMode`]. A saver set up
+/// [`with_retention`](Self::with_retention) prunes the thread after every
+/// `put`; a checkpoint counts as taken at an interrupt when it records
+/// interrupts or an [`INTERRUPT`] write is stored against it.
 #[derive(Debug, Clone)]
 pub struct MemoryCheckpointSaver {
-    checkpoints: HashMap<String, (Checkpoint, CheckpointMetadata)>,
+    /// Checkpoints keyed by thread ID
+    threads: Arc<RwLock<HashMap<String, ThreadCheckpoints>>>,
+    writes: Arc<RwLock<PendingWrites>>,
+    mode: CheckpointMode,
+    retention: Option<RetentionPolicy>,
 }
 
 impl MemoryCheckpointSaver {
     pub fn new() -> Self {
         Self {
-            checkpoints: HashMap::new(),
+            threads: Arc::new(RwLock::new(HashMap::new())),
+            writes: Arc::new(RwLock::new(HashMap::new())),
+            mode: CheckpointMode::Full,
+            retention: None,
         }
     }
 
+    /// Store checkpoints in `mode`
+    ///
+    /// In [`CheckpointMode::Incremental`] a checkpoint put with the
+    /// `checkpoint_id` of a stored parent in its config is kept as a diff
+    /// against that parent.
+    pub fn with_mode(mut self, mode: CheckpointMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Prune each thread with `policy` after every `put`
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Get the pending writes stored for a checkpoint
+    pub fn pending_writes(&self, checkpoint_id: &str) -> Vec<(String, String, Value)> {
+        self.writes
+            .read()
+            .map(|writes| writes.get(checkpoint_id).cloned().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// IDs of the checkpoints stored for `thread_id`, oldest first
+    pub fn list(&self, thread_id: &str) -> Vec<String> {
+        self.threads
+            .read()
+            .map(|threads| {
+                threads
+                    .get(thread_id)
+                    .map(|thread| thread.log.order.clone())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default()
+    }
+
     /// Get the number of checkpoints stored
     pub fn len(&self) -> usize {
-        self.checkpoints.len()
+        self.threads
+            .read()
+            .map(|threads| threads.values().map(|thread| thread.log.len()).sum())
+            .unwrap_or_default()
     }
 
     /// Check if no checkpoints are stored
     pub fn is_empty(&self) -> bool {
-        self.checkpoints.is_empty()
+        self.len() == 0
     }
 
-    /// Clear all checkpoints
+    /// Clear all checkpoints and pending writes
     pub fn clear(&mut self) {
-        self.checkpoints.clear();
+        if let Ok(mut threads) = self.threads.write() {
+            threads.clear();
+        }
+        if let Ok(mut writes) = self.writes.write() {
+            writes.clear();
+        }
+    }
+
+    /// The checkpoint named by `checkpoint_id` in `config` with its metadata
+    ///
+    /// Only the thread named in `config` is searched, or every thread if
+    /// `config` names none.
+    fn load(
+        &self,
+        config: &HashMap<String, Value>,
+    ) -> Result<Option<(Checkpoint, CheckpointMetadata)>, LangGraphError> {
+        let Some(id) = config.get("checkpoint_id").and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let thread_id = config.get("thread_id").and_then(Value::as_str);
+        let threads = self.threads.read().map_err(|_| {
+            LangGraphError::CheckpointError("checkpoints lock poisoned".to_string())
+        })?;
+        let found = threads
+            .iter()
+            .filter(|(name, _)| thread_id.is_none() || thread_id == Some(name.as_str()))
+            .find(|(_, thread)| thread.log.contains(id));
+        match found {
+            Some((_, thread)) => Ok(Some((
+                thread.log.get(id)?,
+                thread.metadata.get(id).cloned().ok_or_else(|| {
+                    LangGraphError::CheckpointError(format!("no metadata for checkpoint '{}'", id))
+                })?,
+            ))),
+            None => Ok(None),
+        }
     }
 }
 
@@ -269,44 +766,83 @@ impl Default for MemoryCheckpointSaver {
 #[async_trait]
 impl BaseCheckpointSaver for MemoryCheckpointSaver {
     fn get(&self, config: &HashMap<String, Value>) -> Result<Option<Checkpoint>, LangGraphError> {
-        if let Some(id) = config.get("checkpoint_id") {
-            if let Some(id_str) = id.as_str() {
-                return Ok(self.checkpoints.get(id_str).map(|(cp, _)| cp.clone()));
-            }
-        }
-        Ok(None)
+        Ok(self.load(config)?.map(|(checkpoint, _)| checkpoint))
     }
 
     fn get_tuple(
         &self,
         config: &HashMap<String, Value>,
     ) -> Result<Option<CheckpointTuple>, LangGraphError> {
-        if let Some(id) = config.get("checkpoint_id") {
-            if let Some(id_str) = id.as_str() {
-                if let Some((checkpoint, metadata)) = self.checkpoints.get(id_str) {
-                    return Ok(Some(CheckpointTuple {
-                        config: config.clone(),
-                        checkpoint: checkpoint.clone(),
-                        metadata: metadata.clone(),
-                        parent_config: None,
-                        pending_writes: None,
-                    }));
-                }
-            }
-        }
-        Ok(None)
+        let Some((checkpoint, metadata)) = self.load(config)? else {
+            return Ok(None);
+        };
+        let pending_writes = self.pending_writes(&checkpoint.id);
+        Ok(Some(CheckpointTuple {
+            config: config.clone(),
+            checkpoint,
+            metadata,
+            parent_config: None,
+            pending_writes: (!pending_writes.is_empty()).then_some(pending_writes),
+        }))
     }
 
+    /// Store `checkpoint` in the thread named in `config`
+    ///
+    /// A `checkpoint_id` in `config` names the parent of the new checkpoint.
     fn put(
         &self,
-        _config: &HashMap<String, Value>,
+        config: &HashMap<String, Value>,
         checkpoint: &Checkpoint,
-        _metadata: &CheckpointMetadata,
+        metadata: &CheckpointMetadata,
         _new_versions: &ChannelVersions,
     ) -> Result<HashMap<String, Value>, LangGraphError> {
-        // In a real implementation, we would mutate the checkpoints map
-        // For this simplified version, we'll just return a config with the checkpoint ID
+        let thread_id = config
+            .get("thread_id")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let interrupt = checkpoint.is_interrupt(&self.pending_writes(&checkpoint.id));
+
+        let mut threads = self.threads.write().map_err(|_| {
+            LangGraphError::CheckpointError("checkpoints lock poisoned".to_string())
+        })?;
+        let thread = threads
+            .entry(thread_id.to_string())
+            .or_insert_with(|| ThreadCheckpoints {
+                log: CheckpointLog::new(self.mode),
+                metadata: HashMap::new(),
+            });
+        let parent_id = config
+            .get("checkpoint_id")
+            .and_then(Value::as_str)
+            .filter(|parent| thread.log.contains(parent));
+        if interrupt {
+            thread.log.put_interrupt(checkpoint, parent_id)?;
+        } else {
+            thread.log.put(checkpoint, parent_id)?;
+        }
+        thread
+            .metadata
+            .insert(checkpoint.id.clone(), metadata.clone());
+
+        if let Some(policy) = self.retention {
+            let pruned = thread.log.prune(policy);
+            if !pruned.is_empty() {
+                for id in &pruned {
+                    thread.metadata.remove(id);
+                }
+                if let Ok(mut writes) = self.writes.write() {
+                    for id in &pruned {
+                        writes.remove(id);
+                    }
+                }
+            }
+        }
+
         let mut new_config = HashMap::new();
+        new_config.insert(
+            "thread_id".to_string(),
+            Value::String(thread_id.to_string()),
+        );
         new_config.insert(
             "checkpoint_id".to_string(),
             Value::String(checkpoint.id.clone()),
@@ -316,11 +852,42 @@ impl BaseCheckpointSaver for MemoryCheckpointSaver {
 
     fn put_writes(
         &self,
-        _config: &HashMap<String, Value>,
-        _writes: &[(String, Value)],
-        _task_id: &str,
+        config: &HashMap<String, Value>,
+        writes: &[(String, Value)],
+        task_id: &str,
     ) -> Result<(), LangGraphError> {
-        // In a real implementation, we would store the writes
+        let checkpoint_id = config
+            .get("checkpoint_id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                LangGraphError::CheckpointError("config is missing 'checkpoint_id'".to_string())
+            })?;
+
+        let mut stored = self
+            .writes
+            .write()
+            .map_err(|_| LangGraphError::CheckpointError("writes lock poisoned".to_string()))?;
+        let checkpoint_writes = stored.entry(checkpoint_id.to_string()).or_default();
+
+        // Replace writes from an earlier attempt of the same task
+        checkpoint_writes.retain(|(existing_task, _, _)| existing_task != task_id);
+        checkpoint_writes.extend(
+            writes
+                .iter()
+                .map(|(channel, value)| (task_id.to_string(), channel.clone(), value.clone())),
+        );
+        drop(stored);
+
+        // An interrupt write marks the already stored checkpoint, so
+        // retention keeps it
+        if writes.iter().any(|(channel, _)| channel == INTERRUPT) {
+            let mut threads = self.threads.write().map_err(|_| {
+                LangGraphError::CheckpointError("checkpoints lock poisoned".to_string())
+            })?;
+            for thread in threads.values_mut() {
+                thread.log.mark_interrupt(checkpoint_id);
+            }
+        }
         Ok(())
     }
 
@@ -411,16 +978,303 @@ mod tests {
         assert_eq!(checkpoint.channel_values, deserialized.channel_values);
     }
 
+    #[test]
+    fn test_checkpoint_id_from_content() {
+        let values = HashMap::from([
+            ("a".to_string(), serde_json::json!({"x": 1, "y": [1, 2]})),
+            ("b".to_string(), Value::String("value".to_string())),
+        ]);
+        let id = CheckpointId::derive(Some("parent"), 2, &values);
+        assert_eq!(id, CheckpointId::derive(Some("parent"), 2, &values.clone()));
+        assert_eq!(id.as_uuid().get_version_num(), 5);
+
+        // Parent, step and values each feed the ID
+        assert_ne!(id, CheckpointId::derive(None, 2, &values));
+        assert_ne!(id, CheckpointId::derive(Some("parent"), 3, &values));
+        let mut changed = values.clone();
+        changed.insert("b".to_string(), Value::String("other".to_string()));
+        assert_ne!(id, CheckpointId::derive(Some("parent"), 2, &changed));
+
+        // The timestamp does not
+        let mut first = Checkpoint::new();
+        first.channel_values = values.clone();
+        first.derive_id(Some("parent"), 2);
+        let mut second = first.copy();
+        second.ts = first.ts + chrono::Duration::seconds(5);
+        second.derive_id(Some("parent"), 2);
+        assert_eq!(first.id, id.to_string());
+        assert_eq!(first.id, second.id);
+    }
+
+    #[test]
+    fn test_incremental_checkpoint_log() {
+        let big = Value::String("x".repeat(10_000));
+        let mut checkpoints = Vec::new();
+        let mut current = Checkpoint::new();
+        current
+            .channel_values
+            .insert("document".to_string(), big.clone());
+        current
+            .channel_values
+            .insert("scratch".to_string(), Value::from(0));
+        for step in 1..=5 {
+            let mut next = current.copy();
+            next.id = format!("cp{}", step);
+            next.channel_values
+                .insert("count".to_string(), Value::from(step));
+            if step == 3 {
+                next.channel_values.remove("scratch");
+            }
+            checkpoints.push(next.copy());
+            current = next;
+        }
+
+        let mut full = CheckpointLog::new(CheckpointMode::Full);
+        let mut incremental = CheckpointLog::new(CheckpointMode::Incremental);
+        let mut parent: Option<String> = None;
+        for checkpoint in &checkpoints {
+            full.put(checkpoint, parent.as_deref()).unwrap();
+            incremental.put(checkpoint, parent.as_deref()).unwrap();
+            parent = Some(checkpoint.id.clone());
+        }
+
+        // Only the first checkpoint carries the large channel
+        assert!(incremental.stored_size().unwrap() * 3 < full.stored_size().unwrap());
+        let Some(StoredCheckpoint::Diff(diff)) = incremental.stored("cp3").cloned() else {
+            panic!("cp3 should be stored as a diff");
+        };
+        assert_eq!(diff.removed, ["scratch"]);
+        assert_eq!(diff.checkpoint.channel_values.len(), 1);
+
+        for checkpoint in &checkpoints {
+            let loaded = incremental.get(&checkpoint.id).unwrap();
+            assert_eq!(loaded.channel_values, checkpoint.channel_values);
+        }
+        assert_eq!(incremental.chain_len("cp5"), 4);
+
+        incremental.compact("cp4").unwrap();
+        assert_eq!(incremental.chain_len("cp5"), 1);
+        assert_eq!(
+            incremental.get("cp5").unwrap().channel_values,
+            checkpoints[4].channel_values
+        );
+
+        // A diff only applies to its own parent
+        let err = Checkpoint::apply_diff(&checkpoints[0], &diff).unwrap_err();
+        assert!(err.to_string().contains("cp2"));
+    }
+
+    #[test]
+    fn test_checkpoint_retention() {
+        let checkpoint = |id: &str, step: i64| {
+            let mut checkpoint = Checkpoint::new();
+            checkpoint.id = id.to_string();
+            checkpoint
+                .channel_values
+                .insert("count".to_string(), Value::from(step));
+            checkpoint
+        };
+        let ids = |log: &CheckpointLog| {
+            let mut ids: Vec<String> = log.entries.keys().cloned().collect();
+            ids.sort();
+            ids
+        };
+
+        // Full checkpoints: the newest two and the interrupt survive
+        let mut full =
+            CheckpointLog::new(CheckpointMode::Full).with_retention(RetentionPolicy::new(2));
+        let mut parent: Option<String> = None;
+        for step in 1..=6 {
+            let id = format!("cp{}", step);
+            if step == 2 {
+                full.put_interrupt(&checkpoint(&id, step), parent.as_deref())
+                    .unwrap();
+            } else {
+                full.put(&checkpoint(&id, step), parent.as_deref()).unwrap();
+            }
+            parent = Some(id);
+        }
+        assert_eq!(ids(&full), ["cp2", "cp5", "cp6"]);
+
+        // Without keeping interrupts only the window is left
+        assert_eq!(
+            full.prune(RetentionPolicy {
+                keep_last: 2,
+                keep_interrupts: false,
+            }),
+            ["cp2"]
+        );
+        assert_eq!(ids(&full), ["cp5", "cp6"]);
+
+        // Diffs keep the chain back to a full checkpoint
+        let mut incremental = CheckpointLog::new(CheckpointMode::Incremental);
+        let mut parent: Option<String> = None;
+        for step in 1..=5 {
+            let id = format!("cp{}", step);
+            incremental
+                .put(&checkpoint(&id, step), parent.as_deref())
+                .unwrap();
+            parent = Some(id);
+        }
+        assert!(incremental.prune(RetentionPolicy::new(1)).is_empty());
+        incremental.compact("cp4").unwrap();
+        assert_eq!(
+            incremental.prune(RetentionPolicy::new(1)),
+            ["cp1", "cp2", "cp3"]
+        );
+        assert_eq!(ids(&incremental), ["cp4", "cp5"]);
+        assert_eq!(
+            incremental.get("cp5").unwrap().channel_values["count"],
+            Value::from(5)
+        );
+    }
+
     #[test]
     fn test_memory_checkpoint_saver() {
         let mut saver = MemoryCheckpointSaver::new();
-        assert_eq!(saver.checkpoints.len(), 0);
+        assert_eq!(saver.len(), 0);
         assert!(saver.is_empty());
 
         saver.clear();
         assert_eq!(saver.len(), 0);
     }
 
+    #[test]
+    fn test_memory_saver_put_writes() {
+        let saver = MemoryCheckpointSaver::new();
+        let mut config = HashMap::new();
+        config.insert(
+            "checkpoint_id".to_string(),
+            Value::String("cp1".to_string()),
+        );
+
+        saver
+            .put_writes(&config, &[("out".to_string(), Value::from(1))], "task-a")
+            .unwrap();
+        saver
+            .put_writes(&config, &[("out".to_string(), Value::from(2))], "task-b")
+            .unwrap();
+        // A retried task replaces its earlier writes
+        saver
+            .put_writes(&config, &[("out".to_string(), Value::from(3))], "task-a")
+            .unwrap();
+
+        let writes = saver.pending_writes("cp1");
+        assert_eq!(writes.len(), 2);
+        assert!(writes.contains(&("task-a".to_string(), "out".to_string(), Value::from(3))));
+        assert!(writes.contains(&("task-b".to_string(), "out".to_string(), Value::from(2))));
+        assert!(saver.pending_writes("other").is_empty());
+    }
+
+    #[test]
+    fn test_memory_saver_retention() {
+        let saver = MemoryCheckpointSaver::new().with_retention(RetentionPolicy::new(1));
+        let config = |id: Option<&str>| {
+            let mut config = HashMap::from([("thread_id".to_string(), Value::from("t1"))]);
+            if let Some(id) = id {
+                config.insert("checkpoint_id".to_string(), Value::from(id));
+            }
+            config
+        };
+        let metadata = |step| CheckpointMetadata {
+            source: "loop".to_string(),
+            step,
+            parents: HashMap::new(),
+        };
+
+        let mut parent: Option<String> = None;
+        for step in 1..=5 {
+            let mut checkpoint = Checkpoint::new();
+            checkpoint.id = format!("cp{}", step);
+            if step == 2 {
+                checkpoint.interrupts.push(Value::from("name?"));
+            }
+            saver
+                .put(
+                    &config(parent.as_deref()),
+                    &checkpoint,
+                    &metadata(step),
+                    &HashMap::new(),
+                )
+                .unwrap();
+            if step == 3 {
+                // Interrupt writes arrive after their checkpoint was put
+                saver
+                    .put_writes(
+                        &config(Some("cp3")),
+                        &[(INTERRUPT.to_string(), Value::from("age?"))],
+                        "task",
+                    )
+                    .unwrap();
+            }
+            parent = Some(checkpoint.id);
+        }
+
+        assert_eq!(saver.list("t1"), ["cp2", "cp3", "cp5"]);
+        let tuple = saver.get_tuple(&config(Some("cp3"))).unwrap().unwrap();
+        assert_eq!(tuple.metadata.step, 3);
+        assert_eq!(tuple.pending_writes.unwrap().len(), 1);
+        assert!(saver.get(&config(Some("cp4"))).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_memory_saver_incremental() {
+        let saver = MemoryCheckpointSaver::new().with_mode(CheckpointMode::Incremental);
+        let config = |id: Option<&str>| {
+            let mut config = HashMap::from([("thread_id".to_string(), Value::from("t1"))]);
+            if let Some(id) = id {
+                config.insert("checkpoint_id".to_string(), Value::from(id));
+            }
+            config
+        };
+        let metadata = CheckpointMetadata {
+            source: "loop".to_string(),
+            step: 0,
+            parents: HashMap::new(),
+        };
+
+        let mut checkpoints = Vec::new();
+        let mut current = Checkpoint::new();
+        current
+            .channel_values
+            .insert("document".to_string(), Value::String("x".repeat(10_000)));
+        let mut parent: Option<String> = None;
+        for step in 1..=3 {
+            current.id = format!("cp{}", step);
+            current
+                .channel_values
+                .insert("count".to_string(), Value::from(step));
+            let stored = saver
+                .put(
+                    &config(parent.as_deref()),
+                    &current,
+                    &metadata,
+                    &HashMap::new(),
+                )
+                .unwrap();
+            assert_eq!(stored, config(Some(&current.id)));
+            checkpoints.push(current.copy());
+            parent = Some(current.id.clone());
+        }
+
+        {
+            let threads = saver.threads.read().unwrap();
+            let log = &threads["t1"].log;
+            assert_eq!(log.chain_len("cp3"), 2);
+            let Some(StoredCheckpoint::Diff(diff)) = log.stored("cp3") else {
+                panic!("cp3 should be stored as a diff");
+            };
+            assert_eq!(diff.checkpoint.channel_values.len(), 1);
+        }
+        for checkpoint in &checkpoints {
+            let tuple = saver
+                .get_tuple(&config(Some(&checkpoint.id)))
+                .unwrap()
+                .unwrap();
+            assert_eq!(tuple.checkpoint.channel_values, checkpoint.channel_values);
+        }
+    }
+
     #[test]
     fn test_checkpoint_memory_usage() {
         let mut checkpoint = Checkpoint::new();
@@ -0,0 +1,478 @@
+//! PostgreSQL checkpoint storage with JSONB columns
+//!
+//! Checkpoints are stored as JSONB keyed by `(thread_id, checkpoint_ns,
+//! checkpoint_id)`. Pending channel writes live in a separate
+//! `checkpoint_writes` table so a partially completed superstep can be
+//! recovered. Tables are created by [`PostgresCheckpointer::setup`], which
+//! applies versioned migrations idempotently.
+
+use crate::checkpoint::{
+    Checkpoint, CheckpointMetadata, CheckpointTuple, RetentionPolicy, INTERRUPT,
+};
+use crate::errors::LangGraphError;
+use deadpool_postgres::tokio_postgres::types::Json;
+use deadpool_postgres::tokio_postgres::{NoTls, Row, Transaction};
+use deadpool_postgres::{Config, Pool, Runtime};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Schema migrations, applied in order and recorded in `checkpoint_migrations`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS checkpoints (
+        thread_id TEXT NOT NULL,
+        checkpoint_ns TEXT NOT NULL DEFAULT '',
+        checkpoint_id TEXT NOT NULL,
+        parent_checkpoint_id TEXT,
+        checkpoint JSONB NOT NULL,
+        metadata JSONB NOT NULL DEFAULT '{}',
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+        PRIMARY KEY (thread_id, checkpoint_ns, checkpoint_id)
+    )",
+    "CREATE TABLE IF NOT EXISTS checkpoint_writes (
+        thread_id TEXT NOT NULL,
+        checkpoint_ns TEXT NOT NULL DEFAULT '',
+        checkpoint_id TEXT NOT NULL,
+        task_id TEXT NOT NULL,
+        idx INTEGER NOT NULL,
+        channel TEXT NOT NULL,
+        value JSONB,
+        PRIMARY KEY (thread_id, checkpoint_ns, checkpoint_id, task_id, idx)
+    )",
+    "CREATE INDEX IF NOT EXISTS checkpoints_thread_id_idx ON checkpoints(thread_id, checkpoint_ns, created_at)",
+];
+
+/// PostgreSQL-based checkpoint storage with a connection pool
+///
+/// With a retention policy every `put` also prunes the thread's namespace.
+#[derive(Clone)]
+pub struct PostgresCheckpointer {
+    pool: Pool,
+    retention: Option<RetentionPolicy>,
+}
+
+impl PostgresCheckpointer {
+    /// Create a checkpointer connected to the given PostgreSQL URL
+    ///
+    /// Call [`setup`](Self::setup) once before first use to create the tables.
+    pub fn new(url: &str) -> Result<Self, LangGraphError> {
+        let mut config = Config::new();
+        config.url = Some(url.to_string());
+        let pool = config
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .map_err(|e| LangGraphError::CheckpointError(format!("Postgres pool error: {}", e)))?;
+        Ok(Self::from_pool(pool))
+    }
+
+    /// Create a checkpointer from an existing connection pool
+    pub fn from_pool(pool: Pool) -> Self {
+        Self {
+            pool,
+            retention: None,
+        }
+    }
+
+    /// Prune each thread with `policy` after every `put`
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Create or upgrade the checkpoint tables
+    ///
+    /// Safe to call on every startup: migrations already recorded in
+    /// `checkpoint_migrations` are skipped.
+    pub async fn setup(&self) -> Result<(), LangGraphError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(pg_error)?;
+
+        tx.batch_execute(
+            "CREATE TABLE IF NOT EXISTS checkpoint_migrations (v INTEGER PRIMARY KEY)",
+        )
+        .await
+        .map_err(pg_error)?;
+
+        let applied: Option<i32> = tx
+            .query_one("SELECT MAX(v) FROM checkpoint_migrations", &[])
+            .await
+            .map_err(pg_error)?
+            .get(0);
+        let start = applied.map(|v| v as usize + 1).unwrap_or(0);
+
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(start) {
+            tx.batch_execute(migration).await.map_err(pg_error)?;
+            tx.execute(
+                "INSERT INTO checkpoint_migrations (v) VALUES ($1)",
+                &[&(version as i32)],
+            )
+            .await
+            .map_err(pg_error)?;
+        }
+
+        tx.commit().await.map_err(pg_error)
+    }
+
+    /// Store a checkpoint for the thread named in `config`
+    ///
+    /// The insert, and pruning under a retention policy, run in a
+    /// transaction, so a crash mid-write leaves the thread at its previous
+    /// checkpoint. If `config` carries a `checkpoint_id`, it is recorded as
+    /// the parent of the new checkpoint.
+    pub async fn put(
+        &self,
+        config: &HashMap<String, Value>,
+        checkpoint: &Checkpoint,
+        metadata: &CheckpointMetadata,
+    ) -> Result<HashMap<String, Value>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let checkpoint_ns = checkpoint_ns(config);
+        let parent_checkpoint_id = checkpoint_id(config);
+
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(pg_error)?;
+        tx.execute(
+            "INSERT INTO checkpoints
+                (thread_id, checkpoint_ns, checkpoint_id, parent_checkpoint_id, checkpoint, metadata)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (thread_id, checkpoint_ns, checkpoint_id)
+             DO UPDATE SET checkpoint = EXCLUDED.checkpoint, metadata = EXCLUDED.metadata",
+            &[
+                &thread_id,
+                &checkpoint_ns,
+                &checkpoint.id,
+                &parent_checkpoint_id,
+                &Json(checkpoint),
+                &Json(metadata),
+            ],
+        )
+        .await
+        .map_err(pg_error)?;
+        if let Some(policy) = self.retention {
+            prune_in(&tx, thread_id, checkpoint_ns, policy).await?;
+        }
+        tx.commit().await.map_err(pg_error)?;
+
+        Ok(checkpoint_config(thread_id, checkpoint_ns, &checkpoint.id))
+    }
+
+    /// Delete the checkpoints of the thread named in `config` that `policy`
+    /// does not retain, with their pending writes, returning their IDs
+    /// oldest first
+    pub async fn prune(
+        &self,
+        config: &HashMap<String, Value>,
+        policy: RetentionPolicy,
+    ) -> Result<Vec<String>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let checkpoint_ns = checkpoint_ns(config);
+
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(pg_error)?;
+        let pruned = prune_in(&tx, thread_id, checkpoint_ns, policy).await?;
+        tx.commit().await.map_err(pg_error)?;
+        Ok(pruned)
+    }
+
+    /// Store pending channel writes produced by a task
+    ///
+    /// Writes are keyed by task and index, so re-sending the same writes after
+    /// a retry overwrites rather than duplicates them.
+    pub async fn put_writes(
+        &self,
+        config: &HashMap<String, Value>,
+        writes: &[(String, Value)],
+        task_id: &str,
+    ) -> Result<(), LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let checkpoint_ns = checkpoint_ns(config);
+        let checkpoint_id = checkpoint_id(config).ok_or_else(|| {
+            LangGraphError::CheckpointError("config is missing 'checkpoint_id'".to_string())
+        })?;
+
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(pg_error)?;
+        for (idx, (channel, value)) in writes.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO checkpoint_writes
+                    (thread_id, checkpoint_ns, checkpoint_id, task_id, idx, channel, value)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (thread_id, checkpoint_ns, checkpoint_id, task_id, idx)
+                 DO UPDATE SET channel = EXCLUDED.channel, value = EXCLUDED.value",
+                &[
+                    &thread_id,
+                    &checkpoint_ns,
+                    &checkpoint_id,
+                    &task_id,
+                    &(idx as i32),
+                    channel,
+                    value,
+                ],
+            )
+            .await
+            .map_err(pg_error)?;
+        }
+        tx.commit().await.map_err(pg_error)
+    }
+
+    /// Fetch a checkpoint together with its pending writes
+    ///
+    /// Returns the checkpoint named by `checkpoint_id` in `config`, or the most
+    /// recent checkpoint of the thread when no id is given.
+    pub async fn get(
+        &self,
+        config: &HashMap<String, Value>,
+    ) -> Result<Option<CheckpointTuple>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let checkpoint_ns = checkpoint_ns(config);
+        let client = self.client().await?;
+
+        let row = match checkpoint_id(config) {
+            Some(checkpoint_id) => client
+                .query_opt(
+                    "SELECT checkpoint, metadata, parent_checkpoint_id FROM checkpoints
+                     WHERE thread_id = $1 AND checkpoint_ns = $2 AND checkpoint_id = $3",
+                    &[&thread_id, &checkpoint_ns, &checkpoint_id],
+                )
+                .await
+                .map_err(pg_error)?,
+            None => client
+                .query_opt(
+                    "SELECT checkpoint, metadata, parent_checkpoint_id FROM checkpoints
+                     WHERE thread_id = $1 AND checkpoint_ns = $2
+                     ORDER BY created_at DESC LIMIT 1",
+                    &[&thread_id, &checkpoint_ns],
+                )
+                .await
+                .map_err(pg_error)?,
+        };
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut tuple = row_to_tuple(thread_id, checkpoint_ns, &row)?;
+
+        let writes = client
+            .query(
+                "SELECT task_id, channel, value FROM checkpoint_writes
+                 WHERE thread_id = $1 AND checkpoint_ns = $2 AND checkpoint_id = $3
+                 ORDER BY task_id, idx",
+                &[&thread_id, &checkpoint_ns, &tuple.checkpoint.id],
+            )
+            .await
+            .map_err(pg_error)?;
+        tuple.pending_writes = Some(
+            writes
+                .iter()
+                .map(|row| {
+                    let value: Option<Value> = row.get(2);
+                    (row.get(0), row.get(1), value.unwrap_or(Value::Null))
+                })
+                .collect(),
+        );
+
+        Ok(Some(tuple))
+    }
+
+    /// List checkpoints of a thread, newest first
+    pub async fn list(
+        &self,
+        config: &HashMap<String, Value>,
+        limit: Option<usize>,
+    ) -> Result<Vec<CheckpointTuple>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let checkpoint_ns = checkpoint_ns(config);
+        let limit = limit.map(|l| l as i64);
+        let client = self.client().await?;
+
+        let rows = client
+            .query(
+                "SELECT checkpoint, metadata, parent_checkpoint_id FROM checkpoints
+                 WHERE thread_id = $1 AND checkpoint_ns = $2
+                 ORDER BY created_at DESC LIMIT $3",
+                &[&thread_id, &checkpoint_ns, &limit],
+            )
+            .await
+            .map_err(pg_error)?;
+
+        rows.iter()
+            .map(|row| row_to_tuple(thread_id, checkpoint_ns, row))
+            .collect()
+    }
+
+    /// Delete all checkpoints and pending writes of a thread
+    pub async fn delete_thread(&self, thread_id: &str) -> Result<(), LangGraphError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(pg_error)?;
+        tx.execute(
+            "DELETE FROM checkpoint_writes WHERE thread_id = $1",
+            &[&thread_id],
+        )
+        .await
+        .map_err(pg_error)?;
+        tx.execute(
+            "DELETE FROM checkpoints WHERE thread_id = $1",
+            &[&thread_id],
+        )
+        .await
+        .map_err(pg_error)?;
+        tx.commit().await.map_err(pg_error)
+    }
+
+    async fn client(&self) -> Result<deadpool_postgres::Client, LangGraphError> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| LangGraphError::CheckpointError(format!("Postgres pool error: {}", e)))
+    }
+}
+
+impl std::fmt::Debug for PostgresCheckpointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresCheckpointer")
+            .field("pool_status", &self.pool.status())
+            .finish()
+    }
+}
+
+/// Prune one thread's namespace inside `tx`
+///
+/// A checkpoint counts as taken at an interrupt when it records the
+/// interrupts the run was paused on or has an interrupt write pending.
+async fn prune_in(
+    tx: &Transaction<'_>,
+    thread_id: &str,
+    checkpoint_ns: &str,
+    policy: RetentionPolicy,
+) -> Result<Vec<String>, LangGraphError> {
+    let rows = tx
+        .query(
+            "SELECT c.checkpoint_id,
+                    jsonb_array_length(COALESCE(c.checkpoint->'interrupts', '[]'::jsonb)) > 0
+                    OR EXISTS (
+                        SELECT 1 FROM checkpoint_writes w
+                        WHERE w.thread_id = c.thread_id AND w.checkpoint_ns = c.checkpoint_ns
+                          AND w.checkpoint_id = c.checkpoint_id AND w.channel = $3
+                    )
+             FROM checkpoints c
+             WHERE c.thread_id = $1 AND c.checkpoint_ns = $2
+             ORDER BY c.created_at, c.checkpoint_id",
+            &[&thread_id, &checkpoint_ns, &INTERRUPT],
+        )
+        .await
+        .map_err(pg_error)?;
+    let ids: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    let interrupts: HashSet<&str> = rows
+        .iter()
+        .filter(|row| row.get::<_, bool>(1))
+        .map(|row| row.get(0))
+        .collect();
+
+    let pruned = policy.prunable(&ids, |id| interrupts.contains(id), |_| None);
+    if !pruned.is_empty() {
+        for table in ["checkpoint_writes", "checkpoints"] {
+            let statement = format!(
+                "DELETE FROM {} WHERE thread_id = $1 AND checkpoint_ns = $2
+                 AND checkpoint_id = ANY($3)",
+                table
+            );
+            tx.execute(statement.as_str(), &[&thread_id, &checkpoint_ns, &pruned])
+                .await
+                .map_err(pg_error)?;
+        }
+    }
+    Ok(pruned)
+}
+
+fn thread_id(config: &HashMap<String, Value>) -> Result<&str, LangGraphError> {
+    config
+        .get("thread_id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| LangGraphError::CheckpointError("config is missing 'thread_id'".to_string()))
+}
+
+fn checkpoint_ns(config: &HashMap<String, Value>) -> &str {
+    config
+        .get("checkpoint_ns")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+}
+
+fn checkpoint_id(config: &HashMap<String, Value>) -> Option<&str> {
+    config.get("checkpoint_id").and_then(Value::as_str)
+}
+
+fn checkpoint_config(
+    thread_id: &str,
+    checkpoint_ns: &str,
+    checkpoint_id: &str,
+) -> HashMap<String, Value> {
+    let mut config = HashMap::new();
+    config.insert(
+        "thread_id".to_string(),
+        Value::String(thread_id.to_string()),
+    );
+    config.insert(
+        "checkpoint_ns".to_string(),
+        Value::String(checkpoint_ns.to_string()),
+    );
+    config.insert(
+        "checkpoint_id".to_string(),
+        Value::String(checkpoint_id.to_string()),
+    );
+    config
+}
+
+fn row_to_tuple(
+    thread_id: &str,
+    checkpoint_ns: &str,
+    row: &Row,
+) -> Result<CheckpointTuple, LangGraphError> {
+    let Json(checkpoint): Json<Checkpoint> = row.try_get(0).map_err(pg_error)?;
+    let Json(metadata): Json<CheckpointMetadata> = row.try_get(1).map_err(pg_error)?;
+    let parent_checkpoint_id: Option<String> = row.try_get(2).map_err(pg_error)?;
+
+    Ok(CheckpointTuple {
+        config: checkpoint_config(thread_id, checkpoint_ns, &checkpoint.id),
+        parent_config: parent_checkpoint_id
+            .as_deref()
+            .map(|parent| checkpoint_config(thread_id, checkpoint_ns, parent)),
+        checkpoint,
+        metadata,
+        pending_writes: None,
+    })
+}
+
+fn pg_error(error: deadpool_postgres::tokio_postgres::Error) -> LangGraphError {
+    LangGraphError::CheckpointError(format!("Postgres error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_idempotent_ddl() {
+        for migration in MIGRATIONS {
+            assert!(migration.contains("IF NOT EXISTS"));
+        }
+    }
+
+    #[test]
+    fn test_config_helpers() {
+        let mut config = HashMap::new();
+        assert!(thread_id(&config).is_err());
+        assert_eq!(checkpoint_ns(&config), "");
+
+        config.insert("thread_id".to_string(), Value::String("t1".to_string()));
+        config.insert(
+            "checkpoint_ns".to_string(),
+            Value::String("sub".to_string()),
+        );
+        assert_eq!(thread_id(&config).unwrap(), "t1");
+        assert_eq!(checkpoint_ns(&config), "sub");
+        assert_eq!(checkpoint_id(&config), None);
+
+        let built = checkpoint_config("t1", "sub", "c1");
+        assert_eq!(built.get("checkpoint_id").unwrap(), "c1");
+        assert_eq!(built.get("checkpoint_ns").unwrap(), "sub");
+    }
+}
@@ -0,0 +1,405 @@
+//! Redis checkpoint storage for distributed resume
+//!
+//! Checkpoints are stored MessagePack-serialized under per-thread keys, with a
+//! sorted set per thread (scored by checkpoint timestamp) providing ordering.
+//! Because all state lives in Redis, any worker process connected to the same
+//! instance can pick up and resume a thread.
+
+use crate::checkpoint::{Checkpoint, CheckpointMetadata, CheckpointTuple, RetentionPolicy};
+use crate::errors::LangGraphError;
+use deadpool_redis::redis::{self, AsyncCommands};
+use deadpool_redis::{Config, Pool, Runtime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Default prefix for all keys written by the checkpointer
+const DEFAULT_KEY_PREFIX: &str = "langgraph";
+
+/// Record stored under each checkpoint key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCheckpoint {
+    checkpoint: Checkpoint,
+    metadata: CheckpointMetadata,
+    parent_checkpoint_id: Option<String>,
+}
+
+/// Redis-based checkpoint storage with a connection pool
+///
+/// Layout for a thread `t`:
+/// - `{prefix}:checkpoint:{t}:{checkpoint_id}` - serialized checkpoint
+/// - `{prefix}:checkpoints:{t}` - sorted set of checkpoint ids scored by timestamp
+///
+/// When a TTL is configured, both keys expire after the TTL; every `put`
+/// refreshes the expiry of the thread index. With a retention policy every
+/// `put` also prunes the thread.
+#[derive(Clone)]
+pub struct RedisCheckpointer {
+    pool: Pool,
+    key_prefix: String,
+    ttl: Option<Duration>,
+    retention: Option<RetentionPolicy>,
+}
+
+impl RedisCheckpointer {
+    /// Create a checkpointer connected to the given Redis URL
+    pub fn new(url: &str) -> Result<Self, LangGraphError> {
+        let pool = Config::from_url(url)
+            .create_pool(Some(Runtime::Tokio1))
+            .map_err(|e| LangGraphError::CheckpointError(format!("Redis pool error: {}", e)))?;
+        Ok(Self::from_pool(pool))
+    }
+
+    /// Create a checkpointer from an existing connection pool
+    pub fn from_pool(pool: Pool) -> Self {
+        Self {
+            pool,
+            key_prefix: DEFAULT_KEY_PREFIX.to_string(),
+            ttl: None,
+            retention: None,
+        }
+    }
+
+    /// Expire checkpoints after the given duration
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Prune each thread with `policy` after every `put`
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    /// Use a custom key prefix (useful for sharing one Redis between apps)
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// Store a checkpoint for the thread named in `config`
+    ///
+    /// Returns a config pointing at the stored checkpoint. If `config` carries
+    /// a `checkpoint_id`, it is recorded as the parent of the new checkpoint.
+    pub async fn put(
+        &self,
+        config: &HashMap<String, Value>,
+        checkpoint: &Checkpoint,
+        metadata: &CheckpointMetadata,
+    ) -> Result<HashMap<String, Value>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let stored = StoredCheckpoint {
+            checkpoint: checkpoint.clone(),
+            metadata: metadata.clone(),
+            parent_checkpoint_id: checkpoint_id(config).map(str::to_string),
+        };
+        let data = rmp_serde::to_vec_named(&stored)?;
+
+        let checkpoint_key = self.checkpoint_key(thread_id, &checkpoint.id);
+        let index_key = self.index_key(thread_id);
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        match self.ttl {
+            Some(ttl) => pipe.set_ex(&checkpoint_key, data, ttl.as_secs().max(1)),
+            None => pipe.set(&checkpoint_key, data),
+        }
+        .ignore()
+        .zadd(&index_key, &checkpoint.id, checkpoint.ts.timestamp_millis())
+        .ignore();
+        if let Some(ttl) = self.ttl {
+            pipe.expire(&index_key, ttl.as_secs().max(1) as i64)
+                .ignore();
+        }
+
+        let mut conn = self.connection().await?;
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(redis_error)?;
+
+        if let Some(policy) = self.retention {
+            self.prune(thread_id, policy).await?;
+        }
+
+        Ok(checkpoint_config(thread_id, &checkpoint.id))
+    }
+
+    /// Delete the checkpoints of a thread that `policy` does not retain,
+    /// returning their IDs oldest first
+    ///
+    /// A checkpoint counts as taken at an interrupt when it records the
+    /// interrupts the run was paused on. Checkpoints whose data has already
+    /// expired are dropped from the index.
+    pub async fn prune(
+        &self,
+        thread_id: &str,
+        policy: RetentionPolicy,
+    ) -> Result<Vec<String>, LangGraphError> {
+        let mut conn = self.connection().await?;
+        let index_key = self.index_key(thread_id);
+
+        let ids: Vec<String> = conn.zrange(&index_key, 0, -1).await.map_err(redis_error)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let keys: Vec<String> = ids
+            .iter()
+            .map(|id| self.checkpoint_key(thread_id, id))
+            .collect();
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+
+        let mut live = Vec::new();
+        let mut expired = Vec::new();
+        let mut interrupts = HashSet::new();
+        for (id, value) in ids.into_iter().zip(values) {
+            match value {
+                Some(bytes) => {
+                    let stored: StoredCheckpoint = rmp_serde::from_slice(&bytes)?;
+                    if stored.checkpoint.is_interrupt(&[]) {
+                        interrupts.insert(id.clone());
+                    }
+                    live.push(id);
+                }
+                None => expired.push(id),
+            }
+        }
+        let pruned = policy.prunable(&live, |id| interrupts.contains(id), |_| None);
+
+        let stale: Vec<&String> = pruned.iter().chain(&expired).collect();
+        if !stale.is_empty() {
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            for id in &stale {
+                pipe.del(self.checkpoint_key(thread_id, id))
+                    .ignore()
+                    .zrem(&index_key, *id)
+                    .ignore();
+            }
+            pipe.query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(redis_error)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Fetch a checkpoint
+    ///
+    /// Returns the checkpoint named by `checkpoint_id` in `config`, or the most
+    /// recent checkpoint of the thread when no id is given.
+    pub async fn get(
+        &self,
+        config: &HashMap<String, Value>,
+    ) -> Result<Option<CheckpointTuple>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let mut conn = self.connection().await?;
+
+        let checkpoint_id = match checkpoint_id(config) {
+            Some(id) => id.to_string(),
+            None => {
+                let latest: Vec<String> = conn
+                    .zrevrange(self.index_key(thread_id), 0, 0)
+                    .await
+                    .map_err(redis_error)?;
+                match latest.into_iter().next() {
+                    Some(id) => id,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let data: Option<Vec<u8>> = conn
+            .get(self.checkpoint_key(thread_id, &checkpoint_id))
+            .await
+            .map_err(redis_error)?;
+
+        data.map(|bytes| decode_tuple(thread_id, &bytes))
+            .transpose()
+    }
+
+    /// List checkpoints of a thread, newest first
+    ///
+    /// Checkpoints whose data has already expired are skipped.
+    pub async fn list(
+        &self,
+        config: &HashMap<String, Value>,
+        limit: Option<usize>,
+    ) -> Result<Vec<CheckpointTuple>, LangGraphError> {
+        let thread_id = thread_id(config)?;
+        let mut conn = self.connection().await?;
+
+        let stop = limit.map(|l| l as isize - 1).unwrap_or(-1);
+        if stop < -1 || limit == Some(0) {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<String> = conn
+            .zrevrange(self.index_key(thread_id), 0, stop)
+            .await
+            .map_err(redis_error)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = ids
+            .iter()
+            .map(|id| self.checkpoint_key(thread_id, id))
+            .collect();
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+
+        values
+            .into_iter()
+            .flatten()
+            .map(|bytes| decode_tuple(thread_id, &bytes))
+            .collect()
+    }
+
+    /// Delete all checkpoints of a thread
+    pub async fn delete_thread(&self, thread_id: &str) -> Result<(), LangGraphError> {
+        let mut conn = self.connection().await?;
+        let index_key = self.index_key(thread_id);
+
+        let ids: Vec<String> = conn.zrange(&index_key, 0, -1).await.map_err(redis_error)?;
+        let mut keys: Vec<String> = ids
+            .iter()
+            .map(|id| self.checkpoint_key(thread_id, id))
+            .collect();
+        keys.push(index_key);
+
+        conn.del::<_, ()>(keys).await.map_err(redis_error)
+    }
+
+    async fn connection(&self) -> Result<deadpool_redis::Connection, LangGraphError> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| LangGraphError::CheckpointError(format!("Redis pool error: {}", e)))
+    }
+
+    fn checkpoint_key(&self, thread_id: &str, checkpoint_id: &str) -> String {
+        format!(
+            "{}:checkpoint:{}:{}",
+            self.key_prefix, thread_id, checkpoint_id
+        )
+    }
+
+    fn index_key(&self, thread_id: &str) -> String {
+        format!("{}:checkpoints:{}", self.key_prefix, thread_id)
+    }
+}
+
+impl std::fmt::Debug for RedisCheckpointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisCheckpointer")
+            .field("key_prefix", &self.key_prefix)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+fn thread_id(config: &HashMap<String, Value>) -> Result<&str, LangGraphError> {
+    config
+        .get("thread_id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| LangGraphError::CheckpointError("config is missing 'thread_id'".to_string()))
+}
+
+fn checkpoint_id(config: &HashMap<String, Value>) -> Option<&str> {
+    config.get("checkpoint_id").and_then(Value::as_str)
+}
+
+fn checkpoint_config(thread_id: &str, checkpoint_id: &str) -> HashMap<String, Value> {
+    let mut config = HashMap::new();
+    config.insert(
+        "thread_id".to_string(),
+        Value::String(thread_id.to_string()),
+    );
+    config.insert(
+        "checkpoint_id".to_string(),
+        Value::String(checkpoint_id.to_string()),
+    );
+    config
+}
+
+fn decode_tuple(thread_id: &str, bytes: &[u8]) -> Result<CheckpointTuple, LangGraphError> {
+    let stored: StoredCheckpoint = rmp_serde::from_slice(bytes)?;
+    Ok(CheckpointTuple {
+        config: checkpoint_config(thread_id, &stored.checkpoint.id),
+        parent_config: stored
+            .parent_checkpoint_id
+            .as_deref()
+            .map(|parent| checkpoint_config(thread_id, parent)),
+        checkpoint: stored.checkpoint,
+        metadata: stored.metadata,
+        pending_writes: None,
+    })
+}
+
+fn redis_error(error: redis::RedisError) -> LangGraphError {
+    LangGraphError::CheckpointError(format!("Redis error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> CheckpointMetadata {
+        CheckpointMetadata {
+            source: "loop".to_string(),
+            step: 3,
+            parents: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_layout() {
+        let saver = RedisCheckpointer::new("redis://127.0.0.1/")
+            .unwrap()
+            .with_key_prefix("app");
+
+        assert_eq!(saver.checkpoint_key("t1", "c1"), "app:checkpoint:t1:c1");
+        assert_eq!(saver.index_key("t1"), "app:checkpoints:t1");
+    }
+
+    #[test]
+    fn test_missing_thread_id() {
+        let config = HashMap::new();
+        assert!(thread_id(&config).is_err());
+    }
+
+    #[test]
+    fn test_stored_checkpoint_roundtrip() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint
+            .channel_values
+            .insert("messages".to_string(), serde_json::json!(["hi"]));
+
+        let stored = StoredCheckpoint {
+            checkpoint: checkpoint.clone(),
+            metadata: metadata(),
+            parent_checkpoint_id: Some("parent".to_string()),
+        };
+        let bytes = rmp_serde::to_vec_named(&stored).unwrap();
+
+        let tuple = decode_tuple("t1", &bytes).unwrap();
+        assert_eq!(tuple.checkpoint.id, checkpoint.id);
+        assert_eq!(tuple.checkpoint.channel_values, checkpoint.channel_values);
+        assert_eq!(tuple.metadata.step, 3);
+        assert_eq!(
+            tuple.config.get("checkpoint_id"),
+            Some(&Value::String(checkpoint.id.clone()))
+        );
+        assert_eq!(
+            tuple.parent_config.unwrap().get("checkpoint_id"),
+            Some(&Value::String("parent".to_string()))
+        );
+    }
+}
@@ -2,7 +2,7 @@ use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyDict};
 #[allow(unused_imports)]
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[cfg(feature = "sqlite")]
 use rusqlite::{params, Connection, Result as SqliteResult};
@@ -10,7 +10,8 @@ use rusqlite::{params, Connection, Result as SqliteResult};
 #[cfg(feature = "compression-zstd")]
 use zstd;
 
-use crate::rust_checkpoint::CheckpointData;
+use crate::checkpoint::RetentionPolicy;
+use crate::rust_checkpoint::{taken_at_interrupt, CheckpointData};
 
 /// Compression algorithm
 #[derive(Debug, Clone, Copy)]
@@ -54,14 +55,28 @@ impl Compression {
 pub struct RustSQLiteCheckpointer {
     db_path: String,
     compression: Compression,
+    /// Policy applied to a thread after each `put`
+    retention: Option<RetentionPolicy>,
 }
 
 #[cfg(feature = "sqlite")]
 #[pymethods]
 impl RustSQLiteCheckpointer {
+    /// Create a checkpointer
+    ///
+    /// Args:
+    ///     db_path: Path of the SQLite database
+    ///     compression: "zstd" or "lz4" to compress stored checkpoints
+    ///     keep_last: Keep only this many of the newest checkpoints per thread
+    ///     keep_interrupts: Also keep checkpoints taken at an interrupt
     #[new]
-    #[pyo3(signature = (db_path, compression=None))]
-    fn new(db_path: String, compression: Option<&str>) -> PyResult<Self> {
+    #[pyo3(signature = (db_path, compression=None, keep_last=None, keep_interrupts=true))]
+    fn new(
+        db_path: String,
+        compression: Option<&str>,
+        keep_last: Option<usize>,
+        keep_interrupts: bool,
+    ) -> PyResult<Self> {
         let comp = match compression {
             None => Compression::None,
             #[cfg(feature = "compression-zstd")]
@@ -79,6 +94,10 @@ impl RustSQLiteCheckpointer {
         let checkpointer = RustSQLiteCheckpointer {
             db_path,
             compression: comp,
+            retention: keep_last.map(|keep_last| RetentionPolicy {
+                keep_last,
+                keep_interrupts,
+            }),
         };
 
         // Initialize database
@@ -97,6 +116,7 @@ impl RustSQLiteCheckpointer {
     ) -> PyResult<bool> {
         // Extract checkpoint data (reuse from rust_checkpoint.rs)
         let channel_values = self.extract_channel_values(py, checkpoint)?;
+        let interrupt = taken_at_interrupt(checkpoint)?;
         let channel_versions = self.extract_versions(checkpoint, "channel_versions")?;
         let versions_seen = self.extract_versions_seen(checkpoint)?;
         let step = checkpoint
@@ -110,6 +130,7 @@ impl RustSQLiteCheckpointer {
             versions_seen,
             pending_writes: Vec::new(),
             step,
+            interrupt,
         };
 
         // Serialize using MessagePack
@@ -131,6 +152,10 @@ impl RustSQLiteCheckpointer {
         )
         .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Insert error: {}", e)))?;
 
+        if let Some(policy) = self.retention {
+            self.prune_thread(&conn, &thread_id, policy)?;
+        }
+
         Ok(true)
     }
 
@@ -224,6 +249,34 @@ impl RustSQLiteCheckpointer {
         Ok(rows > 0)
     }
 
+    /// Delete old checkpoints of a thread
+    ///
+    /// Args:
+    ///     thread_id: Thread/conversation identifier
+    ///     keep_last: Number of the newest checkpoints to keep
+    ///     keep_interrupts: Also keep checkpoints taken at an interrupt
+    ///
+    /// Returns:
+    ///     IDs of the deleted checkpoints, oldest first
+    #[pyo3(signature = (thread_id, keep_last, keep_interrupts=true))]
+    fn prune(
+        &self,
+        thread_id: String,
+        keep_last: usize,
+        keep_interrupts: bool,
+    ) -> PyResult<Vec<String>> {
+        let conn = Connection::open(&self.db_path)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Database error: {}", e)))?;
+        self.prune_thread(
+            &conn,
+            &thread_id,
+            RetentionPolicy {
+                keep_last,
+                keep_interrupts,
+            },
+        )
+    }
+
     /// Get statistics about stored checkpoints
     fn stats(&self) -> PyResult<HashMap<String, usize>> {
         let conn = Connection::open(&self.db_path)
@@ -288,6 +341,53 @@ impl RustSQLiteCheckpointer {
         Ok(())
     }
 
+    /// Delete the checkpoints of `thread_id` that `policy` does not retain
+    ///
+    /// Checkpoints are ordered by when they were stored.
+    fn prune_thread(
+        &self,
+        conn: &Connection,
+        thread_id: &str,
+        policy: RetentionPolicy,
+    ) -> PyResult<Vec<String>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT checkpoint_id, data FROM checkpoints
+                 WHERE thread_id = ?1 ORDER BY created_at, rowid",
+            )
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Prepare error: {}", e)))?;
+        let rows: Vec<(String, Vec<u8>)> = stmt
+            .query_map(params![thread_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Query error: {}", e)))?
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                pyo3::exceptions::PyIOError::new_err(format!("Collection error: {}", e))
+            })?;
+
+        let mut ids = Vec::with_capacity(rows.len());
+        let mut interrupts = HashSet::new();
+        for (id, data) in rows {
+            let checkpoint_data: CheckpointData =
+                rmp_serde::from_slice(&self.compression.decompress(&data)).map_err(|e| {
+                    pyo3::exceptions::PyValueError::new_err(format!("Deserialization error: {}", e))
+                })?;
+            if checkpoint_data.interrupt {
+                interrupts.insert(id.clone());
+            }
+            ids.push(id);
+        }
+
+        let pruned = policy.prunable(&ids, |id| interrupts.contains(id), |_| None);
+        for id in &pruned {
+            conn.execute(
+                "DELETE FROM checkpoints WHERE thread_id = ?1 AND checkpoint_id = ?2",
+                params![thread_id, id],
+            )
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Delete error: {}", e)))?;
+        }
+        Ok(pruned)
+    }
+
     // Helper methods (same as RustCheckpointer)
     fn extract_channel_values(
         &self,
@@ -0,0 +1,126 @@
+//! Command - Control Flow Objects
+//!
+//! Commands steer a [`PregelLoop`](crate::pregel_loop::PregelLoop) run: a
+//! [`Command`] is passed in by the caller to continue a run paused on
+//! `interrupt()` calls, and a [`NodeCommand`] is returned by a node to write
+//! state and choose its successors in one object.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyString, PyTuple};
+use std::collections::HashMap;
+
+use crate::graph::END;
+use crate::send::Send;
+
+/// Command passed to [`PregelLoop::invoke_command`](crate::pregel_loop::PregelLoop::invoke_command)
+#[derive(Clone, Debug)]
+pub enum Command {
+    /// Answer the single pending interrupt
+    Resume(PyObject),
+    /// Answer pending interrupts by ID; interrupts left out pause again
+    ResumeMap(HashMap<String, PyObject>),
+    /// Write values to channels, as if from a node, then continue
+    Update(HashMap<String, PyObject>),
+    /// Drop the paused step and continue by running this node
+    Goto(String),
+}
+
+/// Successor chosen by the `goto` of a [`NodeCommand`]
+#[derive(Clone, Debug)]
+pub enum GotoTarget {
+    /// Run the node next step with its usual input
+    Node(String),
+    /// Run `send.node` next step with `send.arg` as its input
+    Send(Send),
+}
+
+impl GotoTarget {
+    /// Node the target runs
+    pub fn node(&self) -> &str {
+        match self {
+            GotoTarget::Node(node) => node,
+            GotoTarget::Send(send) => &send.node,
+        }
+    }
+
+    /// Python form: the node name, or a `(node, arg)` tuple for a send
+    pub fn to_object(&self, py: Python) -> PyObject {
+        match self {
+            GotoTarget::Node(node) => node.to_object(py),
+            GotoTarget::Send(send) => (&send.node, &send.arg).to_object(py),
+        }
+    }
+
+    /// Read targets back from the form written by [`to_object`](Self::to_object)
+    pub fn from_objects(targets: &PyAny) -> PyResult<Vec<Self>> {
+        targets
+            .iter()?
+            .map(|target| {
+                let target = target?;
+                if let Ok((node, arg)) = target.extract::<(String, PyObject)>() {
+                    Ok(GotoTarget::Send(Send::new(node, arg)))
+                } else {
+                    Ok(GotoTarget::Node(target.extract()?))
+                }
+            })
+            .collect()
+    }
+}
+
+/// `Command(update=..., goto=...)` returned by a node
+///
+/// Any returned object with `update` and `goto` attributes is read as a
+/// command, so LangGraph's `Command` works as is. `update` is processed like
+/// a plain return value; `goto` is a node name, a `Send` (any object with
+/// `node` and `arg`), or a list of them. Routing to [`END`] schedules nothing.
+#[derive(Debug)]
+pub struct NodeCommand {
+    /// State update, `None` to write nothing
+    pub update: Option<PyObject>,
+    /// Successors to run next step
+    pub goto: Vec<GotoTarget>,
+}
+
+impl NodeCommand {
+    /// Read a node's return value as a command, `None` if it isn't one
+    pub fn from_result(result: &PyAny) -> PyResult<Option<Self>> {
+        if !(result.hasattr("update")? && result.hasattr("goto")?) {
+            return Ok(None);
+        }
+        let update = result.getattr("update")?;
+        let update = (!update.is_none()).then(|| update.into());
+        let goto = result.getattr("goto")?;
+        let targets: Vec<&PyAny> =
+            if goto.is_instance_of::<PyList>() || goto.is_instance_of::<PyTuple>() {
+                goto.iter()?.collect::<PyResult<_>>()?
+            } else if goto.is_none() {
+                Vec::new()
+            } else {
+                vec![goto]
+            };
+
+        let mut parsed = Vec::with_capacity(targets.len());
+        for target in targets {
+            if target.is_instance_of::<PyString>() {
+                let node: String = target.extract()?;
+                if node != END {
+                    parsed.push(GotoTarget::Node(node));
+                }
+            } else if target.hasattr("node")? && target.hasattr("arg")? {
+                parsed.push(GotoTarget::Send(Send::new(
+                    target.getattr("node")?.extract()?,
+                    target.getattr("arg")?.into(),
+                )));
+            } else {
+                return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+                    "goto targets must be node names or Sends, got {}",
+                    target.get_type().name()?
+                )));
+            }
+        }
+        Ok(Some(Self {
+            update,
+            goto: parsed,
+        }))
+    }
+}
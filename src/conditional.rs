@@ -6,6 +6,19 @@ use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use std::collections::HashMap;
 
+use crate::command::GotoTarget;
+pub use crate::core::edge::Destination;
+use crate::send::Send;
+
+impl From<Destination> for GotoTarget {
+    fn from(destination: Destination) -> Self {
+        match destination {
+            Destination::Node(node) => GotoTarget::Node(node),
+            Destination::Send { node, arg } => GotoTarget::Send(Send::new(node, arg)),
+        }
+    }
+}
+
 /// A conditional edge that determines next node based on state
 #[derive(Clone)]
 pub struct ConditionalEdge {
@@ -36,8 +49,13 @@ impl ConditionalEdge {
         self
     }
 
-    /// Evaluate the condition and return the target node
-    pub fn evaluate(&self, py: Python, state: &PyDict) -> PyResult<String> {
+    /// Evaluate the condition and return where to route
+    ///
+    /// The condition may return a routing key, a `Send(node, arg)` or a list
+    /// mixing both. Keys are looked up in the path map, while a `Send` names
+    /// its node directly and keeps its own input, so a list of them fans out
+    /// into one task each.
+    pub fn evaluate(&self, py: Python, state: &PyDict) -> PyResult<Vec<Destination>> {
         // Call the condition function with state
         let result = if let Ok(call_method) = self.condition.as_ref(py).getattr("__call__") {
             call_method.call1((state,))?.into()
@@ -45,14 +63,30 @@ impl ConditionalEdge {
             self.condition.call1(py, (state,))?
         };
 
+        let result = result.as_ref(py);
+        if result.is_instance_of::<pyo3::types::PyString>() || is_send(result)? {
+            return Ok(vec![self.destination(result)?]);
+        }
+        result.iter()?.map(|item| self.destination(item?)).collect()
+    }
+
+    /// Destination of a single routing key or `Send` returned by the condition
+    fn destination(&self, item: &PyAny) -> PyResult<Destination> {
+        if is_send(item)? {
+            return Ok(Destination::Send {
+                node: item.getattr("node")?.extract()?,
+                arg: item.getattr("arg")?.into(),
+            });
+        }
+
         // Extract the routing key
-        let routing_key: String = result.extract(py)?;
+        let routing_key: String = item.extract()?;
 
         // Look up in path map
         if let Some(target) = self.path_map.get(&routing_key) {
-            Ok(target.clone())
+            Ok(Destination::Node(target.clone()))
         } else if let Some(ref default) = self.default {
-            Ok(default.clone())
+            Ok(Destination::Node(default.clone()))
         } else {
             Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
                 "Condition returned '{}' but no matching path in map and no default provided",
@@ -93,19 +127,18 @@ impl ConditionalRouter {
         self.edges.push(edge);
     }
 
-    /// Find the next node(s) from a given source node
+    /// Find the destinations of every conditional edge from a source node
     pub fn route_from(
         &self,
         py: Python,
         source_node: &str,
         state: &PyDict,
-    ) -> PyResult<Vec<String>> {
+    ) -> PyResult<Vec<Destination>> {
         let mut targets = Vec::new();
 
         for edge in &self.edges {
             if edge.from_node(source_node) {
-                let target = edge.evaluate(py, state)?;
-                targets.push(target);
+                targets.extend(edge.evaluate(py, state)?);
             }
         }
 
@@ -150,6 +183,14 @@ impl Branch {
     }
 }
 
+/// Whether `obj` is a `Send`, recognized by its `node` and `arg` attributes
+/// like the targets of a `Command`'s `goto`
+pub(crate) fn is_send(obj: &PyAny) -> PyResult<bool> {
+    Ok(!obj.is_instance_of::<pyo3::types::PyString>()
+        && obj.hasattr("node")?
+        && obj.hasattr("arg")?)
+}
+
 /// Evaluate all possible branches from a conditional edge
 pub fn evaluate_branches(edge: &ConditionalEdge) -> Vec<Branch> {
     let mut branches = Vec::new();
@@ -231,4 +272,61 @@ mod tests {
             assert_eq!(branches.len(), 3); // 2 paths + 1 default
         });
     }
+
+    #[test]
+    fn test_condition_returns_nodes_and_sends() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+
+def route(state):
+    return ["done"] + [Send("worker", item) for item in state["items"]]
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let mut path_map = HashMap::new();
+            path_map.insert("done".to_string(), "finish".to_string());
+            let edge = ConditionalEdge::new(
+                "source".to_string(),
+                locals.get_item("route").unwrap().unwrap().into(),
+                path_map,
+            );
+
+            let state = PyDict::new(py);
+            state.set_item("items", vec![1, 2]).unwrap();
+            let destinations = edge.evaluate(py, state).unwrap();
+            assert_eq!(destinations.len(), 3);
+            assert!(matches!(&destinations[0], Destination::Node(node) if node == "finish"));
+            for (destination, item) in destinations[1..].iter().zip([1, 2]) {
+                match destination {
+                    Destination::Send { node, arg } => {
+                        assert_eq!(node, "worker");
+                        assert_eq!(arg.extract::<i32>(py).unwrap(), item);
+                    }
+                    other => panic!("expected a Send, got {:?}", other),
+                }
+            }
+
+            // A single key still routes to one node
+            let edge = ConditionalEdge::new(
+                "source".to_string(),
+                py.eval("lambda state: 'missing'", None, None)
+                    .unwrap()
+                    .into(),
+                HashMap::new(),
+            )
+            .with_default("fallback".to_string());
+            let destinations = edge.evaluate(py, state).unwrap();
+            assert!(matches!(&destinations[..], [Destination::Node(node)] if node == "fallback"));
+        });
+    }
 }
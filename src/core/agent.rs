@@ -0,0 +1,365 @@
+//! Tool-calling agent loop
+//!
+//! [`create_react_agent`] assembles the standard ReAct graph on
+//! [`PregelCore`]: an `agent` node calling the model on the conversation, a
+//! conditional edge sending any tool calls of its reply to the `tools` node,
+//! and an edge from `tools` back to `agent`. The run ends once the model
+//! replies without tool calls.
+//!
+//! The conversation is a list of messages in the `messages` channel, which
+//! is both the input and the output of the graph. Messages are LangChain
+//! message objects or plain dicts: a reply's tool calls are read from its
+//! `tool_calls` attribute or key, each a `{"name", "args", "id"}` dict, and
+//! tool results are appended as `{"role": "tool", "name", "tool_call_id",
+//! "content"}` dicts.
+
+use super::checkpointer::Checkpointer;
+use super::edge::Edge;
+use super::executor::{OutputChannels, PregelCore};
+use super::node::{GuardAction, Node};
+use super::state::{ChannelKind, StateSchema};
+use crate::errors::GraphError;
+use crate::graph::END;
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyList, PyTuple};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Node calling the model
+pub const AGENT_NODE: &str = "agent";
+/// Node running the tool calls of the model's last reply
+pub const TOOLS_NODE: &str = "tools";
+/// Guard holding tool calls for approval, see
+/// [`ReactAgent::interrupt_before_tools`]
+pub const APPROVAL_NODE: &str = "approve_tools";
+/// Channel holding the conversation
+pub const MESSAGES_CHANNEL: &str = "messages";
+/// Channel set to `True` to let the pending tool calls run
+pub const APPROVAL_CHANNEL: &str = "tools_approved";
+
+/// Builder of a tool-calling agent graph
+///
+/// ```ignore
+/// let agent = ReactAgent::new(model, tools)
+///     .interrupt_before_tools()
+///     .compile(None)?;
+/// ```
+pub struct ReactAgent {
+    model: PyObject,
+    tools: HashMap<String, PyObject>,
+    interrupt_before_tools: bool,
+}
+
+impl ReactAgent {
+    /// Agent calling `model` with the list of messages, which returns the
+    /// reply to append, and running the tools of `tools` by name
+    ///
+    /// A tool with an `invoke` method, such as a LangChain tool, is invoked
+    /// with the call's arguments; any other callable is called with them as
+    /// keyword arguments.
+    pub fn new(model: PyObject, tools: HashMap<String, PyObject>) -> Self {
+        Self {
+            model,
+            tools,
+            interrupt_before_tools: false,
+        }
+    }
+
+    /// Interrupt the run whenever the model asks for tools, before they run
+    ///
+    /// The run stops with [`GraphError::Interrupted`], leaving the reply with
+    /// the tool calls as the last message. Let them run with
+    /// [`approve_tools`], or edit the conversation first and then approve.
+    pub fn interrupt_before_tools(mut self) -> Self {
+        self.interrupt_before_tools = true;
+        self
+    }
+
+    /// Assemble the graph and compile it with `checkpointer`, see
+    /// [`PregelCore::compile`]
+    pub fn compile(
+        self,
+        checkpointer: Option<Arc<dyn Checkpointer>>,
+    ) -> Result<PregelCore, GraphError> {
+        let approval = self.interrupt_before_tools;
+        let mut schema = StateSchema::new().field(MESSAGES_CHANNEL, ChannelKind::LastValue);
+        if approval {
+            schema = schema.field(APPROVAL_CHANNEL, ChannelKind::LastValue);
+        }
+        let mut graph = PregelCore::with_schema(schema);
+
+        let model = self.model;
+        graph.add_node(Node::with_inputs(
+            AGENT_NODE.to_string(),
+            &[MESSAGES_CHANNEL],
+            move |py, inputs| {
+                let messages = inputs[0].as_ref(py);
+                let reply = model.call1(py, (messages,))?;
+                let messages = appended(messages, [reply.as_ref(py)])?;
+                Ok(HashMap::from([(MESSAGES_CHANNEL.to_string(), messages)]))
+            },
+        ));
+
+        let tools = self.tools;
+        graph.add_node(Node::with_inputs(
+            TOOLS_NODE.to_string(),
+            &[MESSAGES_CHANNEL],
+            move |py, inputs| {
+                let messages = inputs[0].as_ref(py);
+                let last = messages.get_item(messages.len()? - 1)?;
+                let results = tool_calls(last)?
+                    .into_iter()
+                    .map(|call| run_tool(py, &tools, call))
+                    .collect::<PyResult<Vec<_>>>()?;
+                let mut writes = HashMap::from([(
+                    MESSAGES_CHANNEL.to_string(),
+                    appended(messages, results.iter().map(|r| r.as_ref(py)))?,
+                )]);
+                if approval {
+                    // The next tool calls need approval of their own
+                    writes.insert(APPROVAL_CHANNEL.to_string(), false.into_py(py));
+                }
+                Ok(writes)
+            },
+        ));
+
+        let tools_target = if approval { APPROVAL_NODE } else { TOOLS_NODE };
+        let router = Python::with_gil(|py| -> PyResult<PyObject> {
+            let router = PyCFunction::new_closure(py, None, None, move |args, _| {
+                route_after_model(args.get_item(0)?, tools_target)
+            })?;
+            Ok(router.into())
+        })
+        .map_err(|err| GraphError::NodeExecution {
+            node: AGENT_NODE.to_string(),
+            source: Box::new(err),
+        })?;
+        graph.add_conditional_edges(AGENT_NODE, router, HashMap::new());
+        graph.add_edge(Edge::direct(TOOLS_NODE.to_string(), AGENT_NODE.to_string()));
+
+        if approval {
+            let approved = Python::with_gil(|py| -> PyResult<PyObject> {
+                let approved = PyCFunction::new_closure(py, None, None, |args, _| {
+                    let state: &PyDict = args.get_item(0)?.downcast()?;
+                    match state.get_item(APPROVAL_CHANNEL)? {
+                        Some(approved) => approved.is_true(),
+                        None => Ok(false),
+                    }
+                })?;
+                Ok(approved.into())
+            })
+            .map_err(|err| GraphError::NodeExecution {
+                node: APPROVAL_NODE.to_string(),
+                source: Box::new(err),
+            })?;
+            graph.add_node(
+                Node::guard(
+                    APPROVAL_NODE.to_string(),
+                    approved,
+                    "tool calls are waiting for approval",
+                )
+                .with_guard_action(GuardAction::Interrupt),
+            );
+            graph.add_edge(Edge::direct(
+                APPROVAL_NODE.to_string(),
+                TOOLS_NODE.to_string(),
+            ));
+        }
+
+        graph.set_entry_point(AGENT_NODE.to_string());
+        graph.set_input_channels(vec![MESSAGES_CHANNEL.to_string()]);
+        graph.set_output_channels(OutputChannels::Single(MESSAGES_CHANNEL.to_string()));
+        graph.compile(checkpointer)
+    }
+}
+
+/// Compile a tool-calling agent running `model` and `tools`, see
+/// [`ReactAgent`]
+pub fn create_react_agent(
+    model: PyObject,
+    tools: HashMap<String, PyObject>,
+) -> Result<PregelCore, GraphError> {
+    ReactAgent::new(model, tools).compile(None)
+}
+
+/// Let the tool calls an agent built with
+/// [`ReactAgent::interrupt_before_tools`] stopped on run, continuing the run
+pub fn approve_tools(agent: &mut PregelCore, py: Python<'_>) -> PyResult<PyObject> {
+    agent
+        .state_mut()
+        .update_channel(py, APPROVAL_CHANNEL, true.into_py(py))?;
+    agent.resume(py)
+}
+
+/// Next node after the model replied: `tools_target` if the reply asks for
+/// tools, otherwise the end of the run
+fn route_after_model(state: &PyAny, tools_target: &'static str) -> PyResult<&'static str> {
+    let messages = state.get_item(MESSAGES_CHANNEL)?;
+    let last = messages.get_item(messages.len()? - 1)?;
+    Ok(if tool_calls(last)?.is_empty() {
+        END
+    } else {
+        tools_target
+    })
+}
+
+/// Tool calls of a message, from its `tool_calls` attribute or key
+fn tool_calls(message: &PyAny) -> PyResult<Vec<&PyAny>> {
+    let calls = match message.downcast::<PyDict>() {
+        Ok(message) => message.get_item("tool_calls")?,
+        Err(_) => message.getattr("tool_calls").ok(),
+    };
+    match calls {
+        Some(calls) if !calls.is_none() => calls.iter()?.collect(),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Run one tool call, returning the tool message with its result
+///
+/// A call naming an unknown tool gets an error message in place of a
+/// result, so the model can correct itself.
+fn run_tool(py: Python<'_>, tools: &HashMap<String, PyObject>, call: &PyAny) -> PyResult<PyObject> {
+    let field = |key: &str| -> PyResult<&PyAny> {
+        match call.downcast::<PyDict>() {
+            Ok(call) => Ok(call
+                .get_item(key)?
+                .unwrap_or_else(|| py.None().into_ref(py))),
+            Err(_) => call.getattr(key),
+        }
+    };
+    let name: String = field("name")?.extract()?;
+    let args = field("args")?;
+
+    let content = match tools.get(&name) {
+        Some(tool) => {
+            let tool = tool.as_ref(py);
+            if tool.hasattr("invoke")? {
+                tool.call_method1("invoke", (args,))?
+            } else {
+                let kwargs = args.downcast::<PyDict>().ok();
+                tool.call(PyTuple::empty(py), kwargs)?
+            }
+        }
+        None => format!("Error: unknown tool '{}'", name)
+            .into_py(py)
+            .into_ref(py),
+    };
+
+    let message = PyDict::new(py);
+    message.set_item("role", "tool")?;
+    message.set_item("name", name)?;
+    message.set_item("tool_call_id", field("id")?)?;
+    message.set_item("content", content)?;
+    Ok(message.into())
+}
+
+/// A new list of `messages` followed by `new`
+fn appended<'py>(
+    messages: &'py PyAny,
+    new: impl IntoIterator<Item = &'py PyAny>,
+) -> PyResult<PyObject> {
+    let list = PyList::empty(messages.py());
+    for message in messages.iter()? {
+        list.append(message?)?;
+    }
+    for message in new {
+        list.append(message)?;
+    }
+    Ok(list.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_react_agent_loop() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+def model(messages):
+    results = [m["content"] for m in messages if m.get("role") == "tool"]
+    if results:
+        return {"role": "ai", "content": f"the sum is {results[0]}"}
+    return {
+        "role": "ai",
+        "content": "",
+        "tool_calls": [
+            {"name": "add", "args": {"a": 1, "b": 2}, "id": "call_1"},
+            {"name": "missing", "args": {}, "id": "call_2"},
+        ],
+    }
+def add(a, b):
+    return a + b
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let tools = HashMap::from([("add".to_string(), get("add"))]);
+            let input = || {
+                let human = PyDict::new(py);
+                human.set_item("role", "human").unwrap();
+                human.set_item("content", "what is 1 + 2?").unwrap();
+                let input = PyDict::new(py);
+                input.set_item(MESSAGES_CHANNEL, vec![human]).unwrap();
+                input.to_object(py)
+            };
+            let contents = |messages: &PyObject| -> Vec<String> {
+                messages
+                    .as_ref(py)
+                    .iter()
+                    .unwrap()
+                    .map(|m| m.unwrap().get_item("content").unwrap().to_string())
+                    .collect()
+            };
+
+            let mut agent = create_react_agent(get("model"), tools.clone()).unwrap();
+            let messages = agent.invoke(py, input(), None).unwrap();
+            assert_eq!(
+                contents(&messages),
+                [
+                    "what is 1 + 2?",
+                    "",
+                    "3",
+                    "Error: unknown tool 'missing'",
+                    "the sum is 3",
+                ]
+            );
+            let tool_call_ids: Vec<String> = messages
+                .as_ref(py)
+                .iter()
+                .unwrap()
+                .skip(2)
+                .take(2)
+                .map(|m| {
+                    m.unwrap()
+                        .get_item("tool_call_id")
+                        .unwrap()
+                        .extract()
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(tool_call_ids, ["call_1", "call_2"]);
+
+            // With approval, the run stops before the tools run
+            let mut agent = ReactAgent::new(get("model"), tools)
+                .interrupt_before_tools()
+                .compile(None)
+                .unwrap();
+            let err = agent.invoke(py, input(), None).unwrap_err();
+            assert!(err.is_instance_of::<crate::errors::GraphInterrupted>(py));
+            let pending = agent.state().get_value(py, MESSAGES_CHANNEL).unwrap();
+            assert_eq!(contents(&pending).len(), 2);
+
+            let messages = approve_tools(&mut agent, py).unwrap();
+            assert_eq!(contents(&messages).len(), 5);
+            assert!(!agent.is_interrupted());
+        });
+    }
+}
@@ -0,0 +1,238 @@
+//! Node result cache
+//!
+//! Caches the channel updates a node produced for a given input, so running
+//! the node again on the same input can be skipped. The cache is bounded by
+//! [`CachePolicy::max_entries`] with least-recently-used eviction; entries
+//! older than [`CachePolicy::ttl`] are dropped lazily when looked up.
+
+use super::host::{self, prelude::*};
+use super::metrics::Stopwatch;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Bounds for the node result cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Maximum number of cached results; the least recently used is evicted first
+    pub max_entries: usize,
+    /// How long a result stays valid (`None` = until evicted)
+    pub ttl: Option<Duration>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            ttl: None,
+        }
+    }
+}
+
+/// Cache occupancy and eviction counters
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    /// Entries dropped to stay within `max_entries`
+    pub evictions: u64,
+    /// Entries dropped because their TTL expired
+    pub expirations: u64,
+}
+
+/// Cached result key: node name and a hash of the node's input
+pub type CacheKey = (String, u64);
+
+struct Entry {
+    updates: HashMap<String, Object>,
+    inserted: Stopwatch,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    /// Last-use tick -> key, oldest first
+    recency: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    evictions: u64,
+    expirations: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        Some(entry)
+    }
+}
+
+/// Bounded LRU cache of node results, safe to share between tasks
+pub struct NodeCache {
+    policy: CachePolicy,
+    state: Mutex<CacheState>,
+}
+
+impl NodeCache {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Build the cache key for running `node` on `input`
+    ///
+    /// Fails if the input cannot be pickled (serialized to JSON without the
+    /// `python` feature).
+    pub fn key(py: Host<'_>, node: &str, input: &Object) -> HostResult<CacheKey> {
+        let mut hasher = DefaultHasher::new();
+        host::fingerprint(py, input)?.hash(&mut hasher);
+        Ok((node.to_string(), hasher.finish()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Look up a cached result, marking it as most recently used
+    pub fn get(&self, py: Host<'_>, key: &CacheKey) -> Option<HashMap<String, Object>> {
+        let mut state = self.lock();
+        let age = state.entries.get(key)?.inserted.elapsed();
+        if self.policy.ttl.is_some_and(|ttl| age > ttl) {
+            state.remove(key);
+            state.expirations += 1;
+            return None;
+        }
+
+        let tick = state.tick();
+        let entry = state.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let updates = entry
+            .updates
+            .iter()
+            .map(|(channel, value)| (channel.clone(), value.clone_ref(py)))
+            .collect();
+        state.recency.remove(&previous);
+        state.recency.insert(tick, key.clone());
+        Some(updates)
+    }
+
+    /// Store a result, evicting least recently used entries beyond `max_entries`
+    pub fn put(&self, key: CacheKey, updates: HashMap<String, Object>) {
+        if self.policy.max_entries == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        state.remove(&key);
+        while state.entries.len() >= self.policy.max_entries {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            state.evictions += 1;
+        }
+
+        let tick = state.tick();
+        state.recency.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            Entry {
+                updates,
+                inserted: Stopwatch::start(),
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Current size and eviction counters
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            entries: state.entries.len(),
+            max_entries: self.policy.max_entries,
+            evictions: state.evictions,
+            expirations: state.expirations,
+        }
+    }
+
+    /// Drop all cached results, keeping the counters
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.recency.clear();
+    }
+}
+
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+
+    fn key(n: u64) -> CacheKey {
+        ("node".to_string(), n)
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let cache = NodeCache::new(CachePolicy {
+                max_entries: 2,
+                ttl: None,
+            });
+            cache.put(key(1), HashMap::new());
+            cache.put(key(2), HashMap::new());
+
+            // Touch 1 so that 2 becomes the least recently used
+            assert!(cache.get(py, &key(1)).is_some());
+            cache.put(key(3), HashMap::new());
+
+            assert!(cache.get(py, &key(2)).is_none());
+            assert!(cache.get(py, &key(1)).is_some());
+            assert!(cache.get(py, &key(3)).is_some());
+            assert_eq!(
+                cache.stats(),
+                CacheStats {
+                    entries: 2,
+                    max_entries: 2,
+                    evictions: 1,
+                    expirations: 0,
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_ttl_expires_lazily() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let cache = NodeCache::new(CachePolicy {
+                max_entries: 10,
+                ttl: Some(Duration::from_millis(20)),
+            });
+            let updates = HashMap::from([("out".to_string(), 1.to_object(py))]);
+            cache.put(key(1), updates);
+            assert!(cache.get(py, &key(1)).is_some());
+
+            std::thread::sleep(Duration::from_millis(30));
+            assert_eq!(cache.stats().entries, 1);
+            assert!(cache.get(py, &key(1)).is_none());
+
+            let stats = cache.stats();
+            assert_eq!(stats.entries, 0);
+            assert_eq!(stats.expirations, 1);
+        });
+    }
+}
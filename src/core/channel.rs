@@ -3,21 +3,93 @@
 //! Channels are the core state storage mechanism in LangGraph.
 //! They store values that flow between nodes during graph execution.
 
+use super::host::{self, prelude::*, Kind};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::{PyDict, PySet};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
 use std::fmt;
 
+/// Name of the channel holding run-scoped context passed to `PregelCore::invoke`
+pub const CONTEXT_CHANNEL: &str = "__context__";
+
+/// JSON Schema type of the values a channel holds
+///
+/// Values of opaque host types are described as `Object`, the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Array,
+    #[default]
+    Object,
+}
+
+impl ValueType {
+    /// The JSON Schema `type` keyword for the values
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Integer => "integer",
+            ValueType::Number => "number",
+            ValueType::Boolean => "boolean",
+            ValueType::Array => "array",
+            ValueType::Object => "object",
+        }
+    }
+
+    /// Whether `value` is of this type; any value is an `Object`
+    pub fn matches(&self, py: Host, value: &Object) -> bool {
+        let kind = host::kind(py, value);
+        match self {
+            ValueType::String => kind == Kind::Str,
+            ValueType::Integer => kind == Kind::Int,
+            ValueType::Number => matches!(kind, Kind::Int | Kind::Float),
+            ValueType::Boolean => kind == Kind::Bool,
+            ValueType::Array => kind == Kind::List,
+            ValueType::Object => true,
+        }
+    }
+}
+
+/// JSON Schema of a channel: `value` for its values, `reducer` for whether
+/// it combines writes, and a `default` if it has one that converts to JSON
+fn channel_schema(mut value: Value, reducer: bool, default: Option<Value>) -> Value {
+    value["x-reducer"] = Value::Bool(reducer);
+    if let Some(default) = default {
+        value["default"] = default;
+    }
+    value
+}
+
+/// Defaults of a list-valued channel, if any and if they all convert to JSON
+fn list_default(py: Host, default: &[Object]) -> Option<Value> {
+    if default.is_empty() {
+        return None;
+    }
+    default
+        .iter()
+        .map(|value| host::to_json(py, value))
+        .collect::<Option<_>>()
+        .map(Value::Array)
+}
+
 /// Represents an update to be applied to a channel
 #[derive(Clone)]
 pub struct ChannelUpdate {
-    pub values: Vec<PyObject>,
+    pub values: Vec<Object>,
 }
 
 impl ChannelUpdate {
-    pub fn new(values: Vec<PyObject>) -> Self {
+    pub fn new(values: Vec<Object>) -> Self {
         Self { values }
     }
 
-    pub fn single(value: PyObject) -> Self {
+    pub fn single(value: Object) -> Self {
         Self {
             values: vec![value],
         }
@@ -34,20 +106,69 @@ impl ChannelUpdate {
 #[allow(clippy::wrong_self_convention)]
 pub trait Channel: Send + Sync {
     /// Update the channel with new values
-    fn update(&mut self, py: Python, update: ChannelUpdate) -> PyResult<()>;
+    fn update(&mut self, py: Host, update: ChannelUpdate) -> HostResult<()>;
+
+    /// Fail as [`update`](Self::update) would with `update`, without
+    /// changing the channel
+    ///
+    /// The executor checks every write of a superstep this way before
+    /// applying any, so a node with a bad write leaves all channels
+    /// untouched. Channels whose update cannot fail keep the default.
+    fn check_update(&self, _py: Host, _update: &ChannelUpdate) -> HostResult<()> {
+        Ok(())
+    }
 
     /// Get the current value from the channel
     /// Returns None if the channel is empty
-    fn get(&self, py: Python) -> Option<PyObject>;
+    fn get(&self, py: Host) -> Option<Object>;
 
     /// Check if the channel has a value
     fn is_available(&self) -> bool;
 
     /// Create a checkpoint of the current state
-    fn checkpoint(&self, py: Python) -> PyResult<PyObject>;
+    fn checkpoint(&self, py: Host) -> HostResult<Object>;
 
     /// Restore from a checkpoint
-    fn from_checkpoint(&mut self, py: Python, data: PyObject) -> PyResult<()>;
+    fn from_checkpoint(&mut self, py: Host, data: Object) -> HostResult<()>;
+
+    /// Whether the channel's value is part of checkpointed state
+    fn is_checkpointed(&self) -> bool {
+        true
+    }
+
+    /// Seed the channel with its default value unless it was already seeded,
+    /// written, or restored from a checkpoint
+    ///
+    /// Called when a run starts, so defaults are applied once per channel
+    /// and never on resume.
+    fn apply_default(&mut self, _py: Host) {}
+
+    /// Whether successive writes add to the value instead of replacing it,
+    /// so the channel can take input in chunks
+    fn accumulates(&self) -> bool {
+        false
+    }
+
+    /// Whether the channel combines several writes made in one superstep;
+    /// other channels take at most one write per step
+    fn merges_writes(&self) -> bool {
+        false
+    }
+
+    /// Type of each value written to the channel in an update
+    fn update_type(&self) -> ValueType {
+        ValueType::Object
+    }
+
+    /// JSON Schema of the channel's value, with an `x-reducer` flag set for
+    /// channels that combine writes and the channel's `default`, if any
+    fn json_schema(&self, _py: Host) -> Value {
+        channel_schema(json!({ "type": "object" }), self.merges_writes(), None)
+    }
+
+    /// An empty channel of the same kind and settings, as this channel was
+    /// before its first run
+    fn empty_copy(&self, py: Host) -> Box<dyn Channel>;
 
     /// Get a debug representation
     fn debug_repr(&self) -> String;
@@ -58,16 +179,45 @@ pub trait Channel: Send + Sync {
 /// This is the most common channel type. When updated, it replaces
 /// the previous value with the new one.
 pub struct LastValueChannel {
-    value: Option<PyObject>,
+    value: Option<Object>,
+    /// Value the channel starts with at the first run
+    default: Option<Object>,
+    /// Whether the channel was seeded, written, or restored
+    seeded: bool,
+    /// Type of the value, for the state's JSON Schema
+    value_type: ValueType,
 }
 
 impl LastValueChannel {
     pub fn new() -> Self {
-        Self { value: None }
+        Self {
+            value: None,
+            default: None,
+            seeded: false,
+            value_type: ValueType::default(),
+        }
     }
 
-    pub fn with_value(value: PyObject) -> Self {
-        Self { value: Some(value) }
+    pub fn with_value(value: Object) -> Self {
+        Self {
+            value: Some(value),
+            seeded: true,
+            ..Self::new()
+        }
+    }
+
+    /// Declare the type of the channel's value
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Create a channel that starts out holding `default` once a run begins
+    pub fn with_default(default: Object) -> Self {
+        Self {
+            default: Some(default),
+            ..Self::new()
+        }
     }
 }
 
@@ -78,17 +228,26 @@ impl Default for LastValueChannel {
 }
 
 impl Channel for LastValueChannel {
-    fn update(&mut self, _py: Python, update: ChannelUpdate) -> PyResult<()> {
-        if update.values.is_empty() {
-            return Ok(());
+    fn update(&mut self, py: Host, update: ChannelUpdate) -> HostResult<()> {
+        self.check_update(py, &update)?;
+        if let Some(value) = update.values.into_iter().next() {
+            self.value = Some(value);
+            self.seeded = true;
         }
+        Ok(())
+    }
 
-        // For LastValue, we only keep the last value in the update
-        self.value = Some(update.values.into_iter().last().unwrap());
+    fn check_update(&self, _py: Host, update: &ChannelUpdate) -> HostResult<()> {
+        if update.values.len() > 1 {
+            return Err(host::value_error(format!(
+                "LastValue channel takes one value per step, got {}",
+                update.values.len()
+            )));
+        }
         Ok(())
     }
 
-    fn get(&self, py: Python) -> Option<PyObject> {
+    fn get(&self, py: Host) -> Option<Object> {
         self.value.as_ref().map(|v| v.clone_ref(py))
     }
 
@@ -96,22 +255,50 @@ impl Channel for LastValueChannel {
         self.value.is_some()
     }
 
-    fn checkpoint(&self, py: Python) -> PyResult<PyObject> {
+    fn checkpoint(&self, py: Host) -> HostResult<Object> {
         match &self.value {
             Some(val) => Ok(val.clone_ref(py)),
-            None => Ok(py.None()),
+            None => Ok(host::none(py)),
         }
     }
 
-    fn from_checkpoint(&mut self, py: Python, data: PyObject) -> PyResult<()> {
+    fn from_checkpoint(&mut self, py: Host, data: Object) -> HostResult<()> {
         if data.is_none(py) {
             self.value = None;
         } else {
             self.value = Some(data);
         }
+        self.seeded = true;
         Ok(())
     }
 
+    fn apply_default(&mut self, py: Host) {
+        if !std::mem::replace(&mut self.seeded, true) {
+            self.value = self.default.as_ref().map(|v| v.clone_ref(py));
+        }
+    }
+
+    fn update_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    fn json_schema(&self, py: Host) -> Value {
+        let default = self
+            .default
+            .as_ref()
+            .and_then(|value| host::to_json(py, value));
+        channel_schema(json!({ "type": self.value_type.as_str() }), false, default)
+    }
+
+    fn empty_copy(&self, py: Host) -> Box<dyn Channel> {
+        Box::new(Self {
+            value: None,
+            default: self.default.as_ref().map(|v| v.clone_ref(py)),
+            seeded: false,
+            value_type: self.value_type,
+        })
+    }
+
     fn debug_repr(&self) -> String {
         format!("LastValueChannel(has_value={})", self.value.is_some())
     }
@@ -128,8 +315,14 @@ impl fmt::Debug for LastValueChannel {
 /// This channel stores a list of values. Each update appends to the list
 /// (or replaces it, depending on configuration).
 pub struct TopicChannel {
-    values: Vec<PyObject>,
+    values: Vec<Object>,
     accumulate: bool,
+    /// Values the channel starts with at the first run
+    default: Vec<Object>,
+    /// Whether the channel was seeded, written, or restored
+    seeded: bool,
+    /// Type of each value, for the state's JSON Schema
+    value_type: ValueType,
 }
 
 impl TopicChannel {
@@ -137,16 +330,39 @@ impl TopicChannel {
         Self {
             values: Vec::new(),
             accumulate,
+            default: Vec::new(),
+            seeded: false,
+            value_type: ValueType::default(),
+        }
+    }
+
+    pub fn with_values(values: Vec<Object>, accumulate: bool) -> Self {
+        Self {
+            values,
+            seeded: true,
+            ..Self::new(accumulate)
         }
     }
 
-    pub fn with_values(values: Vec<PyObject>, accumulate: bool) -> Self {
-        Self { values, accumulate }
+    /// Declare the type of each value in the channel
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Create a channel that starts out holding `default` once a run begins
+    ///
+    /// With `accumulate`, later writes are appended to the defaults.
+    pub fn with_default(default: Vec<Object>, accumulate: bool) -> Self {
+        Self {
+            default,
+            ..Self::new(accumulate)
+        }
     }
 }
 
 impl Channel for TopicChannel {
-    fn update(&mut self, _py: Python, update: ChannelUpdate) -> PyResult<()> {
+    fn update(&mut self, _py: Host, update: ChannelUpdate) -> HostResult<()> {
         if update.values.is_empty() {
             return Ok(());
         }
@@ -158,16 +374,17 @@ impl Channel for TopicChannel {
             // Replace all values
             self.values = update.values;
         }
+        self.seeded = true;
 
         Ok(())
     }
 
-    fn get(&self, py: Python) -> Option<PyObject> {
+    fn get(&self, py: Host) -> Option<Object> {
         if self.values.is_empty() {
             None
         } else {
             // Return the list of all values
-            Some(pyo3::types::PyList::new(py, &self.values).to_object(py))
+            Some(host::list(py, self.values.iter().map(|v| v.clone_ref(py))))
         }
     }
 
@@ -175,21 +392,56 @@ impl Channel for TopicChannel {
         !self.values.is_empty()
     }
 
-    fn checkpoint(&self, py: Python) -> PyResult<PyObject> {
-        Ok(pyo3::types::PyList::new(py, &self.values).to_object(py))
+    fn checkpoint(&self, py: Host) -> HostResult<Object> {
+        Ok(host::list(py, self.values.iter().map(|v| v.clone_ref(py))))
     }
 
-    fn from_checkpoint(&mut self, py: Python, data: PyObject) -> PyResult<()> {
+    fn from_checkpoint(&mut self, py: Host, data: Object) -> HostResult<()> {
         if data.is_none(py) {
             self.values.clear();
         } else {
-            // Extract list from PyObject
-            let list: &pyo3::types::PyList = data.extract(py)?;
-            self.values = list.iter().map(|item| item.to_object(py)).collect();
+            self.values = host::list_items(py, &data)?;
         }
+        self.seeded = true;
         Ok(())
     }
 
+    fn apply_default(&mut self, py: Host) {
+        if !std::mem::replace(&mut self.seeded, true) {
+            self.values = self.default.iter().map(|v| v.clone_ref(py)).collect();
+        }
+    }
+
+    fn accumulates(&self) -> bool {
+        self.accumulate
+    }
+
+    fn merges_writes(&self) -> bool {
+        true
+    }
+
+    fn update_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    fn json_schema(&self, py: Host) -> Value {
+        let value = json!({
+            "type": "array",
+            "items": { "type": self.value_type.as_str() },
+        });
+        channel_schema(value, true, list_default(py, &self.default))
+    }
+
+    fn empty_copy(&self, py: Host) -> Box<dyn Channel> {
+        Box::new(Self {
+            values: Vec::new(),
+            accumulate: self.accumulate,
+            default: self.default.iter().map(|v| v.clone_ref(py)).collect(),
+            seeded: false,
+            value_type: self.value_type,
+        })
+    }
+
     fn debug_repr(&self) -> String {
         format!(
             "TopicChannel(count={}, accumulate={})",
@@ -205,7 +457,653 @@ impl fmt::Debug for TopicChannel {
     }
 }
 
-#[cfg(test)]
+/// Sliding window channel - keeps the most recent `capacity` values
+///
+/// Every update appends its values and drops the oldest ones beyond the
+/// capacity, so memory stays bounded for long conversations. `get` returns
+/// the window as a list, oldest first. A capacity of 0 keeps nothing.
+pub struct SlidingWindowChannel {
+    values: VecDeque<Object>,
+    capacity: usize,
+    /// Type of each value, for the state's JSON Schema
+    value_type: ValueType,
+}
+
+impl SlidingWindowChannel {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+            value_type: ValueType::default(),
+        }
+    }
+
+    /// Declare the type of each value in the window
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Maximum number of values kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn push(&mut self, values: impl IntoIterator<Item = Object>) {
+        self.values.extend(values);
+        let excess = self.values.len().saturating_sub(self.capacity);
+        self.values.drain(..excess);
+    }
+}
+
+impl Channel for SlidingWindowChannel {
+    fn update(&mut self, _py: Host, update: ChannelUpdate) -> HostResult<()> {
+        self.push(update.values);
+        Ok(())
+    }
+
+    fn get(&self, py: Host) -> Option<Object> {
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(host::list(py, self.values.iter().map(|v| v.clone_ref(py))))
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn checkpoint(&self, py: Host) -> HostResult<Object> {
+        Ok(host::list(py, self.values.iter().map(|v| v.clone_ref(py))))
+    }
+
+    fn from_checkpoint(&mut self, py: Host, data: Object) -> HostResult<()> {
+        self.values.clear();
+        if !data.is_none(py) {
+            // A checkpoint from a larger window keeps only its newest values
+            let values = host::list_items(py, &data)?;
+            self.push(values);
+        }
+        Ok(())
+    }
+
+    fn accumulates(&self) -> bool {
+        true
+    }
+
+    fn merges_writes(&self) -> bool {
+        true
+    }
+
+    fn update_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    fn json_schema(&self, _py: Host) -> Value {
+        let value = json!({
+            "type": "array",
+            "items": { "type": self.value_type.as_str() },
+            "maxItems": self.capacity,
+        });
+        channel_schema(value, true, None)
+    }
+
+    fn empty_copy(&self, _py: Host) -> Box<dyn Channel> {
+        Box::new(Self::new(self.capacity).with_value_type(self.value_type))
+    }
+
+    fn debug_repr(&self) -> String {
+        format!(
+            "SlidingWindowChannel(count={}, capacity={})",
+            self.values.len(),
+            self.capacity
+        )
+    }
+}
+
+impl fmt::Debug for SlidingWindowChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.debug_repr())
+    }
+}
+
+/// EMA channel - exponential moving average of numeric writes
+///
+/// The first value written becomes the average; each later value `x` moves
+/// it to `alpha * x + (1 - alpha) * average`, so a larger `alpha` follows
+/// recent values more closely. Writes in one update are applied in order.
+/// The checkpoint holds both the average and the number of values seen.
+pub struct EmaChannel {
+    ema: Option<f64>,
+    count: u64,
+    alpha: f64,
+}
+
+impl EmaChannel {
+    /// Create a channel smoothing with `alpha`
+    ///
+    /// # Panics
+    ///
+    /// If `alpha` is not in `(0, 1]`.
+    pub fn new(alpha: f64) -> Self {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "EMA alpha must be in (0, 1], got {alpha}"
+        );
+        Self {
+            ema: None,
+            count: 0,
+            alpha,
+        }
+    }
+
+    /// Smoothing factor applied to each new value
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Number of values averaged so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The values of `update` as numbers, failing on any other value
+    fn numbers(py: Host, update: &ChannelUpdate) -> HostResult<Vec<f64>> {
+        update
+            .values
+            .iter()
+            .map(|value| match host::as_f64(py, value) {
+                Some(number) if ValueType::Number.matches(py, value) => Ok(number),
+                _ => Err(host::type_error(format!(
+                    "EMA channel takes numbers, got {}",
+                    host::type_name(py, value)
+                ))),
+            })
+            .collect()
+    }
+
+    fn push(&mut self, value: f64) {
+        self.ema = Some(match self.ema {
+            Some(ema) => self.alpha * value + (1.0 - self.alpha) * ema,
+            None => value,
+        });
+        self.count += 1;
+    }
+}
+
+impl Channel for EmaChannel {
+    fn update(&mut self, py: Host, update: ChannelUpdate) -> HostResult<()> {
+        // Check every value first so a bad write leaves the average untouched
+        for value in Self::numbers(py, &update)? {
+            self.push(value);
+        }
+        Ok(())
+    }
+
+    fn check_update(&self, py: Host, update: &ChannelUpdate) -> HostResult<()> {
+        Self::numbers(py, update).map(drop)
+    }
+
+    fn get(&self, py: Host) -> Option<Object> {
+        self.ema.map(|ema| host::float(py, ema))
+    }
+
+    fn is_available(&self) -> bool {
+        self.ema.is_some()
+    }
+
+    fn checkpoint(&self, py: Host) -> HostResult<Object> {
+        let data = json!({ "ema": self.ema, "count": self.count });
+        Ok(host::from_json(py, &data))
+    }
+
+    fn from_checkpoint(&mut self, py: Host, data: Object) -> HostResult<()> {
+        self.ema = None;
+        self.count = 0;
+        if !data.is_none(py) {
+            let data = host::to_json(py, &data)
+                .filter(Value::is_object)
+                .ok_or_else(|| host::type_error("EMA checkpoint must be a dict"))?;
+            self.ema = data["ema"].as_f64();
+            self.count = data["count"].as_u64().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn accumulates(&self) -> bool {
+        true
+    }
+
+    fn merges_writes(&self) -> bool {
+        true
+    }
+
+    fn update_type(&self) -> ValueType {
+        ValueType::Number
+    }
+
+    fn json_schema(&self, _py: Host) -> Value {
+        channel_schema(json!({ "type": "number" }), true, None)
+    }
+
+    fn empty_copy(&self, _py: Host) -> Box<dyn Channel> {
+        Box::new(Self::new(self.alpha))
+    }
+
+    fn debug_repr(&self) -> String {
+        format!(
+            "EmaChannel(alpha={}, count={}, ema={:?})",
+            self.alpha, self.count, self.ema
+        )
+    }
+}
+
+impl fmt::Debug for EmaChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.debug_repr())
+    }
+}
+
+/// Dedup channel - accumulates values, skipping those already seen
+///
+/// Each value written is passed to `key_fn`, and the value is kept only if
+/// no earlier value, in this step or any before, had an equal key; `get`
+/// returns the kept values in insertion order. Keys must be hashable. The
+/// checkpoint holds the seen keys along with the values, so deduplication
+/// carries over a resume.
+#[cfg(feature = "python")]
+pub struct DedupChannel {
+    values: Vec<PyObject>,
+    /// Python set of the keys of every value kept
+    seen: Py<PySet>,
+    key_fn: PyObject,
+    /// Type of each value, for the state's JSON Schema
+    value_type: ValueType,
+}
+
+#[cfg(feature = "python")]
+impl DedupChannel {
+    pub fn new(py: Python, key_fn: PyObject) -> Self {
+        Self {
+            values: Vec::new(),
+            seen: PySet::empty(py).expect("empty set").into(),
+            key_fn,
+            value_type: ValueType::default(),
+        }
+    }
+
+    /// Declare the type of each value kept
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// The hashable key of each value of `update`
+    fn keys(&self, py: Python, update: &ChannelUpdate) -> PyResult<Vec<PyObject>> {
+        update
+            .values
+            .iter()
+            .map(|value| {
+                let key = self.key_fn.as_ref(py).call1((value,))?;
+                key.hash()?;
+                Ok(key.into())
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "python")]
+impl Channel for DedupChannel {
+    fn update(&mut self, py: Python, update: ChannelUpdate) -> PyResult<()> {
+        // Key every value first so a failing key leaves the channel untouched
+        let keys = self.keys(py, &update)?;
+        let seen = self.seen.as_ref(py);
+        for (value, key) in update.values.into_iter().zip(keys) {
+            if !seen.contains(&key)? {
+                seen.add(key)?;
+                self.values.push(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, py: Python) -> Option<PyObject> {
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(pyo3::types::PyList::new(py, &self.values).to_object(py))
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn checkpoint(&self, py: Python) -> PyResult<PyObject> {
+        let data = PyDict::new(py);
+        data.set_item("values", pyo3::types::PyList::new(py, &self.values))?;
+        let seen: Vec<&PyAny> = self.seen.as_ref(py).iter().collect();
+        data.set_item("seen", pyo3::types::PyList::new(py, seen))?;
+        Ok(data.to_object(py))
+    }
+
+    fn from_checkpoint(&mut self, py: Python, data: PyObject) -> PyResult<()> {
+        self.values.clear();
+        let seen = PySet::empty(py)?;
+        if !data.is_none(py) {
+            let data: &PyDict = data.extract(py)?;
+            if let Some(values) = data.get_item("values")? {
+                self.values = values.extract()?;
+            }
+            if let Some(keys) = data.get_item("seen")? {
+                for key in keys.iter()? {
+                    seen.add(key?)?;
+                }
+            }
+        }
+        self.seen = seen.into();
+        Ok(())
+    }
+
+    fn check_update(&self, py: Python, update: &ChannelUpdate) -> PyResult<()> {
+        self.keys(py, update).map(drop)
+    }
+
+    fn accumulates(&self) -> bool {
+        true
+    }
+
+    fn merges_writes(&self) -> bool {
+        true
+    }
+
+    fn update_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    fn json_schema(&self, _py: Python) -> Value {
+        let value = json!({
+            "type": "array",
+            "items": { "type": self.value_type.as_str() },
+        });
+        channel_schema(value, true, None)
+    }
+
+    fn empty_copy(&self, py: Python) -> Box<dyn Channel> {
+        Box::new(Self::new(py, self.key_fn.clone_ref(py)).with_value_type(self.value_type))
+    }
+
+    fn debug_repr(&self) -> String {
+        format!("DedupChannel(count={})", self.values.len())
+    }
+}
+
+#[cfg(feature = "python")]
+impl fmt::Debug for DedupChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.debug_repr())
+    }
+}
+
+/// Write to the field at a dotted `path` of an [`ObjectChannel`], e.g.
+/// `user.preferences.theme`, leaving the rest of the object untouched
+#[cfg(feature = "python")]
+#[pyclass]
+#[derive(Clone)]
+pub struct PartialUpdate {
+    /// Dot-separated keys leading from the channel's object to the field
+    #[pyo3(get)]
+    pub path: String,
+    /// Value the field is set to
+    #[pyo3(get)]
+    pub value: PyObject,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PartialUpdate {
+    #[new]
+    pub fn new(path: String, value: PyObject) -> Self {
+        Self { path, value }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PartialUpdate(path={:?})", self.path)
+    }
+}
+
+/// Object channel - a dict updated whole or field by field
+///
+/// A plain write must be a dict and replaces the object; a
+/// [`PartialUpdate`] sets one nested field, creating missing intermediate
+/// dicts. Several writes in one update merge as long as their paths are
+/// disjoint; writes where one path equals or contains another (a plain
+/// write contains every path) are rejected and leave the object unchanged.
+/// Dicts along a written path are copied, so values read earlier never
+/// change under a reader.
+#[cfg(feature = "python")]
+#[derive(Default)]
+pub struct ObjectChannel {
+    value: Option<PyObject>,
+}
+
+#[cfg(feature = "python")]
+impl ObjectChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_value(value: PyObject) -> Self {
+        Self { value: Some(value) }
+    }
+
+    /// Set the field at `path` of `root`, copying every dict on the way
+    fn set_path(py: Python, root: &PyDict, path: &[&str], value: PyObject) -> PyResult<()> {
+        let Some((last, parents)) = path.split_last() else {
+            return Ok(());
+        };
+        let mut object = root;
+        for (depth, key) in parents.iter().enumerate() {
+            let child = match object.get_item(*key)? {
+                None => PyDict::new(py),
+                Some(child) => child
+                    .downcast::<PyDict>()
+                    .map_err(|_| {
+                        pyo3::exceptions::PyTypeError::new_err(format!(
+                            "Object channel cannot write below '{}': not a dict",
+                            path[..=depth].join(".")
+                        ))
+                    })?
+                    .copy()?,
+            };
+            object.set_item(*key, child)?;
+            object = child;
+        }
+        object.set_item(*last, value)
+    }
+
+    /// The object after applying `update`, or `None` if it has no writes
+    ///
+    /// Every dict on a written path is copied, so the current object is
+    /// left untouched whether or not this fails.
+    fn merged(&self, py: Python, update: &ChannelUpdate) -> PyResult<Option<PyObject>> {
+        // Split every write into its path first, so a bad or conflicting
+        // write leaves the object untouched
+        let mut partials = Vec::new();
+        let mut writes: Vec<(Vec<&str>, PyObject)> = Vec::with_capacity(update.values.len());
+        for value in &update.values {
+            match value.extract::<PartialUpdate>(py) {
+                Ok(partial) => partials.push(partial),
+                Err(_) => {
+                    if !value.as_ref(py).is_instance_of::<PyDict>() {
+                        return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+                            "Object channel takes dicts or PartialUpdate, got {}",
+                            value.as_ref(py).get_type().name().unwrap_or("a value")
+                        )));
+                    }
+                    writes.push((Vec::new(), value.clone_ref(py)));
+                }
+            }
+        }
+        for partial in &partials {
+            let path: Vec<&str> = partial.path.split('.').collect();
+            if path.iter().any(|key| key.is_empty()) {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Invalid partial update path '{}'",
+                    partial.path
+                )));
+            }
+            writes.push((path, partial.value.clone_ref(py)));
+        }
+        for (i, (path, _)) in writes.iter().enumerate() {
+            for (other, _) in &writes[i + 1..] {
+                if path.iter().zip(other).all(|(a, b)| a == b) {
+                    let (shorter, longer) = if path.len() <= other.len() {
+                        (path, other)
+                    } else {
+                        (other, path)
+                    };
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "Conflicting writes to object channel: '{}' overlaps '{}'",
+                        shorter.join("."),
+                        longer.join(".")
+                    )));
+                }
+            }
+        }
+
+        let mut root = match &self.value {
+            Some(value) => value.as_ref(py).downcast::<PyDict>()?.copy()?,
+            None => PyDict::new(py),
+        };
+        for (path, value) in writes {
+            if path.is_empty() {
+                root = value.into_ref(py).downcast::<PyDict>()?;
+            } else {
+                Self::set_path(py, root, &path, value)?;
+            }
+        }
+        Ok((!update.values.is_empty()).then(|| root.to_object(py)))
+    }
+}
+
+#[cfg(feature = "python")]
+impl Channel for ObjectChannel {
+    fn update(&mut self, py: Python, update: ChannelUpdate) -> PyResult<()> {
+        if let Some(value) = self.merged(py, &update)? {
+            self.value = Some(value);
+        }
+        Ok(())
+    }
+
+    fn check_update(&self, py: Python, update: &ChannelUpdate) -> PyResult<()> {
+        self.merged(py, update).map(drop)
+    }
+
+    fn get(&self, py: Python) -> Option<PyObject> {
+        self.value.as_ref().map(|v| v.clone_ref(py))
+    }
+
+    fn is_available(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn checkpoint(&self, py: Python) -> PyResult<PyObject> {
+        match &self.value {
+            Some(value) => Ok(value.clone_ref(py)),
+            None => Ok(py.None()),
+        }
+    }
+
+    fn from_checkpoint(&mut self, py: Python, data: PyObject) -> PyResult<()> {
+        self.value = if data.is_none(py) { None } else { Some(data) };
+        Ok(())
+    }
+
+    fn merges_writes(&self) -> bool {
+        true
+    }
+
+    fn json_schema(&self, _py: Python) -> Value {
+        channel_schema(json!({ "type": "object" }), true, None)
+    }
+
+    fn empty_copy(&self, _py: Python) -> Box<dyn Channel> {
+        Box::new(Self::new())
+    }
+
+    fn debug_repr(&self) -> String {
+        format!("ObjectChannel(has_value={})", self.value.is_some())
+    }
+}
+
+#[cfg(feature = "python")]
+impl fmt::Debug for ObjectChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.debug_repr())
+    }
+}
+
+/// Context channel - read-only run configuration
+///
+/// Holds values supplied at invoke time (model name, user id, ...). Nodes can
+/// read it but any update is rejected, and it is excluded from checkpoints
+/// since it belongs to the run rather than the graph state.
+pub struct ContextChannel {
+    value: Option<Object>,
+}
+
+impl ContextChannel {
+    pub fn new(value: Option<Object>) -> Self {
+        Self { value }
+    }
+}
+
+impl Channel for ContextChannel {
+    fn update(&mut self, _py: Host, update: ChannelUpdate) -> HostResult<()> {
+        if update.values.is_empty() {
+            return Ok(());
+        }
+        Err(host::value_error("Context channel is read-only"))
+    }
+
+    fn get(&self, py: Host) -> Option<Object> {
+        self.value.as_ref().map(|v| v.clone_ref(py))
+    }
+
+    fn is_available(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn checkpoint(&self, py: Host) -> HostResult<Object> {
+        Ok(host::none(py))
+    }
+
+    fn from_checkpoint(&mut self, _py: Host, _data: Object) -> HostResult<()> {
+        Ok(())
+    }
+
+    fn is_checkpointed(&self) -> bool {
+        false
+    }
+
+    fn empty_copy(&self, _py: Host) -> Box<dyn Channel> {
+        Box::new(Self::new(None))
+    }
+
+    fn debug_repr(&self) -> String {
+        format!("ContextChannel(has_value={})", self.value.is_some())
+    }
+}
+
+impl fmt::Debug for ContextChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.debug_repr())
+    }
+}
+
+#[cfg(all(test, feature = "python"))]
 mod tests {
     use super::*;
 
@@ -249,6 +1147,40 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_channel_defaults() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut channel = LastValueChannel::with_default(0.to_object(py));
+            assert!(!channel.is_available());
+            channel.apply_default(py);
+            assert_eq!(channel.get(py).unwrap().extract::<i32>(py).unwrap(), 0);
+
+            // Written values are not overwritten by later runs
+            channel
+                .update(py, ChannelUpdate::single(5.to_object(py)))
+                .unwrap();
+            channel.apply_default(py);
+            assert_eq!(channel.get(py).unwrap().extract::<i32>(py).unwrap(), 5);
+
+            // A restored channel is not re-seeded, even if it was empty
+            let mut restored = LastValueChannel::with_default(0.to_object(py));
+            restored.from_checkpoint(py, py.None()).unwrap();
+            restored.apply_default(py);
+            assert!(!restored.is_available());
+
+            // Topic writes accumulate onto the default values
+            let mut topic = TopicChannel::with_default(vec!["system".to_object(py)], true);
+            topic.apply_default(py);
+            topic
+                .update(py, ChannelUpdate::single("user".to_object(py)))
+                .unwrap();
+            let values: Vec<String> = topic.get(py).unwrap().extract(py).unwrap();
+            assert_eq!(values, vec!["system", "user"]);
+        });
+    }
+
     #[test]
     fn test_topic_channel_accumulate() {
         pyo3::prepare_freethreaded_python();
@@ -309,4 +1241,304 @@ mod tests {
             assert_eq!(list.get_item(1).unwrap().extract::<i32>().unwrap(), 4);
         });
     }
+
+    fn window_values(py: Python, channel: &SlidingWindowChannel) -> Vec<i32> {
+        channel
+            .get(py)
+            .map(|list| list.extract(py).unwrap())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_sliding_window_trims_overflow() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut channel = SlidingWindowChannel::new(3);
+            assert!(!channel.is_available());
+
+            let values = (1..=2).map(|v| v.to_object(py)).collect();
+            channel.update(py, ChannelUpdate::new(values)).unwrap();
+            assert_eq!(window_values(py, &channel), [1, 2]);
+
+            // A single update larger than the window keeps its newest values
+            let values = (3..=7).map(|v| v.to_object(py)).collect();
+            channel.update(py, ChannelUpdate::new(values)).unwrap();
+            assert_eq!(window_values(py, &channel), [5, 6, 7]);
+
+            // Only the retained window is checkpointed
+            let checkpoint = channel.checkpoint(py).unwrap();
+            assert_eq!(checkpoint.extract::<Vec<i32>>(py).unwrap(), [5, 6, 7]);
+
+            // Restoring into a smaller window keeps the newest values
+            let mut smaller = SlidingWindowChannel::new(2);
+            smaller.from_checkpoint(py, checkpoint).unwrap();
+            assert_eq!(window_values(py, &smaller), [6, 7]);
+        });
+    }
+
+    #[test]
+    fn test_sliding_window_capacity_boundaries() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            // Exactly `capacity` values are all kept
+            let mut channel = SlidingWindowChannel::new(3);
+            let values = (1..=3).map(|v| v.to_object(py)).collect();
+            channel.update(py, ChannelUpdate::new(values)).unwrap();
+            assert_eq!(window_values(py, &channel), [1, 2, 3]);
+
+            // One more evicts exactly the oldest
+            channel
+                .update(py, ChannelUpdate::single(4.to_object(py)))
+                .unwrap();
+            assert_eq!(window_values(py, &channel), [2, 3, 4]);
+
+            // Empty updates leave the window untouched
+            channel.update(py, ChannelUpdate::new(Vec::new())).unwrap();
+            assert_eq!(window_values(py, &channel), [2, 3, 4]);
+
+            let mut single = SlidingWindowChannel::new(1);
+            let values = (1..=3).map(|v| v.to_object(py)).collect();
+            single.update(py, ChannelUpdate::new(values)).unwrap();
+            assert_eq!(window_values(py, &single), [3]);
+
+            let mut none = SlidingWindowChannel::new(0);
+            none.update(py, ChannelUpdate::single(1.to_object(py)))
+                .unwrap();
+            assert!(!none.is_available());
+            let checkpoint: Vec<i32> = none.checkpoint(py).unwrap().extract(py).unwrap();
+            assert!(checkpoint.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_dedup_channel_skips_seen_keys() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let key_fn = py.eval("lambda doc: doc['id']", None, None).unwrap();
+            let mut channel = DedupChannel::new(py, key_fn.into());
+            let doc = |id: i32, text: &str| {
+                let doc = PyDict::new(py);
+                doc.set_item("id", id).unwrap();
+                doc.set_item("text", text).unwrap();
+                doc.to_object(py)
+            };
+            let ids = |channel: &DedupChannel| -> Vec<i32> {
+                let docs = channel.get(py).unwrap();
+                let docs: Vec<&PyDict> = docs.extract(py).unwrap();
+                docs.iter()
+                    .map(|doc| doc.get_item("id").unwrap().unwrap().extract().unwrap())
+                    .collect()
+            };
+
+            // Step 1 retrieves a document twice; only the first is kept
+            let step1 = vec![doc(1, "a"), doc(2, "b"), doc(1, "a again")];
+            channel.update(py, ChannelUpdate::new(step1)).unwrap();
+            assert_eq!(ids(&channel), [1, 2]);
+
+            // Step 2 retrieves document 2 again
+            let step2 = vec![doc(2, "b"), doc(3, "c")];
+            channel.update(py, ChannelUpdate::new(step2)).unwrap();
+            assert_eq!(ids(&channel), [1, 2, 3]);
+
+            // A failing key leaves the channel untouched
+            let bad = PyDict::new(py).to_object(py);
+            assert!(channel
+                .update(py, ChannelUpdate::new(vec![doc(4, "d"), bad]))
+                .is_err());
+            assert_eq!(ids(&channel), [1, 2, 3]);
+
+            // The seen keys survive a checkpoint round-trip
+            let mut restored = channel.empty_copy(py);
+            restored
+                .from_checkpoint(py, channel.checkpoint(py).unwrap())
+                .unwrap();
+            restored
+                .update(py, ChannelUpdate::new(vec![doc(3, "c"), doc(4, "d")]))
+                .unwrap();
+            let docs = restored.get(py).unwrap();
+            let docs: Vec<&PyDict> = docs.extract(py).unwrap();
+            assert_eq!(docs.len(), 4);
+        });
+    }
+
+    #[test]
+    fn test_context_channel_read_only() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut channel = ContextChannel::new(Some("gpt-4".to_object(py)));
+
+            assert!(channel.is_available());
+            assert_eq!(
+                channel.get(py).unwrap().extract::<String>(py).unwrap(),
+                "gpt-4"
+            );
+            assert!(!channel.is_checkpointed());
+
+            let err = channel
+                .update(py, ChannelUpdate::single("other".to_object(py)))
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            assert_eq!(
+                channel.get(py).unwrap().extract::<String>(py).unwrap(),
+                "gpt-4"
+            );
+        });
+    }
+
+    #[test]
+    fn test_ema_channel_smoothing() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut channel = EmaChannel::new(0.5);
+            assert!(!channel.is_available());
+
+            // The first value seeds the average
+            channel
+                .update(py, ChannelUpdate::single(10.to_object(py)))
+                .unwrap();
+            let ema: f64 = channel.get(py).unwrap().extract(py).unwrap();
+            assert_eq!(ema, 10.0);
+
+            // 0.5 * 20 + 0.5 * 10 = 15, then 0.5 * 5 + 0.5 * 15 = 10
+            channel
+                .update(py, ChannelUpdate::single(20.to_object(py)))
+                .unwrap();
+            let ema: f64 = channel.get(py).unwrap().extract(py).unwrap();
+            assert_eq!(ema, 15.0);
+            channel
+                .update(py, ChannelUpdate::single(5.0.to_object(py)))
+                .unwrap();
+            let ema: f64 = channel.get(py).unwrap().extract(py).unwrap();
+            assert_eq!(ema, 10.0);
+            assert_eq!(channel.count(), 3);
+
+            // Writes in one update are applied in order:
+            // 0.25 * 4 + 0.75 * 0 = 1, then 0.25 * 8 + 0.75 * 1 = 2.75
+            let mut batched = EmaChannel::new(0.25);
+            let values = [0, 4, 8].iter().map(|v| v.to_object(py)).collect();
+            batched.update(py, ChannelUpdate::new(values)).unwrap();
+            let ema: f64 = batched.get(py).unwrap().extract(py).unwrap();
+            assert!((ema - 2.75).abs() < 1e-12);
+
+            // Non-numeric writes are rejected without touching the average
+            let err = channel
+                .update(py, ChannelUpdate::single(true.to_object(py)))
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+            assert_eq!(channel.count(), 3);
+
+            // The checkpoint keeps the average and the count
+            let checkpoint = channel.checkpoint(py).unwrap();
+            let mut restored = EmaChannel::new(0.5);
+            restored.from_checkpoint(py, checkpoint).unwrap();
+            assert_eq!(restored.count(), 3);
+            restored
+                .update(py, ChannelUpdate::single(20.to_object(py)))
+                .unwrap();
+            let ema: f64 = restored.get(py).unwrap().extract(py).unwrap();
+            assert_eq!(ema, 15.0);
+        });
+    }
+
+    #[test]
+    fn test_object_channel_partial_updates() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let partial = |path: &str, value: PyObject| -> PyObject {
+                PartialUpdate::new(path.to_string(), value).into_py(py)
+            };
+            let read = |channel: &ObjectChannel, expr: &str| -> String {
+                let locals = pyo3::types::PyDict::new(py);
+                locals.set_item("v", channel.get(py).unwrap()).unwrap();
+                py.eval(expr, None, Some(locals))
+                    .unwrap()
+                    .str()
+                    .unwrap()
+                    .to_string()
+            };
+
+            let mut channel = ObjectChannel::new();
+            let initial = py
+                .eval(
+                    "{'user': {'name': 'ada', 'preferences': {'theme': 'light'}}}",
+                    None,
+                    None,
+                )
+                .unwrap()
+                .to_object(py);
+            channel
+                .update(py, ChannelUpdate::single(initial.clone_ref(py)))
+                .unwrap();
+
+            // Disjoint nested paths merge, creating missing dicts on the way
+            channel
+                .update(
+                    py,
+                    ChannelUpdate::new(vec![
+                        partial("user.preferences.theme", "dark".to_object(py)),
+                        partial("user.preferences.lang", "en".to_object(py)),
+                        partial("meta.visits", 3.to_object(py)),
+                    ]),
+                )
+                .unwrap();
+            assert_eq!(
+                read(&channel, "v"),
+                "{'user': {'name': 'ada', 'preferences': {'theme': 'dark', 'lang': 'en'}}, 'meta': {'visits': 3}}"
+            );
+
+            // The dict written earlier is copied, not modified
+            let original = initial.as_ref(py).get_item("user").unwrap();
+            assert_eq!(
+                original
+                    .get_item("preferences")
+                    .unwrap()
+                    .get_item("theme")
+                    .unwrap()
+                    .to_string(),
+                "light"
+            );
+
+            // Equal or nested paths in one update conflict and change nothing
+            for writes in [
+                vec![
+                    partial("user.name", "bob".to_object(py)),
+                    partial("user.name", "eve".to_object(py)),
+                ],
+                vec![
+                    partial("user.preferences", py.None()),
+                    partial("user.preferences.theme", "light".to_object(py)),
+                ],
+                vec![
+                    initial.clone_ref(py),
+                    partial("meta.visits", 4.to_object(py)),
+                ],
+            ] {
+                let err = channel.update(py, ChannelUpdate::new(writes)).unwrap_err();
+                assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            }
+            assert_eq!(read(&channel, "v['user']['name']"), "ada");
+            assert_eq!(read(&channel, "v['meta']['visits']"), "3");
+
+            // Paths cannot pass through non-dict values
+            let err = channel
+                .update(
+                    py,
+                    ChannelUpdate::single(partial("user.name.first", py.None())),
+                )
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+
+            // The checkpoint round-trips the merged object
+            let checkpoint = channel.checkpoint(py).unwrap();
+            let mut restored = ObjectChannel::new();
+            restored.from_checkpoint(py, checkpoint).unwrap();
+            assert_eq!(read(&restored, "v['user']['preferences']['lang']"), "en");
+        });
+    }
 }
@@ -0,0 +1,225 @@
+//! Persistence of PregelCore state between supersteps
+//!
+//! A [`Checkpointer`] passed to [`PregelCore::compile`](super::PregelCore::compile)
+//! receives a [`StateSnapshot`] after every committed superstep. The saved
+//! snapshots back the time-travel APIs: listing a run's states and running
+//! the graph again from any of them.
+
+use super::host::prelude::*;
+use super::serializer::{decompress, ChannelCompression, JsonSerializer, Serializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// State committed at the end of a superstep
+#[derive(Debug, Clone)]
+pub struct StateSnapshot {
+    /// Checkpoint ID, derived from the parent ID, the step and the values
+    pub id: String,
+    /// ID of the previous snapshot of the same run, `None` for the first
+    pub parent_id: Option<String>,
+    /// Run that produced the state, `None` if it was saved without one
+    pub run_id: Option<Uuid>,
+    /// Superstep that produced the state, starting at 1 for each run
+    pub step: usize,
+    /// Values of the checkpointed channels
+    pub values: HashMap<String, Object>,
+    /// Nodes scheduled to run next; empty once the run finished
+    pub next: Vec<String>,
+    /// JSON Schema of each checkpointed channel, without its default, when
+    /// the snapshot was taken
+    pub schema: BTreeMap<String, Value>,
+}
+
+/// Storage for the snapshots of a [`PregelCore`](super::PregelCore)
+pub trait Checkpointer: Send + Sync {
+    /// Persist a snapshot
+    fn put(&self, py: Host<'_>, snapshot: StateSnapshot) -> HostResult<()>;
+
+    /// Every persisted snapshot, oldest first
+    fn list(&self, py: Host<'_>) -> HostResult<Vec<StateSnapshot>>;
+}
+
+/// Serialized channel value, recording whether its bytes are compressed so
+/// it loads whatever the current [`ChannelCompression`] settings are
+#[derive(Debug, Clone)]
+struct StoredValue {
+    data: Vec<u8>,
+    compressed: bool,
+}
+
+/// Snapshot with its channel values serialized
+#[derive(Debug, Clone)]
+struct StoredSnapshot {
+    id: String,
+    parent_id: Option<String>,
+    run_id: Option<Uuid>,
+    step: usize,
+    values: HashMap<String, StoredValue>,
+    next: Vec<String>,
+    schema: BTreeMap<String, Value>,
+}
+
+/// Checkpointer keeping snapshots in memory for the life of the process
+///
+/// Values are stored as bytes from the serializer `S`, so later changes to
+/// the live objects do not alter saved snapshots. The default
+/// [`JsonSerializer`] only accepts JSON data; use
+/// [`with_serializer`](Self::with_serializer) with a
+/// [`PickleSerializer`](super::PickleSerializer) or a custom [`Serializer`]
+/// for other values. Channels set up with
+/// [`with_compression`](Self::with_compression) have large values
+/// compressed.
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointer<S: Serializer = JsonSerializer> {
+    serializer: S,
+    compression: HashMap<String, ChannelCompression>,
+    snapshots: Mutex<Vec<StoredSnapshot>>,
+}
+
+impl MemoryCheckpointer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: Serializer> MemoryCheckpointer<S> {
+    /// Create a checkpointer storing values with `serializer`
+    pub fn with_serializer(serializer: S) -> Self {
+        Self {
+            serializer,
+            compression: HashMap::new(),
+            snapshots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Compress the saved values of `channel` as `compression` says
+    pub fn with_compression(
+        mut self,
+        channel: impl Into<String>,
+        compression: ChannelCompression,
+    ) -> Self {
+        self.compression.insert(channel.into(), compression);
+        self
+    }
+}
+
+impl<S: Serializer> Checkpointer for MemoryCheckpointer<S> {
+    fn put(&self, py: Host<'_>, snapshot: StateSnapshot) -> HostResult<()> {
+        let values = snapshot
+            .values
+            .iter()
+            .map(|(name, value)| {
+                let data = self.serializer.dumps(py, value)?;
+                let (data, compressed) = match self.compression.get(name) {
+                    Some(compression) => compression.apply(data)?,
+                    None => (data, false),
+                };
+                Ok((name.clone(), StoredValue { data, compressed }))
+            })
+            .collect::<HostResult<_>>()?;
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(StoredSnapshot {
+                id: snapshot.id,
+                parent_id: snapshot.parent_id,
+                run_id: snapshot.run_id,
+                step: snapshot.step,
+                values,
+                next: snapshot.next,
+                schema: snapshot.schema,
+            });
+        Ok(())
+    }
+
+    fn list(&self, py: Host<'_>) -> HostResult<Vec<StateSnapshot>> {
+        let snapshots = self
+            .snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        snapshots
+            .into_iter()
+            .map(|stored| {
+                let values = stored
+                    .values
+                    .iter()
+                    .map(|(name, stored)| {
+                        let value = if stored.compressed {
+                            self.serializer.loads(py, &decompress(&stored.data)?)?
+                        } else {
+                            self.serializer.loads(py, &stored.data)?
+                        };
+                        Ok((name.clone(), value))
+                    })
+                    .collect::<HostResult<_>>()?;
+                Ok(StateSnapshot {
+                    id: stored.id,
+                    parent_id: stored.parent_id,
+                    run_id: stored.run_id,
+                    step: stored.step,
+                    values,
+                    next: stored.next,
+                    schema: stored.schema,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "python", feature = "compression-zstd"))]
+mod tests {
+    use super::*;
+    use pyo3::prelude::*;
+
+    #[test]
+    fn test_channel_compression() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let messages = py
+                .eval("['hello world'] * 200", None, None)
+                .unwrap()
+                .to_object(py);
+            let snapshot = |question: &str| StateSnapshot {
+                id: question.to_string(),
+                parent_id: None,
+                run_id: None,
+                step: 1,
+                values: HashMap::from([
+                    ("messages".to_string(), messages.clone_ref(py)),
+                    ("question".to_string(), question.to_object(py)),
+                ]),
+                next: vec![],
+                schema: Default::default(),
+            };
+
+            let mut checkpointer = MemoryCheckpointer::new()
+                .with_compression("messages", ChannelCompression::over(256))
+                .with_compression("question", ChannelCompression::over(256));
+            checkpointer.put(py, snapshot("why?")).unwrap();
+            {
+                let stored = checkpointer.snapshots.lock().unwrap();
+                let messages = &stored[0].values["messages"];
+                assert!(messages.compressed);
+                assert!(messages.data.len() < 256);
+                // Below the threshold
+                assert!(!stored[0].values["question"].compressed);
+            }
+
+            // Stored flags, not the current settings, decide how to load
+            checkpointer.compression.clear();
+            let loaded = &checkpointer.list(py).unwrap()[0];
+            assert!(loaded.values["messages"]
+                .as_ref(py)
+                .eq(messages.as_ref(py))
+                .unwrap());
+            assert_eq!(
+                loaded.values["question"].extract::<String>(py).unwrap(),
+                "why?"
+            );
+        });
+    }
+}
@@ -2,9 +2,46 @@
 //!
 //! Edges define how execution flows between nodes in the graph.
 
-use pyo3::prelude::*;
+use super::host::{self, prelude::*};
+use super::state::GraphState;
+use crate::graph::END;
 use std::collections::HashMap;
 
+/// Where a conditional edge routes to
+#[derive(Clone, Debug)]
+pub enum Destination {
+    /// Run the node next step with its usual input
+    Node(String),
+    /// Run `node` next step with `arg` as its input, as a task of its own
+    Send { node: String, arg: Object },
+}
+
+impl Destination {
+    /// Node the destination runs
+    pub fn node(&self) -> &str {
+        match self {
+            Destination::Node(node) => node,
+            Destination::Send { node, .. } => node,
+        }
+    }
+}
+
+/// Which of the targets returned by a conditional edge's condition run
+///
+/// A condition may return a single name or a list of names; the mode only
+/// matters for lists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RouteMode {
+    /// Every returned target runs in the next superstep, side by side.
+    /// Targets routing to [`END`] contribute nothing, so the others still run.
+    #[default]
+    All,
+    /// Only the first returned target runs. If it routes to [`END`], nothing
+    /// runs, even when later names route to nodes; an empty list also runs
+    /// nothing.
+    FirstMatch,
+}
+
 /// Edge defines control flow between nodes
 #[derive(Clone)]
 pub enum Edge {
@@ -14,8 +51,17 @@ pub enum Edge {
     /// Conditional edge: evaluate a condition to determine next node
     Conditional {
         source: String,
-        condition: PyObject,               // Function that returns next node name
+        condition: Callable,               // Function that returns next node name
         branches: HashMap<String, String>, // condition_result -> target_node
+        mode: RouteMode,
+    },
+
+    /// Availability edge: go from source to target only if `channel` holds
+    /// a value once the source's step is committed
+    WhenAvailable {
+        source: String,
+        target: String,
+        channel: String,
     },
 
     /// Start edge: entry point to a node
@@ -31,16 +77,39 @@ impl Edge {
         Self::Direct { source, target }
     }
 
-    /// Create a conditional edge
+    /// Create a conditional edge running every target its condition returns
     pub fn conditional(
         source: String,
-        condition: PyObject,
+        condition: Callable,
         branches: HashMap<String, String>,
     ) -> Self {
         Self::Conditional {
             source,
             condition,
             branches,
+            mode: RouteMode::All,
+        }
+    }
+
+    /// Set how a conditional edge picks among the targets its condition
+    /// returns; other edges are returned unchanged
+    pub fn with_route_mode(mut self, route_mode: RouteMode) -> Self {
+        if let Edge::Conditional { mode, .. } = &mut self {
+            *mode = route_mode;
+        }
+        self
+    }
+
+    /// Create an edge that only fires while `channel` is available
+    ///
+    /// A lighter alternative to a conditional edge for presence checks: the
+    /// edge is checked when the next superstep is scheduled and contributes
+    /// nothing if the channel was never written (or has been consumed).
+    pub fn when_available(source: String, target: String, channel: String) -> Self {
+        Self::WhenAvailable {
+            source,
+            target,
+            channel,
         }
     }
 
@@ -59,6 +128,7 @@ impl Edge {
         match self {
             Edge::Direct { source, .. } => Some(source),
             Edge::Conditional { source, .. } => Some(source),
+            Edge::WhenAvailable { source, .. } => Some(source),
             Edge::Start { .. } => None,
             Edge::End { source } => Some(source),
         }
@@ -68,17 +138,34 @@ impl Edge {
     pub fn target(&self) -> Option<&str> {
         match self {
             Edge::Direct { target, .. } => Some(target),
+            Edge::WhenAvailable { target, .. } => Some(target),
             Edge::Start { target } => Some(target),
             _ => None,
         }
     }
 
+    /// Whether the edge fires given the committed `state`
+    ///
+    /// Only [`Edge::WhenAvailable`] depends on the state; every other edge is
+    /// always active, leaving conditions to [`route`](Self::route).
+    pub fn is_active(&self, state: &GraphState) -> bool {
+        match self {
+            Edge::WhenAvailable { channel, .. } => state
+                .get_channel(channel)
+                .is_some_and(|channel| channel.is_available()),
+            _ => true,
+        }
+    }
+
     /// Evaluate conditional edge to determine next node
     ///
     /// Returns the name of the next node to execute based on the condition.
-    pub fn evaluate_condition(&self, py: Python, state: PyObject) -> PyResult<Option<String>> {
+    /// An [`Edge::WhenAvailable`] yields its target; check
+    /// [`is_active`](Self::is_active) first.
+    pub fn evaluate_condition(&self, py: Host, state: Object) -> HostResult<Option<String>> {
         match self {
             Edge::Direct { target, .. } => Ok(Some(target.clone())),
+            Edge::WhenAvailable { target, .. } => Ok(Some(target.clone())),
             Edge::Start { target } => Ok(Some(target.clone())),
             Edge::End { .. } => Ok(None),
             Edge::Conditional {
@@ -87,16 +174,16 @@ impl Edge {
                 ..
             } => {
                 // Call the condition function with the state
-                let result = condition.call1(py, (state,))?;
+                let result = host::call(py, condition, state)?;
 
                 // Extract the result as a string
-                let result_str = result.extract::<String>(py)?;
+                let result_str = host::as_string(py, &result)?;
 
                 // Look up the target in the branches map
                 let target = branches
                     .get(&result_str)
                     .ok_or_else(|| {
-                        pyo3::exceptions::PyKeyError::new_err(format!(
+                        host::key_error(format!(
                             "Condition result '{}' not found in branches",
                             result_str
                         ))
@@ -109,6 +196,57 @@ impl Edge {
     }
 }
 
+impl Edge {
+    /// Evaluate the edge to the list of destinations it routes to
+    ///
+    /// Unlike [`evaluate_condition`](Self::evaluate_condition), a condition
+    /// may return a list to fan out to several nodes, or only to the first
+    /// entry with [`RouteMode::FirstMatch`]. Each entry is a name, looked up
+    /// in `branches` or used as a node name when `branches` is empty, or a
+    /// `Send(node, arg)`, which runs `node` on `arg` as a task of its own, so
+    /// several sends to one node fan out into as many tasks. Routing to
+    /// [`END`] contributes nothing.
+    pub fn route(&self, py: Host, state: Object) -> HostResult<Vec<Destination>> {
+        let Edge::Conditional {
+            condition,
+            branches,
+            mode,
+            ..
+        } = self
+        else {
+            return Ok(self
+                .evaluate_condition(py, state)?
+                .into_iter()
+                .map(Destination::Node)
+                .collect());
+        };
+
+        let result = host::call(py, condition, state)?;
+        let first_only = *mode == RouteMode::FirstMatch;
+        let mut destinations = Vec::new();
+        for item in host::destinations(py, &result, first_only)? {
+            let Destination::Node(name) = item else {
+                destinations.push(item);
+                continue;
+            };
+            let target = if branches.is_empty() {
+                name
+            } else {
+                branches.get(&name).cloned().ok_or_else(|| {
+                    host::key_error(format!("Condition result '{}' not found in branches", name))
+                })?
+            };
+            let routed = destinations
+                .iter()
+                .any(|d| matches!(d, Destination::Node(node) if *node == target));
+            if target != END && !routed {
+                destinations.push(Destination::Node(target));
+            }
+        }
+        Ok(destinations)
+    }
+}
+
 impl std::fmt::Debug for Edge {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -118,11 +256,25 @@ impl std::fmt::Debug for Edge {
                 .field("target", target)
                 .finish(),
             Edge::Conditional {
-                source, branches, ..
+                source,
+                branches,
+                mode,
+                ..
             } => f
                 .debug_struct("Edge::Conditional")
                 .field("source", source)
                 .field("branches", branches)
+                .field("mode", mode)
+                .finish(),
+            Edge::WhenAvailable {
+                source,
+                target,
+                channel,
+            } => f
+                .debug_struct("Edge::WhenAvailable")
+                .field("source", source)
+                .field("target", target)
+                .field("channel", channel)
                 .finish(),
             Edge::Start { target } => f
                 .debug_struct("Edge::Start")
@@ -133,9 +285,10 @@ impl std::fmt::Debug for Edge {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "python"))]
 mod tests {
     use super::*;
+    use pyo3::prelude::*;
 
     #[test]
     fn test_direct_edge() {
@@ -161,6 +314,33 @@ mod tests {
         assert_eq!(edge.target(), None);
     }
 
+    #[test]
+    fn test_when_available_edge() {
+        use crate::core::channel::LastValueChannel;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let edge = Edge::when_available(
+                "search".to_string(),
+                "has_results".to_string(),
+                "results".to_string(),
+            );
+            assert_eq!(edge.source(), Some("search"));
+            assert_eq!(edge.target(), Some("has_results"));
+
+            let mut state = GraphState::new();
+            assert!(!edge.is_active(&state));
+            state.add_channel("results".to_string(), Box::new(LastValueChannel::new()));
+            assert!(!edge.is_active(&state));
+            state
+                .update_channel(py, "results", 1.to_object(py))
+                .unwrap();
+            assert!(edge.is_active(&state));
+            assert!(Edge::direct("a".to_string(), "b".to_string()).is_active(&state));
+        });
+    }
+
     #[test]
     fn test_conditional_edge_evaluation() {
         pyo3::prepare_freethreaded_python();
@@ -183,6 +363,81 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_route_modes() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let condition = py.eval("lambda state: state", None, None).unwrap();
+            let branches: HashMap<String, String> = [
+                ("search", "search_node"),
+                ("answer", "answer_node"),
+                ("done", END),
+            ]
+            .into_iter()
+            .map(|(name, target)| (name.to_string(), target.to_string()))
+            .collect();
+            let all = Edge::conditional("agent".to_string(), condition.to_object(py), branches);
+            let first = all.clone().with_route_mode(RouteMode::FirstMatch);
+            let nodes = |destinations: Vec<Destination>| -> Vec<String> {
+                destinations.iter().map(|d| d.node().to_string()).collect()
+            };
+            let route =
+                |edge: &Edge, names: &[&str]| nodes(edge.route(py, names.to_object(py)).unwrap());
+
+            assert_eq!(
+                route(&all, &["answer", "search"]),
+                ["answer_node", "search_node"]
+            );
+            assert_eq!(route(&first, &["answer", "search"]), ["answer_node"]);
+
+            // END contributes nothing to All, but ends a FirstMatch route
+            assert_eq!(route(&all, &["done", "search"]), ["search_node"]);
+            assert!(route(&first, &["done", "search"]).is_empty());
+            assert!(route(&first, &[]).is_empty());
+
+            // A single name routes the same in both modes
+            assert_eq!(
+                nodes(first.route(py, "search".to_object(py)).unwrap()),
+                ["search_node"]
+            );
+
+            // Sends bypass the branches and each keep their own argument
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+routed = ["search", Send("worker", 1), Send("worker", 2)]
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let routed = locals.get_item("routed").unwrap().unwrap().to_object(py);
+            let destinations = all.route(py, routed).unwrap();
+            assert_eq!(
+                nodes(destinations.clone()),
+                ["search_node", "worker", "worker"]
+            );
+            let args: Vec<i64> = destinations
+                .iter()
+                .filter_map(|d| match d {
+                    Destination::Send { arg, .. } => Some(arg.extract(py).unwrap()),
+                    Destination::Node(_) => None,
+                })
+                .collect();
+            assert_eq!(args, [1, 2]);
+            let direct = Edge::direct("a".to_string(), "b".to_string());
+            assert_eq!(
+                direct.with_route_mode(RouteMode::FirstMatch).target(),
+                Some("b")
+            );
+        });
+    }
+
     #[test]
     fn test_direct_edge_evaluation() {
         pyo3::prepare_freethreaded_python();
@@ -2,12 +2,243 @@
 //!
 //! This module implements the core Pregel-style graph execution with async support.
 
-use super::channel::{Channel, LastValueChannel};
-use super::edge::Edge;
-use super::node::Node;
-use super::state::GraphState;
+use super::cache::{CacheKey, CachePolicy, CacheStats, NodeCache};
+use super::channel::{Channel, ChannelUpdate, ContextChannel, LastValueChannel, CONTEXT_CHANNEL};
+use super::checkpointer::{Checkpointer, StateSnapshot};
+use super::edge::{Destination, Edge};
+use super::host::{self, prelude::*, Kind};
+use super::introspect::{ChannelInfo, EdgeInfo, EdgeKind, NodeInfo};
+use super::metrics::{Metrics, MetricsSnapshot, NodeSample, Stopwatch};
+use super::node::{GuardAction, Node, NodeFunc};
+use super::state::{GraphState, StateSchema};
+use super::trace::{trace_value, ExecutionEvent, Trace};
+use crate::errors::{GraphError, ValidationIssue};
+use crate::graph::{END, START};
+use futures::future::join_all;
+use futures::{Stream, StreamExt};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
-use std::collections::{HashMap, HashSet};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Channels projected into the result of [`PregelCore::invoke`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputChannels {
+    /// Return the channel's bare value (`None` if it was never written)
+    Single(String),
+    /// Return a dict of the channels; channels never written are omitted
+    Multiple(Vec<String>),
+}
+
+/// Native input check: an `Err` message rejects the input
+pub type InputCheck = Arc<dyn Fn(Host<'_>, &Object) -> Result<(), String> + Send + Sync>;
+
+/// Check of the invoke input, run before any node
+///
+/// The check receives the input as a dict of the channel writes it maps to,
+/// after coercion to input channels, and a rejection fails the run with
+/// [`GraphError::InvalidInput`].
+#[derive(Clone)]
+pub enum InputValidator {
+    /// Rust check
+    Native(InputCheck),
+    /// Python callable; raising an exception or returning a message string
+    /// rejects the input, any other return value accepts it
+    #[cfg(feature = "python")]
+    Python(PyObject),
+}
+
+impl InputValidator {
+    /// Run the check against the channel writes of an input
+    pub fn check(&self, py: Host<'_>, writes: &Object) -> Result<(), GraphError> {
+        match self {
+            InputValidator::Native(check) => check(py, writes).map_err(GraphError::InvalidInput),
+            #[cfg(feature = "python")]
+            InputValidator::Python(func) => match func.call1(py, (writes,)) {
+                Ok(result) => match result.extract::<String>(py) {
+                    Ok(message) => Err(GraphError::InvalidInput(message)),
+                    Err(_) => Ok(()),
+                },
+                Err(err) => Err(GraphError::InvalidInput(err.value(py).to_string())),
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for InputValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputValidator::Native(_) => write!(f, "InputValidator::Native(<function>)"),
+            #[cfg(feature = "python")]
+            InputValidator::Python(func) => write!(f, "InputValidator::Python({})", func),
+        }
+    }
+}
+
+/// Native input transform: maps the raw input to a dict of channel writes
+pub type InputMap = Arc<dyn Fn(Host<'_>, Object) -> HostResult<Object> + Send + Sync>;
+
+/// Reshaping of the invoke input into channel writes, run before the input
+/// is routed to input channels
+///
+/// The transform must return a dict of channel writes; see
+/// [`PregelCore::with_input_transform`].
+#[derive(Clone)]
+pub enum InputTransform {
+    /// Rust function
+    Native(InputMap),
+    /// Python callable taking the input
+    #[cfg(feature = "python")]
+    Python(PyObject),
+}
+
+impl InputTransform {
+    /// Apply the transform to an input
+    pub fn apply(&self, py: Host<'_>, input: Object) -> HostResult<Object> {
+        match self {
+            InputTransform::Native(map) => map(py, input),
+            #[cfg(feature = "python")]
+            InputTransform::Python(func) => func.call1(py, (input,)),
+        }
+    }
+}
+
+impl std::fmt::Debug for InputTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputTransform::Native(_) => write!(f, "InputTransform::Native(<function>)"),
+            #[cfg(feature = "python")]
+            InputTransform::Python(func) => write!(f, "InputTransform::Python({})", func),
+        }
+    }
+}
+
+/// Static execution schedule produced by [`PregelCore::plan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    /// Channels the input writes to
+    pub input_channels: Vec<String>,
+    /// Nodes by the earliest superstep they may run in; `steps[0]` is the
+    /// first superstep
+    pub steps: Vec<Vec<String>>,
+    /// Nodes that can never run for this input
+    pub unreachable: Vec<String>,
+}
+
+/// Settings of a [`PregelCore::validate_runtime`] smoke run
+#[derive(Clone, Default)]
+pub struct RuntimeCheck {
+    /// Replacement behaviour for nodes, by name, e.g. a
+    /// [`NodeFunc::Constant`] instead of a model call
+    pub stubs: HashMap<String, NodeFunc>,
+    /// Fail the check if the run has not finished after this long
+    pub timeout: Option<Duration>,
+}
+
+impl RuntimeCheck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `func` in place of the node named `node`
+    pub fn with_stub(mut self, node: impl Into<String>, func: NodeFunc) -> Self {
+        self.stubs.insert(node.into(), func);
+        self
+    }
+
+    /// Give up on the run after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl std::fmt::Debug for RuntimeCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut stubs: Vec<_> = self.stubs.keys().collect();
+        stubs.sort();
+        f.debug_struct("RuntimeCheck")
+            .field("stubs", &stubs)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// How [`PregelCore::invoke_sync`] runs the nodes of a superstep
+///
+/// Whatever the backend, the barrier applies the writes of a step together,
+/// so results do not depend on which node finishes first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParallelBackend {
+    /// Run nodes one after another on the calling thread
+    #[default]
+    Sequential,
+    /// Run nodes together on a rayon thread pool of `threads` threads, or on
+    /// rayon's global pool when `threads` is 0
+    ///
+    /// Each node takes the GIL while it runs Python code, so only nodes
+    /// that release it, such as native extensions or I/O, run in parallel.
+    #[cfg(feature = "parallel")]
+    Rayon { threads: usize },
+}
+
+/// Values buffered per channel subscription before a slow subscriber starts
+/// missing updates
+const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// Node run planned for a superstep
+struct NodeTask {
+    node: Node,
+    /// Key of the task in the step's records, see [`task_keys`]
+    key: String,
+    input: Object,
+    cache_key: Option<CacheKey>,
+    /// Recorded output fed back instead of calling the node while replaying
+    replayed: Option<HashMap<String, Object>>,
+}
+
+/// Outcome of a node run: its name, its channel updates and its metrics
+type NodeResult = (String, HostResult<HashMap<String, Object>>, NodeSample);
+
+/// Callback run at each superstep barrier with the step number, the
+/// committed state and its snapshot; an `Err` aborts the run
+pub type BarrierCallback =
+    Arc<dyn Fn(Host<'_>, usize, &GraphState, &StateSnapshot) -> HostResult<()> + Send + Sync>;
+
+/// Channel updates keyed by the node that produced them
+pub type NodeOutputs = HashMap<String, HashMap<String, Object>>;
+
+/// Node outputs of one committed superstep
+#[derive(Debug, Clone)]
+pub struct StepRecord {
+    /// Superstep number, starting at 1
+    pub step: usize,
+    /// Channel updates produced by each task of the step, keyed by node, or
+    /// by `"{node}:send:{index}"` for a task started by a `Send`
+    pub outputs: NodeOutputs,
+}
+
+/// A recorded run, captured with [`PregelCore::enable_history`] and
+/// re-executed with [`PregelCore::replay`]
+#[derive(Debug, Clone)]
+pub struct RunHistory {
+    /// Input of the run; a dict of each entry point's input for a run
+    /// started by [`PregelCore::invoke_entries`]
+    pub input: Object,
+    pub context: Option<Object>,
+    /// Committed supersteps in order
+    pub steps: Vec<StepRecord>,
+    /// Whether the run was started by [`PregelCore::invoke_entries`]
+    pub entries: bool,
+}
 
 /// PregelCore is the main execution engine for LangGraph
 ///
@@ -21,7 +252,56 @@ pub struct PregelCore {
     edges: Vec<Edge>,
     state: GraphState,
     entry_point: Option<String>,
+    /// Further entry nodes started alongside `entry_point`
+    entry_points: Vec<String>,
     recursion_limit: usize,
+    metrics: Option<Metrics>,
+    /// Channels the input is written to (`None` = legacy `__input__` channel)
+    input_channels: Option<Vec<String>>,
+    /// Drop input keys that are not input channels instead of rejecting them
+    ignore_unknown_input: bool,
+    /// Channels returned from invoke (`None` = all checkpointed channels)
+    output_channels: Option<OutputChannels>,
+    /// Results of nodes built `with_cache(true)`
+    cache: Option<Arc<NodeCache>>,
+    /// Declared state fields; writes outside them are rejected
+    schema: Option<StateSchema>,
+    /// Frontier of the step a guard interrupted, run again by [`resume`](Self::resume)
+    interrupted: Option<Vec<Destination>>,
+    /// Record node outputs of each run into `history`
+    record_history: bool,
+    /// History of the last run, when recording
+    history: Option<RunHistory>,
+    /// History whose outputs replace node calls up to the given step
+    replaying: Option<(RunHistory, usize)>,
+    /// Events of the run being traced by [`invoke_with_trace`](Self::invoke_with_trace)
+    trace: Option<Vec<ExecutionEvent>>,
+    /// Senders feeding [`subscribe`](Self::subscribe) streams, by channel
+    subscriptions: HashMap<String, broadcast::Sender<Object>>,
+    /// Receives the state after every committed superstep
+    checkpointer: Option<Arc<dyn Checkpointer>>,
+    /// Rejects malformed input before the first superstep
+    input_validator: Option<InputValidator>,
+    /// Reshapes the raw input into channel writes
+    input_transform: Option<InputTransform>,
+    /// ID of the current or last run
+    run_id: Option<Uuid>,
+    /// ID to give the next run instead of a generated one
+    next_run_id: Option<Uuid>,
+    /// ID of the last snapshot of the current run, the parent of the next
+    checkpoint_id: Option<String>,
+    /// Called with the committed state after every superstep
+    on_barrier: Option<BarrierCallback>,
+    /// Description of each node by name, built by [`compile`](Self::compile)
+    node_info: Vec<NodeInfo>,
+    /// How [`invoke_sync`](Self::invoke_sync) runs the nodes of a superstep
+    parallel_backend: ParallelBackend,
+    /// Time by which every node of the run must have returned, set by
+    /// [`validate_runtime`](Self::validate_runtime)
+    node_deadline: Option<Instant>,
+    /// Dedicated pool for [`ParallelBackend::Rayon`] with a thread count
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 impl PregelCore {
@@ -32,7 +312,97 @@ impl PregelCore {
             edges: Vec::new(),
             state: GraphState::new(),
             entry_point: None,
+            entry_points: Vec::new(),
             recursion_limit: 25, // Default from LangGraph
+            metrics: None,
+            input_channels: None,
+            ignore_unknown_input: false,
+            output_channels: None,
+            cache: None,
+            schema: None,
+            interrupted: None,
+            record_history: false,
+            history: None,
+            replaying: None,
+            trace: None,
+            subscriptions: HashMap::new(),
+            checkpointer: None,
+            input_validator: None,
+            input_transform: None,
+            run_id: None,
+            next_run_id: None,
+            checkpoint_id: None,
+            on_barrier: None,
+            node_info: Vec::new(),
+            parallel_backend: ParallelBackend::default(),
+            node_deadline: None,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        }
+    }
+
+    /// Create an executor whose state channels are created from `schema`
+    ///
+    /// Nodes may only write to schema fields: declared writes are checked
+    /// by [`compile`](Self::compile) and other writes fail at run time with
+    /// [`GraphError::InvalidUpdate`] instead of creating a channel.
+    pub fn with_schema(schema: StateSchema) -> Self {
+        Self {
+            state: schema.create_state(),
+            schema: Some(schema),
+            ..Self::new()
+        }
+    }
+
+    /// Check the nodes against the state schema and attach `checkpointer`
+    ///
+    /// Reports every field a node names in its `output_channels` or
+    /// `write_channels` that the schema does not declare. Without a schema
+    /// there is nothing to check.
+    ///
+    /// With a checkpointer, every run saves a [`StateSnapshot`] after each
+    /// committed superstep, which [`state_history`](Self::state_history) and
+    /// [`invoke_from_snapshot`](Self::invoke_from_snapshot) read back.
+    /// Without one the graph runs stateless and those APIs fail with
+    /// [`GraphError::NoCheckpointer`].
+    pub fn compile(
+        mut self,
+        checkpointer: Option<Arc<dyn Checkpointer>>,
+    ) -> Result<Self, GraphError> {
+        self.checkpointer = checkpointer;
+        self.node_info = self.describe_nodes();
+        let Some(schema) = &self.schema else {
+            return Ok(self);
+        };
+
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+        let mut issues = Vec::new();
+        for name in names {
+            let node = &self.nodes[name];
+            let mut fields: Vec<&String> = node
+                .output_channels
+                .iter()
+                .flatten()
+                .chain(&node.write_channels)
+                .filter(|field| !schema.contains(field))
+                .collect();
+            fields.sort();
+            fields.dedup();
+            issues.extend(
+                fields
+                    .into_iter()
+                    .map(|field| ValidationIssue::UnknownStateField {
+                        node: name.clone(),
+                        field: field.clone(),
+                    }),
+            );
+        }
+
+        if issues.is_empty() {
+            Ok(self)
+        } else {
+            Err(GraphError::ValidationFailed(issues))
         }
     }
 
@@ -41,6 +411,22 @@ impl PregelCore {
         self.nodes.insert(node.name.clone(), node);
     }
 
+    /// Route from `source` with `router`, which returns a branch name or a
+    /// list of them
+    ///
+    /// Names are mapped through `branches`, or taken as node names when it
+    /// is empty. With [`START`] as `source` the router picks the first
+    /// node(s) from the initial state, replacing the entry point; routing to
+    /// [`END`] then finishes the run immediately.
+    pub fn add_conditional_edges(
+        &mut self,
+        source: &str,
+        router: Callable,
+        branches: HashMap<String, String>,
+    ) {
+        self.add_edge(Edge::conditional(source.to_string(), router, branches));
+    }
+
     /// Add an edge to the graph
     pub fn add_edge(&mut self, edge: Edge) {
         self.edges.push(edge);
@@ -56,406 +442,4828 @@ impl PregelCore {
         self.entry_point = Some(node_name);
     }
 
+    /// Add entry points that all run in the first superstep
+    ///
+    /// For graphs merging several independent inputs: together with the
+    /// node given to [`set_entry_point`](Self::set_entry_point), if any, every
+    /// entry starts the run, and
+    /// [`invoke_entries`](Self::invoke_entries) gives each one its own input.
+    /// Each entry must name an existing node when the graph runs.
+    pub fn add_entry_points(&mut self, nodes: &[&str]) {
+        for node in nodes {
+            if !self.entry_points.iter().any(|entry| entry == node) {
+                self.entry_points.push(node.to_string());
+            }
+        }
+    }
+
     /// Set the recursion limit
     pub fn set_recursion_limit(&mut self, limit: usize) {
         self.recursion_limit = limit;
     }
 
-    /// Get a reference to the state
-    pub fn state(&self) -> &GraphState {
-        &self.state
+    /// Declare the channels that accept graph input
+    ///
+    /// Input dict keys are routed to these channels; every one of them must
+    /// be provided unless it already holds a value.
+    pub fn set_input_channels(&mut self, channels: Vec<String>) {
+        self.input_channels = Some(channels);
     }
 
-    /// Get a mutable reference to the state
-    pub fn state_mut(&mut self) -> &mut GraphState {
-        &mut self.state
+    /// Check every input before any node runs
+    ///
+    /// The validator sees the input after it is mapped to input channels, so
+    /// rejecting it leaves the state untouched. Streamed input is checked
+    /// chunk by chunk.
+    pub fn set_input_validator(&mut self, validator: InputValidator) {
+        self.input_validator = Some(validator);
     }
 
-    /// Invoke the graph with the given input
+    /// Reshape every input into channel writes before it is routed
     ///
-    /// This is the main entry point for graph execution.
-    /// It handles:
-    /// 1. Setting up initial state from input
-    /// 2. Determining starting node(s)
-    /// 3. Executing nodes in order
-    /// 4. Following edges (direct or conditional)
-    /// 5. Extracting output from designated channels
-    pub async fn invoke_async(&mut self, py: Python<'_>, input: PyObject) -> PyResult<PyObject> {
-        // Initialize state with input
-        // For now, we'll store the input in a special __input__ channel
-        if !self.state.has_channel("__input__") {
-            self.state
-                .add_channel("__input__".to_string(), Box::new(LastValueChannel::new()));
+    /// Lets the graph take a simple input (say, a user's question as a
+    /// string) while its channels hold something richer. The transform runs
+    /// once per invoke, before the input validator, and must return a dict of
+    /// channel writes, which is then routed like a dict input. Each write to a
+    /// typed channel must match the channel's value type, otherwise the run
+    /// fails with [`GraphError::InvalidInput`]. Streamed input is not
+    /// transformed.
+    pub fn with_input_transform(mut self, transform: InputTransform) -> Self {
+        self.input_transform = Some(transform);
+        self
+    }
+
+    /// Run the nodes of each superstep of [`invoke_sync`](Self::invoke_sync)
+    /// with `backend`
+    ///
+    /// Fails with `RuntimeError` if the thread pool of
+    /// [`ParallelBackend::Rayon`] cannot be built. The async paths always
+    /// run nodes one after another.
+    pub fn with_parallel_backend(mut self, backend: ParallelBackend) -> HostResult<Self> {
+        #[cfg(feature = "parallel")]
+        {
+            self.pool = match backend {
+                ParallelBackend::Rayon { threads } if threads > 0 => Some(Arc::new(
+                    rayon::ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .map_err(|e| {
+                            host::runtime_error(format!("Failed to build thread pool: {}", e))
+                        })?,
+                )),
+                _ => None,
+            };
         }
-        self.state.update_channel(py, "__input__", input)?;
+        self.parallel_backend = backend;
+        Ok(self)
+    }
 
-        // Determine starting node
-        let start_node = self.get_start_node()?;
+    /// Run `callback` each time a superstep commits
+    ///
+    /// The callback gets the step number, the state and the
+    /// [`StateSnapshot`] of that state, after the step's writes are applied
+    /// and after the checkpointer, if any, saved the snapshot. It runs on the
+    /// executing thread before the next step is planned, so it suits
+    /// persistence or side effects that must keep pace with the run (a slow
+    /// callback slows the run). An `Err` aborts the run with that error;
+    /// the step stays committed. Replaces any previously registered callback.
+    pub fn on_barrier(
+        &mut self,
+        callback: impl Fn(Host<'_>, usize, &GraphState, &StateSnapshot) -> HostResult<()>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.on_barrier = Some(Arc::new(callback));
+    }
 
-        // Execute the graph
-        self.execute_from(py, start_node).await?;
+    /// Ignore input keys that are not input channels instead of failing
+    pub fn set_ignore_unknown_input(&mut self, ignore: bool) {
+        self.ignore_unknown_input = ignore;
+    }
 
-        // Extract output
-        // For now, return the state (will be refined when wiring to Python)
-        Ok(py.None())
+    /// Declare the channels returned from invoke
+    pub fn set_output_channels(&mut self, channels: OutputChannels) {
+        self.output_channels = Some(channels);
     }
 
-    /// Synchronous invoke wrapper
-    pub fn invoke(&mut self, py: Python<'_>, input: PyObject) -> PyResult<PyObject> {
-        // Use tokio runtime for async execution
-        let rt = tokio::runtime::Runtime::new().map_err(|e| {
-            pyo3::exceptions::PyRuntimeError::new_err(format!("Failed to create runtime: {}", e))
-        })?;
+    /// Start collecting per-node execution metrics
+    pub fn enable_metrics(&mut self) {
+        self.metrics.get_or_insert_with(Metrics::new);
+    }
 
-        rt.block_on(self.invoke_async(py, input))
+    /// Cache the results of nodes built `with_cache(true)`, bounded by `policy`
+    pub fn enable_cache(&mut self, policy: CachePolicy) {
+        self.cache = Some(Arc::new(NodeCache::new(policy)));
     }
 
-    /// Get the starting node for execution
-    fn get_start_node(&self) -> PyResult<String> {
-        // Check for explicit entry point
-        if let Some(ref entry) = self.entry_point {
-            return Ok(entry.clone());
-        }
+    /// Occupancy of the node cache, `None` if caching is disabled
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
 
-        // Look for Start edges
-        for edge in &self.edges {
-            if let Edge::Start { target } = edge {
-                return Ok(target.clone());
+    /// Snapshot of the collected metrics (empty when metrics are disabled)
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let mut snapshot = self
+            .metrics
+            .as_ref()
+            .map(Metrics::snapshot)
+            .unwrap_or_default();
+        snapshot.cache = self.cache_stats();
+        snapshot.run_id = self.run_id.map(|run_id| run_id.to_string());
+        for (name, metrics) in snapshot.nodes.iter_mut() {
+            if let Some(node) = self.nodes.get(name) {
+                metrics.tags = node.tags.clone();
             }
         }
+        snapshot
+    }
 
-        // If no explicit start, look for nodes with no incoming edges
-        let nodes_with_incoming: HashSet<String> = self
-            .edges
-            .iter()
-            .filter_map(|e| match e {
-                Edge::Direct { target, .. } => Some(target.clone()),
-                Edge::Conditional { branches, .. } => Some(branches.values().next()?.clone()),
-                _ => None,
-            })
-            .collect();
+    /// Use `run_id` for the next run instead of a generated ID
+    ///
+    /// Every run gets an ID, recorded on its tracing spans, metrics and
+    /// checkpointed snapshots, so all activity of one request can be found
+    /// by it. Resuming an interrupted run keeps its ID.
+    pub fn set_run_id(&mut self, run_id: Uuid) {
+        self.next_run_id = Some(run_id);
+    }
 
-        // Find nodes without incoming edges
-        let candidates: Vec<String> = self
-            .nodes
-            .keys()
-            .filter(|name| !nodes_with_incoming.contains(*name))
-            .cloned()
-            .collect();
+    /// ID of the current or last run, `None` before the first run
+    pub fn run_id(&self) -> Option<Uuid> {
+        self.run_id
+    }
 
-        if candidates.is_empty() {
-            Err(pyo3::exceptions::PyValueError::new_err(
-                "No entry point found for graph",
-            ))
-        } else {
-            Ok(candidates[0].clone())
-        }
+    /// Give the run being started its ID
+    fn start_run(&mut self) {
+        self.run_id = Some(self.next_run_id.take().unwrap_or_else(Uuid::new_v4));
+        self.checkpoint_id = None;
     }
 
-    /// Execute the graph starting from a specific node
-    async fn execute_from(&mut self, py: Python<'_>, start_node: String) -> PyResult<()> {
-        let mut current_node = start_node;
-        let mut visited = HashSet::new();
-        let mut iterations = 0;
+    /// Record the input and every node's output of each run
+    ///
+    /// The history of the last run is available from [`history`](Self::history).
+    pub fn enable_history(&mut self) {
+        self.record_history = true;
+    }
 
-        loop {
-            iterations += 1;
-            if iterations > self.recursion_limit {
-                return Err(pyo3::exceptions::PyRecursionError::new_err(format!(
-                    "Recursion limit ({}) exceeded",
-                    self.recursion_limit
-                )));
-            }
+    /// History of the last run, if recording is enabled
+    pub fn history(&self) -> Option<&RunHistory> {
+        self.history.as_ref()
+    }
 
-            // Check if we've hit a cycle (optional - for debugging)
-            if visited.contains(&current_node) {
-                // In some graphs, revisiting is OK (loops), but we rely on iteration limit
-                // For now, we'll allow it but rely on recursion_limit
+    /// Watch the values a single channel takes
+    ///
+    /// The stream yields the channel's value after every update to it, from
+    /// the input or from a superstep's writes, in all later runs, whatever is being
+    /// streamed from the run itself. Each subscriber gets every update; a
+    /// subscriber more than 1024 values behind skips the oldest ones rather
+    /// than holding up execution. Dropping the stream unsubscribes, and
+    /// the stream ends when the executor is dropped.
+    pub fn subscribe(&mut self, channel: &str) -> impl Stream<Item = Object> + Send + 'static {
+        let receiver = self
+            .subscriptions
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(SUBSCRIPTION_CAPACITY).0)
+            .subscribe();
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(value) => return Some((value, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
             }
-            visited.insert(current_node.clone());
-
-            // Execute the current node
-            self.execute_node(py, &current_node).await?;
+        })
+    }
 
-            // Determine next node
-            match self.get_next_node(py, &current_node).await? {
-                Some(next) => current_node = next,
-                None => break, // No next node, we're done
+    /// Write to a channel and pass its new value to the channel's subscribers
+    fn write_channel(
+        &mut self,
+        py: Host<'_>,
+        channel: &str,
+        update: ChannelUpdate,
+    ) -> HostResult<()> {
+        self.state.apply_update(py, channel, update)?;
+        if let Some(sender) = self.subscriptions.get(channel) {
+            let value = self
+                .state
+                .get_value(py, channel)
+                .unwrap_or_else(|| host::none(py));
+            if sender.send(value).is_err() {
+                // Every subscriber dropped its stream
+                self.subscriptions.remove(channel);
             }
         }
-
         Ok(())
     }
 
-    /// Execute a single node
-    async fn execute_node(&mut self, py: Python<'_>, node_name: &str) -> PyResult<()> {
-        // Get the node
-        let node = self
-            .nodes
-            .get(node_name)
-            .ok_or_else(|| {
-                pyo3::exceptions::PyKeyError::new_err(format!("Node '{}' not found", node_name))
-            })?
-            .clone(); // Clone to avoid borrow issues
-
-        // Collect input from channels
-        let channel_values: HashMap<String, PyObject> = node
-            .input_channels
-            .as_ref()
-            .map(|channels| {
-                channels
-                    .iter()
-                    .filter_map(|ch_name| {
-                        self.state
-                            .get_value(py, ch_name)
-                            .map(|val| (ch_name.clone(), val))
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
+    /// Re-run a recorded run, feeding back recorded node outputs for the
+    /// first `until_checkpoint` supersteps instead of calling the nodes
+    ///
+    /// Later supersteps, and any past the end of the history, run live. If
+    /// a replayed step schedules different nodes than were recorded, the
+    /// run fails with [`GraphError::ReplayDiverged`].
+    pub async fn replay_async(
+        &mut self,
+        py: Host<'_>,
+        history: &RunHistory,
+        until_checkpoint: usize,
+    ) -> HostResult<Object> {
+        self.replaying = Some((history.clone(), until_checkpoint));
+        let context = history.context.as_ref().map(|ctx| ctx.clone_ref(py));
+        let result = if history.entries {
+            // Entry runs record their inputs as a dict of entry to input
+            let inputs = host::dict_entries(py, &history.input).and_then(|recorded| {
+                recorded.ok_or_else(|| host::type_error("recorded entry inputs are not a dict"))
+            });
+            match inputs {
+                Ok(inputs) => self.invoke_entries_async(py, inputs, context).await,
+                Err(err) => Err(err),
+            }
+        } else {
+            self.invoke_async(py, history.input.clone_ref(py), context)
+                .await
+        };
+        self.replaying = None;
+        result
+    }
 
-        // Extract input for the node
-        let input = node.extract_input(py, &channel_values)?;
+    /// Synchronous wrapper for [`replay_async`](Self::replay_async)
+    pub fn replay(
+        &mut self,
+        py: Host<'_>,
+        history: &RunHistory,
+        until_checkpoint: usize,
+    ) -> HostResult<Object> {
+        let rt = runtime()?;
 
-        // Execute the node
-        let output = node.execute(py, input)?;
+        rt.block_on(self.replay_async(py, history, until_checkpoint))
+    }
 
-        // Map output to channel updates
-        let updates = node.map_output(py, output)?;
+    /// Get a reference to the state
+    pub fn state(&self) -> &GraphState {
+        &self.state
+    }
 
-        // Apply updates to channels
-        for (channel_name, value) in updates {
-            if self.state.has_channel(&channel_name) {
-                self.state.update_channel(py, &channel_name, value)?;
-            } else {
-                // Auto-create channel if it doesn't exist
-                self.state
-                    .add_channel(channel_name.clone(), Box::new(LastValueChannel::new()));
-                self.state.update_channel(py, &channel_name, value)?;
-            }
-        }
+    /// Get a mutable reference to the state
+    pub fn state_mut(&mut self) -> &mut GraphState {
+        &mut self.state
+    }
 
-        Ok(())
+    /// Invoke the graph with the given input
+    ///
+    /// This is the main entry point for graph execution.
+    /// It handles:
+    /// 1. Setting up initial state from input and run context
+    /// 2. Determining starting node(s)
+    /// 3. Executing nodes in order
+    /// 4. Following edges (direct or conditional)
+    /// 5. Extracting output from designated channels (see [`OutputChannels`];
+    ///    without them, a dict of every checkpointed channel is returned)
+    ///
+    /// `context` is exposed read-only to every node through [`CONTEXT_CHANNEL`]
+    /// and is not checkpointed.
+    pub async fn invoke_async(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<Object> {
+        self.invoke_async_with_cancel(py, input, context, &CancellationToken::new())
+            .await
     }
 
-    /// Determine the next node to execute
-    async fn get_next_node(&self, py: Python<'_>, current_node: &str) -> PyResult<Option<String>> {
-        // Find outgoing edges from current node
-        for edge in &self.edges {
-            if let Some(source) = edge.source() {
-                if source == current_node {
-                    // This edge applies
-                    // For conditional edges, evaluate the condition
-                    // For now, we'll pass the entire state as a dict to the condition
-                    let state_dict = self.create_state_dict(py)?;
-                    return edge.evaluate_condition(py, state_dict);
-                }
-            }
-        }
+    /// Invoke the graph, stopping early once `cancel` is triggered
+    ///
+    /// Cancellation is observed between supersteps and while a superstep's
+    /// nodes are running. The writes of an interrupted superstep are
+    /// discarded, so the state is left at the last committed checkpoint
+    /// (see [`checkpoint`](Self::checkpoint)) and the run fails with
+    /// [`GraphError::Cancelled`]. With a checkpointer, the error's
+    /// `checkpoint_id` names the saved snapshot of that checkpoint, and
+    /// [`invoke_from_checkpoint`](Self::invoke_from_checkpoint) resumes the
+    /// run from it.
+    pub async fn invoke_async_with_cancel(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+        cancel: &CancellationToken,
+    ) -> HostResult<Object> {
+        let frontier = self.begin_run(py, input, context)?;
 
-        // Check for End edges
-        for edge in &self.edges {
-            if let Edge::End { source } = edge {
-                if source == current_node {
-                    return Ok(None);
-                }
-            }
-        }
+        // Execute the graph
+        self.execute_frontier(py, frontier, 0, cancel).await?;
 
-        // No outgoing edges found - we're done
-        Ok(None)
+        self.read_output(py)
     }
 
-    /// Create a dictionary representation of the current state
-    fn create_state_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
-        let dict = pyo3::types::PyDict::new(py);
+    /// Invoke the graph on the current thread, without an async runtime
+    ///
+    /// Runs the same supersteps as [`invoke_async`](Self::invoke_async) and
+    /// returns the same output: both plan a step and apply its writes at the
+    /// barrier the same way, and differ only in how the step's nodes run.
+    /// The async path runs them together on tokio's blocking pool; this one
+    /// uses the [`ParallelBackend`] set by
+    /// [`with_parallel_backend`](Self::with_parallel_backend), running them
+    /// one after another by default.
+    ///
+    /// Use it from CLI tools, tests and other synchronous code, including
+    /// code already running inside a tokio runtime, where the blocking
+    /// wrappers such as [`invoke`](Self::invoke) cannot be used. Use
+    /// [`invoke_async`](Self::invoke_async) when the caller is async or the
+    /// run must be cancellable.
+    pub fn invoke_sync(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<Object> {
+        let mut frontier = self.begin_run(py, input, context)?;
 
-        for channel_name in self.state.channel_names() {
-            if let Some(value) = self.state.get_value(py, &channel_name) {
-                dict.set_item(channel_name, value)?;
-            }
-        }
+        let mut step = 0;
+        while !frontier.is_empty() {
+            step += 1;
+            self.check_recursion_limit(step)?;
 
-        Ok(dict.to_object(py))
-    }
+            let span = tracing::info_span!(
+                "superstep",
+                run_id = self.run_id.map(tracing::field::display),
+                step,
+                triggered = frontier.len()
+            );
+            frontier = span.in_scope(|| self.execute_superstep_sync(py, &frontier, step, &span))?;
+            self.save_snapshot(py, step, &frontier)?;
+        }
 
-    /// Get checkpoint of current state
-    pub fn checkpoint(&self, py: Python<'_>) -> PyResult<HashMap<String, PyObject>> {
-        self.state.checkpoint(py)
+        self.read_output(py)
     }
 
-    /// Restore from checkpoint
-    pub fn from_checkpoint(
+    /// Set up a run: record it, install its context, write the input and
+    /// return the starting frontier
+    fn begin_run(
         &mut self,
-        py: Python<'_>,
-        checkpoint: HashMap<String, PyObject>,
-    ) -> PyResult<()> {
-        self.state.from_checkpoint(py, checkpoint)
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<Vec<Destination>> {
+        self.setup_run(py, &input, false, context);
+        self.apply_input(py, input)?;
+
+        // Determine starting node(s)
+        self.start_frontier(py)
     }
-}
 
-impl Default for PregelCore {
-    fn default() -> Self {
-        Self::new()
+    /// Start a run whose input, as recorded in its history, is `input`:
+    /// give it an ID, install its context and seed channel defaults
+    fn setup_run(&mut self, py: Host<'_>, input: &Object, entries: bool, context: Option<Object>) {
+        self.start_run();
+        self.history = self.record_history.then(|| RunHistory {
+            input: input.clone_ref(py),
+            context: context.as_ref().map(|ctx| ctx.clone_ref(py)),
+            steps: Vec::new(),
+            entries,
+        });
+
+        // Run context replaces any context from a previous invocation
+        self.state.add_channel(
+            CONTEXT_CHANNEL.to_string(),
+            Box::new(ContextChannel::new(context)),
+        );
+
+        // Seed channels that start with a default, before the input is written
+        self.state.apply_defaults(py);
+        self.interrupted = None;
     }
-}
 
-impl std::fmt::Debug for PregelCore {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PregelCore")
-            .field("nodes", &self.nodes.keys().collect::<Vec<_>>())
-            .field("edges", &self.edges)
-            .field("entry_point", &self.entry_point)
-            .field("channels", &self.state.channel_names())
-            .finish()
+    /// Blocking wrapper for [`invoke_async`](Self::invoke_async) that drives
+    /// it on a new tokio runtime; see [`invoke_sync`](Self::invoke_sync) to
+    /// run without one
+    pub fn invoke(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<Object> {
+        self.invoke_with_cancel(py, input, context, &CancellationToken::new())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Synchronous wrapper for [`invoke_async_with_cancel`](Self::invoke_async_with_cancel)
+    pub fn invoke_with_cancel(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+        cancel: &CancellationToken,
+    ) -> HostResult<Object> {
+        // Use tokio runtime for async execution
+        let rt = runtime()?;
 
-    #[tokio::test]
-    async fn test_pregel_core_creation() {
-        let executor = PregelCore::new();
-        assert_eq!(executor.nodes.len(), 0);
-        assert_eq!(executor.edges.len(), 0);
-        assert!(executor.entry_point.is_none());
+        rt.block_on(self.invoke_async_with_cancel(py, input, context, cancel))
     }
 
-    #[test]
+    /// Invoke the graph and record the run as a [`Trace`]
+    ///
+    /// Runs like [`invoke_async`](Self::invoke_async) while recording every
+    /// node input and output, channel write and checkpoint in canonical
+    /// order (see [`trace`](super::trace)). The input, the context and every
+    /// recorded value must be JSON data; anything else fails the run with
+    /// `TypeError`. Pass [`Trace::to_history`] to [`replay`](Self::replay)
+    /// to reproduce the run.
+    pub async fn invoke_with_trace_async(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<(Object, Trace)> {
+        let traced_input = trace_value(py, &input, || "the input".to_string())?;
+        let traced_context = context
+            .as_ref()
+            .map(|ctx| trace_value(py, ctx, || "the context".to_string()))
+            .transpose()?;
+
+        self.trace = Some(Vec::new());
+        let result = self.invoke_async(py, input, context).await;
+        let events = self.trace.take().unwrap_or_default();
+        let output = result?;
+        Ok((
+            output,
+            Trace {
+                input: traced_input,
+                context: traced_context,
+                events,
+            },
+        ))
+    }
+
+    /// Synchronous wrapper for [`invoke_with_trace_async`](Self::invoke_with_trace_async)
+    pub fn invoke_with_trace(
+        &mut self,
+        py: Host<'_>,
+        input: Object,
+        context: Option<Object>,
+    ) -> HostResult<(Object, Trace)> {
+        let rt = runtime()?;
+
+        rt.block_on(self.invoke_with_trace_async(py, input, context))
+    }
+
+    /// Invoke the graph on input that arrives as a stream of chunks
+    ///
+    /// Each chunk is written to the graph's single input channel as it
+    /// arrives, and the entry node(s) run on the input received so far, so
+    /// they can start before the stream ends. Successors are scheduled from
+    /// the step that ran on the last chunk: once the stream is exhausted the
+    /// rest of the graph runs to completion as in
+    /// [`invoke_async`](Self::invoke_async). Streamed runs are not recorded
+    /// in [`history`](Self::history).
+    ///
+    /// Every chunk takes one superstep, and steps are numbered across the
+    /// whole run: the steps after the stream continue from the last chunk's,
+    /// and all of them count against the recursion limit.
+    ///
+    /// Only channels that accumulate writes can take streamed input:
+    /// - [`TopicChannel`](super::TopicChannel) with `accumulate` set, holding
+    ///   every chunk
+    /// - [`SlidingWindowChannel`](super::SlidingWindowChannel), holding the
+    ///   most recent chunks
+    /// - [`EmaChannel`](super::EmaChannel), averaging numeric chunks
+    /// - [`DedupChannel`](super::DedupChannel), holding the distinct chunks
+    ///
+    /// A [`LastValueChannel`], an [`ObjectChannel`](super::ObjectChannel) or a
+    /// replacing topic would only ever hold the latest chunk, so they fail
+    /// with [`GraphError::StreamingInputUnsupported`] before the stream is
+    /// polled.
+    pub async fn invoke_stream_input_async<S>(
+        &mut self,
+        py: Host<'_>,
+        mut input_stream: S,
+        context: Option<Object>,
+    ) -> HostResult<Object>
+    where
+        S: Stream<Item = Object> + Unpin,
+    {
+        let channel = self.streaming_input_channel()?;
+        self.start_run();
+        self.history = None;
+        self.state.add_channel(
+            CONTEXT_CHANNEL.to_string(),
+            Box::new(ContextChannel::new(context)),
+        );
+        self.state.apply_defaults(py);
+        self.interrupted = None;
+
+        let cancel = CancellationToken::new();
+        let mut step = 0;
+        let mut frontier = Vec::new();
+        while let Some(chunk) = input_stream.next().await {
+            self.check_recursion_limit(step + 1)?;
+            self.validate_input(py, &[(channel.clone(), chunk.clone_ref(py))])?;
+            self.write_channel(py, &channel, ChannelUpdate::single(chunk))?;
+
+            let entry = self.start_frontier(py)?;
+            frontier = self
+                .run_superstep(py, &entry, step + 1, &cancel)
+                .await?
+                .ok_or_else(|| GraphError::Cancelled {
+                    step,
+                    checkpoint_id: self.saved_checkpoint_id(),
+                })?;
+            step += 1;
+            self.save_snapshot(py, step, &frontier)?;
+        }
+        if step == 0 {
+            return Err(GraphError::MissingInput(channel).into());
+        }
+
+        self.execute_frontier(py, frontier, step, &cancel).await?;
+        self.read_output(py)
+    }
+
+    /// Synchronous wrapper for [`invoke_stream_input_async`](Self::invoke_stream_input_async)
+    pub fn invoke_stream_input<S>(
+        &mut self,
+        py: Host<'_>,
+        input_stream: S,
+        context: Option<Object>,
+    ) -> HostResult<Object>
+    where
+        S: Stream<Item = Object> + Unpin,
+    {
+        let rt = runtime()?;
+
+        rt.block_on(self.invoke_stream_input_async(py, input_stream, context))
+    }
+
+    /// Invoke the graph with a separate input for each entry point
+    ///
+    /// Each input is written to the input channels of its entry node: a
+    /// node reading a single channel takes the input as-is, a node reading
+    /// several takes a dict keyed by channel. The input validator sees the
+    /// writes of all entries together. Only the entries named in `inputs`
+    /// run in the first superstep, side by side; every name must be an entry
+    /// point (see [`add_entry_points`](Self::add_entry_points)) whose node
+    /// declares its input channels. The run is set up as by
+    /// [`invoke_async`](Self::invoke_async): it gets a run ID, its input
+    /// writes are traced, and its history records `inputs` as a dict, which
+    /// [`replay`](Self::replay) starts the same way.
+    pub async fn invoke_entries_async(
+        &mut self,
+        py: Host<'_>,
+        inputs: Vec<(String, Object)>,
+        context: Option<Object>,
+    ) -> HostResult<Object> {
+        let entries = self.start_nodes()?;
+        let mut recorded = Vec::with_capacity(inputs.len());
+        let mut frontier = Vec::with_capacity(inputs.len());
+        let mut writes = Vec::new();
+        for (entry, input) in inputs {
+            recorded.push((entry.clone(), input.clone_ref(py)));
+            if !entries.contains(&entry) || frontier.contains(&entry) {
+                return Err(GraphError::InvalidInput(format!(
+                    "'{}' is not an entry point, or is given more than one input",
+                    entry
+                ))
+                .into());
+            }
+            match self.nodes[&entry].input_channels.as_deref() {
+                Some([channel]) => writes.push((channel.clone(), input)),
+                Some(channels) if !channels.is_empty() => {
+                    if host::kind(py, &input) != Kind::Dict {
+                        return Err(GraphError::InvalidInput(format!(
+                            "input of entry '{}' must be a dict keyed by its input channels",
+                            entry
+                        ))
+                        .into());
+                    }
+                    for channel in channels {
+                        let value = host::dict_get(py, &input, channel)
+                            .ok_or_else(|| GraphError::MissingInput(channel.clone()))?;
+                        writes.push((channel.clone(), value));
+                    }
+                }
+                _ => {
+                    return Err(GraphError::InvalidInput(format!(
+                        "entry '{}' declares no input channels",
+                        entry
+                    ))
+                    .into())
+                }
+            }
+            frontier.push(entry);
+        }
+        self.validate_input(py, &writes)?;
+
+        let recorded = host::dict(py, recorded)?;
+        self.setup_run(py, &recorded, true, context);
+        self.write_input(py, writes)?;
+
+        let frontier = frontier.into_iter().map(Destination::Node).collect();
+        self.execute_frontier(py, frontier, 0, &CancellationToken::new())
+            .await?;
+        self.read_output(py)
+    }
+
+    /// Synchronous wrapper for [`invoke_entries_async`](Self::invoke_entries_async)
+    pub fn invoke_entries(
+        &mut self,
+        py: Host<'_>,
+        inputs: Vec<(String, Object)>,
+        context: Option<Object>,
+    ) -> HostResult<Object> {
+        let rt = runtime()?;
+
+        rt.block_on(self.invoke_entries_async(py, inputs, context))
+    }
+
+    /// The input channel chunks are streamed into
+    fn streaming_input_channel(&self) -> HostResult<String> {
+        let channel = match self.input_channels.as_deref() {
+            Some([channel]) => channel.clone(),
+            _ => {
+                return Err(host::type_error(
+                    "Streamed input needs exactly one input channel",
+                ))
+            }
+        };
+        let accumulates = self
+            .state
+            .get_channel(&channel)
+            .is_some_and(|ch| ch.accumulates());
+        if !accumulates {
+            return Err(GraphError::StreamingInputUnsupported(channel).into());
+        }
+        Ok(channel)
+    }
+
+    /// Write the invoke input to the input channels
+    ///
+    /// A dict input is routed key by key, each value going through the
+    /// channel's own update semantics. Keys that are not input channels fail
+    /// with [`GraphError::InvalidUpdate`] (or are dropped when
+    /// `ignore_unknown_input` is set). A non-dict input is accepted when there
+    /// is exactly one input channel. Without declared input channels the input
+    /// is stored as-is in `__input__`.
+    fn apply_input(&mut self, py: Host<'_>, input: Object) -> HostResult<()> {
+        let writes = self.input_writes(py, input)?;
+        self.validate_input(py, &writes)?;
+        self.write_input(py, writes)
+    }
+
+    /// Write validated input values to their channels as step 0
+    fn write_input(&mut self, py: Host<'_>, writes: Vec<(String, Object)>) -> HostResult<()> {
+        for (channel, value) in writes {
+            if !self.state.has_channel(&channel) {
+                self.state
+                    .add_channel(channel.clone(), Box::new(LastValueChannel::new()));
+            }
+            self.write_channel(py, &channel, ChannelUpdate::single(value))?;
+            self.trace_write(py, 0, &channel, vec![START.to_string()])?;
+        }
+        Ok(())
+    }
+
+    /// Record a channel write of `step` in the trace, if tracing
+    fn trace_write(
+        &mut self,
+        py: Host<'_>,
+        step: usize,
+        channel: &str,
+        writers: Vec<String>,
+    ) -> HostResult<()> {
+        if self.trace.is_none() {
+            return Ok(());
+        }
+        let value = self
+            .state
+            .get_value(py, channel)
+            .unwrap_or_else(|| host::none(py));
+        let value = trace_value(py, &value, || format!("the value of channel '{}'", channel))?;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(ExecutionEvent::ChannelWrite {
+                step,
+                channel: channel.to_string(),
+                writers,
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    /// Record the node inputs of a superstep in the trace, if tracing
+    ///
+    /// Inputs are recorded under their task keys, see [`task_keys`].
+    fn trace_inputs(&mut self, py: Host<'_>, step: usize, tasks: &[NodeTask]) -> HostResult<()> {
+        let Some(trace) = self.trace.as_mut() else {
+            return Ok(());
+        };
+        let mut tasks: Vec<&NodeTask> = tasks.iter().collect();
+        tasks.sort_by(|a, b| a.key.cmp(&b.key));
+        for task in tasks {
+            let input = trace_value(py, &task.input, || {
+                format!("the input of node '{}'", task.node.name)
+            })?;
+            trace.push(ExecutionEvent::NodeInput {
+                step,
+                node: task.key.clone(),
+                input,
+            });
+        }
+        Ok(())
+    }
+
+    /// Run the input validator, if any, on the input's channel writes
+    fn validate_input(&self, py: Host<'_>, writes: &[(String, Object)]) -> HostResult<()> {
+        let Some(validator) = &self.input_validator else {
+            return Ok(());
+        };
+        let writes = host::dict(
+            py,
+            writes
+                .iter()
+                .map(|(channel, value)| (channel.clone(), value.clone_ref(py))),
+        )?;
+        Ok(validator.check(py, &writes)?)
+    }
+
+    /// Run the input transform, if any, and map the result to channel writes
+    fn input_writes(&self, py: Host<'_>, input: Object) -> HostResult<Vec<(String, Object)>> {
+        let Some(transform) = &self.input_transform else {
+            return self.route_input(py, input);
+        };
+        let transformed = transform.apply(py, input)?;
+        if host::kind(py, &transformed) != Kind::Dict {
+            return Err(host::type_error(format!(
+                "Input transform must return a dict of channel writes, got {}",
+                host::type_name(py, &transformed)
+            )));
+        }
+
+        let writes = self.route_input(py, transformed)?;
+        for (channel, value) in &writes {
+            let Some(expected) = self.state.get_channel(channel).map(|ch| ch.update_type()) else {
+                continue;
+            };
+            if !expected.matches(py, value) {
+                return Err(GraphError::InvalidInput(format!(
+                    "input transform wrote {} to channel '{}', which takes {} values",
+                    host::type_name(py, value),
+                    channel,
+                    expected.as_str()
+                ))
+                .into());
+            }
+        }
+        Ok(writes)
+    }
+
+    /// Map the invoke input to `(channel, value)` writes without applying them
+    fn route_input(&self, py: Host<'_>, input: Object) -> HostResult<Vec<(String, Object)>> {
+        let Some(input_channels) = &self.input_channels else {
+            return Ok(vec![("__input__".to_string(), input)]);
+        };
+
+        let mut values: Vec<(String, Object)> = Vec::new();
+        match host::dict_entries(py, &input)? {
+            Some(entries) => {
+                for (channel, value) in entries {
+                    if input_channels.contains(&channel) {
+                        values.push((channel, value));
+                    } else if !self.ignore_unknown_input {
+                        return Err(GraphError::InvalidUpdate {
+                            channel,
+                            writers: vec![START.to_string()],
+                        }
+                        .into());
+                    }
+                }
+            }
+            None => match input_channels.as_slice() {
+                [channel] => values.push((channel.clone(), input)),
+                _ => {
+                    return Err(host::type_error(
+                        "Input must be a dict when the graph has several input channels",
+                    ))
+                }
+            },
+        }
+
+        for channel in input_channels {
+            let provided = values.iter().any(|(name, _)| name == channel);
+            let available = self
+                .state
+                .get_channel(channel)
+                .is_some_and(|ch| ch.is_available());
+            if !provided && !available {
+                return Err(GraphError::MissingInput(channel.clone()).into());
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Report what invoking with `input` would run, without running anything
+    ///
+    /// The input is validated as in [`invoke`](Self::invoke). Starting from the
+    /// entry node, edges are followed statically: a conditional edge
+    /// contributes all of its branches, so the plan is an upper bound on what
+    /// can execute. No node or condition function is called and the state is
+    /// left untouched.
+    pub fn plan(&self, py: Host<'_>, input: Object) -> HostResult<ExecutionPlan> {
+        let mut input_channels: Vec<String> = self
+            .input_writes(py, input)?
+            .into_iter()
+            .map(|(channel, _)| channel)
+            .collect();
+        input_channels.sort();
+
+        let first = match self.start_router() {
+            Some(Edge::Conditional { branches, .. }) => {
+                let mut targets: Vec<String> = branches
+                    .values()
+                    .filter(|target| *target != END)
+                    .cloned()
+                    .collect();
+                targets.sort();
+                targets.dedup();
+                targets
+            }
+            _ => {
+                let mut entries = self.start_nodes()?;
+                entries.sort();
+                entries
+            }
+        };
+        let mut reached: HashSet<String> = first.iter().cloned().collect();
+        // A router that can only pick END runs nothing
+        let mut steps = if first.is_empty() {
+            Vec::new()
+        } else {
+            vec![first]
+        };
+        while let Some(frontier) = steps.last() {
+            let mut next: Vec<String> = frontier
+                .iter()
+                .flat_map(|node| self.static_successors(node))
+                .filter(|node| self.nodes.contains_key(*node) && !reached.contains(*node))
+                .map(String::from)
+                .collect();
+            next.sort();
+            next.dedup();
+            if next.is_empty() {
+                break;
+            }
+            reached.extend(next.iter().cloned());
+            steps.push(next);
+        }
+
+        let mut unreachable: Vec<String> = self
+            .nodes
+            .keys()
+            .filter(|node| !reached.contains(*node))
+            .cloned()
+            .collect();
+        unreachable.sort();
+
+        Ok(ExecutionPlan {
+            input_channels,
+            steps,
+            unreachable,
+        })
+    }
+
+    /// Every node an edge from `node` may lead to
+    fn static_successors<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a str> {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.source() == Some(node))
+            .flat_map(|edge| match edge {
+                Edge::Direct { target, .. } | Edge::WhenAvailable { target, .. } => {
+                    vec![target.as_str()]
+                }
+                Edge::Conditional { branches, .. } => {
+                    branches.values().map(String::as_str).collect()
+                }
+                Edge::Start { .. } | Edge::End { .. } => Vec::new(),
+            })
+    }
+
+    /// The conditional edge routing from [`START`], if any
+    fn start_router(&self) -> Option<&Edge> {
+        self.edges
+            .iter()
+            .find(|edge| matches!(edge, Edge::Conditional { source, .. } if source == START))
+    }
+
+    /// Tasks of the first superstep
+    ///
+    /// A conditional edge from [`START`] is called with the initial state and
+    /// may pick several nodes or `Send`s, or none by routing to [`END`];
+    /// otherwise the start nodes are used.
+    fn start_frontier(&self, py: Host<'_>) -> HostResult<Vec<Destination>> {
+        match self.start_router() {
+            Some(router) => router.route(py, self.create_state_dict(py)?),
+            None => Ok(self
+                .start_nodes()?
+                .into_iter()
+                .map(Destination::Node)
+                .collect()),
+        }
+    }
+
+    /// The entry point followed by the added entry points, or the single
+    /// start node when no entry points were added
+    fn start_nodes(&self) -> HostResult<Vec<String>> {
+        if self.entry_points.is_empty() {
+            return Ok(vec![self.get_start_node()?]);
+        }
+        let mut entries: Vec<String> = self.entry_point.iter().cloned().collect();
+        for entry in &self.entry_points {
+            if !entries.contains(entry) {
+                entries.push(entry.clone());
+            }
+        }
+        if let Some(unknown) = entries
+            .iter()
+            .find(|entry| !self.nodes.contains_key(*entry))
+        {
+            return Err(GraphError::UnknownNode(unknown.clone()).into());
+        }
+        Ok(entries)
+    }
+
+    /// Get the starting node for execution
+    fn get_start_node(&self) -> HostResult<String> {
+        // Check for explicit entry point
+        if let Some(ref entry) = self.entry_point {
+            return Ok(entry.clone());
+        }
+
+        // Look for Start edges
+        for edge in &self.edges {
+            if let Edge::Start { target } = edge {
+                return Ok(target.clone());
+            }
+        }
+
+        // If no explicit start, look for nodes with no incoming edges
+        let nodes_with_incoming: HashSet<String> = self
+            .edges
+            .iter()
+            .filter_map(|e| match e {
+                Edge::Direct { target, .. } | Edge::WhenAvailable { target, .. } => {
+                    Some(target.clone())
+                }
+                Edge::Conditional { branches, .. } => Some(branches.values().next()?.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // Find nodes without incoming edges
+        let candidates: Vec<String> = self
+            .nodes
+            .keys()
+            .filter(|name| !nodes_with_incoming.contains(*name))
+            .cloned()
+            .collect();
+
+        if candidates.is_empty() {
+            Err(host::value_error("No entry point found for graph"))
+        } else {
+            Ok(candidates[0].clone())
+        }
+    }
+
+    /// Whether a guard interrupted the last run, so it can be resumed
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.is_some()
+    }
+
+    /// Continue a run interrupted by a guard
+    ///
+    /// The interrupted step runs again against the current state, so any
+    /// changes made through [`state_mut`](Self::state_mut) are seen by the
+    /// guard. Fails if there is no interrupted run.
+    pub async fn resume_async(&mut self, py: Host<'_>) -> HostResult<Object> {
+        let frontier = self
+            .interrupted
+            .take()
+            .ok_or_else(|| host::runtime_error("No interrupted run to resume"))?;
+        self.execute_frontier(py, frontier, 0, &CancellationToken::new())
+            .await?;
+        self.read_output(py)
+    }
+
+    /// Synchronous wrapper for [`resume_async`](Self::resume_async)
+    pub fn resume(&mut self, py: Host<'_>) -> HostResult<Object> {
+        let rt = runtime()?;
+
+        rt.block_on(self.resume_async(py))
+    }
+
+    /// The graph's nodes, sorted by name, as of [`compile`](Self::compile)
+    pub fn nodes(&self) -> Vec<&NodeInfo> {
+        self.node_info.iter().collect()
+    }
+
+    /// The graph's edges, in the order they were added
+    pub fn edges(&self) -> Vec<EdgeInfo> {
+        self.edges
+            .iter()
+            .map(|edge| match edge {
+                Edge::Direct { source, target } => EdgeInfo {
+                    source: source.clone(),
+                    targets: vec![target.clone()],
+                    kind: EdgeKind::Direct,
+                },
+                Edge::Conditional {
+                    source, branches, ..
+                } => EdgeInfo {
+                    source: source.clone(),
+                    targets: branches
+                        .values()
+                        .cloned()
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect(),
+                    kind: EdgeKind::Conditional,
+                },
+                Edge::WhenAvailable {
+                    source,
+                    target,
+                    channel,
+                } => EdgeInfo {
+                    source: source.clone(),
+                    targets: vec![target.clone()],
+                    kind: EdgeKind::WhenAvailable {
+                        channel: channel.clone(),
+                    },
+                },
+                Edge::Start { target } => EdgeInfo {
+                    source: START.to_string(),
+                    targets: vec![target.clone()],
+                    kind: EdgeKind::Direct,
+                },
+                Edge::End { source } => EdgeInfo {
+                    source: source.clone(),
+                    targets: vec![END.to_string()],
+                    kind: EdgeKind::Direct,
+                },
+            })
+            .collect()
+    }
+
+    /// The graph's state channels, sorted by name; internal channels are
+    /// left out
+    pub fn channels(&self) -> Vec<ChannelInfo> {
+        let mut names = self.state.channel_names();
+        names.sort();
+        names
+            .into_iter()
+            .filter(|name| name != CONTEXT_CHANNEL && !name.starts_with("__"))
+            .filter_map(|name| {
+                let channel = self.state.get_channel(&name)?;
+                Some(ChannelInfo {
+                    value_type: channel.update_type().as_str(),
+                    reducer: channel.merges_writes(),
+                    accumulates: channel.accumulates(),
+                    checkpointed: channel.is_checkpointed(),
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    /// Nodes that may run after `node`, in the order of its edges
+    ///
+    /// Routers without branches pick nodes only known at run time, so they
+    /// contribute none; neither does [`END`].
+    pub fn successors(&self, node: &str) -> Vec<Destination> {
+        let mut targets: Vec<String> = Vec::new();
+        for edge in self.edges() {
+            if edge.source != node {
+                continue;
+            }
+            for target in edge.targets {
+                if target != END && !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        targets.into_iter().map(Destination::Node).collect()
+    }
+
+    /// Describe every node, sorted by name
+    fn describe_nodes(&self) -> Vec<NodeInfo> {
+        let edges = self.edges();
+        let mut entries: HashSet<&str> = self
+            .entry_point
+            .iter()
+            .chain(&self.entry_points)
+            .map(String::as_str)
+            .collect();
+        for edge in edges.iter().filter(|edge| edge.source == START) {
+            entries.extend(edge.targets.iter().map(String::as_str));
+        }
+
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let node = &self.nodes[name];
+                let mut outgoing = edges.iter().filter(|edge| edge.source == *name).peekable();
+                let is_finish = outgoing.peek().is_none()
+                    || outgoing.any(|edge| edge.targets.iter().any(|target| target == END));
+                NodeInfo {
+                    name: name.clone(),
+                    tags: node.tags.clone(),
+                    read_channels: node.readable_input_channels(),
+                    write_channels: if node.write_channels.is_empty() {
+                        node.output_channels.clone()
+                    } else {
+                        Some(node.write_channels.clone())
+                    },
+                    is_entry: entries.contains(name.as_str()),
+                    is_finish,
+                }
+            })
+            .collect()
+    }
+
+    /// JSON Schema (draft 2020-12) describing the graph's state
+    ///
+    /// Each user-visible channel becomes a property carrying its value type,
+    /// its default when one is set and an `x-reducer` flag telling whether
+    /// concurrent writes are merged. Internal channels are left out.
+    pub fn state_json_schema(&self, py: Host<'_>) -> Value {
+        let mut names = self.state.channel_names();
+        names.sort();
+        let properties: Map<String, Value> = names
+            .into_iter()
+            .filter(|name| name != CONTEXT_CHANNEL && !name.starts_with("__"))
+            .filter_map(|name| {
+                let schema = self.state.get_channel(&name)?.json_schema(py);
+                Some((name, schema))
+            })
+            .collect();
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": properties,
+        })
+    }
+
+    /// JSON Schema of each checkpointed channel, as recorded in snapshots
+    ///
+    /// Defaults are left out, so changing one does not make existing
+    /// checkpoints incompatible.
+    fn channel_schema(&self, py: Host<'_>) -> BTreeMap<String, Value> {
+        self.state
+            .channel_names()
+            .into_iter()
+            .filter_map(|name| {
+                let channel = self.state.get_channel(&name)?;
+                channel
+                    .is_checkpointed()
+                    .then(|| (name, schema_without_default(channel.json_schema(py))))
+            })
+            .collect()
+    }
+
+    /// Replace the implementations of existing nodes, keeping the state,
+    /// the checkpointer and any interrupted run
+    ///
+    /// Meant for iterating on node logic during development: swap the nodes
+    /// and [`resume`](Self::resume) or
+    /// [`invoke_from_snapshot`](Self::invoke_from_snapshot) to continue a run
+    /// against the new code instead of starting over. Each replacement must
+    /// name an existing node and declare the same input, output, read and
+    /// write channels. With a checkpointer, the channels recorded in its
+    /// latest snapshot must also match the graph's in type and reducer;
+    /// channels the graph creates on first write are compared as the
+    /// last-value channels they will be. Any mismatch fails with
+    /// [`GraphError::IncompatibleUpdate`] listing all of them.
+    ///
+    /// The node cache is cleared, since its entries came from the old code.
+    pub fn with_updated_nodes(
+        mut self,
+        py: Host<'_>,
+        nodes: impl IntoIterator<Item = Node>,
+    ) -> HostResult<Self> {
+        let nodes: Vec<Node> = nodes.into_iter().collect();
+        let mut problems = Vec::new();
+        for node in &nodes {
+            let Some(current) = self.nodes.get(&node.name) else {
+                problems.push(format!("node '{}' does not exist", node.name));
+                continue;
+            };
+            let declared = [
+                ("input", &current.input_channels, &node.input_channels),
+                ("output", &current.output_channels, &node.output_channels),
+            ]
+            .into_iter()
+            .map(|(kind, old, new)| (kind, sorted(old.as_deref()), sorted(new.as_deref())))
+            .chain([
+                (
+                    "read",
+                    sorted(Some(&current.read_channels)),
+                    sorted(Some(&node.read_channels)),
+                ),
+                (
+                    "write",
+                    sorted(Some(&current.write_channels)),
+                    sorted(Some(&node.write_channels)),
+                ),
+            ]);
+            for (kind, old, new) in declared {
+                if old != new {
+                    problems.push(format!(
+                        "node '{}' changes its {} channels from {:?} to {:?}",
+                        node.name, kind, old, new
+                    ));
+                }
+            }
+        }
+
+        if let Some(checkpointer) = &self.checkpointer {
+            if let Some(latest) = checkpointer.list(py)?.pop() {
+                let current = self.channel_schema(py);
+                let created = schema_without_default(LastValueChannel::new().json_schema(py));
+                for (name, recorded) in &latest.schema {
+                    let schema = match current.get(name) {
+                        Some(schema) => schema,
+                        None if self.state.has_channel(name) => {
+                            problems.push(format!("channel '{}' is no longer checkpointed", name));
+                            continue;
+                        }
+                        None => &created,
+                    };
+                    if schema != recorded {
+                        problems.push(format!(
+                            "channel '{}' was checkpointed as {} but is now {}",
+                            name, recorded, schema
+                        ));
+                    }
+                }
+                problems.extend(
+                    current
+                        .keys()
+                        .filter(|name| !latest.schema.contains_key(*name))
+                        .map(|name| format!("channel '{}' is missing from the checkpoint", name)),
+                );
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(GraphError::IncompatibleUpdate(problems).into());
+        }
+        for node in nodes {
+            self.nodes.insert(node.name.clone(), node);
+        }
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+        Ok(self)
+    }
+
+    /// Smoke-test the graph by running it on `sample_input` in a sandbox
+    ///
+    /// Unlike [`compile`](Self::compile), which only checks the topology,
+    /// this runs the nodes and routers on real data, so it catches routing
+    /// bugs that only show with actual values. The run uses a copy of the
+    /// graph with every channel empty: the executor's own state,
+    /// checkpointer, cache, metrics, history and subscribers are left
+    /// untouched. Nodes named in `check.stubs` run the stub instead of
+    /// their own function, and every stub must name an existing node.
+    ///
+    /// Returns the names of the nodes that ran. The check fails with the
+    /// run's error, or with [`GraphError::RuntimeCheckTimeout`] once
+    /// `check.timeout` has passed. The timeout is a deadline for every node
+    /// as well as for the run: a node that has not returned by then is
+    /// abandoned, and keeps running in the background with its result
+    /// discarded, since Python code can't be interrupted. On wasm32, which
+    /// has no clock, the timeout is not enforced.
+    pub fn validate_runtime(
+        &self,
+        py: Host<'_>,
+        sample_input: Object,
+        check: &RuntimeCheck,
+    ) -> HostResult<BTreeSet<String>> {
+        let timeout = check.timeout.filter(|_| !cfg!(target_arch = "wasm32"));
+        let mut nodes = self.nodes.clone();
+        for (name, stub) in &check.stubs {
+            let node = nodes
+                .get_mut(name)
+                .ok_or_else(|| GraphError::UnknownNode(name.clone()))?;
+            node.func = stub.clone();
+        }
+        let mut sandbox = PregelCore {
+            nodes,
+            edges: self.edges.clone(),
+            state: self.state.empty_copy(py),
+            entry_point: self.entry_point.clone(),
+            entry_points: self.entry_points.clone(),
+            recursion_limit: self.recursion_limit,
+            input_channels: self.input_channels.clone(),
+            ignore_unknown_input: self.ignore_unknown_input,
+            output_channels: self.output_channels.clone(),
+            schema: self.schema.clone(),
+            record_history: true,
+            input_validator: self.input_validator.clone(),
+            input_transform: self.input_transform.clone(),
+            node_deadline: timeout.map(|timeout| Instant::now() + timeout),
+            ..PregelCore::new()
+        };
+
+        let rt = runtime()?;
+        let cancel = CancellationToken::new();
+        if let Some(timeout) = timeout {
+            let cancel = cancel.clone();
+            rt.spawn(async move {
+                tokio::time::sleep(timeout).await;
+                cancel.cancel();
+            });
+        }
+        let result = rt.block_on(sandbox.invoke_async_with_cancel(py, sample_input, None, &cancel));
+        // Abandoned nodes still wait for the GIL held here, so don't join them
+        rt.shutdown_background();
+        if let (Err(_), Some(timeout)) = (&result, timeout) {
+            let past_deadline = sandbox
+                .node_deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+            if cancel.is_cancelled() || past_deadline {
+                return Err(GraphError::RuntimeCheckTimeout {
+                    timeout_ms: timeout.as_millis(),
+                }
+                .into());
+            }
+        }
+        result?;
+
+        Ok(sandbox
+            .history
+            .iter()
+            .flat_map(|history| &history.steps)
+            .flat_map(|step| step.outputs.keys().cloned())
+            .collect())
+    }
+
+    /// Snapshots saved by the checkpointer, oldest first
+    pub fn state_history(&self, py: Host<'_>) -> HostResult<Vec<StateSnapshot>> {
+        let checkpointer = self
+            .checkpointer
+            .as_ref()
+            .ok_or(GraphError::NoCheckpointer("state_history"))?;
+        checkpointer.list(py)
+    }
+
+    /// Restore the state saved in the `index`-th snapshot of
+    /// [`state_history`](Self::state_history) and run the graph on from its
+    /// `next` nodes
+    ///
+    /// Only channels present in the snapshot are restored. The new run's
+    /// supersteps are saved as further snapshots, so the earlier history is
+    /// kept.
+    pub async fn invoke_from_snapshot_async(
+        &mut self,
+        py: Host<'_>,
+        index: usize,
+    ) -> HostResult<Object> {
+        let checkpointer = self
+            .checkpointer
+            .as_ref()
+            .ok_or(GraphError::NoCheckpointer("invoke_from_snapshot"))?;
+        let snapshot = checkpointer
+            .list(py)?
+            .into_iter()
+            .nth(index)
+            .ok_or(GraphError::SnapshotNotFound(index))?;
+        self.run_from_snapshot(py, snapshot).await
+    }
+
+    /// Restore the state saved in the snapshot with ID `checkpoint_id` and
+    /// run the graph on from its `next` nodes
+    ///
+    /// Resumes a cancelled run from the checkpoint its
+    /// [`GraphError::Cancelled`] names, like
+    /// [`invoke_from_snapshot_async`](Self::invoke_from_snapshot_async).
+    pub async fn invoke_from_checkpoint_async(
+        &mut self,
+        py: Host<'_>,
+        checkpoint_id: &str,
+    ) -> HostResult<Object> {
+        let checkpointer = self
+            .checkpointer
+            .as_ref()
+            .ok_or(GraphError::NoCheckpointer("invoke_from_checkpoint"))?;
+        let snapshot = checkpointer
+            .list(py)?
+            .into_iter()
+            .find(|snapshot| snapshot.id == checkpoint_id)
+            .ok_or_else(|| GraphError::SnapshotIdNotFound(checkpoint_id.to_string()))?;
+        self.run_from_snapshot(py, snapshot).await
+    }
+
+    /// Synchronous wrapper for [`invoke_from_checkpoint_async`](Self::invoke_from_checkpoint_async)
+    pub fn invoke_from_checkpoint(
+        &mut self,
+        py: Host<'_>,
+        checkpoint_id: &str,
+    ) -> HostResult<Object> {
+        let rt = runtime()?;
+
+        rt.block_on(self.invoke_from_checkpoint_async(py, checkpoint_id))
+    }
+
+    /// Start a run from the state and `next` nodes of `snapshot`
+    async fn run_from_snapshot(
+        &mut self,
+        py: Host<'_>,
+        snapshot: StateSnapshot,
+    ) -> HostResult<Object> {
+        self.state.from_checkpoint(py, snapshot.values)?;
+        self.interrupted = None;
+        self.start_run();
+        self.checkpoint_id = Some(snapshot.id);
+        let frontier = snapshot.next.into_iter().map(Destination::Node).collect();
+        self.execute_frontier(py, frontier, 0, &CancellationToken::new())
+            .await?;
+        self.read_output(py)
+    }
+
+    /// Synchronous wrapper for [`invoke_from_snapshot_async`](Self::invoke_from_snapshot_async)
+    pub fn invoke_from_snapshot(&mut self, py: Host<'_>, index: usize) -> HostResult<Object> {
+        let rt = runtime()?;
+
+        rt.block_on(self.invoke_from_snapshot_async(py, index))
+    }
+
+    /// ID of the last snapshot of the current run the checkpointer saved
+    fn saved_checkpoint_id(&self) -> Option<String> {
+        self.checkpointer.as_ref().and(self.checkpoint_id.clone())
+    }
+
+    /// Hand the state committed by `step` to the checkpointer, then to the
+    /// barrier callback, if any
+    ///
+    /// The snapshot ID is derived from the previous snapshot's ID, the step
+    /// and the values, so replaying a run gives the same chain of IDs.
+    fn save_snapshot(&mut self, py: Host<'_>, step: usize, next: &[Destination]) -> HostResult<()> {
+        if self.checkpointer.is_none() && self.on_barrier.is_none() {
+            return Ok(());
+        }
+        let values = self.state.checkpoint(py)?;
+        let id = host::checkpoint_id(py, self.checkpoint_id.as_deref(), step, &values)?;
+        let parent_id = self.checkpoint_id.replace(id.clone());
+        let snapshot = StateSnapshot {
+            id,
+            parent_id,
+            run_id: self.run_id,
+            step,
+            values,
+            next: frontier_nodes(next),
+            schema: self.channel_schema(py),
+        };
+        if let Some(checkpointer) = &self.checkpointer {
+            checkpointer.put(py, snapshot.clone())?;
+        }
+        match &self.on_barrier {
+            Some(on_barrier) => on_barrier(py, step, &self.state, &snapshot),
+            None => Ok(()),
+        }
+    }
+
+    /// Execute the graph starting from the nodes of `frontier`
+    ///
+    /// Runs in supersteps: every node in the frontier executes against the
+    /// same state snapshot, their writes are applied together at the barrier,
+    /// and the successors of the step's nodes form the next frontier.
+    ///
+    /// `step` is the number of supersteps the run already committed; the
+    /// frontier runs as the next one.
+    async fn execute_frontier(
+        &mut self,
+        py: Host<'_>,
+        mut frontier: Vec<Destination>,
+        mut step: usize,
+        cancel: &CancellationToken,
+    ) -> HostResult<()> {
+        while !frontier.is_empty() {
+            if cancel.is_cancelled() {
+                return Err(GraphError::Cancelled {
+                    step,
+                    checkpoint_id: self.saved_checkpoint_id(),
+                }
+                .into());
+            }
+            self.check_recursion_limit(step + 1)?;
+
+            frontier = self
+                .run_superstep(py, &frontier, step + 1, cancel)
+                .await?
+                .ok_or_else(|| GraphError::Cancelled {
+                    step,
+                    checkpoint_id: self.saved_checkpoint_id(),
+                })?;
+            step += 1;
+            self.save_snapshot(py, step, &frontier)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run `frontier` as superstep `step` inside its tracing span
+    async fn run_superstep(
+        &mut self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+        cancel: &CancellationToken,
+    ) -> HostResult<Option<Vec<Destination>>> {
+        let span = tracing::info_span!(
+            "superstep",
+            run_id = self.run_id.map(tracing::field::display),
+            step,
+            triggered = frontier.len()
+        );
+        self.execute_superstep(py, frontier, step, &span, cancel)
+            .instrument(span.clone())
+            .await
+    }
+
+    fn check_recursion_limit(&self, step: usize) -> HostResult<()> {
+        if step > self.recursion_limit {
+            return Err(host::recursion_error(format!(
+                "Recursion limit ({}) exceeded",
+                self.recursion_limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// Execute one superstep and return the next frontier
+    ///
+    /// The ready nodes run together, each on a thread of tokio's blocking
+    /// pool, while this thread releases the GIL and waits for all of them.
+    /// A node takes the GIL while it runs Python code, so only nodes that
+    /// release it, such as native extensions or I/O, overlap. Outside a
+    /// tokio runtime, or with a single node, they run on this thread. With
+    /// a node deadline, every node runs on the pool and is abandoned with
+    /// [`GraphError::NodeTimeout`] if it has not returned by then.
+    /// Planning and the barrier are shared with
+    /// [`execute_superstep_sync`](Self::execute_superstep_sync); only how
+    /// the nodes are driven differs.
+    ///
+    /// Returns `None`, without applying any writes, if `cancel` fires before
+    /// the barrier. Nodes already running finish in the background and their
+    /// writes are dropped.
+    async fn execute_superstep(
+        &mut self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+        step_span: &tracing::Span,
+        cancel: &CancellationToken,
+    ) -> HostResult<Option<Vec<Destination>>> {
+        let tasks = self.plan_superstep(py, frontier, step)?;
+        if cancel.is_cancelled() {
+            return Ok(None);
+        }
+
+        // wasm32 has no threads to run blocking tasks on
+        let runtime = tokio::runtime::Handle::try_current()
+            .ok()
+            .filter(|_| !cfg!(target_arch = "wasm32"));
+        let results = match runtime {
+            Some(runtime) if tasks.len() > 1 || self.node_deadline.is_some() => {
+                let cache = self.cache.clone();
+                let deadline = self.node_deadline;
+                let runs: Vec<_> = tasks
+                    .into_iter()
+                    .map(|task| {
+                        let node = task.node.name.clone();
+                        let span = Self::node_span(step_span, &node);
+                        let cache = cache.clone();
+                        let run = runtime.spawn_blocking(move || {
+                            host::with_host(|py| {
+                                span.in_scope(|| Self::run_task(py, cache.as_deref(), task))
+                            })
+                        });
+                        async move {
+                            let Some(deadline) = deadline else {
+                                return run.await;
+                            };
+                            let remaining = deadline.saturating_duration_since(Instant::now());
+                            match tokio::time::timeout(remaining, run).await {
+                                Ok(result) => result,
+                                Err(_) => {
+                                    let err = GraphError::NodeTimeout { node: node.clone() };
+                                    Ok((node, Err(err.into()), NodeSample::default()))
+                                }
+                            }
+                        }
+                    })
+                    .collect();
+                // Results come back in frontier order, whichever node ends first
+                let joined = host::release(py, || {
+                    futures::executor::block_on(async {
+                        tokio::select! {
+                            biased;
+                            _ = cancel.cancelled() => None,
+                            results = join_all(runs) => Some(results),
+                        }
+                    })
+                });
+                match joined {
+                    Some(results) => results
+                        .into_iter()
+                        .map(|result| {
+                            result.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+                        })
+                        .collect(),
+                    None => return Ok(None),
+                }
+            }
+            _ => self.run_tasks_in_turn(py, tasks, step_span),
+        };
+        if cancel.is_cancelled() {
+            // Partial work of the step is dropped, never committed
+            return Ok(None);
+        }
+
+        self.commit_superstep(py, frontier, step, results).map(Some)
+    }
+
+    /// Execute one superstep without an async runtime, running its nodes
+    /// with the configured [`ParallelBackend`], and return the next frontier
+    fn execute_superstep_sync(
+        &mut self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+        step_span: &tracing::Span,
+    ) -> HostResult<Vec<Destination>> {
+        let tasks = self.plan_superstep(py, frontier, step)?;
+        let results = match self.parallel_backend {
+            #[cfg(feature = "parallel")]
+            ParallelBackend::Rayon { .. } if tasks.len() > 1 => {
+                let cache = self.cache.clone();
+                let run = || {
+                    tasks
+                        .into_par_iter()
+                        .map(|task| {
+                            let span = Self::node_span(step_span, &task.node.name);
+                            host::with_host(|py| {
+                                span.in_scope(|| Self::run_task(py, cache.as_deref(), task))
+                            })
+                        })
+                        .collect()
+                };
+                // Results come back in frontier order, as sequentially
+                host::release(py, || match &self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run(),
+                })
+            }
+            _ => self.run_tasks_in_turn(py, tasks, step_span),
+        };
+        self.commit_superstep(py, frontier, step, results)
+    }
+
+    /// Plan the node runs of superstep `step` and trace their inputs
+    fn plan_superstep(
+        &mut self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+    ) -> HostResult<Vec<NodeTask>> {
+        let tasks = self.prepare_tasks(py, frontier, step)?;
+        self.trace_inputs(py, step, &tasks)?;
+        Ok(tasks)
+    }
+
+    /// Run the planned tasks of a superstep one after another on this
+    /// thread, in frontier order
+    fn run_tasks_in_turn(
+        &self,
+        py: Host<'_>,
+        tasks: Vec<NodeTask>,
+        step_span: &tracing::Span,
+    ) -> Vec<NodeResult> {
+        let cache = self.cache.as_deref();
+        tasks
+            .into_iter()
+            .map(|task| {
+                let span = Self::node_span(step_span, &task.node.name);
+                span.in_scope(|| Self::run_task(py, cache, task))
+            })
+            .collect()
+    }
+
+    /// Plan the node runs of a superstep: their inputs, cache keys and, while
+    /// replaying, their recorded outputs
+    ///
+    /// A node destination reads its input from the state, while a `Send`
+    /// runs its node on the send's argument.
+    fn prepare_tasks(
+        &self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+    ) -> HostResult<Vec<NodeTask>> {
+        let mut recorded = self.recorded_outputs(py, frontier, step)?;
+        let mut tasks = Vec::with_capacity(frontier.len());
+        for (destination, key) in frontier.iter().zip(task_keys(frontier)) {
+            let node_name = destination.node();
+            let node = self
+                .nodes
+                .get(node_name)
+                .ok_or_else(|| host::key_error(format!("Node '{}' not found", node_name)))?
+                .clone(); // Clone to avoid borrow issues
+                          // Replayed nodes are not called, so need neither input nor cache
+            let replayed = recorded.as_mut().and_then(|outputs| outputs.remove(&key));
+            if replayed.is_some() {
+                tasks.push(NodeTask {
+                    node,
+                    key,
+                    input: host::none(py),
+                    cache_key: None,
+                    replayed,
+                });
+                continue;
+            }
+            let input = match destination {
+                Destination::Node(_) => self.prepare_input(py, &node)?,
+                Destination::Send { arg, .. } => arg.clone_ref(py),
+            };
+            // Inputs that cannot be pickled are simply not cached
+            let cache_key = match &self.cache {
+                Some(_) if node.cache => NodeCache::key(py, &node.name, &input).ok(),
+                _ => None,
+            };
+            tasks.push(NodeTask {
+                node,
+                key,
+                input,
+                cache_key,
+                replayed: None,
+            });
+        }
+        Ok(tasks)
+    }
+
+    fn node_span(step_span: &tracing::Span, node: &str) -> tracing::Span {
+        tracing::info_span!(
+            parent: step_span,
+            "node",
+            node = %node,
+            duration_ms = tracing::field::Empty,
+        )
+    }
+
+    /// Run a planned node, serving it from the replay record or the cache
+    /// when possible
+    fn run_task(py: Host<'_>, cache: Option<&NodeCache>, task: NodeTask) -> NodeResult {
+        let NodeTask {
+            node,
+            input,
+            cache_key,
+            replayed,
+            ..
+        } = task;
+        let start = Stopwatch::start();
+        let cached = match (cache, &cache_key) {
+            (Some(cache), Some(key)) => cache.get(py, key),
+            _ => None,
+        };
+        let cache_hit = cache_key.as_ref().map(|_| cached.is_some());
+        let result = match replayed.or(cached) {
+            Some(updates) => Ok(updates),
+            None => Self::run_node(py, &node, input),
+        };
+        if let (Some(cache), Some(key), Ok(updates), Some(false)) =
+            (cache, cache_key, &result, cache_hit)
+        {
+            let updates = updates
+                .iter()
+                .map(|(channel, value)| (channel.clone(), value.clone_ref(py)))
+                .collect();
+            cache.put(key, updates);
+        }
+        let sample = NodeSample {
+            duration: start.elapsed(),
+            cache_hit,
+            ..Default::default()
+        };
+        tracing::Span::current().record("duration_ms", sample.duration.as_millis() as u64);
+        (node.name, result, sample)
+    }
+
+    /// Barrier of a superstep: apply the writes of its nodes and return the
+    /// next frontier
+    ///
+    /// All or nothing: every write of the step is checked before any is
+    /// applied, so nothing is applied, recorded in the history or
+    /// checkpointed if a node failed, a guard interrupted the run, the
+    /// writes conflict or a channel rejects a write. A rejected write fails
+    /// the step with [`GraphError::InvalidUpdate`] naming the nodes to blame.
+    fn commit_superstep(
+        &mut self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+        mut results: Vec<NodeResult>,
+    ) -> HostResult<Vec<Destination>> {
+        // An interrupting guard stops the run before anything is applied
+        for (node_name, result, _) in &results {
+            let Err(err) = result else { continue };
+            let Some(NodeFunc::Guard {
+                message,
+                action: GuardAction::Interrupt,
+                ..
+            }) = self.nodes.get(node_name).map(|node| &node.func)
+            else {
+                continue;
+            };
+            if host::is_guard_failure(py, err) {
+                self.interrupted = Some(frontier.to_vec());
+                return Err(GraphError::Interrupted {
+                    node: node_name.clone(),
+                    message: message.clone(),
+                }
+                .into());
+            }
+        }
+
+        // Writes outside the schema fail the step before anything is applied
+        if let Some(schema) = &self.schema {
+            for (node_name, result, _) in &results {
+                let Ok(updates) = result else { continue };
+                let mut unknown: Vec<&String> =
+                    updates.keys().filter(|ch| !schema.contains(ch)).collect();
+                unknown.sort();
+                if let Some(channel) = unknown.first() {
+                    return Err(GraphError::InvalidUpdate {
+                        channel: (*channel).clone(),
+                        writers: vec![node_name.clone()],
+                    }
+                    .into());
+                }
+            }
+        }
+
+        // Barrier: merge metrics and group the writes of all nodes of the
+        // step; the first node that failed fails the step
+        let failed = results.iter().position(|(_, result, _)| result.is_err());
+        if let Some(metrics) = self.metrics.as_mut() {
+            let merged = failed.map_or(results.len(), |index| index + 1);
+            for (node_name, _, sample) in &results[..merged] {
+                metrics.record(node_name, sample);
+            }
+        }
+        if let Some(index) = failed {
+            if let (_, Err(err), _) = results.swap_remove(index) {
+                return Err(err);
+            }
+        }
+        let mut writes: BTreeMap<&String, Vec<(&String, &Object)>> = BTreeMap::new();
+        for (node_name, result, _) in &results {
+            let Ok(updates) = result else { continue };
+            for (channel_name, value) in updates {
+                tracing::debug!(node = %node_name, channel = %channel_name, "channel write");
+                writes
+                    .entry(channel_name)
+                    .or_default()
+                    .push((node_name, value));
+            }
+        }
+
+        // Concurrent writes only merge in channels built for it; anywhere
+        // else the surviving value would depend on scheduling order
+        for (channel_name, channel_writes) in &writes {
+            let merges = self
+                .state
+                .get_channel(channel_name)
+                .is_some_and(|ch| ch.merges_writes());
+            if channel_writes.len() > 1 && !merges {
+                let mut writers: Vec<String> = channel_writes
+                    .iter()
+                    .map(|(node, _)| (*node).clone())
+                    .collect();
+                writers.sort();
+                return Err(GraphError::InvalidUpdate {
+                    channel: (*channel_name).clone(),
+                    writers,
+                }
+                .into());
+            }
+        }
+
+        // Check every write before applying any; the first rejection fails
+        // the step with nothing applied
+        for (channel_name, channel_writes) in &writes {
+            let Some(channel) = self.state.get_channel(channel_name) else {
+                continue;
+            };
+            let check = |values: &[(&String, &Object)]| {
+                let values = values.iter().map(|(_, value)| value.clone_ref(py));
+                channel.check_update(py, &ChannelUpdate::new(values.collect()))
+            };
+            let Err(cause) = check(channel_writes) else {
+                continue;
+            };
+            // Blame the writes that fail on their own; if each passes alone,
+            // they only fail together and every writer is to blame
+            let mut offenders: Vec<&String> = channel_writes
+                .iter()
+                .filter(|write| check(std::slice::from_ref(*write)).is_err())
+                .map(|(node, _)| *node)
+                .collect();
+            if offenders.is_empty() {
+                offenders = channel_writes.iter().map(|(node, _)| *node).collect();
+            }
+            offenders.sort();
+            let err = GraphError::InvalidUpdate {
+                channel: (*channel_name).clone(),
+                writers: offenders.iter().map(|node| (*node).clone()).collect(),
+            };
+            return Err(host::with_cause(py, err.into(), cause));
+        }
+
+        let mut written = Vec::with_capacity(writes.len());
+        for (channel_name, channel_writes) in writes {
+            if !self.state.has_channel(channel_name) {
+                // Auto-create channel if it doesn't exist
+                self.state
+                    .add_channel(channel_name.clone(), Box::new(LastValueChannel::new()));
+            }
+            let mut writers: Vec<String> = Vec::with_capacity(channel_writes.len());
+            let values = channel_writes
+                .into_iter()
+                .map(|(node, value)| {
+                    writers.push(node.clone());
+                    value.clone_ref(py)
+                })
+                .collect();
+            self.write_channel(py, channel_name, ChannelUpdate::new(values))?;
+            writers.sort();
+            written.push((channel_name.clone(), writers));
+        }
+        let keys = task_keys(frontier);
+        if self.trace.is_some() {
+            self.trace_outputs(py, step, &keys, &results)?;
+            for (channel_name, writers) in written {
+                self.trace_write(py, step, &channel_name, writers)?;
+            }
+        }
+
+        if let Some(history) = self.history.as_mut() {
+            let outputs = keys
+                .iter()
+                .zip(&results)
+                .filter_map(|(key, (_, result, _))| {
+                    let updates = result.as_ref().ok()?;
+                    let updates = updates
+                        .iter()
+                        .map(|(channel, value)| (channel.clone(), value.clone_ref(py)))
+                        .collect();
+                    Some((key.clone(), updates))
+                })
+                .collect();
+            history.steps.push(StepRecord { step, outputs });
+        }
+
+        // Successors of this step's nodes form the next frontier; every Send
+        // is a task of its own, while a node runs once
+        let mut next: Vec<Destination> = Vec::new();
+        for (node_name, _, _) in &results {
+            for successor in self.next_nodes(py, node_name)? {
+                let scheduled = matches!(&successor, Destination::Node(node)
+                    if next.iter().any(|d| matches!(d, Destination::Node(n) if n == node)));
+                if !scheduled {
+                    next.push(successor);
+                }
+            }
+        }
+        self.trace_checkpoint(py, step, &next)?;
+
+        Ok(next)
+    }
+
+    /// Record the node outputs of a superstep in the trace, if tracing
+    ///
+    /// Outputs are recorded under their task keys, see [`task_keys`].
+    fn trace_outputs(
+        &mut self,
+        py: Host<'_>,
+        step: usize,
+        keys: &[String],
+        results: &[NodeResult],
+    ) -> HostResult<()> {
+        let Some(trace) = self.trace.as_mut() else {
+            return Ok(());
+        };
+        let mut results: Vec<(&String, &NodeResult)> = keys.iter().zip(results).collect();
+        results.sort_by(|a, b| a.0.cmp(b.0));
+        for (node_name, (_, result, _)) in results {
+            let Ok(updates) = result else { continue };
+            let output = updates
+                .iter()
+                .map(|(channel, value)| {
+                    let value = trace_value(py, value, || {
+                        format!("output '{}' of node '{}'", channel, node_name)
+                    })?;
+                    Ok((channel.clone(), value))
+                })
+                .collect::<HostResult<_>>()?;
+            trace.push(ExecutionEvent::NodeOutput {
+                step,
+                node: node_name.clone(),
+                output,
+            });
+        }
+        Ok(())
+    }
+
+    /// Record the state committed by `step` in the trace, if tracing
+    fn trace_checkpoint(
+        &mut self,
+        py: Host<'_>,
+        step: usize,
+        next: &[Destination],
+    ) -> HostResult<()> {
+        let Some(trace) = self.trace.as_mut() else {
+            return Ok(());
+        };
+        let values = self
+            .state
+            .checkpoint(py)?
+            .into_iter()
+            .map(|(channel, value)| {
+                let traced =
+                    trace_value(py, &value, || format!("the value of channel '{}'", channel))?;
+                Ok((channel, traced))
+            })
+            .collect::<HostResult<_>>()?;
+        let mut next = frontier_nodes(next);
+        next.sort();
+        trace.push(ExecutionEvent::Checkpoint { step, values, next });
+        Ok(())
+    }
+
+    /// Recorded node outputs for `step` while replaying, `None` when live
+    ///
+    /// Fails if the recorded step ran different tasks than `frontier`.
+    fn recorded_outputs(
+        &self,
+        py: Host<'_>,
+        frontier: &[Destination],
+        step: usize,
+    ) -> Result<Option<NodeOutputs>, GraphError> {
+        let Some((history, until)) = &self.replaying else {
+            return Ok(None);
+        };
+        let Some(record) = history.steps.iter().find(|record| record.step == step) else {
+            return Ok(None);
+        };
+        if step > *until {
+            return Ok(None);
+        }
+
+        let mut recorded: Vec<String> = record.outputs.keys().cloned().collect();
+        let mut scheduled = task_keys(frontier);
+        recorded.sort();
+        scheduled.sort();
+        if recorded != scheduled {
+            return Err(GraphError::ReplayDiverged {
+                step,
+                recorded,
+                scheduled,
+            });
+        }
+        Ok(Some(
+            record
+                .outputs
+                .iter()
+                .map(|(node, updates)| {
+                    let updates = updates
+                        .iter()
+                        .map(|(channel, value)| (channel.clone(), value.clone_ref(py)))
+                        .collect();
+                    (node.clone(), updates)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Collect a node's input from the channels it may read
+    ///
+    /// Guards without input channels see the whole state.
+    fn prepare_input(&self, py: Host<'_>, node: &Node) -> HostResult<Object> {
+        if node.guard_action().is_some() && node.readable_input_channels().is_none() {
+            return self.create_state_dict(py);
+        }
+        let channel_values: HashMap<String, Object> = node
+            .readable_input_channels()
+            .map(|channels| {
+                channels
+                    .iter()
+                    .filter_map(|ch_name| {
+                        self.state
+                            .get_value(py, ch_name)
+                            .map(|val| (ch_name.clone(), val))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        node.extract_input(py, &channel_values)
+    }
+
+    /// Execute a single node and map its output to channel updates
+    ///
+    /// Writes outside the node's schema are rejected.
+    fn run_node(py: Host<'_>, node: &Node, input: Object) -> HostResult<HashMap<String, Object>> {
+        let output = node.execute(py, input)?;
+        let updates = node.map_output(py, output)?;
+        node.check_writes(&updates)?;
+        Ok(updates)
+    }
+
+    /// Destinations the edges leaving `current_node` lead to
+    ///
+    /// Every edge from the node contributes: a conditional edge whatever its
+    /// condition routes to, including `Send`s, and an
+    /// [`Edge::WhenAvailable`] its target only if its channel is available
+    /// in the committed state. Routing to [`END`] contributes nothing.
+    fn next_nodes(&self, py: Host<'_>, current_node: &str) -> HostResult<Vec<Destination>> {
+        let mut next = Vec::new();
+        for edge in &self.edges {
+            if edge.source() != Some(current_node) || !edge.is_active(&self.state) {
+                continue;
+            }
+            let state = match edge {
+                Edge::Conditional { .. } => self.create_state_dict(py)?,
+                _ => host::none(py),
+            };
+            next.extend(
+                edge.route(py, state)?
+                    .into_iter()
+                    .filter(|destination| destination.node() != END),
+            );
+        }
+        Ok(next)
+    }
+
+    /// Project the final channel values onto the configured output channels
+    fn read_output(&self, py: Host<'_>) -> HostResult<Object> {
+        let channels = match &self.output_channels {
+            Some(OutputChannels::Single(channel)) => {
+                return Ok(self
+                    .state
+                    .get_value(py, channel)
+                    .unwrap_or_else(|| host::none(py)));
+            }
+            Some(OutputChannels::Multiple(channels)) => channels.clone(),
+            None => self
+                .state
+                .channel_names()
+                .into_iter()
+                .filter(|name| {
+                    self.state
+                        .get_channel(name)
+                        .is_some_and(|ch| ch.is_checkpointed())
+                })
+                .collect(),
+        };
+
+        let values = channels.into_iter().filter_map(|channel| {
+            let value = self.state.get_value(py, &channel)?;
+            Some((channel, value))
+        });
+        host::dict(py, values)
+    }
+
+    /// Create a dictionary representation of the current state
+    fn create_state_dict(&self, py: Host<'_>) -> HostResult<Object> {
+        let values = self
+            .state
+            .channel_names()
+            .into_iter()
+            .filter_map(|channel| {
+                let value = self.state.get_value(py, &channel)?;
+                Some((channel, value))
+            });
+        host::dict(py, values)
+    }
+
+    /// Get checkpoint of current state
+    pub fn checkpoint(&self, py: Host<'_>) -> HostResult<HashMap<String, Object>> {
+        self.state.checkpoint(py)
+    }
+
+    /// Restore from checkpoint
+    pub fn from_checkpoint(
+        &mut self,
+        py: Host<'_>,
+        checkpoint: HashMap<String, Object>,
+    ) -> HostResult<()> {
+        self.state.from_checkpoint(py, checkpoint)
+    }
+}
+
+/// Keys of a frontier's tasks in step records and traces: the node name,
+/// or `"{node}:send:{index}"` for the `Send` at `index`, so several sends to
+/// one node are recorded apart
+fn task_keys(frontier: &[Destination]) -> Vec<String> {
+    frontier
+        .iter()
+        .enumerate()
+        .map(|(index, destination)| match destination {
+            Destination::Node(node) => node.clone(),
+            Destination::Send { node, .. } => format!("{}:send:{}", node, index),
+        })
+        .collect()
+}
+
+/// Nodes a frontier runs, in order and without repeats
+fn frontier_nodes(frontier: &[Destination]) -> Vec<String> {
+    let mut nodes: Vec<String> = Vec::with_capacity(frontier.len());
+    for destination in frontier {
+        if !nodes.iter().any(|node| node == destination.node()) {
+            nodes.push(destination.node().to_string());
+        }
+    }
+    nodes
+}
+
+/// Runtime driving the blocking entry points; wasm32 has no threads, so
+/// there it runs on the calling thread
+fn runtime() -> HostResult<tokio::runtime::Runtime> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Runtime::new();
+    #[cfg(target_arch = "wasm32")]
+    let runtime = tokio::runtime::Builder::new_current_thread().build();
+    runtime.map_err(|e| host::runtime_error(format!("Failed to create runtime: {}", e)))
+}
+
+/// `schema` without its `default` keyword
+fn schema_without_default(mut schema: Value) -> Value {
+    if let Some(properties) = schema.as_object_mut() {
+        properties.remove("default");
+    }
+    schema
+}
+
+/// Channel names in order, for comparing declarations
+fn sorted(channels: Option<&[String]>) -> Option<Vec<&String>> {
+    channels.map(|channels| {
+        let mut channels: Vec<&String> = channels.iter().collect();
+        channels.sort();
+        channels
+    })
+}
+
+impl Default for PregelCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for PregelCore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PregelCore")
+            .field("nodes", &self.nodes.keys().collect::<Vec<_>>())
+            .field("edges", &self.edges)
+            .field("entry_point", &self.entry_point)
+            .field("entry_points", &self.entry_points)
+            .field("channels", &self.state.channel_names())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+    use crate::core::channel::ValueType;
+    use crate::core::state::ChannelKind;
+    use crate::errors::GuardFailed;
+    use pyo3::types::PyDict;
+
+    #[tokio::test]
+    async fn test_pregel_core_creation() {
+        let executor = PregelCore::new();
+        assert_eq!(executor.nodes.len(), 0);
+        assert_eq!(executor.edges.len(), 0);
+        assert!(executor.entry_point.is_none());
+    }
+
+    #[test]
     fn test_add_node() {
         pyo3::prepare_freethreaded_python();
 
         Python::with_gil(|py| {
-            let mut executor = PregelCore::new();
-            let func = py.eval("lambda x: x + 1", None, None).unwrap();
-            let node = Node::new("test".to_string(), func.to_object(py));
+            let mut executor = PregelCore::new();
+            let func = py.eval("lambda x: x + 1", None, None).unwrap();
+            let node = Node::new("test".to_string(), func.to_object(py));
+
+            executor.add_node(node);
+            assert_eq!(executor.nodes.len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_add_edge() {
+        let mut executor = PregelCore::new();
+        let edge = Edge::direct("node1".to_string(), "node2".to_string());
+
+        executor.add_edge(edge);
+        assert_eq!(executor.edges.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_simple_execution() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+
+            // Create a simple node that adds 1
+            let func = py.eval("lambda x: x + 1", None, None).unwrap();
+            let node = Node::with_channels(
+                "add_one".to_string(),
+                func.to_object(py),
+                Some(vec!["input".to_string()]),
+                Some(vec!["output".to_string()]),
+            );
+
+            // Add the node
+            executor.add_node(node);
+
+            // Add channels
+            executor.add_channel("input".to_string(), Box::new(LastValueChannel::new()));
+            executor.add_channel("output".to_string(), Box::new(LastValueChannel::new()));
+
+            // Set entry point
+            executor.set_entry_point("add_one".to_string());
+
+            // Set initial input
+            executor
+                .state_mut()
+                .update_channel(py, "input", 42.to_object(py))
+                .unwrap();
+
+            // Execute
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                executor
+                    .execute_frontier(
+                        py,
+                        vec![Destination::Node("add_one".to_string())],
+                        0,
+                        &CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap();
+            });
+
+            // Check output
+            let result = executor.state().get_value(py, "output").unwrap();
+            assert_eq!(result.extract::<i32>(py).unwrap(), 43);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_linear_graph() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+
+            // Node 1: x + 1
+            let func1 = py.eval("lambda x: x + 1", None, None).unwrap();
+            let node1 = Node::with_channels(
+                "add_one".to_string(),
+                func1.to_object(py),
+                Some(vec!["input".to_string()]),
+                Some(vec!["intermediate".to_string()]),
+            );
+
+            // Node 2: x * 2
+            let func2 = py.eval("lambda x: x * 2", None, None).unwrap();
+            let node2 = Node::with_channels(
+                "multiply_two".to_string(),
+                func2.to_object(py),
+                Some(vec!["intermediate".to_string()]),
+                Some(vec!["output".to_string()]),
+            );
+
+            executor.add_node(node1);
+            executor.add_node(node2);
+
+            // Add edge: add_one -> multiply_two
+            executor.add_edge(Edge::direct(
+                "add_one".to_string(),
+                "multiply_two".to_string(),
+            ));
+
+            // Add channels
+            executor.add_channel("input".to_string(), Box::new(LastValueChannel::new()));
+            executor.add_channel(
+                "intermediate".to_string(),
+                Box::new(LastValueChannel::new()),
+            );
+            executor.add_channel("output".to_string(), Box::new(LastValueChannel::new()));
+
+            // Set entry
+            executor.set_entry_point("add_one".to_string());
+
+            // Set input
+            executor
+                .state_mut()
+                .update_channel(py, "input", 5.to_object(py))
+                .unwrap();
+
+            // Execute
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                executor
+                    .execute_frontier(
+                        py,
+                        vec![Destination::Node("add_one".to_string())],
+                        0,
+                        &CancellationToken::new(),
+                    )
+                    .await
+                    .unwrap();
+            });
+
+            // Check: (5 + 1) * 2 = 12
+            let result = executor.state().get_value(py, "output").unwrap();
+            assert_eq!(result.extract::<i32>(py).unwrap(), 12);
+        });
+    }
+
+    #[test]
+    fn test_write_outside_schema_fails() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            let func = py
+                .eval("lambda x: {'output': 1, 'secret': 2}", None, None)
+                .unwrap();
+            let node = Node::with_channels(
+                "writer".to_string(),
+                func.to_object(py),
+                None,
+                Some(vec!["output".to_string(), "secret".to_string()]),
+            )
+            .with_write_channels(vec!["output".to_string()]);
+            executor.add_node(node);
+            executor.set_entry_point("writer".to_string());
+
+            let err = executor.invoke(py, py.None(), None).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            assert!(!executor.state().has_channel("secret"));
+        });
+    }
+
+    #[test]
+    fn test_rejected_write_commits_nothing() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            executor.add_channel("count".to_string(), Box::new(LastValueChannel::new()));
+            executor.add_channel(
+                "avg".to_string(),
+                Box::new(crate::core::channel::EmaChannel::new(0.5)),
+            );
+            let func = py
+                .eval("lambda x: {'count': 1, 'avg': 'high'}", None, None)
+                .unwrap();
+            executor.add_node(Node::with_channels(
+                "writer".to_string(),
+                func.to_object(py),
+                None,
+                Some(vec!["count".to_string(), "avg".to_string()]),
+            ));
+            executor.set_entry_point("writer".to_string());
+
+            // The bad EMA write also keeps the valid write to `count` out
+            let err = executor.invoke(py, py.None(), None).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            assert!(err.to_string().contains("'avg'"));
+            let cause = err.cause(py).unwrap();
+            assert!(cause.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+            assert!(executor.state().get_value(py, "count").is_none());
+            assert!(executor.state().get_value(py, "avg").is_none());
+        });
+    }
+
+    #[test]
+    fn test_rejected_write_leaves_step_uncommitted() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            executor.add_channel("count".to_string(), Box::new(LastValueChannel::new()));
+            executor.add_channel(
+                "avg".to_string(),
+                Box::new(crate::core::channel::EmaChannel::new(0.5)),
+            );
+            let start = py.eval("lambda x: 0", None, None).unwrap();
+            let good = py.eval("lambda x: 1", None, None).unwrap();
+            let bad = py.eval("lambda x: 'high'", None, None).unwrap();
+            let writer = |name: &str, func: &PyAny, channel: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    None,
+                    Some(vec![channel.to_string()]),
+                )
+            };
+            executor.add_node(writer("start", start, "count"));
+            executor.add_node(writer("good", good, "count"));
+            executor.add_node(writer("bad", bad, "avg"));
+            executor.add_edge(Edge::direct("start".to_string(), "good".to_string()));
+            executor.add_edge(Edge::direct("start".to_string(), "bad".to_string()));
+            executor.set_entry_point("start".to_string());
+            executor.enable_history();
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = executor.compile(Some(checkpointer.clone())).unwrap();
+
+            let err = executor.invoke(py, py.None(), None).unwrap_err();
+            assert!(err.to_string().contains("'avg'"));
+            assert!(err.to_string().contains("'bad'"));
+            assert!(!err.to_string().contains("'good'"));
+
+            // The good node's write in the failed step is not applied either
+            let count = executor.state().get_value(py, "count").unwrap();
+            assert_eq!(count.extract::<i64>(py).unwrap(), 0);
+            assert!(executor.state().get_value(py, "avg").is_none());
+
+            // Only the first step was recorded and checkpointed
+            let steps: Vec<usize> = executor
+                .history()
+                .unwrap()
+                .steps
+                .iter()
+                .map(|record| record.step)
+                .collect();
+            assert_eq!(steps, [1]);
+            let snapshots = checkpointer.list(py).unwrap();
+            assert_eq!(snapshots.len(), 1);
+            let saved: i64 = snapshots[0].values["count"].extract(py).unwrap();
+            assert_eq!(saved, 0);
+        });
+    }
+
+    #[test]
+    fn test_context_visible_and_read_only() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            let func = py.eval("lambda ctx: ctx['model']", None, None).unwrap();
+            executor.add_node(
+                Node::with_channels(
+                    "reader".to_string(),
+                    func.to_object(py),
+                    Some(vec![CONTEXT_CHANNEL.to_string()]),
+                    Some(vec!["model".to_string()]),
+                )
+                .with_read_channels(vec!["input".to_string()]),
+            );
+            executor.set_entry_point("reader".to_string());
+
+            let context = pyo3::types::PyDict::new(py);
+            context.set_item("model", "gpt-4").unwrap();
+            executor
+                .invoke(py, py.None(), Some(context.to_object(py)))
+                .unwrap();
+
+            let model = executor.state().get_value(py, "model").unwrap();
+            assert_eq!(model.extract::<String>(py).unwrap(), "gpt-4");
+            assert!(!executor
+                .checkpoint(py)
+                .unwrap()
+                .contains_key(CONTEXT_CHANNEL));
+
+            // Nodes cannot write the context
+            let mut executor = PregelCore::new();
+            let func = py.eval("lambda x: 1", None, None).unwrap();
+            executor.add_node(Node::with_channels(
+                "writer".to_string(),
+                func.to_object(py),
+                None,
+                Some(vec![CONTEXT_CHANNEL.to_string()]),
+            ));
+            executor.set_entry_point("writer".to_string());
+            assert!(executor.invoke(py, py.None(), None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_input_routed_to_input_channels() {
+        use super::super::channel::TopicChannel;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let build = || {
+                let mut executor = PregelCore::new();
+                let func = py.eval("lambda x: {}", None, None).unwrap();
+                executor.add_node(Node::new("noop".to_string(), func.to_object(py)));
+                executor.set_entry_point("noop".to_string());
+                executor.add_channel(
+                    "messages".to_string(),
+                    Box::new(TopicChannel::with_values(vec!["hi".to_object(py)], true)),
+                );
+                executor.set_input_channels(vec!["question".to_string(), "messages".to_string()]);
+                executor
+            };
+            let input = |items: &[(&str, &str)]| {
+                let dict = pyo3::types::PyDict::new(py);
+                for (key, value) in items {
+                    dict.set_item(key, value).unwrap();
+                }
+                dict.to_object(py)
+            };
+
+            // Values go through each channel's update semantics
+            let mut executor = build();
+            executor
+                .invoke(
+                    py,
+                    input(&[("question", "why?"), ("messages", "hello")]),
+                    None,
+                )
+                .unwrap();
+            let question = executor.state().get_value(py, "question").unwrap();
+            assert_eq!(question.extract::<String>(py).unwrap(), "why?");
+            let messages = executor.state().get_value(py, "messages").unwrap();
+            assert_eq!(
+                messages.extract::<Vec<String>>(py).unwrap(),
+                ["hi", "hello"]
+            );
+
+            // Already-populated channels may be omitted; others are required
+            let mut executor = build();
+            let err = executor.invoke(py, input(&[("messages", "hello")]), None);
+            assert!(err.unwrap_err().to_string().contains("'question'"));
+
+            // Unknown keys are rejected unless explicitly ignored
+            let mut executor = build();
+            let err = executor.invoke(py, input(&[("question", "why?"), ("extra", "x")]), None);
+            assert!(err
+                .unwrap_err()
+                .is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            assert!(!executor.state().has_channel("extra"));
+
+            let mut executor = build();
+            executor.set_ignore_unknown_input(true);
+            executor
+                .invoke(py, input(&[("question", "why?"), ("extra", "x")]), None)
+                .unwrap();
+            assert!(!executor.state().has_channel("extra"));
+        });
+    }
+
+    #[test]
+    fn test_output_projection() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let build = || {
+                let mut executor = PregelCore::new();
+                let func = py
+                    .eval("lambda x: {'answer': 42, 'scratch': 'tmp'}", None, None)
+                    .unwrap();
+                executor.add_node(Node::with_channels(
+                    "solve".to_string(),
+                    func.to_object(py),
+                    None,
+                    Some(vec!["answer".to_string(), "scratch".to_string()]),
+                ));
+                executor.set_entry_point("solve".to_string());
+                executor
+            };
+
+            let mut executor = build();
+            executor.set_output_channels(OutputChannels::Single("answer".to_string()));
+            let output = executor.invoke(py, py.None(), None).unwrap();
+            assert_eq!(output.extract::<i32>(py).unwrap(), 42);
+
+            let mut executor = build();
+            executor.set_output_channels(OutputChannels::Single("never".to_string()));
+            assert!(executor.invoke(py, py.None(), None).unwrap().is_none(py));
+
+            let mut executor = build();
+            executor.set_output_channels(OutputChannels::Multiple(vec![
+                "answer".to_string(),
+                "never".to_string(),
+            ]));
+            let output = executor.invoke(py, py.None(), None).unwrap();
+            let output: HashMap<String, i32> = output.extract(py).unwrap();
+            assert_eq!(output, HashMap::from([("answer".to_string(), 42)]));
+
+            // Without output channels, the whole state is returned minus the run context
+            let mut executor = build();
+            let output = executor.invoke(py, py.None(), None).unwrap();
+            let output = output.downcast::<pyo3::types::PyDict>(py).unwrap();
+            assert!(output.contains("scratch").unwrap());
+            assert!(!output.contains(CONTEXT_CHANNEL).unwrap());
+        });
+    }
+
+    #[test]
+    fn test_plan_runs_no_user_code() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let boom = py
+                .eval("lambda x: 1 / 0", None, None)
+                .unwrap()
+                .to_object(py);
+            let mut executor = PregelCore::new();
+            for name in ["agent", "tools", "answer", "orphan"] {
+                executor.add_node(Node::new(name.to_string(), boom.clone_ref(py)));
+            }
+            executor.set_entry_point("agent".to_string());
+            executor.add_edge(Edge::conditional(
+                "agent".to_string(),
+                boom.clone_ref(py),
+                HashMap::from([
+                    ("call".to_string(), "tools".to_string()),
+                    ("done".to_string(), "answer".to_string()),
+                ]),
+            ));
+            executor.add_edge(Edge::direct("tools".to_string(), "agent".to_string()));
+            executor.set_input_channels(vec!["question".to_string()]);
+
+            let plan = executor.plan(py, "why?".to_object(py)).unwrap();
+            assert_eq!(
+                plan,
+                ExecutionPlan {
+                    input_channels: vec!["question".to_string()],
+                    steps: vec![
+                        vec!["agent".to_string()],
+                        vec!["answer".to_string(), "tools".to_string()],
+                    ],
+                    unreachable: vec!["orphan".to_string()],
+                }
+            );
+            assert!(!executor.state().has_channel("question"));
+
+            // Input is validated as for invoke
+            let input = pyo3::types::PyDict::new(py);
+            input.set_item("other", 1).unwrap();
+            assert!(executor.plan(py, input.to_object(py)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_multiple_entry_points() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let upper = py.eval("lambda s: s.upper()", None, None).unwrap();
+            let double = py.eval("lambda n: n * 2", None, None).unwrap();
+            let merge = py
+                .eval("lambda s: s['text'] * s['count']", None, None)
+                .unwrap();
+            let node = |name: &str, func: &PyAny, input: &str, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    Some(vec![input.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+
+            let mut executor = PregelCore::new();
+            executor.add_node(node("text_in", upper, "raw_text", "text"));
+            executor.add_node(node("count_in", double, "raw_count", "count"));
+            executor.add_node(Node::with_channels(
+                "merge".to_string(),
+                merge.to_object(py),
+                Some(vec!["text".to_string(), "count".to_string()]),
+                Some(vec!["report".to_string()]),
+            ));
+            executor.add_edge(Edge::direct("text_in".to_string(), "merge".to_string()));
+            executor.add_edge(Edge::direct("count_in".to_string(), "merge".to_string()));
+            executor.add_entry_points(&["text_in", "count_in"]);
+
+            let plan = executor.plan(py, py.None()).unwrap();
+            assert_eq!(plan.steps[0], ["count_in", "text_in"]);
+
+            // Both entries run in the first superstep, merge in the second
+            executor.enable_metrics();
+            executor.enable_history();
+            let output = executor
+                .invoke_entries(
+                    py,
+                    vec![
+                        ("text_in".to_string(), "ab".to_object(py)),
+                        ("count_in".to_string(), 1.to_object(py)),
+                    ],
+                    None,
+                )
+                .unwrap();
+            let output = output.downcast::<pyo3::types::PyDict>(py).unwrap();
+            assert_eq!(
+                output.get_item("report").unwrap().unwrap().to_string(),
+                "ABAB"
+            );
+            assert_eq!(executor.metrics().nodes["merge"].invocations, 1);
+
+            // The run is set up as by invoke: it has an ID and is recorded
+            // with its inputs, so it replays from the same entries
+            assert!(executor.run_id().is_some());
+            let history = executor.history().unwrap().clone();
+            assert!(history.entries);
+            assert_eq!(history.steps.len(), 2);
+            let replayed = executor.replay(py, &history, 2).unwrap();
+            assert!(replayed.as_ref(py).eq(output).unwrap());
+
+            let err = executor
+                .invoke_entries(py, vec![("merge".to_string(), py.None())], None)
+                .unwrap_err();
+            assert!(err.to_string().contains("'merge' is not an entry point"));
+
+            executor.add_entry_points(&["missing"]);
+            let err = executor
+                .invoke_entries(py, vec![("text_in".to_string(), "x".to_object(py))], None)
+                .unwrap_err();
+            assert!(err.to_string().contains("Unknown node: 'missing'"));
+        });
+    }
+
+    #[test]
+    fn test_validate_runtime() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+import time
+def fetch(n):
+    raise RuntimeError("no network in CI")
+def route(state):
+    return "big" if state["n"] > 10 else "small"
+def spin(n):
+    time.sleep(0.02)
+    return n
+def hang(n):
+    time.sleep(3)
+    return n
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let double = py.eval("lambda n: n * 2", None, None).unwrap();
+            let node = |name: &str, func: PyObject, input: &str, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func,
+                    Some(vec![input.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+
+            let mut executor = PregelCore::new();
+            executor.add_node(node("fetch", func("fetch"), "n", "n"));
+            executor.add_node(node("big", double.to_object(py), "n", "big"));
+            executor.add_node(node("small", double.to_object(py), "n", "small"));
+            executor.add_conditional_edges(
+                "fetch",
+                func("route"),
+                HashMap::from([
+                    ("big".to_string(), "big".to_string()),
+                    ("small".to_string(), "small".to_string()),
+                ]),
+            );
+            executor.set_entry_point("fetch".to_string());
+            executor.set_input_channels(vec!["n".to_string()]);
+
+            // The real fetch fails; the stubbed one feeds the router real data
+            assert!(executor
+                .validate_runtime(py, 3.to_object(py), &RuntimeCheck::new())
+                .is_err());
+            let check =
+                RuntimeCheck::new().with_stub("fetch", NodeFunc::Constant(50.to_object(py)));
+            let exercised = executor
+                .validate_runtime(py, 3.to_object(py), &check)
+                .unwrap();
+            assert_eq!(exercised, BTreeSet::from(["big".into(), "fetch".into()]));
+            assert!(!executor.state().has_channel("n"));
+            assert!(executor.run_id().is_none());
+
+            let check = RuntimeCheck::new().with_stub("missing", NodeFunc::Passthrough);
+            let err = executor
+                .validate_runtime(py, 3.to_object(py), &check)
+                .unwrap_err();
+            assert!(err.to_string().contains("'missing'"));
+
+            // A loop that never converges on its own
+            let mut looping = PregelCore::new();
+            looping.add_node(node("spin", func("spin"), "n", "n"));
+            looping.add_edge(Edge::direct("spin".to_string(), "spin".to_string()));
+            looping.set_entry_point("spin".to_string());
+            looping.set_input_channels(vec!["n".to_string()]);
+            looping.set_recursion_limit(10_000);
+            let check = RuntimeCheck::new().with_timeout(Duration::from_millis(100));
+            let start = Instant::now();
+            let err = looping
+                .validate_runtime(py, 1.to_object(py), &check)
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTimeoutError>(py));
+            assert!(start.elapsed() < Duration::from_secs(2));
+
+            // A single node that outlives the deadline is abandoned mid-step
+            let mut hanging = PregelCore::new();
+            hanging.add_node(node("hang", func("hang"), "n", "n"));
+            hanging.set_entry_point("hang".to_string());
+            hanging.set_input_channels(vec!["n".to_string()]);
+            let start = Instant::now();
+            let err = hanging
+                .validate_runtime(py, 1.to_object(py), &check)
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTimeoutError>(py));
+            assert!(start.elapsed() < Duration::from_secs(2));
+        });
+    }
+
+    #[test]
+    fn test_when_available_edges() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let summarize = py.eval("lambda r: len(r)", None, None).unwrap();
+            let log = py.eval("lambda q: 'searched'", None, None).unwrap();
+            let build = || {
+                let mut executor = PregelCore::new();
+                executor.add_node(Node::with_inputs(
+                    "search".to_string(),
+                    &["query"],
+                    |py, values| {
+                        let query: String = values[0].extract(py)?;
+                        let mut writes = HashMap::new();
+                        if !query.is_empty() {
+                            writes.insert("results".to_string(), vec![query].to_object(py));
+                        }
+                        Ok(writes)
+                    },
+                ));
+                executor.add_node(Node::with_channels(
+                    "has_results".to_string(),
+                    summarize.to_object(py),
+                    Some(vec!["results".to_string()]),
+                    Some(vec!["summary".to_string()]),
+                ));
+                executor.add_node(Node::with_channels(
+                    "log".to_string(),
+                    log.to_object(py),
+                    Some(vec!["query".to_string()]),
+                    Some(vec!["status".to_string()]),
+                ));
+                executor.add_edge(Edge::when_available(
+                    "search".to_string(),
+                    "has_results".to_string(),
+                    "results".to_string(),
+                ));
+                executor.add_edge(Edge::direct("search".to_string(), "log".to_string()));
+                executor.set_entry_point("search".to_string());
+                executor.set_input_channels(vec!["query".to_string()]);
+                executor
+            };
+
+            let output = build().invoke(py, "rust".to_object(py), None).unwrap();
+            let output = output.downcast::<pyo3::types::PyDict>(py).unwrap();
+            assert_eq!(
+                output.get_item("summary").unwrap().unwrap().to_string(),
+                "1"
+            );
+            assert_eq!(
+                output.get_item("status").unwrap().unwrap().to_string(),
+                "searched"
+            );
+
+            // Nothing written to results: only the direct edge fires
+            let output = build().invoke(py, "".to_object(py), None).unwrap();
+            let output = output.downcast::<pyo3::types::PyDict>(py).unwrap();
+            assert!(!output.contains("summary").unwrap());
+            assert_eq!(
+                output.get_item("status").unwrap().unwrap().to_string(),
+                "searched"
+            );
+        });
+    }
+
+    #[test]
+    fn test_tracing_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+        use tracing_subscriber::registry::LookupSpan;
+
+        /// Records spans as "parent/name" paths
+        struct SpanRecorder(Arc<Mutex<Vec<String>>>);
+
+        impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+        where
+            S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(
+                &self,
+                _attrs: &tracing::span::Attributes<'_>,
+                id: &tracing::span::Id,
+                ctx: Context<'_, S>,
+            ) {
+                let span = ctx.span(id).unwrap();
+                let path = match span.parent() {
+                    Some(parent) => format!("{}/{}", parent.name(), span.name()),
+                    None => span.name().to_string(),
+                };
+                self.0.lock().unwrap().push(path);
+            }
+        }
+
+        pyo3::prepare_freethreaded_python();
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanRecorder(spans.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            Python::with_gil(|py| {
+                let mut executor = PregelCore::new();
+                let func = py.eval("lambda x: {'out': 1}", None, None).unwrap();
+                executor.add_node(Node::new("a".to_string(), func.to_object(py)));
+                executor.add_node(Node::new("b".to_string(), func.to_object(py)));
+                executor.add_edge(Edge::direct("a".to_string(), "b".to_string()));
+                executor.set_entry_point("a".to_string());
+                executor.invoke(py, py.None(), None).unwrap();
+            });
+        });
+
+        let spans = spans.lock().unwrap();
+        let count = |path: &str| spans.iter().filter(|p| *p == path).count();
+        assert_eq!(count("superstep"), 2);
+        assert_eq!(count("superstep/node"), 2);
+    }
+
+    #[test]
+    fn test_metrics_opt_in() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            let func = py.eval("lambda x: {'out': 1}", None, None).unwrap();
+            executor.add_node(
+                Node::new("a".to_string(), func.to_object(py)).with_tags(vec!["llm".to_string()]),
+            );
+            executor.set_entry_point("a".to_string());
+
+            executor.invoke(py, py.None(), None).unwrap();
+            assert!(executor.metrics().nodes.is_empty());
+
+            executor.enable_metrics();
+            executor.invoke(py, py.None(), None).unwrap();
+            executor.invoke(py, py.None(), None).unwrap();
+
+            let snapshot = executor.metrics();
+            assert_eq!(snapshot.nodes["a"].invocations, 2);
+            assert_eq!(snapshot.nodes["a"].retries, 0);
+            assert_eq!(snapshot.nodes["a"].tags, ["llm"]);
+        });
+    }
+
+    #[test]
+    fn test_cancel_discards_inflight_step() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let cancel = CancellationToken::new();
+            let token = cancel.clone();
+            let cancelling =
+                pyo3::types::PyCFunction::new_closure(py, None, None, move |args, _| {
+                    token.cancel();
+                    args.get_item(0).map(|x| x.to_object(args.py()))
+                })
+                .unwrap();
+            let double = py.eval("lambda x: x * 2", None, None).unwrap();
+            let node = |name: &str, func: &PyAny, input: &str, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    Some(vec![input.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+
+            let mut executor = PregelCore::new();
+            executor.add_node(node("first", double, "n", "a"));
+            executor.add_node(node("second", cancelling, "a", "b"));
+            executor.add_node(node("third", double, "b", "c"));
+            executor.add_edge(Edge::direct("first".to_string(), "second".to_string()));
+            executor.add_edge(Edge::direct("second".to_string(), "third".to_string()));
+            executor.set_entry_point("first".to_string());
+            executor.set_input_channels(vec!["n".to_string()]);
+
+            let err = executor
+                .invoke_with_cancel(py, 5.to_object(py), None, &cancel)
+                .unwrap_err();
+            assert!(err.to_string().contains("after 1 committed step"));
+
+            // The step that observed cancellation is not committed
+            let checkpoint = executor.checkpoint(py).unwrap();
+            assert_eq!(checkpoint["a"].extract::<i32>(py).unwrap(), 10);
+            assert!(!checkpoint.contains_key("b"));
+            assert!(!checkpoint.contains_key("c"));
+
+            // An already-cancelled token runs nothing
+            let err = executor
+                .invoke_with_cancel(py, 1.to_object(py), None, &cancel)
+                .unwrap_err();
+            assert!(err.to_string().contains("after 0 committed step"));
+        });
+    }
+
+    #[test]
+    fn test_resume_after_cancel() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let cancel = CancellationToken::new();
+            let token = cancel.clone();
+            let cancelling =
+                pyo3::types::PyCFunction::new_closure(py, None, None, move |args, _| {
+                    token.cancel();
+                    args.get_item(0).map(|x| x.to_object(args.py()))
+                })
+                .unwrap();
+            let double = py.eval("lambda x: x * 2", None, None).unwrap();
+            let node = |name: &str, func: &PyAny, input: &str, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    Some(vec![input.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+
+            let mut executor = PregelCore::new();
+            executor.add_node(node("first", double, "n", "a"));
+            executor.add_node(node("second", cancelling, "a", "b"));
+            executor.add_node(node("third", double, "b", "c"));
+            executor.add_edge(Edge::direct("first".to_string(), "second".to_string()));
+            executor.add_edge(Edge::direct("second".to_string(), "third".to_string()));
+            executor.set_entry_point("first".to_string());
+            executor.set_input_channels(vec!["n".to_string()]);
+            executor.set_output_channels(OutputChannels::Single("c".to_string()));
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = executor.compile(Some(checkpointer)).unwrap();
+
+            // The error names the checkpoint of the last committed step
+            let err = executor
+                .invoke_with_cancel(py, 5.to_object(py), None, &cancel)
+                .unwrap_err();
+            assert!(err.is_instance_of::<crate::errors::GraphCancelled>(py));
+            let checkpoint_id: String = err
+                .value(py)
+                .getattr("checkpoint_id")
+                .unwrap()
+                .extract()
+                .unwrap();
+            let history = executor.state_history(py).unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].id, checkpoint_id);
+            assert_eq!(history[0].next, ["second"]);
+
+            // Resuming runs the cancelled step again and finishes the run
+            let output = executor.invoke_from_checkpoint(py, &checkpoint_id).unwrap();
+            assert_eq!(output.extract::<i32>(py).unwrap(), 20);
+            let history = executor.state_history(py).unwrap();
+            assert_eq!(
+                history[1].parent_id.as_deref(),
+                Some(checkpoint_id.as_str())
+            );
+
+            let err = executor.invoke_from_checkpoint(py, "missing").unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("no saved snapshot with ID 'missing'"));
+        });
+    }
+
+    #[test]
+    fn test_state_schema() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let schema = StateSchema::new()
+                .field("question", ChannelKind::LastValue)
+                .field("answer", ChannelKind::LastValue)
+                .field("notes", ChannelKind::Topic { accumulate: true });
+            let func = py
+                .eval(
+                    "lambda q: {'answer': q.upper(), 'notes': 'seen'}",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            let mut executor = PregelCore::with_schema(schema.clone());
+            assert!(executor.state().has_channel("notes"));
+            executor.add_node(
+                Node::with_channels(
+                    "respond".to_string(),
+                    func.to_object(py),
+                    Some(vec!["question".to_string()]),
+                    Some(vec!["answer".to_string(), "notes".to_string()]),
+                )
+                .with_write_channels(vec!["answer".to_string(), "notes".to_string()]),
+            );
+            executor.set_entry_point("respond".to_string());
+            executor.set_input_channels(vec!["question".to_string()]);
+            let mut executor = executor.compile(None).unwrap();
+
+            let output = executor.invoke(py, "hi".to_object(py), None).unwrap();
+            let output: &pyo3::types::PyDict = output.downcast(py).unwrap();
+            let answer = output.get_item("answer").unwrap().unwrap();
+            assert_eq!(answer.extract::<String>().unwrap(), "HI");
+
+            // Declared writes outside the schema fail compilation
+            let mut executor = PregelCore::with_schema(schema.clone());
+            executor.add_node(Node::with_channels(
+                "respond".to_string(),
+                func.to_object(py),
+                None,
+                Some(vec!["answr".to_string()]),
+            ));
+            let err = executor.compile(None).unwrap_err();
+            assert!(matches!(
+                err,
+                GraphError::ValidationFailed(issues) if issues == vec![ValidationIssue::UnknownStateField {
+                    node: "respond".to_string(),
+                    field: "answr".to_string(),
+                }]
+            ));
+
+            // Undeclared writes are rejected at run time instead of creating a channel
+            let mut executor = PregelCore::with_schema(schema);
+            executor.add_node(Node::with_inputs(
+                "respond".to_string(),
+                &["question"],
+                |_, values| Ok(HashMap::from([("answr".to_string(), values[0].clone())])),
+            ));
+            executor.set_entry_point("respond".to_string());
+            executor.set_input_channels(vec!["question".to_string()]);
+            let mut executor = executor.compile(None).unwrap();
+            let err = executor.invoke(py, "hi".to_object(py), None).unwrap_err();
+            assert!(err.to_string().contains("'answr'"));
+            assert!(!executor.state().has_channel("answr"));
+        });
+    }
+
+    #[test]
+    fn test_channel_defaults_seed_first_run() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let increment = py.eval("lambda c: c + 1", None, None).unwrap();
+            let build = || {
+                let mut executor = PregelCore::new();
+                executor.add_channel(
+                    "count".to_string(),
+                    Box::new(LastValueChannel::with_default(0.to_object(py))),
+                );
+                executor.add_node(Node::with_channels(
+                    "increment".to_string(),
+                    increment.to_object(py),
+                    Some(vec!["count".to_string()]),
+                    Some(vec!["count".to_string()]),
+                ));
+                executor.set_entry_point("increment".to_string());
+                executor.set_output_channels(OutputChannels::Single("count".to_string()));
+                executor
+            };
+
+            // The node reads the default although nothing wrote the channel
+            let mut executor = build();
+            let count = executor.invoke(py, py.None(), None).unwrap();
+            assert_eq!(count.extract::<i32>(py).unwrap(), 1);
+
+            // Resuming from a checkpoint keeps the saved value
+            let checkpoint = executor.checkpoint(py).unwrap();
+            let mut resumed = build();
+            resumed.from_checkpoint(py, checkpoint).unwrap();
+            let count = resumed.invoke(py, py.None(), None).unwrap();
+            assert_eq!(count.extract::<i32>(py).unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn test_builtin_nodes() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            for channel in ["greeting", "message"] {
+                executor.add_channel(channel.to_string(), Box::new(LastValueChannel::new()));
+            }
+            executor.add_node(Node::constant(
+                "defaults".to_string(),
+                "greeting",
+                "hello".to_object(py),
+            ));
+            executor.add_node(Node::passthrough(
+                "rename".to_string(),
+                "greeting",
+                "message",
+            ));
+            executor.add_edge(Edge::direct("defaults".to_string(), "rename".to_string()));
+            executor.set_entry_point("defaults".to_string());
+            executor.set_output_channels(OutputChannels::Single("message".to_string()));
+
+            let message = executor.invoke(py, py.None(), None).unwrap();
+            assert_eq!(message.extract::<String>(py).unwrap(), "hello");
+            let greeting = executor.state().get_value(py, "greeting").unwrap();
+            assert_eq!(greeting.extract::<String>(py).unwrap(), "hello");
+        });
+    }
+
+    #[test]
+    fn test_guard_node() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let positive = py
+                .eval("lambda state: state['total'] > 0", None, None)
+                .unwrap();
+            let build = |action: GuardAction| {
+                let mut executor = PregelCore::new();
+                executor.add_channel("total".to_string(), Box::new(LastValueChannel::new()));
+                executor.add_channel("report".to_string(), Box::new(LastValueChannel::new()));
+                executor.add_node(
+                    Node::guard(
+                        "check".to_string(),
+                        positive.to_object(py),
+                        "total must be positive",
+                    )
+                    .with_guard_action(action),
+                );
+                executor.add_node(Node::passthrough("publish".to_string(), "total", "report"));
+                executor.add_edge(Edge::direct("check".to_string(), "publish".to_string()));
+                executor.set_entry_point("check".to_string());
+                executor.set_input_channels(vec!["total".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("report".to_string()));
+                executor
+            };
+            let input = |total: i32| {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("total", total).unwrap();
+                dict.to_object(py)
+            };
+
+            // A passing guard writes nothing and lets the run continue
+            let mut executor = build(GuardAction::Error);
+            let report = executor.invoke(py, input(3), None).unwrap();
+            assert_eq!(report.extract::<i32>(py).unwrap(), 3);
+
+            let err = executor.invoke(py, input(-1), None).unwrap_err();
+            assert!(err.is_instance_of::<GuardFailed>(py));
+            assert!(err.to_string().contains("total must be positive"));
+            assert!(!executor.is_interrupted());
+
+            // An interrupting guard can be resumed once the state is fixed
+            let mut executor = build(GuardAction::Interrupt);
+            let err = executor.invoke(py, input(-1), None).unwrap_err();
+            assert!(err.is_instance_of::<crate::errors::GraphInterrupted>(py));
+            assert!(executor.is_interrupted());
+            assert!(executor.state().get_value(py, "report").is_none());
+
+            executor
+                .state_mut()
+                .update_channel(py, "total", 5.to_object(py))
+                .unwrap();
+            let report = executor.resume(py).unwrap();
+            assert_eq!(report.extract::<i32>(py).unwrap(), 5);
+            assert!(executor.resume(py).is_err());
+        });
+    }
+
+    #[test]
+    fn test_invoke_with_trace() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+calls = []
+def fetch(_):
+    calls.append("fetch")
+    return len(calls) * 100
+def left(x):
+    calls.append("left")
+    return x + 1
+def right(x):
+    calls.append("right")
+    return x + 2
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let node = |name: &str, input: Option<&str>, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    func(name),
+                    input.map(|channel| vec![channel.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+
+            let mut executor = PregelCore::new();
+            executor.add_node(node("fetch", None, "raw"));
+            executor.add_node(node("right", Some("raw"), "r"));
+            executor.add_node(node("left", Some("raw"), "l"));
+            // Scheduled right first; the trace still lists left first
+            executor.add_edge(Edge::direct("fetch".to_string(), "right".to_string()));
+            executor.add_edge(Edge::direct("fetch".to_string(), "left".to_string()));
+            executor.set_entry_point("fetch".to_string());
+            executor.set_output_channels(OutputChannels::Multiple(vec![
+                "l".to_string(),
+                "r".to_string(),
+            ]));
+
+            let (output, trace) = executor.invoke_with_trace(py, py.None(), None).unwrap();
+            let output = output.as_ref(py).to_string();
+            assert_eq!(output, "{'l': 101, 'r': 102}");
+            let step_two: Vec<String> = trace
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    ExecutionEvent::NodeInput { step: 2, node, .. } => Some(format!("in:{}", node)),
+                    ExecutionEvent::NodeOutput { step: 2, node, .. } => {
+                        Some(format!("out:{}", node))
+                    }
+                    ExecutionEvent::ChannelWrite {
+                        step: 2, channel, ..
+                    } => Some(format!("write:{}", channel)),
+                    ExecutionEvent::Checkpoint { step: 2, next, .. } => {
+                        Some(format!("checkpoint:{:?}", next))
+                    }
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                step_two,
+                [
+                    "in:left",
+                    "in:right",
+                    "out:left",
+                    "out:right",
+                    "write:l",
+                    "write:r",
+                    "checkpoint:[]"
+                ]
+            );
+            assert!(trace.events.contains(&ExecutionEvent::NodeInput {
+                step: 2,
+                node: "left".to_string(),
+                input: json!(100),
+            }));
+
+            // The JSON form replays the run without calling any node
+            let trace = Trace::from_json(&trace.to_json().unwrap()).unwrap();
+            py.run("calls.clear()", Some(locals), None).unwrap();
+            let replayed = executor
+                .replay(py, &trace.to_history(py), usize::MAX)
+                .unwrap();
+            assert_eq!(replayed.as_ref(py).to_string(), output);
+            assert!(!locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .is_true()
+                .unwrap());
+
+            let err = executor
+                .invoke_with_trace(
+                    py,
+                    pyo3::types::PySet::empty(py).unwrap().to_object(py),
+                    None,
+                )
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn test_replay_recorded_run() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+calls = []
+def fetch(_):
+    calls.append("fetch")
+    return len(calls) * 100  # differs on every call, like an external API
+def double(x):
+    calls.append("double")
+    return x * 2
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let calls = |executor: &mut PregelCore, run: &dyn Fn(&mut PregelCore) -> PyObject| {
+                py.run("calls.clear()", Some(locals), None).unwrap();
+                let output = run(executor).extract::<i32>(py).unwrap();
+                let calls: Vec<String> = locals
+                    .get_item("calls")
+                    .unwrap()
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                (output, calls)
+            };
+
+            let mut executor = PregelCore::new();
+            for channel in ["raw", "doubled"] {
+                executor.add_channel(channel.to_string(), Box::new(LastValueChannel::new()));
+            }
+            executor.add_node(Node::with_channels(
+                "fetch".to_string(),
+                func("fetch"),
+                None,
+                Some(vec!["raw".to_string()]),
+            ));
+            executor.add_node(Node::with_channels(
+                "double".to_string(),
+                func("double"),
+                Some(vec!["raw".to_string()]),
+                Some(vec!["doubled".to_string()]),
+            ));
+            executor.add_edge(Edge::direct("fetch".to_string(), "double".to_string()));
+            executor.set_entry_point("fetch".to_string());
+            executor.set_output_channels(OutputChannels::Single("doubled".to_string()));
+            executor.enable_history();
+
+            let (output, _) = calls(&mut executor, &|ex| ex.invoke(py, py.None(), None).unwrap());
+            assert_eq!(output, 200);
+            let history = executor.history().unwrap().clone();
+            assert_eq!(history.steps.len(), 2);
+
+            // The recorded fetch output is fed back; double runs live
+            let (output, ran) = calls(&mut executor, &|ex| ex.replay(py, &history, 1).unwrap());
+            assert_eq!((output, ran), (200, vec!["double".to_string()]));
+
+            // Replaying the whole history calls nothing
+            let (output, ran) = calls(&mut executor, &|ex| ex.replay(py, &history, 2).unwrap());
+            assert_eq!((output, ran), (200, Vec::<String>::new()));
+
+            // Replay boundary 0 runs everything live again
+            let (output, _) = calls(&mut executor, &|ex| ex.replay(py, &history, 0).unwrap());
+            assert_eq!(output, 200);
+
+            let mut diverged = history.clone();
+            let outputs = diverged.steps[0].outputs.remove("fetch").unwrap();
+            diverged.steps[0]
+                .outputs
+                .insert("other".to_string(), outputs);
+            let err = executor.replay(py, &diverged, 2).unwrap_err();
+            assert!(err.to_string().contains("Replay diverged at step 1"));
+        });
+    }
+
+    #[test]
+    fn test_conditional_entry_point() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let router = py
+                .eval(
+                    "lambda s: '__end__' if s['query'] is None else \
+                     'number' if isinstance(s['query'], int) else ['text', 'audit']",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let double = py.eval("lambda q: q * 2", None, None).unwrap();
+            let upper = py.eval("lambda q: q.upper()", None, None).unwrap();
+
+            let mut executor = PregelCore::new();
+            executor.add_node(Node::with_channels(
+                "math".to_string(),
+                double.to_object(py),
+                Some(vec!["query".to_string()]),
+                Some(vec!["answer".to_string()]),
+            ));
+            executor.add_node(Node::with_channels(
+                "text".to_string(),
+                upper.to_object(py),
+                Some(vec!["query".to_string()]),
+                Some(vec!["answer".to_string()]),
+            ));
+            executor.add_node(Node::constant(
+                "audit".to_string(),
+                "audited",
+                true.to_object(py),
+            ));
+            let branches = HashMap::from([
+                ("number".to_string(), "math".to_string()),
+                ("text".to_string(), "text".to_string()),
+                ("audit".to_string(), "audit".to_string()),
+                (END.to_string(), END.to_string()),
+            ]);
+            executor.add_conditional_edges(START, router.to_object(py), branches);
+            executor.set_input_channels(vec!["query".to_string()]);
+            executor.set_output_channels(OutputChannels::Multiple(vec![
+                "answer".to_string(),
+                "audited".to_string(),
+            ]));
+
+            let run = |executor: &mut PregelCore, query: PyObject| {
+                let input = pyo3::types::PyDict::new(py);
+                input.set_item("query", query).unwrap();
+                let output = executor.invoke(py, input.into(), None).unwrap();
+                output.extract::<HashMap<String, PyObject>>(py).unwrap()
+            };
+
+            let output = run(&mut executor, 21.to_object(py));
+            assert_eq!(output["answer"].extract::<i32>(py).unwrap(), 42);
+            assert!(!output.contains_key("audited"));
+
+            // Several start nodes run in the first superstep
+            let output = run(&mut executor, "hi".to_object(py));
+            assert_eq!(output["answer"].extract::<String>(py).unwrap(), "HI");
+            assert!(output["audited"].extract::<bool>(py).unwrap());
+
+            // Routing to END runs nothing
+            let mut fresh = PregelCore::new();
+            fresh.add_conditional_edges(START, router.to_object(py), HashMap::new());
+            fresh.set_input_channels(vec!["query".to_string()]);
+            fresh.set_output_channels(OutputChannels::Multiple(vec!["answer".to_string()]));
+            assert!(run(&mut fresh, py.None()).is_empty());
+
+            let input = pyo3::types::PyDict::new(py);
+            input.set_item("query", 1).unwrap();
+            let plan = executor.plan(py, input.into()).unwrap();
+            assert_eq!(plan.steps, vec![vec!["audit", "math", "text"]]);
+        });
+    }
+
+    #[test]
+    fn test_router_sends_fan_out() {
+        use super::super::channel::TopicChannel;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+calls = []
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+def route(state):
+    return [Send("worker", topic) for topic in state["topics"]] + ["audit"]
+def worker(topic):
+    calls.append(topic)
+    return topic.upper()
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+
+            let build = || {
+                let mut executor = PregelCore::new();
+                executor.add_node(Node::constant(
+                    "plan".to_string(),
+                    "planned",
+                    true.to_object(py),
+                ));
+                executor.add_node(Node::with_channels(
+                    "worker".to_string(),
+                    get("worker"),
+                    None,
+                    Some(vec!["results".to_string()]),
+                ));
+                executor.add_node(Node::constant(
+                    "audit".to_string(),
+                    "audited",
+                    true.to_object(py),
+                ));
+                executor.add_channel("results".to_string(), Box::new(TopicChannel::new(true)));
+                executor.set_entry_point("plan".to_string());
+                executor.add_conditional_edges("plan", get("route"), HashMap::new());
+                executor.set_input_channels(vec!["topics".to_string()]);
+                executor.set_output_channels(OutputChannels::Multiple(vec![
+                    "results".to_string(),
+                    "audited".to_string(),
+                ]));
+                executor.enable_history();
+                executor
+            };
+            let mut executor = build();
+
+            // Each Send runs the worker on its own topic, beside the routed node
+            let input = pyo3::types::PyDict::new(py);
+            input.set_item("topics", vec!["a", "b", "c"]).unwrap();
+            let output = executor.invoke_sync(py, input.into(), None).unwrap();
+            let output = output.extract::<HashMap<String, PyObject>>(py).unwrap();
+            let mut results: Vec<String> = output["results"].extract(py).unwrap();
+            results.sort();
+            assert_eq!(results, ["A", "B", "C"]);
+            assert!(output["audited"].extract::<bool>(py).unwrap());
+
+            // The sends are recorded apart and replay without calling the worker
+            let history = executor.history().unwrap().clone();
+            let mut keys: Vec<&String> = history.steps[1].outputs.keys().collect();
+            keys.sort();
+            assert_eq!(
+                keys,
+                ["audit", "worker:send:0", "worker:send:1", "worker:send:2"]
+            );
+            let replayed = build().replay(py, &history, usize::MAX).unwrap();
+            let replayed = replayed.extract::<HashMap<String, PyObject>>(py).unwrap();
+            let mut results: Vec<String> = replayed["results"].extract(py).unwrap();
+            results.sort();
+            assert_eq!(results, ["A", "B", "C"]);
+            let calls: Vec<String> = get("calls").extract(py).unwrap();
+            assert_eq!(calls, ["a", "b", "c"]);
+        });
+    }
+    #[test]
+    fn test_streamed_input() {
+        use super::super::channel::TopicChannel;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+calls = []
+def read(chunks):
+    calls.append(("read", len(chunks)))
+    return "".join(chunks)
+def report(text):
+    calls.append(("report", text))
+    return text.upper()
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let build = |input: Box<dyn Channel>| {
+                let mut executor = PregelCore::new();
+                executor.add_channel("chunks".to_string(), input);
+                executor.add_node(Node::with_channels(
+                    "read".to_string(),
+                    func("read"),
+                    Some(vec!["chunks".to_string()]),
+                    Some(vec!["text".to_string()]),
+                ));
+                executor.add_node(Node::with_channels(
+                    "report".to_string(),
+                    func("report"),
+                    Some(vec!["text".to_string()]),
+                    Some(vec!["output".to_string()]),
+                ));
+                executor.add_edge(Edge::direct("read".to_string(), "report".to_string()));
+                executor.set_entry_point("read".to_string());
+                executor.set_input_channels(vec!["chunks".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
+                executor
+            };
+            let chunks = || futures::stream::iter(["a", "b", "c"].map(|c| c.to_object(py)));
+
+            let mut executor = build(Box::new(TopicChannel::new(true)));
+            let output = executor.invoke_stream_input(py, chunks(), None).unwrap();
+            assert_eq!(output.extract::<String>(py).unwrap(), "ABC");
+
+            // The entry node sees each partial input, the rest runs once
+            let calls: Vec<(String, PyObject)> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            let calls: Vec<String> = calls
+                .iter()
+                .map(|(node, arg)| format!("{}:{}", node, arg.as_ref(py)))
+                .collect();
+            assert_eq!(calls, ["read:1", "read:2", "read:3", "report:abc"]);
+
+            // Steps are numbered across the stream and the steps after it
+            let steps = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let mut executor = build(Box::new(TopicChannel::new(true)));
+            let seen = steps.clone();
+            executor.on_barrier(move |_, step, _, _| {
+                seen.lock().unwrap().push(step);
+                Ok(())
+            });
+            executor.invoke_stream_input(py, chunks(), None).unwrap();
+            assert_eq!(*steps.lock().unwrap(), [1, 2, 3, 4]);
+
+            // and share one recursion budget
+            let mut executor = build(Box::new(TopicChannel::new(true)));
+            executor.set_recursion_limit(3);
+            let err = executor
+                .invoke_stream_input(py, chunks(), None)
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyRecursionError>(py));
+            executor.set_recursion_limit(4);
+            executor.invoke_stream_input(py, chunks(), None).unwrap();
+
+            // Channels that replace their value can't take streamed input
+            let mut executor = build(Box::<LastValueChannel>::default());
+            let err = executor
+                .invoke_stream_input(py, chunks(), None)
+                .unwrap_err();
+            assert!(err.to_string().contains("'chunks' does not accumulate"));
+
+            let mut executor = build(Box::new(TopicChannel::new(true)));
+            let err = executor
+                .invoke_stream_input(py, futures::stream::empty(), None)
+                .unwrap_err();
+            assert!(err.to_string().contains("'chunks'"));
+        });
+    }
+
+    #[test]
+    fn test_subscribe_channel() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+def plan(x):
+    return "planned"
+def act(status):
+    return "done"
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let mut executor = PregelCore::new();
+            executor.add_node(Node::with_channels(
+                "plan".to_string(),
+                func("plan"),
+                Some(vec!["input".to_string()]),
+                Some(vec!["status".to_string()]),
+            ));
+            executor.add_node(Node::with_channels(
+                "act".to_string(),
+                func("act"),
+                Some(vec!["status".to_string()]),
+                Some(vec!["status".to_string()]),
+            ));
+            executor.add_edge(Edge::direct("plan".to_string(), "act".to_string()));
+            executor.set_entry_point("plan".to_string());
+            executor.set_input_channels(vec!["input".to_string()]);
+
+            let first = executor.subscribe("status");
+            let second = executor.subscribe("status");
+            // A dropped subscription doesn't hold up the run
+            drop(executor.subscribe("status"));
+            let input = executor.subscribe("input");
+
+            executor.invoke(py, 1.to_object(py), None).unwrap();
+            drop(executor);
+
+            let collect = |stream| {
+                futures::executor::block_on(StreamExt::collect::<Vec<PyObject>>(stream))
+                    .iter()
+                    .map(|value| value.as_ref(py).to_string())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(collect(first), ["planned", "done"]);
+            assert_eq!(collect(second), ["planned", "done"]);
+            assert_eq!(collect(input), ["1"]);
+        });
+    }
+
+    #[test]
+    fn test_compile_with_checkpointer() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+calls = []
+def draft(x):
+    calls.append("draft")
+    return x + 1
+def review(x):
+    calls.append("review")
+    return x * 10
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let build = || {
+                let mut executor = PregelCore::new();
+                for (name, input, output) in
+                    [("draft", "input", "draft"), ("review", "draft", "output")]
+                {
+                    executor.add_node(Node::with_channels(
+                        name.to_string(),
+                        locals.get_item(name).unwrap().unwrap().to_object(py),
+                        Some(vec![input.to_string()]),
+                        Some(vec![output.to_string()]),
+                    ));
+                }
+                executor.add_edge(Edge::direct("draft".to_string(), "review".to_string()));
+                executor.set_entry_point("draft".to_string());
+                executor.set_input_channels(vec!["input".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
+                executor
+            };
 
-            executor.add_node(node);
-            assert_eq!(executor.nodes.len(), 1);
+            // Without a checkpointer the graph runs stateless
+            let mut executor = build().compile(None).unwrap();
+            executor.invoke(py, 1.to_object(py), None).unwrap();
+            let err = executor.state_history(py).unwrap_err();
+            assert!(err.to_string().contains("No checkpointer"));
+            let err = executor.invoke_from_snapshot(py, 0).unwrap_err();
+            assert!(err.to_string().contains("invoke_from_snapshot"));
+
+            // Each committed superstep is saved
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = build().compile(Some(checkpointer.clone())).unwrap();
+            locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .call_method0("clear")
+                .unwrap();
+            let output = executor.invoke(py, 1.to_object(py), None).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 20);
+            let history = executor.state_history(py).unwrap();
+            assert_eq!(history.len(), checkpointer.list(py).unwrap().len());
+            let steps: Vec<(usize, Vec<String>)> =
+                history.iter().map(|s| (s.step, s.next.clone())).collect();
+            assert_eq!(steps, [(1, vec!["review".to_string()]), (2, vec![])]);
+            assert_eq!(history[0].values["draft"].extract::<i64>(py).unwrap(), 2);
+
+            // Time travel runs on from the restored state
+            executor
+                .state_mut()
+                .update_channel(py, "draft", 5.to_object(py))
+                .unwrap();
+            let output = executor.invoke_from_snapshot(py, 0).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 20);
+            let calls: Vec<String> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(calls, ["draft", "review", "review"]);
+            assert_eq!(executor.state_history(py).unwrap().len(), 3);
+            let err = executor.invoke_from_snapshot(py, 9).unwrap_err();
+            assert!(err.to_string().contains("index 9"));
         });
     }
 
     #[test]
-    fn test_add_edge() {
-        let mut executor = PregelCore::new();
-        let edge = Edge::direct("node1".to_string(), "node2".to_string());
+    fn test_concurrent_write_conflict() {
+        use super::super::channel::TopicChannel;
 
-        executor.add_edge(edge);
-        assert_eq!(executor.edges.len(), 1);
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let router = py.eval("lambda s: ['left', 'right']", None, None).unwrap();
+            let build = |channel: Option<Box<dyn Channel>>| {
+                let mut executor = PregelCore::new();
+                if let Some(channel) = channel {
+                    executor.add_channel("result".to_string(), channel);
+                }
+                for side in ["left", "right"] {
+                    executor.add_node(Node::constant(
+                        side.to_string(),
+                        "result",
+                        side.to_object(py),
+                    ));
+                }
+                executor.add_conditional_edges(START, router.to_object(py), HashMap::new());
+                executor.set_input_channels(vec!["query".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("result".to_string()));
+                executor
+            };
+
+            // Two writes to a LastValue channel in one step conflict
+            let mut executor = build(None);
+            let err = executor.invoke(py, 1.to_object(py), None).unwrap_err();
+            assert_eq!(
+                err.value(py).to_string(),
+                "Invalid update: channel 'result' takes one write per step, \
+                 but nodes 'left', 'right' wrote to it"
+            );
+            assert!(executor.state().get_value(py, "result").is_none());
+
+            // A topic merges them
+            let mut executor = build(Some(Box::new(TopicChannel::new(false))));
+            let output = executor.invoke(py, 1.to_object(py), None).unwrap();
+            let mut values: Vec<String> = output.extract(py).unwrap();
+            values.sort();
+            assert_eq!(values, ["left", "right"]);
+        });
     }
 
-    #[tokio::test]
-    async fn test_simple_execution() {
+    #[test]
+    fn test_input_validator() {
         pyo3::prepare_freethreaded_python();
 
         Python::with_gil(|py| {
-            let mut executor = PregelCore::new();
+            let double = py.eval("lambda n: n * 2", None, None).unwrap();
+            let build = |validator: InputValidator| {
+                let mut executor = PregelCore::new();
+                executor.add_node(Node::with_channels(
+                    "double".to_string(),
+                    double.to_object(py),
+                    Some(vec!["n".to_string()]),
+                    Some(vec!["output".to_string()]),
+                ));
+                executor.set_entry_point("double".to_string());
+                executor.set_input_channels(vec!["n".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
+                executor.set_input_validator(validator);
+                executor
+            };
 
-            // Create a simple node that adds 1
-            let func = py.eval("lambda x: x + 1", None, None).unwrap();
-            let node = Node::with_channels(
-                "add_one".to_string(),
-                func.to_object(py),
-                Some(vec!["input".to_string()]),
-                Some(vec!["output".to_string()]),
+            // The validator sees the input after coercion to channels
+            let native = InputValidator::Native(Arc::new(|py, writes| {
+                let n: i64 = writes.as_ref(py).get_item("n").unwrap().extract().unwrap();
+                if n < 0 {
+                    Err(format!("n must be positive, got {}", n))
+                } else {
+                    Ok(())
+                }
+            }));
+            let mut executor = build(native);
+            let output = executor.invoke(py, 2.to_object(py), None).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 4);
+            let err = executor.invoke(py, (-1).to_object(py), None).unwrap_err();
+            assert_eq!(
+                err.value(py).to_string(),
+                "Invalid input: n must be positive, got -1"
             );
 
-            // Add the node
-            executor.add_node(node);
+            // Python validators reject by returning a message or raising
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+def check(writes):
+    if writes["n"] == 0:
+        return "n is zero"
+    if writes["n"] > 100:
+        raise ValueError("n is too large")
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let check = locals.get_item("check").unwrap().unwrap().to_object(py);
+            let mut executor = build(InputValidator::Python(check));
+            assert!(executor.invoke(py, 3.to_object(py), None).is_ok());
+            for (n, message) in [(0, "n is zero"), (101, "n is too large")] {
+                let err = executor.invoke(py, n.to_object(py), None).unwrap_err();
+                assert_eq!(
+                    err.value(py).to_string(),
+                    format!("Invalid input: {}", message)
+                );
+            }
+            // Rejected input is never written
+            assert_eq!(
+                executor
+                    .state()
+                    .get_value(py, "n")
+                    .unwrap()
+                    .extract::<i64>(py)
+                    .unwrap(),
+                3
+            );
+        });
+    }
 
-            // Add channels
-            executor.add_channel("input".to_string(), Box::new(LastValueChannel::new()));
-            executor.add_channel("output".to_string(), Box::new(LastValueChannel::new()));
+    #[test]
+    fn test_state_json_schema() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let schema = StateSchema::new()
+                .typed_field(
+                    "log",
+                    ChannelKind::Topic { accumulate: true },
+                    ValueType::String,
+                )
+                .field("blob", ChannelKind::LastValue);
+            let mut core = PregelCore::with_schema(schema);
+            core.state.add_channel(
+                "count".to_string(),
+                Box::new(
+                    LastValueChannel::with_default(3i64.into_py(py))
+                        .with_value_type(ValueType::Integer),
+                ),
+            );
 
-            // Set entry point
+            let json = core.state_json_schema(py);
+            assert_eq!(json["type"], "object");
+            let properties = json["properties"].as_object().unwrap();
+            assert_eq!(properties.len(), 3);
+            assert_eq!(properties["count"]["type"], "integer");
+            assert_eq!(properties["count"]["default"], 3);
+            assert_eq!(properties["count"]["x-reducer"], false);
+            assert_eq!(properties["log"]["type"], "array");
+            assert_eq!(properties["log"]["items"]["type"], "string");
+            assert_eq!(properties["log"]["x-reducer"], true);
+            assert_eq!(properties["blob"]["type"], "object");
+        });
+    }
+
+    #[tokio::test]
+    async fn test_invoke_sync() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut executor = PregelCore::new();
+            for (name, func, read, write) in [
+                ("add_one", "lambda x: x + 1", "input", "middle"),
+                ("double", "lambda x: x * 2", "middle", "output"),
+            ] {
+                executor.add_node(Node::with_channels(
+                    name.to_string(),
+                    py.eval(func, None, None).unwrap().to_object(py),
+                    Some(vec![read.to_string()]),
+                    Some(vec![write.to_string()]),
+                ));
+            }
+            executor.add_edge(Edge::direct("add_one".to_string(), "double".to_string()));
             executor.set_entry_point("add_one".to_string());
+            executor.set_input_channels(vec!["input".to_string()]);
+            executor.set_output_channels(OutputChannels::Single("output".to_string()));
 
-            // Set initial input
-            executor
-                .state_mut()
-                .update_channel(py, "input", 42.to_object(py))
-                .unwrap();
+            // Runs inside the test's runtime, where a blocking invoke would panic
+            let output = executor.invoke_sync(py, 5.to_object(py), None).unwrap();
+            assert_eq!(output.extract::<i32>(py).unwrap(), 12);
+        });
+    }
 
-            // Execute
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                executor
-                    .execute_from(py, "add_one".to_string())
-                    .await
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_rayon_backend_runs_step_in_parallel() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            // Each node waits for the other two, which only returns if all
+            // three run at once; waiting releases the GIL
+            py.run(
+                r#"
+import threading
+barrier = threading.Barrier(3, timeout=5)
+def make(n):
+    def node(x):
+        barrier.wait()
+        return (threading.get_ident(), x * n)
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut executor = PregelCore::new()
+                .with_parallel_backend(ParallelBackend::Rayon { threads: 3 })
+                .unwrap();
+            executor.add_node(Node::with_channels(
+                "start".to_string(),
+                py.eval("lambda x: x", None, None).unwrap().to_object(py),
+                Some(vec!["input".to_string()]),
+                Some(vec!["start".to_string()]),
+            ));
+            executor.set_entry_point("start".to_string());
+            let names = ["times_1", "times_2", "times_3"];
+            for (n, name) in names.iter().enumerate() {
+                let func = py
+                    .eval(&format!("make({})", n + 1), Some(locals), None)
                     .unwrap();
-            });
+                executor.add_node(Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    Some(vec!["start".to_string()]),
+                    Some(vec![name.to_string()]),
+                ));
+                executor.add_edge(Edge::direct("start".to_string(), name.to_string()));
+            }
+            executor.set_input_channels(vec!["input".to_string()]);
+            executor.set_output_channels(OutputChannels::Multiple(
+                names.iter().map(|name| name.to_string()).collect(),
+            ));
 
-            // Check output
-            let result = executor.state().get_value(py, "output").unwrap();
-            assert_eq!(result.extract::<i32>(py).unwrap(), 43);
+            let output = executor.invoke_sync(py, 5.to_object(py), None).unwrap();
+            let output: HashMap<String, (u64, i32)> = output.extract(py).unwrap();
+            let values: Vec<i32> = names.iter().map(|name| output[*name].1).collect();
+            assert_eq!(values, [5, 10, 15]);
+            let threads: HashSet<u64> = output.values().map(|(thread, _)| *thread).collect();
+            assert_eq!(threads.len(), 3);
         });
     }
 
-    #[tokio::test]
-    async fn test_linear_graph() {
+    #[test]
+    fn test_async_superstep_runs_nodes_together() {
         pyo3::prepare_freethreaded_python();
 
         Python::with_gil(|py| {
-            let mut executor = PregelCore::new();
+            let locals = pyo3::types::PyDict::new(py);
+            // As with rayon, each node only returns if all three run at once
+            py.run(
+                r#"
+import threading
+barrier = threading.Barrier(3, timeout=5)
+def make(n):
+    def node(x):
+        barrier.wait()
+        return x * n
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
 
-            // Node 1: x + 1
-            let func1 = py.eval("lambda x: x + 1", None, None).unwrap();
-            let node1 = Node::with_channels(
-                "add_one".to_string(),
-                func1.to_object(py),
+            let mut executor = PregelCore::new();
+            executor.add_node(Node::with_channels(
+                "start".to_string(),
+                py.eval("lambda x: x", None, None).unwrap().to_object(py),
                 Some(vec!["input".to_string()]),
-                Some(vec!["intermediate".to_string()]),
-            );
+                Some(vec!["start".to_string()]),
+            ));
+            executor.set_entry_point("start".to_string());
+            let names = ["times_1", "times_2", "times_3"];
+            for (n, name) in names.iter().enumerate() {
+                let func = py
+                    .eval(&format!("make({})", n + 1), Some(locals), None)
+                    .unwrap();
+                executor.add_node(Node::with_channels(
+                    name.to_string(),
+                    func.to_object(py),
+                    Some(vec!["start".to_string()]),
+                    Some(vec![name.to_string()]),
+                ));
+                executor.add_edge(Edge::direct("start".to_string(), name.to_string()));
+            }
+            executor.set_input_channels(vec!["input".to_string()]);
+            executor.set_output_channels(OutputChannels::Multiple(
+                names.iter().map(|name| name.to_string()).collect(),
+            ));
 
-            // Node 2: x * 2
-            let func2 = py.eval("lambda x: x * 2", None, None).unwrap();
-            let node2 = Node::with_channels(
-                "multiply_two".to_string(),
-                func2.to_object(py),
-                Some(vec!["intermediate".to_string()]),
+            let output = executor.invoke(py, 5.to_object(py), None).unwrap();
+            let output: HashMap<String, i32> = output.extract(py).unwrap();
+            let values: Vec<i32> = names.iter().map(|name| output[*name]).collect();
+            assert_eq!(values, [5, 10, 15]);
+        });
+    }
+
+    #[test]
+    fn test_with_updated_nodes() {
+        use super::super::channel::TopicChannel;
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+def draft(x):
+    return x + 1
+def review(x):
+    return x * 10
+def strict_review(x):
+    return x * 100
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let node = |name: &str, func: &str, input: &str, output: &str| {
+                Node::with_channels(
+                    name.to_string(),
+                    locals.get_item(func).unwrap().unwrap().to_object(py),
+                    Some(vec![input.to_string()]),
+                    Some(vec![output.to_string()]),
+                )
+            };
+            let build = || {
+                let mut executor = PregelCore::new();
+                executor.add_node(node("draft", "draft", "input", "draft"));
+                executor.add_node(node("review", "review", "draft", "output"));
+                executor.add_edge(Edge::direct("draft".to_string(), "review".to_string()));
+                executor.set_entry_point("draft".to_string());
+                executor.set_input_channels(vec!["input".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
+                executor
+            };
+
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = build().compile(Some(checkpointer.clone())).unwrap();
+            let output = executor.invoke(py, 1.to_object(py), None).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 20);
+
+            // The new review code continues from the snapshot taken after draft
+            let mut executor = executor
+                .with_updated_nodes(py, [node("review", "strict_review", "draft", "output")])
+                .unwrap();
+            let output = executor.invoke_from_snapshot(py, 0).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 200);
+
+            // Topology changes are rejected
+            let err = executor
+                .with_updated_nodes(
+                    py,
+                    [
+                        node("review", "review", "draft", "summary"),
+                        node("publish", "review", "output", "output"),
+                    ],
+                )
+                .unwrap_err();
+            let message = err.to_string();
+            assert!(message.contains("node 'review' changes its output channels"));
+            assert!(message.contains("node 'publish' does not exist"));
+
+            // A rebuilt graph sharing the checkpointer is checked against the
+            // recorded channel schema
+            build()
+                .compile(Some(checkpointer.clone()))
+                .unwrap()
+                .with_updated_nodes(py, [node("review", "review", "draft", "output")])
+                .unwrap();
+            let mut changed = build();
+            changed.add_channel("draft".to_string(), Box::new(TopicChannel::new(false)));
+            let err = changed
+                .compile(Some(checkpointer))
+                .unwrap()
+                .with_updated_nodes(py, [node("review", "review", "draft", "output")])
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("channel 'draft' was checkpointed as"));
+        });
+    }
+
+    #[test]
+    fn test_input_transform() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let upper = py.eval("lambda q: q.upper()", None, None).unwrap();
+            let build = |transform: InputTransform| {
+                let mut executor = PregelCore::new();
+                executor.add_channel(
+                    "question".to_string(),
+                    Box::new(LastValueChannel::new().with_value_type(ValueType::String)),
+                );
+                executor.add_node(Node::with_channels(
+                    "upper".to_string(),
+                    upper.to_object(py),
+                    Some(vec!["question".to_string()]),
+                    Some(vec!["output".to_string()]),
+                ));
+                executor.set_entry_point("upper".to_string());
+                executor.set_input_channels(vec!["question".to_string(), "turns".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
+                executor.with_input_transform(transform)
+            };
+
+            // A bare string becomes the writes of both input channels
+            let wrap = py
+                .eval("lambda q: {'question': q.strip(), 'turns': 0}", None, None)
+                .unwrap();
+            let mut executor = build(InputTransform::Python(wrap.to_object(py)));
+            let output = executor.invoke(py, " why? ".to_object(py), None).unwrap();
+            assert_eq!(output.extract::<String>(py).unwrap(), "WHY?");
+            let plan = executor.plan(py, "why?".to_object(py)).unwrap();
+            assert_eq!(plan.input_channels, vec!["question", "turns"]);
+
+            // Writes are checked against the channel's value type
+            let native = InputTransform::Native(Arc::new(|py, input| {
+                let writes = PyDict::new(py);
+                writes.set_item("question", input.as_ref(py).len()?)?;
+                writes.set_item("turns", 0)?;
+                Ok(writes.to_object(py))
+            }));
+            let err = build(native)
+                .invoke(py, "why?".to_object(py), None)
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("wrote int to channel 'question', which takes string values"));
+
+            let identity = py.eval("lambda q: q", None, None).unwrap();
+            let err = build(InputTransform::Python(identity.to_object(py)))
+                .invoke(py, "why?".to_object(py), None)
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn test_run_id() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let double = py.eval("lambda n: n * 2", None, None).unwrap();
+            let mut executor = PregelCore::new();
+            executor.add_node(Node::with_channels(
+                "double".to_string(),
+                double.to_object(py),
+                Some(vec!["n".to_string()]),
                 Some(vec!["output".to_string()]),
+            ));
+            executor.set_entry_point("double".to_string());
+            executor.set_input_channels(vec!["n".to_string()]);
+            executor.enable_metrics();
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = executor.compile(Some(checkpointer)).unwrap();
+            assert_eq!(executor.run_id(), None);
+
+            // A given ID is used for the next run only
+            let run_id = Uuid::new_v4();
+            executor.set_run_id(run_id);
+            executor.invoke(py, 1.to_object(py), None).unwrap();
+            assert_eq!(executor.run_id(), Some(run_id));
+            assert_eq!(executor.metrics().run_id, Some(run_id.to_string()));
+
+            executor.invoke(py, 2.to_object(py), None).unwrap();
+            let generated = executor.run_id().unwrap();
+            assert_ne!(generated, run_id);
+
+            let run_ids: Vec<Option<Uuid>> = executor
+                .state_history(py)
+                .unwrap()
+                .iter()
+                .map(|snapshot| snapshot.run_id)
+                .collect();
+            assert_eq!(run_ids, vec![Some(run_id), Some(generated)]);
+        });
+    }
+
+    #[test]
+    fn test_snapshot_ids_replay() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let double = py.eval("lambda n: n * 2", None, None).unwrap();
+            let inc = py.eval("lambda n: n + 1", None, None).unwrap();
+            let run = |n: i32| {
+                let mut executor = PregelCore::new();
+                executor.add_node(Node::with_channels(
+                    "double".to_string(),
+                    double.to_object(py),
+                    Some(vec!["n".to_string()]),
+                    Some(vec!["mid".to_string()]),
+                ));
+                executor.add_node(Node::with_channels(
+                    "inc".to_string(),
+                    inc.to_object(py),
+                    Some(vec!["mid".to_string()]),
+                    Some(vec!["output".to_string()]),
+                ));
+                executor.add_edge(Edge::direct("double".to_string(), "inc".to_string()));
+                executor.set_entry_point("double".to_string());
+                executor.set_input_channels(vec!["n".to_string()]);
+                let checkpointer = Arc::new(MemoryCheckpointer::new());
+                let mut executor = executor.compile(Some(checkpointer)).unwrap();
+                executor.invoke(py, n.to_object(py), None).unwrap();
+                executor.state_history(py).unwrap()
+            };
+
+            // Each snapshot's parent is the previous one of the run
+            let history = run(1);
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[0].parent_id, None);
+            assert_eq!(
+                history[1].parent_id.as_deref(),
+                Some(history[0].id.as_str())
             );
+            assert_ne!(history[0].id, history[1].id);
 
-            executor.add_node(node1);
-            executor.add_node(node2);
+            // Replaying the same input gives the same chain, other input doesn't
+            let ids = |history: Vec<StateSnapshot>| -> Vec<String> {
+                history.into_iter().map(|snapshot| snapshot.id).collect()
+            };
+            let first = ids(history);
+            assert_eq!(ids(run(1)), first);
+            let other = ids(run(2));
+            assert!(other.iter().all(|id| !first.contains(id)));
+        });
+    }
 
-            // Add edge: add_one -> multiply_two
-            executor.add_edge(Edge::direct(
-                "add_one".to_string(),
-                "multiply_two".to_string(),
-            ));
+    #[test]
+    fn test_node_cache_bounded() {
+        pyo3::prepare_freethreaded_python();
 
-            // Add channels
-            executor.add_channel("input".to_string(), Box::new(LastValueChannel::new()));
-            executor.add_channel(
-                "intermediate".to_string(),
-                Box::new(LastValueChannel::new()),
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                "calls = []\ndef square(x):\n    calls.append(x)\n    return x * x",
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let func = locals.get_item("square").unwrap().unwrap();
+
+            let mut executor = PregelCore::new();
+            executor.add_node(
+                Node::with_channels(
+                    "square".to_string(),
+                    func.to_object(py),
+                    Some(vec!["n".to_string()]),
+                    Some(vec!["out".to_string()]),
+                )
+                .with_cache(true),
             );
-            executor.add_channel("output".to_string(), Box::new(LastValueChannel::new()));
+            executor.set_entry_point("square".to_string());
+            executor.set_input_channels(vec!["n".to_string()]);
+            executor.enable_metrics();
+            executor.enable_cache(CachePolicy {
+                max_entries: 1,
+                ttl: None,
+            });
 
-            // Set entry
-            executor.set_entry_point("add_one".to_string());
+            for n in [2, 2, 3, 2] {
+                let output = executor.invoke(py, n.to_object(py), None).unwrap();
+                let output: HashMap<String, i32> = output.extract(py).unwrap();
+                assert_eq!(output["out"], n * n);
+            }
 
-            // Set input
-            executor
-                .state_mut()
-                .update_channel(py, "input", 5.to_object(py))
-                .unwrap();
+            // The second 2 is a hit; 3 evicts it, so the last 2 runs again
+            let calls = locals.get_item("calls").unwrap().unwrap();
+            assert_eq!(calls.len().unwrap(), 3);
+            let snapshot = executor.metrics();
+            assert_eq!(snapshot.nodes["square"].cache_hit_ratio, Some(0.25));
+            let cache = snapshot.cache.unwrap();
+            assert_eq!(cache.entries, 1);
+            assert_eq!(cache.evictions, 2);
+        });
+    }
 
-            // Execute
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
+    #[test]
+    fn test_on_barrier_sees_committed_state() {
+        use super::super::checkpointer::MemoryCheckpointer;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let add = py.eval("lambda x: x + 1", None, None).unwrap();
+            let build = || {
+                let mut executor = PregelCore::new();
+                for (name, input, output) in [("a", "input", "a"), ("b", "a", "output")] {
+                    executor.add_node(Node::with_channels(
+                        name.to_string(),
+                        add.to_object(py),
+                        Some(vec![input.to_string()]),
+                        Some(vec![output.to_string()]),
+                    ));
+                }
+                executor.add_edge(Edge::direct("a".to_string(), "b".to_string()));
+                executor.set_entry_point("a".to_string());
+                executor.set_input_channels(vec!["input".to_string()]);
+                executor.set_output_channels(OutputChannels::Single("output".to_string()));
                 executor
-                    .execute_from(py, "add_one".to_string())
-                    .await
-                    .unwrap();
+            };
+
+            // The callback runs once per step, after the checkpointer saved it
+            let checkpointer = Arc::new(MemoryCheckpointer::new());
+            let mut executor = build().compile(Some(checkpointer.clone())).unwrap();
+            let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let recorded = seen.clone();
+            let saved = checkpointer.clone();
+            executor.on_barrier(move |py, step, state, snapshot| {
+                let a: i64 = state.get_value(py, "a").unwrap().extract(py)?;
+                assert_eq!(snapshot.values["a"].extract::<i64>(py)?, a);
+                assert_eq!(saved.list(py)?.len(), step);
+                recorded.lock().unwrap().push((
+                    step,
+                    snapshot.next.clone(),
+                    state.get_value(py, "output").is_some(),
+                ));
+                Ok(())
             });
+            let output = executor.invoke(py, 1.to_object(py), None).unwrap();
+            assert_eq!(output.extract::<i64>(py).unwrap(), 3);
+            assert_eq!(
+                *seen.lock().unwrap(),
+                [(1, vec!["b".to_string()], false), (2, vec![], true)]
+            );
 
-            // Check: (5 + 1) * 2 = 12
-            let result = executor.state().get_value(py, "output").unwrap();
-            assert_eq!(result.extract::<i32>(py).unwrap(), 12);
+            // An error from the callback aborts the run after the failing step
+            let mut executor = build();
+            executor.on_barrier(|_, step, _, _| {
+                if step == 1 {
+                    return Err(pyo3::exceptions::PyIOError::new_err("warehouse down"));
+                }
+                Ok(())
+            });
+            let err = executor.invoke(py, 1.to_object(py), None).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyIOError>(py));
+            assert!(executor.state().get_value(py, "a").is_some());
+            assert!(executor.state().get_value(py, "output").is_none());
+        });
+    }
+
+    #[test]
+    fn test_graph_introspection() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let router = py.eval("lambda state: 'done'", None, None).unwrap();
+            let schema = StateSchema::new()
+                .field("draft", ChannelKind::LastValue)
+                .field("notes", ChannelKind::Topic { accumulate: true });
+            let mut executor = PregelCore::with_schema(schema);
+            executor.add_node(
+                Node::passthrough("write".to_string(), "draft", "draft")
+                    .with_tags(vec!["llm".to_string()]),
+            );
+            executor.add_node(
+                Node::passthrough("review".to_string(), "draft", "notes")
+                    .with_write_channels(vec!["notes".to_string()]),
+            );
+            executor.add_node(Node::passthrough("publish".to_string(), "draft", "draft"));
+            executor.add_edge(Edge::direct("write".to_string(), "review".to_string()));
+            executor.add_conditional_edges(
+                "review",
+                router.to_object(py),
+                HashMap::from([
+                    ("retry".to_string(), "write".to_string()),
+                    ("done".to_string(), "publish".to_string()),
+                    ("drop".to_string(), END.to_string()),
+                ]),
+            );
+            executor.set_entry_point("write".to_string());
+            let executor = executor.compile(None).unwrap();
+
+            let nodes = executor.nodes();
+            let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+            assert_eq!(names, ["publish", "review", "write"]);
+            let write = nodes[2];
+            assert_eq!(write.tags, ["llm"]);
+            assert_eq!(
+                write.read_channels.as_deref(),
+                Some(&["draft".to_string()][..])
+            );
+            assert!(write.is_entry && !write.is_finish);
+            assert_eq!(
+                nodes[1].write_channels.as_deref(),
+                Some(&["notes".to_string()][..])
+            );
+            // Both a branch to END and the lack of outgoing edges end the run
+            assert!(nodes[0].is_finish && nodes[1].is_finish && !nodes[1].is_entry);
+
+            let edges = executor.edges();
+            assert_eq!(edges.len(), 2);
+            assert_eq!(edges[1].kind, EdgeKind::Conditional);
+            assert_eq!(edges[1].targets, [END, "publish", "write"]);
+
+            let successors: Vec<String> = executor
+                .successors("review")
+                .iter()
+                .map(|destination| destination.node().to_string())
+                .collect();
+            assert_eq!(successors, ["publish", "write"]);
+            assert!(executor.successors("publish").is_empty());
+
+            let channels = executor.channels();
+            assert_eq!(channels.len(), 2);
+            assert_eq!(channels[1].name, "notes");
+            assert!(channels[1].reducer && channels[1].accumulates);
+            assert!(!channels[0].reducer);
+
+            let dict = edges[1].to_dict(py).unwrap();
+            assert_eq!(
+                dict.get_item("kind")
+                    .unwrap()
+                    .unwrap()
+                    .extract::<&str>()
+                    .unwrap(),
+                "conditional"
+            );
+            assert!(dict.get_item("channel").unwrap().unwrap().is_none());
+            let dict = write.to_dict(py).unwrap();
+            assert!(dict
+                .get_item("is_entry")
+                .unwrap()
+                .unwrap()
+                .is_true()
+                .unwrap());
+        });
+    }
+}
+
+#[cfg(all(test, not(feature = "python")))]
+mod json_tests {
+    use super::*;
+    use crate::core::state::ChannelKind;
+    use crate::errors::LangGraphError;
+    use serde_json::json;
+
+    fn graph(channels: &[&str]) -> PregelCore {
+        let schema = channels.iter().fold(StateSchema::new(), |schema, channel| {
+            schema.field(*channel, ChannelKind::LastValue)
+        });
+        let mut graph = PregelCore::with_schema(schema);
+        graph.set_input_channels(channels.iter().map(|ch| ch.to_string()).collect());
+        graph
+    }
+
+    fn counter(name: &str, channel: &'static str) -> Node {
+        Node::with_inputs(name.to_string(), &[channel], move |_py, values| {
+            let count = values[0].as_i64().unwrap_or(0);
+            Ok(HashMap::from([(channel.to_string(), json!(count + 1))]))
+        })
+    }
+
+    #[test]
+    fn test_closure_graph_loop() {
+        let mut graph = graph(&["count", "label"]);
+        graph.add_node(counter("inc", "count"));
+        graph.set_entry_point("inc".to_string());
+        let router = host::callable(|state| {
+            let done = state["count"].as_i64().unwrap_or(0) >= 3;
+            Ok(json!(if done { END } else { "inc" }))
         });
+        graph.add_conditional_edges("inc", router, HashMap::new());
+
+        let input = json!({ "count": 0, "label": "x" });
+        let output = host::with_host(|py| graph.invoke_sync(py, input.clone(), None)).unwrap();
+        assert_eq!(output, json!({ "count": 3, "label": "x" }));
+
+        graph.set_recursion_limit(2);
+        let err = host::with_host(|py| graph.invoke_sync(py, input, None)).unwrap_err();
+        assert!(matches!(
+            err,
+            LangGraphError::Host {
+                kind: "RecursionError",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_closure_graph_fan_out_conflict() {
+        let mut graph = graph(&["a", "b"]);
+        graph.add_node(Node::with_inputs("start".to_string(), &[], |_py, _| {
+            Ok(HashMap::new())
+        }));
+        graph.add_node(counter("a", "a"));
+        graph.add_node(counter("b", "b"));
+        graph.set_entry_point("start".to_string());
+        graph.add_edge(Edge::direct("start".to_string(), "a".to_string()));
+        graph.add_edge(Edge::direct("start".to_string(), "b".to_string()));
+
+        let input = json!({ "a": 0, "b": 0 });
+        let output = host::with_host(|py| graph.invoke_sync(py, input.clone(), None)).unwrap();
+        assert_eq!(output, json!({ "a": 1, "b": 1 }));
+
+        graph.add_node(counter("c", "a"));
+        graph.add_edge(Edge::direct("start".to_string(), "c".to_string()));
+        let err = host::with_host(|py| graph.invoke_sync(py, input, None)).unwrap_err();
+        assert!(err.to_string().contains("'a', 'c'"));
     }
 }
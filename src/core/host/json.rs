@@ -0,0 +1,256 @@
+//! JSON values as host values, for builds without the `python` feature
+//!
+//! Errors the Python build raises as built-in exceptions surface as
+//! [`LangGraphError::Host`] carrying the exception's name, so messages read
+//! the same in both builds.
+
+use super::Kind;
+use crate::checkpoint::CheckpointId;
+use crate::core::edge::Destination;
+use crate::errors::{GraphError, LangGraphError};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+pub type Object = Value;
+pub type HostError = LangGraphError;
+pub type HostResult<T> = Result<T, LangGraphError>;
+
+/// Proof of access to the host, the counterpart of pyo3's `Python` token;
+/// JSON needs no interpreter, so it is free to get with [`with_host`]
+#[derive(Debug, Clone, Copy)]
+pub struct Host<'py>(PhantomData<&'py ()>);
+
+/// Function called with one value
+pub type Callable = Arc<dyn Fn(Object) -> HostResult<Object> + Send + Sync>;
+
+/// Wrap a closure as a [`Callable`]
+pub fn callable<F>(func: F) -> Callable
+where
+    F: Fn(Object) -> HostResult<Object> + Send + Sync + 'static,
+{
+    Arc::new(func)
+}
+
+/// The reference-counting methods of Python objects, for JSON values
+pub trait ObjectExt {
+    /// A copy of the value
+    fn clone_ref(&self, py: Host<'_>) -> Object;
+
+    /// Whether the value is `null`
+    fn is_none(&self, py: Host<'_>) -> bool;
+}
+
+impl ObjectExt for Value {
+    fn clone_ref(&self, _py: Host<'_>) -> Object {
+        self.clone()
+    }
+
+    fn is_none(&self, _py: Host<'_>) -> bool {
+        self.is_null()
+    }
+}
+
+pub fn with_host<F, R>(f: F) -> R
+where
+    F: for<'py> FnOnce(Host<'py>) -> R,
+{
+    f(Host(PhantomData))
+}
+
+/// Run `f`; there is no lock to release
+pub fn release<F, T>(_py: Host<'_>, f: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    f()
+}
+
+pub fn none(_py: Host<'_>) -> Object {
+    Value::Null
+}
+
+pub fn call(_py: Host<'_>, func: &Callable, arg: Object) -> HostResult<Object> {
+    func(arg)
+}
+
+/// Whether `value` is truthy by Python's rules: `null`, `false`, zero and
+/// empty strings, arrays and objects are falsy
+pub fn is_truthy(_py: Host<'_>, value: &Object) -> HostResult<bool> {
+    Ok(match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    })
+}
+
+pub fn kind(_py: Host<'_>, value: &Object) -> Kind {
+    match value {
+        Value::Null => Kind::None,
+        Value::Bool(_) => Kind::Bool,
+        Value::Number(n) if n.is_f64() => Kind::Float,
+        Value::Number(_) => Kind::Int,
+        Value::String(_) => Kind::Str,
+        Value::Array(_) => Kind::List,
+        Value::Object(_) => Kind::Dict,
+    }
+}
+
+/// Name of the Python type `value` converts to, for error messages
+pub fn type_name(py: Host<'_>, value: &Object) -> String {
+    match kind(py, value) {
+        Kind::None => "NoneType",
+        Kind::Bool => "bool",
+        Kind::Int => "int",
+        Kind::Float => "float",
+        Kind::Str => "str",
+        Kind::List => "list",
+        Kind::Dict | Kind::Other => "dict",
+    }
+    .to_string()
+}
+
+pub fn to_json(_py: Host<'_>, value: &Object) -> Option<Value> {
+    Some(value.clone())
+}
+
+pub fn from_json(_py: Host<'_>, value: &Value) -> Object {
+    value.clone()
+}
+
+/// `value` as a number; NaN and infinities become `null`
+pub fn float(_py: Host<'_>, value: f64) -> Object {
+    Value::from(value)
+}
+
+pub fn as_f64(_py: Host<'_>, value: &Object) -> Option<f64> {
+    value.as_f64()
+}
+
+pub fn as_string(py: Host<'_>, value: &Object) -> HostResult<String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| type_error(format!("expected a str, got {}", type_name(py, value))))
+}
+
+pub fn list(_py: Host<'_>, items: impl IntoIterator<Item = Object>) -> Object {
+    Value::Array(items.into_iter().collect())
+}
+
+pub fn list_items(py: Host<'_>, value: &Object) -> HostResult<Vec<Object>> {
+    value
+        .as_array()
+        .cloned()
+        .ok_or_else(|| type_error(format!("expected a list, got {}", type_name(py, value))))
+}
+
+pub fn dict(
+    _py: Host<'_>,
+    entries: impl IntoIterator<Item = (String, Object)>,
+) -> HostResult<Object> {
+    Ok(Value::Object(entries.into_iter().collect::<Map<_, _>>()))
+}
+
+/// Entries of an object, `None` if `value` is not an object
+pub fn dict_entries(_py: Host<'_>, value: &Object) -> HostResult<Option<Vec<(String, Object)>>> {
+    Ok(value.as_object().map(|fields| {
+        fields
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }))
+}
+
+pub fn dict_get(_py: Host<'_>, value: &Object, key: &str) -> Option<Object> {
+    value.as_object()?.get(key).cloned()
+}
+
+/// Destinations a router returned: a node name, a send written as
+/// `{"node": ..., "arg": ...}`, or an array of those; with `first_only`,
+/// only the first entry of an array counts
+pub fn destinations(
+    py: Host<'_>,
+    routed: &Object,
+    first_only: bool,
+) -> HostResult<Vec<Destination>> {
+    let items = match routed {
+        Value::Array(items) if first_only => &items[..items.len().min(1)],
+        Value::Array(items) => &items[..],
+        routed => std::slice::from_ref(routed),
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(node) => Ok(Destination::Node(node.clone())),
+            Value::Object(send) => match (send.get("node"), send.get("arg")) {
+                (Some(Value::String(node)), Some(arg)) => Ok(Destination::Send {
+                    node: node.clone(),
+                    arg: arg.clone(),
+                }),
+                _ => Err(type_error("a send needs a string 'node' and an 'arg'")),
+            },
+            item => Err(type_error(format!(
+                "expected a node name or a send, got {}",
+                type_name(py, item)
+            ))),
+        })
+        .collect()
+}
+
+/// Bytes identifying `value` for the node cache: its JSON text
+pub fn fingerprint(_py: Host<'_>, value: &Object) -> HostResult<Vec<u8>> {
+    Ok(serde_json::to_vec(value)?)
+}
+
+/// Content-derived ID of a checkpoint, see [`CheckpointId`]
+pub fn checkpoint_id(
+    _py: Host<'_>,
+    parent_id: Option<&str>,
+    step: usize,
+    values: &HashMap<String, Object>,
+) -> HostResult<String> {
+    Ok(CheckpointId::derive(parent_id, step as i32, values).to_string())
+}
+
+fn host_error(kind: &'static str, message: impl Into<String>) -> HostError {
+    LangGraphError::Host {
+        kind,
+        message: message.into(),
+    }
+}
+
+pub fn type_error(message: impl Into<String>) -> HostError {
+    host_error("TypeError", message)
+}
+
+pub fn value_error(message: impl Into<String>) -> HostError {
+    host_error("ValueError", message)
+}
+
+pub fn key_error(message: impl Into<String>) -> HostError {
+    host_error("KeyError", message)
+}
+
+pub fn runtime_error(message: impl Into<String>) -> HostError {
+    host_error("RuntimeError", message)
+}
+
+pub fn recursion_error(message: impl Into<String>) -> HostError {
+    host_error("RecursionError", message)
+}
+
+/// `err`; errors carry no cause here, so `cause` is dropped
+pub fn with_cause(_py: Host<'_>, err: HostError, _cause: HostError) -> HostError {
+    err
+}
+
+/// Whether `err` is a failed guard's [`GraphError::GuardFailed`]
+pub fn is_guard_failure(_py: Host<'_>, err: &HostError) -> bool {
+    matches!(err, LangGraphError::Graph(GraphError::GuardFailed { .. }))
+}
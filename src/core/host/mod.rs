@@ -0,0 +1,45 @@
+//! Values exchanged with the host language
+//!
+//! Channels, nodes, edges and the executor hold, pass and call values only
+//! through the names defined here, so `core` builds with or without pyo3.
+//! With the `python` feature the host is the Python interpreter: values are
+//! Python objects, callables are Python callables and errors are Python
+//! exceptions. Without it values are JSON ([`serde_json::Value`]), callables
+//! are Rust closures and errors are [`LangGraphError`](crate::errors::LangGraphError)s;
+//! this is what the `wasm` build runs on.
+//!
+//! Both backends provide the same functions, so code written against one
+//! builds against the other.
+
+#[cfg(not(feature = "python"))]
+mod json;
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(not(feature = "python"))]
+pub use json::*;
+#[cfg(feature = "python")]
+pub use python::*;
+
+/// Types used by every module working with host values
+pub mod prelude {
+    #[cfg(not(feature = "python"))]
+    pub use super::json::ObjectExt;
+    pub use super::{Callable, Host, HostError, HostResult, Object};
+}
+
+/// Shape of a host value, as far as the executor cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    None,
+    Bool,
+    Int,
+    Float,
+    Str,
+    /// A list, or a tuple in Python
+    List,
+    /// A dict, or a JSON object
+    Dict,
+    /// Anything else, e.g. an instance of a Python class
+    Other,
+}
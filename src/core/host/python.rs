@@ -0,0 +1,235 @@
+//! Python objects as host values
+
+use super::Kind;
+use crate::conditional::is_send;
+use crate::core::edge::Destination;
+use crate::errors::GuardFailed;
+use crate::python::{py_to_value, value_to_py};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub use pyo3::{PyErr as HostError, PyObject as Object, PyResult as HostResult, Python as Host};
+
+/// Function called with one value: any Python callable
+pub type Callable = PyObject;
+
+/// Run `f` holding the GIL
+pub fn with_host<F, R>(f: F) -> R
+where
+    F: for<'py> FnOnce(Host<'py>) -> R,
+{
+    Python::with_gil(f)
+}
+
+/// Run `f` with the GIL released, so other threads can take it
+pub fn release<F, T>(py: Host<'_>, f: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    py.allow_threads(f)
+}
+
+/// Python's `None`
+pub fn none(py: Host<'_>) -> Object {
+    py.None()
+}
+
+/// Call `func` with `arg`
+pub fn call(py: Host<'_>, func: &Callable, arg: Object) -> HostResult<Object> {
+    func.call1(py, (arg,))
+}
+
+/// Whether `value` is truthy
+pub fn is_truthy(py: Host<'_>, value: &Object) -> HostResult<bool> {
+    value.is_true(py)
+}
+
+/// Shape of `value`; instances of subclasses count as their base type
+pub fn kind(py: Host<'_>, value: &Object) -> Kind {
+    let value = value.as_ref(py);
+    if value.is_none() {
+        Kind::None
+    } else if value.is_instance_of::<PyBool>() {
+        Kind::Bool
+    } else if value.is_instance_of::<PyLong>() {
+        Kind::Int
+    } else if value.is_instance_of::<PyFloat>() {
+        Kind::Float
+    } else if value.is_instance_of::<PyString>() {
+        Kind::Str
+    } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        Kind::List
+    } else if value.is_instance_of::<PyDict>() {
+        Kind::Dict
+    } else {
+        Kind::Other
+    }
+}
+
+/// Name of the type of `value`, for error messages
+pub fn type_name(py: Host<'_>, value: &Object) -> String {
+    value
+        .as_ref(py)
+        .get_type()
+        .name()
+        .unwrap_or("a value")
+        .to_string()
+}
+
+/// `value` as JSON, `None` if it is not JSON data
+pub fn to_json(py: Host<'_>, value: &Object) -> Option<Value> {
+    py_to_value(value.as_ref(py))
+}
+
+/// The Python equivalent of `value`
+pub fn from_json(py: Host<'_>, value: &Value) -> Object {
+    value_to_py(py, value)
+}
+
+pub fn float(py: Host<'_>, value: f64) -> Object {
+    value.to_object(py)
+}
+
+/// `value` as a float, if it is a number
+pub fn as_f64(py: Host<'_>, value: &Object) -> Option<f64> {
+    value.extract(py).ok()
+}
+
+/// `value` as a string, failing with `TypeError` for anything else
+pub fn as_string(py: Host<'_>, value: &Object) -> HostResult<String> {
+    value.extract(py)
+}
+
+/// A list of `items`
+pub fn list(py: Host<'_>, items: impl IntoIterator<Item = Object>) -> Object {
+    let items: Vec<Object> = items.into_iter().collect();
+    PyList::new(py, items).into()
+}
+
+/// Items of a list or tuple, failing with `TypeError` for anything else
+pub fn list_items(py: Host<'_>, value: &Object) -> HostResult<Vec<Object>> {
+    value.extract(py)
+}
+
+/// A dict of `entries`
+pub fn dict(
+    py: Host<'_>,
+    entries: impl IntoIterator<Item = (String, Object)>,
+) -> HostResult<Object> {
+    let dict = PyDict::new(py);
+    for (key, value) in entries {
+        dict.set_item(key, value)?;
+    }
+    Ok(dict.into())
+}
+
+/// Entries of a dict, `None` if `value` is not a dict; fails with
+/// `TypeError` if a key is not a string
+pub fn dict_entries(py: Host<'_>, value: &Object) -> HostResult<Option<Vec<(String, Object)>>> {
+    let Ok(dict) = value.downcast::<PyDict>(py) else {
+        return Ok(None);
+    };
+    dict.iter()
+        .map(|(key, value)| Ok((key.extract()?, value.into())))
+        .collect::<HostResult<_>>()
+        .map(Some)
+}
+
+/// Value of `key` in a dict, `None` if it is missing or `value` is not a dict
+pub fn dict_get(py: Host<'_>, value: &Object, key: &str) -> Option<Object> {
+    let dict = value.downcast::<PyDict>(py).ok()?;
+    dict.get_item(key).ok()?.map(Into::into)
+}
+
+/// Destinations a router returned: a node name, a `Send`, or an iterable
+/// of those; with `first_only`, only the first entry of an iterable counts
+pub fn destinations(
+    py: Host<'_>,
+    routed: &Object,
+    first_only: bool,
+) -> HostResult<Vec<Destination>> {
+    let routed = routed.as_ref(py);
+    let mut items: Vec<&PyAny> = if routed.is_instance_of::<PyString>() || is_send(routed)? {
+        vec![routed]
+    } else {
+        routed.iter()?.collect::<PyResult<_>>()?
+    };
+    if first_only {
+        items.truncate(1);
+    }
+    items
+        .into_iter()
+        .map(|item| {
+            if is_send(item)? {
+                Ok(Destination::Send {
+                    node: item.getattr("node")?.extract()?,
+                    arg: item.getattr("arg")?.into(),
+                })
+            } else {
+                Ok(Destination::Node(item.extract()?))
+            }
+        })
+        .collect()
+}
+
+/// Bytes identifying `value` for the node cache: its pickle
+pub fn fingerprint(py: Host<'_>, value: &Object) -> HostResult<Vec<u8>> {
+    let pickled = py
+        .import("pickle")?
+        .getattr("dumps")?
+        .call1((value,))?
+        .downcast::<PyBytes>()?;
+    Ok(pickled.as_bytes().to_vec())
+}
+
+/// Content-derived ID of a checkpoint, see
+/// [`derive_checkpoint_id`](crate::python::derive_checkpoint_id)
+pub fn checkpoint_id(
+    py: Host<'_>,
+    parent_id: Option<&str>,
+    step: usize,
+    values: &HashMap<String, Object>,
+) -> HostResult<String> {
+    crate::python::derive_checkpoint_id(
+        py,
+        parent_id,
+        step,
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.as_ref(py))),
+    )
+}
+
+pub fn type_error(message: impl Into<String>) -> HostError {
+    pyo3::exceptions::PyTypeError::new_err(message.into())
+}
+
+pub fn value_error(message: impl Into<String>) -> HostError {
+    pyo3::exceptions::PyValueError::new_err(message.into())
+}
+
+pub fn key_error(message: impl Into<String>) -> HostError {
+    pyo3::exceptions::PyKeyError::new_err(message.into())
+}
+
+pub fn runtime_error(message: impl Into<String>) -> HostError {
+    pyo3::exceptions::PyRuntimeError::new_err(message.into())
+}
+
+pub fn recursion_error(message: impl Into<String>) -> HostError {
+    pyo3::exceptions::PyRecursionError::new_err(message.into())
+}
+
+/// `err`, raised from `cause`
+pub fn with_cause(py: Host<'_>, err: HostError, cause: HostError) -> HostError {
+    err.set_cause(py, Some(cause));
+    err
+}
+
+/// Whether `err` is a failed guard's [`GuardFailed`]
+pub fn is_guard_failure(py: Host<'_>, err: &HostError) -> bool {
+    err.is_instance_of::<GuardFailed>(py)
+}
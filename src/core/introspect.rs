@@ -0,0 +1,118 @@
+//! Read-only description of a compiled graph's structure
+//!
+//! [`PregelCore::nodes`](super::PregelCore::nodes),
+//! [`edges`](super::PregelCore::edges),
+//! [`channels`](super::PregelCore::channels) and
+//! [`successors`](super::PregelCore::successors) describe the graph without
+//! running it, for tools drawing, validating or comparing graphs. Each
+//! description converts to a plain dict with `to_dict` for Python callers
+//! (with the `python` feature).
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::PyDict;
+
+/// A node of a compiled graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// Channels passed to the node, `None` when it receives the whole state
+    pub read_channels: Option<Vec<String>>,
+    /// Channels the node may write, `None` when it may write any
+    pub write_channels: Option<Vec<String>>,
+    /// Whether the run starts at the node
+    pub is_entry: bool,
+    /// Whether the run can end after the node: it has no outgoing edge, or
+    /// an edge or branch to [`END`](crate::graph::END)
+    pub is_finish: bool,
+}
+
+#[cfg(feature = "python")]
+impl NodeInfo {
+    /// The node as a dict with keys `name`, `tags`, `read_channels`,
+    /// `write_channels`, `is_entry` and `is_finish`
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("tags", self.tags.clone())?;
+        dict.set_item("read_channels", self.read_channels.clone())?;
+        dict.set_item("write_channels", self.write_channels.clone())?;
+        dict.set_item("is_entry", self.is_entry)?;
+        dict.set_item("is_finish", self.is_finish)?;
+        Ok(dict)
+    }
+}
+
+/// How an edge picks its target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Always taken
+    Direct,
+    /// Taken to the targets a router picks
+    Conditional,
+    /// Taken once `channel` holds a value
+    WhenAvailable { channel: String },
+}
+
+/// An edge of a compiled graph
+///
+/// Start and end edges are direct edges from [`START`](crate::graph::START)
+/// and to [`END`](crate::graph::END).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeInfo {
+    pub source: String,
+    /// Nodes the edge may lead to, sorted for conditional edges; empty for a
+    /// router without branches, whose targets are only known at run time
+    pub targets: Vec<String>,
+    pub kind: EdgeKind,
+}
+
+#[cfg(feature = "python")]
+impl EdgeInfo {
+    /// The edge as a dict with keys `source`, `targets`, `kind` (`"direct"`,
+    /// `"conditional"` or `"when_available"`) and `channel`
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let (kind, channel) = match &self.kind {
+            EdgeKind::Direct => ("direct", None),
+            EdgeKind::Conditional => ("conditional", None),
+            EdgeKind::WhenAvailable { channel } => ("when_available", Some(channel)),
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("source", &self.source)?;
+        dict.set_item("targets", self.targets.clone())?;
+        dict.set_item("kind", kind)?;
+        dict.set_item("channel", channel)?;
+        Ok(dict)
+    }
+}
+
+/// A state channel of a compiled graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelInfo {
+    pub name: String,
+    /// JSON Schema type of the values written to the channel
+    pub value_type: &'static str,
+    /// Whether several writes in one superstep are combined
+    pub reducer: bool,
+    /// Whether writes add to the value instead of replacing it
+    pub accumulates: bool,
+    /// Whether the value is saved in checkpoints
+    pub checkpointed: bool,
+}
+
+#[cfg(feature = "python")]
+impl ChannelInfo {
+    /// The channel as a dict with keys `name`, `value_type`, `reducer`,
+    /// `accumulates` and `checkpointed`
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("value_type", self.value_type)?;
+        dict.set_item("reducer", self.reducer)?;
+        dict.set_item("accumulates", self.accumulates)?;
+        dict.set_item("checkpointed", self.checkpointed)?;
+        Ok(dict)
+    }
+}
@@ -0,0 +1,309 @@
+//! Execution metrics
+//!
+//! Opt-in per-node statistics for `PregelCore`. Each node task produces a
+//! [`NodeSample`] on its own; samples are merged into the [`Metrics`]
+//! collector at the superstep barrier, so parallel nodes never contend on a
+//! shared lock.
+
+use super::cache::CacheStats;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Start of a wall-clock measurement
+///
+/// wasm32 has no clock without calling into JavaScript, so there every
+/// measurement reads zero.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    started: Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.started.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::ZERO;
+    }
+}
+
+/// Measurements from a single node execution
+#[derive(Debug, Clone, Default)]
+pub struct NodeSample {
+    /// Wall-clock time of the execution, including retries
+    pub duration: Duration,
+    /// Number of retries before the final attempt
+    pub retries: u32,
+    /// Whether the result came from the node cache (`None` = no cache lookup)
+    pub cache_hit: Option<bool>,
+}
+
+/// Accumulated statistics for one node
+#[derive(Debug, Default)]
+struct NodeStats {
+    durations: Vec<Duration>,
+    retries: u64,
+    cache_hits: u64,
+    cache_lookups: u64,
+}
+
+/// Metrics collector owned by the executor
+#[derive(Debug, Default)]
+pub struct Metrics {
+    nodes: HashMap<String, NodeStats>,
+    /// Committed supersteps
+    supersteps: u64,
+    /// Checkpoints handed to the checkpointer
+    checkpoints: u64,
+    /// Time from the start of the run to its last committed superstep
+    elapsed: Duration,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge a node sample into the collector
+    pub fn record(&mut self, node: &str, sample: &NodeSample) {
+        let stats = self.nodes.entry(node.to_string()).or_default();
+        stats.durations.push(sample.duration);
+        stats.retries += sample.retries as u64;
+        if let Some(hit) = sample.cache_hit {
+            stats.cache_lookups += 1;
+            if hit {
+                stats.cache_hits += 1;
+            }
+        }
+    }
+
+    /// Summarize the collected statistics
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(name, stats)| {
+                let mut durations = stats.durations.clone();
+                durations.sort();
+                let total: Duration = durations.iter().sum();
+
+                let metrics = NodeMetrics {
+                    invocations: durations.len() as u64,
+                    total_ms: millis(total),
+                    p50_ms: millis(percentile(&durations, 50)),
+                    p99_ms: millis(percentile(&durations, 99)),
+                    retries: stats.retries,
+                    cache_hit_ratio: (stats.cache_lookups > 0)
+                        .then(|| stats.cache_hits as f64 / stats.cache_lookups as f64),
+                    tags: Vec::new(),
+                };
+                (name.clone(), metrics)
+            })
+            .collect();
+
+        MetricsSnapshot {
+            nodes,
+            cache: None,
+            run_id: None,
+        }
+    }
+
+    /// Count a committed superstep, `elapsed` into the run
+    pub fn record_superstep(&mut self, elapsed: Duration) {
+        self.supersteps += 1;
+        self.elapsed = elapsed;
+    }
+
+    /// Count a checkpoint handed to the checkpointer
+    pub fn record_checkpoint(&mut self) {
+        self.checkpoints += 1;
+    }
+
+    /// Run-level summary of the collected statistics
+    pub fn run_stats(&self) -> RunStats {
+        RunStats {
+            supersteps: self.supersteps,
+            node_calls: self
+                .nodes
+                .iter()
+                .map(|(name, stats)| (name.clone(), stats.durations.len() as u64))
+                .collect(),
+            duration: self.elapsed,
+            checkpoints: self.checkpoints,
+        }
+    }
+
+    /// Discard all collected statistics
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Summary of a single run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStats {
+    pub supersteps: u64,
+    /// Calls of each node that ran
+    pub node_calls: HashMap<String, u64>,
+    /// Time from the start of the run to its last committed superstep
+    pub duration: Duration,
+    pub checkpoints: u64,
+}
+
+#[cfg(feature = "python")]
+impl RunStats {
+    /// The stats as a dict with keys `supersteps`, `node_calls`,
+    /// `duration_ms` and `checkpoints`
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py pyo3::types::PyDict> {
+        let dict = pyo3::types::PyDict::new(py);
+        dict.set_item("supersteps", self.supersteps)?;
+        dict.set_item("node_calls", self.node_calls.clone())?;
+        dict.set_item("duration_ms", millis(self.duration))?;
+        dict.set_item("checkpoints", self.checkpoints)?;
+        Ok(dict)
+    }
+}
+
+/// Nearest-rank percentile of sorted durations
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Summary statistics for one node
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetrics {
+    pub invocations: u64,
+    pub total_ms: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub retries: u64,
+    /// Fraction of cache lookups that hit, `None` if the node never used the cache
+    pub cache_hit_ratio: Option<f64>,
+    /// Tags the node was added with
+    pub tags: Vec<String>,
+}
+
+/// Point-in-time view of the collected metrics, keyed by node name
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub nodes: HashMap<String, NodeMetrics>,
+    /// Node cache occupancy, `None` if the executor has no cache
+    pub cache: Option<CacheStats>,
+    /// ID of the executor's latest run, `None` before the first run
+    pub run_id: Option<String>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl MetricsSnapshot {
+    /// Get metrics for a single node
+    fn get(&self, node: &str) -> Option<NodeMetrics> {
+        self.nodes.get(node).cloned()
+    }
+
+    fn __repr__(&self) -> String {
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+        format!("MetricsSnapshot(nodes={:?})", names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ms: u64) -> NodeSample {
+        NodeSample {
+            duration: Duration::from_millis(ms),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshot_percentiles() {
+        let mut metrics = Metrics::new();
+        for ms in 1..=100 {
+            metrics.record("llm", &sample(ms));
+        }
+
+        let snapshot = metrics.snapshot();
+        let llm = &snapshot.nodes["llm"];
+        assert_eq!(llm.invocations, 100);
+        assert_eq!(llm.total_ms, 5050.0);
+        assert_eq!(llm.p50_ms, 50.0);
+        assert_eq!(llm.p99_ms, 99.0);
+        assert_eq!(llm.cache_hit_ratio, None);
+    }
+
+    #[test]
+    fn test_retries_and_cache_ratio() {
+        let mut metrics = Metrics::new();
+        metrics.record(
+            "tool",
+            &NodeSample {
+                retries: 2,
+                cache_hit: Some(false),
+                ..sample(5)
+            },
+        );
+        metrics.record(
+            "tool",
+            &NodeSample {
+                cache_hit: Some(true),
+                ..sample(1)
+            },
+        );
+
+        let tool = &metrics.snapshot().nodes["tool"];
+        assert_eq!(tool.retries, 2);
+        assert_eq!(tool.cache_hit_ratio, Some(0.5));
+
+        metrics.reset();
+        assert!(metrics.snapshot().nodes.is_empty());
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let mut metrics = Metrics::new();
+        metrics.record("plan", &sample(2));
+        metrics.record("act", &sample(3));
+        metrics.record_superstep(Duration::from_millis(4));
+        metrics.record("act", &sample(3));
+        metrics.record_superstep(Duration::from_millis(9));
+        metrics.record_checkpoint();
+
+        let stats = metrics.run_stats();
+        assert_eq!(stats.supersteps, 2);
+        assert_eq!(
+            stats.node_calls,
+            HashMap::from([("plan".to_string(), 1), ("act".to_string(), 2)])
+        );
+        assert_eq!(stats.duration, Duration::from_millis(9));
+        assert_eq!(stats.checkpoints, 1);
+
+        metrics.reset();
+        assert_eq!(metrics.run_stats(), RunStats::default());
+    }
+}
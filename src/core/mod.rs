@@ -6,18 +6,59 @@
 //! - Nodes: Computation units that read/write channels
 //! - Edges: Control flow between nodes
 //! - PregelCore: Main async execution engine
+//! - NodeCache: Bounded cache of node results
+//! - Checkpointer: Storage of the state after each superstep
+//! - Serializer: Conversion of channel values to bytes for checkpointers
+//! - Trace: Complete, replayable record of a run
+//! - Introspection: Read-only description of a compiled graph
+//! - Agent: Tool-calling agent loop built on PregelCore
+//! - Host: Values, callables and errors of the host language
 //!
 //! This implementation is designed to be wire-compatible with Python LangGraph
 //! while providing high-performance async execution in Rust.
+//!
+//! Everything but the agent builds without the `python` feature, with JSON
+//! values and Rust closures in place of Python objects (see [`host`]); the
+//! `wasm` build runs [`PregelCore`] this way. Channels and serializers that
+//! only make sense for Python objects need the feature.
 
+#[cfg(feature = "python")]
+pub mod agent;
+pub mod cache;
 pub mod channel;
+pub mod checkpointer;
 pub mod edge;
 pub mod executor;
+pub mod host;
+pub mod introspect;
+pub mod metrics;
 pub mod node;
+pub mod serializer;
 pub mod state;
+pub mod trace;
 
-pub use channel::{Channel, ChannelUpdate, LastValueChannel, TopicChannel};
-pub use edge::Edge;
-pub use executor::PregelCore;
-pub use node::Node;
-pub use state::GraphState;
+#[cfg(feature = "python")]
+pub use agent::{approve_tools, create_react_agent, ReactAgent};
+pub use cache::{CachePolicy, CacheStats, NodeCache};
+pub use channel::{
+    Channel, ChannelUpdate, ContextChannel, EmaChannel, LastValueChannel, SlidingWindowChannel,
+    TopicChannel, ValueType, CONTEXT_CHANNEL,
+};
+#[cfg(feature = "python")]
+pub use channel::{DedupChannel, ObjectChannel, PartialUpdate};
+pub use checkpointer::{Checkpointer, MemoryCheckpointer, StateSnapshot};
+pub use edge::{Destination, Edge, RouteMode};
+pub use executor::{
+    BarrierCallback, ExecutionPlan, InputCheck, InputMap, InputTransform, InputValidator,
+    NodeOutputs, OutputChannels, ParallelBackend, PregelCore, RunHistory, RuntimeCheck, StepRecord,
+};
+pub use introspect::{ChannelInfo, EdgeInfo, EdgeKind, NodeInfo};
+pub use metrics::{Metrics, MetricsSnapshot, NodeMetrics, RunStats};
+pub use node::{GuardAction, InputsFunc, Node, NodeFunc};
+#[cfg(feature = "python")]
+pub use serializer::PickleSerializer;
+pub use serializer::{ChannelCompression, JsonSerializer, Serializer};
+pub use state::{
+    ChannelKey, ChannelKind, GraphState, NamespacedState, StateSchema, NAMESPACE_SEPARATOR,
+};
+pub use trace::{Divergence, ExecutionEvent, Trace, TraceDiff};
@@ -3,105 +3,352 @@
 //! Nodes are computation units that read from and write to channels.
 //! Each node has a function that processes input and produces output.
 
-use pyo3::prelude::*;
+use super::channel::CONTEXT_CHANNEL;
+use super::host::{self, prelude::*};
+use crate::errors::{catch_node_panic, GraphError};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Function of a node built with [`Node::with_inputs`]: takes the values of
+/// its input channels in order and returns writes keyed by channel
+pub type InputsFunc =
+    Arc<dyn Fn(Host<'_>, Vec<Object>) -> HostResult<HashMap<String, Object>> + Send + Sync>;
+
+/// What a node runs when executed
+#[derive(Clone)]
+pub enum NodeFunc {
+    /// Call a host callable (a Python callable with the `python` feature)
+    /// with the node's input
+    Call(Callable),
+    /// Call a Rust function with the values of the node's input channels
+    Inputs(InputsFunc),
+    /// Return the input unchanged, without calling user code
+    Passthrough,
+    /// Return a fixed value, ignoring the input
+    Constant(Object),
+    /// Check `predicate` against the input, failing with `message` if it is false
+    Guard {
+        predicate: Callable,
+        message: String,
+        action: GuardAction,
+    },
+}
+
+/// What happens when a guard's predicate is false
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GuardAction {
+    /// Fail the run with [`GraphError::GuardFailed`]
+    #[default]
+    Error,
+    /// Stop the run with [`GraphError::Interrupted`] so it can be resumed,
+    /// e.g. after a human has fixed the state
+    Interrupt,
+}
 
 /// Node represents a computation unit in the graph
 ///
 /// A node consists of:
 /// - name: Unique identifier
-/// - func: Python callable to execute
+/// - func: Host callable (or built-in behaviour) to execute
 /// - input_channels: Which channels to read from (optional)
 /// - output_channels: Which channels to write to (optional)
+/// - read_channels: Channels the node may see (empty = all)
+/// - write_channels: Channels the node may update (empty = all)
+/// - cache: Whether results go through the executor's node cache
+/// - tags: Labels reported with the node's metrics
 #[derive(Clone)]
 pub struct Node {
     pub name: String,
-    pub func: PyObject,
+    pub func: NodeFunc,
     pub input_channels: Option<Vec<String>>,
     pub output_channels: Option<Vec<String>>,
+    pub read_channels: Vec<String>,
+    pub write_channels: Vec<String>,
+    pub cache: bool,
+    pub tags: Vec<String>,
 }
 
 impl Node {
     /// Create a new node
-    pub fn new(name: String, func: PyObject) -> Self {
+    pub fn new(name: String, func: Callable) -> Self {
         Self {
             name,
-            func,
+            func: NodeFunc::Call(func),
             input_channels: None,
             output_channels: None,
+            read_channels: Vec::new(),
+            write_channels: Vec::new(),
+            cache: false,
+            tags: Vec::new(),
         }
     }
 
     /// Create a node with input/output channel specifications
     pub fn with_channels(
         name: String,
-        func: PyObject,
+        func: Callable,
         input_channels: Option<Vec<String>>,
         output_channels: Option<Vec<String>>,
     ) -> Self {
         Self {
             name,
-            func,
+            func: NodeFunc::Call(func),
             input_channels,
             output_channels,
+            read_channels: Vec::new(),
+            write_channels: Vec::new(),
+            cache: false,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Create a node copying `from_channel` into `to_channel`
+    ///
+    /// Runs no user code; useful for joins and for renaming state.
+    pub fn passthrough(name: String, from_channel: &str, to_channel: &str) -> Self {
+        Self {
+            func: NodeFunc::Passthrough,
+            input_channels: Some(vec![from_channel.to_string()]),
+            output_channels: Some(vec![to_channel.to_string()]),
+            ..Self::builtin(name)
+        }
+    }
+
+    /// Create a node writing `value` to `channel` each time it runs
+    pub fn constant(name: String, channel: &str, value: Object) -> Self {
+        Self {
+            func: NodeFunc::Constant(value),
+            output_channels: Some(vec![channel.to_string()]),
+            ..Self::builtin(name)
+        }
+    }
+
+    /// Create a node computing writes from just the channels in `inputs`
+    ///
+    /// `func` receives the channels' values in the order given and returns
+    /// updates keyed by channel name. If any of the channels has no value
+    /// when the node runs, it fails with [`GraphError::MissingNodeInput`]
+    /// before `func` is called.
+    /// A panic in `func` fails the node with [`GraphError::NodeExecution`]
+    /// (raised as `RuntimeError`) instead of aborting the process.
+    pub fn with_inputs<F>(name: String, inputs: &[&str], func: F) -> Self
+    where
+        F: Fn(Host<'_>, Vec<Object>) -> HostResult<HashMap<String, Object>> + Send + Sync + 'static,
+    {
+        Self {
+            func: NodeFunc::Inputs(Arc::new(func)),
+            input_channels: Some(inputs.iter().map(|ch| ch.to_string()).collect()),
+            ..Self::builtin(name)
+        }
+    }
+
+    /// Create a node asserting `predicate(state)` between stages
+    ///
+    /// The predicate receives the current state as a dict (or the node's
+    /// input channels, if set). When it returns a truthy value the node
+    /// writes nothing; otherwise the run fails with
+    /// [`GraphError::GuardFailed`], or is interrupted with
+    /// [`GuardAction::Interrupt`] (see [`with_guard_action`](Self::with_guard_action)).
+    pub fn guard(name: String, predicate: Callable, message: &str) -> Self {
+        Self {
+            func: NodeFunc::Guard {
+                predicate,
+                message: message.to_string(),
+                action: GuardAction::Error,
+            },
+            output_channels: Some(Vec::new()),
+            ..Self::builtin(name)
+        }
+    }
+
+    /// Choose whether a failing guard errors or interrupts the run
+    ///
+    /// Has no effect on nodes that are not guards.
+    pub fn with_guard_action(mut self, action: GuardAction) -> Self {
+        if let NodeFunc::Guard {
+            action: current, ..
+        } = &mut self.func
+        {
+            *current = action;
+        }
+        self
+    }
+
+    /// The action taken when this node is a guard and its predicate fails
+    pub fn guard_action(&self) -> Option<GuardAction> {
+        match &self.func {
+            NodeFunc::Guard { action, .. } => Some(*action),
+            _ => None,
+        }
+    }
+
+    fn builtin(name: String) -> Self {
+        Self {
+            name,
+            func: NodeFunc::Passthrough,
+            input_channels: None,
+            output_channels: None,
+            read_channels: Vec::new(),
+            write_channels: Vec::new(),
+            cache: false,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Restrict the channels this node receives as input
+    ///
+    /// Input channels outside this list are dropped, except the context
+    /// channel which every node may read. A node without `input_channels`
+    /// receives exactly these channels.
+    pub fn with_read_channels(mut self, channels: Vec<String>) -> Self {
+        self.read_channels = channels;
+        self
+    }
+
+    /// Restrict the channels this node may update
+    ///
+    /// Writes to any other channel are rejected with [`GraphError::InvalidUpdate`].
+    pub fn with_write_channels(mut self, channels: Vec<String>) -> Self {
+        self.write_channels = channels;
+        self
+    }
+
+    /// Reuse this node's results for repeated inputs when the executor has a cache
+    ///
+    /// Only enable for nodes without side effects.
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Label the node; tags are reported in [`NodeMetrics`](super::NodeMetrics)
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Channels whose values are passed to the node, after applying `read_channels`
+    pub fn readable_input_channels(&self) -> Option<Vec<String>> {
+        match &self.input_channels {
+            Some(channels) if !self.read_channels.is_empty() => Some(
+                channels
+                    .iter()
+                    .filter(|ch| *ch == CONTEXT_CHANNEL || self.read_channels.contains(ch))
+                    .cloned()
+                    .collect(),
+            ),
+            Some(channels) => Some(channels.clone()),
+            None if !self.read_channels.is_empty() => Some(self.read_channels.clone()),
+            None => None,
+        }
+    }
+
+    /// Reject updates to the context channel or to channels outside `write_channels`
+    pub fn check_writes(&self, updates: &HashMap<String, Object>) -> Result<(), GraphError> {
+        let mut channels: Vec<&String> = updates.keys().collect();
+        channels.sort();
+        match channels.into_iter().find(|ch| {
+            *ch == CONTEXT_CHANNEL
+                || (!self.write_channels.is_empty() && !self.write_channels.contains(ch))
+        }) {
+            Some(channel) => Err(GraphError::InvalidUpdate {
+                channel: channel.clone(),
+                writers: vec![self.name.clone()],
+            }),
+            None => Ok(()),
         }
     }
 
     /// Execute the node function with the given input
     ///
     /// This method:
-    /// 1. Calls the Python function with the input
+    /// 1. Calls the host function with the input (or applies the built-in)
     /// 2. Returns the result
-    pub fn execute(&self, py: Python, input: PyObject) -> PyResult<PyObject> {
-        // Call the Python function
-        self.func.call1(py, (input,))
+    pub fn execute(&self, py: Host, input: Object) -> HostResult<Object> {
+        match &self.func {
+            NodeFunc::Call(func) => host::call(py, func, input),
+            NodeFunc::Inputs(func) => {
+                let inputs = host::list_items(py, &input)?;
+                let writes = catch_node_panic(&self.name, || func(py, inputs))??;
+                host::dict(py, writes)
+            }
+            NodeFunc::Passthrough => Ok(input),
+            NodeFunc::Constant(value) => Ok(value.clone_ref(py)),
+            NodeFunc::Guard {
+                predicate, message, ..
+            } => {
+                if host::is_truthy(py, &host::call(py, predicate, input)?)? {
+                    Ok(host::none(py))
+                } else {
+                    Err(GraphError::GuardFailed {
+                        message: message.clone(),
+                    }
+                    .into())
+                }
+            }
+        }
     }
 
     /// Execute the node asynchronously
     ///
     /// This is a synchronous wrapper that will be used by the async executor.
     /// The actual async execution happens at the executor level.
-    pub async fn execute_async(&self, input: PyObject) -> PyResult<PyObject> {
-        Python::with_gil(|py| self.execute(py, input))
+    pub async fn execute_async(&self, input: Object) -> HostResult<Object> {
+        host::with_host(|py| self.execute(py, input))
     }
 
     /// Get input from channels
     ///
     /// Extract input values from the specified input channels.
-    /// Returns the input to pass to the node function.
+    /// Returns the input to pass to the node function: for a node built
+    /// with [`with_inputs`](Self::with_inputs), the values of all its input
+    /// channels in order.
     pub fn extract_input(
         &self,
-        py: Python,
-        channel_values: &HashMap<String, PyObject>,
-    ) -> PyResult<PyObject> {
-        match &self.input_channels {
+        py: Host,
+        channel_values: &HashMap<String, Object>,
+    ) -> HostResult<Object> {
+        if let (NodeFunc::Inputs(_), Some(channels)) = (&self.func, &self.input_channels) {
+            let values = channels
+                .iter()
+                .map(|channel| {
+                    channel_values
+                        .get(channel)
+                        .map(|value| value.clone_ref(py))
+                        .ok_or_else(|| GraphError::MissingNodeInput {
+                            node: self.name.clone(),
+                            channel: channel.clone(),
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(host::list(py, values));
+        }
+        match &self.readable_input_channels() {
             None => {
                 // No input channels specified, return None
-                Ok(py.None())
+                Ok(host::none(py))
             }
             Some(channels) if channels.is_empty() => {
                 // Empty input channels, return None
-                Ok(py.None())
+                Ok(host::none(py))
             }
             Some(channels) if channels.len() == 1 => {
                 // Single input channel - return just the value
                 let channel_name = &channels[0];
-                channel_values.get(channel_name).cloned().ok_or_else(|| {
-                    pyo3::exceptions::PyKeyError::new_err(format!(
-                        "Input channel '{}' not found",
-                        channel_name
-                    ))
-                })
+                channel_values
+                    .get(channel_name)
+                    .map(|value| value.clone_ref(py))
+                    .ok_or_else(|| {
+                        host::key_error(format!("Input channel '{}' not found", channel_name))
+                    })
             }
             Some(channels) => {
                 // Multiple input channels - return dict
-                let dict = pyo3::types::PyDict::new(py);
-                for channel_name in channels {
-                    if let Some(value) = channel_values.get(channel_name) {
-                        dict.set_item(channel_name, value)?;
-                    }
-                }
-                Ok(dict.to_object(py))
+                let entries = channels.iter().filter_map(|channel_name| {
+                    let value = channel_values.get(channel_name)?;
+                    Some((channel_name.clone(), value.clone_ref(py)))
+                });
+                host::dict(py, entries)
             }
         }
     }
@@ -110,12 +357,19 @@ impl Node {
     ///
     /// Takes the node's output and maps it to channel updates.
     /// Returns a HashMap of channel_name -> value.
-    pub fn map_output(&self, py: Python, output: PyObject) -> PyResult<HashMap<String, PyObject>> {
+    pub fn map_output(&self, py: Host, output: Object) -> HostResult<HashMap<String, Object>> {
         let mut updates = HashMap::new();
 
         match &self.output_channels {
             None => {
-                // No output channels - no updates
+                // No output channels - no updates, except from Rust nodes,
+                // which return theirs keyed by channel name
+                if let NodeFunc::Inputs(_) = self.func {
+                    updates = host::dict_entries(py, &output)?
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect();
+                }
             }
             Some(channels) if channels.is_empty() => {
                 // Empty output channels - no updates
@@ -127,10 +381,10 @@ impl Node {
             }
             Some(channels) => {
                 // Multiple output channels - output should be a dict
-                if let Ok(dict) = output.extract::<&pyo3::types::PyDict>(py) {
+                if host::kind(py, &output) == host::Kind::Dict {
                     for channel_name in channels {
-                        if let Ok(Some(value)) = dict.get_item(channel_name) {
-                            updates.insert(channel_name.clone(), value.to_object(py));
+                        if let Some(value) = host::dict_get(py, &output, channel_name) {
+                            updates.insert(channel_name.clone(), value);
                         }
                     }
                 } else {
@@ -153,13 +407,17 @@ impl std::fmt::Debug for Node {
             .field("name", &self.name)
             .field("input_channels", &self.input_channels)
             .field("output_channels", &self.output_channels)
+            .field("read_channels", &self.read_channels)
+            .field("write_channels", &self.write_channels)
+            .field("tags", &self.tags)
             .finish()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "python"))]
 mod tests {
     use super::*;
+    use pyo3::prelude::*;
 
     #[test]
     fn test_node_creation() {
@@ -211,6 +469,42 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_with_inputs_node() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let node =
+                Node::with_inputs("greet".to_string(), &["name", "greeting"], |py, values| {
+                    let name: String = values[0].extract(py)?;
+                    let greeting: String = values[1].extract(py)?;
+                    let reply = format!("{}, {}!", greeting, name);
+                    Ok(HashMap::from([("reply".to_string(), reply.to_object(py))]))
+                });
+
+            // Values are passed in the declared order, whatever the state holds
+            let mut channel_values = HashMap::new();
+            channel_values.insert("greeting".to_string(), "Hello".to_object(py));
+            channel_values.insert("name".to_string(), "Ada".to_object(py));
+            channel_values.insert("other".to_string(), 1.to_object(py));
+            let input = node.extract_input(py, &channel_values).unwrap();
+            let output = node.execute(py, input).unwrap();
+            let updates = node.map_output(py, output).unwrap();
+            assert_eq!(updates.len(), 1);
+            assert_eq!(
+                updates["reply"].extract::<String>(py).unwrap(),
+                "Hello, Ada!"
+            );
+
+            // A missing input fails before the function runs
+            channel_values.remove("greeting");
+            let err = node.extract_input(py, &channel_values).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("node 'greet' needs channel 'greeting'"));
+        });
+    }
+
     #[test]
     fn test_extract_input_multiple_channels() {
         pyo3::prepare_freethreaded_python();
@@ -300,4 +594,57 @@ mod tests {
             assert_eq!(updates.get("out2").unwrap().extract::<i32>(py).unwrap(), 2);
         });
     }
+
+    #[test]
+    fn test_read_channels_restrict_input() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let func = py.eval("lambda x: x", None, None).unwrap();
+            let node = Node::new("test".to_string(), func.to_object(py))
+                .with_read_channels(vec!["a".to_string(), "b".to_string()]);
+
+            let mut channel_values = HashMap::new();
+            channel_values.insert("a".to_string(), 1.to_object(py));
+            channel_values.insert("b".to_string(), 2.to_object(py));
+            channel_values.insert("secret".to_string(), 3.to_object(py));
+
+            let input = node.extract_input(py, &channel_values).unwrap();
+            let dict = input.downcast::<pyo3::types::PyDict>(py).unwrap();
+            assert_eq!(dict.len(), 2);
+            assert!(dict.get_item("secret").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_write_channels_reject_unlisted_write() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let func = py.eval("lambda x: x", None, None).unwrap();
+            let node = Node::with_channels(
+                "writer".to_string(),
+                func.to_object(py),
+                None,
+                Some(vec!["output".to_string(), "secret".to_string()]),
+            )
+            .with_write_channels(vec!["output".to_string()]);
+
+            let output = py.eval("{'output': 1, 'secret': 2}", None, None).unwrap();
+            let updates = node.map_output(py, output.to_object(py)).unwrap();
+            assert_eq!(updates.len(), 2);
+
+            match node.check_writes(&updates) {
+                Err(GraphError::InvalidUpdate { channel, writers }) => {
+                    assert_eq!(writers, ["writer"]);
+                    assert_eq!(channel, "secret");
+                }
+                other => panic!("expected InvalidUpdate, got {:?}", other),
+            }
+
+            let mut allowed = HashMap::new();
+            allowed.insert("output".to_string(), 1.to_object(py));
+            assert!(node.check_writes(&allowed).is_ok());
+        });
+    }
 }
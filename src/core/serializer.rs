@@ -0,0 +1,248 @@
+//! Serialization of channel values for checkpointers
+//!
+//! A [`Checkpointer`](super::Checkpointer) stores channel values as bytes
+//! produced by a [`Serializer`], so a saved snapshot is independent of the
+//! live objects the graph keeps mutating. [`JsonSerializer`] handles plain
+//! data; [`PickleSerializer`] round-trips arbitrary Python objects (with the
+//! `python` feature).
+//! [`ChannelCompression`] optionally compresses the bytes of large channels.
+
+use super::host::{self, prelude::*};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+/// Converts channel values to bytes and back
+pub trait Serializer: Send + Sync {
+    /// Serialize a channel value
+    fn dumps(&self, py: Host<'_>, value: &Object) -> HostResult<Vec<u8>>;
+
+    /// Rebuild a channel value from the bytes of [`dumps`](Self::dumps)
+    fn loads(&self, py: Host<'_>, data: &[u8]) -> HostResult<Object>;
+}
+
+/// Serializer for JSON data: None, bool, int, float, str, and lists and
+/// str-keyed dicts of those
+///
+/// Other values fail with `TypeError`. Tuples come back as lists.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonSerializer;
+
+impl Serializer for JsonSerializer {
+    fn dumps(&self, py: Host<'_>, value: &Object) -> HostResult<Vec<u8>> {
+        let json = host::to_json(py, value).ok_or_else(|| {
+            host::type_error(format!(
+                "{} is not JSON serializable; use a PickleSerializer or a custom Serializer",
+                host::type_name(py, value)
+            ))
+        })?;
+        serde_json::to_vec(&json).map_err(|e| host::value_error(e.to_string()))
+    }
+
+    fn loads(&self, py: Host<'_>, data: &[u8]) -> HostResult<Object> {
+        let value: serde_json::Value =
+            serde_json::from_slice(data).map_err(|e| host::value_error(e.to_string()))?;
+        Ok(host::from_json(py, &value))
+    }
+}
+
+/// Serializer using Python's `pickle`, for values of any picklable type
+///
+/// Unpickling runs code chosen by whoever produced the bytes, so only load
+/// checkpoints from storage that untrusted parties cannot write to. Pickles
+/// also depend on the classes they reference: renaming or removing a class
+/// makes older checkpoints unloadable.
+#[cfg(feature = "python")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PickleSerializer;
+
+#[cfg(feature = "python")]
+impl Serializer for PickleSerializer {
+    fn dumps(&self, py: Python<'_>, value: &PyObject) -> PyResult<Vec<u8>> {
+        py.import("pickle")?
+            .call_method1("dumps", (value,))?
+            .extract()
+    }
+
+    fn loads(&self, py: Python<'_>, data: &[u8]) -> PyResult<PyObject> {
+        let bytes = pyo3::types::PyBytes::new(py, data);
+        Ok(py.import("pickle")?.call_method1("loads", (bytes,))?.into())
+    }
+}
+
+/// Compression of one channel's serialized values in a checkpoint
+///
+/// Compressing pays off for large accumulated channels such as long message
+/// histories; small values are cheaper to store as they are, hence the
+/// threshold. Compression uses zstd and needs the `compression-zstd`
+/// feature; without it values are stored uncompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelCompression {
+    /// Whether to compress the channel's values
+    pub compress: bool,
+    /// Only values serializing to more than this many bytes are compressed
+    pub threshold: usize,
+}
+
+impl ChannelCompression {
+    /// Compress values serializing to more than `threshold` bytes
+    pub fn over(threshold: usize) -> Self {
+        Self {
+            compress: true,
+            threshold,
+        }
+    }
+
+    /// Compress `data` if the policy applies to it, returning the bytes to
+    /// store and whether they are compressed
+    pub(crate) fn apply(&self, data: Vec<u8>) -> HostResult<(Vec<u8>, bool)> {
+        if !self.compress || data.len() <= self.threshold {
+            return Ok((data, false));
+        }
+        #[cfg(feature = "compression-zstd")]
+        {
+            Ok((zstd::encode_all(data.as_slice(), 3)?, true))
+        }
+        #[cfg(not(feature = "compression-zstd"))]
+        {
+            Ok((data, false))
+        }
+    }
+}
+
+/// Decompress bytes stored compressed by [`ChannelCompression`]
+pub(crate) fn decompress(data: &[u8]) -> HostResult<Vec<u8>> {
+    #[cfg(feature = "compression-zstd")]
+    {
+        Ok(zstd::decode_all(data)?)
+    }
+    #[cfg(not(feature = "compression-zstd"))]
+    {
+        let _ = data;
+        Err(host::value_error(
+            "checkpoint holds compressed values; enable the compression-zstd feature to load it",
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+    use crate::core::checkpointer::{Checkpointer, MemoryCheckpointer, StateSnapshot};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_checkpointer_serializers() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = pyo3::types::PyDict::new(py);
+            py.run(
+                r#"
+from fractions import Fraction
+plain = {"items": [1, 2.5, "x", None, True]}
+custom = Fraction(1, 3)
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let snapshot = |name: &str| StateSnapshot {
+                id: name.to_string(),
+                parent_id: None,
+                run_id: None,
+                step: 1,
+                values: HashMap::from([(
+                    "value".to_string(),
+                    locals.get_item(name).unwrap().unwrap().to_object(py),
+                )]),
+                next: vec![],
+                schema: Default::default(),
+            };
+
+            // JSON keeps plain data and refuses other objects
+            let json = MemoryCheckpointer::new();
+            json.put(py, snapshot("plain")).unwrap();
+            let err = json.put(py, snapshot("custom")).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+            assert!(err.to_string().contains("Fraction"));
+
+            // Saved values are copies, unaffected by later mutation
+            locals
+                .get_item("plain")
+                .unwrap()
+                .unwrap()
+                .set_item("items", 0)
+                .unwrap();
+            let history = json.list(py).unwrap();
+            assert_eq!(history.len(), 1);
+            let items = history[0].values["value"]
+                .as_ref(py)
+                .get_item("items")
+                .unwrap();
+            assert_eq!(items.len().unwrap(), 5);
+
+            let pickle = MemoryCheckpointer::with_serializer(PickleSerializer);
+            pickle.put(py, snapshot("custom")).unwrap();
+            let loaded = &pickle.list(py).unwrap()[0].values["value"];
+            let custom = locals.get_item("custom").unwrap().unwrap();
+            assert!(loaded.as_ref(py).eq(custom).unwrap());
+        });
+    }
+
+    #[test]
+    fn test_json_keeps_ints_and_floats_apart() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let value = py
+                .eval(
+                    "{'int': 42, 'float': 42.0, 'big': 2**64 - 1, 'sum': 0.1 + 0.2, 'nested': [1, 1.0]}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(
+                JsonSerializer
+                    .dumps(py, &value.get_item("float").unwrap().into())
+                    .unwrap(),
+                b"42.0"
+            );
+
+            let checkpointer = MemoryCheckpointer::new();
+            checkpointer
+                .put(
+                    py,
+                    StateSnapshot {
+                        id: "1".to_string(),
+                        parent_id: None,
+                        run_id: None,
+                        step: 1,
+                        values: HashMap::from([("value".to_string(), value.to_object(py))]),
+                        next: vec![],
+                        schema: Default::default(),
+                    },
+                )
+                .unwrap();
+            let loaded = checkpointer.list(py).unwrap()[0].values["value"].clone_ref(py);
+
+            // Equal values of the same types, so 42 == 42.0 alone is not enough
+            let types = py
+                .eval(
+                    "lambda v: {k: [type(i).__name__ for i in x] if isinstance(x, list) else type(x).__name__ for k, x in v.items()}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            assert!(loaded.as_ref(py).eq(value).unwrap());
+            assert!(types
+                .call1((loaded.clone_ref(py),))
+                .unwrap()
+                .eq(types.call1((value,)).unwrap())
+                .unwrap());
+            let float = loaded.as_ref(py).get_item("float").unwrap();
+            assert!(float.is_exact_instance_of::<pyo3::types::PyFloat>());
+            let int = loaded.as_ref(py).get_item("int").unwrap();
+            assert!(int.is_exact_instance_of::<pyo3::types::PyLong>());
+        });
+    }
+}
@@ -1,11 +1,158 @@
 //! Graph state management
 //!
 //! GraphState manages a collection of named channels that store
-//! the current state of the graph execution.
-
-use super::channel::{Channel, ChannelUpdate};
-use pyo3::prelude::*;
+//! the current state of the graph execution. A [`StateSchema`] declares
+//! the state's fields up front so their channels are created together.
+//! [`GraphState::namespace`] scopes channel names under a prefix, so
+//! composed graphs don't collide.
+
+#[cfg(feature = "python")]
+use super::channel::ObjectChannel;
+use super::channel::{
+    Channel, ChannelUpdate, LastValueChannel, SlidingWindowChannel, TopicChannel, ValueType,
+};
+use super::host::{self, prelude::*};
+use crate::channels::{AnyChannel, TypedChannel};
+use crate::errors::{GraphError, LangGraphError};
 use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Separator between a namespace and the channel names inside it
+pub const NAMESPACE_SEPARATOR: char = '.';
+
+/// Channel type backing a state field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKind {
+    /// Keeps the most recent write ([`LastValueChannel`])
+    LastValue,
+    /// Collects the writes of a step ([`TopicChannel`]); with `accumulate`
+    /// values are kept across steps
+    Topic { accumulate: bool },
+    /// Keeps the most recent `capacity` writes ([`SlidingWindowChannel`])
+    SlidingWindow { capacity: usize },
+    /// A dict updated whole or by field path ([`ObjectChannel`])
+    #[cfg(feature = "python")]
+    Object,
+}
+
+impl ChannelKind {
+    /// Create an empty channel of this kind
+    pub fn create(&self) -> Box<dyn Channel> {
+        self.create_typed(ValueType::default())
+    }
+
+    /// Create an empty channel of this kind holding values of `value_type`
+    pub fn create_typed(&self, value_type: ValueType) -> Box<dyn Channel> {
+        match self {
+            ChannelKind::LastValue => Box::new(LastValueChannel::new().with_value_type(value_type)),
+            ChannelKind::Topic { accumulate } => {
+                Box::new(TopicChannel::new(*accumulate).with_value_type(value_type))
+            }
+            ChannelKind::SlidingWindow { capacity } => {
+                Box::new(SlidingWindowChannel::new(*capacity).with_value_type(value_type))
+            }
+            #[cfg(feature = "python")]
+            ChannelKind::Object => Box::new(ObjectChannel::new()),
+        }
+    }
+}
+
+/// Named state fields, each stored in a channel of the same name
+///
+/// ```ignore
+/// let schema = StateSchema::new()
+///     .field("question", ChannelKind::LastValue)
+///     .field("messages", ChannelKind::Topic { accumulate: true });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSchema {
+    fields: Vec<(String, ChannelKind, ValueType)>,
+}
+
+impl StateSchema {
+    /// Create an empty schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field; redeclaring a field replaces its channel kind
+    pub fn field(self, name: impl Into<String>, kind: ChannelKind) -> Self {
+        self.typed_field(name, kind, ValueType::default())
+    }
+
+    /// Add a field holding values of `value_type`, as reported by
+    /// [`PregelCore::state_json_schema`](super::PregelCore::state_json_schema)
+    pub fn typed_field(
+        mut self,
+        name: impl Into<String>,
+        kind: ChannelKind,
+        value_type: ValueType,
+    ) -> Self {
+        let name = name.into();
+        match self.fields.iter_mut().find(|(field, _, _)| *field == name) {
+            Some(field) => {
+                field.1 = kind;
+                field.2 = value_type;
+            }
+            None => self.fields.push((name, kind, value_type)),
+        }
+        self
+    }
+
+    /// Fields in declaration order
+    pub fn fields(&self) -> impl Iterator<Item = (&str, ChannelKind)> {
+        self.fields
+            .iter()
+            .map(|(name, kind, _)| (name.as_str(), *kind))
+    }
+
+    /// Whether `name` is a declared field
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|(field, _, _)| field == name)
+    }
+
+    /// Create a state holding an empty channel for every field
+    pub fn create_state(&self) -> GraphState {
+        GraphState::with_channels(
+            self.fields
+                .iter()
+                .map(|(name, kind, value_type)| (name.clone(), kind.create_typed(*value_type)))
+                .collect(),
+        )
+    }
+}
+
+/// Handle to a native channel of a [`GraphState`], typed by the channel's
+/// value type `T` and update type `U`
+///
+/// Returned by [`GraphState::add_typed_channel`]; reading and writing
+/// through it is checked at compile time.
+pub struct ChannelKey<T, U = T> {
+    name: String,
+    types: PhantomData<fn() -> (T, U)>,
+}
+
+impl<T, U> ChannelKey<T, U> {
+    /// Name of the channel
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<T, U> Clone for ChannelKey<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            types: PhantomData,
+        }
+    }
+}
+
+impl<T, U> std::fmt::Debug for ChannelKey<T, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ChannelKey").field(&self.name).finish()
+    }
+}
 
 /// GraphState manages all channels in a graph
 ///
@@ -13,8 +160,26 @@ use std::collections::HashMap;
 /// - Channel lookup by name
 /// - Atomic updates to multiple channels
 /// - Checkpointing and restoration
+///
+/// Besides the channels of host values, it holds native channels of any
+/// value types for pure-Rust use. Each keeps its concrete type behind an
+/// [`AnyChannel`]: the [`ChannelKey`] returned when adding one reads and
+/// writes it with compile-time types, and access by name fails with
+/// [`LangGraphError::ChannelTypeMismatch`] when asked for another type.
+///
+/// ```
+/// use fast_langgraph::core::GraphState;
+/// use fast_langgraph::LastValueChannel;
+///
+/// let mut state = GraphState::new();
+/// let question = state.add_typed_channel("question", LastValueChannel::<String>::new());
+/// state.update_typed(&question, vec!["why?".to_string()]).unwrap();
+/// assert_eq!(state.get_typed(&question).unwrap(), "why?");
+/// assert!(state.get::<i64>("question").is_err());
+/// ```
 pub struct GraphState {
     channels: HashMap<String, Box<dyn Channel>>,
+    typed: HashMap<String, Box<dyn AnyChannel>>,
 }
 
 impl GraphState {
@@ -22,12 +187,120 @@ impl GraphState {
     pub fn new() -> Self {
         Self {
             channels: HashMap::new(),
+            typed: HashMap::new(),
+        }
+    }
+
+    /// Add the native channel `channel` under `name`, replacing any native
+    /// channel of that name, and return its typed handle
+    pub fn add_typed_channel<T, U>(
+        &mut self,
+        name: impl Into<String>,
+        channel: impl crate::channels::Channel<T, U> + 'static,
+    ) -> ChannelKey<T, U>
+    where
+        T: Send + Sync + 'static,
+        U: Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.typed
+            .insert(name.clone(), Box::new(TypedChannel::new(channel)));
+        ChannelKey {
+            name,
+            types: PhantomData,
         }
     }
 
+    fn typed_channel(&self, name: &str) -> Result<&dyn AnyChannel, LangGraphError> {
+        self.typed
+            .get(name)
+            .map(|channel| channel.as_ref())
+            .ok_or_else(|| GraphError::UnknownChannel(name.to_string()).into())
+    }
+
+    /// Value of the native channel `name` as a `T`
+    pub fn get<T: 'static>(&self, name: &str) -> Result<&T, LangGraphError> {
+        let channel = self.typed_channel(name)?;
+        channel
+            .value()?
+            .downcast_ref()
+            .ok_or_else(|| LangGraphError::ChannelTypeMismatch {
+                channel: name.to_string(),
+                expected: channel.value_type(),
+                requested: std::any::type_name::<T>(),
+            })
+    }
+
+    /// Write `values` to the native channel `name`, returning whether it
+    /// changed
+    pub fn update<U: 'static>(
+        &mut self,
+        name: &str,
+        values: Vec<U>,
+    ) -> Result<bool, LangGraphError> {
+        let channel = self
+            .typed
+            .get_mut(name)
+            .ok_or_else(|| GraphError::UnknownChannel(name.to_string()))?;
+        channel.update_any(Box::new(values)).unwrap_or_else(|| {
+            Err(LangGraphError::ChannelTypeMismatch {
+                channel: name.to_string(),
+                expected: channel.update_type(),
+                requested: std::any::type_name::<U>(),
+            })
+        })
+    }
+
+    /// Value of the native channel behind `key`
+    pub fn get_typed<T: 'static, U>(&self, key: &ChannelKey<T, U>) -> Result<&T, LangGraphError> {
+        self.get(&key.name)
+    }
+
+    /// Write `values` to the native channel behind `key`, returning whether
+    /// it changed
+    pub fn update_typed<T, U: 'static>(
+        &mut self,
+        key: &ChannelKey<T, U>,
+        values: Vec<U>,
+    ) -> Result<bool, LangGraphError> {
+        self.update(&key.name, values)
+    }
+
+    /// Checkpoint of every native channel, by name
+    pub fn typed_checkpoint(&self) -> Result<HashMap<String, serde_json::Value>, LangGraphError> {
+        self.typed
+            .iter()
+            .map(|(name, channel)| Ok((name.clone(), channel.checkpoint()?)))
+            .collect()
+    }
+
+    /// Restore the native channels named in `checkpoint`
+    pub fn restore_typed_checkpoint(
+        &mut self,
+        checkpoint: HashMap<String, serde_json::Value>,
+    ) -> Result<(), LangGraphError> {
+        for (name, value) in checkpoint {
+            self.typed
+                .get_mut(&name)
+                .ok_or_else(|| GraphError::UnknownChannel(name.clone()))?
+                .from_checkpoint(value)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the native channels
+    pub fn typed_channel_names(&self) -> Vec<String> {
+        self.typed.keys().cloned().collect()
+    }
+}
+
+impl GraphState {
     /// Create graph state with initial channels
     pub fn with_channels(channels: HashMap<String, Box<dyn Channel>>) -> Self {
-        Self { channels }
+        Self {
+            channels,
+            ..Self::new()
+        }
     }
 
     /// Add a channel to the state
@@ -49,21 +322,31 @@ impl GraphState {
     }
 
     /// Get the value from a specific channel
-    pub fn get_value(&self, py: Python, channel_name: &str) -> Option<PyObject> {
+    pub fn get_value(&self, py: Host, channel_name: &str) -> Option<Object> {
         self.get_channel(channel_name).and_then(|ch| ch.get(py))
     }
 
     /// Update a single channel with a value
     pub fn update_channel(
         &mut self,
-        py: Python,
+        py: Host,
         channel_name: &str,
-        value: PyObject,
-    ) -> PyResult<()> {
+        value: Object,
+    ) -> HostResult<()> {
+        self.apply_update(py, channel_name, ChannelUpdate::single(value))
+    }
+
+    /// Apply all writes a channel received in one step as a single update
+    pub fn apply_update(
+        &mut self,
+        py: Host,
+        channel_name: &str,
+        update: ChannelUpdate,
+    ) -> HostResult<()> {
         if let Some(channel) = self.get_channel_mut(channel_name) {
-            channel.update(py, ChannelUpdate::single(value))
+            channel.update(py, update)
         } else {
-            Err(pyo3::exceptions::PyKeyError::new_err(format!(
+            Err(host::key_error(format!(
                 "Channel '{}' not found",
                 channel_name
             )))
@@ -71,13 +354,33 @@ impl GraphState {
     }
 
     /// Update multiple channels atomically
-    pub fn update_many(&mut self, py: Python, updates: HashMap<String, PyObject>) -> PyResult<()> {
+    pub fn update_many(&mut self, py: Host, updates: HashMap<String, Object>) -> HostResult<()> {
         for (channel_name, value) in updates {
             self.update_channel(py, &channel_name, value)?;
         }
         Ok(())
     }
 
+    /// Seed every channel that was never written with its default value
+    ///
+    /// Channels restored from a checkpoint are left as they are.
+    pub fn apply_defaults(&mut self, py: Host) {
+        for channel in self.channels.values_mut() {
+            channel.apply_default(py);
+        }
+    }
+
+    /// A state with an empty copy of every channel, as this state was before
+    /// its first run
+    pub fn empty_copy(&self, py: Host) -> GraphState {
+        GraphState::with_channels(
+            self.channels
+                .iter()
+                .map(|(name, channel)| (name.clone(), channel.empty_copy(py)))
+                .collect(),
+        )
+    }
+
     /// Check if a channel exists
     pub fn has_channel(&self, name: &str) -> bool {
         self.channels.contains_key(name)
@@ -88,10 +391,13 @@ impl GraphState {
         self.channels.keys().cloned().collect()
     }
 
-    /// Create a checkpoint of all channels
-    pub fn checkpoint(&self, py: Python) -> PyResult<HashMap<String, PyObject>> {
+    /// Create a checkpoint of all checkpointed channels
+    pub fn checkpoint(&self, py: Host) -> HostResult<HashMap<String, Object>> {
         let mut checkpoint = HashMap::new();
         for (name, channel) in &self.channels {
+            if !channel.is_checkpointed() {
+                continue;
+            }
             checkpoint.insert(name.clone(), channel.checkpoint(py)?);
         }
         Ok(checkpoint)
@@ -100,9 +406,9 @@ impl GraphState {
     /// Restore state from a checkpoint
     pub fn from_checkpoint(
         &mut self,
-        py: Python,
-        checkpoint: HashMap<String, PyObject>,
-    ) -> PyResult<()> {
+        py: Host,
+        checkpoint: HashMap<String, Object>,
+    ) -> HostResult<()> {
         for (name, data) in checkpoint {
             if let Some(channel) = self.get_channel_mut(&name) {
                 channel.from_checkpoint(py, data)?;
@@ -111,6 +417,19 @@ impl GraphState {
         Ok(())
     }
 
+    /// View of the channels under `prefix`
+    ///
+    /// The view adds `prefix.` to every channel name it is given, so a
+    /// subgraph can use its own names (`results`) while the parent sees them
+    /// namespaced (`retriever.results`). Channels live in this state under
+    /// their fully-qualified names, which is also what checkpoints store.
+    pub fn namespace(&mut self, prefix: &str) -> NamespacedState<'_> {
+        NamespacedState {
+            state: self,
+            prefix: prefix.to_string(),
+        }
+    }
+
     /// Get the number of channels
     pub fn len(&self) -> usize {
         self.channels.len()
@@ -122,6 +441,78 @@ impl GraphState {
     }
 }
 
+/// Channels of a [`GraphState`] under a namespace, see [`GraphState::namespace`]
+pub struct NamespacedState<'a> {
+    state: &'a mut GraphState,
+    prefix: String,
+}
+
+impl NamespacedState<'_> {
+    /// The namespace's fully-qualified prefix
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Fully-qualified name of the namespace's channel `name`
+    pub fn qualify(&self, name: &str) -> String {
+        format!("{}{}{}", self.prefix, NAMESPACE_SEPARATOR, name)
+    }
+
+    /// View of a namespace nested inside this one
+    pub fn namespace(&mut self, prefix: &str) -> NamespacedState<'_> {
+        let prefix = self.qualify(prefix);
+        NamespacedState {
+            state: self.state,
+            prefix,
+        }
+    }
+
+    /// Add a channel to the namespace
+    pub fn add_channel(&mut self, name: &str, channel: Box<dyn Channel>) {
+        let name = self.qualify(name);
+        self.state.add_channel(name, channel);
+    }
+
+    /// Get a reference to a channel of the namespace
+    pub fn get_channel(&self, name: &str) -> Option<&dyn Channel> {
+        self.state.get_channel(&self.qualify(name))
+    }
+
+    /// Get a mutable reference to a channel of the namespace
+    pub fn get_channel_mut(&mut self, name: &str) -> Option<&mut dyn Channel> {
+        let name = self.qualify(name);
+        self.state.get_channel_mut(&name)
+    }
+
+    /// Get the value of a channel of the namespace
+    pub fn get_value(&self, py: Host, name: &str) -> Option<Object> {
+        self.state.get_value(py, &self.qualify(name))
+    }
+
+    /// Update a channel of the namespace with a value
+    pub fn update_channel(&mut self, py: Host, name: &str, value: Object) -> HostResult<()> {
+        let name = self.qualify(name);
+        self.state.update_channel(py, &name, value)
+    }
+
+    /// Check if the namespace has a channel
+    pub fn has_channel(&self, name: &str) -> bool {
+        self.state.has_channel(&self.qualify(name))
+    }
+
+    /// Names of the namespace's channels, without the prefix; channels of
+    /// nested namespaces keep their inner prefix
+    pub fn channel_names(&self) -> Vec<String> {
+        let prefix = format!("{}{}", self.prefix, NAMESPACE_SEPARATOR);
+        self.state
+            .channels
+            .keys()
+            .filter_map(|name| name.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
 impl Default for GraphState {
     fn default() -> Self {
         Self::new()
@@ -133,6 +524,7 @@ impl std::fmt::Debug for GraphState {
         f.debug_struct("GraphState")
             .field("channel_count", &self.channels.len())
             .field("channels", &self.channels.keys().collect::<Vec<_>>())
+            .field("typed_channels", &self.typed.keys().collect::<Vec<_>>())
             .finish()
     }
 }
@@ -140,9 +532,13 @@ impl std::fmt::Debug for GraphState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "python")]
     use crate::core::channel::LastValueChannel;
+    #[cfg(feature = "python")]
+    use pyo3::prelude::*;
 
     #[test]
+    #[cfg(feature = "python")]
     fn test_graph_state_creation() {
         let state = GraphState::new();
         assert_eq!(state.len(), 0);
@@ -150,6 +546,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "python")]
     fn test_add_channel() {
         pyo3::prepare_freethreaded_python();
 
@@ -163,6 +560,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "python")]
     fn test_update_and_get() {
         pyo3::prepare_freethreaded_python();
 
@@ -182,6 +580,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "python")]
     fn test_update_many() {
         pyo3::prepare_freethreaded_python();
 
@@ -218,6 +617,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "python")]
     fn test_checkpoint_restore() {
         pyo3::prepare_freethreaded_python();
 
@@ -243,6 +643,62 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "python")]
+    fn test_namespace() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut state = GraphState::new();
+            state.add_channel("results".to_string(), Box::new(LastValueChannel::new()));
+            let mut retriever = state.namespace("retriever");
+            retriever.add_channel("results", Box::new(LastValueChannel::new()));
+            retriever
+                .update_channel(py, "results", "docs".to_object(py))
+                .unwrap();
+            let mut ranker = retriever.namespace("ranker");
+            assert_eq!(ranker.prefix(), "retriever.ranker");
+            ranker.add_channel("scores", Box::new(LastValueChannel::new()));
+            ranker
+                .update_channel(py, "scores", 3.to_object(py))
+                .unwrap();
+
+            // The parent sees fully-qualified names and no collision
+            let mut names = state.channel_names();
+            names.sort();
+            assert_eq!(
+                names,
+                ["results", "retriever.ranker.scores", "retriever.results"]
+            );
+            assert!(state.get_value(py, "results").is_none());
+            let value = state.get_value(py, "retriever.results").unwrap();
+            assert_eq!(value.extract::<String>(py).unwrap(), "docs");
+
+            let retriever = state.namespace("retriever");
+            let mut names = retriever.channel_names();
+            names.sort();
+            assert_eq!(names, ["ranker.scores", "results"]);
+            assert!(!retriever.has_channel("scores"));
+
+            // Checkpoints keep the fully-qualified names
+            let checkpoint = state.checkpoint(py).unwrap();
+            assert!(checkpoint.contains_key("retriever.ranker.scores"));
+            let mut restored = GraphState::new();
+            restored
+                .namespace("retriever")
+                .namespace("ranker")
+                .add_channel("scores", Box::new(LastValueChannel::new()));
+            restored.from_checkpoint(py, checkpoint).unwrap();
+            let value = restored
+                .namespace("retriever")
+                .namespace("ranker")
+                .get_value(py, "scores")
+                .unwrap();
+            assert_eq!(value.extract::<i32>(py).unwrap(), 3);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "python")]
     fn test_channel_names() {
         let mut state = GraphState::new();
         state.add_channel("a".to_string(), Box::new(LastValueChannel::new()));
@@ -254,4 +710,63 @@ mod tests {
 
         assert_eq!(names, vec!["a", "b", "c"]);
     }
+
+    #[test]
+    fn test_typed_channels() {
+        use crate::channels::BinaryOperatorAggregateChannel;
+
+        let mut state = GraphState::new();
+        let question = state.add_typed_channel(
+            "question",
+            crate::channels::LastValueChannel::<String>::new(),
+        );
+        let total = state.add_typed_channel(
+            "total",
+            BinaryOperatorAggregateChannel::new(|a: i64, b: i64| a + b),
+        );
+
+        assert!(state
+            .update_typed(&question, vec!["why?".to_string()])
+            .unwrap());
+        assert!(state.update_typed(&total, vec![1, 2, 3]).unwrap());
+        assert!(state.update("total", vec![4i64]).unwrap());
+        assert_eq!(state.get_typed(&question).unwrap(), "why?");
+        assert_eq!(*state.get_typed(&total).unwrap(), 10);
+
+        // Reading or writing another type by name fails without touching the value
+        let err = state.get::<i32>("total").unwrap_err();
+        assert!(matches!(
+            err,
+            LangGraphError::ChannelTypeMismatch { ref channel, expected: "i64", requested: "i32" }
+                if channel == "total"
+        ));
+        assert!(err.to_string().contains("holds i64, not i32"));
+        let err = state.update("question", vec![42]).unwrap_err();
+        assert!(matches!(err, LangGraphError::ChannelTypeMismatch { .. }));
+        assert_eq!(state.get::<String>("question").unwrap(), "why?");
+
+        // Channel errors and unknown channels come through as they are
+        let err = state
+            .update_typed(&question, vec!["a".to_string(), "b".to_string()])
+            .unwrap_err();
+        assert!(matches!(err, LangGraphError::InvalidUpdate(_)));
+        assert!(matches!(
+            state.get::<String>("missing").unwrap_err(),
+            LangGraphError::Graph(GraphError::UnknownChannel(_))
+        ));
+
+        // Checkpoints restore into channels of the same types
+        let checkpoint = state.typed_checkpoint().unwrap();
+        let mut restored = GraphState::new();
+        restored.add_typed_channel(
+            "question",
+            crate::channels::LastValueChannel::<String>::new(),
+        );
+        let total = restored.add_typed_channel(
+            "total",
+            BinaryOperatorAggregateChannel::new(|a: i64, b: i64| a + b),
+        );
+        restored.restore_typed_checkpoint(checkpoint).unwrap();
+        assert_eq!(*restored.get_typed(&total).unwrap(), 10);
+    }
 }
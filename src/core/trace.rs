@@ -0,0 +1,461 @@
+//! Complete, ordered record of a PregelCore run
+//!
+//! [`PregelCore::invoke_with_trace`](super::PregelCore::invoke_with_trace)
+//! records every node input, node output, channel write and checkpoint of a
+//! run as an [`ExecutionEvent`]. Unlike the debug stream, a [`Trace`] holds
+//! everything needed to reproduce the run: it serializes to JSON and
+//! converts to the [`RunHistory`] taken by
+//! [`PregelCore::replay`](super::PregelCore::replay).
+//!
+//! Events are in canonical order, so two runs of the same graph on the same
+//! input produce identical traces however their nodes were scheduled: within
+//! a superstep, node inputs come first, then node outputs, both sorted by
+//! node name, then channel writes sorted by channel name, then the
+//! checkpoint. [`Trace::canonicalize`] restores that order in traces
+//! assembled or edited by hand, and [`Trace::diff`] compares two runs for
+//! golden-trace regression tests.
+
+use super::executor::{RunHistory, StepRecord};
+use super::host::{self, prelude::*};
+use crate::errors::GraphError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// One step of a traced run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecutionEvent {
+    /// Input a node was called with
+    NodeInput {
+        step: usize,
+        node: String,
+        input: Value,
+    },
+    /// Channel updates a node produced
+    NodeOutput {
+        step: usize,
+        node: String,
+        output: BTreeMap<String, Value>,
+    },
+    /// A channel written at a barrier, or by the input at step 0
+    ChannelWrite {
+        step: usize,
+        channel: String,
+        /// Nodes whose writes were applied, sorted; `__start__` for the input
+        writers: Vec<String>,
+        /// Value of the channel after the write
+        value: Value,
+    },
+    /// State committed at the end of a superstep
+    Checkpoint {
+        step: usize,
+        /// Values of the checkpointed channels
+        values: BTreeMap<String, Value>,
+        /// Nodes scheduled to run next, sorted; empty once the run finished
+        next: Vec<String>,
+    },
+}
+
+/// Record of a run taken with
+/// [`PregelCore::invoke_with_trace`](super::PregelCore::invoke_with_trace)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub input: Value,
+    pub context: Option<Value>,
+    /// Events of the run in canonical order
+    pub events: Vec<ExecutionEvent>,
+}
+
+impl Trace {
+    /// Serialize the trace to JSON
+    pub fn to_json(&self) -> Result<String, GraphError> {
+        serde_json::to_string(self).map_err(|e| GraphError::InvalidTrace(e.to_string()))
+    }
+
+    /// Read a trace written by [`to_json`](Self::to_json)
+    pub fn from_json(json: &str) -> Result<Self, GraphError> {
+        serde_json::from_str(json).map_err(|e| GraphError::InvalidTrace(e.to_string()))
+    }
+
+    /// The run as a [`RunHistory`], to hand to
+    /// [`PregelCore::replay`](super::PregelCore::replay)
+    pub fn to_history(&self, py: Host<'_>) -> RunHistory {
+        let mut steps: Vec<StepRecord> = Vec::new();
+        for event in &self.events {
+            let ExecutionEvent::NodeOutput { step, node, output } = event else {
+                continue;
+            };
+            if steps.last().map(|record| record.step) != Some(*step) {
+                steps.push(StepRecord {
+                    step: *step,
+                    outputs: Default::default(),
+                });
+            }
+            let updates = output
+                .iter()
+                .map(|(channel, value)| (channel.clone(), host::from_json(py, value)))
+                .collect();
+            if let Some(record) = steps.last_mut() {
+                record.outputs.insert(node.clone(), updates);
+            }
+        }
+        RunHistory {
+            input: host::from_json(py, &self.input),
+            context: self.context.as_ref().map(|ctx| host::from_json(py, ctx)),
+            steps,
+            entries: false,
+        }
+    }
+
+    /// Put the events in canonical order and sort the node lists they hold
+    ///
+    /// Traces recorded by `invoke_with_trace` already are canonical, so two
+    /// canonical traces of runs that behaved the same compare equal.
+    pub fn canonicalize(&mut self) {
+        for event in &mut self.events {
+            match event {
+                ExecutionEvent::ChannelWrite { writers, .. } => writers.sort(),
+                ExecutionEvent::Checkpoint { next, .. } => next.sort(),
+                _ => {}
+            }
+        }
+        self.events.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    }
+
+    /// Where this run and `other` behaved differently
+    ///
+    /// Compares the input and context, each node's input and output, and
+    /// the nodes scheduled after each step, which is where routing decisions
+    /// show. Channel values follow from those and are not compared, and
+    /// neither is event order, so traces need not be canonical. This trace is
+    /// the "before" side of every divergence.
+    pub fn diff(&self, other: &Trace) -> TraceDiff {
+        let mut divergences = Vec::new();
+        if self.input != other.input || self.context != other.context {
+            divergences.push(Divergence::Input {
+                before: (self.input.clone(), self.context.clone()),
+                after: (other.input.clone(), other.context.clone()),
+            });
+        }
+
+        let (before, after) = (self.by_step(), other.by_step());
+        let steps: BTreeSet<usize> = before.keys().chain(after.keys()).copied().collect();
+        let empty = StepEvents::default();
+        for step in steps {
+            let before = before.get(&step).unwrap_or(&empty);
+            let after = after.get(&step).unwrap_or(&empty);
+            let nodes: BTreeSet<&str> = before
+                .inputs
+                .keys()
+                .chain(before.outputs.keys())
+                .chain(after.inputs.keys())
+                .chain(after.outputs.keys())
+                .copied()
+                .collect();
+            for node in &nodes {
+                let (input_before, input_after) = (before.inputs.get(node), after.inputs.get(node));
+                if input_before != input_after {
+                    divergences.push(Divergence::NodeInput {
+                        step,
+                        node: node.to_string(),
+                        before: input_before.map(|v| (*v).clone()),
+                        after: input_after.map(|v| (*v).clone()),
+                    });
+                }
+            }
+            for node in &nodes {
+                let (output_before, output_after) =
+                    (before.outputs.get(node), after.outputs.get(node));
+                if output_before != output_after {
+                    divergences.push(Divergence::NodeOutput {
+                        step,
+                        node: node.to_string(),
+                        before: output_before.map(|v| (*v).clone()),
+                        after: output_after.map(|v| (*v).clone()),
+                    });
+                }
+            }
+            if before.next != after.next {
+                divergences.push(Divergence::Routing {
+                    step,
+                    before: before.next.clone(),
+                    after: after.next.clone(),
+                });
+            }
+        }
+        TraceDiff { divergences }
+    }
+
+    /// Node inputs, node outputs and scheduled nodes of each step
+    fn by_step(&self) -> BTreeMap<usize, StepEvents<'_>> {
+        let mut steps: BTreeMap<usize, StepEvents> = BTreeMap::new();
+        for event in &self.events {
+            match event {
+                ExecutionEvent::NodeInput { step, node, input } => {
+                    steps.entry(*step).or_default().inputs.insert(node, input);
+                }
+                ExecutionEvent::NodeOutput { step, node, output } => {
+                    steps.entry(*step).or_default().outputs.insert(node, output);
+                }
+                ExecutionEvent::Checkpoint { step, next, .. } => {
+                    let mut next = next.clone();
+                    next.sort();
+                    steps.entry(*step).or_default().next = Some(next);
+                }
+                ExecutionEvent::ChannelWrite { .. } => {}
+            }
+        }
+        steps
+    }
+}
+
+impl ExecutionEvent {
+    /// Position of the event in canonical order: by step, then inputs,
+    /// outputs, writes and the checkpoint, each sorted by name
+    fn sort_key(&self) -> (usize, u8, &str) {
+        match self {
+            ExecutionEvent::NodeInput { step, node, .. } => (*step, 0, node),
+            ExecutionEvent::NodeOutput { step, node, .. } => (*step, 1, node),
+            ExecutionEvent::ChannelWrite { step, channel, .. } => (*step, 2, channel),
+            ExecutionEvent::Checkpoint { step, .. } => (*step, 3, ""),
+        }
+    }
+}
+
+/// Compared events of one step of a trace
+#[derive(Default)]
+struct StepEvents<'a> {
+    inputs: BTreeMap<&'a str, &'a Value>,
+    outputs: BTreeMap<&'a str, &'a BTreeMap<String, Value>>,
+    /// Nodes scheduled after the step, sorted; `None` without a checkpoint
+    next: Option<Vec<String>>,
+}
+
+/// A difference between two runs found by [`Trace::diff`]
+///
+/// `before` is the side of the trace `diff` was called on; `None` means the
+/// node did not run in that trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// The runs started from different input or context
+    Input {
+        before: (Value, Option<Value>),
+        after: (Value, Option<Value>),
+    },
+    /// A node was called with a different input, or ran in only one trace
+    NodeInput {
+        step: usize,
+        node: String,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+    /// A node produced different channel updates, or ran in only one trace
+    NodeOutput {
+        step: usize,
+        node: String,
+        before: Option<BTreeMap<String, Value>>,
+        after: Option<BTreeMap<String, Value>>,
+    },
+    /// Different nodes were scheduled after a step
+    Routing {
+        step: usize,
+        before: Option<Vec<String>>,
+        after: Option<Vec<String>>,
+    },
+}
+
+/// Differences between two traces, in run order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceDiff {
+    /// By step, then node inputs, node outputs and routing, each by node
+    pub divergences: Vec<Divergence>,
+}
+
+impl TraceDiff {
+    /// Whether the runs behaved the same
+    pub fn is_empty(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// The earliest divergence
+    pub fn first(&self) -> Option<&Divergence> {
+        self.divergences.first()
+    }
+
+    /// Step and name of the first node whose input or output diverged
+    pub fn first_divergent_node(&self) -> Option<(usize, &str)> {
+        self.divergences
+            .iter()
+            .find_map(|divergence| match divergence {
+                Divergence::NodeInput { step, node, .. }
+                | Divergence::NodeOutput { step, node, .. } => Some((*step, node.as_str())),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for TraceDiff {
+    /// One divergence per line with its before and after values, for test
+    /// failure messages
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn json<T: Serialize>(value: &Option<T>) -> String {
+            match value {
+                Some(value) => serde_json::to_string(value).unwrap_or_default(),
+                None => "(did not run)".to_string(),
+            }
+        }
+        if self.is_empty() {
+            return write!(f, "traces match");
+        }
+        for divergence in &self.divergences {
+            match divergence {
+                Divergence::Input { before, after } => writeln!(
+                    f,
+                    "input: {} -> {}",
+                    json(&Some(before)),
+                    json(&Some(after))
+                )?,
+                Divergence::NodeInput {
+                    step,
+                    node,
+                    before,
+                    after,
+                } => writeln!(
+                    f,
+                    "step {step}, node '{node}' input: {} -> {}",
+                    json(before),
+                    json(after)
+                )?,
+                Divergence::NodeOutput {
+                    step,
+                    node,
+                    before,
+                    after,
+                } => writeln!(
+                    f,
+                    "step {step}, node '{node}' output: {} -> {}",
+                    json(before),
+                    json(after)
+                )?,
+                Divergence::Routing {
+                    step,
+                    before,
+                    after,
+                } => writeln!(
+                    f,
+                    "step {step}, next nodes: {} -> {}",
+                    json(before),
+                    json(after)
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert a value to its traced form, failing with `TypeError` for values
+/// that are not JSON data
+pub(crate) fn trace_value(
+    py: Host<'_>,
+    value: &Object,
+    what: impl FnOnce() -> String,
+) -> HostResult<Value> {
+    host::to_json(py, value).ok_or_else(|| {
+        host::type_error(format!(
+            "{} is {}, which is not JSON serializable and cannot be traced",
+            what(),
+            host::type_name(py, value)
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace(answer: &str, next: &[&str]) -> Trace {
+        let next: Vec<String> = next.iter().map(|n| n.to_string()).collect();
+        Trace {
+            input: json!({ "question": "hi" }),
+            context: None,
+            events: vec![
+                ExecutionEvent::NodeInput {
+                    step: 1,
+                    node: "answer".to_string(),
+                    input: json!({ "question": "hi" }),
+                },
+                ExecutionEvent::NodeInput {
+                    step: 1,
+                    node: "log".to_string(),
+                    input: json!({ "question": "hi" }),
+                },
+                ExecutionEvent::NodeOutput {
+                    step: 1,
+                    node: "answer".to_string(),
+                    output: [("answer".to_string(), json!(answer))].into(),
+                },
+                ExecutionEvent::NodeOutput {
+                    step: 1,
+                    node: "log".to_string(),
+                    output: [("logged".to_string(), json!(true))].into(),
+                },
+                ExecutionEvent::Checkpoint {
+                    step: 1,
+                    values: BTreeMap::new(),
+                    next,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_trace_json_round_trip() {
+        let trace = trace("hello", &["review", "done"]);
+        let json = trace.to_json().unwrap();
+        assert_eq!(Trace::from_json(&json).unwrap(), trace);
+
+        let err = Trace::from_json("{\"input\": 1}").unwrap_err();
+        assert!(matches!(err, GraphError::InvalidTrace(_)));
+    }
+
+    #[test]
+    fn test_trace_diff() {
+        let golden = trace("hello", &["review", "done"]);
+
+        // Event order and the order of scheduled nodes don't matter
+        let mut shuffled = trace("hello", &["done", "review"]);
+        shuffled.events.reverse();
+        assert!(golden.diff(&shuffled).is_empty());
+        assert_ne!(golden, shuffled);
+        shuffled.canonicalize();
+        assert_eq!(golden.events[..4], shuffled.events[..4]);
+
+        let changed = trace("goodbye", &["done"]);
+        let diff = golden.diff(&changed);
+        assert_eq!(diff.first_divergent_node(), Some((1, "answer")));
+        assert_eq!(
+            diff.divergences,
+            [
+                Divergence::NodeOutput {
+                    step: 1,
+                    node: "answer".to_string(),
+                    before: Some([("answer".to_string(), json!("hello"))].into()),
+                    after: Some([("answer".to_string(), json!("goodbye"))].into()),
+                },
+                Divergence::Routing {
+                    step: 1,
+                    before: Some(vec!["done".to_string(), "review".to_string()]),
+                    after: Some(vec!["done".to_string()]),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "step 1, node 'answer' output: {\"answer\":\"hello\"} -> {\"answer\":\"goodbye\"}\n\
+             step 1, next nodes: [\"done\",\"review\"] -> [\"done\"]\n"
+        );
+    }
+}
@@ -30,6 +30,364 @@ pub enum LangGraphError {
 
     #[error("Graph recursion limit exceeded")]
     GraphRecursionError,
+
+    #[error("Store error: {0}")]
+    StoreError(String),
+
+    /// A channel was read or written as a type other than its own
+    #[error("Channel type mismatch: channel '{channel}' holds {expected}, not {requested}")]
+    ChannelTypeMismatch {
+        channel: String,
+        expected: &'static str,
+        requested: &'static str,
+    },
+
+    /// An error the `python` build raises as the built-in exception `kind`,
+    /// e.g. `TypeError`, from a core build without it
+    #[error("{kind}: {message}")]
+    Host { kind: &'static str, message: String },
+
+    #[error(transparent)]
+    Graph(#[from] GraphError),
+}
+
+/// Errors raised while building or compiling a graph
+#[derive(Error, Debug)]
+pub enum GraphError {
+    #[error("Graph validation failed: {}", format_issues(.0))]
+    ValidationFailed(Vec<ValidationIssue>),
+
+    /// `writers` wrote to `channel` when they may not: a single writer is
+    /// not allowed to write the channel, several conflict within one step,
+    /// or the channel rejected the values written
+    #[error("Invalid update: {}", format_invalid_update(.channel, .writers))]
+    InvalidUpdate {
+        channel: String,
+        writers: Vec<String>,
+    },
+
+    #[error("Missing input: required input channel '{0}' was not provided")]
+    MissingInput(String),
+
+    /// A node built with `Node::with_inputs` ran before one of its inputs
+    /// had a value
+    #[error("Missing node input: node '{node}' needs channel '{channel}', which has no value")]
+    MissingNodeInput { node: String, channel: String },
+
+    /// The graph's input validator rejected the input
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Duplicate node: a node named '{0}' already exists")]
+    DuplicateNode(String),
+
+    /// The run was cancelled; state holds the checkpoint committed after
+    /// `step`, saved as `checkpoint_id` if the run has a checkpointer and
+    /// committed a step
+    #[error("Cancelled: run was cancelled after {step} committed step(s)")]
+    Cancelled {
+        step: usize,
+        checkpoint_id: Option<String>,
+    },
+
+    #[error("Guard failed: {message}")]
+    GuardFailed { message: String },
+
+    /// A guard interrupted the run; state holds the last committed checkpoint
+    /// and the interrupted step runs again on resume
+    #[error("Interrupted: guard '{node}' failed: {message}")]
+    Interrupted { node: String, message: String },
+
+    /// A node attempt ran past its own timeout or the step timeout
+    #[error("Node timeout: node '{node}' did not finish in time")]
+    NodeTimeout { node: String },
+
+    #[error("Not interrupted: the run has no pending interrupt to resume")]
+    NotInterrupted,
+
+    #[error("Ambiguous resume: {0} interrupts are pending; resume them by ID")]
+    AmbiguousResume(usize),
+
+    #[error("Unknown interrupt: no pending interrupt has ID '{0}'")]
+    UnknownInterrupt(String),
+
+    #[error("Unknown node: '{0}'")]
+    UnknownNode(String),
+
+    #[error("Unknown channel: '{0}'")]
+    UnknownChannel(String),
+
+    /// Channel aliases mirror writes in a loop; holds the channels of the
+    /// cycle, starting and ending with the same one
+    #[error("Alias cycle: {}", .0.join(" -> "))]
+    AliasCycle(Vec<String>),
+
+    /// Time-travel APIs read the snapshots saved by a checkpointer
+    #[error("No checkpointer: compile the graph with a checkpointer to use {0}")]
+    NoCheckpointer(&'static str),
+
+    #[error("Checkpoint not found: no saved snapshot at index {0}")]
+    SnapshotNotFound(usize),
+
+    #[error("Checkpoint not found: no saved snapshot with ID '{0}'")]
+    SnapshotIdNotFound(String),
+
+    /// A graph spec could not be loaded
+    #[error("Invalid graph spec: {0}")]
+    InvalidSpec(String),
+
+    /// A trace could not be written or read as JSON
+    #[error("Invalid trace: {0}")]
+    InvalidTrace(String),
+
+    /// A barrier channel waits for writers that can no longer run, so the
+    /// node it triggers never will
+    #[error(
+        "Deadlock: barrier channel '{channel}' waits for {} which can no longer run",
+        format_names(.missing)
+    )]
+    Deadlock {
+        channel: String,
+        missing: Vec<String>,
+    },
+
+    /// Replacement nodes change the graph's channels, or the channels no
+    /// longer match those recorded in the latest checkpoint
+    #[error("Incompatible graph update: {}", .0.join("; "))]
+    IncompatibleUpdate(Vec<String>),
+
+    #[error("Streaming input unsupported: channel '{0}' does not accumulate writes")]
+    StreamingInputUnsupported(String),
+
+    #[error("Replay diverged at step {step}: recorded {recorded:?}, scheduled {scheduled:?}")]
+    ReplayDiverged {
+        step: usize,
+        recorded: Vec<String>,
+        scheduled: Vec<String>,
+    },
+
+    /// A checkpoint was saved by a graph whose structure differs from the
+    /// one resuming it; holds both schema fingerprints
+    #[error("Checkpoint incompatible: graph fingerprint is '{expected}', checkpoint was saved by '{found}'")]
+    CheckpointIncompatible { expected: String, found: String },
+
+    /// Channel state outgrew its memory budget at a barrier; `bytes` is the
+    /// channel's approximate size when its own limit was exceeded, or that of
+    /// the whole state when the global one was
+    #[error("State too large: channel '{channel}' brought state over its memory budget at about {bytes} bytes")]
+    StateTooLarge { channel: String, bytes: usize },
+
+    /// A runtime check's run did not finish within its time limit
+    #[error("Runtime check timed out: the run did not finish within {timeout_ms} ms")]
+    RuntimeCheckTimeout { timeout_ms: u128 },
+
+    /// A node failed outside its normal error path, e.g. a Rust node
+    /// panicked (the source is then a [`NodePanic`])
+    #[error("Node execution failed: node '{node}': {source}")]
+    NodeExecution {
+        node: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// A Rust node closure panicked; holds the panic message
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("panicked: {message}")]
+pub struct NodePanic {
+    pub message: String,
+}
+
+impl NodePanic {
+    /// Read the message of a caught panic payload
+    ///
+    /// `panic!` payloads are a `&str` or a `String`; other payloads (from
+    /// `std::panic::panic_any`) get a placeholder message.
+    pub fn from_payload(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "<non-string panic payload>".to_string(),
+            },
+        };
+        Self { message }
+    }
+}
+
+/// Run the node `node`'s code, turning a panic into
+/// [`GraphError::NodeExecution`] with a [`NodePanic`] source
+///
+/// Only unwinding panics are caught. A panic in a build with
+/// `panic = "abort"`, a panic raised while already unwinding, and process
+/// failures such as stack overflow or out-of-memory still abort the process.
+/// State the node was mutating through shared references (locks, cells) may
+/// be left half-updated, so nodes should only communicate through their
+/// return value.
+pub fn catch_node_panic<R>(node: &str, f: impl FnOnce() -> R) -> Result<R, GraphError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        GraphError::NodeExecution {
+            node: node.to_string(),
+            source: Box::new(NodePanic::from_payload(payload)),
+        }
+    })
+}
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    fast_langgraph,
+    GraphCancelled,
+    pyo3::exceptions::PyException,
+    "Raised when a graph run is cancelled before completing; `checkpoint_id` names the last committed checkpoint, if saved."
+);
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    fast_langgraph,
+    GuardFailed,
+    pyo3::exceptions::PyException,
+    "Raised when a guard node's predicate does not hold."
+);
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    fast_langgraph,
+    GraphInterrupted,
+    pyo3::exceptions::PyException,
+    "Raised when a guard node interrupts a run so it can be resumed later."
+);
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    fast_langgraph,
+    NodeInterrupt,
+    pyo3::exceptions::PyException,
+    "Raised by interrupt() to pause the graph; carries the interrupt value."
+);
+
+#[cfg(feature = "python")]
+impl From<GraphError> for pyo3::PyErr {
+    fn from(error: GraphError) -> Self {
+        match error {
+            GraphError::Cancelled {
+                ref checkpoint_id, ..
+            } => {
+                // The checkpoint to resume from travels with the exception
+                let err = GraphCancelled::new_err(error.to_string());
+                pyo3::Python::with_gil(|py| {
+                    let _ = err
+                        .value(py)
+                        .setattr("checkpoint_id", checkpoint_id.clone());
+                });
+                err
+            }
+            GraphError::GuardFailed { .. } => GuardFailed::new_err(error.to_string()),
+            GraphError::Interrupted { .. } => GraphInterrupted::new_err(error.to_string()),
+            GraphError::NodeTimeout { .. } | GraphError::RuntimeCheckTimeout { .. } => {
+                pyo3::exceptions::PyTimeoutError::new_err(error.to_string())
+            }
+            GraphError::NodeExecution { .. } => {
+                pyo3::exceptions::PyRuntimeError::new_err(error.to_string())
+            }
+            GraphError::StateTooLarge { .. } => {
+                pyo3::exceptions::PyMemoryError::new_err(error.to_string())
+            }
+            _ => pyo3::exceptions::PyValueError::new_err(error.to_string()),
+        }
+    }
+}
+
+/// A single structural problem found by graph validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// No entry point was set
+    MissingEntryPoint,
+    /// The entry point names a node that does not exist
+    UnknownEntryPoint(String),
+    /// An edge references a node that does not exist
+    UnknownEdgeNode { source: String, target: String },
+    /// A conditional edge's path_map routes to a node that does not exist
+    UnknownPathTarget {
+        source: String,
+        branch: String,
+        target: String,
+    },
+    /// The node cannot be reached from the entry point
+    UnreachableNode(String),
+    /// The node has no outgoing edge and is not marked as a finish point
+    DanglingNode(String),
+    /// A cycle not closed by an edge marked `cyclic`, as a node path
+    Cycle(Vec<String>),
+    /// A node declares a write to a field missing from the state schema
+    UnknownStateField { node: String, field: String },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::MissingEntryPoint => write!(f, "no entry point set"),
+            ValidationIssue::UnknownEntryPoint(node) => {
+                write!(f, "entry point '{}' not found in nodes", node)
+            }
+            ValidationIssue::UnknownEdgeNode { source, target } => {
+                write!(
+                    f,
+                    "edge '{}' -> '{}' references unknown node",
+                    source, target
+                )
+            }
+            ValidationIssue::UnknownPathTarget {
+                source,
+                branch,
+                target,
+            } => write!(
+                f,
+                "conditional edge from '{}' maps '{}' to unknown node '{}'",
+                source, branch, target
+            ),
+            ValidationIssue::UnreachableNode(node) => {
+                write!(f, "node '{}' is unreachable from the entry point", node)
+            }
+            ValidationIssue::DanglingNode(node) => write!(
+                f,
+                "node '{}' has no outgoing edge and is not a finish point",
+                node
+            ),
+            ValidationIssue::Cycle(path) => write!(f, "unmarked cycle {}", path.join(" -> ")),
+            ValidationIssue::UnknownStateField { node, field } => write!(
+                f,
+                "node '{}' writes '{}' which is not a state field",
+                node, field
+            ),
+        }
+    }
+}
+
+fn format_invalid_update(channel: &str, writers: &[String]) -> String {
+    match writers {
+        [writer] => format!("node '{}' cannot write to channel '{}'", writer, channel),
+        _ => format!(
+            "channel '{}' takes one write per step, but nodes {} wrote to it",
+            channel,
+            format_names(writers)
+        ),
+    }
+}
+
+/// Quoted, comma-separated names
+fn format_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 #[cfg(feature = "msgpack")]
@@ -2,10 +2,59 @@
 //!
 //! This module implements the core execution logic for running graphs.
 //! It handles node execution, state management, and error handling.
+//!
+//! Nodes built with [`NodeFunction::from_state_fn`] read the [`State`] and
+//! return [`Writes`], which the executor applies after the node runs. Writes
+//! to a channel declared with a reducer (see
+//! [`ChannelSpec::with_reducer`](crate::graph::ChannelSpec::with_reducer))
+//! are merged into its value with the reducer registered under that name
+//! through [`Executor::with_reducer`]; other writes replace the value. This
+//! path needs no Python.
+//!
+//! A node that panics fails the run with
+//! [`GraphError::NodeExecution`](crate::errors::GraphError::NodeExecution)
+//! rather than unwinding through the executor; see
+//! [`catch_node_panic`] for which panics are recoverable.
 
-use crate::graph::{Edge, Graph, NodeFunction};
+use crate::errors::{catch_node_panic, GraphError};
+use crate::graph::{Edge, Graph, NodeFunction, END};
 use std::any::Any;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Entry of [`RetryPolicy::retry_on`](crate::graph::RetryPolicy::retry_on)
+/// that makes the executor retry a node after it panics
+pub const PANIC_RETRY_KIND: &str = "panic";
+
+/// State updates returned by a node, keyed by channel
+pub type Writes = HashMap<String, Box<dyn Any + Send + Sync>>;
+
+/// Merges a write into a channel's current value, returning the new value
+pub type Reducer = Arc<
+    dyn Fn(
+            Box<dyn Any + Send + Sync>,
+            Box<dyn Any + Send + Sync>,
+        ) -> Result<Box<dyn Any + Send + Sync>, String>
+        + Send
+        + Sync,
+>;
+
+/// Build a [`Reducer`] over values of type `T`
+///
+/// The reducer fails if the current value or the write is not a `T`.
+pub fn reducer<T: Send + Sync + 'static>(
+    merge: impl Fn(T, T) -> T + Send + Sync + 'static,
+) -> Reducer {
+    Arc::new(
+        move |current, write| match (current.downcast::<T>(), write.downcast::<T>()) {
+            (Ok(current), Ok(write)) => Ok(Box::new(merge(*current, *write))),
+            _ => Err(format!(
+                "Reducer expected values of type {}",
+                std::any::type_name::<T>()
+            )),
+        },
+    )
+}
 
 /// State represents the current execution state of the graph
 pub struct State {
@@ -31,6 +80,13 @@ impl State {
         self.values.get(key).map(|v| v.as_ref())
     }
 
+    /// Get a value from state as a `T`
+    ///
+    /// Returns `None` if the key is missing or holds another type.
+    pub fn get_as<T: 'static>(&self, key: &str) -> Option<&T> {
+        self.values.get(key)?.downcast_ref()
+    }
+
     /// Set a value in state
     pub fn set(&mut self, key: String, value: Box<dyn Any + Send + Sync>) {
         self.values.insert(key, value);
@@ -60,26 +116,51 @@ impl Default for State {
     }
 }
 
+/// Index the graph's edges and compute its execution order once, so runs
+/// only read the topology
+fn prepare(mut graph: Graph) -> Arc<Graph> {
+    graph.index_edges();
+    graph.execution_order();
+    Arc::new(graph)
+}
+
 /// Executor runs graphs and manages execution state
+///
+/// The graph is prepared when the executor is created and never changes
+/// afterwards; only the state is per run.
 pub struct Executor {
-    /// The graph to execute
-    graph: Graph,
+    /// The graph to execute, shared with the [`GraphPool`] it came from
+    graph: Arc<Graph>,
     /// Current execution state
     state: State,
+    /// Reducers by name, for channels declared with one
+    reducers: Arc<HashMap<String, Reducer>>,
 }
 
 impl Executor {
     /// Create a new executor for the given graph
     pub fn new(graph: Graph) -> Self {
-        Self {
-            graph,
-            state: State::new(),
-        }
+        Self::with_state(graph, State::new())
     }
 
     /// Create an executor with initial state
     pub fn with_state(graph: Graph, state: State) -> Self {
-        Self { graph, state }
+        Self {
+            graph: prepare(graph),
+            state,
+            reducers: Arc::default(),
+        }
+    }
+
+    /// Register the reducer named `name` in channel specs
+    pub fn with_reducer(mut self, name: impl Into<String>, reducer: Reducer) -> Self {
+        Arc::make_mut(&mut self.reducers).insert(name.into(), reducer);
+        self
+    }
+
+    /// Take the state, ending the executor
+    pub fn into_state(self) -> State {
+        self.state
     }
 
     /// Get a reference to the current state
@@ -106,20 +187,78 @@ impl Executor {
             .get(node_name)
             .ok_or_else(|| format!("Node '{}' not found in graph", node_name))?;
 
-        // Execute the node function with current state
+        // Execute the node function with current state; a panic is retried
+        // if the node's retry policy lists "panic"
         let state_ref = &self.state as &dyn Any;
-        let _result = match &node.function {
-            NodeFunction::Python(func) | NodeFunction::Rust(func) => func(state_ref)?,
+        let func = match &node.function {
+            NodeFunction::Python(func) | NodeFunction::Rust(func) => func,
+        };
+        let max_attempts = match &node.retry_policy {
+            Some(policy) if policy.retry_on.iter().any(|kind| kind == PANIC_RETRY_KIND) => {
+                policy.max_attempts.max(1)
+            }
+            _ => 1,
+        };
+        let mut attempt = 1;
+        let result = loop {
+            match catch_node_panic(node_name, || func(state_ref)) {
+                Ok(result) => break result?,
+                Err(e) if attempt >= max_attempts => return Err(e.to_string()),
+                Err(_) => {
+                    if let Some(policy) = &node.retry_policy {
+                        std::thread::sleep(policy.backoff.delay(attempt));
+                    }
+                    attempt += 1;
+                }
+            }
         };
 
-        // Update state with result
-        // For now, we'll assume the result is a HashMap update
-        // In a real implementation, this would be more sophisticated
-        // and handle different return types
+        // Results other than writes leave the state unchanged
+        match result.downcast::<Writes>() {
+            Ok(writes) => self.apply_writes(*writes),
+            Err(_) => Ok(()),
+        }
+    }
 
+    /// Apply `writes` to the state, merging through channel reducers
+    pub fn apply_writes(&mut self, writes: Writes) -> Result<(), String> {
+        for (channel, value) in writes {
+            let reducer = match self
+                .graph
+                .channels
+                .get(&channel)
+                .and_then(|spec| spec.reducer.as_ref())
+            {
+                Some(name) => Some(self.reducers.get(name).ok_or_else(|| {
+                    format!(
+                        "Channel '{}' uses reducer '{}', which is not registered",
+                        channel, name
+                    )
+                })?),
+                None => None,
+            };
+            let value = match (reducer, self.state.values.remove(&channel)) {
+                (Some(reducer), Some(current)) => reducer(current, value)
+                    .map_err(|e| format!("Reducer of channel '{}' failed: {}", channel, e))?,
+                _ => value,
+            };
+            self.state.set(channel, value);
+        }
         Ok(())
     }
 
+    /// Store the run's input: writes are applied to the state, any other
+    /// value is kept under `__input__`
+    fn set_input(&mut self, input: Box<dyn Any + Send + Sync>) -> Result<(), String> {
+        match input.downcast::<Writes>() {
+            Ok(writes) => self.apply_writes(*writes),
+            Err(input) => {
+                self.state.set("__input__".to_string(), input);
+                Ok(())
+            }
+        }
+    }
+
     /// Execute the entire graph from entry to finish
     ///
     /// This follows the execution order determined by the graph topology.
@@ -129,18 +268,16 @@ impl Executor {
         input: Box<dyn Any + Send + Sync>,
     ) -> Result<Box<dyn Any + Send + Sync>, String> {
         // Set initial input as state
-        // For simplicity, we'll store it under a special "__input__" key
-        self.state.set("__input__".to_string(), input);
+        self.set_input(input)?;
 
-        // Get execution order (clone to avoid borrow checker issues)
-        let execution_order = self
-            .graph
-            .execution_order()
-            .ok_or_else(|| "Cannot execute graph: contains cycles or invalid topology".to_string())?
-            .to_vec();
+        // Execution order computed when the graph was prepared
+        let graph = Arc::clone(&self.graph);
+        let execution_order = graph.cached_execution_order().ok_or_else(|| {
+            "Cannot execute graph: contains cycles or invalid topology".to_string()
+        })?;
 
         // Execute nodes in order
-        for node_name in &execution_order {
+        for node_name in execution_order {
             self.execute_node(node_name)?;
         }
 
@@ -168,7 +305,7 @@ impl Executor {
         input: Box<dyn Any + Send + Sync>,
     ) -> Result<Box<dyn Any + Send + Sync>, String> {
         // Set initial input
-        self.state.set("__input__".to_string(), input);
+        self.set_input(input)?;
 
         // Execute specified nodes in order
         for node_name in node_names {
@@ -197,6 +334,11 @@ impl Executor {
                 let state_ref = &self.state as &dyn Any;
                 let condition_result = condition(state_ref)?;
 
+                // Routing straight to END stops this branch
+                if condition_result == END && !path_map.contains_key(END) {
+                    return Ok(Some(END.to_string()));
+                }
+
                 // Look up the target in the path map
                 let target = path_map.get(&condition_result).ok_or_else(|| {
                     format!(
@@ -219,7 +361,7 @@ impl Executor {
         input: Box<dyn Any + Send + Sync>,
     ) -> Result<Box<dyn Any + Send + Sync>, String> {
         // Set initial input
-        self.state.set("__input__".to_string(), input);
+        self.set_input(input)?;
 
         // Start from entry point
         let mut current_node = self
@@ -248,24 +390,18 @@ impl Executor {
             self.execute_node(&current_node)?;
             visited.insert(current_node.clone());
 
-            // Find outgoing edges from current node
-            let mut next_node: Option<String> = None;
-            for edge in &self.graph.edges {
-                match edge {
-                    Edge::Direct { source, target } if source == &current_node => {
-                        next_node = Some(target.clone());
-                        break;
-                    }
-                    Edge::Conditional { source, .. } if source == &current_node => {
-                        next_node = self.evaluate_conditional_edge(edge)?;
-                        break;
-                    }
-                    _ => {}
-                }
-            }
+            // Follow the first direct edge, else the first conditional edge
+            let next_node = match self.graph.successors(&current_node).first() {
+                Some(target) => Some(target.clone()),
+                None => match self.graph.conditional_edges(&current_node).first() {
+                    Some(edge) => self.evaluate_conditional_edge(edge)?,
+                    None => None,
+                },
+            };
 
             // Move to next node or finish
             match next_node {
+                Some(next) if next == END => break, // Routed to END
                 Some(next) => current_node = next,
                 None => break, // No outgoing edges, we're done
             }
@@ -287,6 +423,57 @@ impl Executor {
     }
 }
 
+/// A prepared graph shared by many runs
+///
+/// Preparing a graph (validating it, indexing its edges, ordering its
+/// nodes) is paid once when the pool is created. Each run then gets its own
+/// [`Executor`] holding an `Arc` of the same graph and reducers plus a fresh
+/// [`State`], so creating one allocates only the state. The pool is cheap to
+/// clone and can be shared across threads.
+#[derive(Clone)]
+pub struct GraphPool {
+    graph: Arc<Graph>,
+    reducers: Arc<HashMap<String, Reducer>>,
+}
+
+impl GraphPool {
+    /// Validate and prepare `graph` (see [`Graph::compile`])
+    pub fn new(graph: Graph) -> Result<Self, GraphError> {
+        Ok(Self {
+            graph: prepare(graph.compile()?),
+            reducers: Arc::default(),
+        })
+    }
+
+    /// Register the reducer named `name` in channel specs for every run
+    pub fn with_reducer(mut self, name: impl Into<String>, reducer: Reducer) -> Self {
+        Arc::make_mut(&mut self.reducers).insert(name.into(), reducer);
+        self
+    }
+
+    /// The shared graph
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// An executor for one run, starting from an empty state
+    pub fn executor(&self) -> Executor {
+        Executor {
+            graph: Arc::clone(&self.graph),
+            state: State::new(),
+            reducers: Arc::clone(&self.reducers),
+        }
+    }
+
+    /// Run the graph on `input` (see [`Executor::invoke`]) and return the
+    /// run's final state
+    pub fn invoke(&self, input: Box<dyn Any + Send + Sync>) -> Result<State, String> {
+        let mut executor = self.executor();
+        executor.invoke(input)?;
+        Ok(executor.into_state())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -338,4 +525,200 @@ mod tests {
         let executor = Executor::with_state(graph, state);
         assert!(executor.state().get("initial").is_some());
     }
+
+    #[test]
+    fn test_routing_to_end_stops_branch() {
+        use crate::graph::{Node, START};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let mut graph = Graph::new();
+        graph.add_node(Node {
+            name: "agent".to_string(),
+            function: NodeFunction::Rust(Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(()))
+            })),
+            retry_policy: None,
+        });
+        graph.add_edge(Edge::Direct {
+            source: START.to_string(),
+            target: "agent".to_string(),
+            cyclic: false,
+        });
+        graph.add_edge(Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok(END.to_string())),
+            path_map: HashMap::new(),
+            cyclic: false,
+            name: None,
+        });
+
+        let mut executor = Executor::new(graph);
+        assert!(executor.invoke_with_conditions(Box::new(())).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_pure_rust_graph_with_reducer() {
+        use crate::graph::ChannelSpec;
+
+        // Each node appends to "log", merged by the "extend" reducer
+        let step = |name: &'static str| {
+            NodeFunction::from_state_fn(move |state| {
+                let count = state.get_as::<i64>("count").copied().unwrap_or(0);
+                Ok(Writes::from([
+                    (
+                        "log".to_string(),
+                        Box::new(vec![name.to_string()]) as Box<dyn Any + Send + Sync>,
+                    ),
+                    ("count".to_string(), Box::new(count + 1)),
+                ]))
+            })
+        };
+
+        let mut graph = Graph::new();
+        graph
+            .add_sequence(&[
+                ("fetch", step("fetch")),
+                ("parse", step("parse")),
+                ("store", step("store")),
+            ])
+            .unwrap();
+        graph.add_channel(
+            "log",
+            ChannelSpec::new("BinaryOperatorAggregate").with_reducer("extend"),
+        );
+        graph.add_channel("count", ChannelSpec::new("LastValue"));
+
+        let mut executor = Executor::new(graph).with_reducer(
+            "extend",
+            reducer(|mut current: Vec<String>, write: Vec<String>| {
+                current.extend(write);
+                current
+            }),
+        );
+        let input = Writes::from([(
+            "log".to_string(),
+            Box::new(vec!["start".to_string()]) as Box<dyn Any + Send + Sync>,
+        )]);
+        executor.invoke(Box::new(input)).unwrap();
+
+        let state = executor.state();
+        assert_eq!(
+            state.get_as::<Vec<String>>("log").unwrap(),
+            &["start", "fetch", "parse", "store"]
+        );
+        assert_eq!(state.get_as::<i64>("count"), Some(&3));
+
+        // A node's error stops the run
+        let mut graph = Graph::new();
+        graph
+            .add_sequence(&[(
+                "fail",
+                NodeFunction::from_state_fn(|_| Err(GraphError::UnknownChannel("x".to_string()))),
+            )])
+            .unwrap();
+        let err = Executor::new(graph).invoke(Box::new(())).unwrap_err();
+        assert!(err.contains("Unknown channel"));
+
+        // A declared reducer must be registered
+        let mut graph = Graph::new();
+        graph.add_sequence(&[("only", step("only"))]).unwrap();
+        graph.add_channel(
+            "log",
+            ChannelSpec::new("BinaryOperatorAggregate").with_reducer("extend"),
+        );
+        let err = Executor::new(graph).invoke(Box::new(())).unwrap_err();
+        assert!(err.contains("reducer 'extend'"));
+    }
+
+    #[test]
+    fn test_node_panic_is_caught_and_retried() {
+        use crate::graph::{BackoffStrategy, RetryPolicy};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let flaky = NodeFunction::from_state_fn(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                panic!("flaky failure");
+            }
+            Ok(Writes::from([(
+                "done".to_string(),
+                Box::new(true) as Box<dyn Any + Send + Sync>,
+            )]))
+        });
+
+        // Without a policy the panic fails the run with its message
+        let mut graph = Graph::new();
+        graph.add_sequence(&[("flaky", flaky.clone())]).unwrap();
+        let err = Executor::new(graph).invoke(Box::new(())).unwrap_err();
+        assert!(err.contains("node 'flaky'"));
+        assert!(err.contains("panicked: flaky failure"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A policy retrying panics runs the node until it succeeds
+        let mut graph = Graph::new();
+        graph.add_sequence(&[("flaky", flaky)]).unwrap();
+        graph.nodes.get_mut("flaky").unwrap().retry_policy = Some(RetryPolicy {
+            max_attempts: 3,
+            retry_on: vec![PANIC_RETRY_KIND.to_string()],
+            backoff: BackoffStrategy::Constant { delay_ms: 0 },
+        });
+        let mut executor = Executor::new(graph);
+        executor.invoke(Box::new(())).unwrap();
+        assert_eq!(executor.state().get_as::<bool>("done"), Some(&true));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_graph_pool_shares_topology() {
+        use crate::graph::ChannelSpec;
+
+        let add = |amount: i64| {
+            NodeFunction::from_state_fn(move |_| {
+                Ok(Writes::from([(
+                    "total".to_string(),
+                    Box::new(amount) as Box<dyn Any + Send + Sync>,
+                )]))
+            })
+        };
+        let mut graph = Graph::new();
+        graph
+            .add_sequence(&[("one", add(1)), ("two", add(2)), ("three", add(3))])
+            .unwrap();
+        graph.add_channel(
+            "total",
+            ChannelSpec::new("BinaryOperatorAggregate").with_reducer("sum"),
+        );
+        let pool = GraphPool::new(graph)
+            .unwrap()
+            .with_reducer("sum", reducer(|a: i64, b: i64| a + b));
+
+        // Runs start from their own state on the same prepared graph
+        for start in [0i64, 10] {
+            let input = Writes::from([(
+                "total".to_string(),
+                Box::new(start) as Box<dyn Any + Send + Sync>,
+            )]);
+            let state = pool.invoke(Box::new(input)).unwrap();
+            assert_eq!(state.get_as::<i64>("total"), Some(&(start + 6)));
+        }
+        let (first, second) = (pool.executor(), pool.clone().executor());
+        assert!(std::ptr::eq(first.graph(), second.graph()));
+        assert!(first.state().values().is_empty());
+
+        // Graphs failing validation are rejected up front
+        let mut graph = Graph::new();
+        graph.add_node(crate::graph::Node {
+            name: "orphan".to_string(),
+            function: add(1),
+            retry_policy: None,
+        });
+        assert!(GraphPool::new(graph).is_err());
+    }
 }
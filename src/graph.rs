@@ -3,9 +3,20 @@
 //! This module defines the graph structure for LangGraph execution,
 //! including nodes, edges, and execution flow.
 
-use std::collections::{HashMap, HashSet};
+use crate::errors::{GraphError, ValidationIssue};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
+/// Virtual source node: an edge from `START` sets the graph's entry point
+pub const START: &str = "__start__";
+
+/// Virtual sink node: routing to `END` terminates that branch
+pub const END: &str = "__end__";
+
 /// A node in the graph represents a computation unit
 #[derive(Clone, Debug)]
 pub struct Node {
@@ -33,6 +44,27 @@ pub enum NodeFunction {
     Rust(NodeFn),
 }
 
+impl NodeFunction {
+    /// Wrap a pure-Rust node reading the executor's [`State`](crate::executor::State)
+    ///
+    /// The returned [`Writes`](crate::executor::Writes) are applied by the
+    /// [`Executor`](crate::executor::Executor) once the node returns.
+    pub fn from_state_fn(
+        func: impl Fn(&crate::executor::State) -> Result<crate::executor::Writes, GraphError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        NodeFunction::Rust(Arc::new(move |state| {
+            let state = state
+                .downcast_ref::<crate::executor::State>()
+                .ok_or_else(|| "Node expected the executor state".to_string())?;
+            let writes = func(state).map_err(|e| e.to_string())?;
+            Ok(Box::new(writes))
+        }))
+    }
+}
+
 impl std::fmt::Debug for NodeFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -43,15 +75,26 @@ impl std::fmt::Debug for NodeFunction {
 }
 
 /// Edge defines data flow between nodes
+///
+/// Edges marked `cyclic` are intentional loops (e.g. agent -> tools -> agent)
+/// and are exempt from cycle detection in [`Graph::compile`].
 #[derive(Clone)]
 pub enum Edge {
     /// Direct edge from source to target
-    Direct { source: String, target: String },
+    Direct {
+        source: String,
+        target: String,
+        cyclic: bool,
+    },
     /// Conditional edge that evaluates a function to determine target
     Conditional {
         source: String,
         condition: ConditionFn,
         path_map: HashMap<String, String>,
+        cyclic: bool,
+        /// Name the condition is registered under in a [`NodeRegistry`];
+        /// `None` uses the source node's name
+        name: Option<String>,
     },
     /// Entry point edge (no source)
     Entry { target: String },
@@ -60,18 +103,29 @@ pub enum Edge {
 impl std::fmt::Debug for Edge {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Edge::Direct { source, target } => f
+            Edge::Direct {
+                source,
+                target,
+                cyclic,
+            } => f
                 .debug_struct("Edge::Direct")
                 .field("source", source)
                 .field("target", target)
+                .field("cyclic", cyclic)
                 .finish(),
             Edge::Conditional {
-                source, path_map, ..
+                source,
+                path_map,
+                cyclic,
+                name,
+                ..
             } => f
                 .debug_struct("Edge::Conditional")
                 .field("source", source)
                 .field("condition", &"<function>")
                 .field("path_map", path_map)
+                .field("cyclic", cyclic)
+                .field("name", name)
                 .finish(),
             Edge::Entry { target } => f
                 .debug_struct("Edge::Entry")
@@ -82,20 +136,93 @@ impl std::fmt::Debug for Edge {
 }
 
 /// Retry policy for node execution
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RetryPolicy {
     pub max_attempts: usize,
     pub retry_on: Vec<String>, // Exception types to retry on
     pub backoff: BackoffStrategy,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum BackoffStrategy {
     Constant { delay_ms: u64 },
     Exponential { base_ms: u64, max_ms: u64 },
     Linear { increment_ms: u64 },
 }
 
+impl BackoffStrategy {
+    /// Delay before retrying after failed attempt number `attempt` (from 1)
+    pub fn delay(&self, attempt: usize) -> std::time::Duration {
+        let attempt = attempt.max(1) as u64;
+        let ms = match *self {
+            BackoffStrategy::Constant { delay_ms } => delay_ms,
+            BackoffStrategy::Exponential { base_ms, max_ms } => base_ms
+                .saturating_mul(1u64.checked_shl((attempt - 1) as u32).unwrap_or(u64::MAX))
+                .min(max_ms),
+            BackoffStrategy::Linear { increment_ms } => increment_ms.saturating_mul(attempt),
+        };
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+/// Declared state channel: its type and the reducer merging its writes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelSpec {
+    /// Channel type, e.g. `"LastValue"` or `"Topic"`
+    pub kind: String,
+    /// Name of the reducer, if writes are merged rather than replaced
+    pub reducer: Option<String>,
+}
+
+impl ChannelSpec {
+    /// Create a spec for a channel of the given type without a reducer
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            reducer: None,
+        }
+    }
+
+    /// Merge writes with the named reducer
+    pub fn with_reducer(mut self, reducer: impl Into<String>) -> Self {
+        self.reducer = Some(reducer.into());
+        self
+    }
+}
+
+/// Outgoing edges by source node, so a node's successors are found without
+/// scanning every edge
+#[derive(Debug, Default)]
+struct Adjacency {
+    /// Targets of direct edges, in insertion order
+    direct: HashMap<String, Vec<String>>,
+    /// Conditional edges, in insertion order
+    conditional: HashMap<String, Vec<Edge>>,
+}
+
+impl Adjacency {
+    fn build(edges: &[Edge]) -> Self {
+        let mut adjacency = Self::default();
+        for edge in edges {
+            match edge {
+                Edge::Direct { source, target, .. } => adjacency
+                    .direct
+                    .entry(source.clone())
+                    .or_default()
+                    .push(target.clone()),
+                Edge::Conditional { source, .. } => adjacency
+                    .conditional
+                    .entry(source.clone())
+                    .or_default()
+                    .push(edge.clone()),
+                Edge::Entry { .. } => {}
+            }
+        }
+        adjacency
+    }
+}
+
 /// Graph represents the complete execution topology
 #[derive(Debug)]
 pub struct Graph {
@@ -107,8 +234,12 @@ pub struct Graph {
     pub entry_point: Option<String>,
     /// Finish point(s) - nodes that produce final output
     pub finish_points: Vec<String>,
+    /// Declared state channels by name
+    pub channels: HashMap<String, ChannelSpec>,
     /// Computed execution order (topologically sorted)
     execution_order: Option<Vec<String>>,
+    /// Successor index over `edges`, built by [`index_edges`](Self::index_edges)
+    adjacency: Option<Adjacency>,
 }
 
 impl Graph {
@@ -119,7 +250,9 @@ impl Graph {
             edges: Vec::new(),
             entry_point: None,
             finish_points: Vec::new(),
+            channels: HashMap::new(),
             execution_order: None,
+            adjacency: None,
         }
     }
 
@@ -131,10 +264,67 @@ impl Graph {
     }
 
     /// Add an edge to the graph
+    ///
+    /// A direct edge from [`START`] is stored as an entry edge and sets the
+    /// entry point if none is set yet.
     pub fn add_edge(&mut self, edge: Edge) {
+        let edge = match edge {
+            Edge::Direct { source, target, .. } if source == START => {
+                if self.entry_point.is_none() {
+                    self.entry_point = Some(target.clone());
+                }
+                Edge::Entry { target }
+            }
+            edge => edge,
+        };
         self.edges.push(edge);
-        // Invalidate cached execution order
+        // Invalidate cached execution order and successor index
         self.execution_order = None;
+        self.adjacency = None;
+    }
+
+    /// Add nodes running one after another
+    ///
+    /// Each node gets a direct edge to the next one; the first is wired from
+    /// [`START`] (becoming the entry point if none is set) and the last to
+    /// [`END`]. Fails without changing the graph if a name is already taken
+    /// or repeated within the sequence.
+    pub fn add_sequence(&mut self, steps: &[(&str, NodeFunction)]) -> Result<(), GraphError> {
+        let mut seen = HashSet::new();
+        for (name, _) in steps {
+            if self.nodes.contains_key(*name) || !seen.insert(*name) {
+                return Err(GraphError::DuplicateNode(name.to_string()));
+            }
+        }
+
+        let names: Vec<&str> = steps.iter().map(|(name, _)| *name).collect();
+        for (name, function) in steps {
+            self.add_node(Node {
+                name: name.to_string(),
+                function: function.clone(),
+                retry_policy: None,
+            });
+        }
+        let (Some(first), Some(last)) = (names.first(), names.last()) else {
+            return Ok(());
+        };
+
+        let direct = |source: &str, target: &str| Edge::Direct {
+            source: source.to_string(),
+            target: target.to_string(),
+            cyclic: false,
+        };
+        self.add_edge(direct(START, first));
+        for pair in names.windows(2) {
+            self.add_edge(direct(pair[0], pair[1]));
+        }
+        self.add_edge(direct(last, END));
+        Ok(())
+    }
+
+    /// Declare a state channel, replacing any previous spec under `name`
+    pub fn add_channel(&mut self, name: impl Into<String>, spec: ChannelSpec) {
+        self.channels.insert(name.into(), spec);
     }
 
     /// Set the entry point for graph execution
@@ -149,6 +339,60 @@ impl Graph {
         }
     }
 
+    /// Build the successor index used by [`successors`](Self::successors)
+    /// and [`conditional_edges`](Self::conditional_edges)
+    ///
+    /// [`compile`](Self::compile) builds it; adding an edge drops it. Call
+    /// this again after changing `edges` directly.
+    pub fn index_edges(&mut self) {
+        self.adjacency = Some(Adjacency::build(&self.edges));
+    }
+
+    /// Targets of the direct edges leaving `node`, in insertion order
+    ///
+    /// O(1) once the edges are indexed; otherwise every edge is scanned.
+    pub fn successors(&self, node: &str) -> Cow<'_, [String]> {
+        match &self.adjacency {
+            Some(adjacency) => {
+                Cow::Borrowed(adjacency.direct.get(node).map_or(&[][..], Vec::as_slice))
+            }
+            None => Cow::Owned(
+                self.edges
+                    .iter()
+                    .filter_map(|edge| match edge {
+                        Edge::Direct { source, target, .. } if source == node => {
+                            Some(target.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Conditional edges leaving `node`, in insertion order
+    ///
+    /// O(1) once the edges are indexed; otherwise every edge is scanned.
+    pub fn conditional_edges(&self, node: &str) -> Cow<'_, [Edge]> {
+        match &self.adjacency {
+            Some(adjacency) => Cow::Borrowed(
+                adjacency
+                    .conditional
+                    .get(node)
+                    .map_or(&[][..], Vec::as_slice),
+            ),
+            None => Cow::Owned(
+                self.edges
+                    .iter()
+                    .filter(
+                        |edge| matches!(edge, Edge::Conditional { source, .. } if source == node),
+                    )
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
     /// Get the execution order (topologically sorted)
     /// Returns None if graph has cycles
     pub fn execution_order(&mut self) -> Option<&[String]> {
@@ -158,7 +402,16 @@ impl Graph {
         self.execution_order.as_deref()
     }
 
+    /// The execution order computed by an earlier call to
+    /// [`execution_order`](Self::execution_order) or [`compile`](Self::compile),
+    /// without computing it
+    pub fn cached_execution_order(&self) -> Option<&[String]> {
+        self.execution_order.as_deref()
+    }
+
     /// Compute topological sort of nodes for execution order
+    ///
+    /// Edges marked `cyclic` are ignored so intentional loops still get an order.
     fn compute_execution_order(&self) -> Option<Vec<String>> {
         let mut order = Vec::new();
         let mut visited = HashSet::new();
@@ -172,7 +425,9 @@ impl Graph {
 
         for edge in &self.edges {
             match edge {
-                Edge::Direct { source, target } => {
+                Edge::Direct { cyclic: true, .. } | Edge::Conditional { cyclic: true, .. } => {}
+                Edge::Direct { target, .. } if target == END => {}
+                Edge::Direct { source, target, .. } => {
                     adj_list
                         .entry(source.clone())
                         .or_default()
@@ -181,7 +436,7 @@ impl Graph {
                 Edge::Conditional {
                     source, path_map, ..
                 } => {
-                    for target in path_map.values() {
+                    for target in path_map.values().filter(|t| *t != END) {
                         adj_list
                             .entry(source.clone())
                             .or_default()
@@ -190,7 +445,7 @@ impl Graph {
                 }
                 Edge::Entry { target } => {
                     // Entry edges don't contribute to ordering
-                    if let Some(entry) = &self.entry_point {
+                    if let Some(entry) = self.entry_point.as_ref().filter(|e| *e != target) {
                         adj_list
                             .entry(entry.clone())
                             .or_default()
@@ -260,6 +515,93 @@ impl Graph {
         Some(order)
     }
 
+    /// Whether an edge may point at `name` (a real node or [`END`])
+    fn is_target(&self, name: &str) -> bool {
+        name == END || self.nodes.contains_key(name)
+    }
+
+    /// Find cycles formed by edges not marked `cyclic`
+    ///
+    /// Reports one cycle path per strongly connected component, starting and
+    /// ending at the component's first node by name.
+    fn find_cycles(&self) -> Vec<Vec<String>> {
+        let mut graph: DiGraph<&str, ()> = DiGraph::new();
+        let mut indices: HashMap<&str, NodeIndex> = HashMap::new();
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+        for name in names {
+            indices.insert(name, graph.add_node(name));
+        }
+
+        for edge in &self.edges {
+            let (source, targets): (&String, Vec<&String>) = match edge {
+                Edge::Direct {
+                    source,
+                    target,
+                    cyclic: false,
+                } => (source, vec![target]),
+                Edge::Conditional {
+                    source,
+                    path_map,
+                    cyclic: false,
+                    ..
+                } => {
+                    let mut targets: Vec<&String> = path_map.values().collect();
+                    targets.sort();
+                    (source, targets)
+                }
+                _ => continue,
+            };
+            for target in targets {
+                if let (Some(&from), Some(&to)) =
+                    (indices.get(source.as_str()), indices.get(target.as_str()))
+                {
+                    graph.add_edge(from, to, ());
+                }
+            }
+        }
+
+        let mut cycles = Vec::new();
+        for component in tarjan_scc(&graph) {
+            let is_cycle = component.len() > 1 || graph.contains_edge(component[0], component[0]);
+            if !is_cycle {
+                continue;
+            }
+
+            let members: HashSet<NodeIndex> = component.iter().copied().collect();
+            let start = *component.iter().min_by_key(|i| graph[**i]).unwrap();
+
+            // Shortest path from start back to itself within the component
+            let mut parents: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+            let mut queue = VecDeque::from([start]);
+            'search: while let Some(node) = queue.pop_front() {
+                for next in graph.neighbors(node) {
+                    if next == start {
+                        parents.insert(start, node);
+                        break 'search;
+                    }
+                    if members.contains(&next) && !parents.contains_key(&next) {
+                        parents.insert(next, node);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            let mut path = vec![graph[start].to_string()];
+            let mut node = parents[&start];
+            while node != start {
+                path.push(graph[node].to_string());
+                node = parents[&node];
+            }
+            path[1..].reverse();
+            path.push(graph[start].to_string());
+            cycles.push(path);
+        }
+
+        cycles.sort();
+        cycles
+    }
+
     /// Get all nodes that have no incoming edges (potential entry points)
     pub fn find_entry_candidates(&self) -> Vec<String> {
         let mut has_incoming = HashSet::new();
@@ -313,11 +655,11 @@ impl Graph {
         // Check that all edge targets exist
         for edge in &self.edges {
             match edge {
-                Edge::Direct { source, target } => {
+                Edge::Direct { source, target, .. } => {
                     if !self.nodes.contains_key(source) {
                         return Err(format!("Edge source '{}' not found in nodes", source));
                     }
-                    if !self.nodes.contains_key(target) {
+                    if !self.is_target(target) {
                         return Err(format!("Edge target '{}' not found in nodes", target));
                     }
                 }
@@ -328,7 +670,7 @@ impl Graph {
                         return Err(format!("Edge source '{}' not found in nodes", source));
                     }
                     for target in path_map.values() {
-                        if !self.nodes.contains_key(target) {
+                        if !self.is_target(target) {
                             return Err(format!("Edge target '{}' not found in nodes", target));
                         }
                     }
@@ -347,7 +689,9 @@ impl Graph {
             edges: self.edges.clone(),
             entry_point: self.entry_point.clone(),
             finish_points: self.finish_points.clone(),
+            channels: self.channels.clone(),
             execution_order: None,
+            adjacency: None,
         };
 
         if graph_mut.execution_order().is_none() {
@@ -356,6 +700,669 @@ impl Graph {
 
         Ok(())
     }
+
+    /// Compile the graph, rejecting structural problems
+    ///
+    /// Reports every problem at once in [`GraphError::ValidationFailed`]:
+    /// edges and `path_map` entries naming unknown nodes, nodes unreachable
+    /// from the entry point, nodes with no outgoing edge that are not
+    /// finish points, and cycles that are not closed by a `cyclic` edge.
+    pub fn compile(mut self) -> Result<Self, GraphError> {
+        let mut issues = self.structural_issues();
+        issues.extend(self.find_cycles().into_iter().map(ValidationIssue::Cycle));
+        if !issues.is_empty() {
+            return Err(GraphError::ValidationFailed(issues));
+        }
+
+        self.execution_order = self.compute_execution_order();
+        self.index_edges();
+        Ok(self)
+    }
+
+    /// Collect all structural validation issues
+    fn structural_issues(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut adj_list: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut entries: Vec<&str> = self.entry_point.iter().map(String::as_str).collect();
+
+        for edge in &self.edges {
+            match edge {
+                Edge::Direct { source, target, .. } => {
+                    if !self.nodes.contains_key(source) || !self.is_target(target) {
+                        issues.push(ValidationIssue::UnknownEdgeNode {
+                            source: source.clone(),
+                            target: target.clone(),
+                        });
+                    }
+                    adj_list.entry(source).or_default().push(target);
+                }
+                Edge::Conditional {
+                    source, path_map, ..
+                } => {
+                    if !self.nodes.contains_key(source) {
+                        issues.push(ValidationIssue::UnknownEdgeNode {
+                            source: source.clone(),
+                            target: "<conditional>".to_string(),
+                        });
+                    }
+                    let mut branches: Vec<_> = path_map.iter().collect();
+                    branches.sort();
+                    for (branch, target) in branches {
+                        if !self.is_target(target) {
+                            issues.push(ValidationIssue::UnknownPathTarget {
+                                source: source.clone(),
+                                branch: branch.clone(),
+                                target: target.clone(),
+                            });
+                        }
+                        adj_list.entry(source).or_default().push(target);
+                    }
+                }
+                Edge::Entry { target } => entries.push(target),
+            }
+        }
+
+        if entries.is_empty() {
+            issues.push(ValidationIssue::MissingEntryPoint);
+        }
+        for entry in &entries {
+            if !self.nodes.contains_key(*entry) {
+                issues.push(ValidationIssue::UnknownEntryPoint(entry.to_string()));
+            }
+        }
+
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+
+        // Reachability is meaningless without an entry point
+        if !entries.is_empty() {
+            let mut reached: HashSet<&str> = HashSet::new();
+            let mut queue: VecDeque<&str> = entries.iter().copied().collect();
+            while let Some(node) = queue.pop_front() {
+                if reached.insert(node) {
+                    if let Some(targets) = adj_list.get(node) {
+                        queue.extend(targets.iter().copied());
+                    }
+                }
+            }
+            for name in &names {
+                if !reached.contains(name.as_str()) {
+                    issues.push(ValidationIssue::UnreachableNode((*name).clone()));
+                }
+            }
+        }
+
+        for name in &names {
+            if !adj_list.contains_key(name.as_str()) && !self.finish_points.contains(name) {
+                issues.push(ValidationIssue::DanglingNode((*name).clone()));
+            }
+        }
+
+        issues
+    }
+
+    /// Render the graph in Graphviz DOT format
+    ///
+    /// Regular nodes are boxes, nodes with a conditional edge are diamonds,
+    /// and finish points or nodes routing to [`END`] get a double border.
+    /// [`START`] is drawn as a circle and [`END`] as a double circle.
+    /// Conditional edges are labelled with their branch key and `cyclic`
+    /// edges are dashed. Output is deterministic: nodes are sorted by name
+    /// and branches by key.
+    pub fn to_dot(&self) -> String {
+        let mut routers = HashSet::new();
+        let mut terminals: HashSet<&str> = self.finish_points.iter().map(String::as_str).collect();
+        let mut lines = Vec::new();
+        let mut has_end = !self.finish_points.is_empty();
+        let mut to_end = HashSet::new();
+
+        let edge_attrs = |label: Option<&str>, cyclic: bool| {
+            let mut attrs = Vec::new();
+            if let Some(label) = label {
+                attrs.push(format!("label={}", dot_id(label)));
+            }
+            if cyclic {
+                attrs.push("style=dashed".to_string());
+            }
+            if attrs.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attrs.join(", "))
+            }
+        };
+
+        if let Some(entry) = &self.entry_point {
+            lines.push(format!("{} -> {};", dot_id(START), dot_id(entry)));
+        }
+        for edge in &self.edges {
+            match edge {
+                Edge::Direct {
+                    source,
+                    target,
+                    cyclic,
+                } => {
+                    if target == END {
+                        terminals.insert(source);
+                        to_end.insert(source.as_str());
+                        has_end = true;
+                    }
+                    lines.push(format!(
+                        "{} -> {}{};",
+                        dot_id(source),
+                        dot_id(target),
+                        edge_attrs(None, *cyclic)
+                    ));
+                }
+                Edge::Conditional {
+                    source,
+                    path_map,
+                    cyclic,
+                    ..
+                } => {
+                    routers.insert(source.as_str());
+                    let mut branches: Vec<_> = path_map.iter().collect();
+                    branches.sort();
+                    for (key, target) in branches {
+                        if target == END {
+                            terminals.insert(source);
+                            has_end = true;
+                        }
+                        lines.push(format!(
+                            "{} -> {}{};",
+                            dot_id(source),
+                            dot_id(target),
+                            edge_attrs(Some(key), *cyclic)
+                        ));
+                    }
+                }
+                Edge::Entry { target } => {
+                    if self.entry_point.as_ref() != Some(target) {
+                        lines.push(format!("{} -> {};", dot_id(START), dot_id(target)));
+                    }
+                }
+            }
+        }
+        for finish in &self.finish_points {
+            if !to_end.contains(finish.as_str()) {
+                lines.push(format!("{} -> {};", dot_id(finish), dot_id(END)));
+            }
+        }
+
+        let mut out = String::from("digraph {\n");
+        out.push_str(&format!(
+            "    {} [label=\"START\", shape=circle];\n",
+            dot_id(START)
+        ));
+        if has_end {
+            out.push_str(&format!(
+                "    {} [label=\"END\", shape=doublecircle];\n",
+                dot_id(END)
+            ));
+        }
+        let mut names: Vec<&String> = self.nodes.keys().collect();
+        names.sort();
+        for name in names {
+            let shape = if routers.contains(name.as_str()) {
+                "diamond"
+            } else {
+                "box"
+            };
+            let border = if terminals.contains(name.as_str()) {
+                ", peripheries=2"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "    {} [shape={}{}];\n",
+                dot_id(name),
+                shape,
+                border
+            ));
+        }
+        for line in lines {
+            out.push_str("    ");
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl Graph {
+    /// Structural differences from `self` to `other`
+    ///
+    /// Each edge is matched to at most one edge of the other graph: an
+    /// identical edge if there is one, else the next unmatched edge in the
+    /// same slot, reported as changed. Direct edges share a slot when they
+    /// have the same source and target, entry edges the same target and
+    /// conditional edges the same source, so duplicate edges and several
+    /// routers from one node are each accounted for. Routers can't be
+    /// compared, so a conditional edge whose condition is a different
+    /// function instance is reported in [`GraphDiff::changed_routers`] even
+    /// if it behaves the same.
+    pub fn diff(&self, other: &Graph) -> GraphDiff {
+        let (added_nodes, removed_nodes) = key_changes(&self.nodes, &other.nodes);
+        let (added_channels, removed_channels) = key_changes(&self.channels, &other.channels);
+
+        let mut diff = GraphDiff {
+            added_nodes,
+            removed_nodes,
+            added_channels,
+            removed_channels,
+            ..Default::default()
+        };
+        let (pairs, added, removed) = match_edges(&self.edges, &other.edges);
+        for (old, edge) in pairs {
+            let (old_summary, new_summary) = (EdgeSummary::from(old), EdgeSummary::from(edge));
+            if old_summary != new_summary {
+                diff.changed_edges.push(EdgeChange {
+                    before: old_summary,
+                    after: new_summary,
+                });
+            }
+            if let (
+                Edge::Conditional { condition: a, .. },
+                Edge::Conditional {
+                    source,
+                    condition: b,
+                    ..
+                },
+            ) = (old, edge)
+            {
+                if !Arc::ptr_eq(a, b) && !diff.changed_routers.contains(source) {
+                    diff.changed_routers.push(source.clone());
+                }
+            }
+        }
+        diff.changed_routers.sort();
+        diff.added_edges = added.into_iter().map(EdgeSummary::from).collect();
+        diff.removed_edges = removed.into_iter().map(EdgeSummary::from).collect();
+
+        let mut names: Vec<&String> = self.channels.keys().collect();
+        names.sort();
+        for name in names {
+            if let Some(after) = other.channels.get(name) {
+                let before = &self.channels[name];
+                if before != after {
+                    diff.changed_channels.push(ChannelChange {
+                        name: name.clone(),
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+            }
+        }
+
+        if self.entry_point != other.entry_point {
+            diff.entry_point = Some((self.entry_point.clone(), other.entry_point.clone()));
+        }
+        diff
+    }
+}
+
+/// Slot of an edge when matching edges across graph versions, see
+/// [`Graph::diff`]
+fn edge_slot(edge: &Edge) -> (&'static str, &str, &str) {
+    match edge {
+        Edge::Direct { source, target, .. } => ("direct", source.as_str(), target.as_str()),
+        Edge::Conditional { source, .. } => ("conditional", source.as_str(), ""),
+        Edge::Entry { target } => ("entry", START, target.as_str()),
+    }
+}
+
+/// Match the edges of two graph versions one to one
+///
+/// Returns the matched pairs, the edges only in `after` and the edges only
+/// in `before`, each sorted by slot. Identical edges, with the same router
+/// for conditional edges, are matched first, then identical summaries,
+/// then the remaining edges by slot in graph order.
+fn match_edges<'a>(
+    before: &'a [Edge],
+    after: &'a [Edge],
+) -> (Vec<(&'a Edge, &'a Edge)>, Vec<&'a Edge>, Vec<&'a Edge>) {
+    let same_router = |a: &Edge, b: &Edge| match (a, b) {
+        (Edge::Conditional { condition: a, .. }, Edge::Conditional { condition: b, .. }) => {
+            Arc::ptr_eq(a, b)
+        }
+        _ => true,
+    };
+    // Strictest pass first
+    let matches = |pass: usize, a: &Edge, b: &Edge| match pass {
+        0 => EdgeSummary::from(a) == EdgeSummary::from(b) && same_router(a, b),
+        1 => EdgeSummary::from(a) == EdgeSummary::from(b),
+        _ => edge_slot(a) == edge_slot(b),
+    };
+
+    let mut unmatched: Vec<&Edge> = before.iter().collect();
+    let mut pending: Vec<&Edge> = after.iter().collect();
+    let mut pairs = Vec::new();
+    for pass in 0..3 {
+        pending.retain(|edge| {
+            let Some(index) = unmatched.iter().position(|old| matches(pass, old, edge)) else {
+                return true;
+            };
+            pairs.push((unmatched.remove(index), *edge));
+            false
+        });
+    }
+    pairs.sort_by_key(|(_, edge)| edge_slot(edge));
+    pending.sort_by_key(|edge| edge_slot(edge));
+    unmatched.sort_by_key(|edge| edge_slot(edge));
+    (pairs, pending, unmatched)
+}
+
+/// Version of the spec format written by [`Graph::to_spec`]
+pub const SPEC_VERSION: u64 = 1;
+
+/// Portable description of a graph, the JSON form of [`Graph::to_spec`]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GraphSpec {
+    version: u64,
+    nodes: Vec<NodeSpec>,
+    edges: Vec<EdgeSummary>,
+    #[serde(default)]
+    channels: BTreeMap<String, ChannelSpec>,
+    entry_point: Option<String>,
+    #[serde(default)]
+    finish_points: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeSpec {
+    name: String,
+    #[serde(default)]
+    retry_policy: Option<RetryPolicy>,
+}
+
+/// Node functions and conditions for rebuilding a graph with
+/// [`Graph::from_spec`]
+///
+/// Node functions are keyed by node name, conditions by the name of their
+/// conditional edge, which defaults to its source node (see
+/// [`Edge::Conditional`]).
+#[derive(Clone, Default)]
+pub struct NodeRegistry {
+    nodes: HashMap<String, NodeFunction>,
+    conditions: HashMap<String, ConditionFn>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the function of the node named `name`
+    pub fn with_node(mut self, name: impl Into<String>, function: NodeFunction) -> Self {
+        self.nodes.insert(name.into(), function);
+        self
+    }
+
+    /// Register the condition of the conditional edge named `name`
+    pub fn with_condition(mut self, name: impl Into<String>, condition: ConditionFn) -> Self {
+        self.conditions.insert(name.into(), condition);
+        self
+    }
+}
+
+impl std::fmt::Debug for NodeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut nodes: Vec<_> = self.nodes.keys().collect();
+        nodes.sort();
+        let mut conditions: Vec<_> = self.conditions.keys().collect();
+        conditions.sort();
+        f.debug_struct("NodeRegistry")
+            .field("nodes", &nodes)
+            .field("conditions", &conditions)
+            .finish()
+    }
+}
+
+impl Graph {
+    /// Describe the graph's topology as JSON
+    ///
+    /// The spec lists the nodes by name with their retry policies, every
+    /// edge with its `path_map`, `cyclic` flag and condition name (when it
+    /// isn't the source node's), the declared channels and
+    /// the entry and finish points, under a `version` field. Node functions
+    /// and conditions are left out; [`from_spec`](Self::from_spec) takes
+    /// them from a [`NodeRegistry`]. Nodes and channels are sorted by name so
+    /// equal graphs give equal specs.
+    pub fn to_spec(&self) -> serde_json::Value {
+        let mut nodes: Vec<NodeSpec> = self
+            .nodes
+            .values()
+            .map(|node| NodeSpec {
+                name: node.name.clone(),
+                retry_policy: node.retry_policy.clone(),
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        let spec = GraphSpec {
+            version: SPEC_VERSION,
+            nodes,
+            edges: self.edges.iter().map(EdgeSummary::from).collect(),
+            channels: self
+                .channels
+                .iter()
+                .map(|(name, spec)| (name.clone(), spec.clone()))
+                .collect(),
+            entry_point: self.entry_point.clone(),
+            finish_points: self.finish_points.clone(),
+        };
+        serde_json::to_value(spec).expect("graph spec serializes to JSON")
+    }
+
+    /// Rebuild a graph from a [`to_spec`](Self::to_spec) description
+    ///
+    /// Every node takes its function from `registry`, and every conditional
+    /// edge the condition registered under its name. Fails with
+    /// [`GraphError::InvalidSpec`] if the spec is malformed, was written by
+    /// another format version, names a node or condition the registry lacks
+    /// or gives two conditional edges the same name, and with [`GraphError::ValidationFailed`] if the topology does
+    /// not pass the structural checks of [`compile`](Self::compile). The
+    /// graph is returned uncompiled.
+    pub fn from_spec(
+        spec: &serde_json::Value,
+        registry: &NodeRegistry,
+    ) -> Result<Self, GraphError> {
+        let version = spec.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(SPEC_VERSION) {
+            return Err(GraphError::InvalidSpec(format!(
+                "unsupported version {}, expected {}",
+                spec.get("version").unwrap_or(&serde_json::Value::Null),
+                SPEC_VERSION
+            )));
+        }
+        let spec =
+            GraphSpec::deserialize(spec).map_err(|e| GraphError::InvalidSpec(e.to_string()))?;
+
+        let mut graph = Graph::new();
+        for node in spec.nodes {
+            if graph.nodes.contains_key(&node.name) {
+                return Err(GraphError::InvalidSpec(format!(
+                    "node '{}' is listed twice",
+                    node.name
+                )));
+            }
+            let function = registry.nodes.get(&node.name).cloned().ok_or_else(|| {
+                GraphError::InvalidSpec(format!("no function registered for node '{}'", node.name))
+            })?;
+            graph.add_node(Node {
+                name: node.name,
+                function,
+                retry_policy: node.retry_policy,
+            });
+        }
+        let mut condition_names = HashSet::new();
+        for edge in spec.edges {
+            graph.add_edge(match edge {
+                EdgeSummary::Direct {
+                    source,
+                    target,
+                    cyclic,
+                } => Edge::Direct {
+                    source,
+                    target,
+                    cyclic,
+                },
+                EdgeSummary::Conditional {
+                    source,
+                    path_map,
+                    cyclic,
+                    name,
+                } => {
+                    let key = name.as_deref().unwrap_or(&source);
+                    if !condition_names.insert(key.to_string()) {
+                        return Err(GraphError::InvalidSpec(format!(
+                            "condition '{}' names more than one conditional edge",
+                            key
+                        )));
+                    }
+                    let condition = registry.conditions.get(key).cloned().ok_or_else(|| {
+                        GraphError::InvalidSpec(format!(
+                            "no condition '{}' registered for the conditional edge from '{}'",
+                            key, source
+                        ))
+                    })?;
+                    Edge::Conditional {
+                        source,
+                        condition,
+                        path_map: path_map.into_iter().collect(),
+                        cyclic,
+                        name,
+                    }
+                }
+                EdgeSummary::Entry { target } => Edge::Entry { target },
+            });
+        }
+        graph.channels = spec.channels.into_iter().collect();
+        graph.entry_point = spec.entry_point;
+        graph.finish_points = spec.finish_points;
+
+        let issues = graph.structural_issues();
+        if issues.is_empty() {
+            Ok(graph)
+        } else {
+            Err(GraphError::ValidationFailed(issues))
+        }
+    }
+}
+
+/// Keys only in `after` and keys only in `before`, each sorted
+fn key_changes<V>(
+    before: &HashMap<String, V>,
+    after: &HashMap<String, V>,
+) -> (Vec<String>, Vec<String>) {
+    let only = |a: &HashMap<String, V>, b: &HashMap<String, V>| {
+        let mut keys: Vec<String> = a.keys().filter(|k| !b.contains_key(*k)).cloned().collect();
+        keys.sort();
+        keys
+    };
+    (only(after, before), only(before, after))
+}
+
+/// Structural differences between two graphs, from [`Graph::diff`]
+///
+/// Every list is sorted so the diff, and its JSON form, is deterministic.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<EdgeSummary>,
+    pub removed_edges: Vec<EdgeSummary>,
+    /// Edges present in both graphs whose branches or `cyclic` flag differ
+    pub changed_edges: Vec<EdgeChange>,
+    /// Sources of conditional edges whose condition is a different function
+    pub changed_routers: Vec<String>,
+    pub added_channels: Vec<String>,
+    pub removed_channels: Vec<String>,
+    /// Channels whose type or reducer differ
+    pub changed_channels: Vec<ChannelChange>,
+    /// Entry point before and after, if it changed
+    pub entry_point: Option<(Option<String>, Option<String>)>,
+}
+
+impl GraphDiff {
+    /// Whether the graphs are structurally identical
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Comparable description of an [`Edge`], without its condition function
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EdgeSummary {
+    Direct {
+        source: String,
+        target: String,
+        cyclic: bool,
+    },
+    Conditional {
+        source: String,
+        path_map: BTreeMap<String, String>,
+        cyclic: bool,
+        /// Name of the condition, when it isn't the source node's
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    Entry {
+        target: String,
+    },
+}
+
+impl From<&Edge> for EdgeSummary {
+    fn from(edge: &Edge) -> Self {
+        match edge {
+            Edge::Direct {
+                source,
+                target,
+                cyclic,
+            } => EdgeSummary::Direct {
+                source: source.clone(),
+                target: target.clone(),
+                cyclic: *cyclic,
+            },
+            Edge::Conditional {
+                source,
+                path_map,
+                cyclic,
+                name,
+                ..
+            } => EdgeSummary::Conditional {
+                source: source.clone(),
+                path_map: path_map.clone().into_iter().collect(),
+                cyclic: *cyclic,
+                name: name.clone(),
+            },
+            Edge::Entry { target } => EdgeSummary::Entry {
+                target: target.clone(),
+            },
+        }
+    }
+}
+
+/// An edge present in both graphs, before and after
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EdgeChange {
+    pub before: EdgeSummary,
+    pub after: EdgeSummary,
+}
+
+/// A channel present in both graphs with a different spec
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChannelChange {
+    pub name: String,
+    pub before: ChannelSpec,
+    pub after: ChannelSpec,
+}
+
+/// Quote a name as a DOT identifier
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
 impl Default for Graph {
@@ -406,10 +1413,12 @@ mod tests {
         graph.add_edge(Edge::Direct {
             source: "node0".to_string(),
             target: "node1".to_string(),
+            cyclic: false,
         });
         graph.add_edge(Edge::Direct {
             source: "node1".to_string(),
             target: "node2".to_string(),
+            cyclic: false,
         });
         graph.set_entry_point("node0".to_string());
 
@@ -438,17 +1447,540 @@ mod tests {
         graph.add_edge(Edge::Direct {
             source: "node0".to_string(),
             target: "node1".to_string(),
+            cyclic: false,
         });
         graph.add_edge(Edge::Direct {
             source: "node1".to_string(),
             target: "node2".to_string(),
+            cyclic: false,
         });
         graph.add_edge(Edge::Direct {
             source: "node2".to_string(),
             target: "node0".to_string(),
+            cyclic: false,
         });
 
         // Should detect cycle
         assert!(graph.execution_order().is_none());
     }
+
+    fn noop_node(name: &str) -> Node {
+        Node {
+            name: name.to_string(),
+            function: NodeFunction::Rust(Arc::new(|_| Ok(Box::new(())))),
+            retry_policy: None,
+        }
+    }
+
+    fn direct(source: &str, target: &str) -> Edge {
+        Edge::Direct {
+            source: source.to_string(),
+            target: target.to_string(),
+            cyclic: false,
+        }
+    }
+
+    #[test]
+    fn test_compile_valid_graph() {
+        let mut graph = Graph::new();
+        graph.add_node(noop_node("a"));
+        graph.add_node(noop_node("b"));
+        graph.add_edge(direct("a", "b"));
+        graph.set_entry_point("a".to_string());
+        graph.add_finish_point("b".to_string());
+
+        let mut compiled = graph.compile().unwrap();
+        assert_eq!(compiled.execution_order().unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_compile_aggregates_issues() {
+        let mut graph = Graph::new();
+        for name in ["a", "b", "orphan"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(direct("a", "b"));
+        graph.add_edge(Edge::Conditional {
+            source: "b".to_string(),
+            condition: Arc::new(|_| Ok("done".to_string())),
+            path_map: HashMap::from([("done".to_string(), "typo".to_string())]),
+            cyclic: false,
+            name: None,
+        });
+        graph.set_entry_point("a".to_string());
+
+        let Err(GraphError::ValidationFailed(issues)) = graph.compile() else {
+            panic!("expected validation failure");
+        };
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::UnknownPathTarget {
+                    source: "b".to_string(),
+                    branch: "done".to_string(),
+                    target: "typo".to_string(),
+                },
+                ValidationIssue::UnreachableNode("orphan".to_string()),
+                ValidationIssue::DanglingNode("orphan".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_missing_entry_point() {
+        let mut graph = Graph::new();
+        graph.add_node(noop_node("a"));
+        graph.add_finish_point("a".to_string());
+
+        let err = graph.compile().unwrap_err();
+        assert!(err.to_string().contains("no entry point set"));
+    }
+
+    fn agent_graph(cyclic: bool) -> Graph {
+        let mut graph = Graph::new();
+        for name in ["agent", "tools", "done"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok("finish".to_string())),
+            path_map: HashMap::from([
+                ("call".to_string(), "tools".to_string()),
+                ("finish".to_string(), "done".to_string()),
+            ]),
+            cyclic: false,
+            name: None,
+        });
+        graph.add_edge(Edge::Direct {
+            source: "tools".to_string(),
+            target: "agent".to_string(),
+            cyclic,
+        });
+        graph.set_entry_point("agent".to_string());
+        graph.add_finish_point("done".to_string());
+        graph
+    }
+
+    #[test]
+    fn test_compile_rejects_unmarked_cycle() {
+        let Err(GraphError::ValidationFailed(issues)) = agent_graph(false).compile() else {
+            panic!("expected validation failure");
+        };
+        assert_eq!(
+            issues,
+            vec![ValidationIssue::Cycle(vec![
+                "agent".to_string(),
+                "tools".to_string(),
+                "agent".to_string(),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_compile_allows_cyclic_edge() {
+        let mut compiled = agent_graph(true).compile().unwrap();
+        assert_eq!(compiled.execution_order().unwrap()[0], "agent");
+    }
+
+    #[test]
+    fn test_compile_reports_all_cycles() {
+        let mut graph = Graph::new();
+        for name in ["a", "b", "c"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(direct("a", "a"));
+        graph.add_edge(direct("a", "b"));
+        graph.add_edge(direct("b", "c"));
+        graph.add_edge(direct("c", "b"));
+        graph.set_entry_point("a".to_string());
+
+        let Err(GraphError::ValidationFailed(issues)) = graph.compile() else {
+            panic!("expected validation failure");
+        };
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::Cycle(vec!["a".to_string(), "a".to_string()]),
+                ValidationIssue::Cycle(vec!["b".to_string(), "c".to_string(), "b".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_start_and_end_sentinels() {
+        let mut graph = Graph::new();
+        graph.add_node(noop_node("agent"));
+        graph.add_node(noop_node("tools"));
+        graph.add_edge(direct(START, "agent"));
+        graph.add_edge(Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok("finish".to_string())),
+            path_map: HashMap::from([
+                ("call".to_string(), "tools".to_string()),
+                ("finish".to_string(), END.to_string()),
+            ]),
+            cyclic: false,
+            name: None,
+        });
+        graph.add_edge(Edge::Direct {
+            source: "tools".to_string(),
+            target: "agent".to_string(),
+            cyclic: true,
+        });
+
+        assert_eq!(graph.entry_point.as_deref(), Some("agent"));
+        assert!(graph.validate().is_ok());
+
+        let mut compiled = graph.compile().unwrap();
+        assert_eq!(compiled.execution_order().unwrap(), ["agent", "tools"]);
+    }
+
+    #[test]
+    fn test_add_sequence() {
+        let noop = || NodeFunction::Rust(Arc::new(|_| Ok(Box::new(()))));
+
+        let mut graph = Graph::new();
+        graph
+            .add_sequence(&[("fetch", noop()), ("parse", noop()), ("store", noop())])
+            .unwrap();
+
+        assert_eq!(graph.entry_point.as_deref(), Some("fetch"));
+        let mut compiled = graph.compile().unwrap();
+        assert_eq!(
+            compiled.execution_order().unwrap(),
+            ["fetch", "parse", "store"]
+        );
+
+        // Collisions leave the graph untouched
+        let edges = compiled.edges.len();
+        let err = compiled
+            .add_sequence(&[("new", noop()), ("parse", noop())])
+            .unwrap_err();
+        assert!(matches!(err, GraphError::DuplicateNode(name) if name == "parse"));
+        assert!(!compiled.nodes.contains_key("new"));
+        assert_eq!(compiled.edges.len(), edges);
+
+        let mut graph = Graph::new();
+        assert!(graph.add_sequence(&[("a", noop()), ("a", noop())]).is_err());
+    }
+
+    #[test]
+    fn test_successor_index() {
+        let mut graph = Graph::new();
+        for name in ["router", "a", "b", "c"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(direct(START, "router"));
+        graph.add_edge(direct("router", "a"));
+        graph.add_edge(Edge::Conditional {
+            source: "router".to_string(),
+            condition: Arc::new(|_| Ok("b".to_string())),
+            path_map: HashMap::from([("b".to_string(), "b".to_string())]),
+            cyclic: false,
+            name: None,
+        });
+        graph.add_edge(direct("router", "c"));
+        for name in ["a", "b", "c"] {
+            graph.add_edge(direct(name, END));
+        }
+
+        // Scanning and the compiled index agree
+        let scanned = graph.successors("router").into_owned();
+        assert_eq!(scanned, ["a", "c"]);
+        let mut compiled = graph.compile().unwrap();
+        assert!(matches!(compiled.successors("router"), Cow::Borrowed(_)));
+        assert_eq!(compiled.successors("router").as_ref(), scanned);
+        assert_eq!(compiled.conditional_edges("router").len(), 1);
+        assert!(compiled.successors(END).is_empty());
+
+        // A new edge drops the index rather than leaving it stale
+        compiled.add_node(noop_node("d"));
+        compiled.add_edge(direct("router", "d"));
+        assert_eq!(compiled.successors("router").as_ref(), ["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_to_dot_snapshot() {
+        let mut graph = Graph::new();
+        for name in ["agent", "tools", "summarize"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(Edge::Direct {
+            source: START.to_string(),
+            target: "agent".to_string(),
+            cyclic: false,
+        });
+        graph.add_edge(Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok("done".to_string())),
+            path_map: HashMap::from([
+                ("call".to_string(), "tools".to_string()),
+                ("done".to_string(), "summarize".to_string()),
+            ]),
+            cyclic: false,
+            name: None,
+        });
+        graph.add_edge(Edge::Direct {
+            source: "tools".to_string(),
+            target: "agent".to_string(),
+            cyclic: true,
+        });
+        graph.add_edge(Edge::Direct {
+            source: "summarize".to_string(),
+            target: END.to_string(),
+            cyclic: false,
+        });
+
+        let expected = r#"digraph {
+    "__start__" [label="START", shape=circle];
+    "__end__" [label="END", shape=doublecircle];
+    "agent" [shape=diamond];
+    "summarize" [shape=box, peripheries=2];
+    "tools" [shape=box];
+    "__start__" -> "agent";
+    "agent" -> "tools" [label="call"];
+    "agent" -> "summarize" [label="done"];
+    "tools" -> "agent" [style=dashed];
+    "summarize" -> "__end__";
+}
+"#;
+        assert_eq!(graph.to_dot(), expected);
+        assert_eq!(dot_id(r#"say "hi""#), r#""say \"hi\"""#);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut before = agent_graph(true);
+        before.add_channel("messages", ChannelSpec::new("Topic"));
+        before.add_channel("draft", ChannelSpec::new("LastValue"));
+        assert!(before.diff(&before).is_empty());
+
+        // Same router instance, rewired to a new node
+        let Edge::Conditional { condition, .. } = &before.edges[0] else {
+            panic!("expected the agent router first");
+        };
+        let mut after = Graph::new();
+        for name in ["agent", "tools", "review"] {
+            after.add_node(noop_node(name));
+        }
+        after.add_edge(Edge::Conditional {
+            source: "agent".to_string(),
+            condition: condition.clone(),
+            path_map: HashMap::from([
+                ("call".to_string(), "tools".to_string()),
+                ("finish".to_string(), "review".to_string()),
+            ]),
+            cyclic: false,
+            name: None,
+        });
+        after.add_edge(direct("tools", "agent"));
+        after.add_edge(direct("review", END));
+        after.set_entry_point("agent".to_string());
+        after.add_channel(
+            "messages",
+            ChannelSpec::new("Topic").with_reducer("add_messages"),
+        );
+        after.add_channel("notes", ChannelSpec::new("LastValue"));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added_nodes, ["review"]);
+        assert_eq!(diff.removed_nodes, ["done"]);
+        assert_eq!(
+            diff.added_edges,
+            [EdgeSummary::from(&direct("review", END))]
+        );
+        assert!(diff.removed_edges.is_empty());
+        let changed: Vec<&EdgeSummary> = diff.changed_edges.iter().map(|c| &c.after).collect();
+        assert!(matches!(
+            changed[..],
+            [
+                EdgeSummary::Conditional { .. },
+                EdgeSummary::Direct { cyclic: false, .. }
+            ]
+        ));
+        assert!(diff.changed_routers.is_empty());
+        assert_eq!(diff.added_channels, ["notes"]);
+        assert_eq!(diff.removed_channels, ["draft"]);
+        assert_eq!(diff.changed_channels[0].name, "messages");
+        assert_eq!(diff.entry_point, None);
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(
+            json["added_edges"][0],
+            serde_json::json!({
+                "type": "direct",
+                "source": "review",
+                "target": END,
+                "cyclic": false,
+            })
+        );
+        assert_eq!(
+            json["changed_channels"][0]["after"]["reducer"],
+            "add_messages"
+        );
+
+        // Routers are compared by identity
+        let diff = agent_graph(true).diff(&agent_graph(true));
+        assert_eq!(diff.changed_routers, ["agent"]);
+        assert!(diff.changed_edges.is_empty());
+    }
+
+    #[test]
+    fn test_diff_matches_each_edge_once() {
+        let router = |target: &str| Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok("go".to_string())),
+            path_map: HashMap::from([("go".to_string(), target.to_string())]),
+            cyclic: false,
+            name: None,
+        };
+        let mut before = Graph::new();
+        for name in ["agent", "tools", "review"] {
+            before.add_node(noop_node(name));
+        }
+        before.add_edge(router("tools"));
+        before.add_edge(router("review"));
+        before.add_edge(direct("tools", "agent"));
+        before.add_edge(direct("tools", "agent"));
+        assert!(before.diff(&before).is_empty());
+
+        // Dropping one of two routers from a node, and one duplicate edge
+        let mut after = Graph::new();
+        for name in ["agent", "tools", "review"] {
+            after.add_node(noop_node(name));
+        }
+        after.edges.push(before.edges[0].clone());
+        after.edges.push(before.edges[2].clone());
+
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.removed_edges,
+            [
+                EdgeSummary::from(&router("review")),
+                EdgeSummary::from(&direct("tools", "agent"))
+            ]
+        );
+        assert!(diff.added_edges.is_empty());
+        assert!(diff.changed_edges.is_empty());
+        assert!(diff.changed_routers.is_empty());
+
+        // Edges added back are reported as added
+        let diff = after.diff(&before);
+        assert_eq!(diff.added_edges.len(), 2);
+        assert!(diff.removed_edges.is_empty());
+    }
+
+    #[test]
+    fn test_spec_round_trip() {
+        let mut graph = agent_graph(true);
+        graph.add_channel(
+            "messages",
+            ChannelSpec::new("Topic").with_reducer("add_messages"),
+        );
+        graph.nodes.get_mut("tools").unwrap().retry_policy = Some(RetryPolicy {
+            max_attempts: 3,
+            retry_on: vec!["TimeoutError".to_string()],
+            backoff: BackoffStrategy::Exponential {
+                base_ms: 100,
+                max_ms: 1000,
+            },
+        });
+        let spec = graph.to_spec();
+        assert_eq!(spec["version"], SPEC_VERSION);
+        assert_eq!(spec["nodes"][2]["name"], "tools");
+        assert_eq!(
+            spec["nodes"][2]["retry_policy"]["backoff"]["type"],
+            "exponential"
+        );
+        assert_eq!(spec["edges"][0]["path_map"]["call"], "tools");
+
+        let registry = ["agent", "tools", "done"]
+            .into_iter()
+            .fold(NodeRegistry::new(), |registry, name| {
+                registry.with_node(name, noop_node(name).function)
+            })
+            .with_condition("agent", Arc::new(|_| Ok("finish".to_string())));
+        let rebuilt = Graph::from_spec(&spec, &registry).unwrap();
+        assert_eq!(rebuilt.to_spec(), spec);
+        assert!(rebuilt.compile().is_ok());
+
+        // Registry gaps, foreign versions and unknown fields are rejected
+        let no_router = NodeRegistry {
+            conditions: HashMap::new(),
+            ..registry.clone()
+        };
+        assert!(matches!(
+            Graph::from_spec(&spec, &no_router),
+            Err(GraphError::InvalidSpec(msg)) if msg.contains("'agent'")
+        ));
+        let mut future = spec.clone();
+        future["version"] = serde_json::json!(SPEC_VERSION + 1);
+        assert!(matches!(
+            Graph::from_spec(&future, &registry),
+            Err(GraphError::InvalidSpec(msg)) if msg.contains("unsupported version")
+        ));
+        let mut extra = spec.clone();
+        extra["layout"] = serde_json::json!("tb");
+        assert!(matches!(
+            Graph::from_spec(&extra, &registry),
+            Err(GraphError::InvalidSpec(_))
+        ));
+
+        // Topology is validated on load
+        let mut dangling = spec;
+        dangling["edges"][1]["target"] = serde_json::json!("missing");
+        assert!(matches!(
+            Graph::from_spec(&dangling, &registry),
+            Err(GraphError::ValidationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_spec_conditions_by_name() {
+        let router = |name: Option<&str>, target: &str| Edge::Conditional {
+            source: "agent".to_string(),
+            condition: Arc::new(|_| Ok("go".to_string())),
+            path_map: HashMap::from([("go".to_string(), target.to_string())]),
+            cyclic: false,
+            name: name.map(String::from),
+        };
+        let mut graph = Graph::new();
+        for name in ["agent", "tools", "review"] {
+            graph.add_node(noop_node(name));
+        }
+        graph.add_edge(router(None, "tools"));
+        graph.add_edge(router(Some("audit"), "review"));
+        graph.set_entry_point("agent".to_string());
+        graph.add_finish_point("tools".to_string());
+        graph.add_finish_point("review".to_string());
+        let spec = graph.to_spec();
+        assert!(spec["edges"][0].get("name").is_none());
+        assert_eq!(spec["edges"][1]["name"], "audit");
+
+        // Two routers from one node each take their own condition
+        let nodes = ["agent", "tools", "review"]
+            .into_iter()
+            .fold(NodeRegistry::new(), |registry, name| {
+                registry.with_node(name, noop_node(name).function)
+            });
+        let registry = nodes
+            .clone()
+            .with_condition("agent", Arc::new(|_| Ok("go".to_string())))
+            .with_condition("audit", Arc::new(|_| Ok("go".to_string())));
+        let rebuilt = Graph::from_spec(&spec, &registry).unwrap();
+        assert_eq!(rebuilt.to_spec(), spec);
+
+        // Unknown and duplicate names are rejected
+        let partial = nodes.with_condition("agent", Arc::new(|_| Ok("go".to_string())));
+        assert!(matches!(
+            Graph::from_spec(&spec, &partial),
+            Err(GraphError::InvalidSpec(msg)) if msg.contains("'audit'")
+        ));
+        let mut duplicate = spec;
+        duplicate["edges"][0]["name"] = serde_json::json!("audit");
+        assert!(matches!(
+            Graph::from_spec(&duplicate, &registry),
+            Err(GraphError::InvalidSpec(msg)) if msg.contains("more than one")
+        ));
+    }
 }
@@ -6,24 +6,44 @@
 // Allow non-local definitions for pyo3 macros across all modules
 #![allow(non_local_definitions)]
 
+#[cfg(feature = "python")]
 pub mod channel_manager;
 pub mod channels;
 pub mod checkpoint;
+#[cfg(feature = "postgres")]
+pub mod checkpoint_postgres;
+#[cfg(feature = "redis")]
+pub mod checkpoint_redis;
+#[cfg(feature = "python")]
 pub mod checkpoint_sqlite;
+#[cfg(feature = "python")]
+pub mod command;
+#[cfg(feature = "python")]
 pub mod conditional;
 pub mod errors;
 pub mod executor;
+#[cfg(feature = "python")]
 pub mod fast_channels;
+#[cfg(feature = "python")]
 pub mod function_cache;
 pub mod graph;
+#[cfg(feature = "python")]
 pub mod llm_cache;
 pub mod pregel;
+#[cfg(feature = "python")]
 pub mod pregel_algo;
+#[cfg(feature = "python")]
 pub mod pregel_loop;
+#[cfg(feature = "python")]
 pub mod pregel_node;
+#[cfg(feature = "python")]
 pub mod rust_checkpoint;
+#[cfg(feature = "python")]
 pub mod send;
+#[cfg(feature = "python")]
 pub mod state_merge;
+pub mod store;
+#[cfg(feature = "python")]
 pub mod stream_output;
 // pub mod state;  // Will be created in Phase 2
 
@@ -32,19 +52,22 @@ pub mod stream_output;
 pub mod hybrid;
 
 // New core module with Python-compatible async execution
-#[cfg(feature = "python")]
 pub mod core;
 
 #[cfg(feature = "python")]
 pub mod python;
 
+// JS bindings over PregelCore for wasm32 builds, which run core on JSON
+// values and so leave out the `python` feature
+#[cfg(all(feature = "wasm", not(feature = "python")))]
+pub mod wasm;
+
 // Re-export key types
-pub use channels::{Channel, LastValueChannel};
-pub use checkpoint::Checkpoint;
-pub use executor::Executor;
+pub use channels::{AnyChannel, Channel, LastValueChannel, TypedChannel};
+pub use checkpoint::{Checkpoint, RetentionPolicy};
+pub use executor::{Executor, GraphPool, State, Writes};
 pub use graph::Graph;
-pub use pregel::PregelExecutor;
+pub use pregel::{BatchConfig, ExecutionEvent, PregelExecutor, RunOutput};
 
-// Re-export core types when python feature is enabled
-#[cfg(feature = "python")]
+// Re-export core types
 pub use core::{Edge as CoreEdge, GraphState, Node as CoreNode, PregelCore};
@@ -2,12 +2,60 @@
 
 use crate::channels::Channel;
 use crate::checkpoint::Checkpoint;
-use crate::errors::LangGraphError;
+use crate::errors::{catch_node_panic, LangGraphError};
+use futures::stream::{self, StreamExt};
 use petgraph::graph::DiGraph;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Channels of a graph, keyed by name
+type ChannelMap<T, U> = HashMap<String, Arc<RwLock<dyn Channel<T, U>>>>;
+
+/// Creates a fresh, empty channel for an isolated run
+pub type ChannelFactory<T, U> = Arc<dyn Fn() -> Arc<RwLock<dyn Channel<T, U>>> + Send + Sync>;
+
+/// Callback receiving execution lifecycle events
+pub type EventCallback = Arc<dyn Fn(ExecutionEvent) + Send + Sync>;
+
+/// Lifecycle event reported to the [`PregelExecutor::on_event`] callback
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionEvent {
+    /// A superstep is about to run `tasks` tasks
+    StepStart { step: usize, tasks: usize },
+    /// A superstep applied its writes
+    StepEnd {
+        step: usize,
+        tasks: usize,
+        duration: std::time::Duration,
+    },
+    /// A node started executing
+    NodeStart {
+        step: usize,
+        node: String,
+        task_id: String,
+    },
+    /// A node finished successfully
+    NodeEnd {
+        step: usize,
+        node: String,
+        task_id: String,
+        duration: std::time::Duration,
+    },
+    /// A node failed
+    NodeError {
+        step: usize,
+        node: String,
+        task_id: String,
+        error: String,
+    },
+    /// A checkpoint of the run's state was produced
+    CheckpointWritten {
+        checkpoint_id: String,
+        thread_id: Option<String>,
+    },
+}
+
 /// Represents a node in the computation graph
 #[derive(Clone)]
 pub struct PregelNode<T, U> {
@@ -70,15 +118,46 @@ impl Default for PregelConfig {
     }
 }
 
+/// Options for [`PregelExecutor::invoke_batch`]
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Maximum number of inputs executing at once
+    pub max_concurrency: usize,
+    /// Prefix of the per-input thread IDs (`"{prefix}-{index}"`)
+    pub thread_id_prefix: String,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: num_cpus::get(),
+            thread_id_prefix: "batch".to_string(),
+        }
+    }
+}
+
+/// Final state of one isolated run
+#[derive(Debug, Clone)]
+pub struct RunOutput<T> {
+    /// Thread the run's checkpoint belongs to
+    pub thread_id: String,
+    /// Values of the channels holding a value at the end of the run
+    pub values: HashMap<String, T>,
+    /// Checkpoint of the run's final channel state
+    pub checkpoint: Checkpoint,
+}
+
 /// Core Pregel execution engine
 pub struct PregelExecutor<T: Clone + Send + Sync, U: Clone + Send + Sync> {
     #[allow(dead_code)]
     graph: DiGraph<String, ()>,
     nodes: HashMap<String, PregelNode<T, U>>,
-    channels: HashMap<String, Arc<RwLock<dyn Channel<T, U>>>>,
+    channels: ChannelMap<T, U>,
+    channel_factories: HashMap<String, ChannelFactory<T, U>>,
     checkpoint: Arc<RwLock<Checkpoint>>,
     stats: Arc<RwLock<PregelStats>>,
     config: PregelConfig,
+    on_event: Option<EventCallback>,
 }
 
 impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelExecutor<T, U> {
@@ -91,6 +170,7 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
             graph: DiGraph::new(),
             nodes: HashMap::new(),
             channels: HashMap::new(),
+            channel_factories: HashMap::new(),
             checkpoint: Arc::new(RwLock::new(Checkpoint::new())),
             stats: Arc::new(RwLock::new(PregelStats {
                 tasks_executed: 0,
@@ -99,6 +179,7 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
                 memory_usage: 0,
             })),
             config,
+            on_event: None,
         }
     }
 
@@ -118,18 +199,166 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
         Ok(())
     }
 
+    /// Register a callback for execution lifecycle events
+    ///
+    /// The callback runs synchronously on the executing thread (node events
+    /// on the task running the node), so a slow callback blocks execution; hand
+    /// work off to a channel or background task if it can take long. Events
+    /// carry owned copies of their data, so the callback cannot touch graph
+    /// state. Replaces any previously registered callback.
+    pub fn on_event(&mut self, callback: impl Fn(ExecutionEvent) + Send + Sync + 'static) {
+        self.on_event = Some(Arc::new(callback));
+    }
+
+    fn emit(&self, event: ExecutionEvent) {
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Add a channel created by `factory`
+    ///
+    /// Unlike [`add_channel`](Self::add_channel), the factory lets isolated
+    /// runs ([`invoke_isolated`](Self::invoke_isolated),
+    /// [`invoke_batch`](Self::invoke_batch)) create their own copy of the channel.
+    pub fn add_channel_factory(
+        &mut self,
+        name: String,
+        factory: ChannelFactory<T, U>,
+    ) -> Result<(), LangGraphError> {
+        self.channels.insert(name.clone(), factory());
+        self.channel_factories.insert(name, factory);
+        Ok(())
+    }
+
+    /// Run the graph on `input` against fresh channel state
+    ///
+    /// Input values are written to their channels, then each superstep runs
+    /// the nodes triggered by a channel updated in the previous step. The run
+    /// ends when a step updates no channel. Only channels registered with
+    /// [`add_channel_factory`](Self::add_channel_factory) take part.
+    pub async fn invoke_isolated(
+        &self,
+        input: HashMap<String, U>,
+        thread_id: String,
+    ) -> Result<RunOutput<T>, LangGraphError> {
+        let start_time = std::time::Instant::now();
+        let channels: ChannelMap<T, U> = self
+            .channel_factories
+            .iter()
+            .map(|(name, factory)| (name.clone(), factory()))
+            .collect();
+
+        let input_writes = PregelTaskWrites {
+            task_id: "input".to_string(),
+            writes: input.into_iter().collect(),
+        };
+        let mut updated = Self::apply_writes_to(&channels, &[input_writes]).await?;
+
+        let mut steps = 0;
+        while !updated.is_empty() {
+            if steps >= self.config.max_supersteps {
+                return Err(LangGraphError::GraphRecursionError);
+            }
+            if let Some(timeout) = self.config.timeout {
+                if start_time.elapsed() > timeout {
+                    return Err(LangGraphError::GraphRecursionError);
+                }
+            }
+
+            let tasks = self.prepare_tasks_in(&channels, Some(&updated)).await?;
+            if tasks.is_empty() {
+                break;
+            }
+            let step_start = std::time::Instant::now();
+            self.emit(ExecutionEvent::StepStart {
+                step: steps,
+                tasks: tasks.len(),
+            });
+            let task_writes = self.execute_tasks(tasks, steps).await?;
+            updated = Self::apply_writes_to(&channels, &task_writes).await?;
+            self.emit(ExecutionEvent::StepEnd {
+                step: steps,
+                tasks: task_writes.len(),
+                duration: step_start.elapsed(),
+            });
+            steps += 1;
+
+            let mut stats = self.stats.write().await;
+            stats.tasks_executed += task_writes.len();
+            stats.supersteps_completed += 1;
+        }
+
+        let mut values = HashMap::new();
+        let mut checkpoint = Checkpoint::new();
+        for (name, channel) in &channels {
+            let channel = channel.read().await;
+            if let Ok(value) = channel.get() {
+                values.insert(name.clone(), value.clone());
+            }
+            checkpoint
+                .channel_values
+                .insert(name.clone(), channel.checkpoint()?);
+        }
+        checkpoint.derive_id(None, steps as i32);
+
+        self.stats.write().await.total_execution_time += start_time.elapsed();
+        self.emit(ExecutionEvent::CheckpointWritten {
+            checkpoint_id: checkpoint.id.clone(),
+            thread_id: Some(thread_id.clone()),
+        });
+
+        Ok(RunOutput {
+            thread_id,
+            values,
+            checkpoint,
+        })
+    }
+
+    /// Run the graph on many independent inputs
+    ///
+    /// Each input runs in isolation (see [`invoke_isolated`](Self::invoke_isolated))
+    /// under its own thread ID, with at most `config.max_concurrency` runs in
+    /// flight. Results are in input order, and a failed input does not affect
+    /// the others.
+    pub async fn invoke_batch(
+        &self,
+        inputs: Vec<HashMap<String, U>>,
+        config: BatchConfig,
+    ) -> Vec<Result<RunOutput<T>, LangGraphError>> {
+        let runs = inputs.into_iter().enumerate().map(|(index, input)| {
+            let thread_id = format!("{}-{}", config.thread_id_prefix, index);
+            self.invoke_isolated(input, thread_id)
+        });
+
+        stream::iter(runs)
+            .buffered(config.max_concurrency.max(1))
+            .collect()
+            .await
+    }
+
     /// Execute the graph for a single superstep
     pub async fn execute_step(&self) -> Result<Vec<PregelTaskWrites<U>>, LangGraphError> {
         let start_time = std::time::Instant::now();
+        let step = self.stats.read().await.supersteps_completed;
 
         // Prepare tasks for this step
         let tasks = self.prepare_tasks().await?;
+        self.emit(ExecutionEvent::StepStart {
+            step,
+            tasks: tasks.len(),
+        });
 
         // Execute tasks in parallel
-        let task_writes = self.execute_tasks(tasks).await?;
+        let task_writes = self.execute_tasks(tasks, step).await?;
 
         // Apply writes to channels
         self.apply_writes(&task_writes).await?;
+        self.emit(ExecutionEvent::StepEnd {
+            step,
+            tasks: task_writes.len(),
+            duration: start_time.elapsed(),
+        });
 
         // Update statistics
         {
@@ -178,15 +407,33 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
 
     /// Prepare tasks for execution based on channel updates
     async fn prepare_tasks(&self) -> Result<Vec<PregelTask<T>>, LangGraphError> {
+        self.prepare_tasks_in(&self.channels, None).await
+    }
+
+    /// Prepare tasks reading from `channels`
+    ///
+    /// With `updated`, only nodes triggered by one of those channels are
+    /// considered.
+    async fn prepare_tasks_in(
+        &self,
+        channels: &ChannelMap<T, U>,
+        updated: Option<&HashSet<String>>,
+    ) -> Result<Vec<PregelTask<T>>, LangGraphError> {
         let mut tasks = Vec::new();
 
         // For each node, check if its triggers have been updated
         for (node_id, node) in &self.nodes {
+            if let Some(updated) = updated {
+                if !node.triggers.iter().any(|t| updated.contains(t)) {
+                    continue;
+                }
+            }
+
             let mut inputs = Vec::new();
 
             // Collect inputs from channels
             for channel_name in &node.channels {
-                if let Some(channel) = self.channels.get(channel_name) {
+                if let Some(channel) = channels.get(channel_name) {
                     let channel_guard = channel.read().await;
                     if channel_guard.is_available() {
                         // In a real implementation, we would collect the actual values
@@ -217,38 +464,77 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
     }
 
     /// Execute tasks in parallel
+    ///
+    /// A panicking node fails the step with
+    /// [`GraphError::NodeExecution`](crate::errors::GraphError::NodeExecution)
+    /// instead of taking down the executor (see [`catch_node_panic`]).
     async fn execute_tasks(
         &self,
         tasks: Vec<PregelTask<T>>,
+        step: usize,
     ) -> Result<Vec<PregelTaskWrites<U>>, LangGraphError> {
         let mut task_futures = Vec::new();
+        let mut task_nodes = Vec::new();
 
         for task in tasks {
             if let Some(node) = self.nodes.get(&task.node_id) {
                 let processor = Arc::clone(&node.processor);
                 let input = task.input;
                 let task_id = task.id.clone();
+                let node_id = task.node_id;
+                task_nodes.push(node_id.clone());
+                let on_event = self.on_event.clone();
+                let emit = move |event| {
+                    if let Some(on_event) = &on_event {
+                        on_event(event);
+                    }
+                };
 
                 task_futures.push(tokio::spawn(async move {
-                    match processor(input) {
-                        Ok(output) => Ok(PregelTaskWrites {
-                            task_id,
-                            writes: vec![("output".to_string(), output)], // Simplified
-                        }),
-                        Err(e) => Err(e),
+                    emit(ExecutionEvent::NodeStart {
+                        step,
+                        node: node_id.clone(),
+                        task_id: task_id.clone(),
+                    });
+                    let start_time = std::time::Instant::now();
+                    let result = catch_node_panic(&node_id, || processor(input))
+                        .map_err(LangGraphError::from)
+                        .and_then(|result| result);
+                    match result {
+                        Ok(output) => {
+                            emit(ExecutionEvent::NodeEnd {
+                                step,
+                                node: node_id,
+                                task_id: task_id.clone(),
+                                duration: start_time.elapsed(),
+                            });
+                            Ok(PregelTaskWrites {
+                                task_id,
+                                writes: vec![("output".to_string(), output)], // Simplified
+                            })
+                        }
+                        Err(e) => {
+                            emit(ExecutionEvent::NodeError {
+                                step,
+                                node: node_id,
+                                task_id,
+                                error: e.to_string(),
+                            });
+                            Err(e)
+                        }
                     }
                 }));
             }
         }
 
         let mut results = Vec::new();
-        for future in task_futures {
+        for (future, node_id) in task_futures.into_iter().zip(task_nodes) {
             match future.await {
                 Ok(Ok(writes)) => results.push(writes),
                 Ok(Err(e)) => return Err(e),
                 Err(e) => {
                     return Err(LangGraphError::NodeExecutionError {
-                        node_id: "unknown".to_string(),
+                        node_id,
                         source: Box::new(e),
                     })
                 }
@@ -263,6 +549,15 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
         &self,
         task_writes: &[PregelTaskWrites<U>],
     ) -> Result<(), LangGraphError> {
+        Self::apply_writes_to(&self.channels, task_writes).await?;
+        Ok(())
+    }
+
+    /// Apply task writes to `channels`, returning the channels that changed
+    async fn apply_writes_to(
+        channels: &ChannelMap<T, U>,
+        task_writes: &[PregelTaskWrites<U>],
+    ) -> Result<HashSet<String>, LangGraphError> {
         // Group writes by channel
         let mut channel_writes: HashMap<String, Vec<U>> = HashMap::new();
 
@@ -276,14 +571,17 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
         }
 
         // Apply writes to channels
+        let mut updated = HashSet::new();
         for (channel_name, values) in channel_writes {
-            if let Some(channel) = self.channels.get(&channel_name) {
+            if let Some(channel) = channels.get(&channel_name) {
                 let mut channel_guard = channel.write().await;
-                channel_guard.update(values)?;
+                if channel_guard.update(values)? {
+                    updated.insert(channel_name);
+                }
             }
         }
 
-        Ok(())
+        Ok(updated)
     }
 
     /// Get the current checkpoint
@@ -293,7 +591,12 @@ impl<T: Clone + Send + Sync + 'static, U: Clone + Send + Sync + 'static> PregelE
 
     /// Set a new checkpoint
     pub async fn set_checkpoint(&self, checkpoint: Checkpoint) {
+        let checkpoint_id = checkpoint.id.clone();
         *self.checkpoint.write().await = checkpoint;
+        self.emit(ExecutionEvent::CheckpointWritten {
+            checkpoint_id,
+            thread_id: None,
+        });
     }
 
     /// Get execution statistics
@@ -340,6 +643,168 @@ mod tests {
         assert_eq!(stats.supersteps_completed, 0);
     }
 
+    fn doubler() -> PregelExecutor<i32, i32> {
+        let mut executor: PregelExecutor<i32, i32> = PregelExecutor::new();
+        for name in ["input", "output"] {
+            executor
+                .add_channel_factory(
+                    name.to_string(),
+                    Arc::new(|| {
+                        Arc::new(RwLock::new(crate::channels::LastValueChannel::<i32>::new()))
+                    }),
+                )
+                .unwrap();
+        }
+        executor
+            .add_node(PregelNode {
+                id: "double".to_string(),
+                triggers: vec!["input".to_string()],
+                channels: vec!["input".to_string()],
+                processor: Arc::new(|x: i32| {
+                    if x < 0 {
+                        return Err(LangGraphError::InvalidUpdate("negative".to_string()));
+                    }
+                    Ok(x * 2)
+                }),
+            })
+            .unwrap();
+        executor
+    }
+
+    #[tokio::test]
+    async fn test_invoke_batch_isolates_inputs() {
+        let executor = doubler();
+        let inputs = [1, -1, 3]
+            .into_iter()
+            .map(|x| HashMap::from([("input".to_string(), x)]))
+            .collect();
+        let config = BatchConfig {
+            max_concurrency: 2,
+            ..Default::default()
+        };
+
+        let results = executor.invoke_batch(inputs, config).await;
+        assert_eq!(results.len(), 3);
+
+        let first = results[0].as_ref().unwrap();
+        assert_eq!(first.thread_id, "batch-0");
+        assert_eq!(first.values["input"], 1);
+        assert_eq!(first.values["output"], 2);
+        assert_eq!(first.checkpoint.channel_values["output"], 2);
+
+        // Identical runs get identical checkpoint IDs, distinct states distinct ones
+        let again = executor
+            .invoke_isolated(
+                HashMap::from([("input".to_string(), 1)]),
+                "again".to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(again.checkpoint.id, first.checkpoint.id);
+        assert_ne!(
+            results[2].as_ref().unwrap().checkpoint.id,
+            first.checkpoint.id
+        );
+
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().values["output"], 6);
+
+        // The executor's own channels are untouched
+        assert!(!executor.channels["output"].read().await.is_available());
+    }
+
+    #[tokio::test]
+    async fn test_on_event_lifecycle() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut executor = doubler();
+        let recorded = events.clone();
+        executor.on_event(move |event| {
+            let name = match event {
+                ExecutionEvent::StepStart { .. } => "step_start",
+                ExecutionEvent::StepEnd { .. } => "step_end",
+                ExecutionEvent::NodeStart { .. } => "node_start",
+                ExecutionEvent::NodeEnd { .. } => "node_end",
+                ExecutionEvent::NodeError { .. } => "node_error",
+                ExecutionEvent::CheckpointWritten { .. } => "checkpoint",
+            };
+            recorded.lock().unwrap().push(name);
+        });
+
+        let input = HashMap::from([("input".to_string(), 2)]);
+        executor
+            .invoke_isolated(input, "t1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "step_start",
+                "node_start",
+                "node_end",
+                "step_end",
+                "checkpoint"
+            ]
+        );
+
+        events.lock().unwrap().clear();
+        let input = HashMap::from([("input".to_string(), -2)]);
+        assert!(executor
+            .invoke_isolated(input, "t2".to_string())
+            .await
+            .is_err());
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["step_start", "node_start", "node_error"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_node_panic_becomes_error() {
+        let mut executor = doubler();
+        executor
+            .add_node(PregelNode {
+                id: "double".to_string(),
+                triggers: vec!["input".to_string()],
+                channels: vec!["input".to_string()],
+                processor: Arc::new(|x: i32| {
+                    if x == 0 {
+                        panic!("cannot double {}", x);
+                    }
+                    Ok(x * 2)
+                }),
+            })
+            .unwrap();
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = errors.clone();
+        executor.on_event(move |event| {
+            if let ExecutionEvent::NodeError { error, .. } = event {
+                recorded.lock().unwrap().push(error);
+            }
+        });
+
+        let input = HashMap::from([("input".to_string(), 0)]);
+        let err = executor
+            .invoke_isolated(input, "t1".to_string())
+            .await
+            .unwrap_err();
+        match err {
+            LangGraphError::Graph(crate::errors::GraphError::NodeExecution { node, source }) => {
+                assert_eq!(node, "double");
+                assert_eq!(source.to_string(), "panicked: cannot double 0");
+            }
+            other => panic!("expected NodeExecution, got {:?}", other),
+        }
+        assert_eq!(errors.lock().unwrap().len(), 1);
+
+        // The executor keeps serving other inputs
+        let input = HashMap::from([("input".to_string(), 4)]);
+        let output = executor
+            .invoke_isolated(input, "t2".to_string())
+            .await
+            .unwrap();
+        assert_eq!(output.values["output"], 8);
+    }
+
     #[test]
     fn test_pregel_config() {
         let config = PregelConfig::default();
@@ -3,108 +3,237 @@
 //! This module implements the key algorithms for Pregel execution:
 //! - prepare_next_tasks: Determines which nodes to execute next
 //! - apply_writes: Applies task outputs to channels
+//! - build_trigger_index: Maps channels to the nodes they trigger
 
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
+use crate::command::GotoTarget;
 use crate::pregel_node::{PregelExecutableTask, PregelNode};
-use crate::send::process_pending_sends;
+use crate::send::Send;
 
 /// Result of task execution with writes
 pub struct TaskWrites {
     pub name: String,
     pub writes: Vec<(String, PyObject)>,
     pub triggers: Vec<String>,
+    /// Time spent running the task (zero if its writes were recovered)
+    pub duration: Duration,
+    /// Successors the task chose by returning a `Command`
+    pub goto: Vec<GotoTarget>,
+    /// Whether the node streamed its updates as it yielded them, so they
+    /// were already surfaced in `updates` mode
+    pub streamed: bool,
+}
+
+/// Reverse index from channel name to the nodes triggered by that channel
+pub type TriggerIndex = HashMap<String, HashSet<String>>;
+
+/// Build the channel -> triggered nodes index for a set of nodes
+pub fn build_trigger_index(nodes: &HashMap<String, PregelNode>) -> TriggerIndex {
+    let mut index = TriggerIndex::new();
+    for (node_name, node) in nodes {
+        for trigger in &node.triggers {
+            index
+                .entry(trigger.clone())
+                .or_default()
+                .insert(node_name.clone());
+        }
+    }
+    index
+}
+
+/// Nodes triggered by any of the updated channels
+pub fn triggered_nodes(
+    index: &TriggerIndex,
+    updated_channels: &HashSet<String>,
+) -> HashSet<String> {
+    updated_channels
+        .iter()
+        .filter_map(|channel| index.get(channel))
+        .flatten()
+        .cloned()
+        .collect()
 }
 
 /// Prepare next tasks to execute based on current checkpoint state
+///
+/// When `candidates` is given, only those nodes are checked (see
+/// [`triggered_nodes`]); otherwise every node is scanned.
 #[allow(clippy::too_many_arguments)]
 pub fn prepare_next_tasks(
     py: Python,
     checkpoint_id: &str,
     channel_versions: &HashMap<String, usize>,
     versions_seen: &HashMap<String, HashMap<String, usize>>,
-    pending_sends: &[PyObject],
+    pending_sends: &[Send],
     nodes: &HashMap<String, PregelNode>,
+    candidates: Option<&HashSet<String>>,
     step: usize,
     for_execution: bool,
 ) -> PyResult<Vec<PregelExecutableTask>> {
     let mut tasks = Vec::new();
 
     // First, process pending sends (dynamic task creation)
-    let sends = process_pending_sends(py, pending_sends)?;
-    for send in sends {
+    for send in pending_sends {
         // Check if the target node exists
         if let Some(node) = nodes.get(&send.node) {
-            let task_id = format!("{}:{}:send:{}", checkpoint_id, step, send.node);
-
-            // Get the runnable
-            let proc = node.get_runnable(py)?;
-
-            // Create config
-            let config = if let Some(ref node_config) = node.config {
-                node_config.clone_ref(py)
-            } else {
-                PyDict::new(py).into()
-            };
-
-            let task = PregelExecutableTask {
-                name: send.node.clone(),
-                input: send.arg,
-                proc,
-                writes: Vec::new(),
-                config,
-                triggers: vec!["__send__".to_string()],
-                retry_policy: node.retry_policy.clone(),
-                id: task_id,
-            };
-
-            tasks.push(task);
+            let index = tasks.len();
+            tasks.push(prepare_send_task(
+                py,
+                checkpoint_id,
+                step,
+                index,
+                send.clone(),
+                node,
+            )?);
         }
     }
 
     // Find all nodes that should execute based on channel versions
-    for (node_name, node) in nodes {
-        if node.should_run(channel_versions, versions_seen) {
-            // This node should run - create a task for it
-
-            // Prepare input by reading from trigger channels
-            let input = prepare_node_input(py, node, channel_versions)?;
-
-            if for_execution {
-                // Create executable task
-                let task_id = format!("{}:{}:{}", checkpoint_id, step, node_name);
-
-                // Get the actual runnable
-                let proc = node.get_runnable(py)?;
-
-                // Create config
-                let config = if let Some(ref node_config) = node.config {
-                    node_config.clone_ref(py)
-                } else {
-                    PyDict::new(py).into()
-                };
-
-                let task = PregelExecutableTask {
-                    name: node_name.clone(),
-                    input,
-                    proc,
-                    writes: Vec::new(),
-                    config,
-                    triggers: node.triggers.clone(),
-                    retry_policy: node.retry_policy.clone(),
-                    id: task_id,
-                };
-
-                tasks.push(task);
-            }
+    let nodes_to_check: Vec<(&String, &PregelNode)> = match candidates {
+        Some(names) => names
+            .iter()
+            .filter_map(|name| nodes.get_key_value(name))
+            .collect(),
+        None => nodes.iter().collect(),
+    };
+    for (node_name, node) in nodes_to_check {
+        // This node should run - create a task for it
+        if for_execution && node.should_run(channel_versions, versions_seen) {
+            tasks.push(prepare_node_task(
+                py,
+                checkpoint_id,
+                step,
+                node_name,
+                node,
+                channel_versions,
+            )?);
         }
     }
 
     Ok(tasks)
 }
 
+/// Add the tasks for the `goto` targets of the previous step's commands
+///
+/// A goto adds to the triggered tasks rather than replacing them: a node that
+/// is both triggered and named in a goto runs once, while each `Send` target
+/// always runs as a task of its own. Targets naming unknown nodes are skipped.
+pub fn prepare_goto_tasks(
+    py: Python,
+    checkpoint_id: &str,
+    step: usize,
+    goto: &[GotoTarget],
+    nodes: &HashMap<String, PregelNode>,
+    channel_versions: &HashMap<String, usize>,
+    tasks: &mut Vec<PregelExecutableTask>,
+) -> PyResult<()> {
+    for target in goto {
+        let Some(node) = nodes.get(target.node()) else {
+            continue;
+        };
+        let task = match target {
+            GotoTarget::Node(name) => {
+                if tasks.iter().any(|task| &task.name == name) {
+                    continue;
+                }
+                prepare_node_task(py, checkpoint_id, step, name, node, channel_versions)?
+            }
+            GotoTarget::Send(send) => {
+                prepare_send_task(py, checkpoint_id, step, tasks.len(), send.clone(), node)?
+            }
+        };
+        tasks.push(task);
+    }
+    Ok(())
+}
+
+/// Create the task running `send.node` with `send.arg` as input
+///
+/// `index` is the task's position in the step, keeping the IDs of several
+/// sends to one node apart; the node stays the ID's last segment.
+fn prepare_send_task(
+    py: Python,
+    checkpoint_id: &str,
+    step: usize,
+    index: usize,
+    send: Send,
+    node: &PregelNode,
+) -> PyResult<PregelExecutableTask> {
+    let task_id = format!("{}:{}:send:{}:{}", checkpoint_id, step, index, send.node);
+
+    // Get the runnable
+    let proc = node.get_runnable(py)?;
+
+    // Create config
+    let config = if let Some(ref node_config) = node.config {
+        node_config.clone_ref(py)
+    } else {
+        PyDict::new(py).into()
+    };
+
+    Ok(PregelExecutableTask {
+        name: send.node,
+        input: send.arg,
+        proc,
+        writes: Vec::new(),
+        config,
+        triggers: vec!["__send__".to_string()],
+        retry_policy: node.retry_policy.clone(),
+        id: task_id,
+        writer: None,
+        store: None,
+        context: None,
+        timeout: node.timeout,
+        deadline: None,
+        resume: Vec::new(),
+    })
+}
+
+/// Create the task running `node` in `step`, whether or not it is triggered
+pub fn prepare_node_task(
+    py: Python,
+    checkpoint_id: &str,
+    step: usize,
+    node_name: &str,
+    node: &PregelNode,
+    channel_versions: &HashMap<String, usize>,
+) -> PyResult<PregelExecutableTask> {
+    // Prepare input by reading from trigger channels
+    let input = prepare_node_input(py, node, channel_versions)?;
+    let task_id = format!("{}:{}:{}", checkpoint_id, step, node_name);
+
+    // Get the actual runnable
+    let proc = node.get_runnable(py)?;
+
+    // Create config
+    let config = if let Some(ref node_config) = node.config {
+        node_config.clone_ref(py)
+    } else {
+        PyDict::new(py).into()
+    };
+
+    Ok(PregelExecutableTask {
+        name: node_name.to_string(),
+        input,
+        proc,
+        writes: Vec::new(),
+        config,
+        triggers: node.triggers.clone(),
+        retry_policy: node.retry_policy.clone(),
+        id: task_id,
+        writer: None,
+        store: None,
+        context: None,
+        timeout: node.timeout,
+        deadline: None,
+        resume: Vec::new(),
+    })
+}
+
 /// Prepare input for a node by reading its trigger channels
 fn prepare_node_input(
     py: Python,
@@ -117,13 +246,23 @@ fn prepare_node_input(
 }
 
 /// Apply task writes to channels and update checkpoint
+///
+/// Each channel receives its writes of the step in a single `update` call,
+/// ordered by the name of the writing node, and a node's own writes keep the
+/// order it made them in. Channels folding writes through a binary operator
+/// therefore give the same result however the tasks were scheduled, even for
+/// non-commutative operators such as list append.
+///
+/// Returns the names of the channels whose version changed.
 pub fn apply_writes(
     py: Python,
     checkpoint_versions: &mut HashMap<String, usize>,
     versions_seen: &mut HashMap<String, HashMap<String, usize>>,
     channels: &mut HashMap<String, PyObject>,
     tasks: &[TaskWrites],
-) -> PyResult<()> {
+) -> PyResult<HashSet<String>> {
+    let mut updated_channels = HashSet::new();
+
     // Update versions_seen for all tasks
     for task in tasks {
         let task_seen = versions_seen.entry(task.name.clone()).or_default();
@@ -138,9 +277,11 @@ pub fn apply_writes(
     // Find the current maximum version
     let max_version = checkpoint_versions.values().max().copied().unwrap_or(0);
 
-    // Group writes by channel
+    // Group writes by channel, in node-name order (stable for same-name tasks)
+    let mut ordered: Vec<&TaskWrites> = tasks.iter().collect();
+    ordered.sort_by(|a, b| a.name.cmp(&b.name));
     let mut writes_by_channel: HashMap<String, Vec<PyObject>> = HashMap::new();
-    for task in tasks {
+    for task in ordered {
         for (channel, value) in &task.writes {
             writes_by_channel
                 .entry(channel.clone())
@@ -164,6 +305,7 @@ pub fn apply_writes(
                     // Increment version for this channel
                     let new_version = max_version + 1;
                     checkpoint_versions.insert(channel_name.clone(), new_version);
+                    updated_channels.insert(channel_name.clone());
                 }
             }
         }
@@ -181,12 +323,13 @@ pub fn apply_writes(
                 if was_updated {
                     let new_version = max_version + 1;
                     checkpoint_versions.insert(channel_name.clone(), new_version);
+                    updated_channels.insert(channel_name.clone());
                 }
             }
         }
     }
 
-    Ok(())
+    Ok(updated_channels)
 }
 
 /// Check if execution should interrupt at this point
@@ -232,4 +375,32 @@ mod tests {
         assert_eq!(increment_version(Some(0)), 1);
         assert_eq!(increment_version(Some(5)), 6);
     }
+
+    #[test]
+    fn test_trigger_index() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let node = |name: &str, triggers: &[&str]| {
+                PregelNode::new(
+                    py.None(),
+                    name.to_string(),
+                    triggers.iter().map(|t| t.to_string()).collect(),
+                    Vec::new(),
+                )
+            };
+            let mut nodes = HashMap::new();
+            nodes.insert("a".to_string(), node("a", &["input"]));
+            nodes.insert("b".to_string(), node("b", &["input", "a_out"]));
+            nodes.insert("c".to_string(), node("c", &["b_out"]));
+
+            let index = build_trigger_index(&nodes);
+            assert_eq!(index["input"].len(), 2);
+
+            let updated: HashSet<String> = ["a_out".to_string()].into();
+            assert_eq!(triggered_nodes(&index, &updated), ["b".to_string()].into());
+            let untracked: HashSet<String> = ["other".to_string()].into();
+            assert!(triggered_nodes(&index, &untracked).is_empty());
+        });
+    }
 }
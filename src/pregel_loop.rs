@@ -4,11 +4,77 @@
 //! the superstep iteration model.
 
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
-use std::collections::HashMap;
+use pyo3::types::{PyBytes, PyDict, PyFrozenSet, PyList, PySet, PyString, PyTuple};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-use crate::pregel_algo::{apply_writes, prepare_next_tasks, should_interrupt, TaskWrites};
-use crate::pregel_node::{PregelExecutableTask, PregelNode};
+use crate::channel_manager::{ChannelAliases, ChannelDefaults};
+use crate::checkpoint::CheckpointId;
+use crate::command::{Command, GotoTarget, NodeCommand};
+use crate::conditional::{ConditionalEdge, ConditionalRouter};
+use crate::core::metrics::{Metrics, NodeSample, RunStats};
+use crate::errors::GraphError;
+use crate::graph::END;
+use crate::pregel_algo::{
+    apply_writes, build_trigger_index, prepare_goto_tasks, prepare_next_tasks, prepare_node_task,
+    should_interrupt, triggered_nodes, TaskWrites, TriggerIndex,
+};
+use crate::pregel_node::{is_interrupt, PregelExecutableTask, PregelNode, StreamedUpdates};
+use crate::python::derive_checkpoint_id;
+use crate::stream_output::{DebugInfo, StreamBuffer, StreamChunk, StreamMode, StreamWriter};
+
+/// Writer name recorded for channel writes made by [`Command::Update`]
+pub const COMMAND_WRITER: &str = "__command__";
+
+/// Marker channel recorded for a task that completed without writing anything,
+/// so it is still recognized as finished when resuming
+pub const NO_WRITES: &str = "__no_writes__";
+
+/// Marker channel holding the `goto` targets of a task's returned `Command`
+/// among its pending writes
+pub const GOTO_WRITES: &str = "__goto__";
+
+/// When checkpoints are persisted relative to step execution
+///
+/// Each mode decides when `checkpointer.put` is awaited, trading crash
+/// safety for speed. Pending task writes are saved through `put_writes` as
+/// tasks finish regardless of the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Persist the checkpoint before the next step starts. A crash loses at
+    /// most the step in flight.
+    Sync,
+    /// Persist in the background while the next step runs. A crash can also
+    /// lose the last completed step if its checkpoint was still being written.
+    #[default]
+    Async,
+    /// Persist only when the run completes or is interrupted. A crash loses
+    /// all progress of the run.
+    Exit,
+}
+
+impl std::str::FromStr for Durability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sync" => Ok(Durability::Sync),
+            "async" => Ok(Durability::Async),
+            "exit" => Ok(Durability::Exit),
+            _ => Err(format!("Unknown durability mode: {}", s)),
+        }
+    }
+}
+
+/// Condition on the committed state that pauses a run, see
+/// [`PregelLoop::interrupt_when`]
+///
+/// Called with a dict of the current channel values.
+pub type StatePredicate = Arc<dyn Fn(&PyDict) -> bool + Send + Sync>;
 
 /// Configuration for Pregel execution
 #[derive(Clone, Debug)]
@@ -19,6 +85,32 @@ pub struct PregelConfig {
     pub interrupt_before: Vec<String>,
     /// Nodes to interrupt after execution
     pub interrupt_after: Vec<String>,
+    /// When checkpoints are persisted
+    pub durability: Durability,
+    /// Stream each node's chunks as soon as it finishes instead of at the
+    /// step barrier; writes are still applied and checkpointed at the barrier
+    pub stream_eager: bool,
+    /// Only stream node events (updates, custom, messages, debug) from nodes
+    /// carrying one of these tags; empty streams every node
+    pub stream_tags: Vec<String>,
+    /// Time limit for a whole superstep; also caps every node's own timeout
+    pub step_timeout: Option<Duration>,
+    /// ID recorded in the run's checkpoint metadata and debug events;
+    /// generated when `None`
+    pub run_id: Option<uuid::Uuid>,
+    /// Stream subgraphs run by nodes in this mode, forwarding their chunks
+    /// into this run's stream under the task's namespace; `None` streams
+    /// only this graph
+    pub stream_subgraphs: Option<StreamMode>,
+    /// Resume from a checkpoint whose schema fingerprint differs from this
+    /// graph's instead of failing with [`GraphError::CheckpointIncompatible`]
+    pub force_resume: bool,
+    /// Approximate bytes a single channel's value may hold after a barrier
+    /// before the run fails with [`GraphError::StateTooLarge`]
+    pub max_channel_bytes: Option<usize>,
+    /// Approximate bytes all channel values together may hold after a
+    /// barrier before the run fails with [`GraphError::StateTooLarge`]
+    pub max_state_bytes: Option<usize>,
 }
 
 impl Default for PregelConfig {
@@ -27,6 +119,15 @@ impl Default for PregelConfig {
             recursion_limit: 25,
             interrupt_before: Vec::new(),
             interrupt_after: Vec::new(),
+            durability: Durability::default(),
+            stream_eager: false,
+            stream_tags: Vec::new(),
+            step_timeout: None,
+            run_id: None,
+            stream_subgraphs: None,
+            force_resume: false,
+            max_channel_bytes: None,
+            max_state_bytes: None,
         }
     }
 }
@@ -41,9 +142,21 @@ pub struct CheckpointState {
     /// Versions seen by each node (node_name -> {channel_name -> version})
     pub versions_seen: HashMap<String, HashMap<String, usize>>,
     /// Pending writes to be applied
-    pub pending_writes: Vec<(String, PyObject, String)>, // (channel, value, node)
-    /// Pending Send objects for dynamic dispatch
-    pub pending_sends: Vec<PyObject>,
+    pub pending_writes: Vec<(String, PyObject, String)>, // (channel, value, task_id)
+    /// Sends returned in the last committed step, dispatched as tasks of
+    /// the next one
+    pub pending_sends: Vec<crate::send::Send>,
+    /// Nodes chosen by the `Command`s returned in the last committed step
+    pub pending_goto: Vec<GotoTarget>,
+    /// The `interrupt()` calls pausing the current step
+    pub interrupts: Vec<PendingInterrupt>,
+    /// Answers given on resume to each task's `interrupt()` calls, keyed by
+    /// task ID and in call order; cleared when the step commits
+    pub resume_answers: HashMap<String, Vec<PyObject>>,
+    /// Fingerprint of the graph that saved the checkpoint, see
+    /// [`PregelLoop::schema_fingerprint`]; `None` for checkpoints saved
+    /// before fingerprints were recorded
+    pub fingerprint: Option<String>,
 }
 
 impl CheckpointState {
@@ -54,11 +167,15 @@ impl CheckpointState {
             versions_seen: HashMap::new(),
             pending_writes: Vec::new(),
             pending_sends: Vec::new(),
+            pending_goto: Vec::new(),
+            interrupts: Vec::new(),
+            resume_answers: HashMap::new(),
+            fingerprint: None,
         }
     }
 
     /// Create from Python checkpoint dict
-    pub fn from_py_checkpoint(_py: Python, checkpoint: &PyDict) -> PyResult<Self> {
+    pub fn from_py_checkpoint(py: Python, checkpoint: &PyDict) -> PyResult<Self> {
         let id = checkpoint
             .get_item("id")?
             .and_then(|v| v.extract::<String>().ok())
@@ -74,35 +191,223 @@ impl CheckpointState {
             .and_then(|v| v.extract::<HashMap<String, HashMap<String, usize>>>().ok())
             .unwrap_or_default();
 
-        let pending_sends = checkpoint
-            .get_item("pending_sends")?
-            .and_then(|v| v.extract::<Vec<PyObject>>().ok())
+        // Sends are saved as `(node, arg)` tuples; `Send` objects also load
+        let pending_sends = match checkpoint.get_item("pending_sends")? {
+            Some(sends) => sends
+                .iter()?
+                .map(|send| {
+                    let send = send?;
+                    match send.extract::<(String, PyObject)>() {
+                        Ok((node, arg)) => Ok(crate::send::Send::new(node, arg)),
+                        Err(_) => crate::send::Send::from_py_send(py, send),
+                    }
+                })
+                .collect::<PyResult<_>>()?,
+            None => Vec::new(),
+        };
+
+        let pending_goto = match checkpoint.get_item("pending_goto")? {
+            Some(targets) => GotoTarget::from_objects(targets)?,
+            None => Vec::new(),
+        };
+
+        let interrupts = checkpoint
+            .get_item("interrupts")?
+            .and_then(|v| v.extract::<Vec<(String, String, PyObject, String)>>().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, node, value, task)| PendingInterrupt {
+                id,
+                node,
+                task,
+                value,
+            })
+            .collect();
+
+        let resume_answers = checkpoint
+            .get_item("resume_answers")?
+            .and_then(|v| v.extract::<HashMap<String, Vec<PyObject>>>().ok())
             .unwrap_or_default();
 
+        let fingerprint = checkpoint
+            .get_item("fingerprint")?
+            .and_then(|v| v.extract::<String>().ok());
+
         Ok(Self {
             id,
             channel_versions,
             versions_seen,
             pending_writes: Vec::new(),
             pending_sends,
+            pending_goto,
+            interrupts,
+            resume_answers,
+            fingerprint,
         })
     }
 
+    /// Load pending writes saved by a checkpointer
+    ///
+    /// `pending_writes` is a sequence of `(task_id, channel, value)` tuples as
+    /// returned in a checkpoint tuple. Writes stay keyed by task ID, so
+    /// several Send tasks to one node are recovered separately.
+    pub fn load_pending_writes(&mut self, py: Python, pending_writes: &PyAny) -> PyResult<()> {
+        for item in pending_writes.iter()? {
+            let (task_id, channel, value): (String, String, PyObject) = item?.extract()?;
+            self.pending_writes
+                .push((channel, value.clone_ref(py), task_id));
+        }
+        Ok(())
+    }
+
+    /// Give the checkpoint the ID `id`, moving the task IDs recorded against
+    /// the old one
+    ///
+    /// Task IDs start with the ID of the checkpoint their step was planned
+    /// from, so when a paused step is saved under a new ID its interrupts,
+    /// resume answers and pending writes must follow.
+    pub fn set_id(&mut self, id: String) {
+        let prefix = format!("{}:", std::mem::replace(&mut self.id, id));
+        let rekey = |task: &str| match task.strip_prefix(&prefix) {
+            Some(rest) => format!("{}:{}", self.id, rest),
+            None => task.to_string(),
+        };
+        for interrupt in &mut self.interrupts {
+            interrupt.task = rekey(&interrupt.task);
+        }
+        for (_, _, task_id) in &mut self.pending_writes {
+            *task_id = rekey(task_id);
+        }
+        self.resume_answers = std::mem::take(&mut self.resume_answers)
+            .into_iter()
+            .map(|(task, answers)| (rekey(&task), answers))
+            .collect();
+    }
+
     /// Convert to Python checkpoint dict
     pub fn to_py_checkpoint(&self, py: Python) -> PyResult<PyObject> {
         let checkpoint = PyDict::new(py);
         checkpoint.set_item("id", &self.id)?;
         checkpoint.set_item("channel_versions", self.channel_versions.clone())?;
         checkpoint.set_item("versions_seen", self.versions_seen.clone())?;
-        checkpoint.set_item("pending_sends", &self.pending_sends)?;
+        let pending_sends: Vec<(&str, &PyObject)> = self
+            .pending_sends
+            .iter()
+            .map(|send| (send.node.as_str(), &send.arg))
+            .collect();
+        checkpoint.set_item("pending_sends", pending_sends)?;
+        let pending_goto: Vec<PyObject> =
+            self.pending_goto.iter().map(|t| t.to_object(py)).collect();
+        checkpoint.set_item("pending_goto", pending_goto)?;
+        let interrupts: Vec<(&str, &str, &PyObject, &str)> = self
+            .interrupts
+            .iter()
+            .map(|i| (i.id.as_str(), i.node.as_str(), &i.value, i.task.as_str()))
+            .collect();
+        checkpoint.set_item("interrupts", interrupts)?;
+        checkpoint.set_item("resume_answers", &self.resume_answers)?;
+        checkpoint.set_item("fingerprint", &self.fingerprint)?;
         Ok(checkpoint.into())
     }
 }
 
+/// An `interrupt()` call pausing the current step
+#[derive(Clone, Debug)]
+pub struct PendingInterrupt {
+    /// `"{node}:{n}"` for the task's `n`-th `interrupt()` call in the step,
+    /// counting from 0; Send tasks are `"send:{index}:{node}:{n}"`
+    pub id: String,
+    /// Node that called `interrupt()`
+    pub node: String,
+    /// ID of the task that called `interrupt()`, which its answers are
+    /// keyed by
+    pub task: String,
+    /// Value passed to `interrupt()`
+    pub value: PyObject,
+}
+
+/// ID of a task's next `interrupt()` call, see [`PendingInterrupt::id`]
+fn interrupt_id(task: &PregelExecutableTask) -> String {
+    match task.id.find(":send:") {
+        Some(at) => format!("{}:{}", &task.id[at + 1..], task.resume.len()),
+        None => format!("{}:{}", task.name, task.resume.len()),
+    }
+}
+
+/// The `goto` targets of a step's tasks, scheduled for the next step
+fn collect_goto(tasks: &[TaskWrites]) -> (Vec<GotoTarget>, Vec<crate::send::Send>) {
+    let mut nodes = Vec::new();
+    let mut sends = Vec::new();
+    for target in tasks.iter().flat_map(|task| task.goto.iter().cloned()) {
+        match target {
+            GotoTarget::Send(send) => sends.push(send),
+            node => nodes.push(node),
+        }
+    }
+    (nodes, sends)
+}
+
+/// Approximate bytes held by `value`: CPython's footprint of strings, bytes,
+/// containers and their contents, and of objects with their `__dict__`
+///
+/// Cheap rather than exact: other objects count as a fixed size, shared
+/// objects count once per reference, and counting stops as soon as the total
+/// exceeds `cap`, which also ends reference cycles.
+fn approx_size(value: &PyAny, cap: usize) -> usize {
+    let mut size = 0;
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        if size > cap {
+            break;
+        }
+        size += if let Ok(string) = value.downcast::<PyString>() {
+            48 + string.len().unwrap_or(0)
+        } else if let Ok(bytes) = value.downcast::<PyBytes>() {
+            32 + bytes.as_bytes().len()
+        } else if let Ok(list) = value.downcast::<PyList>() {
+            stack.extend(list.iter());
+            56 + 8 * list.len()
+        } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+            stack.extend(tuple.iter());
+            40 + 8 * tuple.len()
+        } else if let Ok(dict) = value.downcast::<PyDict>() {
+            stack.extend(dict.iter().flat_map(|(key, value)| [key, value]));
+            64 + 24 * dict.len()
+        } else if let Ok(set) = value.downcast::<PySet>() {
+            stack.extend(set.iter());
+            64 + 16 * set.len()
+        } else if let Ok(set) = value.downcast::<PyFrozenSet>() {
+            stack.extend(set.iter());
+            64 + 16 * set.len()
+        } else {
+            if let Ok(attrs) = value.getattr("__dict__") {
+                stack.push(attrs);
+            }
+            32
+        };
+    }
+    size
+}
+
+/// Superstep whose tasks have not all run yet
+struct PendingStep {
+    /// Tasks still to run, in order
+    tasks: VecDeque<PregelExecutableTask>,
+    /// Writes of tasks that finished before the previous run was interrupted
+    recovered: HashMap<String, Vec<(String, PyObject)>>,
+    /// Writes of the tasks run so far
+    writes: Vec<TaskWrites>,
+}
+
 /// Main Pregel execution loop
 pub struct PregelLoop {
     /// Graph nodes
     nodes: HashMap<String, PregelNode>,
+    /// Channel name -> nodes triggered by it, built once per loop
+    trigger_to_nodes: TriggerIndex,
+    /// Nodes triggered by the channels updated at the last barrier
+    /// (`None` until the first barrier, meaning all nodes are checked)
+    candidates: Option<HashSet<String>>,
     /// Channels for state management
     channels: HashMap<String, PyObject>,
     /// Current checkpoint
@@ -111,6 +416,51 @@ pub struct PregelLoop {
     config: PregelConfig,
     /// Current step number
     step: usize,
+    /// Python checkpointer receiving per-task writes via `put_writes`
+    checkpointer: Option<PyObject>,
+    /// Config passed to the checkpointer
+    checkpoint_config: Option<PyObject>,
+    /// Buffer receiving chunks from node stream writers, drained at each barrier
+    stream_buffer: Arc<Mutex<StreamBuffer>>,
+    /// Checkpoint `put` running in the background ([`Durability::Async`])
+    pending_put: Option<JoinHandle<PyResult<()>>>,
+    /// Stops streaming at the next step boundary once cancelled
+    cancel: CancellationToken,
+    /// Step being streamed eagerly, one task per [`stream_step`](Self::stream_step)
+    pending_step: Option<PendingStep>,
+    /// Long-term store handed to every task
+    store: Option<PyObject>,
+    /// Context of the run handed to every task
+    context: Option<PyObject>,
+    /// Node to run as the whole next step, from [`Command::Goto`]
+    goto: Option<String>,
+    /// Whether the channels of the current run were finished
+    channels_finished: bool,
+    /// ID of the run, from [`PregelConfig::run_id`] or generated
+    run_id: uuid::Uuid,
+    /// Channels exposing `names` and `seen`, found on the first barrier check
+    barrier_channels: Option<Vec<String>>,
+    /// Channels mirroring the writes of others at each barrier
+    aliases: ChannelAliases,
+    /// Starting values written when a run begins
+    defaults: ChannelDefaults,
+    /// Conditional edges routing from nodes once their step commits
+    router: ConditionalRouter,
+    /// Statistics of the current run, reset when input is written
+    metrics: Metrics,
+    /// When the current run started
+    started: Instant,
+    /// Pauses the run once the committed state satisfies it
+    interrupt_when: Option<StatePredicate>,
+    /// Approximate size of each channel's value at the last barrier, measured
+    /// only when a memory budget is configured; `None` until measured and
+    /// after input is written
+    channel_sizes: Option<HashMap<String, usize>>,
+    /// Nodes the run halts before, set by [`invoke_until`](Self::invoke_until)
+    stop_nodes: HashSet<String>,
+    /// Step the run last halted before for a stop node, which
+    /// [`continue_run`](Self::continue_run) runs without halting again
+    stopped_at: Option<usize>,
 }
 
 impl PregelLoop {
@@ -120,16 +470,120 @@ impl PregelLoop {
         channels: HashMap<String, PyObject>,
         config: PregelConfig,
     ) -> Self {
-        let checkpoint_id = uuid::Uuid::new_v4().to_string();
+        let checkpoint_id = CheckpointId::derive(None, 0, &HashMap::new()).to_string();
         Self {
+            trigger_to_nodes: build_trigger_index(&nodes),
+            candidates: None,
             nodes,
             channels,
             checkpoint: CheckpointState::new(checkpoint_id),
+            run_id: config.run_id.unwrap_or_else(uuid::Uuid::new_v4),
             config,
             step: 0,
+            checkpointer: None,
+            checkpoint_config: None,
+            stream_buffer: Arc::new(Mutex::new(StreamBuffer::new(StreamMode::Custom))),
+            pending_put: None,
+            cancel: CancellationToken::new(),
+            pending_step: None,
+            store: None,
+            context: None,
+            goto: None,
+            channels_finished: false,
+            barrier_channels: None,
+            aliases: ChannelAliases::new(),
+            defaults: ChannelDefaults::new(),
+            metrics: Metrics::new(),
+            started: Instant::now(),
+            interrupt_when: None,
+            channel_sizes: None,
+            stop_nodes: HashSet::new(),
+            stopped_at: None,
+            router: ConditionalRouter::new(),
         }
     }
 
+    /// Persist each task's writes through `checkpointer.put_writes(config, writes, task_id)`
+    /// as soon as the task completes, and checkpoints through
+    /// `checkpointer.put(config, checkpoint, metadata, new_versions)` as
+    /// configured by [`PregelConfig::durability`]
+    ///
+    /// If the process dies mid-superstep, the saved writes let a resumed loop
+    /// skip the tasks that already finished (see [`CheckpointState::load_pending_writes`]).
+    pub fn with_checkpointer(mut self, checkpointer: PyObject, config: PyObject) -> Self {
+        self.checkpointer = Some(checkpointer);
+        self.checkpoint_config = Some(config);
+        self
+    }
+
+    /// Give every node access to a long-term `store`
+    ///
+    /// Nodes declaring a `store` parameter receive it as a keyword argument;
+    /// Runnables find it in `config["configurable"]`.
+    pub fn with_store(mut self, store: PyObject) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Give every node the `context` the run was invoked with
+    ///
+    /// Nodes declaring a `context` parameter receive it as a keyword
+    /// argument; Runnables find it in `config["configurable"]`.
+    pub fn with_context(mut self, context: PyObject) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Mirror writes into aliased channels at every barrier
+    ///
+    /// An aliased channel receives the writes of its source in the same
+    /// `update` call as its own, so both advance their versions and trigger
+    /// their nodes together. Aliases naming channels the loop does not have
+    /// are ignored, like any write to an unknown channel.
+    pub fn with_channel_aliases(mut self, aliases: ChannelAliases) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Start channels from the values their factories build
+    ///
+    /// [`initialize_input`](Self::initialize_input) writes each factory's
+    /// value before the input, without bumping the channel's version, so
+    /// the default triggers no node. Channels the loaded checkpoint has a
+    /// version for keep their restored value. Factories naming channels the
+    /// loop does not have are ignored.
+    pub fn with_channel_defaults(mut self, defaults: ChannelDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Route from `edge.source` with a conditional edge
+    ///
+    /// The edge's condition is called with the committed state after every
+    /// step its source node ran in. The nodes it routes to run in the next
+    /// step alongside the triggered ones, and every `Send` it returns runs
+    /// its node on the send's argument as a task of its own; both are queued
+    /// like the `goto` of a returned `Command`, so they survive an
+    /// interrupt. Routing to [`END`] schedules nothing.
+    pub fn with_conditional_edge(mut self, edge: ConditionalEdge) -> Self {
+        self.router.add_edge(edge);
+        self
+    }
+
+    /// Pause the run at the first barrier after which `predicate` holds
+    ///
+    /// Checked alongside [`PregelConfig::interrupt_after`], once a step's
+    /// writes are committed and checkpointed, so the predicate sees the
+    /// latest state. The run returns the current state, as for a node-list
+    /// interrupt.
+    pub fn interrupt_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&PyDict) -> bool + Send + Sync + 'static,
+    {
+        self.interrupt_when = Some(Arc::new(predicate));
+        self
+    }
+
     /// Create from existing checkpoint (for resuming)
     pub fn from_checkpoint(
         _py: Python,
@@ -139,55 +593,292 @@ impl PregelLoop {
         config: PregelConfig,
     ) -> Self {
         Self {
+            trigger_to_nodes: build_trigger_index(&nodes),
+            candidates: None,
             nodes,
             channels,
             checkpoint,
+            run_id: config.run_id.unwrap_or_else(uuid::Uuid::new_v4),
             config,
             step: 0,
+            checkpointer: None,
+            checkpoint_config: None,
+            stream_buffer: Arc::new(Mutex::new(StreamBuffer::new(StreamMode::Custom))),
+            pending_put: None,
+            cancel: CancellationToken::new(),
+            pending_step: None,
+            store: None,
+            context: None,
+            goto: None,
+            channels_finished: false,
+            barrier_channels: None,
+            aliases: ChannelAliases::new(),
+            defaults: ChannelDefaults::new(),
+            metrics: Metrics::new(),
+            started: Instant::now(),
+            interrupt_when: None,
+            channel_sizes: None,
+            stop_nodes: HashSet::new(),
+            stopped_at: None,
+            router: ConditionalRouter::new(),
         }
     }
 
     /// Execute one superstep
     fn execute_step(&mut self, py: Python) -> PyResult<Vec<TaskWrites>> {
-        // Prepare tasks for this step
-        let mut tasks = prepare_next_tasks(
-            py,
-            &self.checkpoint.id,
-            &self.checkpoint.channel_versions,
-            &self.checkpoint.versions_seen,
-            &self.checkpoint.pending_sends,
-            &self.nodes,
-            self.step,
-            true,
-        )?;
+        let mut pending = self.prepare_step(py)?;
+        while self.run_next_task(py, &mut pending)?.is_some() {}
+        Ok(pending.writes)
+    }
 
-        if tasks.is_empty() {
-            // No tasks to execute - we've reached convergence
-            return Ok(Vec::new());
+    /// Tasks scheduled for the current step: the node of a
+    /// [`Command::Goto`], or the triggered nodes together with those chosen
+    /// by returned Commands
+    fn scheduled_tasks(&self, py: Python) -> PyResult<Vec<PregelExecutableTask>> {
+        match &self.goto {
+            Some(node) => Ok(vec![prepare_node_task(
+                py,
+                &self.checkpoint.id,
+                self.step,
+                node,
+                &self.nodes[node],
+                &self.checkpoint.channel_versions,
+            )?]),
+            None => {
+                let mut tasks = prepare_next_tasks(
+                    py,
+                    &self.checkpoint.id,
+                    &self.checkpoint.channel_versions,
+                    &self.checkpoint.versions_seen,
+                    &self.checkpoint.pending_sends,
+                    &self.nodes,
+                    self.candidates.as_ref(),
+                    self.step,
+                    true,
+                )?;
+                prepare_goto_tasks(
+                    py,
+                    &self.checkpoint.id,
+                    self.step,
+                    &self.checkpoint.pending_goto,
+                    &self.nodes,
+                    &self.checkpoint.channel_versions,
+                    &mut tasks,
+                )?;
+                Ok(tasks)
+            }
         }
+    }
+
+    /// Prepare the tasks of the next superstep
+    ///
+    /// No tasks means the run has converged.
+    fn prepare_step(&mut self, py: Python) -> PyResult<PendingStep> {
+        let mut tasks = self.scheduled_tasks(py)?;
+        self.goto = None;
 
-        // Execute all tasks
-        let mut task_writes = Vec::new();
+        // Higher-priority nodes are dispatched first; ties go by node name
+        // so the order is deterministic
+        tasks.sort_by_cached_key(|task| {
+            let priority = self.nodes.get(&task.name).map_or(0, |node| node.priority);
+            (Reverse(priority), task.name.clone())
+        });
+
+        // Give each task a writer for streaming output mid-execution
+        let deadline = self
+            .config
+            .step_timeout
+            .map(|timeout| Instant::now() + timeout);
         for task in &mut tasks {
-            // Execute the task
-            match task.execute_with_retry(py) {
-                Ok(result) => {
-                    // Process the result and extract writes
-                    let writes = self.process_task_result(py, task, result)?;
-                    task_writes.push(TaskWrites {
-                        name: task.name.clone(),
-                        writes,
-                        triggers: task.triggers.clone(),
-                    });
+            task.deadline = deadline;
+            let mut writer =
+                StreamWriter::new(task.name.clone(), self.step, self.stream_buffer.clone());
+            if !self.streams_node(&task.name) {
+                writer = writer.muted();
+            }
+            if let Some(mode) = &self.config.stream_subgraphs {
+                writer =
+                    writer.forwarding_subgraphs(mode.clone(), format!("{}:{}", task.name, task.id));
+            }
+            task.writer = Some(Py::new(py, writer)?);
+            task.store = self.store.as_ref().map(|store| store.clone_ref(py));
+            task.context = self.context.as_ref().map(|context| context.clone_ref(py));
+            if let Some(answers) = self.checkpoint.resume_answers.get(&task.id) {
+                task.resume = answers.iter().map(|a| a.clone_ref(py)).collect();
+            }
+        }
+
+        // Writes of tasks that finished before the previous run was interrupted
+        let mut recovered: HashMap<String, Vec<(String, PyObject)>> = HashMap::new();
+        for (channel, value, task_id) in &self.checkpoint.pending_writes {
+            let writes = recovered.entry(task_id.clone()).or_default();
+            if channel != NO_WRITES {
+                writes.push((channel.clone(), value.clone_ref(py)));
+            }
+        }
+
+        Ok(PendingStep {
+            tasks: tasks.into(),
+            recovered,
+            writes: Vec::new(),
+        })
+    }
+
+    /// Run the next task of a step, returning its writes
+    ///
+    /// Returns `None` once every task has run; the writes of all tasks are
+    /// collected in `pending.writes`. Tasks calling `interrupt()` are
+    /// recorded in the checkpoint's `interrupts` and skipped.
+    fn run_next_task<'a>(
+        &mut self,
+        py: Python,
+        pending: &'a mut PendingStep,
+    ) -> PyResult<Option<&'a TaskWrites>> {
+        let Some(mut task) = pending.tasks.pop_front() else {
+            return Ok(None);
+        };
+
+        // Reuse saved writes instead of re-running a completed task
+        let start = Instant::now();
+        let (writes, goto, duration, streamed) = match pending.recovered.remove(&task.id) {
+            Some(recovered) => {
+                let mut writes = Vec::with_capacity(recovered.len());
+                let mut goto = Vec::new();
+                for (channel, value) in recovered {
+                    if channel == GOTO_WRITES {
+                        goto.extend(GotoTarget::from_objects(value.as_ref(py))?);
+                    } else {
+                        writes.push((channel, value));
+                    }
+                }
+                (writes, goto, Duration::ZERO, false)
+            }
+            None => {
+                // Fails if the task failed even after retries
+                let result = match task.execute_with_retry(py) {
+                    Ok(result) => result,
+                    Err(err) if is_interrupt(py, &err) => {
+                        // The node paused; the other tasks of the step still run
+                        let value = err.value(py).getattr("args")?.get_item(0)?.to_object(py);
+                        self.checkpoint.interrupts.push(PendingInterrupt {
+                            id: interrupt_id(&task),
+                            node: task.name.clone(),
+                            task: task.id.clone(),
+                            value,
+                        });
+                        return self.run_next_task(py, pending);
+                    }
+                    Err(err) => return Err(err),
+                };
+                // A returned Command carries both the update and the successors
+                let (result, goto) = match NodeCommand::from_result(result.as_ref(py))? {
+                    Some(command) => (
+                        command.update.unwrap_or_else(|| PyDict::new(py).into()),
+                        command.goto,
+                    ),
+                    None => (result, Vec::new()),
+                };
+                if let Some(target) = goto.iter().find(|t| !self.nodes.contains_key(t.node())) {
+                    return Err(GraphError::UnknownNode(target.node().to_string()).into());
                 }
-                Err(e) => {
-                    // Task failed even after retries
-                    return Err(e);
+                // Process the result and extract writes
+                let streamed = result.as_ref(py).is_instance_of::<StreamedUpdates>();
+                let writes = self.process_task_result(py, &task, result)?;
+                self.save_pending_writes(py, &task, &writes, &goto)?;
+                (writes, goto, start.elapsed(), streamed)
+            }
+        };
+        pending.writes.push(TaskWrites {
+            name: task.name.clone(),
+            writes,
+            triggers: task.triggers.clone(),
+            duration,
+            goto,
+            streamed,
+        });
+        Ok(pending.writes.last())
+    }
+
+    /// Queue what the tasks of a committed step chose to run next: the
+    /// `goto` of their `Command`s and the destinations of the conditional
+    /// edges from their nodes
+    fn queue_successors(&mut self, py: Python, tasks: &[TaskWrites]) -> PyResult<()> {
+        let (mut goto, mut sends) = collect_goto(tasks);
+        if tasks
+            .iter()
+            .any(|task| self.router.has_conditional_edges(&task.name))
+        {
+            let state = self.get_current_state(py)?;
+            let state = state.downcast::<PyDict>(py)?;
+            for task in tasks {
+                for destination in self.router.route_from(py, &task.name, state)? {
+                    if destination.node() == END {
+                        continue;
+                    }
+                    if !self.nodes.contains_key(destination.node()) {
+                        return Err(GraphError::UnknownNode(destination.node().to_string()).into());
+                    }
+                    match destination.into() {
+                        GotoTarget::Send(send) => sends.push(send),
+                        node => goto.push(node),
+                    }
                 }
             }
         }
+        self.checkpoint.pending_goto = goto;
+        self.checkpoint.pending_sends = sends;
+        Ok(())
+    }
+
+    /// Record a completed task's writes until the superstep commits
+    fn save_pending_writes(
+        &mut self,
+        py: Python,
+        task: &PregelExecutableTask,
+        writes: &[(String, PyObject)],
+        goto: &[GotoTarget],
+    ) -> PyResult<()> {
+        let goto: Option<PyObject> = (!goto.is_empty()).then(|| {
+            let targets: Vec<PyObject> = goto.iter().map(|t| t.to_object(py)).collect();
+            targets.to_object(py)
+        });
+        if writes.is_empty() {
+            self.checkpoint.pending_writes.push((
+                NO_WRITES.to_string(),
+                py.None(),
+                task.id.clone(),
+            ));
+        }
+        if let Some(goto) = &goto {
+            self.checkpoint.pending_writes.push((
+                GOTO_WRITES.to_string(),
+                goto.clone_ref(py),
+                task.id.clone(),
+            ));
+        }
+        for (channel, value) in writes {
+            self.checkpoint.pending_writes.push((
+                channel.clone(),
+                value.clone_ref(py),
+                task.id.clone(),
+            ));
+        }
+
+        if let (Some(checkpointer), Some(config)) = (&self.checkpointer, &self.checkpoint_config) {
+            let py_writes = PyList::empty(py);
+            if writes.is_empty() {
+                py_writes.append((NO_WRITES, py.None()))?;
+            }
+            if let Some(goto) = &goto {
+                py_writes.append((GOTO_WRITES, goto))?;
+            }
+            for (channel, value) in writes {
+                py_writes.append((channel, value.clone_ref(py)))?;
+            }
+            checkpointer.call_method1(py, "put_writes", (config, py_writes, &task.id))?;
+        }
 
-        Ok(task_writes)
+        Ok(())
     }
 
     /// Process task result and extract channel writes
@@ -197,41 +888,10 @@ impl PregelLoop {
         task: &PregelExecutableTask,
         result: PyObject,
     ) -> PyResult<Vec<(String, PyObject)>> {
-        let mut writes = Vec::new();
-
-        // Get the node to check its output channels
-        if let Some(node) = self.nodes.get(&task.name) {
-            // Pre-allocate based on channel count for better performance
-            if !node.channels.is_empty() {
-                writes = Vec::with_capacity(node.channels.len());
-            }
-            if !node.channels.is_empty() {
-                // Node specifies output channels
-                if result.as_ref(py).is_instance_of::<PyDict>() {
-                    // Result is a dict - extract values for each channel
-                    let result_dict = result.downcast::<PyDict>(py)?;
-                    for channel_name in &node.channels {
-                        if let Some(value) = result_dict.get_item(channel_name)? {
-                            writes.push((channel_name.clone(), value.into()));
-                        }
-                    }
-                } else {
-                    // Result is a single value - write to all channels
-                    for channel_name in &node.channels {
-                        writes.push((channel_name.clone(), result.clone_ref(py)));
-                    }
-                }
-            } else {
-                // No specific channels - assume result is a dict of channel updates
-                if result.as_ref(py).is_instance_of::<PyDict>() {
-                    let result_dict = result.downcast::<PyDict>(py)?;
-                    for item in result_dict.items() {
-                        let (key, value): (String, PyObject) = item.extract()?;
-                        writes.push((key, value));
-                    }
-                }
-            }
-        }
+        let mut writes = match self.nodes.get(&task.name) {
+            Some(node) => node.map_output(py, &result)?,
+            None => Vec::new(),
+        };
 
         // Add accumulated writes from the task
         writes.extend(task.writes.clone());
@@ -240,24 +900,236 @@ impl PregelLoop {
     }
 
     /// Main execution loop - invoke pattern
+    ///
+    /// If a node calls `interrupt(value)`, the run pauses at the end of that
+    /// step without committing it and the state is returned with an
+    /// `"__interrupt__"` entry listing each `{"id", "node", "value"}`; continue
+    /// with [`resume`](Self::resume) or [`invoke_command`](Self::invoke_command).
     pub fn invoke(&mut self, py: Python, input: PyObject) -> PyResult<PyObject> {
+        self.stop_nodes.clear();
         // Initialize channels with input
         self.initialize_input(py, input)?;
+        self.run(py)
+    }
+
+    /// Run like [`invoke`](Self::invoke), but halt before any of `stop_nodes`
+    /// executes
+    ///
+    /// The run stops at the start of the first step scheduling a stop node,
+    /// with the committed state checkpointed, and returns the state with a
+    /// `"__stop__"` entry listing the stop nodes about to run. Unlike an
+    /// `"__interrupt__"`, nothing is left pending: [`continue_run`](Self::continue_run)
+    /// runs that step and halts again the next time a stop node is
+    /// scheduled. Fails with [`GraphError::UnknownNode`] if a stop node is
+    /// not in the graph.
+    pub fn invoke_until(
+        &mut self,
+        py: Python,
+        input: PyObject,
+        stop_nodes: HashSet<String>,
+    ) -> PyResult<PyObject> {
+        if let Some(node) = stop_nodes.iter().find(|n| !self.nodes.contains_key(*n)) {
+            return Err(GraphError::UnknownNode(node.clone()).into());
+        }
+        self.stop_nodes = stop_nodes;
+        self.stopped_at = None;
+        self.initialize_input(py, input)?;
+        self.run(py)
+    }
+
+    /// Continue a run halted by [`invoke_until`](Self::invoke_until), starting
+    /// with the step it stopped before
+    pub fn continue_run(&mut self, py: Python) -> PyResult<PyObject> {
+        self.check_fingerprint(py)?;
+        self.run(py)
+    }
+
+    /// Fingerprint of the graph's structure: its channel names with their
+    /// Python types, and its node names
+    ///
+    /// Saved in every checkpoint so a run is only resumed by a graph its
+    /// checkpoint fits. A UUIDv5 over the canonical JSON of both, so it is
+    /// stable across processes.
+    pub fn schema_fingerprint(&self, py: Python) -> String {
+        let channels: BTreeMap<&String, String> = self
+            .channels
+            .iter()
+            .map(|(name, channel)| {
+                let kind = channel.as_ref(py).get_type().name().unwrap_or("object");
+                (name, kind.to_string())
+            })
+            .collect();
+        let mut nodes: Vec<&String> = self.nodes.keys().collect();
+        nodes.sort();
+        let content = serde_json::json!({ "channels": channels, "nodes": nodes });
+        uuid::Uuid::new_v5(&CheckpointId::NAMESPACE, content.to_string().as_bytes()).to_string()
+    }
+
+    /// Check that the loaded checkpoint was saved by a graph with this
+    /// graph's [`schema_fingerprint`](Self::schema_fingerprint)
+    ///
+    /// Fails with [`GraphError::CheckpointIncompatible`] otherwise, unless
+    /// [`PregelConfig::force_resume`] is set. Checkpoints without a
+    /// fingerprint are accepted.
+    pub fn check_fingerprint(&self, py: Python) -> Result<(), GraphError> {
+        match &self.checkpoint.fingerprint {
+            Some(found) if !self.config.force_resume => {
+                let expected = self.schema_fingerprint(py);
+                if *found != expected {
+                    return Err(GraphError::CheckpointIncompatible {
+                        expected,
+                        found: found.clone(),
+                    });
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Continue a run paused on a single `interrupt()`, answering it
+    ///
+    /// Shorthand for [`invoke_command`](Self::invoke_command) with
+    /// [`Command::Resume`].
+    pub fn resume(&mut self, py: Python, answer: PyObject) -> PyResult<PyObject> {
+        self.invoke_command(py, Command::Resume(answer))
+    }
+
+    /// Apply `command` and continue the run
+    ///
+    /// Resuming re-runs each answered node from the start: its earlier
+    /// `interrupt()` calls return the answers given so far and the call that
+    /// paused it returns the new answer. Tasks of the paused step that
+    /// finished are not re-run, while interrupted nodes left unanswered run
+    /// again and pause anew. Answers must match pending interrupts:
+    /// [`Command::Resume`] fails with [`GraphError::AmbiguousResume`] when
+    /// several are pending and [`Command::ResumeMap`] with
+    /// [`GraphError::UnknownInterrupt`] on an ID that isn't pending; nothing
+    /// is applied if validation fails.
+    ///
+    /// The checkpoint must have been saved by a graph with the same schema
+    /// fingerprint, see [`check_fingerprint`](Self::check_fingerprint).
+    pub fn invoke_command(&mut self, py: Python, command: Command) -> PyResult<PyObject> {
+        self.check_fingerprint(py)?;
+        match command {
+            Command::Resume(answer) => {
+                let task = match self.checkpoint.interrupts.as_slice() {
+                    [] => return Err(GraphError::NotInterrupted.into()),
+                    [interrupt] => interrupt.task.clone(),
+                    pending => return Err(GraphError::AmbiguousResume(pending.len()).into()),
+                };
+                self.answer_interrupts(vec![(task, answer)]);
+            }
+            Command::ResumeMap(answers) => {
+                if self.checkpoint.interrupts.is_empty() {
+                    return Err(GraphError::NotInterrupted.into());
+                }
+                let mut by_task = Vec::with_capacity(answers.len());
+                for (id, answer) in answers {
+                    let interrupt = self
+                        .checkpoint
+                        .interrupts
+                        .iter()
+                        .find(|interrupt| interrupt.id == id)
+                        .ok_or(GraphError::UnknownInterrupt(id))?;
+                    by_task.push((interrupt.task.clone(), answer));
+                }
+                self.answer_interrupts(by_task);
+            }
+            Command::Update(values) => {
+                if let Some(channel) = values.keys().find(|c| !self.channels.contains_key(*c)) {
+                    return Err(GraphError::InvalidUpdate {
+                        channel: channel.clone(),
+                        writers: vec![COMMAND_WRITER.to_string()],
+                    }
+                    .into());
+                }
+                self.checkpoint.interrupts.clear();
+                let update = TaskWrites {
+                    name: COMMAND_WRITER.to_string(),
+                    writes: values.into_iter().collect(),
+                    triggers: Vec::new(),
+                    duration: Duration::ZERO,
+                    goto: Vec::new(),
+                    streamed: false,
+                };
+                let updated_channels = self.apply_task_writes(py, std::slice::from_ref(&update))?;
+                self.checkpoint.versions_seen.remove(COMMAND_WRITER);
+                // Nodes pending before the update still need to run
+                let triggered = triggered_nodes(&self.trigger_to_nodes, &updated_channels);
+                if let Some(candidates) = &mut self.candidates {
+                    candidates.extend(triggered);
+                }
+            }
+            Command::Goto(node) => {
+                if !self.nodes.contains_key(&node) {
+                    return Err(GraphError::UnknownNode(node).into());
+                }
+                self.checkpoint.interrupts.clear();
+                self.checkpoint.resume_answers.clear();
+                self.checkpoint.pending_writes.clear();
+                self.checkpoint.pending_goto.clear();
+                self.checkpoint.pending_sends.clear();
+                self.goto = Some(node);
+            }
+        }
+        self.run(py)
+    }
+
+    /// Stop nodes scheduled for the current step, sorted; empty if there are
+    /// none or the run already halted before this step
+    fn scheduled_stops(&self, py: Python) -> PyResult<Vec<String>> {
+        if self.stop_nodes.is_empty() || self.stopped_at == Some(self.step) {
+            return Ok(Vec::new());
+        }
+        let mut names: Vec<String> = self
+            .scheduled_tasks(py)?
+            .into_iter()
+            .map(|task| task.name)
+            .collect();
+        names.retain(|name| self.stop_nodes.contains(name));
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Record answers, keyed by task ID, for the tasks' next `interrupt()`
+    /// calls
+    fn answer_interrupts(&mut self, answers: Vec<(String, PyObject)>) {
+        self.checkpoint.interrupts.clear();
+        for (task, answer) in answers {
+            self.checkpoint
+                .resume_answers
+                .entry(task)
+                .or_default()
+                .push(answer);
+        }
+    }
 
+    /// Execute supersteps until convergence, an interrupt or the recursion limit
+    ///
+    /// Channels are finished when the run ends, whether it completed or
+    /// failed; see [`finish_channels`](Self::finish_channels).
+    fn run(&mut self, py: Python) -> PyResult<PyObject> {
+        self.run_steps(py).map_err(|err| self.abort_run(py, err))
+    }
+
+    fn run_steps(&mut self, py: Python) -> PyResult<PyObject> {
         // Execute supersteps until convergence or limit
         while self.step < self.config.recursion_limit {
-            // Check for interrupt before execution
+            // Halt before a stop node of invoke_until
+            let stops = self.scheduled_stops(py)?;
+            if !stops.is_empty() {
+                self.stopped_at = Some(self.step);
+                self.finish_checkpoints(py)?;
+                let state = self.get_current_state(py)?;
+                state.call_method1(py, "__setitem__", ("__stop__", stops))?;
+                return Ok(state);
+            }
+
+            // Check for interrupt before execution, including goto targets
             if !self.config.interrupt_before.is_empty() {
-                let tasks_to_run = prepare_next_tasks(
-                    py,
-                    &self.checkpoint.id,
-                    &self.checkpoint.channel_versions,
-                    &self.checkpoint.versions_seen,
-                    &self.checkpoint.pending_sends,
-                    &self.nodes,
-                    self.step,
-                    true,
-                )?;
+                let tasks_to_run = self.scheduled_tasks(py)?;
 
                 if should_interrupt(
                     &self.checkpoint.channel_versions,
@@ -266,6 +1138,7 @@ impl PregelLoop {
                     &tasks_to_run,
                 ) {
                     // Return current state with interrupt marker
+                    self.finish_checkpoints(py)?;
                     return self.get_current_state(py);
                 }
             }
@@ -273,19 +1146,36 @@ impl PregelLoop {
             // Execute one superstep
             let task_writes = self.execute_step(py)?;
 
+            if !self.checkpoint.interrupts.is_empty() {
+                // Keep the step uncommitted until it is resumed
+                self.persist_interrupt(py)?;
+                let state = self.get_current_state(py)?;
+                state.call_method1(
+                    py,
+                    "__setitem__",
+                    ("__interrupt__", self.interrupt_payload(py)?),
+                )?;
+                return Ok(state);
+            }
+
             if task_writes.is_empty() {
                 // No more tasks - reached convergence
                 break;
             }
 
             // Apply writes to channels
-            apply_writes(
-                py,
-                &mut self.checkpoint.channel_versions,
-                &mut self.checkpoint.versions_seen,
-                &mut self.channels,
-                &task_writes,
-            )?;
+            let updated_channels = self.apply_task_writes(py, &task_writes)?;
+            self.record_superstep(&task_writes);
+            // Only nodes triggered by a changed channel can be ready next step
+            self.candidates = Some(triggered_nodes(&self.trigger_to_nodes, &updated_channels));
+            // Superstep committed - its pending writes are no longer needed
+            self.checkpoint.pending_writes.clear();
+            self.checkpoint.resume_answers.clear();
+            self.queue_successors(py, &task_writes)?;
+            // Nothing consumes writer output outside of streaming
+            self.drain_stream_buffer();
+            self.save_step_checkpoint(py)?;
+            self.check_barriers(py)?;
 
             // Check for interrupt after execution
             if !self.config.interrupt_after.is_empty() {
@@ -296,6 +1186,7 @@ impl PregelLoop {
                     &self.checkpoint.versions_seen,
                     &self.checkpoint.pending_sends,
                     &self.nodes,
+                    self.candidates.as_ref(),
                     self.step,
                     true,
                 )?;
@@ -306,13 +1197,26 @@ impl PregelLoop {
                     &self.config.interrupt_after,
                     &tasks_just_ran,
                 ) {
+                    self.finish_checkpoints(py)?;
                     return self.get_current_state(py);
                 }
             }
 
+            // Check the state predicate on the committed writes
+            if let Some(predicate) = self.interrupt_when.clone() {
+                let state = self.get_current_state(py)?;
+                if predicate(state.downcast::<PyDict>(py)?) {
+                    self.finish_checkpoints(py)?;
+                    return Ok(state);
+                }
+            }
+
             self.step += 1;
         }
 
+        self.finish_channels(py, true)?;
+        self.finish_checkpoints(py)?;
+
         if self.step >= self.config.recursion_limit {
             return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
                 format!("Recursion limit of {} reached", self.config.recursion_limit),
@@ -324,7 +1228,19 @@ impl PregelLoop {
     }
 
     /// Initialize channels with input data
-    fn initialize_input(&mut self, py: Python, input: PyObject) -> PyResult<()> {
+    ///
+    /// A loop resuming from a checkpoint must fit it, see
+    /// [`check_fingerprint`](Self::check_fingerprint); this is where
+    /// [`invoke`](Self::invoke) and the streams check it.
+    pub fn initialize_input(&mut self, py: Python, input: PyObject) -> PyResult<()> {
+        self.check_fingerprint(py)?;
+        self.channels_finished = false;
+        self.channel_sizes = None;
+        self.metrics.reset();
+        self.started = Instant::now();
+        let versions = &self.checkpoint.channel_versions;
+        self.defaults
+            .seed(py, &self.channels, |name| versions.contains_key(name))?;
         // Determine which channels to write input to
         // For now, write to all channels that exist
         if input.as_ref(py).is_instance_of::<PyDict>() {
@@ -344,11 +1260,15 @@ impl PregelLoop {
             }
         }
 
+        // The input starts a new checkpoint, which the step's task IDs use
+        let state = self.get_current_state(py)?;
+        let id = self.next_checkpoint_id(py, state.as_ref(py))?;
+        self.checkpoint.set_id(id);
         Ok(())
     }
 
     /// Get current state from all channels
-    fn get_current_state(&self, py: Python) -> PyResult<PyObject> {
+    pub fn get_current_state(&self, py: Python) -> PyResult<PyObject> {
         let state = PyDict::new(py);
 
         for (channel_name, channel) in &self.channels {
@@ -371,67 +1291,702 @@ impl PregelLoop {
 
     /// Execute with streaming - yields intermediate states
     pub fn stream(&mut self, py: Python, input: PyObject) -> PyResult<Vec<PyObject>> {
+        let chunks = self.stream_chunks(py, input, &StreamMode::Values)?;
+        Ok(chunks.into_iter().map(|chunk| chunk.data).collect())
+    }
+
+    /// Execute with streaming in the given mode(s)
+    ///
+    /// Chunks that nodes emit through their [`StreamWriter`] while running
+    /// are yielded at the step barrier in the order they were written, ahead
+    /// of that step's `updates` and `values` chunks.
+    pub fn stream_chunks(
+        &mut self,
+        py: Python,
+        input: PyObject,
+        mode: &StreamMode,
+    ) -> PyResult<Vec<StreamChunk>> {
         let mut results = Vec::new();
 
         // Initialize channels with input
         self.initialize_input(py, input)?;
 
         // Execute supersteps until convergence or limit
-        while self.step < self.config.recursion_limit {
-            // Execute one superstep
-            let task_writes = self.execute_step(py)?;
+        while let Some(chunks) = self.stream_step(py, mode)? {
+            results.extend(chunks);
+        }
 
-            if task_writes.is_empty() {
-                // No more tasks - reached convergence
-                break;
+        Ok(results)
+    }
+
+    /// Execute one superstep and return its chunks, or `None` once the run
+    /// has converged
+    ///
+    /// With [`PregelConfig::stream_eager`], each call runs a single task and
+    /// returns that node's chunks; once all tasks have run, the next call
+    /// commits the step and returns its `values` chunk.
+    ///
+    /// Call [`initialize_input`](Self::initialize_input) before the first
+    /// step. If the [cancellation token](Self::cancellation_token) fires, the
+    /// step in progress is discarded, the last committed checkpoint is
+    /// persisted and the step fails with [`GraphError::Cancelled`]. Channels
+    /// are finished once the run converges or a step fails.
+    pub fn stream_step(
+        &mut self,
+        py: Python,
+        mode: &StreamMode,
+    ) -> PyResult<Option<Vec<StreamChunk>>> {
+        self.next_stream_step(py, mode)
+            .map_err(|err| self.abort_run(py, err))
+    }
+
+    fn next_stream_step(
+        &mut self,
+        py: Python,
+        mode: &StreamMode,
+    ) -> PyResult<Option<Vec<StreamChunk>>> {
+        self.check_cancelled(py)?;
+        if !self.checkpoint.interrupts.is_empty() {
+            // Paused by interrupt() until resumed
+            return Ok(None);
+        }
+        let mut pending = match self.pending_step.take() {
+            Some(pending) => pending,
+            None => {
+                if self.step >= self.config.recursion_limit {
+                    self.finish_channels(py, true)?;
+                    self.finish_checkpoints(py)?;
+                    return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
+                        format!("Recursion limit of {} reached", self.config.recursion_limit),
+                    ));
+                }
+                let pending = self.prepare_step(py)?;
+                if pending.tasks.is_empty() {
+                    // No more tasks - reached convergence
+                    self.finish_channels(py, true)?;
+                    self.finish_checkpoints(py)?;
+                    return Ok(None);
+                }
+                pending
             }
+        };
 
-            // Apply writes to channels
-            apply_writes(
-                py,
-                &mut self.checkpoint.channel_versions,
-                &mut self.checkpoint.versions_seen,
-                &mut self.channels,
-                &task_writes,
-            )?;
+        let mut results = Vec::new();
 
-            // Yield current state
-            let current_state = self.get_current_state(py)?;
-            results.push(current_state);
+        if self.config.stream_eager {
+            // Run one task and yield its chunks before the next one starts;
+            // the step is committed once all tasks have run
+            if let Some(task) = self.run_next_task(py, &mut pending)? {
+                let update = self.task_chunks(py, task, mode)?;
+                self.check_cancelled(py)?;
+                results.extend(self.drain_chunks(mode));
+                results.extend(update);
+                self.pending_step = Some(pending);
+                return Ok(Some(results));
+            }
+        } else {
+            // Execute one superstep
+            while self.run_next_task(py, &mut pending)?.is_some() {}
+            self.check_cancelled(py)?;
+        }
 
-            self.step += 1;
+        if !self.checkpoint.interrupts.is_empty() {
+            // End the stream with the interrupts, leaving the step uncommitted
+            results.extend(self.drain_chunks(mode));
+            if mode.includes(&StreamMode::Updates) {
+                let update = PyDict::new(py);
+                update.set_item("__interrupt__", self.interrupt_payload(py)?)?;
+                results.push(StreamChunk::new(
+                    StreamMode::Updates,
+                    update.into(),
+                    self.step,
+                ));
+            }
+            self.persist_interrupt(py)?;
+            return Ok(Some(results));
         }
+        let task_writes = pending.writes;
 
-        if self.step >= self.config.recursion_limit {
-            return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
-                format!("Recursion limit of {} reached", self.config.recursion_limit),
+        // Apply writes to channels
+        let updated_channels = self.apply_task_writes(py, &task_writes)?;
+        self.record_superstep(&task_writes);
+        // Only nodes triggered by a changed channel can be ready next step
+        self.candidates = Some(triggered_nodes(&self.trigger_to_nodes, &updated_channels));
+        // Superstep committed - its pending writes are no longer needed
+        self.checkpoint.pending_writes.clear();
+        self.checkpoint.resume_answers.clear();
+        self.queue_successors(py, &task_writes)?;
+        self.save_step_checkpoint(py)?;
+        self.check_barriers(py)?;
+
+        // Yield chunks written by nodes during the step, in write order
+        // (already yielded per task in eager mode)
+        if !self.config.stream_eager {
+            results.extend(self.drain_chunks(mode));
+            for task in &task_writes {
+                results.extend(self.task_chunks(py, task, mode)?);
+            }
+        }
+
+        // Yield current state
+        if mode.includes(&StreamMode::Values) {
+            let current_state = self.get_current_state(py)?;
+            results.push(StreamChunk::new(
+                StreamMode::Values,
+                current_state,
+                self.step,
             ));
         }
 
-        Ok(results)
-    }
+        self.step += 1;
 
-    /// Get the current checkpoint
-    pub fn get_checkpoint(&self) -> &CheckpointState {
-        &self.checkpoint
+        Ok(Some(results))
     }
 
-    /// Get current step number
-    pub fn get_step(&self) -> usize {
-        self.step
+    /// The pending interrupts as a list of `{"id", "node", "value"}` dicts
+    fn interrupt_payload(&self, py: Python) -> PyResult<PyObject> {
+        let payload = PyList::empty(py);
+        for pending in &self.checkpoint.interrupts {
+            let interrupt = PyDict::new(py);
+            interrupt.set_item("id", &pending.id)?;
+            interrupt.set_item("node", &pending.node)?;
+            interrupt.set_item("value", &pending.value)?;
+            payload.append(interrupt)?;
+        }
+        Ok(payload.into())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Persist the paused run, whatever the durability, so it can be resumed
+    /// from the checkpointer
+    fn persist_interrupt(&mut self, py: Python) -> PyResult<()> {
+        self.put_checkpoint(py)?;
+        self.wait_for_checkpoint(py)
+    }
 
-    #[test]
-    fn test_checkpoint_state() {
-        let checkpoint = CheckpointState::new("test-id".to_string());
-        assert_eq!(checkpoint.id, "test-id");
-        assert_eq!(checkpoint.channel_versions.len(), 0);
-        assert_eq!(checkpoint.versions_seen.len(), 0);
+    /// Whether events of `node` pass the [`PregelConfig::stream_tags`] filter
+    fn streams_node(&self, node: &str) -> bool {
+        self.config.stream_tags.is_empty()
+            || self.nodes.get(node).is_some_and(|node| {
+                node.tags
+                    .iter()
+                    .any(|tag| self.config.stream_tags.contains(tag))
+            })
+    }
+
+    /// The `updates` and `debug` chunks for a task's writes that `mode` includes
+    ///
+    /// Nodes filtered out by tag yield nothing, before any Python object is built.
+    fn task_chunks(
+        &self,
+        py: Python,
+        task: &TaskWrites,
+        mode: &StreamMode,
+    ) -> PyResult<Vec<StreamChunk>> {
+        let updates = mode.includes(&StreamMode::Updates);
+        let debug = mode.includes(&StreamMode::Debug);
+        if !(updates || debug) || !self.streams_node(&task.name) {
+            return Ok(Vec::new());
+        }
+        let update = PyDict::new(py);
+        for (channel, value) in &task.writes {
+            update.set_item(channel, value)?;
+        }
+
+        let mut chunks = Vec::new();
+        // A streaming node's updates were surfaced as it yielded them
+        if updates && !task.streamed {
+            chunks.push(StreamChunk::updates(
+                py,
+                &task.name,
+                update.into(),
+                self.step,
+            )?);
+        }
+        if debug {
+            let tags = self
+                .nodes
+                .get(&task.name)
+                .map(|node| node.tags.clone())
+                .unwrap_or_default();
+            let info = DebugInfo::new()
+                .with_output(update.into())
+                .with_duration(task.duration.as_secs_f64() * 1000.0)
+                .with_tags(tags)
+                .with_run_id(self.run_id);
+            chunks.push(StreamChunk::debug(py, &task.name, &info, self.step)?);
+        }
+        Ok(chunks)
+    }
+
+    /// Take the chunks nodes wrote since the last drain that `mode` includes
+    fn drain_chunks(&self, mode: &StreamMode) -> Vec<StreamChunk> {
+        self.drain_stream_buffer()
+            .into_iter()
+            .filter(|chunk| mode.includes(&chunk.mode))
+            .collect()
+    }
+
+    /// Apply the writes of a step to the channels, mirrored into their aliases
+    fn apply_task_writes(&mut self, py: Python, tasks: &[TaskWrites]) -> PyResult<HashSet<String>> {
+        let mirrored: Vec<TaskWrites>;
+        let tasks = if self.aliases.is_empty() {
+            tasks
+        } else {
+            mirrored = tasks
+                .iter()
+                .map(|task| TaskWrites {
+                    name: task.name.clone(),
+                    writes: self.aliases.mirror(py, &task.writes),
+                    triggers: task.triggers.clone(),
+                    duration: task.duration,
+                    goto: task.goto.clone(),
+                    streamed: task.streamed,
+                })
+                .collect();
+            &mirrored
+        };
+        let updated = apply_writes(
+            py,
+            &mut self.checkpoint.channel_versions,
+            &mut self.checkpoint.versions_seen,
+            &mut self.channels,
+            tasks,
+        )?;
+        self.check_state_size(py, &updated)?;
+        Ok(updated)
+    }
+
+    /// Fail with [`GraphError::StateTooLarge`] once a channel's value, or all
+    /// of them together, outgrow [`PregelConfig::max_channel_bytes`] or
+    /// [`PregelConfig::max_state_bytes`]
+    ///
+    /// Sizes are those of [`approx_size`], and only the channels `updated` at
+    /// this barrier are measured again.
+    fn check_state_size(&mut self, py: Python, updated: &HashSet<String>) -> PyResult<()> {
+        let max_channel_bytes = self.config.max_channel_bytes;
+        let max_state_bytes = self.config.max_state_bytes;
+        if max_channel_bytes.is_none() && max_state_bytes.is_none() {
+            return Ok(());
+        }
+        let (mut sizes, mut stale): (HashMap<String, usize>, Vec<&String>) =
+            match self.channel_sizes.take() {
+                Some(sizes) => (
+                    sizes,
+                    updated
+                        .iter()
+                        .filter(|name| self.channels.contains_key(*name))
+                        .collect(),
+                ),
+                None => (HashMap::new(), self.channels.keys().collect()),
+            };
+        stale.sort();
+        for name in &stale {
+            sizes.remove(*name);
+        }
+
+        let mut total: usize = sizes.values().sum();
+        for name in stale {
+            let state_budget = max_state_bytes.map_or(usize::MAX, |max| max.saturating_sub(total));
+            let cap = max_channel_bytes.map_or(state_budget, |max| max.min(state_budget));
+            // An empty channel holds nothing
+            let size = match self.channels[name].as_ref(py).call_method0("get") {
+                Ok(value) => approx_size(value, cap),
+                Err(_) => 0,
+            };
+            if max_channel_bytes.is_some_and(|max| size > max) {
+                return Err(GraphError::StateTooLarge {
+                    channel: name.clone(),
+                    bytes: size,
+                }
+                .into());
+            }
+            total += size;
+            if max_state_bytes.is_some_and(|max| total > max) {
+                return Err(GraphError::StateTooLarge {
+                    channel: name.clone(),
+                    bytes: total,
+                }
+                .into());
+            }
+            sizes.insert(name.clone(), size);
+        }
+        self.channel_sizes = Some(sizes);
+        Ok(())
+    }
+
+    /// Merge the tasks of a committed superstep into the run's metrics
+    fn record_superstep(&mut self, tasks: &[TaskWrites]) {
+        for task in tasks {
+            let sample = NodeSample {
+                duration: task.duration,
+                ..Default::default()
+            };
+            self.metrics.record(&task.name, &sample);
+        }
+        self.metrics.record_superstep(self.started.elapsed());
+    }
+
+    /// Supersteps, node calls, duration and checkpoints of the current run,
+    /// or of the last one once it ended
+    ///
+    /// Tasks whose writes were recovered from a checkpoint count as calls.
+    pub fn run_stats(&self) -> RunStats {
+        self.metrics.run_stats()
+    }
+
+    /// Token that stops [`stream_step`](Self::stream_step) when cancelled
+    ///
+    /// The token can be cancelled from any thread.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Fail with [`GraphError::Cancelled`] if the run was cancelled
+    ///
+    /// Writes of the step in progress are dropped without being applied, so
+    /// the persisted checkpoint is that of the last committed step.
+    fn check_cancelled(&mut self, py: Python) -> PyResult<()> {
+        if !self.cancel.is_cancelled() {
+            return Ok(());
+        }
+        self.pending_step = None;
+        self.drain_stream_buffer();
+        self.finish_checkpoints(py)?;
+        Err(GraphError::Cancelled {
+            step: self.step,
+            checkpoint_id: self
+                .checkpointer
+                .as_ref()
+                .map(|_| self.checkpoint.id.clone()),
+        }
+        .into())
+    }
+
+    /// Fail with [`GraphError::Deadlock`] if a barrier channel waits for a
+    /// writer that can no longer run
+    ///
+    /// Barrier channels are those exposing `names` and `seen` sets, such as
+    /// `NamedBarrierValue` and a `DynamicBarrierValue` once it knows its
+    /// names. A barrier that has seen some but not all of its writers is
+    /// outstanding, and each missing writer must be a node scheduled for the
+    /// next step or reachable from one through the channels it writes and
+    /// the nodes those trigger. A node without declared output channels may
+    /// write anything, so reaching one makes every node reachable.
+    fn check_barriers(&mut self, py: Python) -> PyResult<()> {
+        let barriers = match &self.barrier_channels {
+            Some(barriers) => barriers,
+            None => {
+                let mut barriers: Vec<String> = self
+                    .channels
+                    .iter()
+                    .filter(|(_, channel)| {
+                        let channel = channel.as_ref(py);
+                        channel.hasattr("names").unwrap_or(false)
+                            && channel.hasattr("seen").unwrap_or(false)
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                barriers.sort();
+                self.barrier_channels.insert(barriers)
+            }
+        };
+
+        let mut outstanding = Vec::new();
+        for name in barriers {
+            let channel = self.channels[name].as_ref(py);
+            let Ok(names) = channel.getattr("names")?.extract::<HashSet<String>>() else {
+                continue;
+            };
+            let seen: HashSet<String> = channel.getattr("seen")?.extract()?;
+            let mut missing: Vec<String> = names.difference(&seen).cloned().collect();
+            if !seen.is_empty() && !missing.is_empty() {
+                missing.sort();
+                outstanding.push((name.clone(), missing));
+            }
+        }
+        if outstanding.is_empty() {
+            return Ok(());
+        }
+
+        let reachable = self.reachable_nodes(py)?;
+        for (channel, missing) in outstanding {
+            let missing: Vec<String> = missing
+                .into_iter()
+                .filter(|writer| !reachable.contains(writer))
+                .collect();
+            if !missing.is_empty() {
+                return Err(GraphError::Deadlock { channel, missing }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Nodes that can still run: those scheduled for the next step and all
+    /// nodes reachable from them through their output channels
+    fn reachable_nodes(&self, py: Python) -> PyResult<HashSet<String>> {
+        let mut queue: VecDeque<String> = prepare_next_tasks(
+            py,
+            &self.checkpoint.id,
+            &self.checkpoint.channel_versions,
+            &self.checkpoint.versions_seen,
+            &self.checkpoint.pending_sends,
+            &self.nodes,
+            self.candidates.as_ref(),
+            self.step,
+            true,
+        )?
+        .into_iter()
+        .map(|task| task.name)
+        .chain(
+            self.checkpoint
+                .pending_goto
+                .iter()
+                .map(|target| target.node().to_string()),
+        )
+        .chain(self.goto.clone())
+        .collect();
+
+        let mut reachable = HashSet::new();
+        while let Some(name) = queue.pop_front() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&name) else {
+                continue;
+            };
+            if node.channels.is_empty() {
+                return Ok(self.nodes.keys().cloned().collect());
+            }
+            for channel in &node.channels {
+                queue.extend(
+                    self.trigger_to_nodes
+                        .get(channel)
+                        .into_iter()
+                        .flatten()
+                        .cloned(),
+                );
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// Call `finish()` on every channel at the end of the run, in name order
+    ///
+    /// A channel returning `True` has produced a final update, so it gets a
+    /// new version like a channel written at a barrier. With `checkpoint` and
+    /// per-step durability the state is saved again so the last checkpoint
+    /// includes those updates; with [`Durability::Exit`] the final
+    /// checkpoint is written afterwards anyway. Every channel is finished
+    /// even if one raises, and the first error is returned. Channels are
+    /// finished at most once per run; those without `finish()` are skipped.
+    fn finish_channels(&mut self, py: Python, checkpoint: bool) -> PyResult<()> {
+        if std::mem::replace(&mut self.channels_finished, true) {
+            return Ok(());
+        }
+        let mut names: Vec<String> = self.channels.keys().cloned().collect();
+        names.sort();
+
+        let mut finalized = Vec::new();
+        let mut first_error = None;
+        for name in names {
+            let Ok(finish) = self.channels[&name].getattr(py, "finish") else {
+                continue;
+            };
+            match finish.call0(py) {
+                Ok(updated) => {
+                    if updated.extract::<bool>(py).unwrap_or(false) {
+                        finalized.push(name);
+                    }
+                }
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        let version = self
+            .checkpoint
+            .channel_versions
+            .values()
+            .max()
+            .copied()
+            .unwrap_or(0)
+            + 1;
+        for name in &finalized {
+            self.checkpoint
+                .channel_versions
+                .insert(name.clone(), version);
+        }
+        if let Some(err) = first_error {
+            return Err(err);
+        }
+        if checkpoint && !finalized.is_empty() && self.config.durability != Durability::Exit {
+            self.put_checkpoint(py)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the channels of a run that failed with `err` and return `err`
+    ///
+    /// The last checkpoint is left as it was, and errors raised while
+    /// finishing are dropped in favour of the one that ended the run.
+    fn abort_run(&mut self, py: Python, err: PyErr) -> PyErr {
+        let _ = self.finish_channels(py, false);
+        err
+    }
+
+    /// Persist the checkpoint of a committed step unless durability is `exit`
+    fn save_step_checkpoint(&mut self, py: Python) -> PyResult<()> {
+        match self.config.durability {
+            Durability::Sync | Durability::Async => self.put_checkpoint(py),
+            Durability::Exit => Ok(()),
+        }
+    }
+
+    /// Persist the final checkpoint and wait for any in-flight `put`
+    fn finish_checkpoints(&mut self, py: Python) -> PyResult<()> {
+        if self.config.durability == Durability::Exit {
+            self.put_checkpoint(py)?;
+        }
+        self.wait_for_checkpoint(py)
+    }
+
+    /// Save the current state through `checkpointer.put`
+    ///
+    /// Each saved checkpoint gets an ID derived from the previous one, see
+    /// [`next_checkpoint_id`](Self::next_checkpoint_id), which the task IDs
+    /// of a paused step follow, see [`CheckpointState::set_id`]. With
+    /// [`Durability::Async`] the call runs on a background thread; at most one
+    /// `put` is in flight, so checkpoints are still written in step order.
+    fn put_checkpoint(&mut self, py: Python) -> PyResult<()> {
+        let Some(checkpointer) = self.checkpointer.as_ref().map(|c| c.clone_ref(py)) else {
+            return Ok(());
+        };
+        let Some(config) = self.checkpoint_config.as_ref().map(|c| c.clone_ref(py)) else {
+            return Ok(());
+        };
+        self.wait_for_checkpoint(py)?;
+
+        let state = self.get_current_state(py)?;
+        let id = self.next_checkpoint_id(py, state.as_ref(py))?;
+        self.checkpoint.set_id(id);
+        self.checkpoint.fingerprint = Some(self.schema_fingerprint(py));
+        let checkpoint = self.checkpoint.to_py_checkpoint(py)?;
+        checkpoint.call_method1(py, "__setitem__", ("channel_values", state))?;
+        let metadata = PyDict::new(py);
+        metadata.set_item("source", "loop")?;
+        metadata.set_item("step", self.step)?;
+        metadata.set_item("run_id", self.run_id.to_string())?;
+        let new_versions = self.checkpoint.channel_versions.clone().into_py(py);
+        let args: Py<pyo3::types::PyTuple> =
+            (config, checkpoint, metadata, new_versions).into_py(py);
+
+        match self.config.durability {
+            Durability::Async => {
+                self.pending_put = Some(std::thread::spawn(move || {
+                    Python::with_gil(|py| {
+                        checkpointer.call_method1(py, "put", args.as_ref(py))?;
+                        Ok(())
+                    })
+                }));
+            }
+            Durability::Sync | Durability::Exit => {
+                checkpointer.call_method1(py, "put", args.as_ref(py))?;
+            }
+        }
+        self.metrics.record_checkpoint();
+        Ok(())
+    }
+
+    /// ID of a checkpoint of `state` taken after the current checkpoint
+    ///
+    /// Derived from the current ID, the step and the state, so replaying a
+    /// run gives the same chain of IDs.
+    fn next_checkpoint_id(&self, py: Python, state: &PyAny) -> PyResult<String> {
+        let values = state
+            .downcast::<PyDict>()?
+            .iter()
+            .map(|(name, value)| Ok((name.extract::<String>()?, value)))
+            .collect::<PyResult<Vec<_>>>()?;
+        derive_checkpoint_id(py, Some(&self.checkpoint.id), self.step, values)
+    }
+
+    /// Block until the background checkpoint `put` (if any) has completed
+    fn wait_for_checkpoint(&mut self, py: Python) -> PyResult<()> {
+        let Some(handle) = self.pending_put.take() else {
+            return Ok(());
+        };
+        py.allow_threads(|| handle.join()).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Checkpoint writer panicked")
+        })?
+    }
+
+    /// Take all chunks written by nodes since the last drain
+    fn drain_stream_buffer(&self) -> Vec<StreamChunk> {
+        match self.stream_buffer.lock() {
+            Ok(mut buffer) => {
+                let chunks = buffer.chunks().to_vec();
+                buffer.clear();
+                chunks
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Get the current checkpoint
+    pub fn get_checkpoint(&self) -> &CheckpointState {
+        &self.checkpoint
+    }
+
+    /// Sends queued to run as tasks of the next step
+    ///
+    /// After an interrupt these are the fan-out tasks the resumed run
+    /// dispatches first.
+    pub fn pending_sends(&self) -> &[crate::send::Send] {
+        &self.checkpoint.pending_sends
+    }
+
+    /// Replace the sends queued for the next step, to add or drop some
+    /// before resuming an interrupted run
+    ///
+    /// Fails with [`GraphError::UnknownNode`] if a send targets a node not
+    /// in the graph, leaving the queue unchanged. The new queue is saved
+    /// with the next checkpoint.
+    pub fn update_pending_sends(
+        &mut self,
+        sends: Vec<crate::send::Send>,
+    ) -> Result<(), GraphError> {
+        if let Some(send) = sends
+            .iter()
+            .find(|send| !self.nodes.contains_key(&send.node))
+        {
+            return Err(GraphError::UnknownNode(send.node.clone()));
+        }
+        self.checkpoint.pending_sends = sends;
+        Ok(())
+    }
+
+    /// Get current step number
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+
+    /// ID of the run, recorded in checkpoint metadata and debug events
+    pub fn run_id(&self) -> uuid::Uuid {
+        self.run_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_state() {
+        let checkpoint = CheckpointState::new("test-id".to_string());
+        assert_eq!(checkpoint.id, "test-id");
+        assert_eq!(checkpoint.channel_versions.len(), 0);
+        assert_eq!(checkpoint.versions_seen.len(), 0);
     }
 
     #[test]
@@ -441,4 +1996,2608 @@ mod tests {
         assert_eq!(config.interrupt_before.len(), 0);
         assert_eq!(config.interrupt_after.len(), 0);
     }
+
+    /// Globals with a minimal Python `Chan` channel class defined
+    fn python_env(py: Python<'_>) -> &PyDict {
+        let globals = PyDict::new(py);
+        py.run(
+            r#"
+class Chan:
+    def __init__(self):
+        self.value = None
+    def update(self, values):
+        for v in values:
+            self.value = v
+        return bool(values)
+    def get(self):
+        if self.value is None:
+            raise Exception("empty")
+        return self.value
+"#,
+            Some(globals),
+            None,
+        )
+        .unwrap();
+        globals
+    }
+
+    #[test]
+    fn test_resume_skips_tasks_with_pending_writes() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Recorder:
+    def __init__(self):
+        self.writes = []
+    def put_writes(self, config, writes, task_id):
+        for channel, value in writes:
+            self.writes.append((task_id, channel, value))
+
+calls = {"a": 0, "b": 0}
+def node_a(_):
+    calls["a"] += 1
+    return {"out_a": "a"}
+def node_b(_):
+    calls["b"] += 1
+    return {"out_b": "b"}
+recorder = Recorder()
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let build = || {
+                let mut nodes = HashMap::new();
+                for name in ["a", "b"] {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            get(&format!("node_{}", name)),
+                            name.to_string(),
+                            vec!["input".to_string()],
+                            vec![format!("out_{}", name)],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "out_a", "out_b"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                (nodes, channels)
+            };
+            let input = || {
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                input.to_object(py)
+            };
+
+            // First run: only task "a" completes before the crash
+            let (nodes, channels) = build();
+            let mut crashed = PregelLoop::new(nodes, channels, PregelConfig::default())
+                .with_checkpointer(get("recorder"), PyDict::new(py).to_object(py));
+            crashed.initialize_input(py, input()).unwrap();
+            let mut task = prepare_next_tasks(
+                py,
+                &crashed.checkpoint.id,
+                &crashed.checkpoint.channel_versions,
+                &crashed.checkpoint.versions_seen,
+                &crashed.checkpoint.pending_sends,
+                &crashed.nodes,
+                None,
+                0,
+                true,
+            )
+            .unwrap()
+            .into_iter()
+            .find(|t| t.name == "a")
+            .unwrap();
+            let result = task.execute_with_retry(py).unwrap();
+            let writes = crashed.process_task_result(py, &task, result).unwrap();
+            crashed
+                .save_pending_writes(py, &task, &writes, &[])
+                .unwrap();
+
+            // Resume the same checkpoint from the saved writes
+            let (nodes, channels) = build();
+            let mut checkpoint = CheckpointState::new(crashed.checkpoint.id.clone());
+            let saved = get("recorder").getattr(py, "writes").unwrap();
+            checkpoint
+                .load_pending_writes(py, saved.as_ref(py))
+                .unwrap();
+            let mut resumed = PregelLoop::from_checkpoint(
+                py,
+                nodes,
+                channels,
+                checkpoint,
+                PregelConfig::default(),
+            );
+            let state = resumed.invoke(py, input()).unwrap();
+            let state = state.downcast::<PyDict>(py).unwrap();
+
+            let calls = get("calls");
+            let calls = calls.downcast::<PyDict>(py).unwrap();
+            let count = |name: &str| {
+                calls
+                    .get_item(name)
+                    .unwrap()
+                    .unwrap()
+                    .extract::<usize>()
+                    .unwrap()
+            };
+            assert_eq!(count("a"), 1);
+            assert_eq!(count("b"), 1);
+            assert!(state.get_item("out_a").unwrap().is_some());
+            assert!(state.get_item("out_b").unwrap().is_some());
+            assert!(resumed.get_checkpoint().pending_writes.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_stream_messages_from_node_writer() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def llm(_, writer):
+    for token in ["Hel", "lo"]:
+        writer.write_message(token)
+    return {"reply": "Hello"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            nodes.insert(
+                "llm".to_string(),
+                PregelNode::new(
+                    locals.get_item("llm").unwrap().unwrap().to_object(py),
+                    "llm".to_string(),
+                    vec!["input".to_string()],
+                    vec!["reply".to_string()],
+                ),
+            );
+            let mut channels = HashMap::new();
+            for name in ["input", "reply"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let input = PyDict::new(py);
+            input.set_item("input", "hi").unwrap();
+
+            let mut pregel = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let mode = StreamMode::Multiple(vec![StreamMode::Messages, StreamMode::Values]);
+            let chunks = pregel.stream_chunks(py, input.into(), &mode).unwrap();
+
+            let modes: Vec<&str> = chunks.iter().map(|c| c.mode.to_str()).collect();
+            assert_eq!(modes, ["messages", "messages", "values"]);
+
+            let (token, meta): (String, &PyDict) = chunks[0].data.extract(py).unwrap();
+            assert_eq!(token, "Hel");
+            assert_eq!(
+                meta.get_item("langgraph_node")
+                    .unwrap()
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "llm"
+            );
+
+            // The final state is still committed at the barrier
+            let state = chunks[2].data.downcast::<PyDict>(py).unwrap();
+            assert_eq!(
+                state
+                    .get_item("reply")
+                    .unwrap()
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "Hello"
+            );
+        });
+    }
+
+    #[test]
+    fn test_stream_custom_events() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def worker(_, writer):
+    writer("downloaded 1/2")
+    writer.write_message("token")
+    writer.write("downloaded 2/2")
+    return {"done": True}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let build = || {
+                let mut nodes = HashMap::new();
+                nodes.insert(
+                    "worker".to_string(),
+                    PregelNode::new(
+                        locals.get_item("worker").unwrap().unwrap().to_object(py),
+                        "worker".to_string(),
+                        vec!["input".to_string()],
+                        vec!["done".to_string()],
+                    ),
+                );
+                let mut channels = HashMap::new();
+                for name in ["input", "done"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+            };
+            let input = || {
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                input.to_object(py)
+            };
+
+            let mode = StreamMode::Multiple(vec![StreamMode::Custom, StreamMode::Updates]);
+            let chunks = build().stream_chunks(py, input(), &mode).unwrap();
+
+            let modes: Vec<&str> = chunks.iter().map(|c| c.mode.to_str()).collect();
+            assert_eq!(modes, ["custom", "custom", "updates"]);
+            assert_eq!(
+                chunks[1].data.extract::<String>(py).unwrap(),
+                "downloaded 2/2"
+            );
+            assert_eq!(chunks[0].node(py).as_deref(), Some("worker"));
+
+            // Writer output is discarded, not an error, outside of streaming
+            assert!(build().invoke(py, input()).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_stream_tags_filter() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def llm(_, writer):
+    writer("thinking")
+    return {"answer": 1}
+
+def tool(_, writer):
+    writer("fetching")
+    return {"result": 2}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, output) in [("llm", "answer"), ("tool", "result")] {
+                let node = PregelNode::new(
+                    locals.get_item(name).unwrap().unwrap().to_object(py),
+                    name.to_string(),
+                    vec!["input".to_string()],
+                    vec![output.to_string()],
+                );
+                let node = if name == "llm" {
+                    node.with_tags(vec!["llm".to_string()])
+                } else {
+                    node
+                };
+                nodes.insert(name.to_string(), node);
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "answer", "result"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let run_id = uuid::Uuid::new_v4();
+            let config = PregelConfig {
+                stream_tags: vec!["llm".to_string()],
+                run_id: Some(run_id),
+                ..Default::default()
+            };
+            let mut pregel_loop = PregelLoop::new(nodes, channels, config);
+
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            let mode = StreamMode::Multiple(vec![
+                StreamMode::Updates,
+                StreamMode::Custom,
+                StreamMode::Debug,
+            ]);
+            let chunks = pregel_loop
+                .stream_chunks(py, input.to_object(py), &mode)
+                .unwrap();
+
+            let mut modes: Vec<&str> = chunks.iter().map(|c| c.mode.to_str()).collect();
+            modes.sort_unstable();
+            assert_eq!(modes, ["custom", "debug", "updates"]);
+            let custom = chunks
+                .iter()
+                .find(|c| c.mode == StreamMode::Custom)
+                .unwrap();
+            assert_eq!(custom.node(py).as_deref(), Some("llm"));
+            let updates = chunks
+                .iter()
+                .find(|c| c.mode == StreamMode::Updates)
+                .unwrap();
+            assert!(updates.data.as_ref(py).get_item("llm").is_ok());
+
+            let debug = chunks.iter().find(|c| c.mode == StreamMode::Debug).unwrap();
+            let tags: Vec<String> = debug
+                .data
+                .as_ref(py)
+                .get_item("tags")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(tags, ["llm"]);
+            let debug_run_id: String = debug
+                .data
+                .as_ref(py)
+                .get_item("run_id")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(debug_run_id, run_id.to_string());
+
+            // The filtered node still ran and wrote its channel
+            let state = pregel_loop.get_current_state(py).unwrap();
+            assert_eq!(
+                state
+                    .as_ref(py)
+                    .get_item("result")
+                    .unwrap()
+                    .extract::<i32>()
+                    .unwrap(),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn test_node_timeout_per_attempt() {
+        use crate::pregel_node::RetryPolicyConfig;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+import time
+attempts = []
+def slow(_):
+    attempts.append(1)
+    time.sleep(0.3)
+    return {"slow_out": 1}
+def fast(_):
+    return {"fast_out": 2}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let run = |slow_timeout: Duration, step_timeout: Option<Duration>| {
+                py.run("attempts.clear()", Some(locals), None).unwrap();
+                let node = |name: &str| {
+                    PregelNode::new(
+                        locals.get_item(name).unwrap().unwrap().to_object(py),
+                        name.to_string(),
+                        vec!["input".to_string()],
+                        vec![format!("{}_out", name)],
+                    )
+                };
+                let mut slow = node("slow").with_timeout(slow_timeout);
+                slow.retry_policy = Some(RetryPolicyConfig {
+                    initial_interval: 0.0,
+                    backoff_factor: 1.0,
+                    max_interval: 0.0,
+                    max_attempts: 2,
+                    jitter: false,
+                });
+                let mut nodes = HashMap::new();
+                nodes.insert("slow".to_string(), slow);
+                nodes.insert(
+                    "fast".to_string(),
+                    node("fast").with_timeout(Duration::from_secs(10)),
+                );
+                let mut channels = HashMap::new();
+                for name in ["input", "slow_out", "fast_out"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let config = PregelConfig {
+                    step_timeout,
+                    ..Default::default()
+                };
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                let result = PregelLoop::new(nodes, channels, config).invoke(py, input.into());
+                let attempts = locals.get_item("attempts").unwrap().unwrap().len().unwrap();
+                (result, attempts)
+            };
+
+            // Each retry gets the node's own timeout
+            let (result, attempts) = run(Duration::from_millis(50), None);
+            let err = result.unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyTimeoutError>(py));
+            assert!(err.to_string().contains("'slow'"));
+            assert_eq!(attempts, 2);
+
+            // The step timeout caps a longer node timeout, across attempts
+            let (result, attempts) = run(Duration::from_secs(10), Some(Duration::from_millis(50)));
+            assert!(result
+                .unwrap_err()
+                .is_instance_of::<pyo3::exceptions::PyTimeoutError>(py));
+            assert_eq!(attempts, 1);
+
+            // Nodes finishing within their timeout are unaffected
+            let (result, _) = run(Duration::from_secs(10), None);
+            assert!(result.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_interrupt_and_resume() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            locals
+                .set_item(
+                    "interrupt",
+                    pyo3::wrap_pyfunction!(crate::pregel_node::interrupt, py).unwrap(),
+                )
+                .unwrap();
+            py.run(
+                r#"
+runs = []
+def ask(_):
+    runs.append("ask")
+    name = interrupt("name?")
+    age = interrupt({"question": "age?"})
+    return {"answer": f"{name}:{age}"}
+def other(_):
+    runs.append("other")
+    return {"other_out": 1}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, output) in [("ask", "answer"), ("other", "other_out")] {
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        locals.get_item(name).unwrap().unwrap().to_object(py),
+                        name.to_string(),
+                        vec!["input".to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "answer", "other_out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+
+            let interrupt_of = |state: &PyObject| {
+                let interrupts = state.as_ref(py).get_item("__interrupt__").unwrap();
+                assert_eq!(interrupts.len().unwrap(), 1);
+                let interrupt = interrupts.get_item(0).unwrap();
+                assert_eq!(
+                    interrupt
+                        .get_item("node")
+                        .unwrap()
+                        .extract::<String>()
+                        .unwrap(),
+                    "ask"
+                );
+                interrupt.get_item("value").unwrap().to_object(py)
+            };
+
+            // Each interrupt pauses the step without committing it
+            let state = pregel_loop.invoke(py, input.into()).unwrap();
+            let value = interrupt_of(&state);
+            assert_eq!(value.extract::<String>(py).unwrap(), "name?");
+            assert!(state.as_ref(py).get_item("answer").is_err());
+
+            let state = pregel_loop.resume(py, "ann".to_object(py)).unwrap();
+            let value = interrupt_of(&state);
+            assert_eq!(
+                value
+                    .as_ref(py)
+                    .get_item("question")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "age?"
+            );
+
+            // The node re-runs from the start, replaying earlier answers
+            let state = pregel_loop.resume(py, 30.to_object(py)).unwrap();
+            assert!(state.as_ref(py).get_item("__interrupt__").is_err());
+            assert_eq!(
+                state
+                    .as_ref(py)
+                    .get_item("answer")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "ann:30"
+            );
+            let runs: Vec<String> = locals.get_item("runs").unwrap().unwrap().extract().unwrap();
+            let count = |name: &str| runs.iter().filter(|run| *run == name).count();
+            assert_eq!((count("ask"), count("other")), (3, 1));
+            assert!(pregel_loop.get_checkpoint().resume_answers.is_empty());
+
+            assert!(pregel_loop.resume(py, py.None()).is_err());
+
+            // Outside of a node there is nothing to pause
+            let err = py.run("interrupt(1)", Some(locals), None).unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+        });
+    }
+
+    #[test]
+    fn test_resume_after_saving_interrupt() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            locals
+                .set_item(
+                    "interrupt",
+                    pyo3::wrap_pyfunction!(crate::pregel_node::interrupt, py).unwrap(),
+                )
+                .unwrap();
+            py.run(
+                r#"
+class Saver:
+    def put(self, config, checkpoint, metadata, new_versions):
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+def ask(state):
+    return {"answer": interrupt("name?")}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            nodes.insert(
+                "ask".to_string(),
+                PregelNode::new(
+                    locals.get_item("ask").unwrap().unwrap().to_object(py),
+                    "ask".to_string(),
+                    vec!["input".to_string()],
+                    vec!["answer".to_string()],
+                ),
+            );
+            let mut channels = HashMap::new();
+            for name in ["input", "answer"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let saver = py.eval("Saver()", Some(locals), None).unwrap();
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default())
+                .with_checkpointer(saver.to_object(py), PyDict::new(py).into());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            pregel_loop.invoke(py, input.into()).unwrap();
+
+            // Saving the pause gave the checkpoint a new ID; the answer
+            // still reaches the task
+            let paused = &pregel_loop.get_checkpoint().interrupts[0];
+            assert!(paused
+                .task
+                .starts_with(&format!("{}:", pregel_loop.get_checkpoint().id)));
+            let state = pregel_loop.resume(py, "ann".to_object(py)).unwrap();
+            let answer: String = state
+                .as_ref(py)
+                .get_item("answer")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(answer, "ann");
+        });
+    }
+
+    #[test]
+    fn test_retention_keeps_interrupted_checkpoint() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            locals
+                .set_item(
+                    "interrupt",
+                    pyo3::wrap_pyfunction!(crate::pregel_node::interrupt, py).unwrap(),
+                )
+                .unwrap();
+            locals
+                .set_item(
+                    "RustCheckpointer",
+                    py.get_type::<crate::rust_checkpoint::RustCheckpointer>(),
+                )
+                .unwrap();
+            py.run(
+                r#"
+class Saver:
+    def __init__(self, keep_interrupts):
+        self.store = RustCheckpointer(keep_last=1, keep_interrupts=keep_interrupts)
+        self.interrupted = []
+    def put(self, config, checkpoint, metadata, new_versions):
+        if checkpoint["interrupts"]:
+            self.interrupted.append(checkpoint["id"])
+        self.store.put("t1", checkpoint["id"], checkpoint)
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+def ask(state):
+    return {"answer": interrupt("name?")}
+def after(state):
+    return {"done": True}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let run = |keep_interrupts: &str| {
+                let mut nodes = HashMap::new();
+                for (name, trigger, output) in
+                    [("ask", "input", "answer"), ("after", "answer", "done")]
+                {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            locals.get_item(name).unwrap().unwrap().to_object(py),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![output.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "answer", "done"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let saver = py
+                    .eval(&format!("Saver({})", keep_interrupts), Some(locals), None)
+                    .unwrap()
+                    .to_object(py);
+                let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default())
+                    .with_checkpointer(saver.clone_ref(py), PyDict::new(py).into());
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                pregel_loop.invoke(py, input.into()).unwrap();
+                let state = pregel_loop.resume(py, "ann".to_object(py)).unwrap();
+                assert!(state.as_ref(py).get_item("done").is_ok());
+
+                let saver = saver.as_ref(py);
+                let interrupted: Vec<String> =
+                    saver.getattr("interrupted").unwrap().extract().unwrap();
+                assert_eq!(interrupted.len(), 1);
+                let mut kept: Vec<String> = saver
+                    .getattr("store")
+                    .unwrap()
+                    .call_method1("list_checkpoints", ("t1",))
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                kept.retain(|id| *id != pregel_loop.get_checkpoint().id);
+                (interrupted, kept)
+            };
+
+            // The paused checkpoint outlives the keep_last window
+            let (interrupted, kept) = run("True");
+            assert_eq!(kept, interrupted);
+
+            let (_, kept) = run("False");
+            assert!(kept.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_checkpoint_ids_replay() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Saver:
+    def __init__(self):
+        self.ids = []
+    def put(self, config, checkpoint, metadata, new_versions):
+        self.ids.append(checkpoint["id"])
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+def first(_):
+    return {"mid": "m"}
+def second(_):
+    return {"out": "o"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let run = |input: i64| {
+                let mut nodes = HashMap::new();
+                for (name, trigger, output) in [("first", "input", "mid"), ("second", "mid", "out")]
+                {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            locals.get_item(name).unwrap().unwrap().to_object(py),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![output.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "mid", "out"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let saver = py.eval("Saver()", Some(locals), None).unwrap();
+                let state = PyDict::new(py);
+                state.set_item("input", input).unwrap();
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+                    .with_checkpointer(saver.to_object(py), PyDict::new(py).into())
+                    .invoke(py, state.into())
+                    .unwrap();
+                saver
+                    .getattr("ids")
+                    .unwrap()
+                    .extract::<Vec<String>>()
+                    .unwrap()
+            };
+
+            // Replaying the run gives the same chain of IDs
+            let ids = run(1);
+            assert_eq!(ids.len(), 2);
+            assert_ne!(ids[0], ids[1]);
+            assert_eq!(run(1), ids);
+
+            // Another input starts another chain
+            let other = run(2);
+            assert!(other.iter().all(|id| !ids.contains(id)));
+        });
+    }
+
+    #[test]
+    fn test_resume_commands() {
+        use crate::command::Command;
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            locals
+                .set_item(
+                    "interrupt",
+                    pyo3::wrap_pyfunction!(crate::pregel_node::interrupt, py).unwrap(),
+                )
+                .unwrap();
+            py.run(
+                r#"
+def a(_):
+    return {"a_out": interrupt("a?")}
+def b(_):
+    return {"b_out": interrupt("b?")}
+def fallback(_):
+    return {"a_out": "fallback"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let build = || {
+                let mut nodes = HashMap::new();
+                for (name, trigger, output) in [
+                    ("a", "input", "a_out"),
+                    ("b", "input", "b_out"),
+                    ("fallback", "escalate", "a_out"),
+                ] {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            locals.get_item(name).unwrap().unwrap().to_object(py),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![output.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "escalate", "a_out", "b_out", "note"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                pregel_loop.invoke(py, input.into()).unwrap();
+                pregel_loop
+            };
+            let pending = |pregel_loop: &PregelLoop| {
+                let mut ids: Vec<String> = pregel_loop
+                    .get_checkpoint()
+                    .interrupts
+                    .iter()
+                    .map(|i| i.id.clone())
+                    .collect();
+                ids.sort();
+                ids
+            };
+            let value = |state: &PyObject, key: &str| {
+                state
+                    .as_ref(py)
+                    .get_item(key)
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap()
+            };
+
+            // Resume values must match the pending interrupts
+            let mut pregel_loop = build();
+            assert_eq!(pending(&pregel_loop), ["a:0", "b:0"]);
+            let err = pregel_loop
+                .invoke_command(py, Command::Resume("x".to_object(py)))
+                .unwrap_err();
+            assert!(err.to_string().contains("2 interrupts are pending"));
+            let unknown = HashMap::from([("c:0".to_string(), "x".to_object(py))]);
+            let err = pregel_loop
+                .invoke_command(py, Command::ResumeMap(unknown))
+                .unwrap_err();
+            assert!(err.to_string().contains("'c:0'"));
+            assert_eq!(pending(&pregel_loop), ["a:0", "b:0"]);
+
+            // Unanswered interrupts pause again
+            let answers = HashMap::from([("a:0".to_string(), "A".to_object(py))]);
+            pregel_loop
+                .invoke_command(py, Command::ResumeMap(answers))
+                .unwrap();
+            assert_eq!(pending(&pregel_loop), ["b:0"]);
+            let state = pregel_loop.resume(py, "B".to_object(py)).unwrap();
+            assert_eq!(
+                (value(&state, "a_out"), value(&state, "b_out")),
+                ("A".to_string(), "B".to_string())
+            );
+            let err = pregel_loop.resume(py, py.None()).unwrap_err();
+            assert!(err.to_string().contains("no pending interrupt"));
+
+            // Updates write to channels before the paused step runs again
+            let mut pregel_loop = build();
+            let note = HashMap::from([("note".to_string(), "checked".to_object(py))]);
+            let state = pregel_loop
+                .invoke_command(py, Command::Update(note))
+                .unwrap();
+            assert_eq!(value(&state, "note"), "checked");
+            assert_eq!(pending(&pregel_loop), ["a:0", "b:0"]);
+            let typo = HashMap::from([("nope".to_string(), py.None())]);
+            assert!(pregel_loop
+                .invoke_command(py, Command::Update(typo))
+                .is_err());
+
+            // Goto drops the paused step and runs the node even if untriggered
+            let mut pregel_loop = build();
+            let state = pregel_loop
+                .invoke_command(py, Command::Goto("fallback".to_string()))
+                .unwrap();
+            assert_eq!(value(&state, "a_out"), "fallback");
+            assert!(pending(&pregel_loop).is_empty());
+            assert!(pregel_loop
+                .invoke_command(py, Command::Goto("missing".to_string()))
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_node_command_goto() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Command:
+    def __init__(self, update=None, goto=None):
+        self.update = update
+        self.goto = goto
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+def router(_):
+    return Command(update={"route": "r"}, goto=["tools", "__end__", Send("worker", 7)])
+def lost(_):
+    return Command(goto="missing")
+def tools(_):
+    return {"tools_out": "ran"}
+def worker(arg):
+    return {"worker_out": arg}
+def after(_):
+    return {"after_out": "ran"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let build = |entry: &str| {
+                let mut nodes = HashMap::new();
+                for (name, trigger, output) in [
+                    (entry, "input", "route"),
+                    ("tools", "never", "tools_out"),
+                    ("worker", "never", "worker_out"),
+                    ("after", "route", "after_out"),
+                ] {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            locals.get_item(name).unwrap().unwrap().to_object(py),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![output.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in [
+                    "input",
+                    "never",
+                    "route",
+                    "tools_out",
+                    "worker_out",
+                    "after_out",
+                ] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+            };
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+
+            // goto runs its targets next step alongside triggered nodes
+            let mut pregel_loop = build("router");
+            let state = pregel_loop.invoke(py, input.into()).unwrap();
+            let state = state.as_ref(py);
+            assert_eq!(
+                state
+                    .get_item("route")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "r"
+            );
+            assert_eq!(
+                state
+                    .get_item("tools_out")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "ran"
+            );
+            assert_eq!(
+                state
+                    .get_item("worker_out")
+                    .unwrap()
+                    .extract::<i64>()
+                    .unwrap(),
+                7
+            );
+            assert_eq!(
+                state
+                    .get_item("after_out")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "ran"
+            );
+            assert!(pregel_loop.get_checkpoint().pending_goto.is_empty());
+
+            // interrupt_before pauses ahead of a goto target too
+            let mut pregel_loop = build("router");
+            pregel_loop.config.interrupt_before = vec!["tools".to_string()];
+            let state = pregel_loop.invoke(py, input.into()).unwrap();
+            let state = state.as_ref(py);
+            assert!(state.get_item("route").is_ok());
+            assert!(state.get_item("tools_out").is_err());
+            assert!(state.get_item("after_out").is_err());
+            assert_eq!(pregel_loop.get_checkpoint().pending_goto.len(), 1);
+
+            // Routing to an unknown node fails the step
+            let mut pregel_loop = build("lost");
+            let err = pregel_loop.invoke(py, input.into()).unwrap_err();
+            assert!(err.to_string().contains("'missing'"));
+        });
+    }
+
+    #[test]
+    fn test_priority_dispatch_order() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+def make(name):
+    def node(_):
+        calls.append(name)
+        return {name + "_out": 1}
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            let mut channels = HashMap::new();
+            for (name, priority) in [("bulk", 0), ("urgent", 5), ("archive", 0), ("cleanup", -1)] {
+                let func = py
+                    .eval(&format!("make('{}')", name), Some(locals), None)
+                    .unwrap();
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        func.to_object(py),
+                        name.to_string(),
+                        vec!["input".to_string()],
+                        vec![format!("{}_out", name)],
+                    )
+                    .with_priority(priority),
+                );
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(format!("{}_out", name), chan.to_object(py));
+            }
+            let chan = py.eval("Chan()", Some(locals), None).unwrap();
+            channels.insert("input".to_string(), chan.to_object(py));
+
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            pregel_loop.invoke(py, input.into()).unwrap();
+
+            // Descending priority, ties in name order
+            let calls: Vec<String> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(calls, ["urgent", "archive", "bulk", "cleanup"]);
+        });
+    }
+
+    #[test]
+    fn test_concurrent_appends_fold_in_node_name_order() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            // Dispatch order (priority) runs against name order
+            py.run(
+                r#"
+import operator
+class Aggregate:
+    def __init__(self, op, initial):
+        self.op = op
+        self.value = initial
+    def update(self, values):
+        for v in values:
+            self.value = self.op(self.value, v)
+        return bool(values)
+    def get(self):
+        return self.value
+def make(name):
+    def node(_):
+        return {"items": [name]}
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, priority) in [("a", 0), ("b", 1), ("c", 2)] {
+                let func = py
+                    .eval(&format!("make('{}')", name), Some(locals), None)
+                    .unwrap();
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        func.to_object(py),
+                        name.to_string(),
+                        vec!["input".to_string()],
+                        vec!["items".to_string()],
+                    )
+                    .with_priority(priority),
+                );
+            }
+            let mut channels = HashMap::new();
+            let items = py
+                .eval("Aggregate(operator.add, [])", Some(locals), None)
+                .unwrap();
+            channels.insert("items".to_string(), items.to_object(py));
+            let chan = py.eval("Chan()", Some(locals), None).unwrap();
+            channels.insert("input".to_string(), chan.to_object(py));
+
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            let output = pregel_loop.invoke(py, input.into()).unwrap();
+            let output: &PyDict = output.downcast(py).unwrap();
+            let items: Vec<String> = output
+                .get_item("items")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(items, ["a", "b", "c"]);
+        });
+    }
+
+    /// Nodes and channels of a graph whose `items` channel is never
+    /// written, so it holds its starting value
+    fn default_factory_graph(
+        py: Python<'_>,
+    ) -> (HashMap<String, PregelNode>, HashMap<String, PyObject>) {
+        let locals = python_env(py);
+        py.run(
+            "def node(_):\n    return {'done': True}\n",
+            Some(locals),
+            None,
+        )
+        .unwrap();
+        let func = locals.get_item("node").unwrap().unwrap();
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            "node".to_string(),
+            PregelNode::new(
+                func.to_object(py),
+                "node".to_string(),
+                vec!["input".to_string()],
+                vec!["done".to_string()],
+            ),
+        );
+        let mut channels = HashMap::new();
+        for name in ["input", "items", "done"] {
+            let chan = py.eval("Chan()", Some(locals), None).unwrap();
+            channels.insert(name.to_string(), chan.to_object(py));
+        }
+        (nodes, channels)
+    }
+
+    #[test]
+    fn test_default_factory_runs_once_per_run() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let (nodes, channels) = default_factory_graph(py);
+            let mut defaults = ChannelDefaults::new();
+            defaults.add("items", |py| PyList::empty(py).into());
+
+            // Each run gets its own loop over the same channel objects, as
+            // Pregel.invoke does
+            let run = || {
+                let mut pregel_loop =
+                    PregelLoop::new(nodes.clone(), channels.clone(), PregelConfig::default())
+                        .with_channel_defaults(defaults.clone());
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                let output = pregel_loop.invoke(py, input.into()).unwrap();
+                output
+                    .into_ref(py)
+                    .get_item("items")
+                    .unwrap()
+                    .downcast::<PyList>()
+                    .unwrap()
+            };
+
+            let first = run();
+            first.append("from the first run").unwrap();
+            let second = run();
+            assert!(!second.is(first));
+            assert!(second.is_empty());
+            assert_eq!(first.len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_default_factory_skips_restored_channels() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let (nodes, channels) = default_factory_graph(py);
+            let saved = PyList::new(py, ["saved"]);
+            channels["items"]
+                .call_method1(py, "update", (PyList::new(py, [saved]),))
+                .unwrap();
+            let mut checkpoint = CheckpointState::new("resumed".to_string());
+            checkpoint.channel_versions.insert("items".to_string(), 1);
+
+            let mut defaults = ChannelDefaults::new();
+            defaults.add("items", |_| panic!("restored channels keep their value"));
+            let mut resumed = PregelLoop::from_checkpoint(
+                py,
+                nodes,
+                channels,
+                checkpoint,
+                PregelConfig::default(),
+            )
+            .with_channel_defaults(defaults);
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            let output = resumed.invoke(py, input.into()).unwrap();
+            let items = output.as_ref(py).get_item("items").unwrap();
+            assert!(items.is(saved));
+        });
+    }
+
+    #[test]
+    fn test_none_return_writes_nothing() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+def skip(_):
+    return None
+def write_null(_):
+    return {"null_out": None}
+def make(name):
+    def node(_):
+        calls.append(name)
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let node = |func: &str, name: &str, trigger: &str, out: &str| {
+                let func = py.eval(func, Some(locals), None).unwrap();
+                PregelNode::new(
+                    func.to_object(py),
+                    name.to_string(),
+                    vec![trigger.to_string()],
+                    vec![out.to_string()],
+                )
+            };
+            let mut nodes = HashMap::new();
+            for node in [
+                node("skip", "skip", "input", "skip_out"),
+                node("write_null", "write_null", "input", "null_out"),
+                node("make('after_skip')", "after_skip", "skip_out", "done"),
+                node("make('after_null')", "after_null", "null_out", "done"),
+            ] {
+                nodes.insert(node.name.clone(), node);
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "skip_out", "null_out", "done"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            pregel_loop.invoke(py, input.into()).unwrap();
+
+            // A bare None triggers nothing; an explicit null is a write
+            let calls: Vec<String> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(calls, ["after_null"]);
+            assert!(!pregel_loop
+                .get_checkpoint()
+                .channel_versions
+                .contains_key("skip_out"));
+        });
+    }
+
+    #[test]
+    fn test_interrupt_when_state_predicate_holds() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+def draft(x):
+    calls.append("draft")
+    return 0.4
+def publish(x):
+    calls.append("publish")
+    return "published"
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let build = || {
+                let mut nodes = HashMap::new();
+                for (name, trigger, out) in [
+                    ("draft", "input", "confidence"),
+                    ("publish", "confidence", "result"),
+                ] {
+                    let func = locals.get_item(name).unwrap().unwrap();
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            func.to_object(py),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![out.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "confidence", "result"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+            };
+            let input = || {
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                input.to_object(py)
+            };
+            let below = |threshold: f64| {
+                move |state: &PyDict| {
+                    state
+                        .get_item("confidence")
+                        .ok()
+                        .flatten()
+                        .and_then(|value| value.extract::<f64>().ok())
+                        .is_some_and(|confidence| confidence < threshold)
+                }
+            };
+
+            // Confidence 0.4 drops below 0.5 after draft commits
+            let mut paused = build().interrupt_when(below(0.5));
+            let state = paused.invoke(py, input()).unwrap();
+            let state = state.downcast::<PyDict>(py).unwrap();
+            assert_eq!(
+                state
+                    .get_item("confidence")
+                    .unwrap()
+                    .unwrap()
+                    .extract::<f64>()
+                    .unwrap(),
+                0.4
+            );
+            assert!(state.get_item("result").unwrap().is_none());
+
+            let mut completed = build().interrupt_when(below(0.3));
+            let state = completed.invoke(py, input()).unwrap();
+            let state = state.downcast::<PyDict>(py).unwrap();
+            assert!(state.get_item("result").unwrap().is_some());
+
+            let calls: Vec<String> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(calls, ["draft", "draft", "publish"]);
+        });
+    }
+
+    #[test]
+    fn test_pending_sends_after_interrupt() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+class Command:
+    def __init__(self, update=None, goto=None):
+        self.update = update
+        self.goto = goto
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+def router(_):
+    return Command(update={"route": "fan"}, goto=[Send("worker", 1), Send("worker", 2)])
+def worker(arg):
+    calls.append(arg)
+    return {"worker_out": arg}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, trigger, output) in [
+                ("router", "input", "route"),
+                ("worker", "never", "worker_out"),
+            ] {
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        locals.get_item(name).unwrap().unwrap().to_object(py),
+                        name.to_string(),
+                        vec![trigger.to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "never", "route", "worker_out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            // Pause once the router has fanned out, before the workers run
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default())
+                .interrupt_when(|state: &PyDict| {
+                    state.get_item("worker_out").ok().flatten().is_none()
+                });
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            pregel_loop.invoke(py, input.into()).unwrap();
+
+            let args = |sends: &[crate::send::Send]| -> Vec<(String, i64)> {
+                sends
+                    .iter()
+                    .map(|send| (send.node.clone(), send.arg.extract(py).unwrap()))
+                    .collect()
+            };
+            let queued = [("worker".to_string(), 1), ("worker".to_string(), 2)];
+            assert_eq!(args(pregel_loop.pending_sends()), queued);
+
+            // The queue is saved with the checkpoint
+            let saved = pregel_loop.get_checkpoint().to_py_checkpoint(py).unwrap();
+            let restored =
+                CheckpointState::from_py_checkpoint(py, saved.downcast(py).unwrap()).unwrap();
+            assert_eq!(args(&restored.pending_sends), queued);
+
+            // Sends to unknown nodes are rejected
+            let err = pregel_loop
+                .update_pending_sends(vec![crate::send::Send::new(
+                    "missing".to_string(),
+                    py.None(),
+                )])
+                .unwrap_err();
+            assert!(matches!(err, GraphError::UnknownNode(node) if node == "missing"));
+            assert_eq!(pregel_loop.pending_sends().len(), 2);
+
+            // Drop one send and add another, then resume
+            let mut sends: Vec<_> = pregel_loop
+                .pending_sends()
+                .iter()
+                .filter(|send| send.arg.extract::<i64>(py).unwrap() != 1)
+                .cloned()
+                .collect();
+            sends.push(crate::send::Send::new(
+                "worker".to_string(),
+                5.to_object(py),
+            ));
+            pregel_loop.update_pending_sends(sends).unwrap();
+            pregel_loop
+                .invoke_command(py, Command::Update(HashMap::new()))
+                .unwrap();
+
+            let mut calls: Vec<i64> = locals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            calls.sort();
+            assert_eq!(calls, [2, 5]);
+            assert!(pregel_loop.pending_sends().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_resume_send_tasks_to_one_node() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            locals
+                .set_item(
+                    "interrupt",
+                    pyo3::wrap_pyfunction!(crate::pregel_node::interrupt, py).unwrap(),
+                )
+                .unwrap();
+            py.run(
+                r#"
+runs = []
+answers = []
+class Command:
+    def __init__(self, update=None, goto=None):
+        self.update = update
+        self.goto = goto
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+def router(_):
+    return Command(update={"route": "fan"}, goto=[Send("worker", "x"), Send("worker", "y")])
+def worker(arg):
+    runs.append(arg)
+    answer = interrupt(arg)
+    answers.append((arg, answer))
+    return {"worker_out": answer}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, trigger, output) in [
+                ("router", "input", "route"),
+                ("worker", "never", "worker_out"),
+            ] {
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        locals.get_item(name).unwrap().unwrap().to_object(py),
+                        name.to_string(),
+                        vec![trigger.to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "never", "route", "worker_out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+            pregel_loop.invoke(py, input.into()).unwrap();
+
+            // Both Send tasks to the worker pause with their own IDs
+            let pending = |pregel_loop: &PregelLoop| {
+                let mut ids: Vec<String> = pregel_loop
+                    .get_checkpoint()
+                    .interrupts
+                    .iter()
+                    .map(|i| i.id.clone())
+                    .collect();
+                ids.sort();
+                ids
+            };
+            assert_eq!(
+                pending(&pregel_loop),
+                ["send:0:worker:0", "send:1:worker:0"]
+            );
+            let saved = pregel_loop.get_checkpoint().to_py_checkpoint(py).unwrap();
+            let restored =
+                CheckpointState::from_py_checkpoint(py, saved.downcast(py).unwrap()).unwrap();
+            let tasks: Vec<&str> = restored
+                .interrupts
+                .iter()
+                .map(|i| i.task.as_str())
+                .collect();
+            let expected: Vec<&str> = pregel_loop
+                .get_checkpoint()
+                .interrupts
+                .iter()
+                .map(|i| i.task.as_str())
+                .collect();
+            assert_eq!(tasks, expected);
+
+            // Answering one task leaves the other paused
+            let first = HashMap::from([("send:0:worker:0".to_string(), "X".to_object(py))]);
+            pregel_loop
+                .invoke_command(py, Command::ResumeMap(first))
+                .unwrap();
+            assert_eq!(pending(&pregel_loop), ["send:1:worker:0"]);
+
+            // The finished task's writes are reused; only the other one re-runs
+            pregel_loop.resume(py, "Y".to_object(py)).unwrap();
+            assert!(pregel_loop.get_checkpoint().interrupts.is_empty());
+            let mut answers: Vec<(String, String)> = locals
+                .get_item("answers")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            answers.sort();
+            assert_eq!(
+                answers,
+                [
+                    ("x".to_string(), "X".to_string()),
+                    ("y".to_string(), "Y".to_string())
+                ]
+            );
+            let runs: Vec<String> = locals.get_item("runs").unwrap().unwrap().extract().unwrap();
+            let count = |arg: &str| runs.iter().filter(|run| *run == arg).count();
+            assert_eq!((count("x"), count("y")), (2, 3));
+        });
+    }
+
+    #[test]
+    fn test_conditional_edge_sends_fan_out() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+class Send:
+    def __init__(self, node, arg):
+        self.node = node
+        self.arg = arg
+def plan(_):
+    return {"plan_out": "planned"}
+def route(state):
+    return [Send("worker", topic) for topic in state["input"]] + ["done"]
+def worker(topic):
+    calls.append(topic)
+    return {"worker_out": topic.upper()}
+def summary(_):
+    calls.append("summary")
+    return {"summary_out": "ok"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+
+            let mut nodes = HashMap::new();
+            for (name, trigger, output) in [
+                ("plan", "input", "plan_out"),
+                ("worker", "never", "worker_out"),
+                ("summary", "never", "summary_out"),
+            ] {
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        get(name),
+                        name.to_string(),
+                        vec![trigger.to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "never", "plan_out", "worker_out", "summary_out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let path_map = HashMap::from([("done".to_string(), "summary".to_string())]);
+            let mut pregel_loop =
+                PregelLoop::new(nodes, channels, PregelConfig::default()).with_conditional_edge(
+                    ConditionalEdge::new("plan".to_string(), get("route"), path_map),
+                );
+            let input = PyDict::new(py);
+            input.set_item("input", vec!["a", "b", "c"]).unwrap();
+            let state = pregel_loop.invoke(py, input.into()).unwrap();
+
+            // One worker task per Send, beside the mapped node, in one step
+            let mut calls: Vec<String> = get("calls").extract(py).unwrap();
+            calls.sort();
+            assert_eq!(calls, ["a", "b", "c", "summary"]);
+            assert_eq!(pregel_loop.step, 2);
+            let summary = state.as_ref(py).get_item("summary_out").unwrap();
+            assert_eq!(summary.extract::<String>().unwrap(), "ok");
+        });
+    }
+
+    #[test]
+    fn test_channels_finished_when_run_ends() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+finished = []
+class Buffered(Chan):
+    def __init__(self, name):
+        super().__init__()
+        self.name = name
+    def finish(self):
+        finished.append(self.name)
+        if self.value is None:
+            return False
+        self.value = f"flushed {self.value}"
+        return True
+
+class Saver:
+    def __init__(self):
+        self.puts = []
+    def put(self, config, checkpoint, metadata, new_versions):
+        self.puts.append(dict(checkpoint["channel_values"]))
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+saver = Saver()
+def node_a(_):
+    return {"out": 1}
+def node_fail(_):
+    raise ValueError("boom")
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let run = |func: &str| {
+                let mut nodes = HashMap::new();
+                nodes.insert(
+                    "a".to_string(),
+                    PregelNode::new(
+                        get(func),
+                        "a".to_string(),
+                        vec!["input".to_string()],
+                        vec!["out".to_string()],
+                    ),
+                );
+                let mut channels = HashMap::new();
+                channels.insert(
+                    "input".to_string(),
+                    py.eval("Chan()", Some(locals), None).unwrap().to_object(py),
+                );
+                for name in ["out", "log"] {
+                    let chan = py
+                        .eval(&format!("Buffered('{}')", name), Some(locals), None)
+                        .unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let input = PyDict::new(py);
+                input.set_item("input", 0).unwrap();
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+                    .with_checkpointer(get("saver"), PyDict::new(py).to_object(py))
+                    .invoke(py, input.to_object(py))
+            };
+
+            // The final update of `out` is saved in one more checkpoint
+            let state = run("node_a").unwrap();
+            let out: String = state.as_ref(py).get_item("out").unwrap().extract().unwrap();
+            assert_eq!(out, "flushed 1");
+            let finished: Vec<String> = get("finished").extract(py).unwrap();
+            assert_eq!(finished, vec!["log", "out"]);
+            let puts: Vec<HashMap<String, PyObject>> = get("saver")
+                .getattr(py, "puts")
+                .unwrap()
+                .extract(py)
+                .unwrap();
+            assert_eq!(puts.len(), 2);
+            let saved: String = puts[1]["out"].extract(py).unwrap();
+            assert_eq!(saved, "flushed 1");
+
+            // A failing run still finishes its channels and keeps its error
+            py.run("finished.clear(); saver.__init__()", Some(locals), None)
+                .unwrap();
+            let err = run("node_fail").unwrap_err();
+            assert!(err.to_string().contains("boom"));
+            let finished: Vec<String> = get("finished").extract(py).unwrap();
+            assert_eq!(finished, vec!["log", "out"]);
+        });
+    }
+
+    #[test]
+    fn test_unsatisfiable_barrier_is_a_deadlock() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Barrier:
+    """Waits for a write from each of `names`; updated once all were seen"""
+    def __init__(self, names):
+        self.names = set(names)
+        self.seen = set()
+    def update(self, values):
+        self.seen.update(values)
+        return bool(values) and self.seen == self.names
+    def get(self):
+        if self.seen != self.names:
+            raise Exception("empty")
+        return None
+
+def a(_):
+    return {"join": "a", "mid": 1}
+def b(_):
+    return {"join": "b"}
+def joined(_):
+    return {"out": "joined"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let run = |b_trigger: &str| {
+                let mut nodes = HashMap::new();
+                for (name, trigger, outputs) in [
+                    ("a", "input", vec!["join", "mid"]),
+                    ("b", b_trigger, vec!["join"]),
+                    ("joined", "join", vec!["out"]),
+                ] {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            get(name),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            outputs.into_iter().map(String::from).collect(),
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "mid", "never", "out"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let barrier = py.eval("Barrier(['a', 'b'])", Some(locals), None).unwrap();
+                channels.insert("join".to_string(), barrier.to_object(py));
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+                    .invoke(py, input.to_object(py))
+            };
+
+            // `b` runs in the step after `a`, completing the barrier
+            let state = run("mid").unwrap();
+            let out: String = state.as_ref(py).get_item("out").unwrap().extract().unwrap();
+            assert_eq!(out, "joined");
+
+            // Nothing writes `b`'s trigger, so the barrier can never complete
+            let err = run("never").unwrap_err();
+            assert_eq!(
+                err.value(py).to_string(),
+                "Deadlock: barrier channel 'join' waits for 'b' which can no longer run"
+            );
+        });
+    }
+
+    #[test]
+    fn test_durability_controls_checkpoint_puts() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Saver:
+    def __init__(self):
+        self.puts = []
+        self.seen_by_b = None
+    def put(self, config, checkpoint, metadata, new_versions):
+        self.puts.append((metadata["step"], checkpoint["channel_values"]))
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+saver = Saver()
+def node_a(_):
+    return {"mid": 1}
+def node_b(_):
+    saver.seen_by_b = len(saver.puts)
+    return {"out": 2}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap().to_object(py);
+            let run = |durability: Durability| {
+                py.run("saver.__init__()", Some(locals), None).unwrap();
+                let mut nodes = HashMap::new();
+                for (name, trigger, out) in [("a", "input", "mid"), ("b", "mid", "out")] {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            get(&format!("node_{}", name)),
+                            name.to_string(),
+                            vec![trigger.to_string()],
+                            vec![out.to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "mid", "out"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let config = PregelConfig {
+                    durability,
+                    ..PregelConfig::default()
+                };
+                let input = PyDict::new(py);
+                input.set_item("input", 0).unwrap();
+                PregelLoop::new(nodes, channels, config)
+                    .with_checkpointer(get("saver"), PyDict::new(py).to_object(py))
+                    .invoke(py, input.to_object(py))
+                    .unwrap();
+
+                let saver = get("saver");
+                let steps: Vec<usize> = saver
+                    .getattr(py, "puts")
+                    .unwrap()
+                    .extract::<Vec<(usize, PyObject)>>(py)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(step, _)| step)
+                    .collect();
+                let seen_by_b: usize = saver.getattr(py, "seen_by_b").unwrap().extract(py).unwrap();
+                (steps, seen_by_b)
+            };
+
+            // The first step's checkpoint is written before the second step runs
+            assert_eq!(run(Durability::Sync), (vec![0, 1], 1));
+            // Every step is written by the time the run returns
+            assert_eq!(run(Durability::Async).0, vec![0, 1]);
+            // Only the final state is written
+            assert_eq!(run(Durability::Exit), (vec![2], 0));
+
+            assert_eq!("exit".parse::<Durability>(), Ok(Durability::Exit));
+            assert!("never".parse::<Durability>().is_err());
+        });
+    }
+
+    #[test]
+    fn test_store_shared_across_runs() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def remember(state, store):
+    seen = store.search(["users", "alice"])
+    store.put(["users", "alice"], "visit-%d" % len(seen), {"input": state})
+    return len(seen)
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let store = Py::new(py, crate::store::PyInMemoryStore::default()).unwrap();
+            let run = || -> usize {
+                let mut nodes = HashMap::new();
+                nodes.insert(
+                    "remember".to_string(),
+                    PregelNode::new(
+                        locals.get_item("remember").unwrap().unwrap().to_object(py),
+                        "remember".to_string(),
+                        vec!["input".to_string()],
+                        vec!["seen".to_string()],
+                    ),
+                );
+                let mut channels = HashMap::new();
+                for name in ["input", "seen"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let input = PyDict::new(py);
+                input.set_item("input", "hi").unwrap();
+
+                // Each run is a separate thread with fresh channels
+                let mut pregel = PregelLoop::new(nodes, channels, PregelConfig::default())
+                    .with_store(store.to_object(py));
+                let state = pregel.invoke(py, input.into()).unwrap();
+                state
+                    .as_ref(py)
+                    .get_item("seen")
+                    .unwrap()
+                    .extract()
+                    .unwrap()
+            };
+
+            assert_eq!(run(), 0);
+            assert_eq!(run(), 1);
+            assert_eq!(store.as_ref(py).len().unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn test_context_passed_to_nodes() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def greet(state, context):
+    return context["greeting"]
+
+class Shout:
+    def invoke(self, state, config):
+        return config["configurable"]["__pregel_context"]["greeting"].upper()
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, func, output) in [
+                ("greet", "greet", "greeting"),
+                ("shout", "Shout()", "shouted"),
+            ] {
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        py.eval(func, Some(locals), None).unwrap().to_object(py),
+                        name.to_string(),
+                        vec!["input".to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "greeting", "shouted"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let input = PyDict::new(py);
+            input.set_item("input", "hi").unwrap();
+            let context = py.eval("{'greeting': 'hello'}", None, None).unwrap();
+
+            let mut pregel = PregelLoop::new(nodes, channels, PregelConfig::default())
+                .with_context(context.to_object(py));
+            let state = pregel.invoke(py, input.into()).unwrap();
+            let state = state.as_ref(py);
+            let get = |key: &str| -> String { state.get_item(key).unwrap().extract().unwrap() };
+            assert_eq!(get("greeting"), "hello");
+            assert_eq!(get("shouted"), "HELLO");
+        });
+    }
+
+    #[test]
+    fn test_stream_eager_yields_before_barrier() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Saver:
+    def __init__(self):
+        self.steps = []
+    def put(self, config, checkpoint, metadata, new_versions):
+        self.steps.append(metadata["step"])
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+log = []
+def node(name):
+    def run(_):
+        log.append("run:" + name)
+        return name
+    return run
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let run = |stream_eager: bool| -> Vec<String> {
+                py.run("log.clear(); saver = Saver()", Some(locals), None)
+                    .unwrap();
+                let mut nodes = HashMap::new();
+                let mut channels = HashMap::new();
+                for name in ["a", "b"] {
+                    let func = py
+                        .eval(&format!("node('{}')", name), Some(locals), None)
+                        .unwrap();
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            func.to_object(py),
+                            name.to_string(),
+                            vec!["input".to_string()],
+                            vec![format!("{}_out", name)],
+                        ),
+                    );
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(format!("{}_out", name), chan.to_object(py));
+                }
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert("input".to_string(), chan.to_object(py));
+
+                let config = PregelConfig {
+                    durability: Durability::Sync,
+                    stream_eager,
+                    ..PregelConfig::default()
+                };
+                let saver = locals.get_item("saver").unwrap().unwrap().to_object(py);
+                let mut executor = PregelLoop::new(nodes, channels, config)
+                    .with_checkpointer(saver.clone_ref(py), PyDict::new(py).into());
+                let input = PyDict::new(py);
+                input.set_item("input", 0).unwrap();
+                executor.initialize_input(py, input.into()).unwrap();
+
+                let log: &PyList = locals.get_item("log").unwrap().unwrap().downcast().unwrap();
+                let mode = StreamMode::Updates;
+                while let Some(chunks) = executor.stream_step(py, &mode).unwrap() {
+                    for chunk in chunks {
+                        let update: HashMap<String, PyObject> = chunk.data.extract(py).unwrap();
+                        for node in update.keys() {
+                            let puts = saver.getattr(py, "steps").unwrap();
+                            let puts = puts.as_ref(py).len().unwrap();
+                            log.append(format!("yield:{} puts:{}", node, puts)).unwrap();
+                        }
+                    }
+                }
+                log.extract().unwrap()
+            };
+
+            // Each node's update is yielded before the next node runs; the
+            // step is checkpointed once both have run
+            let eager = run(true);
+            assert_eq!(eager.len(), 4);
+            for pair in eager.chunks(2) {
+                let node = pair[0].strip_prefix("run:").unwrap();
+                assert_eq!(pair[1], format!("yield:{} puts:0", node));
+            }
+
+            // Without eager streaming updates wait for the barrier
+            let batched = run(false);
+            assert!(batched[..2].iter().all(|e| e.starts_with("run:")));
+            assert!(batched[2..].iter().all(|e| e.ends_with("puts:1")));
+        });
+    }
+
+    #[test]
+    fn test_cancel_discards_step_in_progress() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+class Saver:
+    def __init__(self):
+        self.steps = []
+    def put(self, config, checkpoint, metadata, new_versions):
+        self.steps.append(metadata["step"])
+        return config
+    def put_writes(self, config, writes, task_id):
+        pass
+
+saver = Saver()
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut channels = HashMap::new();
+            for name in ["input", "mid", "out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let out = channels["out"].clone_ref(py);
+            let mut executor = PregelLoop::new(
+                HashMap::new(),
+                channels,
+                PregelConfig {
+                    durability: Durability::Sync,
+                    ..PregelConfig::default()
+                },
+            );
+            let saver = locals.get_item("saver").unwrap().unwrap().to_object(py);
+            executor = executor.with_checkpointer(saver.clone_ref(py), PyDict::new(py).into());
+
+            // "b" cancels the run while its step is in progress
+            let token = executor.cancellation_token();
+            let cancelling = pyo3::types::PyCFunction::new_closure(py, None, None, move |_, _| {
+                token.cancel();
+                PyResult::Ok(2)
+            })
+            .unwrap();
+            let identity = py.eval("lambda x: 1", None, None).unwrap();
+            for (name, func, trigger, output) in [
+                ("a", identity, "input", "mid"),
+                ("b", cancelling.as_ref(), "mid", "out"),
+            ] {
+                executor.nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        func.to_object(py),
+                        name.to_string(),
+                        vec![trigger.to_string()],
+                        vec![output.to_string()],
+                    ),
+                );
+            }
+            executor.trigger_to_nodes = build_trigger_index(&executor.nodes);
+
+            let input = PyDict::new(py);
+            input.set_item("input", 0).unwrap();
+            executor.initialize_input(py, input.into()).unwrap();
+            let mode = StreamMode::Values;
+            assert!(executor.stream_step(py, &mode).unwrap().is_some());
+
+            let Err(err) = executor.stream_step(py, &mode) else {
+                panic!("step should be cancelled");
+            };
+            assert!(err.is_instance_of::<crate::errors::GraphCancelled>(py));
+            // b's write was dropped and only step 0 was checkpointed
+            assert!(out.getattr(py, "value").unwrap().is_none(py));
+            let steps: Vec<usize> = saver.getattr(py, "steps").unwrap().extract(py).unwrap();
+            assert_eq!(steps, vec![0]);
+        });
+    }
+
+    #[test]
+    fn test_resume_rejects_incompatible_checkpoint() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run("def node(_):\n    return {'out': 1}\n", Some(locals), None)
+                .unwrap();
+            let build = |node_names: &[&str], config: PregelConfig, fingerprint: Option<String>| {
+                let mut nodes = HashMap::new();
+                for name in node_names {
+                    nodes.insert(
+                        name.to_string(),
+                        PregelNode::new(
+                            locals.get_item("node").unwrap().unwrap().to_object(py),
+                            name.to_string(),
+                            vec!["input".to_string()],
+                            vec!["out".to_string()],
+                        ),
+                    );
+                }
+                let mut channels = HashMap::new();
+                for name in ["input", "out"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let mut checkpoint = CheckpointState::new("saved".to_string());
+                checkpoint.fingerprint = fingerprint;
+                PregelLoop::from_checkpoint(py, nodes, channels, checkpoint, config)
+            };
+
+            let original = build(&["a", "b"], PregelConfig::default(), None);
+            let saved = original.schema_fingerprint(py);
+            // Stable for the same structure, whatever the node order
+            assert_eq!(
+                build(&["b", "a"], PregelConfig::default(), None).schema_fingerprint(py),
+                saved
+            );
+            assert!(original.check_fingerprint(py).is_ok());
+
+            let mut changed = build(&["a", "c"], PregelConfig::default(), Some(saved.clone()));
+            let expected = changed.schema_fingerprint(py);
+            assert_ne!(expected, saved);
+            match changed.check_fingerprint(py) {
+                Err(GraphError::CheckpointIncompatible {
+                    expected: e,
+                    found: f,
+                }) => {
+                    assert_eq!(e, expected);
+                    assert_eq!(f, saved);
+                }
+                other => panic!("expected CheckpointIncompatible, got {:?}", other),
+            }
+            assert!(changed.resume(py, py.None()).is_err());
+            // Plain runs and streams from the checkpoint are refused as well
+            let input = || {
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                input.to_object(py)
+            };
+            let err = changed.invoke(py, input()).unwrap_err();
+            assert!(err.to_string().contains(&saved), "{}", err);
+            assert!(changed.stream(py, input()).is_err());
+            assert!(changed.continue_run(py).is_err());
+
+            let forced = build(
+                &["a", "c"],
+                PregelConfig {
+                    force_resume: true,
+                    ..Default::default()
+                },
+                Some(saved.clone()),
+            );
+            assert!(forced.check_fingerprint(py).is_ok());
+            assert!(build(&["a", "b"], PregelConfig::default(), Some(saved))
+                .check_fingerprint(py)
+                .is_ok());
+        });
+    }
+
+    #[test]
+    fn test_state_too_large_fails_at_barrier() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+def write(size):
+    def node(_):
+        return {"a": "x" * size, "b": ["y" * size]}
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let run = |size: usize, config: PregelConfig| {
+                let func = py
+                    .eval(&format!("write({})", size), Some(locals), None)
+                    .unwrap();
+                let mut nodes = HashMap::new();
+                nodes.insert(
+                    "write".to_string(),
+                    PregelNode::new(
+                        func.to_object(py),
+                        "write".to_string(),
+                        vec!["input".to_string()],
+                        vec!["a".to_string(), "b".to_string()],
+                    ),
+                );
+                let mut channels = HashMap::new();
+                for name in ["input", "a", "b"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                PregelLoop::new(nodes, channels, config).invoke(py, input.into())
+            };
+            let too_large = |result: PyResult<PyObject>, expected: &str| {
+                let err = result.unwrap_err();
+                assert!(err.is_instance_of::<pyo3::exceptions::PyMemoryError>(py));
+                let message = err.value(py).to_string();
+                assert!(message.contains(expected), "{}", message);
+            };
+
+            let per_channel = || PregelConfig {
+                max_channel_bytes: Some(1000),
+                ..Default::default()
+            };
+            assert!(run(500, per_channel()).is_ok());
+            too_large(run(2000, per_channel()), "channel 'a'");
+
+            let global = || PregelConfig {
+                max_state_bytes: Some(1500),
+                ..Default::default()
+            };
+            assert!(run(200, global()).is_ok());
+            // Each channel fits alone but not together with the other
+            too_large(run(1000, global()), "channel 'b'");
+        });
+    }
+
+    #[test]
+    fn test_invoke_until_stops_before_node() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+calls = []
+def make(name, out):
+    def node(_):
+        calls.append(name)
+        return {out: name}
+    return node
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let mut nodes = HashMap::new();
+            for (name, trigger, out) in [("a", "input", "a_out"), ("b", "a_out", "b_out")] {
+                let func = py
+                    .eval(&format!("make('{}', '{}')", name, out), Some(locals), None)
+                    .unwrap();
+                nodes.insert(
+                    name.to_string(),
+                    PregelNode::new(
+                        func.to_object(py),
+                        name.to_string(),
+                        vec![trigger.to_string()],
+                        vec![out.to_string()],
+                    ),
+                );
+            }
+            let mut channels = HashMap::new();
+            for name in ["input", "a_out", "b_out"] {
+                let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                channels.insert(name.to_string(), chan.to_object(py));
+            }
+            let mut pregel_loop = PregelLoop::new(nodes, channels, PregelConfig::default());
+            let input = PyDict::new(py);
+            input.set_item("input", 1).unwrap();
+
+            let unknown = ["missing".to_string()].into();
+            assert!(pregel_loop
+                .invoke_until(py, input.to_object(py), unknown)
+                .is_err());
+
+            let stops = ["b".to_string()].into();
+            let state = pregel_loop
+                .invoke_until(py, input.to_object(py), stops)
+                .unwrap();
+            let state: &PyDict = state.downcast(py).unwrap();
+            let stop: Vec<String> = state
+                .get_item("__stop__")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(stop, ["b"]);
+            assert!(state.get_item("__interrupt__").unwrap().is_none());
+            assert!(state.get_item("a_out").unwrap().is_some());
+            assert!(state.get_item("b_out").unwrap().is_none());
+            let calls = locals.get_item("calls").unwrap().unwrap();
+            assert_eq!(calls.extract::<Vec<String>>().unwrap(), ["a"]);
+
+            // Continuing runs the stop node and finishes the graph
+            let state = pregel_loop.continue_run(py).unwrap();
+            let state: &PyDict = state.downcast(py).unwrap();
+            assert!(state.get_item("__stop__").unwrap().is_none());
+            assert!(state.get_item("b_out").unwrap().is_some());
+            assert_eq!(calls.extract::<Vec<String>>().unwrap(), ["a", "b"]);
+        });
+    }
+
+    #[test]
+    fn test_generator_node_streams_updates() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = python_env(py);
+            py.run(
+                r#"
+import operator
+class Aggregate:
+    def __init__(self, op, initial):
+        self.op = op
+        self.value = initial
+    def update(self, values):
+        for v in values:
+            self.value = self.op(self.value, v)
+        return bool(values)
+    def get(self):
+        return self.value
+def generate(_):
+    yield {"tokens": ["a"]}
+    yield {"tokens": ["b"]}
+    yield {"answer": "ab"}
+async def agenerate(_):
+    for token in ["a", "b"]:
+        yield {"tokens": [token]}
+    yield {"answer": "ab"}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+
+            let build = |func: &str| {
+                let mut nodes = HashMap::new();
+                nodes.insert(
+                    "llm".to_string(),
+                    PregelNode::new(
+                        locals.get_item(func).unwrap().unwrap().to_object(py),
+                        "llm".to_string(),
+                        vec!["input".to_string()],
+                        vec!["tokens".to_string(), "answer".to_string()],
+                    ),
+                );
+                let mut channels = HashMap::new();
+                for name in ["input", "answer"] {
+                    let chan = py.eval("Chan()", Some(locals), None).unwrap();
+                    channels.insert(name.to_string(), chan.to_object(py));
+                }
+                let tokens = py
+                    .eval("Aggregate(operator.add, [])", Some(locals), None)
+                    .unwrap();
+                channels.insert("tokens".to_string(), tokens.to_object(py));
+                PregelLoop::new(nodes, channels, PregelConfig::default())
+            };
+            let input = || {
+                let input = PyDict::new(py);
+                input.set_item("input", 1).unwrap();
+                input.to_object(py)
+            };
+
+            // Each yielded update is its own chunk, with no combined one at
+            // the barrier
+            let chunks = build("generate")
+                .stream_chunks(py, input(), &StreamMode::Updates)
+                .unwrap();
+            let updates: Vec<String> = chunks
+                .iter()
+                .map(|chunk| chunk.data.as_ref(py).get_item("llm").unwrap().to_string())
+                .collect();
+            assert_eq!(
+                updates,
+                ["{'tokens': ['a']}", "{'tokens': ['b']}", "{'answer': 'ab'}"]
+            );
+
+            // The reducer channel folds in every update's write
+            for func in ["generate", "agenerate"] {
+                let state = build(func).invoke(py, input()).unwrap();
+                let state: &PyDict = state.downcast(py).unwrap();
+                let tokens: Vec<String> = state
+                    .get_item("tokens")
+                    .unwrap()
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                assert_eq!(tokens, ["a", "b"], "{}", func);
+                let answer: String = state
+                    .get_item("answer")
+                    .unwrap()
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                assert_eq!(answer, "ab");
+            }
+        });
+    }
 }
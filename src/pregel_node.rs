@@ -5,7 +5,19 @@
 
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyTuple};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::errors::{GraphError, NodeInterrupt};
+use crate::stream_output::{StreamWriter, CONFIG_KEY_STREAM_WRITER};
+
+/// Key under `config["configurable"]` holding the graph's long-term store
+pub const CONFIG_KEY_STORE: &str = "__pregel_store";
+
+/// Key under `config["configurable"]` holding the run's `context`
+pub const CONFIG_KEY_CONTEXT: &str = "__pregel_context";
 
 /// PregelNode wraps a Python runnable with execution metadata
 #[derive(Clone)]
@@ -24,6 +36,12 @@ pub struct PregelNode {
     pub retry_policy: Option<RetryPolicyConfig>,
     /// Additional configuration
     pub config: Option<PyObject>,
+    /// Labels for selecting the node's stream output and metrics
+    pub tags: Vec<String>,
+    /// Time limit for each attempt at running the node
+    pub timeout: Option<Duration>,
+    /// Dispatch order within a superstep, highest first (default 0)
+    pub priority: i32,
 }
 
 #[derive(Clone, Debug)]
@@ -89,7 +107,79 @@ impl PregelNode {
             mapper: None,
             retry_policy: None,
             config: None,
+            tags: Vec::new(),
+            timeout: None,
+            priority: 0,
+        }
+    }
+
+    /// Label the node, e.g. to stream only nodes tagged `"llm"`
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Fail each attempt that runs longer than `timeout` with
+    /// [`GraphError::NodeTimeout`]; retries get the full timeout again
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Dispatch the node ahead of lower-priority nodes ready in the same step
+    ///
+    /// Priority only decides which ready nodes start first, e.g. so
+    /// latency-sensitive nodes are not queued behind bulk work. It never
+    /// changes the result: every node of the step still runs against the
+    /// same state and the writes are applied together at the barrier.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Channel writes for the value the node's runnable returned
+    ///
+    /// A dict writes each of its entries that names one of the node's
+    /// `channels` (every entry if the node declares none), and any other
+    /// value is written to all of `channels`. Returning `None` writes
+    /// nothing, so no channel version advances and no successor is
+    /// triggered; to write a null, return it under the channel's key, e.g.
+    /// `{"out": None}`.
+    ///
+    /// The [`StreamedUpdates`] of a node returning a generator map to the
+    /// writes of each update in yield order, so a channel written by several
+    /// updates receives several writes in the step.
+    pub fn map_output(&self, py: Python, result: &PyObject) -> PyResult<Vec<(String, PyObject)>> {
+        if result.is_none(py) {
+            return Ok(Vec::new());
+        }
+        if let Ok(streamed) = result.downcast::<PyCell<StreamedUpdates>>(py) {
+            let mut writes = Vec::new();
+            for update in &streamed.borrow().updates {
+                writes.extend(self.map_output(py, update)?);
+            }
+            return Ok(writes);
+        }
+        let mut writes = Vec::with_capacity(self.channels.len());
+        if let Ok(result_dict) = result.downcast::<PyDict>(py) {
+            if self.channels.is_empty() {
+                for item in result_dict.items() {
+                    let (key, value): (String, PyObject) = item.extract()?;
+                    writes.push((key, value));
+                }
+            } else {
+                for channel_name in &self.channels {
+                    if let Some(value) = result_dict.get_item(channel_name)? {
+                        writes.push((channel_name.clone(), value.into()));
+                    }
+                }
+            }
+        } else {
+            for channel_name in &self.channels {
+                writes.push((channel_name.clone(), result.clone_ref(py)));
+            }
         }
+        Ok(writes)
     }
 
     /// Get the actual runnable to execute
@@ -155,18 +245,100 @@ pub struct PregelExecutableTask {
     pub retry_policy: Option<RetryPolicyConfig>,
     /// Unique task ID
     pub id: String,
+    /// Writer for emitting stream chunks while the task runs
+    pub writer: Option<Py<StreamWriter>>,
+    /// Long-term store shared by all threads of the graph
+    pub store: Option<PyObject>,
+    /// Context the run was invoked with
+    pub context: Option<PyObject>,
+    /// Time limit for each attempt, from the node
+    pub timeout: Option<Duration>,
+    /// Time by which every attempt must finish, from the step timeout
+    pub deadline: Option<Instant>,
+    /// Answers to the node's `interrupt()` calls from earlier runs, in call order
+    pub resume: Vec<PyObject>,
 }
 
 impl PregelExecutableTask {
     /// Execute this task
+    ///
+    /// When the task has a stream writer, a store or a context, Runnables
+    /// find them in `config["configurable"]` and plain callables declaring a
+    /// `writer`, `store` or `context` parameter receive them as keyword
+    /// arguments.
+    ///
+    /// While the node runs, its [`interrupt`] calls return the answers in
+    /// `resume` in order; the first call past them pauses the graph.
+    ///
+    /// A node returning a generator or async generator streams its updates:
+    /// each one is surfaced through the task's writer as it is yielded, and
+    /// all of them are returned together as [`StreamedUpdates`].
     pub fn execute(&mut self, py: Python) -> PyResult<PyObject> {
+        let answers = self.resume.iter().map(|a| a.clone_ref(py)).collect();
+        let previous = INTERRUPT_ANSWERS.with(|cell| cell.replace(Some(answers)));
+        let result = self
+            .call(py)
+            .and_then(|result| self.collect_updates(py, result));
+        INTERRUPT_ANSWERS.with(|cell| cell.replace(previous));
+        result
+    }
+
+    /// Drain a generator or async generator returned by the node into
+    /// [`StreamedUpdates`], writing each update to the stream as it comes;
+    /// any other result is returned as is
+    ///
+    /// An async generator is driven on an event loop of its own, so the node
+    /// must not be running inside another loop on this thread.
+    fn collect_updates(&self, py: Python, result: PyObject) -> PyResult<PyObject> {
+        let inspect = py.import("inspect")?;
+        let is_async = inspect.call_method1("isasyncgen", (&result,))?.is_true()?;
+        if !is_async && !inspect.call_method1("isgenerator", (&result,))?.is_true()? {
+            return Ok(result);
+        }
+
+        let mut updates = Vec::new();
+        let mut push = |update: &PyAny| -> PyResult<()> {
+            if let Some(writer) = &self.writer {
+                writer.borrow(py).write_update(py, update.into())?;
+            }
+            updates.push(update.into());
+            Ok(())
+        };
+        if is_async {
+            let event_loop = py.import("asyncio")?.call_method0("new_event_loop")?;
+            let drained = (|| -> PyResult<()> {
+                loop {
+                    let next = result.call_method0(py, "__anext__")?;
+                    match event_loop.call_method1("run_until_complete", (next,)) {
+                        Ok(update) => push(update)?,
+                        Err(err)
+                            if err.is_instance_of::<pyo3::exceptions::PyStopAsyncIteration>(py) =>
+                        {
+                            return Ok(());
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+            })();
+            event_loop.call_method0("close")?;
+            drained?;
+        } else {
+            for update in result.as_ref(py).iter()? {
+                push(update?)?;
+            }
+        }
+        Ok(Py::new(py, StreamedUpdates { updates })?.into_py(py))
+    }
+
+    /// Call the runnable with the task input
+    fn call(&self, py: Python) -> PyResult<PyObject> {
         // Try multiple calling conventions to support different node types
 
         // 1. Try Runnable.invoke(input, config=config)
         if let Ok(invoke_method) = self.proc.getattr(py, "invoke") {
             let args = PyTuple::new(py, &[self.input.clone_ref(py)]);
             let kwargs = PyDict::new(py);
-            kwargs.set_item("config", self.config.clone_ref(py))?;
+            kwargs.set_item("config", self.config_with_handles(py)?)?;
 
             if let Ok(result) = invoke_method.call(py, args, Some(kwargs)) {
                 return Ok(result);
@@ -178,11 +350,57 @@ impl PregelExecutableTask {
             }
         }
 
-        // 3. Try calling directly as __call__(input)
+        // 3. Try calling directly as __call__(input), with writer=/store=/context= if declared
+        let kwargs = PyDict::new(py);
+        if self.writer.is_some() || self.store.is_some() || self.context.is_some() {
+            let params = declared_params(py, &self.proc);
+            if let Some(writer) = self.writer.as_ref().filter(|_| params.contains("writer")) {
+                kwargs.set_item("writer", writer)?;
+            }
+            if let Some(store) = self.store.as_ref().filter(|_| params.contains("store")) {
+                kwargs.set_item("store", store)?;
+            }
+            if let Some(context) = self.context.as_ref().filter(|_| params.contains("context")) {
+                kwargs.set_item("context", context)?;
+            }
+        }
+        if !kwargs.is_empty() {
+            return self
+                .proc
+                .call(py, (self.input.clone_ref(py),), Some(kwargs));
+        }
         let result = self.proc.call1(py, (self.input.clone_ref(py),))?;
         Ok(result)
     }
 
+    /// Task config with the stream writer, store and context added under
+    /// `configurable`
+    fn config_with_handles(&self, py: Python) -> PyResult<PyObject> {
+        if self.writer.is_none() && self.store.is_none() && self.context.is_none() {
+            return Ok(self.config.clone_ref(py));
+        }
+
+        let config = match self.config.downcast::<PyDict>(py) {
+            Ok(dict) => dict.copy()?,
+            Err(_) => PyDict::new(py),
+        };
+        let configurable = match config.get_item("configurable")? {
+            Some(existing) => existing.downcast::<PyDict>()?.copy()?,
+            None => PyDict::new(py),
+        };
+        if let Some(writer) = &self.writer {
+            configurable.set_item(CONFIG_KEY_STREAM_WRITER, writer)?;
+        }
+        if let Some(store) = &self.store {
+            configurable.set_item(CONFIG_KEY_STORE, store)?;
+        }
+        if let Some(context) = &self.context {
+            configurable.set_item(CONFIG_KEY_CONTEXT, context)?;
+        }
+        config.set_item("configurable", configurable)?;
+        Ok(config.into())
+    }
+
     /// Execute with retry logic
     pub fn execute_with_retry(&mut self, py: Python) -> PyResult<PyObject> {
         if let Some(retry_policy) = self.retry_policy.clone() {
@@ -192,11 +410,11 @@ impl PregelExecutableTask {
             while attempts < retry_policy.max_attempts {
                 attempts += 1;
 
-                match self.execute(py) {
+                match self.execute_attempt(py) {
                     Ok(result) => return Ok(result),
                     Err(e) => {
                         // Check if we should retry this error
-                        if attempts < retry_policy.max_attempts {
+                        if attempts < retry_policy.max_attempts && !is_interrupt(py, &e) {
                             // Calculate backoff delay
                             let delay_ms = retry_policy.initial_interval
                                 * retry_policy.backoff_factor.powi(attempts as i32 - 1);
@@ -223,9 +441,122 @@ impl PregelExecutableTask {
             }
         } else {
             // No retry policy - execute once
-            self.execute(py)
+            self.execute_attempt(py)
+        }
+    }
+
+    /// Run one attempt within the task's timeout and deadline
+    ///
+    /// With a limit, the call runs on its own thread and is abandoned with
+    /// [`GraphError::NodeTimeout`] once the limit passes. Python code can't
+    /// be interrupted, so an abandoned call keeps running to completion and
+    /// its result is discarded.
+    fn execute_attempt(&mut self, py: Python) -> PyResult<PyObject> {
+        let remaining = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let limit = match (self.timeout, remaining) {
+            (Some(timeout), Some(remaining)) => timeout.min(remaining),
+            (limit, None) | (None, limit) => match limit {
+                Some(limit) => limit,
+                None => return self.execute(py),
+            },
+        };
+        let timed_out = || GraphError::NodeTimeout {
+            node: self.name.clone(),
+        };
+        if limit.is_zero() {
+            return Err(timed_out().into());
+        }
+
+        let mut attempt = self.detached(py);
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = Python::with_gil(|py| attempt.execute(py));
+            let _ = sender.send(result);
+        });
+        match py.allow_threads(move || receiver.recv_timeout(limit)) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(timed_out().into()),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                    "Node '{}' exited without a result",
+                    self.name
+                )))
+            }
         }
     }
+
+    /// Copy of the task for running one attempt on another thread
+    fn detached(&self, py: Python) -> Self {
+        Self {
+            name: self.name.clone(),
+            input: self.input.clone_ref(py),
+            proc: self.proc.clone_ref(py),
+            writes: Vec::new(),
+            config: self.config.clone_ref(py),
+            triggers: self.triggers.clone(),
+            retry_policy: None,
+            id: self.id.clone(),
+            writer: self.writer.as_ref().map(|writer| writer.clone_ref(py)),
+            store: self.store.as_ref().map(|store| store.clone_ref(py)),
+            context: self.context.as_ref().map(|context| context.clone_ref(py)),
+            timeout: None,
+            deadline: None,
+            resume: self.resume.iter().map(|a| a.clone_ref(py)).collect(),
+        }
+    }
+}
+
+/// The updates a node returning a generator or async generator yielded,
+/// in yield order
+///
+/// Each update is mapped to writes like a plain return value, and all of
+/// them are applied at the step's barrier: a reducer channel folds in each
+/// update's write in turn, while a channel keeping a single value accepts
+/// only one write per step, so it should appear in one update only,
+/// typically the last.
+#[pyclass]
+pub struct StreamedUpdates {
+    /// Updates in the order they were yielded
+    pub updates: Vec<PyObject>,
+}
+
+thread_local! {
+    /// Unused resume answers of the task running on this thread, `None`
+    /// outside of a task
+    static INTERRUPT_ANSWERS: RefCell<Option<VecDeque<PyObject>>> = const { RefCell::new(None) };
+}
+
+/// Pause the graph from inside a node, surfacing `value` to the caller
+///
+/// The node is re-run from the start when the run is resumed: this call then
+/// returns the answer given on resume instead of pausing. A node may
+/// interrupt several times; each call is answered by its own resume, in order.
+#[pyfunction]
+pub fn interrupt(value: PyObject) -> PyResult<PyObject> {
+    INTERRUPT_ANSWERS.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(answers) => answers
+            .pop_front()
+            .ok_or_else(|| NodeInterrupt::new_err((value,))),
+        None => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            "interrupt() called outside of a graph node",
+        )),
+    })
+}
+
+/// Whether `error` was raised by [`interrupt`]
+pub fn is_interrupt(py: Python, error: &PyErr) -> bool {
+    error.is_instance_of::<NodeInterrupt>(py)
+}
+
+/// Names of the parameters a callable declares (empty if not inspectable)
+fn declared_params(py: Python, func: &PyObject) -> HashSet<String> {
+    py.import("inspect")
+        .and_then(|inspect| inspect.call_method1("signature", (func,)))
+        .and_then(|sig| sig.getattr("parameters"))
+        .and_then(|params| params.iter()?.map(|name| name?.extract()).collect())
+        .unwrap_or_default()
 }
 
 /// PregelTaskDescription is a lightweight description of a task to be executed
@@ -1,10 +1,16 @@
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList, PyTuple, PyType};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 // Import our Rust core modules
-use crate::pregel_loop::{PregelConfig, PregelLoop};
+use crate::channel_manager::ChannelAliases;
+use crate::core::{InputValidator, RunStats};
+use crate::pregel_loop::{Durability, PregelConfig, PregelLoop};
 use crate::pregel_node::PregelNode;
+use crate::stream_output::{StreamChunk, StreamMode, StreamWriter, CONFIG_KEY_STREAM_WRITER};
 
 /// Configuration for output formatting options
 ///
@@ -424,6 +430,113 @@ impl Checkpoint {
     }
 }
 
+/// Convert a Python object to a native value, or `None` if it (or anything
+/// inside it) is not a plain dict, list, str, int, float, bool or None
+///
+/// Only exact types are converted: subclasses such as enums or `IntEnum`
+/// members carry behaviour a `Value` would lose.
+pub fn py_to_value(obj: &PyAny) -> Option<serde_json::Value> {
+    use pyo3::types::{PyBool, PyFloat, PyLong, PyString};
+    use serde_json::Value;
+
+    if obj.is_none() {
+        Some(Value::Null)
+    } else if obj.is_exact_instance_of::<PyBool>() {
+        obj.extract().ok().map(Value::Bool)
+    } else if obj.is_exact_instance_of::<PyLong>() {
+        // Integers beyond 64 bits stay opaque
+        match obj.extract::<i64>() {
+            Ok(n) => Some(n.into()),
+            Err(_) => obj.extract::<u64>().ok().map(Value::from),
+        }
+    } else if obj.is_exact_instance_of::<PyFloat>() {
+        // NaN and infinities have no JSON representation
+        serde_json::Number::from_f64(obj.extract().ok()?).map(Value::Number)
+    } else if obj.is_exact_instance_of::<PyString>() {
+        obj.extract().ok().map(Value::String)
+    } else if obj.is_exact_instance_of::<PyList>() {
+        let list: &PyList = obj.downcast().ok()?;
+        list.iter()
+            .map(py_to_value)
+            .collect::<Option<_>>()
+            .map(Value::Array)
+    } else if obj.is_exact_instance_of::<PyDict>() {
+        let dict: &PyDict = obj.downcast().ok()?;
+        dict.iter()
+            .map(|(key, value)| {
+                let key = key.downcast::<PyString>().ok()?;
+                Some((key.to_str().ok()?.to_string(), py_to_value(value)?))
+            })
+            .collect::<Option<_>>()
+            .map(Value::Object)
+    } else {
+        None
+    }
+}
+
+/// Convert a native value to the equivalent Python object
+///
+/// Integers and floats stay distinct: `42` comes back as an `int` and `42.0`
+/// as a `float`, including after a trip through JSON text.
+pub fn value_to_py(py: Python, value: &serde_json::Value) -> PyObject {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.to_object(py),
+        // A float never converts to an integer, even when it is integral
+        Value::Number(n) if n.is_f64() => n.as_f64().unwrap_or(f64::NAN).to_object(py),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.to_object(py),
+            None => n.as_u64().unwrap_or_default().to_object(py),
+        },
+        Value::String(s) => s.to_object(py),
+        Value::Array(items) => {
+            PyList::new(py, items.iter().map(|item| value_to_py(py, item))).into()
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, item) in fields {
+                // Setting a str key on a fresh dict cannot fail
+                let _ = dict.set_item(key, value_to_py(py, item));
+            }
+            dict.into()
+        }
+    }
+}
+
+/// Content-derived ID of the checkpoint holding the Python `values` after
+/// `step`, taken after the checkpoint `parent_id`
+///
+/// Values [`py_to_value`] converts are hashed as JSON, anything else by its
+/// pickled bytes, or by its `repr` if it cannot be pickled. See
+/// [`CheckpointId`](crate::checkpoint::CheckpointId) for the scheme.
+pub fn derive_checkpoint_id<'a>(
+    py: Python,
+    parent_id: Option<&str>,
+    step: usize,
+    values: impl IntoIterator<Item = (String, &'a PyAny)>,
+) -> PyResult<String> {
+    let dumps = py.import("pickle")?.getattr("dumps")?;
+    let values = values
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match py_to_value(value) {
+                Some(value) => value,
+                None => match dumps.call1((value,)) {
+                    Ok(pickled) => {
+                        let bytes: &[u8] = pickled.extract()?;
+                        serde_json::json!({ "pickle": bytes })
+                    }
+                    Err(_) => serde_json::json!({ "repr": value.repr()?.to_str()? }),
+                },
+            };
+            Ok((name, value))
+        })
+        .collect::<PyResult<HashMap<_, _>>>()?;
+    Ok(crate::checkpoint::CheckpointId::derive(parent_id, step as i32, &values).to_string())
+}
+
 /// Helper function to extract node metadata and create PregelNode
 fn extract_pregel_node(py: Python, node_name: &str, node_obj: &PyObject) -> PyResult<PregelNode> {
     // Extract triggers (channels this node depends on)
@@ -455,6 +568,26 @@ fn extract_pregel_node(py: Python, node_name: &str, node_obj: &PyObject) -> PyRe
     // Extract config if available
     let config = node_obj.getattr(py, "config").ok();
 
+    // Extract tags for stream filtering
+    let tags = node_obj
+        .getattr(py, "tags")
+        .and_then(|tags| tags.extract::<Vec<String>>(py))
+        .unwrap_or_default();
+
+    // Extract the per-attempt timeout in seconds
+    let timeout = node_obj
+        .getattr(py, "timeout")
+        .and_then(|timeout| timeout.extract::<Option<f64>>(py))
+        .ok()
+        .flatten()
+        .map(Duration::from_secs_f64);
+
+    // Extract the dispatch priority
+    let priority = node_obj
+        .getattr(py, "priority")
+        .and_then(|priority| priority.extract::<i32>(py))
+        .unwrap_or(0);
+
     Ok(PregelNode {
         runnable: node_obj.clone_ref(py),
         name: node_name.to_string(),
@@ -463,9 +596,239 @@ fn extract_pregel_node(py: Python, node_name: &str, node_obj: &PyObject) -> PyRe
         mapper: None,
         retry_policy,
         config,
+        tags,
+        timeout,
+        priority,
     })
 }
 
+/// Parse a `stream_mode` argument (a mode name or list of names)
+fn resolve_stream_mode(
+    py: Python,
+    stream_mode: Option<PyObject>,
+    default: &str,
+) -> PyResult<StreamMode> {
+    let parse = |name: &str| {
+        name.parse::<StreamMode>()
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    };
+
+    match stream_mode {
+        None => parse(default),
+        Some(mode) => match mode.extract::<String>(py) {
+            Ok(name) => parse(&name),
+            Err(_) => {
+                let names: Vec<String> = mode.extract(py)?;
+                let modes = names
+                    .iter()
+                    .map(|name| parse(name))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(StreamMode::Multiple(modes))
+            }
+        },
+    }
+}
+
+/// Parse a `durability` argument, defaulting to `async`
+fn resolve_durability(py: Python, durability: Option<PyObject>) -> PyResult<Durability> {
+    match durability {
+        Some(mode) if !mode.is_none(py) => mode
+            .extract::<String>(py)?
+            .parse::<Durability>()
+            .map_err(pyo3::exceptions::PyValueError::new_err),
+        _ => Ok(Durability::default()),
+    }
+}
+
+/// The stream writer of the task running the graph as a subgraph, with the
+/// mode to stream in, if the parent forwards subgraph chunks
+fn subgraph_stream_writer(
+    py: Python,
+    run_config: Option<&PyObject>,
+) -> Option<(Py<StreamWriter>, StreamMode)> {
+    let writer: Py<StreamWriter> = run_config?
+        .downcast::<PyDict>(py)
+        .ok()?
+        .get_item("configurable")
+        .ok()??
+        .downcast::<PyDict>()
+        .ok()?
+        .get_item(CONFIG_KEY_STREAM_WRITER)
+        .ok()??
+        .extract()
+        .ok()?;
+    let mode = writer.borrow(py).subgraph_mode()?.clone();
+    Some((writer, mode))
+}
+
+/// The `run_id` of a run config, given as a `uuid.UUID` or a string
+fn config_run_id(py: Python, run_config: Option<&PyObject>) -> PyResult<Option<uuid::Uuid>> {
+    let Some(run_id) = run_config
+        .and_then(|config| config.downcast::<PyDict>(py).ok())
+        .and_then(|config| config.get_item("run_id").ok().flatten())
+        .filter(|run_id| !run_id.is_none())
+    else {
+        return Ok(None);
+    };
+    run_id
+        .str()?
+        .to_str()?
+        .parse()
+        .map(Some)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid run_id: {}", e)))
+}
+
+/// Create a PregelLoop persisting to `checkpointer` and sharing `store`, if set
+fn new_pregel_loop(
+    py: Python,
+    nodes: HashMap<String, PregelNode>,
+    channels: HashMap<String, PyObject>,
+    config: PregelConfig,
+    checkpointer: Option<&PyObject>,
+    run_config: Option<PyObject>,
+    store: Option<&PyObject>,
+) -> PregelLoop {
+    let mut pregel_loop = PregelLoop::new(nodes, channels, config);
+    if let Some(store) = store.filter(|store| !store.is_none(py)) {
+        pregel_loop = pregel_loop.with_store(store.clone_ref(py));
+    }
+    match checkpointer {
+        Some(checkpointer) if !checkpointer.is_none(py) => {
+            let run_config = run_config.unwrap_or_else(|| PyDict::new(py).into());
+            pregel_loop.with_checkpointer(checkpointer.clone_ref(py), run_config)
+        }
+        _ => pregel_loop,
+    }
+}
+
+/// Parse a `channel_aliases` argument mapping each channel to an alias or a
+/// list of aliases; aliases must name existing channels and form no cycle
+fn parse_channel_aliases(
+    aliases: &PyAny,
+    channels: &HashMap<String, PyObject>,
+) -> PyResult<ChannelAliases> {
+    let mut parsed = ChannelAliases::new();
+    for (channel, targets) in aliases.downcast::<PyDict>()?.iter() {
+        let channel: String = channel.extract()?;
+        let targets: Vec<String> = match targets.extract::<String>() {
+            Ok(alias) => vec![alias],
+            Err(_) => targets.extract()?,
+        };
+        for alias in targets {
+            parsed.add(&channel, &alias)?;
+        }
+    }
+    if let Some(unknown) = parsed.channels().find(|name| !channels.contains_key(*name)) {
+        return Err(crate::errors::GraphError::UnknownChannel(unknown.clone()).into());
+    }
+    Ok(parsed)
+}
+
+/// Per-input configs for a batch: one shared config or a list aligned with the inputs
+fn batch_configs(
+    py: Python,
+    config: Option<PyObject>,
+    len: usize,
+) -> PyResult<Vec<Option<Py<PyDict>>>> {
+    let Some(config) = config.filter(|c| !c.is_none(py)) else {
+        return Ok(vec![None; len]);
+    };
+    if let Ok(configs) = config.downcast::<PyList>(py) {
+        if configs.len() != len {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Got {} configs for {} inputs",
+                configs.len(),
+                len
+            )));
+        }
+        return configs.iter().map(|c| Ok(Some(c.extract()?))).collect();
+    }
+    let config: Py<PyDict> = config.extract(py)?;
+    Ok((0..len).map(|_| Some(config.clone_ref(py))).collect())
+}
+
+/// Invoke `pregel` on each input from a pool of worker threads
+///
+/// The calling thread releases the GIL while waiting. Each worker holds it
+/// for the whole of an invoke, so inputs only overlap while a node releases
+/// the GIL itself (blocking I/O, `time.sleep`, native code that drops it);
+/// pure-Python nodes run one at a time.
+fn run_batch(
+    py: Python,
+    pregel: Py<Pregel>,
+    inputs: Vec<PyObject>,
+    configs: Vec<Option<Py<PyDict>>>,
+    context: Option<PyObject>,
+    max_concurrency: usize,
+) -> Vec<PyResult<PyObject>> {
+    let len = inputs.len();
+    let queue = Arc::new(Mutex::new(
+        inputs
+            .into_iter()
+            .zip(configs)
+            .enumerate()
+            .collect::<Vec<_>>()
+            .into_iter(),
+    ));
+    let results: Arc<Mutex<Vec<Option<PyResult<PyObject>>>>> =
+        Arc::new(Mutex::new((0..len).map(|_| None).collect()));
+    let pregel = Arc::new(pregel);
+    let context = Arc::new(context);
+
+    let workers: Vec<_> = (0..max_concurrency.clamp(1, len.max(1)))
+        .map(|_| {
+            let (queue, results, pregel, context) = (
+                queue.clone(),
+                results.clone(),
+                pregel.clone(),
+                context.clone(),
+            );
+            std::thread::spawn(move || loop {
+                let Some((index, (input, config))) = queue.lock().ok().and_then(|mut q| q.next())
+                else {
+                    break;
+                };
+                let result = Python::with_gil(|py| {
+                    let context = context.as_ref().as_ref().map(|c| c.clone_ref(py));
+                    pregel.borrow(py).invoke(
+                        py,
+                        input,
+                        config.map(Into::into),
+                        context,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                });
+                if let Ok(mut results) = results.lock() {
+                    results[index] = Some(result);
+                }
+            })
+        })
+        .collect();
+
+    py.allow_threads(|| {
+        for worker in workers {
+            let _ = worker.join();
+        }
+    });
+
+    let results = std::mem::take(&mut *results.lock().unwrap_or_else(|e| e.into_inner()));
+    results
+        .into_iter()
+        .map(|result| {
+            result.unwrap_or_else(|| {
+                Err(pyo3::exceptions::PyRuntimeError::new_err(
+                    "Batch worker exited before running its input",
+                ))
+            })
+        })
+        .collect()
+}
+
 /// Pregel provides the main execution engine for LangGraph
 #[pyclass(subclass)]
 pub struct Pregel {
@@ -475,16 +838,42 @@ pub struct Pregel {
     pub channels: HashMap<String, PyObject>,
     #[pyo3(get, set)]
     pub stream_mode: String,
+    /// Yield each node's chunks from `stream` as soon as the node finishes
+    #[pyo3(get, set)]
+    pub stream_eager: bool,
+    /// Seconds a superstep may take; also caps every node's own `timeout`
+    #[pyo3(get, set)]
+    pub step_timeout: Option<f64>,
+    /// Approximate bytes one channel's value may hold after a superstep
+    /// before the run fails with `MemoryError`
+    #[pyo3(get, set)]
+    pub max_channel_bytes: Option<usize>,
+    /// Approximate bytes all channel values may hold together after a
+    /// superstep before the run fails with `MemoryError`
+    #[pyo3(get, set)]
+    pub max_state_bytes: Option<usize>,
     #[pyo3(get, set)]
     pub output_channels: Option<PyObject>,
     #[pyo3(get, set)]
     pub input_channels: Option<PyObject>,
+    /// Called with the input's channel writes before any node runs; raising
+    /// or returning a message string rejects the input
+    #[pyo3(get, set)]
+    pub validate_input: Option<PyObject>,
     #[pyo3(get, set)]
     pub checkpointer: Option<PyObject>,
+    /// Long-term store passed to nodes declaring a `store` parameter
+    #[pyo3(get, set)]
+    pub store: Option<PyObject>,
     #[pyo3(get, set)]
     pub builder: Option<PyObject>,
     #[pyo3(get, set)]
     pub config_type: Option<PyObject>,
+    /// Channels mirroring the writes of others, from the `channel_aliases`
+    /// kwarg mapping a channel to its alias or list of aliases
+    channel_aliases: ChannelAliases,
+    /// Statistics of the latest run, invoked or streamed
+    last_run_stats: Mutex<RunStats>,
 }
 
 #[pymethods]
@@ -500,6 +889,26 @@ impl Pregel {
             .and_then(|v| v.extract::<String>().ok())
             .unwrap_or_else(|| "values".to_string());
 
+        let stream_eager = kwargs
+            .and_then(|kw| kw.get_item("stream_eager").ok().flatten())
+            .and_then(|v| v.extract::<bool>().ok())
+            .unwrap_or(false);
+
+        let step_timeout = kwargs
+            .and_then(|kw| kw.get_item("step_timeout").ok().flatten())
+            .and_then(|v| v.extract::<Option<f64>>().ok())
+            .flatten();
+
+        let max_channel_bytes = kwargs
+            .and_then(|kw| kw.get_item("max_channel_bytes").ok().flatten())
+            .and_then(|v| v.extract::<Option<usize>>().ok())
+            .flatten();
+
+        let max_state_bytes = kwargs
+            .and_then(|kw| kw.get_item("max_state_bytes").ok().flatten())
+            .and_then(|v| v.extract::<Option<usize>>().ok())
+            .flatten();
+
         let output_channels = kwargs
             .and_then(|kw| kw.get_item("output_channels").ok().flatten())
             .map(|v| v.into());
@@ -508,10 +917,19 @@ impl Pregel {
             .and_then(|kw| kw.get_item("input_channels").ok().flatten())
             .map(|v| v.into());
 
+        let validate_input = kwargs
+            .and_then(|kw| kw.get_item("validate_input").ok().flatten())
+            .filter(|v| !v.is_none())
+            .map(|v| v.into());
+
         let checkpointer = kwargs
             .and_then(|kw| kw.get_item("checkpointer").ok().flatten())
             .map(|v| v.into());
 
+        let store = kwargs
+            .and_then(|kw| kw.get_item("store").ok().flatten())
+            .map(|v| v.into());
+
         let builder = kwargs
             .and_then(|kw| kw.get_item("builder").ok().flatten())
             .map(|v| v.into());
@@ -532,15 +950,29 @@ impl Pregel {
             .and_then(|v| v.extract::<HashMap<String, PyObject>>().ok())
             .unwrap_or_default();
 
+        let channel_aliases =
+            match kwargs.and_then(|kw| kw.get_item("channel_aliases").ok().flatten()) {
+                Some(aliases) if !aliases.is_none() => parse_channel_aliases(aliases, &channels)?,
+                _ => ChannelAliases::new(),
+            };
+
         Ok(Pregel {
             nodes,
             channels,
             stream_mode,
+            stream_eager,
+            step_timeout,
+            max_channel_bytes,
+            max_state_bytes,
             output_channels,
             input_channels,
+            validate_input,
             checkpointer,
+            store,
             builder,
             config_type,
+            channel_aliases,
+            last_run_stats: Mutex::default(),
         })
     }
 
@@ -576,6 +1008,8 @@ impl Pregel {
         durability: Option<PyObject>,
         debug: Option<PyObject>,
     ) -> PyResult<PyObject> {
+        self.check_input(py, &input)?;
+
         // NEW: Try to use Rust PregelLoop if we have the right structure
         if !self.nodes.is_empty() {
             // Check if nodes look like PregelNodes (have metadata)
@@ -589,7 +1023,15 @@ impl Pregel {
             };
 
             if use_rust_loop {
-                return self.invoke_with_rust_loop(py, input, interrupt_before, interrupt_after);
+                return self.invoke_with_rust_loop(
+                    py,
+                    input,
+                    config,
+                    context,
+                    interrupt_before,
+                    interrupt_after,
+                    durability,
+                );
             }
         }
 
@@ -776,9 +1218,18 @@ impl Pregel {
     }
 
     /// Stream graph steps for a single input
+    ///
+    /// Graphs run by the Rust loop return a lazy [`PregelStream`] iterator
+    /// that can be stopped with `cancel()`, or used as a context manager
+    /// that stops the run and releases it on exit. With `tags`, node events
+    /// are only streamed from nodes carrying one of the tags. With
+    /// `subgraphs=True`, events of graphs run by nodes are streamed too and
+    /// every item is prefixed with its namespace: a tuple of
+    /// `"node:task_id"` segments, one per level of nesting, and `()` for
+    /// events of this graph.
     #[allow(clippy::too_many_arguments, unused_variables)]
     fn stream(
-        &self,
+        slf: PyRef<'_, Self>,
         py: Python,
         input: PyObject,
         config: Option<PyObject>,
@@ -790,10 +1241,13 @@ impl Pregel {
         durability: Option<PyObject>,
         subgraphs: Option<bool>,
         debug: Option<bool>,
+        tags: Option<Vec<String>>,
     ) -> PyResult<PyObject> {
+        slf.check_input(py, &input)?;
+
         // NEW: Try to use Rust PregelLoop if we have the right structure
-        if !self.nodes.is_empty() {
-            let first_node = self.nodes.values().next();
+        if !slf.nodes.is_empty() {
+            let first_node = slf.nodes.values().next();
             let use_rust_loop = if let Some(node_obj) = first_node {
                 node_obj.as_ref(py).hasattr("triggers").unwrap_or(false)
                     || node_obj.as_ref(py).hasattr("channels").unwrap_or(false)
@@ -802,7 +1256,19 @@ impl Pregel {
             };
 
             if use_rust_loop {
-                return self.stream_with_rust_loop(py, input, interrupt_before, interrupt_after);
+                return Self::stream_with_rust_loop(
+                    slf,
+                    py,
+                    input,
+                    config,
+                    stream_mode,
+                    interrupt_before,
+                    interrupt_after,
+                    durability,
+                    subgraphs.unwrap_or(false),
+                    tags,
+                    context,
+                );
             }
         }
 
@@ -831,9 +1297,13 @@ impl Pregel {
     }
 
     /// Asynchronously stream graph steps for a single input
+    ///
+    /// Graphs run by the Rust loop return a [`PregelAsyncStream`], which
+    /// runs each step in a worker thread and can be used with `async with`
+    /// to stop the run and release it on exit.
     #[allow(clippy::too_many_arguments, unused_variables)]
     fn astream(
-        &self,
+        slf: PyRef<'_, Self>,
         py: Python,
         input: PyObject,
         config: Option<PyObject>,
@@ -845,36 +1315,118 @@ impl Pregel {
         durability: Option<PyObject>,
         subgraphs: Option<bool>,
         debug: Option<bool>,
+        tags: Option<Vec<String>>,
     ) -> PyResult<PyObject> {
-        // In a real implementation, this would async stream the graph execution
-        // For now, we'll return an empty list
-        Ok(PyList::empty(py).into())
+        let stream = Self::stream(
+            slf,
+            py,
+            input,
+            config,
+            context,
+            stream_mode,
+            output,
+            interrupt_before,
+            interrupt_after,
+            durability,
+            subgraphs,
+            debug,
+            tags,
+        )?;
+        match stream.extract::<Py<PregelStream>>(py) {
+            Ok(stream) => Ok(Py::new(py, PregelAsyncStream { stream })?.into_py(py)),
+            // FALLBACK: graphs not run by the Rust loop stream nothing
+            Err(_) => Ok(stream),
+        }
+    }
+
+    /// Summary of the latest run as a dict
+    ///
+    /// Holds `supersteps`, `node_calls` (calls per node), `duration_ms` (up
+    /// to the last committed superstep) and `checkpoints` (checkpoints saved
+    /// through the checkpointer). Reset when a run starts; a streamed run
+    /// is counted up to the step last consumed.
+    fn last_run_stats(&self, py: Python) -> PyResult<PyObject> {
+        let stats = self
+            .last_run_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        Ok(stats.to_dict(py)?.into())
     }
 
     /// Batch invoke the graph with multiple inputs
+    ///
+    /// Inputs run on up to `max_concurrency` worker threads (also read from
+    /// `config["max_concurrency"]`). A worker holds the GIL while it runs an
+    /// input, so runs only overlap while their nodes release it, as blocking
+    /// I/O does. `config` is either shared by all inputs or a list aligned
+    /// with them, and `context` is passed to every run. Results are aligned with `inputs`; a failed input
+    /// raises its exception, or with `return_exceptions=True` the exception
+    /// object is returned in its place.
+    #[pyo3(signature = (inputs, config=None, context=None, *, max_concurrency=None, return_exceptions=false))]
     fn batch(
-        &self,
-        _py: Python,
+        slf: PyRef<'_, Self>,
+        py: Python,
         inputs: Vec<PyObject>,
-        _config: Option<PyObject>,
-        _context: Option<PyObject>,
+        config: Option<PyObject>,
+        context: Option<PyObject>,
+        max_concurrency: Option<usize>,
+        return_exceptions: bool,
     ) -> PyResult<Vec<PyObject>> {
-        // In a real implementation, this would execute the graph for multiple inputs
-        // For now, we'll just return the inputs
-        Ok(inputs)
+        let configs = batch_configs(py, config, inputs.len())?;
+        let max_concurrency = max_concurrency
+            .or_else(|| {
+                configs
+                    .iter()
+                    .flatten()
+                    .find_map(|cfg| cfg.as_ref(py).get_item("max_concurrency").ok().flatten())
+                    .and_then(|v| v.extract().ok())
+            })
+            .unwrap_or_else(num_cpus::get);
+
+        let pregel: Py<Self> = slf.into();
+        let results = run_batch(py, pregel, inputs, configs, context, max_concurrency);
+
+        results
+            .into_iter()
+            .map(|result| match result {
+                Err(err) if return_exceptions => Ok(err.into_value(py).into()),
+                result => result,
+            })
+            .collect()
     }
 
     /// Asynchronously batch invoke the graph with multiple inputs
+    ///
+    /// Returns a coroutine running [`batch`](Pregel::batch) in a worker thread
+    /// (`asyncio.to_thread`), so the event loop is not blocked.
+    #[pyo3(signature = (inputs, config=None, context=None, *, max_concurrency=None, return_exceptions=false))]
     fn abatch(
-        &self,
-        _py: Python,
+        slf: PyRef<'_, Self>,
+        py: Python,
         inputs: Vec<PyObject>,
-        _config: Option<PyObject>,
-        _context: Option<PyObject>,
-    ) -> PyResult<Vec<PyObject>> {
-        // In a real implementation, this would async execute the graph for multiple inputs
-        // For now, we'll just return the inputs
-        Ok(inputs)
+        config: Option<PyObject>,
+        context: Option<PyObject>,
+        max_concurrency: Option<usize>,
+        return_exceptions: bool,
+    ) -> PyResult<PyObject> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("max_concurrency", max_concurrency)?;
+        kwargs.set_item("return_exceptions", return_exceptions)?;
+        let batch = py.import("functools")?.getattr("partial")?.call(
+            (
+                slf.into_py(py).getattr(py, "batch")?,
+                inputs,
+                config,
+                context,
+            ),
+            Some(kwargs),
+        )?;
+
+        Ok(py
+            .import("asyncio")?
+            .call_method1("to_thread", (batch,))?
+            .into())
     }
 
     /// Get the input schema for the graph
@@ -1190,12 +1742,16 @@ MockSchema
     }
 
     /// Internal: Invoke using Rust PregelLoop
+    #[allow(clippy::too_many_arguments)]
     fn invoke_with_rust_loop(
         &self,
         py: Python,
         input: PyObject,
+        run_config: Option<PyObject>,
+        context: Option<PyObject>,
         interrupt_before: Option<PyObject>,
         interrupt_after: Option<PyObject>,
+        durability: Option<PyObject>,
     ) -> PyResult<PyObject> {
         // 1. Convert Python nodes to PregelNode structures
         let mut pregel_nodes = HashMap::new();
@@ -1213,18 +1769,47 @@ MockSchema
             .and_then(|v| v.extract::<Vec<String>>(py).ok())
             .unwrap_or_default();
 
-        // 3. Create PregelConfig
+        // 3. Create PregelConfig; a subgraph of a streaming parent streams
+        // into the parent task's writer
+        let parent_writer = subgraph_stream_writer(py, run_config.as_ref());
         let config = PregelConfig {
             recursion_limit: 25,
             interrupt_before: interrupt_before_list,
             interrupt_after: interrupt_after_list,
+            durability: resolve_durability(py, durability)?,
+            stream_eager: false,
+            stream_tags: Vec::new(),
+            step_timeout: self.step_timeout.map(Duration::from_secs_f64),
+            run_id: config_run_id(py, run_config.as_ref())?,
+            stream_subgraphs: parent_writer.as_ref().map(|(_, mode)| mode.clone()),
+            force_resume: false,
+            max_channel_bytes: self.max_channel_bytes,
+            max_state_bytes: self.max_state_bytes,
         };
 
         // 4. Create PregelLoop
-        let mut loop_executor = PregelLoop::new(pregel_nodes, self.channels.clone(), config);
+        let mut loop_executor = new_pregel_loop(
+            py,
+            pregel_nodes,
+            self.channels.clone(),
+            config,
+            self.checkpointer.as_ref(),
+            run_config,
+            self.store.as_ref(),
+        )
+        .with_channel_aliases(self.channel_aliases.clone());
+        if let Some(context) = context.filter(|context| !context.is_none(py)) {
+            loop_executor = loop_executor.with_context(context);
+        }
 
         // 5. Execute
-        let result = loop_executor.invoke(py, input)?;
+        self.set_last_run_stats(RunStats::default());
+        let result = match &parent_writer {
+            Some((writer, mode)) => self.stream_into(py, &mut loop_executor, input, writer, mode),
+            None => loop_executor.invoke(py, input),
+        };
+        self.set_last_run_stats(loop_executor.run_stats());
+        let result = result?;
 
         // 6. Format output based on output_channels
         self.format_output(py, result)
@@ -1281,16 +1866,24 @@ MockSchema
     }
 
     /// Internal: Stream using Rust PregelLoop
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (input, run_config=None, stream_mode=None, interrupt_before=None, interrupt_after=None, durability=None, subgraphs=false, tags=None, context=None))]
     fn stream_with_rust_loop(
-        &self,
+        slf: PyRef<'_, Self>,
         py: Python,
         input: PyObject,
+        run_config: Option<PyObject>,
+        stream_mode: Option<PyObject>,
         interrupt_before: Option<PyObject>,
         interrupt_after: Option<PyObject>,
+        durability: Option<PyObject>,
+        subgraphs: bool,
+        tags: Option<Vec<String>>,
+        context: Option<PyObject>,
     ) -> PyResult<PyObject> {
         // 1. Convert Python nodes to PregelNode structures
         let mut pregel_nodes = HashMap::new();
-        for (node_name, node_obj) in &self.nodes {
+        for (node_name, node_obj) in &slf.nodes {
             let pregel_node = extract_pregel_node(py, node_name, node_obj)?;
             pregel_nodes.insert(node_name.clone(), pregel_node);
         }
@@ -1305,26 +1898,379 @@ MockSchema
             .unwrap_or_default();
 
         // 3. Create PregelConfig
+        let mode = resolve_stream_mode(py, stream_mode, &slf.stream_mode)?;
         let config = PregelConfig {
             recursion_limit: 25,
             interrupt_before: interrupt_before_list,
             interrupt_after: interrupt_after_list,
+            durability: resolve_durability(py, durability)?,
+            stream_eager: slf.stream_eager,
+            stream_tags: tags.unwrap_or_default(),
+            step_timeout: slf.step_timeout.map(Duration::from_secs_f64),
+            run_id: config_run_id(py, run_config.as_ref())?,
+            stream_subgraphs: subgraphs.then(|| mode.clone()),
+            force_resume: false,
+            max_channel_bytes: slf.max_channel_bytes,
+            max_state_bytes: slf.max_state_bytes,
         };
 
         // 4. Create PregelLoop
-        let mut loop_executor = PregelLoop::new(pregel_nodes, self.channels.clone(), config);
+        let mut loop_executor = new_pregel_loop(
+            py,
+            pregel_nodes,
+            slf.channels.clone(),
+            config,
+            slf.checkpointer.as_ref(),
+            run_config,
+            slf.store.as_ref(),
+        )
+        .with_channel_aliases(slf.channel_aliases.clone());
+        if let Some(context) = context.filter(|context| !context.is_none(py)) {
+            loop_executor = loop_executor.with_context(context);
+        }
+
+        // 5. Write the input; steps run as the returned iterator is consumed
+        slf.set_last_run_stats(RunStats::default());
+        loop_executor.initialize_input(py, input)?;
+
+        let stream = PregelStream {
+            cancel: loop_executor.cancellation_token(),
+            executor: Some(loop_executor),
+            pregel: slf.into(),
+            mode,
+            subgraphs,
+            buffered: VecDeque::new(),
+            gate: Arc::default(),
+        };
+        Ok(Py::new(py, stream)?.into_py(py))
+    }
+}
+
+impl Pregel {
+    fn set_last_run_stats(&self, stats: RunStats) {
+        *self
+            .last_run_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = stats;
+    }
+
+    /// Run the graph as a subgraph, forwarding every chunk streamed in
+    /// `mode` to the parent task's `writer`, and return the final state
+    ///
+    /// Values chunks of this graph are formatted like its output first.
+    fn stream_into(
+        &self,
+        py: Python,
+        loop_executor: &mut PregelLoop,
+        input: PyObject,
+        writer: &Py<StreamWriter>,
+        mode: &StreamMode,
+    ) -> PyResult<PyObject> {
+        loop_executor.initialize_input(py, input)?;
+        while let Some(chunks) = loop_executor.stream_step(py, mode)? {
+            for mut chunk in chunks {
+                if chunk.mode == StreamMode::Values && chunk.namespace.is_empty() {
+                    chunk.data = self.format_output(py, chunk.data)?;
+                }
+                writer.borrow(py).forward(chunk)?;
+            }
+        }
+        loop_executor.get_current_state(py)
+    }
+
+    /// Run `validate_input` on the input entries naming a channel
+    fn check_input(&self, py: Python, input: &PyObject) -> PyResult<()> {
+        let Some(validate_input) = &self.validate_input else {
+            return Ok(());
+        };
+        let writes = PyDict::new(py);
+        if let Ok(input) = input.downcast::<PyDict>(py) {
+            for (key, value) in input.iter() {
+                if self.channels.contains_key(&key.extract::<String>()?) {
+                    writes.set_item(key, value)?;
+                }
+            }
+        }
+        Ok(InputValidator::Python(validate_input.clone_ref(py)).check(py, &writes.into())?)
+    }
+}
+
+/// Iterator over the chunks of a streaming run, returned by `Pregel.stream`
+///
+/// Each `next()` runs supersteps until a chunk is available, so work only
+/// happens as the stream is consumed.
+///
+/// `cancel()` is thread-safe: it only sets a flag and may be called from any
+/// thread, including from a node while the stream is being advanced. The run
+/// stops at the next step boundary: the writes of the step in progress are
+/// discarded, the last committed checkpoint is persisted, and the pending
+/// `next()` raises `GraphCancelled`. The iterator itself should be consumed
+/// from one thread at a time.
+///
+/// Used as a context manager (`with graph.stream(...) as s:`), leaving the
+/// block calls `close()`, so a consumer stopping early does not leave the
+/// run holding its checkpointer or in-flight checkpoint writes.
+#[pyclass]
+pub struct PregelStream {
+    pregel: Py<Pregel>,
+    /// Loop driving the run; `None` once it has finished or failed
+    executor: Option<PregelLoop>,
+    mode: StreamMode,
+    /// Whether items are prefixed with their subgraph namespace
+    subgraphs: bool,
+    /// Formatted chunks of the last step not yet yielded
+    buffered: VecDeque<PyObject>,
+    cancel: CancellationToken,
+    /// Held while a step runs, so `close()` can wait for it
+    gate: Arc<StepGate>,
+}
+
+/// Flag held while a stream step runs
+///
+/// Waiting releases the GIL, which the step needs to make progress.
+#[derive(Default)]
+struct StepGate {
+    busy: Mutex<bool>,
+    idle: Condvar,
+}
+
+impl StepGate {
+    /// Wait until no step runs, then hold the gate until the guard drops
+    fn enter(&self, py: Python) -> StepGuard<'_> {
+        py.allow_threads(|| {
+            let mut busy = self.busy.lock().unwrap_or_else(PoisonError::into_inner);
+            while *busy {
+                busy = self.idle.wait(busy).unwrap_or_else(PoisonError::into_inner);
+            }
+            *busy = true;
+        });
+        StepGuard(self)
+    }
+
+    fn is_busy(&self) -> bool {
+        *self.busy.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct StepGuard<'a>(&'a StepGate);
+
+impl Drop for StepGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.busy.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        self.0.idle.notify_all();
+    }
+}
+
+#[pymethods]
+impl PregelStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(slf: &PyCell<Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+        let gate = slf.borrow().gate.clone();
+        loop {
+            if let Some(item) = slf.borrow_mut().buffered.pop_front() {
+                return Ok(Some(item));
+            }
+            let _running = gate.enter(py);
+            let (mut executor, mode, subgraphs) = {
+                let mut this = slf.borrow_mut();
+                let Some(executor) = this.executor.take() else {
+                    return Ok(None);
+                };
+                (executor, this.mode.clone(), this.subgraphs)
+            };
+
+            // No borrow is held while the step runs, so cancel() stays callable
+            let step = executor.stream_step(py, &mode);
+            let pregel = slf.borrow().pregel.clone_ref(py);
+            pregel.borrow(py).set_last_run_stats(executor.run_stats());
+            let Some(chunks) = step? else {
+                return Ok(None);
+            };
+
+            let mut this = slf.borrow_mut();
+            for chunk in chunks {
+                let item = format_stream_chunk(py, &pregel.borrow(py), &mode, subgraphs, chunk)?;
+                this.buffered.push_back(item);
+            }
+            this.executor = Some(executor);
+        }
+    }
+
+    /// Stop the run at the next step boundary
+    fn cancel(&self) {
+        self.cancel.cancel();
+    }
 
-        // 5. Execute with streaming
-        let results = loop_executor.stream(py, input)?;
+    /// Whether `cancel()` has been called
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
 
-        // 6. Format each result and return as list
-        let formatted_results = PyList::empty(py);
-        for result in results {
-            let formatted = self.format_output(py, result)?;
-            formatted_results.append(formatted)?;
+    /// Stop the run if it has not finished and release it
+    ///
+    /// Waits for a step in progress on another thread to stop, then
+    /// persists the last committed checkpoint, waits for in-flight
+    /// checkpoint writes and finishes the channels, as a cancelled run
+    /// does. Later `next()` calls end the iteration. Must not be called from
+    /// a node of the run itself; use `cancel()` there.
+    fn close(slf: &PyCell<Self>) {
+        let py = slf.py();
+        let gate = {
+            let this = slf.borrow();
+            if this.executor.is_some() || this.gate.is_busy() {
+                this.cancel.cancel();
+            }
+            this.gate.clone()
+        };
+        let _closing = gate.enter(py);
+        let (executor, mode) = {
+            let mut this = slf.borrow_mut();
+            this.buffered.clear();
+            (this.executor.take(), this.mode.clone())
+        };
+        if let Some(mut executor) = executor {
+            // The run is cancelled, so this only winds it down; the
+            // resulting GraphCancelled is expected
+            let _ = executor.stream_step(py, &mode);
+            let pregel = slf.borrow().pregel.clone_ref(py);
+            pregel.borrow(py).set_last_run_stats(executor.run_stats());
         }
+    }
 
-        Ok(formatted_results.into())
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Close the stream; exceptions raised in the block propagate
+    fn __exit__(
+        slf: &PyCell<Self>,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> bool {
+        Self::close(slf);
+        false
+    }
+}
+
+/// Async iterator over the chunks of a streaming run, returned by
+/// `Pregel.astream`
+///
+/// Wraps a [`PregelStream`]: each step runs in a worker thread
+/// (`asyncio.to_thread`), so the event loop is not blocked. Used with
+/// `async with`, leaving the block cancels the run and waits for the step
+/// in progress to stop before releasing it, so abandoning the stream early
+/// leaves no work running in the background.
+#[pyclass]
+pub struct PregelAsyncStream {
+    stream: Py<PregelStream>,
+}
+
+impl PregelAsyncStream {
+    /// Run `call` on the wrapped stream in a worker thread, as an awaitable
+    fn to_thread<F>(&self, py: Python, call: F) -> PyResult<PyObject>
+    where
+        F: Fn(&PyCell<PregelStream>) -> PyResult<PyObject> + Send + 'static,
+    {
+        let stream = self.stream.clone_ref(py);
+        let func = pyo3::types::PyCFunction::new_closure(py, None, None, move |args, _| {
+            call(stream.as_ref(args.py()))
+        })?;
+        Ok(py
+            .import("asyncio")?
+            .getattr("to_thread")?
+            .call1((func,))?
+            .into())
+    }
+}
+
+#[pymethods]
+impl PregelAsyncStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Awaitable resolving to the next chunk, or raising
+    /// `StopAsyncIteration` once the run is over
+    fn __anext__(&self, py: Python) -> PyResult<Option<PyObject>> {
+        self.to_thread(py, |stream| {
+            PregelStream::__next__(stream)?
+                .ok_or_else(|| pyo3::exceptions::PyStopAsyncIteration::new_err(()))
+        })
+        .map(Some)
+    }
+
+    /// Stop the run at the next step boundary
+    fn cancel(&self, py: Python) {
+        self.stream.borrow(py).cancel();
+    }
+
+    /// Whether `cancel()` has been called
+    #[getter]
+    fn cancelled(&self, py: Python) -> bool {
+        self.stream.borrow(py).cancelled()
+    }
+
+    /// Awaitable stopping the run if it has not finished and releasing it;
+    /// see `PregelStream.close`
+    fn aclose(&self, py: Python) -> PyResult<PyObject> {
+        self.to_thread(py, |stream| {
+            PregelStream::close(stream);
+            Ok(stream.py().None())
+        })
+    }
+
+    fn __aenter__(slf: PyRef<'_, Self>, py: Python) -> PyResult<PyObject> {
+        Ok(py
+            .import("asyncio")?
+            .getattr("sleep")?
+            .call1((0, slf))?
+            .into())
+    }
+
+    /// Close the stream; exceptions raised in the block propagate
+    fn __aexit__(
+        &self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<PyObject> {
+        self.aclose(py)
+    }
+}
+
+/// Format a chunk for Python; combined modes yield `(mode, data)` tuples,
+/// and with `subgraphs` every item is prefixed with its namespace tuple
+fn format_stream_chunk(
+    py: Python,
+    pregel: &Pregel,
+    mode: &StreamMode,
+    subgraphs: bool,
+    chunk: StreamChunk,
+) -> PyResult<PyObject> {
+    let data = match chunk.mode {
+        // Subgraphs format their own values before forwarding them
+        StreamMode::Values if chunk.namespace.is_empty() => pregel.format_output(py, chunk.data)?,
+        _ => chunk.data,
+    };
+    let multiple = matches!(mode, StreamMode::Multiple(_));
+    if subgraphs {
+        let namespace = PyTuple::new(py, &chunk.namespace);
+        if multiple {
+            Ok((namespace, chunk.mode.to_str(), data).into_py(py))
+        } else {
+            Ok((namespace, data).into_py(py))
+        }
+    } else if multiple {
+        Ok((chunk.mode.to_str(), data).into_py(py))
+    } else {
+        Ok(data)
     }
 }
 
@@ -1337,6 +2283,27 @@ fn fast_langgraph(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<Pregel>()?;
     m.add_class::<GraphExecutor>()?;
     m.add_class::<OutputConfig>()?;
+    m.add_class::<StreamWriter>()?;
+    m.add_class::<PregelStream>()?;
+    m.add_class::<PregelAsyncStream>()?;
+    m.add(
+        "GraphCancelled",
+        _py.get_type::<crate::errors::GraphCancelled>(),
+    )?;
+    m.add("GuardFailed", _py.get_type::<crate::errors::GuardFailed>())?;
+    m.add(
+        "GraphInterrupted",
+        _py.get_type::<crate::errors::GraphInterrupted>(),
+    )?;
+    m.add(
+        "NodeInterrupt",
+        _py.get_type::<crate::errors::NodeInterrupt>(),
+    )?;
+    m.add_function(wrap_pyfunction!(crate::pregel_node::interrupt, m)?)?;
+    m.add_class::<crate::core::MetricsSnapshot>()?;
+    m.add_class::<crate::core::NodeMetrics>()?;
+    m.add_class::<crate::core::CacheStats>()?;
+    m.add_class::<crate::core::PartialUpdate>()?;
 
     // Register hybrid acceleration classes
     crate::hybrid::register_hybrid_classes(m)?;
@@ -1359,6 +2326,9 @@ fn fast_langgraph(_py: Python, m: &PyModule) -> PyResult<()> {
     // Register function cache
     crate::function_cache::register_function_cache(_py, m)?;
 
+    // Register long-term store
+    crate::store::register_store(_py, m)?;
+
     Ok(())
 }
 
@@ -1399,3 +2369,209 @@ impl GraphExecutor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::{Channel, TopicChannel};
+    use serde_json::json;
+
+    #[test]
+    fn test_coercion_round_trip() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(
+                r#"
+import enum
+class Color(enum.IntEnum):
+    RED = 1
+plain = {"n": 1, "big": 2**64 - 1, "x": 0.5, "ok": True, "tags": ["a", None]}
+custom = {"color": Color.RED}
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let get = |name: &str| locals.get_item(name).unwrap().unwrap();
+
+            let native = py_to_value(get("plain")).unwrap();
+            assert_eq!(
+                native,
+                json!({"n": 1, "big": u64::MAX, "x": 0.5, "ok": true, "tags": ["a", null]})
+            );
+            assert!(value_to_py(py, &native)
+                .as_ref(py)
+                .eq(get("plain"))
+                .unwrap());
+
+            // Custom types keep the whole container opaque
+            assert!(py_to_value(get("custom")).is_none());
+            assert!(py_to_value(py.eval("float('nan')", None, None).unwrap()).is_none());
+            assert!(py_to_value(py.eval("2**70", None, None).unwrap()).is_none());
+
+            // Native values feed Rust channels directly
+            let mut topic = TopicChannel::<serde_json::Value>::new(true);
+            let writes = py.eval("[1, 'two', [3]]", None, None).unwrap();
+            let values: Vec<_> = writes
+                .iter()
+                .unwrap()
+                .map(|w| py_to_value(w.unwrap()).unwrap())
+                .collect();
+            assert!(topic.update(values).unwrap());
+            assert_eq!(*topic.get_values(), [json!(1), json!("two"), json!([3])]);
+        });
+    }
+
+    #[test]
+    fn test_stream_context_managers_stop_the_run() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            locals.set_item("Pregel", py.get_type::<Pregel>()).unwrap();
+            py.run(
+                r#"
+import asyncio
+class Chan:
+    def __init__(self):
+        self.value = None
+    def update(self, values):
+        for v in values:
+            self.value = v
+        return bool(values)
+    def get(self):
+        if self.value is None:
+            raise Exception("empty")
+        return self.value
+class Node:
+    def __init__(self, trigger, out):
+        self.triggers = [trigger]
+        self.channels = [out]
+    def __call__(self, x):
+        calls.append(self.channels[0])
+        return self.channels[0]
+calls = []
+def build():
+    return Pregel(
+        nodes={"a": Node("input", "mid"), "b": Node("mid", "out")},
+        channels={name: Chan() for name in ["input", "mid", "out"]},
+    )
+
+# Leaving the block early stops the run before b
+with build().stream({"input": 1}) as s:
+    next(s)
+assert s.cancelled and list(s) == [] and calls == ["mid"], calls
+
+# A stream consumed to the end is not cancelled on exit
+calls.clear()
+graph = build()
+with graph.stream({"input": 1}) as s:
+    list(s)
+assert not s.cancelled and calls == ["mid", "out"], calls
+stats = graph.last_run_stats()
+assert stats["supersteps"] == 2 and stats["node_calls"] == {"a": 1, "b": 1}, stats
+assert stats["checkpoints"] == 0 and stats["duration_ms"] >= 0, stats
+graph.stream({"input": 1})
+assert graph.last_run_stats()["supersteps"] == 0
+
+async def consume_first():
+    async with build().astream({"input": 1}) as s:
+        async for _ in s:
+            break
+    return s
+calls.clear()
+s = asyncio.run(consume_first())
+assert s.cancelled and calls == ["mid"], calls
+
+# `async for` yields the same chunks as the sync stream, then stops
+async def consume_all():
+    return [chunk async for chunk in build().astream({"input": 1})]
+calls.clear()
+chunks = asyncio.run(consume_all())
+assert chunks == list(build().stream({"input": 1})) and len(chunks) == 2, chunks
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_stream_subgraphs_with_namespaces() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            locals.set_item("Pregel", py.get_type::<Pregel>()).unwrap();
+            py.run(
+                r#"
+class Chan:
+    def __init__(self):
+        self.value = None
+    def update(self, values):
+        for v in values:
+            self.value = v
+        return bool(values)
+    def get(self):
+        if self.value is None:
+            raise Exception("empty")
+        return self.value
+class Node:
+    def __init__(self, trigger, out, value):
+        self.triggers = [trigger]
+        self.channels = [out]
+        self.value = value
+    def __call__(self, x):
+        return self.value
+class Subgraph:
+    def __init__(self, graph, trigger, out, key):
+        self.graph = graph
+        self.key = key
+        self.triggers = [trigger]
+        self.channels = [out]
+    def invoke(self, x, config=None):
+        return self.graph.invoke({"input": x}, config)[self.key]
+def graph(nodes, channels):
+    return Pregel(nodes=nodes, channels={name: Chan() for name in channels})
+
+inner = graph({"a": Node("input", "mid", 2), "b": Node("mid", "out", 3)}, ["input", "mid", "out"])
+outer = graph(
+    {"sub": Subgraph(inner, "input", "result", "out"), "after": Node("result", "done", 4)},
+    ["input", "result", "done"],
+)
+
+# Without subgraphs only the graph's own events are streamed
+items = list(outer.stream({"input": 1}, stream_mode="updates"))
+assert items == [{"sub": {"result": 3}}, {"after": {"done": 4}}], items
+
+# Subgraph events come first, under the task's namespace
+items = list(outer.stream({"input": 1}, stream_mode="updates", subgraphs=True))
+ns = items[0][0]
+assert len(ns) == 1 and ns[0].startswith("sub:"), items
+assert items == [
+    (ns, {"a": {"mid": 2}}),
+    (ns, {"b": {"out": 3}}),
+    ((), {"sub": {"result": 3}}),
+    ((), {"after": {"done": 4}}),
+], items
+
+# The namespace has one segment per level of nesting
+top = graph({"outer": Subgraph(outer, "input", "final", "done")}, ["input", "final"])
+items = list(top.stream({"input": 1}, stream_mode=["values", "updates"], subgraphs=True))
+namespaces = [item[0] for item in items]
+assert all(len(item) == 3 for item in items), items
+assert [len(ns) for ns in namespaces if ns] == [2] * 4 + [1] * 4, namespaces
+assert all(ns[0].startswith("outer:") for ns in namespaces if ns), namespaces
+assert all(ns[1].startswith("sub:") for ns in namespaces if len(ns) == 2), namespaces
+assert items[-1] == ((), "values", {"input": 1, "final": 4}), items
+"#,
+                Some(locals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use crate::checkpoint::{RetentionPolicy, INTERRUPT};
 use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyDict};
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,86 @@ pub struct CheckpointData {
 
     /// Current step number
     pub step: usize,
+
+    /// Whether the checkpoint was taken at an interrupt
+    #[serde(default)]
+    pub interrupt: bool,
+}
+
+/// Checkpoint as stored by [`RustCheckpointer`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum StoredData {
+    Full(CheckpointData),
+    /// `data` with only the channel values changed since `parent_id`
+    Diff {
+        parent_id: String,
+        data: CheckpointData,
+        /// Channels of the parent missing from the checkpoint, sorted
+        removed: Vec<String>,
+    },
+}
+
+impl StoredData {
+    /// Store `data` as its change from `parent`, the checkpoint `parent_id`
+    fn diff(parent_id: String, parent: &CheckpointData, mut data: CheckpointData) -> Self {
+        let mut removed: Vec<String> = parent
+            .channel_values
+            .keys()
+            .filter(|name| !data.channel_values.contains_key(*name))
+            .cloned()
+            .collect();
+        removed.sort();
+        data.channel_values
+            .retain(|name, value| parent.channel_values.get(name) != Some(value));
+        StoredData::Diff {
+            parent_id,
+            data,
+            removed,
+        }
+    }
+
+    fn parent_id(&self) -> Option<&str> {
+        match self {
+            StoredData::Full(_) => None,
+            StoredData::Diff { parent_id, .. } => Some(parent_id),
+        }
+    }
+
+    fn encode(&self) -> PyResult<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|e| {
+            pyo3::exceptions::PyValueError::new_err(format!("Serialization error: {}", e))
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> PyResult<Self> {
+        rmp_serde::from_slice(bytes).map_err(|e| {
+            pyo3::exceptions::PyValueError::new_err(format!("Deserialization error: {}", e))
+        })
+    }
+}
+
+/// Whether a Python checkpoint dict was taken at an interrupt
+///
+/// True when its `interrupts` are non-empty, as saved by the Pregel loop,
+/// or when one of its `(task_id, channel, value)` `pending_writes` is an
+/// [`INTERRUPT`] write.
+pub(crate) fn taken_at_interrupt(checkpoint: &PyDict) -> PyResult<bool> {
+    if let Some(interrupts) = checkpoint.get_item("interrupts")? {
+        if !interrupts.is_none() && interrupts.len()? > 0 {
+            return Ok(true);
+        }
+    }
+    if let Some(writes) = checkpoint.get_item("pending_writes")? {
+        if !writes.is_none() {
+            for write in writes.iter()? {
+                let (_, channel, _): (&PyAny, String, &PyAny) = write?.extract()?;
+                if channel == INTERRUPT {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
 }
 
 /// High-performance checkpoint implementation using MessagePack
@@ -27,14 +108,35 @@ pub struct CheckpointData {
 pub struct RustCheckpointer {
     /// In-memory checkpoint storage (thread_id -> checkpoint_id -> data)
     checkpoints: HashMap<String, HashMap<String, Vec<u8>>>,
+    /// Checkpoint IDs of each thread in the order they were first saved,
+    /// with whether each was taken at an interrupt
+    history: HashMap<String, Vec<(String, bool)>>,
+    /// Policy applied to a thread after each `put`
+    retention: Option<RetentionPolicy>,
+    /// Store each checkpoint as a diff against the thread's previous one
+    incremental: bool,
 }
 
 #[pymethods]
 impl RustCheckpointer {
+    /// Create a checkpointer
+    ///
+    /// Args:
+    ///     keep_last: Keep only this many of the newest checkpoints per thread
+    ///     keep_interrupts: Also keep checkpoints taken at an interrupt
+    ///     incremental: Store only the channels changed since the thread's
+    ///         previous checkpoint; loading replays the chain of changes
     #[new]
-    fn new() -> Self {
+    #[pyo3(signature = (keep_last=None, keep_interrupts=true, incremental=false))]
+    fn new(keep_last: Option<usize>, keep_interrupts: bool, incremental: bool) -> Self {
         RustCheckpointer {
             checkpoints: HashMap::new(),
+            history: HashMap::new(),
+            retention: keep_last.map(|keep_last| RetentionPolicy {
+                keep_last,
+                keep_interrupts,
+            }),
+            incremental,
         }
     }
 
@@ -56,6 +158,7 @@ impl RustCheckpointer {
     ) -> PyResult<bool> {
         // Extract checkpoint data
         let channel_values = self.extract_channel_values(py, checkpoint)?;
+        let interrupt = taken_at_interrupt(checkpoint)?;
         let channel_versions = self.extract_versions(checkpoint, "channel_versions")?;
         let versions_seen = self.extract_versions_seen(checkpoint)?;
         let step = checkpoint
@@ -70,19 +173,48 @@ impl RustCheckpointer {
             versions_seen,
             pending_writes: Vec::new(),
             step,
+            interrupt,
+        };
+
+        // A replaced checkpoint may be the parent of later diffs
+        self.compact_children(&thread_id, &checkpoint_id)?;
+        let parent_id = self
+            .history
+            .get(&thread_id)
+            .and_then(|history| history.last())
+            .map(|(id, _)| id.clone())
+            .filter(|id| self.incremental && *id != checkpoint_id);
+        let stored = match parent_id {
+            Some(parent_id) => {
+                let parent = self.load(&thread_id, &parent_id)?.ok_or_else(|| {
+                    pyo3::exceptions::PyKeyError::new_err(format!(
+                        "Parent checkpoint '{}' not found",
+                        parent_id
+                    ))
+                })?;
+                StoredData::diff(parent_id, &parent, checkpoint_data)
+            }
+            None => StoredData::Full(checkpoint_data),
         };
 
         // Serialize using MessagePack (much faster than pickle!)
-        let serialized = rmp_serde::to_vec(&checkpoint_data).map_err(|e| {
-            pyo3::exceptions::PyValueError::new_err(format!("Serialization error: {}", e))
-        })?;
+        let serialized = stored.encode()?;
 
         // Store in memory
+        let history = self.history.entry(thread_id.clone()).or_default();
+        match history.iter_mut().find(|(id, _)| *id == checkpoint_id) {
+            Some(entry) => entry.1 = interrupt,
+            None => history.push((checkpoint_id.clone(), interrupt)),
+        }
         self.checkpoints
-            .entry(thread_id)
+            .entry(thread_id.clone())
             .or_default()
             .insert(checkpoint_id, serialized);
 
+        if let Some(policy) = self.retention {
+            self.prune_thread(&thread_id, policy);
+        }
+
         Ok(true)
     }
 
@@ -100,20 +232,10 @@ impl RustCheckpointer {
         thread_id: String,
         checkpoint_id: String,
     ) -> PyResult<Option<PyObject>> {
-        // Retrieve from storage
-        let serialized = match self.checkpoints.get(&thread_id) {
-            Some(thread_checkpoints) => match thread_checkpoints.get(&checkpoint_id) {
-                Some(data) => data,
-                None => return Ok(None),
-            },
-            None => return Ok(None),
+        let Some(checkpoint_data) = self.load(&thread_id, &checkpoint_id)? else {
+            return Ok(None);
         };
 
-        // Deserialize using MessagePack
-        let checkpoint_data: CheckpointData = rmp_serde::from_slice(serialized).map_err(|e| {
-            pyo3::exceptions::PyValueError::new_err(format!("Deserialization error: {}", e))
-        })?;
-
         // Convert back to Python dict
         let result = self.checkpoint_data_to_py(py, &checkpoint_data)?;
 
@@ -129,18 +251,46 @@ impl RustCheckpointer {
     }
 
     /// Delete a checkpoint
-    fn delete(&mut self, thread_id: String, checkpoint_id: String) -> bool {
-        self.checkpoints
+    ///
+    /// Checkpoints stored as diffs against it are stored in full first.
+    fn delete(&mut self, thread_id: String, checkpoint_id: String) -> PyResult<bool> {
+        self.compact_children(&thread_id, &checkpoint_id)?;
+        if let Some(history) = self.history.get_mut(&thread_id) {
+            history.retain(|(id, _)| *id != checkpoint_id);
+        }
+        Ok(self
+            .checkpoints
             .get_mut(&thread_id)
             .and_then(|thread_checkpoints| thread_checkpoints.remove(&checkpoint_id))
-            .is_some()
+            .is_some())
     }
 
     /// Clear all checkpoints for a thread
     fn clear_thread(&mut self, thread_id: String) -> bool {
+        self.history.remove(&thread_id);
         self.checkpoints.remove(&thread_id).is_some()
     }
 
+    /// Delete old checkpoints of a thread
+    ///
+    /// Args:
+    ///     thread_id: Thread/conversation identifier
+    ///     keep_last: Number of the newest checkpoints to keep
+    ///     keep_interrupts: Also keep checkpoints taken at an interrupt
+    ///
+    /// Returns:
+    ///     IDs of the deleted checkpoints, oldest first
+    #[pyo3(signature = (thread_id, keep_last, keep_interrupts=true))]
+    fn prune(&mut self, thread_id: String, keep_last: usize, keep_interrupts: bool) -> Vec<String> {
+        self.prune_thread(
+            &thread_id,
+            RetentionPolicy {
+                keep_last,
+                keep_interrupts,
+            },
+        )
+    }
+
     /// Get statistics about stored checkpoints
     fn stats(&self) -> HashMap<String, usize> {
         let mut stats = HashMap::new();
@@ -163,6 +313,111 @@ impl RustCheckpointer {
 }
 
 impl RustCheckpointer {
+    /// Delete the checkpoints of `thread_id` that `policy` does not retain
+    ///
+    /// Parents of retained diffs are kept so every retained checkpoint can
+    /// still be loaded.
+    fn prune_thread(&mut self, thread_id: &str, policy: RetentionPolicy) -> Vec<String> {
+        let parents: HashMap<String, String> = self
+            .checkpoints
+            .get(thread_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(id, bytes)| {
+                let stored = StoredData::decode(bytes).ok()?;
+                Some((id.clone(), stored.parent_id()?.to_string()))
+            })
+            .collect();
+        let Some(history) = self.history.get_mut(thread_id) else {
+            return Vec::new();
+        };
+        let ids: Vec<String> = history.iter().map(|(id, _)| id.clone()).collect();
+        let pruned = policy.prunable(
+            &ids,
+            |id| {
+                history
+                    .iter()
+                    .any(|(other, interrupt)| other == id && *interrupt)
+            },
+            |id| parents.get(id).cloned(),
+        );
+        history.retain(|(id, _)| !pruned.contains(id));
+        if let Some(thread_checkpoints) = self.checkpoints.get_mut(thread_id) {
+            for id in &pruned {
+                thread_checkpoints.remove(id);
+            }
+        }
+        pruned
+    }
+
+    /// Load the full checkpoint `checkpoint_id`, applying its chain of diffs
+    fn load(&self, thread_id: &str, checkpoint_id: &str) -> PyResult<Option<CheckpointData>> {
+        let Some(thread_checkpoints) = self.checkpoints.get(thread_id) else {
+            return Ok(None);
+        };
+        let mut diffs = Vec::new();
+        let mut current = checkpoint_id.to_string();
+        let base = loop {
+            let Some(bytes) = thread_checkpoints.get(&current) else {
+                if diffs.is_empty() {
+                    return Ok(None);
+                }
+                return Err(pyo3::exceptions::PyKeyError::new_err(format!(
+                    "Parent checkpoint '{}' not found",
+                    current
+                )));
+            };
+            match StoredData::decode(bytes)? {
+                StoredData::Full(data) => break data,
+                StoredData::Diff {
+                    parent_id,
+                    data,
+                    removed,
+                } => {
+                    current = parent_id;
+                    diffs.push((data, removed));
+                }
+            }
+        };
+
+        let mut checkpoint_data = base;
+        for (data, removed) in diffs.into_iter().rev() {
+            let mut channel_values = checkpoint_data.channel_values;
+            for name in &removed {
+                channel_values.remove(name);
+            }
+            channel_values.extend(data.channel_values);
+            checkpoint_data = CheckpointData {
+                channel_values,
+                ..data
+            };
+        }
+        Ok(Some(checkpoint_data))
+    }
+
+    /// Store in full every checkpoint of `thread_id` kept as a diff against
+    /// `checkpoint_id`, so it no longer depends on it
+    fn compact_children(&mut self, thread_id: &str, checkpoint_id: &str) -> PyResult<()> {
+        let Some(thread_checkpoints) = self.checkpoints.get(thread_id) else {
+            return Ok(());
+        };
+        let mut children = Vec::new();
+        for (id, bytes) in thread_checkpoints {
+            if StoredData::decode(bytes)?.parent_id() == Some(checkpoint_id) {
+                children.push(id.clone());
+            }
+        }
+        for id in children {
+            if let Some(data) = self.load(thread_id, &id)? {
+                let serialized = StoredData::Full(data).encode()?;
+                if let Some(thread_checkpoints) = self.checkpoints.get_mut(thread_id) {
+                    thread_checkpoints.insert(id, serialized);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Extract channel values from Python checkpoint dict
     fn extract_channel_values(
         &self,
@@ -281,3 +536,77 @@ pub fn register_checkpoint(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<RustCheckpointer>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_round_trip() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut full = RustCheckpointer::new(None, true, false);
+            let mut incremental = RustCheckpointer::new(None, true, true);
+            let document = "x".repeat(10_000);
+            for step in 1..=4 {
+                let values = PyDict::new(py);
+                values.set_item("document", &document).unwrap();
+                values.set_item("count", step).unwrap();
+                if step < 3 {
+                    values.set_item("scratch", step).unwrap();
+                }
+                let checkpoint = PyDict::new(py);
+                checkpoint.set_item("channel_values", values).unwrap();
+                checkpoint.set_item("step", step).unwrap();
+                for saver in [&mut full, &mut incremental] {
+                    saver
+                        .put(py, "t1".to_string(), format!("cp{}", step), checkpoint)
+                        .unwrap();
+                }
+            }
+
+            // Only the first checkpoint carries the large channel
+            assert!(incremental.stats()["total_bytes"] * 3 < full.stats()["total_bytes"]);
+
+            let values = |saver: &RustCheckpointer, id: &str| {
+                let checkpoint = saver.get(py, "t1".to_string(), id.to_string()).unwrap();
+                let checkpoint = checkpoint
+                    .unwrap()
+                    .into_ref(py)
+                    .downcast::<PyDict>()
+                    .unwrap();
+                let values: HashMap<String, PyObject> = checkpoint
+                    .get_item("channel_values")
+                    .unwrap()
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                let mut values: Vec<(String, String)> = values
+                    .into_iter()
+                    .map(|(name, value)| (name, value.as_ref(py).str().unwrap().to_string()))
+                    .collect();
+                values.sort();
+                values
+            };
+            for step in 1..=4 {
+                let id = format!("cp{}", step);
+                assert_eq!(values(&incremental, &id), values(&full, &id));
+            }
+
+            // Deleting a parent keeps its children loadable
+            assert!(incremental
+                .delete("t1".to_string(), "cp2".to_string())
+                .unwrap());
+            assert_eq!(values(&incremental, "cp3"), values(&full, "cp3"));
+
+            // Pruning keeps the chain back to a full checkpoint
+            let pruned = incremental.prune("t1".to_string(), 1, false);
+            assert_eq!(pruned, ["cp1"]);
+            let mut kept = incremental.list_checkpoints("t1".to_string());
+            kept.sort();
+            assert_eq!(kept, ["cp3", "cp4"]);
+            assert_eq!(values(&incremental, "cp4"), values(&full, "cp4"));
+        });
+    }
+}
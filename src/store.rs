@@ -0,0 +1,497 @@
+//! Long-term key-value memory shared across threads
+//!
+//! Checkpoints hold the state of a single thread (conversation). A store
+//! holds data that outlives threads, such as user preferences or facts an
+//! agent learned, organised as `namespace / key -> JSON value`. Namespaces
+//! are paths like `["users", "alice", "memories"]`, so related items can be
+//! searched together by prefix.
+//!
+//! With an embedding function, [`InMemoryStore::semantic_search`] ranks items
+//! by meaning rather than exact key, comparing every item against the query
+//! (brute force, no index).
+
+use crate::errors::LangGraphError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+/// A stored value with its location and timestamps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub namespace: Vec<String>,
+    pub key: String,
+    pub value: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Trait for long-term memory stores
+#[async_trait]
+pub trait BaseStore: Send + Sync {
+    /// Fetch the item stored under `namespace` and `key`
+    async fn get(&self, namespace: &[String], key: &str) -> Result<Option<Item>, LangGraphError>;
+
+    /// Store `value` under `namespace` and `key`, replacing any previous value
+    async fn put(
+        &self,
+        namespace: &[String],
+        key: &str,
+        value: Value,
+    ) -> Result<(), LangGraphError>;
+
+    /// Items whose namespace starts with `namespace_prefix` and whose value
+    /// has every field of `filter` with an equal value
+    ///
+    /// Results are ordered by namespace, then key.
+    async fn search(
+        &self,
+        namespace_prefix: &[String],
+        filter: &HashMap<String, Value>,
+    ) -> Result<Vec<Item>, LangGraphError>;
+}
+
+/// An item returned by [`InMemoryStore::semantic_search`] with its similarity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredItem {
+    pub item: Item,
+    /// Cosine similarity between the query and the item, in `[-1, 1]`
+    pub score: f32,
+}
+
+/// Function turning text into an embedding vector
+pub type EmbedFn = Arc<dyn Fn(&str) -> Vec<f32> + Send + Sync>;
+
+struct Entry {
+    item: Item,
+    /// Embedding of the item's value, computed on `put` when an embedder is set
+    embedding: Option<Vec<f32>>,
+}
+
+/// Stored entries keyed by namespace and key
+type EntryMap = BTreeMap<(Vec<String>, String), Entry>;
+
+/// In-memory store; clones share the same data
+#[derive(Clone, Default)]
+pub struct InMemoryStore {
+    items: Arc<RwLock<EntryMap>>,
+    embed: Option<EmbedFn>,
+}
+
+impl std::fmt::Debug for InMemoryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryStore")
+            .field("len", &self.len())
+            .field("semantic", &self.embed.is_some())
+            .finish()
+    }
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Embed item values with `embed` so they can be found by
+    /// [`semantic_search`](Self::semantic_search)
+    ///
+    /// String values are embedded as-is, other values as their JSON text.
+    /// Only items put after this call are embedded.
+    pub fn with_embedder<F>(mut self, embed: F) -> Self
+    where
+        F: Fn(&str) -> Vec<f32> + Send + Sync + 'static,
+    {
+        self.embed = Some(Arc::new(embed));
+        self
+    }
+
+    /// Items under `namespace_prefix` most similar to `query`, best first
+    ///
+    /// Returns at most `limit` items. Items stored without an embedding are
+    /// skipped. Fails if the store has no embedder.
+    pub fn semantic_search(
+        &self,
+        namespace_prefix: &[String],
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ScoredItem>, LangGraphError> {
+        let embed = self.embed.as_ref().ok_or_else(|| {
+            LangGraphError::StoreError("semantic search requires an embedder".to_string())
+        })?;
+        let query = embed(query);
+
+        let items = self.items.read().map_err(lock_error)?;
+        let mut scored: Vec<ScoredItem> = items
+            .values()
+            .filter(|entry| entry.item.namespace.starts_with(namespace_prefix))
+            .filter_map(|entry| {
+                let embedding = entry.embedding.as_ref()?;
+                Some(ScoredItem {
+                    item: entry.item.clone(),
+                    score: cosine_similarity(&query, embedding),
+                })
+            })
+            .collect();
+        // Stable sort keeps namespace/key order among equal scores
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    /// Get the number of stored items
+    pub fn len(&self) -> usize {
+        self.items.read().map(|items| items.len()).unwrap_or(0)
+    }
+
+    /// Check if no items are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn lock_error<T>(_: T) -> LangGraphError {
+    LangGraphError::StoreError("store lock poisoned".to_string())
+}
+
+/// Cosine similarity of two vectors (0 if either is zero or lengths differ)
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 {
+        0.0
+    } else {
+        dot / denom
+    }
+}
+
+/// Text embedded for a value: strings as-is, anything else as JSON
+fn embedding_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Whether `value` has every field of `filter` with an equal value
+fn matches_filter(value: &Value, filter: &HashMap<String, Value>) -> bool {
+    filter
+        .iter()
+        .all(|(field, expected)| value.get(field) == Some(expected))
+}
+
+#[async_trait]
+impl BaseStore for InMemoryStore {
+    async fn get(&self, namespace: &[String], key: &str) -> Result<Option<Item>, LangGraphError> {
+        let items = self.items.read().map_err(lock_error)?;
+        Ok(items
+            .get(&(namespace.to_vec(), key.to_string()))
+            .map(|entry| entry.item.clone()))
+    }
+
+    async fn put(
+        &self,
+        namespace: &[String],
+        key: &str,
+        value: Value,
+    ) -> Result<(), LangGraphError> {
+        // Embed before taking the lock; the embedder may be slow
+        let embedding = self
+            .embed
+            .as_ref()
+            .map(|embed| embed(&embedding_text(&value)));
+        let mut items = self.items.write().map_err(lock_error)?;
+        let now = Utc::now();
+        let created_at = items
+            .get(&(namespace.to_vec(), key.to_string()))
+            .map_or(now, |entry| entry.item.created_at);
+        items.insert(
+            (namespace.to_vec(), key.to_string()),
+            Entry {
+                item: Item {
+                    namespace: namespace.to_vec(),
+                    key: key.to_string(),
+                    value,
+                    created_at,
+                    updated_at: now,
+                },
+                embedding,
+            },
+        );
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        namespace_prefix: &[String],
+        filter: &HashMap<String, Value>,
+    ) -> Result<Vec<Item>, LangGraphError> {
+        let items = self.items.read().map_err(lock_error)?;
+        Ok(items
+            .values()
+            .map(|entry| &entry.item)
+            .filter(|item| item.namespace.starts_with(namespace_prefix))
+            .filter(|item| matches_filter(&item.value, filter))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(feature = "python")]
+mod python {
+    use super::*;
+    use crate::python::{py_to_value, value_to_py};
+    use futures::executor::block_on;
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    fn to_json(value: &PyAny) -> PyResult<Value> {
+        py_to_value(value).ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "store values must be JSON-serializable, got {}",
+                value.get_type().name().unwrap_or("object")
+            ))
+        })
+    }
+
+    fn item_to_py(py: Python, item: &Item) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("namespace", item.namespace.clone())?;
+        dict.set_item("key", &item.key)?;
+        dict.set_item("value", value_to_py(py, &item.value))?;
+        dict.set_item("created_at", item.created_at.to_rfc3339())?;
+        dict.set_item("updated_at", item.updated_at.to_rfc3339())?;
+        Ok(dict.into())
+    }
+
+    fn store_error(error: LangGraphError) -> PyErr {
+        pyo3::exceptions::PyRuntimeError::new_err(error.to_string())
+    }
+
+    /// Raise the error left by the embedding callable, if any
+    fn check_embed_error(py: Python) -> PyResult<()> {
+        PyErr::take(py).map_or(Ok(()), Err)
+    }
+
+    /// In-memory long-term store for JSON-serializable values
+    ///
+    /// Pass it to `Pregel(store=...)` so nodes can share memory across
+    /// threads. Items are returned as dicts with `namespace`, `key`,
+    /// `value`, `created_at` and `updated_at`. With `embed`, a callable
+    /// mapping text to a list of floats, `search(..., query=...)` ranks
+    /// items by similarity and adds a `score` to each.
+    #[pyclass(name = "RustInMemoryStore")]
+    #[derive(Clone, Default)]
+    pub struct PyInMemoryStore {
+        store: InMemoryStore,
+    }
+
+    #[pymethods]
+    impl PyInMemoryStore {
+        #[new]
+        #[pyo3(signature = (embed=None))]
+        fn new(embed: Option<PyObject>) -> Self {
+            let Some(embed) = embed else {
+                return Self::default();
+            };
+            // Errors are left on the interpreter and raised by the calling method
+            let store = InMemoryStore::new().with_embedder(move |text| {
+                Python::with_gil(|py| {
+                    embed
+                        .call1(py, (text,))
+                        .and_then(|vector| vector.extract(py))
+                        .unwrap_or_else(|err| {
+                            err.restore(py);
+                            Vec::new()
+                        })
+                })
+            });
+            Self { store }
+        }
+
+        /// Get the item at `namespace` and `key`, or None
+        fn get(&self, py: Python, namespace: Vec<String>, key: &str) -> PyResult<PyObject> {
+            match block_on(self.store.get(&namespace, key)).map_err(store_error)? {
+                Some(item) => item_to_py(py, &item),
+                None => Ok(py.None()),
+            }
+        }
+
+        /// Store a JSON-serializable value at `namespace` and `key`
+        fn put(
+            &self,
+            py: Python,
+            namespace: Vec<String>,
+            key: &str,
+            value: &PyAny,
+        ) -> PyResult<()> {
+            let value = to_json(value)?;
+            let result = block_on(self.store.put(&namespace, key, value));
+            check_embed_error(py)?;
+            result.map_err(store_error)
+        }
+
+        /// Items under a namespace prefix whose value matches every `filter` field
+        ///
+        /// With `query`, returns the `limit` items most similar to it instead.
+        #[pyo3(signature = (namespace_prefix, filter=None, query=None, limit=10))]
+        fn search(
+            &self,
+            py: Python,
+            namespace_prefix: Vec<String>,
+            filter: Option<&PyDict>,
+            query: Option<&str>,
+            limit: usize,
+        ) -> PyResult<Vec<PyObject>> {
+            if let Some(query) = query {
+                let result = self.store.semantic_search(&namespace_prefix, query, limit);
+                check_embed_error(py)?;
+                return result
+                    .map_err(store_error)?
+                    .iter()
+                    .map(|scored| {
+                        let item = item_to_py(py, &scored.item)?;
+                        item.as_ref(py).set_item("score", scored.score)?;
+                        Ok(item)
+                    })
+                    .collect();
+            }
+            let filter = match filter {
+                Some(filter) => filter
+                    .iter()
+                    .map(|(field, value)| Ok((field.extract()?, to_json(value)?)))
+                    .collect::<PyResult<_>>()?,
+                None => HashMap::new(),
+            };
+            block_on(self.store.search(&namespace_prefix, &filter))
+                .map_err(store_error)?
+                .iter()
+                .map(|item| item_to_py(py, item))
+                .collect()
+        }
+
+        fn __len__(&self) -> usize {
+            self.store.len()
+        }
+    }
+
+    pub fn register_store(_py: Python, m: &PyModule) -> PyResult<()> {
+        m.add_class::<PyInMemoryStore>()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+pub use python::{register_store, PyInMemoryStore};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ns(path: &[&str]) -> Vec<String> {
+        path.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryStore::new();
+        let alice = ns(&["users", "alice"]);
+        store
+            .put(&alice, "lang", json!({"kind": "pref", "value": "rust"}))
+            .await
+            .unwrap();
+        store
+            .put(
+                &alice,
+                "fact",
+                json!({"kind": "fact", "value": "likes tea"}),
+            )
+            .await
+            .unwrap();
+        store
+            .put(&ns(&["users", "bob"]), "lang", json!({"kind": "pref"}))
+            .await
+            .unwrap();
+
+        let item = store.get(&alice, "lang").await.unwrap().unwrap();
+        assert_eq!(item.value["value"], "rust");
+        assert!(store.get(&alice, "missing").await.unwrap().is_none());
+
+        // Overwriting keeps the creation time
+        store
+            .put(&alice, "lang", json!({"kind": "pref", "value": "python"}))
+            .await
+            .unwrap();
+        let updated = store.get(&alice, "lang").await.unwrap().unwrap();
+        assert_eq!(updated.created_at, item.created_at);
+        assert_eq!(store.len(), 3);
+
+        // Clones share data, so memory is visible across threads
+        let shared = store.clone();
+        let prefs = tokio::spawn(async move {
+            let filter = HashMap::from([("kind".to_string(), json!("pref"))]);
+            shared.search(&ns(&["users"]), &filter).await.unwrap()
+        })
+        .await
+        .unwrap();
+        let keys: Vec<(&str, &str)> = prefs
+            .iter()
+            .map(|item| (item.namespace[1].as_str(), item.key.as_str()))
+            .collect();
+        assert_eq!(keys, vec![("alice", "lang"), ("bob", "lang")]);
+
+        let all = store.search(&alice, &HashMap::new()).await.unwrap();
+        assert_eq!(all.len(), 2);
+    }
+
+    /// Toy embedding: how often each topic word appears
+    fn embed_topics(text: &str) -> Vec<f32> {
+        ["coffee", "tea", "rust"]
+            .iter()
+            .map(|topic| text.matches(topic).count() as f32)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search() {
+        let memories = ns(&["users", "alice"]);
+        let plain = InMemoryStore::new();
+        assert!(plain.semantic_search(&memories, "tea", 1).is_err());
+
+        let store = InMemoryStore::new().with_embedder(embed_topics);
+        store
+            .put(
+                &memories,
+                "drink",
+                json!("prefers tea over coffee, tea always"),
+            )
+            .await
+            .unwrap();
+        store
+            .put(&memories, "work", json!({"writes": "rust"}))
+            .await
+            .unwrap();
+        store
+            .put(&ns(&["users", "bob"]), "drink", json!("tea"))
+            .await
+            .unwrap();
+
+        let results = store.semantic_search(&memories, "a cup of tea", 5).unwrap();
+        let keys: Vec<&str> = results.iter().map(|r| r.item.key.as_str()).collect();
+        assert_eq!(keys, ["drink", "work"]);
+        assert!(results[0].score > 0.8);
+        assert_eq!(results[1].score, 0.0);
+
+        // Non-string values are embedded as JSON; `limit` caps the results
+        let results = store.semantic_search(&ns(&["users"]), "rust", 1).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.key, "work");
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+    }
+}
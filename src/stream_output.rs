@@ -3,8 +3,16 @@
 //! This module defines different streaming output modes for Pregel execution.
 
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyTuple};
 use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::pregel_loop::PregelLoop;
+use crate::python::py_to_value;
+
+/// Key under `config["configurable"]` holding the task's [`StreamWriter`]
+pub const CONFIG_KEY_STREAM_WRITER: &str = "__pregel_stream_writer";
 
 /// Stream mode determines what information is yielded during streaming execution
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
@@ -16,6 +24,10 @@ pub enum StreamMode {
     Updates,
     /// Emit debug information including task execution details
     Debug,
+    /// Emit message chunks (e.g. LLM tokens) as nodes produce them
+    Messages,
+    /// Emit arbitrary events written by nodes through their stream writer
+    Custom,
     /// Emit multiple modes combined
     Multiple(Vec<StreamMode>),
 }
@@ -28,6 +40,8 @@ impl std::str::FromStr for StreamMode {
             "values" => Ok(StreamMode::Values),
             "updates" => Ok(StreamMode::Updates),
             "debug" => Ok(StreamMode::Debug),
+            "messages" => Ok(StreamMode::Messages),
+            "custom" => Ok(StreamMode::Custom),
             _ => Err(format!("Unknown stream mode: {}", s)),
         }
     }
@@ -40,9 +54,19 @@ impl StreamMode {
             StreamMode::Values => "values",
             StreamMode::Updates => "updates",
             StreamMode::Debug => "debug",
+            StreamMode::Messages => "messages",
+            StreamMode::Custom => "custom",
             StreamMode::Multiple(_) => "multiple",
         }
     }
+
+    /// Whether this mode is, or is combined with, `mode`
+    pub fn includes(&self, mode: &StreamMode) -> bool {
+        match self {
+            StreamMode::Multiple(modes) => modes.iter().any(|m| m.includes(mode)),
+            other => other == mode,
+        }
+    }
 }
 
 /// Output chunk from streaming execution
@@ -56,6 +80,9 @@ pub struct StreamChunk {
     pub step: usize,
     /// Metadata
     pub metadata: Option<HashMap<String, PyObject>>,
+    /// Subgraph tasks the chunk was forwarded through, outermost first, as
+    /// `"node:task_id"` segments; empty for chunks of the graph itself
+    pub namespace: Vec<String>,
 }
 
 impl StreamChunk {
@@ -66,6 +93,7 @@ impl StreamChunk {
             data,
             step,
             metadata: None,
+            namespace: Vec::new(),
         }
     }
 
@@ -95,6 +123,56 @@ impl StreamChunk {
         Ok(Self::new(StreamMode::Updates, dict.into(), step))
     }
 
+    /// Create a messages chunk: a `(chunk, metadata)` tuple
+    ///
+    /// The metadata carries the emitting node as `langgraph_node` and the
+    /// superstep as `langgraph_step`, plus any caller-supplied entries.
+    pub fn message(
+        py: Python,
+        node_name: &str,
+        chunk: PyObject,
+        metadata: Option<&PyDict>,
+        step: usize,
+    ) -> PyResult<Self> {
+        let meta = match metadata {
+            Some(metadata) => metadata.copy()?,
+            None => PyDict::new(py),
+        };
+        meta.set_item("langgraph_node", node_name)?;
+        meta.set_item("langgraph_step", step)?;
+
+        let data = PyTuple::new(py, [chunk, meta.into()]);
+        Ok(Self::new(StreamMode::Messages, data.into(), step))
+    }
+
+    /// Create a custom chunk carrying the emitting node's name as metadata
+    pub fn custom(py: Python, node_name: &str, value: PyObject, step: usize) -> Self {
+        Self::new(StreamMode::Custom, value, step)
+            .with_metadata("langgraph_node".to_string(), node_name.to_object(py))
+    }
+
+    /// Whether this chunk is a complete message built by a
+    /// [`MessageAggregator`]
+    pub fn is_message_complete(&self, py: Python) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("event"))
+            .is_some_and(|event| {
+                event
+                    .extract::<&str>(py)
+                    .is_ok_and(|event| event == MESSAGE_COMPLETE_EVENT)
+            })
+    }
+
+    /// Name of the node that emitted this chunk, if recorded
+    pub fn node(&self, py: Python) -> Option<String> {
+        self.metadata
+            .as_ref()?
+            .get("langgraph_node")?
+            .extract(py)
+            .ok()
+    }
+
     /// Create a debug chunk
     pub fn debug(py: Python, node_name: &str, info: &DebugInfo, step: usize) -> PyResult<Self> {
         let dict = PyDict::new(py);
@@ -114,6 +192,12 @@ impl StreamChunk {
             dict.set_item("error", error)?;
         }
 
+        dict.set_item("tags", &info.tags)?;
+
+        if let Some(run_id) = info.run_id {
+            dict.set_item("run_id", run_id.to_string())?;
+        }
+
         dict.set_item("duration_ms", info.duration_ms)?;
 
         Ok(Self::new(StreamMode::Debug, dict.into(), step))
@@ -142,6 +226,9 @@ pub struct DebugInfo {
     pub output: Option<PyObject>,
     pub error: Option<String>,
     pub duration_ms: f64,
+    pub tags: Vec<String>,
+    /// Run the event belongs to
+    pub run_id: Option<uuid::Uuid>,
 }
 
 impl DebugInfo {
@@ -151,6 +238,8 @@ impl DebugInfo {
             output: None,
             error: None,
             duration_ms: 0.0,
+            tags: Vec::new(),
+            run_id: None,
         }
     }
 
@@ -173,6 +262,16 @@ impl DebugInfo {
         self.duration_ms = duration_ms;
         self
     }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_run_id(mut self, run_id: uuid::Uuid) -> Self {
+        self.run_id = Some(run_id);
+        self
+    }
 }
 
 impl Default for DebugInfo {
@@ -233,6 +332,337 @@ impl StreamBuffer {
     }
 }
 
+/// Handle passed to a running node for emitting stream chunks mid-execution
+///
+/// Chunks go into a buffer shared by all tasks of the run and are surfaced
+/// in the order they were written, ahead of the step's state output. Calling
+/// the writer is the same as `write(value)`.
+///
+/// A writer set up with [`forwarding_subgraphs`](Self::forwarding_subgraphs)
+/// also carries the chunks of a subgraph run by the node, so they surface in
+/// the parent's stream under the task's namespace.
+#[pyclass]
+#[derive(Clone)]
+pub struct StreamWriter {
+    node: String,
+    step: usize,
+    buffer: Arc<Mutex<StreamBuffer>>,
+    /// Drop writes instead of buffering them
+    muted: bool,
+    /// Mode subgraphs of the task stream in, and the task's namespace
+    /// segment, when their chunks are forwarded
+    subgraphs: Option<(StreamMode, String)>,
+}
+
+impl StreamWriter {
+    pub fn new(node: String, step: usize, buffer: Arc<Mutex<StreamBuffer>>) -> Self {
+        Self {
+            node,
+            step,
+            buffer,
+            muted: false,
+            subgraphs: None,
+        }
+    }
+
+    /// Discard everything written, for nodes filtered out of the stream
+    pub fn muted(mut self) -> Self {
+        self.muted = true;
+        self
+    }
+
+    /// Accept the chunks of subgraphs streamed in `mode`, prefixing their
+    /// namespace with `segment`
+    pub fn forwarding_subgraphs(mut self, mode: StreamMode, segment: String) -> Self {
+        self.subgraphs = Some((mode, segment));
+        self
+    }
+
+    /// Mode a subgraph run by the task should stream in, if its chunks are
+    /// forwarded
+    pub fn subgraph_mode(&self) -> Option<&StreamMode> {
+        self.subgraphs.as_ref().map(|(mode, _)| mode)
+    }
+
+    /// Surface a chunk of a subgraph in the parent's stream, under this
+    /// task's namespace
+    ///
+    /// Dropped if the writer is muted or does not forward subgraphs.
+    pub fn forward(&self, mut chunk: StreamChunk) -> PyResult<()> {
+        match &self.subgraphs {
+            Some((_, segment)) if !self.muted => {
+                chunk.namespace.insert(0, segment.clone());
+                self.push(chunk)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Emit one of the updates a node streams by returning a generator
+    /// (surfaced under `stream_mode="updates"`)
+    pub fn write_update(&self, py: Python, update: PyObject) -> PyResult<()> {
+        if self.muted {
+            return Ok(());
+        }
+        self.push(StreamChunk::updates(py, &self.node, update, self.step)?)
+    }
+
+    fn push(&self, chunk: StreamChunk) -> PyResult<()> {
+        self.buffer
+            .lock()
+            .map_err(|_| pyo3::exceptions::PyRuntimeError::new_err("Stream buffer lock poisoned"))?
+            .push(chunk);
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl StreamWriter {
+    /// Emit a message chunk (surfaced under `stream_mode="messages"`)
+    #[pyo3(signature = (chunk, metadata=None))]
+    fn write_message(
+        &self,
+        py: Python,
+        chunk: PyObject,
+        metadata: Option<&PyDict>,
+    ) -> PyResult<()> {
+        // Muted writers skip building the chunk altogether
+        if self.muted {
+            return Ok(());
+        }
+        let chunk = StreamChunk::message(py, &self.node, chunk, metadata, self.step)?;
+        self.push(chunk)
+    }
+
+    /// Emit a custom event (surfaced under `stream_mode="custom"`)
+    fn write(&self, py: Python, value: PyObject) -> PyResult<()> {
+        if self.muted {
+            return Ok(());
+        }
+        self.push(StreamChunk::custom(py, &self.node, value, self.step))
+    }
+
+    fn __call__(&self, py: Python, value: PyObject) -> PyResult<()> {
+        self.write(py, value)
+    }
+
+    /// Name of the node this writer belongs to
+    #[getter]
+    fn node(&self) -> &str {
+        &self.node
+    }
+}
+
+/// Value of the `event` metadata entry marking a chunk built by
+/// [`MessageAggregator`] from a complete message
+pub const MESSAGE_COMPLETE_EVENT: &str = "message_complete";
+
+/// A message being assembled from its chunks
+struct PendingMessage {
+    namespace: Vec<String>,
+    node: String,
+    id: Option<String>,
+    step: usize,
+    message: PyObject,
+    metadata: Py<PyDict>,
+}
+
+/// Assembles `messages` chunks into complete messages while passing every
+/// chunk through
+///
+/// Chunks belong to the same message when they share the emitting node, the
+/// message ID (the chunk's `id` attribute, else an `id` metadata entry;
+/// chunks with neither form one message per node) and the subgraph
+/// namespace, so interleaved chunks of concurrent nodes are kept apart.
+/// Chunks are joined with `+`, which concatenates strings and merges
+/// LangChain message chunks.
+///
+/// A message is complete once its step is over: when a chunk of a later
+/// step arrives, when a non-`messages` chunk of its step arrives (nodes'
+/// chunks are surfaced ahead of the step's state output), or at
+/// [`finish`](Self::finish). It is then emitted, ahead of the chunk that
+/// completed it, as a `messages` chunk `(message, metadata)` whose metadata
+/// (that of the message's first chunk) and chunk metadata carry `event` set
+/// to [`MESSAGE_COMPLETE_EVENT`]. Completed messages come out in the order
+/// their first chunks arrived.
+#[derive(Default)]
+pub struct MessageAggregator {
+    pending: Vec<PendingMessage>,
+}
+
+impl MessageAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take `chunk`, returning the messages it completes followed by the
+    /// chunk itself
+    pub fn push(&mut self, py: Python, chunk: StreamChunk) -> PyResult<Vec<StreamChunk>> {
+        let is_message = chunk.mode == StreamMode::Messages && !chunk.is_message_complete(py);
+        let mut out = self.complete(py, |pending| {
+            pending.step < chunk.step || (!is_message && pending.step == chunk.step)
+        });
+        if is_message {
+            self.accumulate(py, &chunk)?;
+        }
+        out.push(chunk);
+        Ok(out)
+    }
+
+    /// Complete every message still being assembled, at the end of the stream
+    pub fn finish(&mut self, py: Python) -> Vec<StreamChunk> {
+        self.complete(py, |_| true)
+    }
+
+    /// Pass `chunks` through an aggregator, completing all messages at the end
+    pub fn aggregate(
+        py: Python,
+        chunks: impl IntoIterator<Item = StreamChunk>,
+    ) -> PyResult<Vec<StreamChunk>> {
+        let mut aggregator = Self::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(aggregator.push(py, chunk)?);
+        }
+        out.extend(aggregator.finish(py));
+        Ok(out)
+    }
+
+    fn accumulate(&mut self, py: Python, chunk: &StreamChunk) -> PyResult<()> {
+        let (part, metadata): (&PyAny, &PyDict) = chunk.data.extract(py)?;
+        let node = match metadata.get_item("langgraph_node")? {
+            Some(node) => node.extract()?,
+            None => String::new(),
+        };
+        let id = match part.getattr("id").ok().filter(|id| !id.is_none()) {
+            Some(id) => Some(id.str()?.to_string()),
+            None => match metadata.get_item("id")? {
+                Some(id) if !id.is_none() => Some(id.str()?.to_string()),
+                _ => None,
+            },
+        };
+
+        let existing = self.pending.iter_mut().find(|pending| {
+            pending.node == node && pending.id == id && pending.namespace == chunk.namespace
+        });
+        match existing {
+            Some(pending) => {
+                pending.message = pending.message.call_method1(py, "__add__", (part,))?;
+            }
+            None => self.pending.push(PendingMessage {
+                namespace: chunk.namespace.clone(),
+                node,
+                id,
+                step: chunk.step,
+                message: part.into(),
+                metadata: metadata.copy()?.into(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Emit the pending messages matching `done`, in arrival order
+    fn complete(&mut self, py: Python, done: impl Fn(&PendingMessage) -> bool) -> Vec<StreamChunk> {
+        let (finished, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|message| done(message));
+        self.pending = pending;
+        finished
+            .into_iter()
+            .map(|message| {
+                let metadata = message.metadata.into_ref(py);
+                // Setting a str key on a dict cannot fail
+                let _ = metadata.set_item("event", MESSAGE_COMPLETE_EVENT);
+                let data = PyTuple::new(py, [message.message, metadata.to_object(py)]);
+                let mut chunk = StreamChunk::new(StreamMode::Messages, data.into(), message.step)
+                    .with_metadata("event".to_string(), MESSAGE_COMPLETE_EVENT.to_object(py));
+                chunk.namespace = message.namespace;
+                chunk
+            })
+            .collect()
+    }
+}
+
+/// Writes stream chunks as newline-delimited JSON
+///
+/// Each chunk becomes one `{"step", "mode", "payload"}` line, with the
+/// chunk's subgraph namespace as `"ns"` if it has one, flushed as soon
+/// as it is written so a reader on the other end of a pipe sees events as
+/// they happen. A payload with no JSON form (anything but dicts, lists,
+/// strings, numbers, bools and None; `messages` tuples become arrays) is
+/// written as a `{"step", "mode", "error"}` line instead.
+pub struct JsonLinesWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    /// Write one chunk as a line
+    pub fn write_chunk(&mut self, py: Python, chunk: &StreamChunk) -> std::io::Result<()> {
+        let data = chunk.data.as_ref(py);
+        let payload = match data.downcast::<PyTuple>() {
+            Ok(tuple) => tuple
+                .iter()
+                .map(py_to_value)
+                .collect::<Option<_>>()
+                .map(serde_json::Value::Array),
+            Err(_) => py_to_value(data),
+        };
+        let mut line = match payload {
+            Some(payload) => serde_json::json!({
+                "step": chunk.step,
+                "mode": chunk.mode.to_str(),
+                "payload": payload,
+            }),
+            None => serde_json::json!({
+                "step": chunk.step,
+                "mode": chunk.mode.to_str(),
+                "error": format!(
+                    "payload is not JSON-serializable: {}",
+                    data.get_type().name().unwrap_or("object")
+                ),
+            }),
+        };
+        if !chunk.namespace.is_empty() {
+            line["ns"] = serde_json::json!(chunk.namespace);
+        }
+        serde_json::to_writer(&mut self.out, &line)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+
+    /// Run `pregel_loop` on `input`, writing every chunk in `mode` as it is
+    /// produced
+    ///
+    /// Returns the number of lines written. Fails if the run fails or the
+    /// output cannot be written to; lines already written stay written.
+    pub fn write_stream(
+        &mut self,
+        py: Python,
+        pregel_loop: &mut PregelLoop,
+        input: PyObject,
+        mode: &StreamMode,
+    ) -> PyResult<usize> {
+        pregel_loop.initialize_input(py, input)?;
+        let mut lines = 0;
+        while let Some(chunks) = pregel_loop.stream_step(py, mode)? {
+            for chunk in &chunks {
+                self.write_chunk(py, chunk)?;
+                lines += 1;
+            }
+        }
+        Ok(lines)
+    }
+
+    /// The underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,6 +679,14 @@ mod tests {
         assert!(StreamMode::from_str("invalid").is_err());
     }
 
+    #[test]
+    fn test_stream_mode_includes() {
+        let mode = StreamMode::Multiple(vec![StreamMode::Values, StreamMode::Messages]);
+        assert!(mode.includes(&StreamMode::Messages));
+        assert!(!mode.includes(&StreamMode::Updates));
+        assert!(StreamMode::Messages.includes(&StreamMode::Messages));
+    }
+
     #[test]
     fn test_stream_mode_to_str() {
         assert_eq!(StreamMode::Values.to_str(), "values");
@@ -289,4 +727,136 @@ mod tests {
         assert!(info.input.is_none());
         assert!(info.output.is_none());
     }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_stream_writer_message() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let buffer = Arc::new(Mutex::new(StreamBuffer::new(StreamMode::Messages)));
+            let writer =
+                Py::new(py, StreamWriter::new("llm".to_string(), 2, buffer.clone())).unwrap();
+
+            let locals = PyDict::new(py);
+            locals.set_item("writer", writer).unwrap();
+            py.run(
+                "writer.write_message('Hel')\nwriter.write_message('lo', {'id': 7})",
+                None,
+                Some(locals),
+            )
+            .unwrap();
+
+            let buffer = buffer.lock().unwrap();
+            assert_eq!(buffer.len(), 2);
+            let (chunk, meta): (String, &PyDict) = buffer.chunks()[1].data.extract(py).unwrap();
+            assert_eq!(chunk, "lo");
+            let get = |key: &str| meta.get_item(key).unwrap().unwrap().to_string();
+            assert_eq!(get("langgraph_node"), "llm");
+            assert_eq!(get("langgraph_step"), "2");
+            assert_eq!(get("id"), "7");
+        });
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_json_lines_writer() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let mut writer = JsonLinesWriter::new(Vec::new());
+            let update = py.eval("{'agent': {'count': 2}}", None, None).unwrap();
+            writer
+                .write_chunk(py, &StreamChunk::new(StreamMode::Updates, update.into(), 1))
+                .unwrap();
+            let chunk = StreamChunk::message(py, "llm", "Hi".to_object(py), None, 2).unwrap();
+            writer.write_chunk(py, &chunk).unwrap();
+            let opaque = py.eval("object()", None, None).unwrap();
+            writer
+                .write_chunk(py, &StreamChunk::new(StreamMode::Custom, opaque.into(), 3))
+                .unwrap();
+
+            let output = String::from_utf8(writer.into_inner()).unwrap();
+            let lines: Vec<serde_json::Value> = output
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            assert_eq!(
+                lines[0],
+                serde_json::json!({
+                    "step": 1,
+                    "mode": "updates",
+                    "payload": {"agent": {"count": 2}},
+                })
+            );
+            assert_eq!(lines[1]["payload"][0], "Hi");
+            assert_eq!(lines[1]["payload"][1]["langgraph_node"], "llm");
+            assert_eq!(lines[2]["mode"], "custom");
+            assert_eq!(
+                lines[2]["error"],
+                "payload is not JSON-serializable: object"
+            );
+            assert!(lines[2].get("payload").is_none());
+        });
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_message_aggregator_interleaved_nodes() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let message = |node: &str, text: &str, id: i64, step: usize| {
+                let metadata = PyDict::new(py);
+                metadata.set_item("id", id).unwrap();
+                StreamChunk::message(py, node, text.to_object(py), Some(metadata), step).unwrap()
+            };
+            // Two nodes stream concurrently in step 1; "b" sends two messages
+            let chunks = vec![
+                message("a", "Hel", 1, 1),
+                message("b", "Fo", 2, 1),
+                message("a", "lo", 1, 1),
+                message("b", "o", 2, 1),
+                message("b", "Bar", 3, 1),
+                StreamChunk::updates(py, "a", py.None(), 1).unwrap(),
+                message("a", "Next", 1, 2),
+            ];
+
+            let out = MessageAggregator::aggregate(py, chunks).unwrap();
+            let describe = |chunk: &StreamChunk| -> String {
+                if chunk.mode != StreamMode::Messages {
+                    return chunk.mode.to_str().to_string();
+                }
+                let (text, meta): (String, &PyDict) = chunk.data.extract(py).unwrap();
+                let node = meta.get_item("langgraph_node").unwrap().unwrap();
+                if chunk.is_message_complete(py) {
+                    let event = meta.get_item("event").unwrap().unwrap();
+                    format!("{}:{}={}", event, node, text)
+                } else {
+                    format!("{}:{}", node, text)
+                }
+            };
+            let described: Vec<String> = out.iter().map(describe).collect();
+            assert_eq!(
+                described,
+                [
+                    "a:Hel",
+                    "b:Fo",
+                    "a:lo",
+                    "b:o",
+                    "b:Bar",
+                    // The step's state output completes its messages first
+                    "message_complete:a=Hello",
+                    "message_complete:b=Foo",
+                    "message_complete:b=Bar",
+                    "updates",
+                    "a:Next",
+                    // The rest complete at the end of the stream
+                    "message_complete:a=Next",
+                ]
+            );
+            assert_eq!(out[5].step, 1);
+            assert_eq!(out[10].step, 2);
+        });
+    }
 }
@@ -0,0 +1,147 @@
+//! JavaScript API over [`PregelCore`]
+//!
+//! Values cross the boundary as JSON: the input and every state handed to a
+//! callback go through `JSON.parse`, and what a callback returns goes
+//! through `JSON.stringify`. Each state key is a last-value channel, and
+//! the graph runs its supersteps on the calling thread.
+//!
+//! ```js
+//! const graph = new WasmGraph(["count"]);
+//! graph.addNode("inc", (state) => ({ count: state.count + 1 }));
+//! graph.setEntryPoint("inc");
+//! graph.addEdge("inc", "__end__");
+//! graph.invoke({ count: 0 }); // { count: 1 }
+//! ```
+
+use crate::core::host::{self, Object};
+use crate::core::{ChannelKind, Edge, Node, PregelCore, StateSchema};
+use crate::errors::GraphError;
+use js_sys::{Function, JSON};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Graph whose nodes and routers are JS functions
+#[wasm_bindgen]
+pub struct WasmGraph {
+    inner: PregelCore,
+    channels: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl WasmGraph {
+    /// Create a graph whose state has the keys `channels`
+    #[wasm_bindgen(constructor)]
+    pub fn new(channels: Vec<String>) -> Self {
+        let schema = channels.iter().fold(StateSchema::new(), |schema, channel| {
+            schema.field(channel.as_str(), ChannelKind::LastValue)
+        });
+        let mut inner = PregelCore::with_schema(schema);
+        inner.set_input_channels(channels.clone());
+        Self { inner, channels }
+    }
+
+    /// Add a node, replacing any node of the same name; `func` receives the
+    /// state and returns an object of updates or `null`
+    #[wasm_bindgen(js_name = addNode)]
+    pub fn add_node(&mut self, name: String, func: Function) {
+        let func = JsFunction(func);
+        let channels = self.channels.clone();
+        let node = name.clone();
+        let inputs: Vec<&str> = self.channels.iter().map(String::as_str).collect();
+        self.inner.add_node(Node::with_inputs(
+            name,
+            &inputs,
+            move |_py, values: Vec<Object>| {
+                let state = channels.iter().cloned().zip(values).collect::<Map<_, _>>();
+                let updates = func.call(&Value::Object(state)).map_err(|message| {
+                    GraphError::NodeExecution {
+                        node: node.clone(),
+                        source: message.into(),
+                    }
+                })?;
+                match updates {
+                    Value::Null => Ok(HashMap::new()),
+                    Value::Object(updates) => Ok(updates.into_iter().collect()),
+                    updates => Err(host::type_error(format!(
+                        "node '{}' must return an object of updates or null, got {}",
+                        node, updates
+                    ))),
+                }
+            },
+        ));
+    }
+
+    /// Run `target` after `source`; pass `"__end__"` to finish
+    #[wasm_bindgen(js_name = addEdge)]
+    pub fn add_edge(&mut self, source: String, target: String) {
+        self.inner.add_edge(Edge::direct(source, target));
+    }
+
+    /// Let `router` pick what runs after `source`; it receives the state and
+    /// returns a node name or an array of node names
+    #[wasm_bindgen(js_name = addConditionalEdges)]
+    pub fn add_conditional_edges(&mut self, source: String, router: Function) {
+        let router = JsFunction(router);
+        let after = source.clone();
+        let router = host::callable(move |state| {
+            router.call(&state).map_err(|message| {
+                host::runtime_error(format!("router after '{}' failed: {}", after, message))
+            })
+        });
+        self.inner
+            .add_conditional_edges(&source, router, HashMap::new());
+    }
+
+    #[wasm_bindgen(js_name = setEntryPoint)]
+    pub fn set_entry_point(&mut self, node: String) {
+        self.inner.set_entry_point(node);
+    }
+
+    #[wasm_bindgen(js_name = setRecursionLimit)]
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.inner.set_recursion_limit(limit);
+    }
+
+    /// Run the graph on `input`, which must give every state key a value,
+    /// and return the final state
+    pub fn invoke(&mut self, input: JsValue) -> Result<JsValue, JsError> {
+        let input = from_js(&input).map_err(|e| JsError::new(&e))?;
+        let output = host::with_host(|py| self.inner.invoke_sync(py, input, None))?;
+        to_js(&output).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// A JS function called by a node or a router
+struct JsFunction(Function);
+
+// SAFETY: wasm32 runs the module on a single thread, so the function is
+// never sent to or shared with another thread
+unsafe impl Send for JsFunction {}
+unsafe impl Sync for JsFunction {}
+
+impl JsFunction {
+    fn call(&self, value: &Value) -> Result<Value, String> {
+        let result = self
+            .0
+            .call1(&JsValue::NULL, &to_js(value)?)
+            .map_err(describe)?;
+        from_js(&result)
+    }
+}
+
+fn to_js(value: &Value) -> Result<JsValue, String> {
+    JSON::parse(&value.to_string()).map_err(describe)
+}
+
+fn from_js(value: &JsValue) -> Result<Value, String> {
+    if value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    let json = JSON::stringify(value).map_err(describe)?;
+    serde_json::from_str(&String::from(json)).map_err(|e| e.to_string())
+}
+
+fn describe(value: JsValue) -> String {
+    value.as_string().unwrap_or_else(|| format!("{:?}", value))
+}
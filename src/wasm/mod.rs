@@ -0,0 +1,15 @@
+//! WebAssembly build of the graph executor
+//!
+//! Enabled by the `wasm` feature. Build for the browser without the Python
+//! bindings:
+//!
+//! ```text
+//! cargo build --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+//!
+//! This module provides WasmGraph, a wasm-bindgen API over
+//! [`PregelCore`](crate::core::PregelCore) taking JS callbacks as nodes.
+
+pub mod bindings;
+
+pub use bindings::WasmGraph;
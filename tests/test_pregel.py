@@ -270,6 +270,126 @@ def test_async_methods():
         return False
 
 
+def test_pregel_batch():
+    """Test Pregel batch and abatch methods"""
+    try:
+        import asyncio
+
+        import fast_langgraph
+
+        def double(state):
+            if state.get("bad"):
+                raise ValueError("bad input")
+            return {"out": state["n"] * 2}
+
+        pregel = fast_langgraph.Pregel(
+            nodes={"double": double}, output_channels="output", input_channels="input"
+        )
+
+        # Results are aligned with inputs
+        result = pregel.batch([{"n": 1}, {"n": 2}, {"n": 3}], max_concurrency=2)
+        assert result == [{"out": 2}, {"out": 4}, {"out": 6}]
+        print("✓ Pregel.batch() returns results in input order")
+
+        # Failures surface per item
+        result = pregel.batch([{"n": 1}, {"bad": True}], return_exceptions=True)
+        assert result[0] == {"out": 2}
+        assert isinstance(result[1], ValueError)
+        print("✓ Pregel.batch() returns exceptions in place")
+
+        try:
+            pregel.batch([{"n": 1}, {"bad": True}])
+            raise AssertionError("batch should raise")
+        except ValueError:
+            print("✓ Pregel.batch() raises failed items by default")
+
+        result = asyncio.run(pregel.abatch([{"n": 5}], {"max_concurrency": 1}))
+        assert result == [{"out": 10}]
+        print("✓ Pregel.abatch() works")
+
+        return True
+
+    except Exception as e:
+        print(f"✗ Error testing Pregel batch: {e}")
+        return False
+
+
+def test_pregel_stream_cancel():
+    """Test cancelling a streaming run through its stop handle"""
+    try:
+        import threading
+        import time
+
+        import fast_langgraph
+
+        class Chan:
+            def __init__(self):
+                self.value = None
+
+            def update(self, values):
+                for v in values:
+                    self.value = v
+                return bool(values)
+
+            def get(self):
+                if self.value is None:
+                    raise Exception("empty")
+                return self.value
+
+        class Node:
+            def __init__(self, trigger, channel, func):
+                self.triggers = [trigger]
+                self.channels = [channel]
+                self.func = func
+
+            def __call__(self, _input):
+                return self.func()
+
+        def make_pregel(slow_step=None):
+            return fast_langgraph.Pregel(
+                nodes={
+                    "a": Node("input", "mid", lambda: 1),
+                    "b": Node("mid", "out", slow_step or (lambda: 2)),
+                },
+                channels={name: Chan() for name in ("input", "mid", "out")},
+            )
+
+        # Cancelling between chunks stops before the next step runs
+        stream = make_pregel().stream({"input": 0})
+        first = next(stream)
+        assert first["mid"] == 1
+        stream.cancel()
+        assert stream.cancelled
+        try:
+            next(stream)
+            raise AssertionError("stream should be cancelled")
+        except fast_langgraph.GraphCancelled:
+            pass
+        print("✓ Pregel.stream() raises GraphCancelled after cancel()")
+
+        # cancel() from another thread discards the step in progress
+        def slow():
+            time.sleep(0.2)
+            return 2
+
+        stream = make_pregel(slow).stream({"input": 0})
+        chunks = [next(stream)]
+        threading.Timer(0.05, stream.cancel).start()
+        try:
+            chunks.extend(stream)
+            raise AssertionError("stream should be cancelled")
+        except fast_langgraph.GraphCancelled:
+            pass
+        assert all("out" not in chunk for chunk in chunks)
+        print("✓ Pregel.stream() can be cancelled from another thread")
+
+        return True
+
+    except Exception as e:
+        print(f"✗ Error testing Pregel stream cancellation: {e}")
+        return False
+
+
 def main():
     """Main test function"""
     print("Testing LangGraph Rust Pregel Implementation")
@@ -283,6 +403,8 @@ def main():
         test_pregel_astream,
         test_pregel_api_compatibility,
         test_async_methods,
+        test_pregel_batch,
+        test_pregel_stream_cancel,
     ]
 
     results = []